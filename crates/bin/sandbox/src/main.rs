@@ -12,19 +12,41 @@ use raven_engine::prelude::{*,
 pub struct Sandbox;
 
 impl Sandbox {
-    pub fn load_assets(&self) -> Vec<Arc<AssetHandle>> {
+    /// Load the gun mesh and the skybox faces, None if any of them failed to load.
+    pub fn load_assets(&self) -> Option<Vec<Arc<AssetHandle>>> {
         let asset_api = asset::get().read();
 
-        asset_api.load_asset(AssetLoadDesc::load_mesh("mesh/cerberus_gun/scene.gltf")).unwrap();
-        //asset_manager.load_asset(AssetLoadDesc::load_mesh("mesh/cornell_box/scene.gltf")).unwrap();
-        asset_api.load_asset(AssetLoadDesc::load_texture("texture/skybox/right.jpg")).unwrap();
-        asset_api.load_asset(AssetLoadDesc::load_texture("texture/skybox/left.jpg")).unwrap();
-        asset_api.load_asset(AssetLoadDesc::load_texture("texture/skybox/top.jpg")).unwrap();
-        asset_api.load_asset(AssetLoadDesc::load_texture("texture/skybox/bottom.jpg")).unwrap();
-        asset_api.load_asset(AssetLoadDesc::load_texture("texture/skybox/front.jpg")).unwrap();
-        asset_api.load_asset(AssetLoadDesc::load_texture("texture/skybox/back.jpg")).unwrap();
+        let load_descs = [
+            AssetLoadDesc::load_mesh("mesh/cerberus_gun/scene.gltf"),
+            //AssetLoadDesc::load_mesh("mesh/cornell_box/scene.gltf"),
+            AssetLoadDesc::load_texture("texture/skybox/right.jpg"),
+            AssetLoadDesc::load_texture("texture/skybox/left.jpg"),
+            AssetLoadDesc::load_texture("texture/skybox/top.jpg"),
+            AssetLoadDesc::load_texture("texture/skybox/bottom.jpg"),
+            AssetLoadDesc::load_texture("texture/skybox/front.jpg"),
+            AssetLoadDesc::load_texture("texture/skybox/back.jpg"),
+        ];
+
+        let mut is_all_loaded = true;
+        for load_desc in load_descs {
+            if let Err(err) = asset_api.load_asset(load_desc) {
+                glog::error!("{}", err);
+                is_all_loaded = false;
+            }
+        }
+
+        let mut handles = Vec::new();
+        for result in asset_api.dispatch_load_tasks() {
+            match result {
+                Ok(handle) => handles.push(handle),
+                Err(err) => {
+                    glog::error!("{}", err);
+                    is_all_loaded = false;
+                }
+            }
+        }
 
-        asset_api.dispatch_load_tasks().unwrap()
+        is_all_loaded.then_some(handles)
     }
     
     pub fn prepare_scene(&self, tex_handles: &[Arc<AssetHandle>; 6], mesh_handle: &Arc<AssetHandle>) {
@@ -88,10 +110,13 @@ impl App for Sandbox {
     fn init(&mut self) -> anyhow::Result<()> {
         glog::info!("User app init!");
 
-        let handles = self.load_assets();
-        let tex_handles: &[Arc<AssetHandle>; 6] = handles.split_at(1).1.try_into().unwrap();
-        
-        self.prepare_scene(tex_handles, &handles[0]);
+        if let Some(handles) = self.load_assets() {
+            let tex_handles: &[Arc<AssetHandle>; 6] = handles.split_at(1).1.try_into().unwrap();
+
+            self.prepare_scene(tex_handles, &handles[0]);
+        } else {
+            glog::error!("Failed to load the sandbox assets, the scene is left empty.");
+        }
         self.bind_input();
 
         Ok(())
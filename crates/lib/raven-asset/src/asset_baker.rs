@@ -1,27 +1,140 @@
+use std::hash::{Hash, Hasher};
 use std::sync::Arc;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use parking_lot::RwLockReadGuard;
 use turbosloth::*;
+use wyhash::WyHash;
 
-use raven_filesystem;
+use raven_filesystem::{self, ProjectFolder};
 
 use super::{asset_registry::{AssetHandle, self, AssetRegistry}, error::AssetPipelineError, AssetType, Mesh, Material, Texture, get_uri_bake_stem, AsConcreteAsset};
+use super::loader::{LoadAssetType, LoadAssetMeshType};
+use super::AssetLoadDesc;
+
+/// Bump this when the baked formats change, so that all the baked assets are invalidated.
+/// (e.g. 4: the baked meshes store the indices of the LODs)
+const BAKE_VERSION: u64 = 4;
+
+/// Parameters which affect the baked output besides the source bytes.
+#[derive(Clone, Copy, Hash, Debug, PartialEq, Eq)]
+pub(crate) struct BakeParams {
+    pub generate_mipmap: bool,
+    pub mesh_lod_count: u32,
+}
+
+impl BakeParams {
+    pub(crate) fn from_load_desc(load_desc: &AssetLoadDesc) -> Self {
+        match load_desc.load_ty {
+            LoadAssetType::Texture(_) => Self { generate_mipmap: true, mesh_lod_count: 0 },
+            LoadAssetType::Mesh(_) => Self { generate_mipmap: false, mesh_lod_count: load_desc.mesh_lod_count },
+            _ => Self { generate_mipmap: false, mesh_lod_count: 0 },
+        }
+    }
+}
+
+/// Hash of the source bytes, the bytes of its dependencies and the bake parameters, stable between runs.
+pub(crate) fn calc_bake_hash(source: &[u8], dependencies: &[Vec<u8>], params: &BakeParams) -> u64 {
+    let mut hasher = WyHash::with_seed(BAKE_VERSION);
+    hasher.write(source);
+    for dependency in dependencies {
+        // prefix the length, so that moving bytes from one file to another still changes the hash
+        hasher.write_usize(dependency.len());
+        hasher.write(dependency);
+    }
+    params.hash(&mut hasher);
+
+    hasher.finish()
+}
+
+/// Relative uris (to the directory of the source) of the external files which are loaded with the source.
+/// (e.g. the .bin buffers and the textures of a gltf, embedded data uris are already part of the source)
+fn get_source_dependencies(source: &[u8], load_ty: &LoadAssetType) -> Vec<String> {
+    match load_ty {
+        LoadAssetType::Mesh(LoadAssetMeshType::Gltf) => {
+            // the loader will report the broken gltf, nothing more to hash here
+            let document = match gltf::Gltf::from_slice(source) {
+                Ok(gltf) => gltf.document,
+                Err(_) => return Vec::new(),
+            };
+
+            let buffers = document.buffers().filter_map(|buffer| match buffer.source() {
+                gltf::buffer::Source::Uri(uri) => Some(uri),
+                gltf::buffer::Source::Bin => None,
+            });
+            let images = document.images().filter_map(|image| match image.source() {
+                gltf::image::Source::Uri { uri, .. } => Some(uri),
+                gltf::image::Source::View { .. } => None,
+            });
+
+            buffers.chain(images)
+                .filter(|uri| !uri.starts_with("data:"))
+                .map(|uri| uri.to_owned())
+                .collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Bake hash of the source file and its dependencies in the Assets folder.
+/// Return None if the source file does not exist (i.e. only the baked assets are shipped).
+pub(crate) fn calc_source_bake_hash(uri: &PathBuf, load_ty: &LoadAssetType, params: &BakeParams) -> anyhow::Result<Option<u64>> {
+    let path = raven_filesystem::resolve_path(uri, ProjectFolder::Assets)?;
+    if !path.is_file() {
+        return Ok(None);
+    }
+
+    let source = std::fs::read(&path)?;
+
+    let base_path = path.parent().map(Path::to_path_buf).unwrap_or_default();
+    let dependencies = get_source_dependencies(&source, load_ty).into_iter()
+        .map(|dep_uri| {
+            // a missing dependency fails the load anyway, hash its uri so that it is different from an empty file
+            std::fs::read(base_path.join(&dep_uri)).unwrap_or_else(|_| dep_uri.into_bytes())
+        })
+        .collect::<Vec<_>>();
+
+    Ok(Some(calc_bake_hash(&source, &dependencies, params)))
+}
+
+/// The bake hash is stored in a sidecar file next to the baked asset. (e.g. foo.mesh -> foo.mesh.hash)
+fn get_bake_hash_sidecar(baked_name: &Path) -> PathBuf {
+    let mut sidecar = baked_name.as_os_str().to_owned();
+    sidecar.push(".hash");
+    PathBuf::from(sidecar)
+}
+
+/// Read the bake hash stored alongside the baked asset (relative to the Baked folder).
+pub(crate) fn read_bake_hash(baked_name: &Path) -> Option<u64> {
+    // map_file() also looks into the mounted pak, where the sidecars are packed with the baked assets
+    let sidecar = raven_filesystem::map_file(&get_bake_hash_sidecar(baked_name), ProjectFolder::Baked).ok()?;
+    let hash = std::str::from_utf8(&sidecar).ok()?;
+
+    u64::from_str_radix(hash.trim(), 16).ok()
+}
+
+fn write_bake_hash(baked_path: &Path, hash: u64) -> anyhow::Result<()> {
+    std::fs::write(get_bake_hash_sidecar(baked_path), format!("{:016x}", hash))?;
+    Ok(())
+}
 
 #[derive(Clone, Hash)]
 pub struct AssetBaker {
     origin_res_path: PathBuf,
     handle: Arc<AssetHandle>,
+    /// Written alongside the baked asset to skip re-baking the unchanged source next time.
+    bake_hash: Option<u64>,
 }
 
 impl AssetBaker {
-    pub fn new(handle: Arc<AssetHandle>, origin_res_path: impl Into<PathBuf>) -> Self {
+    pub fn new(handle: Arc<AssetHandle>, origin_res_path: impl Into<PathBuf>, bake_hash: Option<u64>) -> Self {
         let origin_res_path = origin_res_path.into();
         assert!(origin_res_path.is_relative() && !origin_res_path.is_dir());
 
         Self {
             origin_res_path,
             handle,
+            bake_hash,
         }
     }
 }
@@ -45,9 +158,9 @@ impl LazyWorker for AssetBaker {
                 return Ok(());
             }
 
-            raven_filesystem::exist_or_create(raven_filesystem::ProjectFolder::Baked)?;
+            raven_filesystem::exist_or_create(ProjectFolder::Baked)?;
 
-            let path = raven_filesystem::get_project_folder_path_absolute(raven_filesystem::ProjectFolder::Baked)?;
+            let path = raven_filesystem::get_project_folder_path_absolute(ProjectFolder::Baked)?;
             let filename = get_uri_bake_stem(&self.origin_res_path);
             let mut path = path.join(filename);
 
@@ -66,6 +179,10 @@ impl LazyWorker for AssetBaker {
                     unimplemented!()
                 }
             }
+
+            if let Some(hash) = self.bake_hash {
+                write_bake_hash(&path, hash)?;
+            }
         }
 
         Ok(())
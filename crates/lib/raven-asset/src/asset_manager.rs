@@ -3,12 +3,10 @@ use std::collections::HashMap;
 
 use parking_lot::Mutex;
 use turbosloth::*;
-use memmap2::{Mmap, MmapOptions};
 
 use raven_thread::executor;
-use raven_filesystem;
+use raven_filesystem::{self, MappedFile};
 
-use super::error::AssetPipelineError;
 use super::get_uri_bake_stem;
 use super::loader::extract_asset_type;
 use super::{
@@ -16,11 +14,14 @@ use super::{
         LoadAssetType, extract_mesh_type, extract_texture_type, 
         AssetLoader, LoadAssetMeshType, mesh_loader::GltfMeshLoader, LoadAssetTextureType, texture_loader::JpgTextureLoader
     }, 
-    RawAsset, asset_registry::{AssetHandle, get_runtime_asset_registry}, asset_process::AssetProcessor, asset_baker::AssetBaker, BakedAsset, BakedRawAsset
+    RawAsset, asset_registry::{AssetHandle, get_runtime_asset_registry}, asset_process::AssetProcessor, BakedAsset, BakedRawAsset,
+    asset_baker::{self, AssetBaker, BakeParams},
+    error::AssetLoadError,
 };
 
 lazy_static::lazy_static! {
-    pub(crate) static ref ASSETS_MMAP: Mutex<HashMap<PathBuf, Mmap>> = Mutex::new(HashMap::new());
+    /// Baked assets mapped from the mounted pak or the loose baked files.
+    pub(crate) static ref ASSETS_MMAP: Mutex<HashMap<PathBuf, MappedFile>> = Mutex::new(HashMap::new());
 }
 
 #[derive(Hash, Clone, Debug)]
@@ -35,13 +36,23 @@ impl From<PathBuf> for AssetPipelineKey {
 pub struct AssetManager {
     loaders: Mutex<Vec<Arc<dyn AssetLoader + Send + Sync>>>,
     //loader_groups: Mutex<Vec<LoadGroup>>,
+    /// Bake hashes of the assets waiting to be baked, keyed by the uri.
+    bake_hashes: Mutex<HashMap<PathBuf, u64>>,
 
     lazy_cache: Arc<LazyCache>,
 }
 
+/// Color of the placeholder used in place of the textures failed to load, visible on purpose.
+pub const MISSING_TEXTURE_COLOR: [u8; 4] = [255, 0, 255, 255];
+
+/// Number of the coarser mesh LODs generated by default, the LODs are opt-in by AssetLoadDesc::lod_count().
+pub const DEFAULT_MESH_LOD_COUNT: u32 = 0;
+
 pub struct AssetLoadDesc {
     pub load_ty: LoadAssetType,
     pub uri: PathBuf,
+    /// Number of the coarser LODs generated when processing a mesh, 0 means no LOD.
+    pub mesh_lod_count: u32,
 }
 
 impl AssetLoadDesc {
@@ -53,10 +64,12 @@ impl AssetLoadDesc {
             panic!("Invalid mesh uri! {:?}", uri);
         }
 
-        let load_ty = extract_mesh_type(&uri).unwrap();
+        // unsupported formats are reported when loading
+        let load_ty = extract_mesh_type(&uri).unwrap_or(LoadAssetMeshType::Unknown);
         Self {
             load_ty: LoadAssetType::Mesh(load_ty),
             uri,
+            mesh_lod_count: DEFAULT_MESH_LOD_COUNT,
         }
     }
 
@@ -66,12 +79,19 @@ impl AssetLoadDesc {
             panic!("Invalid texture uri! {:?}", uri);
         }
 
-        let load_ty = extract_texture_type(&uri).unwrap();
+        let load_ty = extract_texture_type(&uri).unwrap_or(LoadAssetTextureType::Unknown);
         Self {
             load_ty: LoadAssetType::Texture(load_ty),
             uri,
+            mesh_lod_count: 0,
         }
     }
+
+    /// Set the number of the coarser LODs generated for the mesh. (each LOD roughly halves the detail of the previous one)
+    pub fn lod_count(mut self, lod_count: u32) -> Self {
+        self.mesh_lod_count = lod_count;
+        self
+    }
 } 
 
 impl AssetManager {
@@ -81,13 +101,26 @@ impl AssetManager {
         Self {
             loaders: Mutex::new(Vec::new()),
             //loader_groups: Mutex::new(Vec::new()),
+            bake_hashes: Mutex::new(HashMap::new()),
 
             lazy_cache: LazyCache::create(),
         }
     }
 
-    pub fn load_asset(&self, load_desc: AssetLoadDesc) -> anyhow::Result<()> {
-        let is_baked = self.is_baked(&load_desc.uri);
+    pub fn load_asset(&self, load_desc: AssetLoadDesc) -> Result<(), AssetLoadError> {
+        let supported = match &load_desc.load_ty {
+            LoadAssetType::Mesh(mesh_ty) => matches!(mesh_ty, LoadAssetMeshType::Gltf),
+            LoadAssetType::Texture(tex_ty) => matches!(tex_ty, LoadAssetTextureType::Jpg),
+            _ => false,
+        };
+        if !supported {
+            return Err(AssetLoadError::UnsupportedFormat { uri: load_desc.uri });
+        }
+
+        let bake_params = BakeParams::from_load_desc(&load_desc);
+        let bake_hash = asset_baker::calc_source_bake_hash(&load_desc.uri, &load_desc.load_ty, &bake_params)
+            .map_err(|err| AssetLoadError::ReadFailure { uri: load_desc.uri.clone(), reason: err.to_string() })?;
+        let is_baked = self.is_baked(&load_desc.uri, bake_hash);
 
         if let Some(baked) = is_baked {
             Self::mmap_baked_asset(&baked, &load_desc.uri)?;
@@ -95,28 +128,25 @@ impl AssetManager {
             let mut registry = get_runtime_asset_registry().write();
             // add the baked asset immediately
             let handle = registry.register_asset(Box::new(BakedAsset { uri: load_desc.uri.clone() }));
-            let bake_folder = raven_filesystem::get_project_folder_path_absolute(raven_filesystem::ProjectFolder::Baked)?;
             
             if let Some(mat_refs) = registry.get_asset_relative_materials(&handle) {
                 for mat_ref in mat_refs {
                     let uri = PathBuf::from(format!("{:8.8x}.mat", mat_ref.uuid()));
-                    let baked = bake_folder.join(uri.clone());
     
-                    Self::mmap_baked_asset(&baked, &uri)?;
+                    Self::mmap_baked_asset(&uri, &uri)?;
                 }
             }
 
             if let Some(tex_refs) = registry.get_asset_relative_textures(&handle) {
                 for tex_ref in tex_refs {
                     let uri = PathBuf::from(format!("{:8.8x}.tex", tex_ref.uuid()));
-                    let baked = bake_folder.join(uri.clone());
     
-                    Self::mmap_baked_asset(&baked, &uri)?;
+                    Self::mmap_baked_asset(&uri, &uri)?;
                 }
             }
 
             let mut loaders = self.loaders.lock();
-            let AssetLoadDesc { uri, load_ty: _ } = load_desc;
+            let AssetLoadDesc { uri, .. } = load_desc;
 
             // push a dummy task, it actually do nothing but just return the existed AssetHandle
             loaders.push(Arc::new(BakedAssetLoader { handle, uri }));
@@ -124,77 +154,113 @@ impl AssetManager {
             return Ok(());
         }
 
+        if let Some(hash) = bake_hash {
+            self.bake_hashes.lock().insert(load_desc.uri.clone(), hash);
+        }
+
         let mut loaders = self.loaders.lock();
-        let AssetLoadDesc { uri, load_ty } = load_desc;
+        let AssetLoadDesc { uri, load_ty, .. } = load_desc;
 
+        // the missing sources are reported in dispatch_load_tasks(), where the textures fall back to the placeholder
         match load_ty {
-            LoadAssetType::Mesh(mesh_ty) => {
-                match mesh_ty {
-                    LoadAssetMeshType::Gltf => { 
-                        loaders.push(Arc::new(GltfMeshLoader::new(uri)));
-                    }
-                    LoadAssetMeshType::Obj => { 
-                        unimplemented!()
-                    }
-                }
+            LoadAssetType::Mesh(_) => {
+                loaders.push(Arc::new(GltfMeshLoader::new(uri).generate_lods(bake_params.mesh_lod_count)));
             }
-            LoadAssetType::Texture(tex_ty) => {
-                match tex_ty {
-                    LoadAssetTextureType::Jpg => {
-                        // TODO: expose params
-                        loaders.push(Arc::new(JpgTextureLoader::new(uri).generate_mipmap(true)));
-                    }
-                    _ => unimplemented!()
-                }
+            LoadAssetType::Texture(_) => {
+                // TODO: expose params
+                loaders.push(Arc::new(JpgTextureLoader::new(uri).generate_mipmap(bake_params.generate_mipmap)));
             }
-            _ => unimplemented!()
+            _ => unreachable!()
         }
 
         Ok(())
     }
 
-    pub fn dispatch_load_tasks(&self) -> anyhow::Result<Vec<Arc<AssetHandle>>> {
+    /// Load, process and bake all the pending assets, the results are returned in the loading order.
+    /// A failed asset does not stop the others from loading, the textures fall back to the placeholder,
+    /// the other failures are returned in place of their handles.
+    pub fn dispatch_load_tasks(&self) -> Vec<Result<Arc<AssetHandle>, AssetLoadError>> {
         // TODO: optimize this
-        let mut loaders = self.loaders.lock();
-        let load_tasks = loaders.drain(..);
+        let workers = self.loaders.lock().drain(..).collect::<Vec<_>>();
           
-        let tasks_iter = load_tasks.into_iter()
+        let tasks_iter = workers.iter().cloned()
             .map(|worker| { 
                 let load_asset = LoadRawAsset { worker };
                 executor::spawn(load_asset.into_lazy().eval(&self.lazy_cache))
             });
 
-        let tasks = smol::block_on(futures::future::try_join_all(tasks_iter))?;
+        let loaded = smol::block_on(futures::future::join_all(tasks_iter));
 
-        let uris = tasks.iter()
-            .map(|loaded_raw| loaded_raw.key.0.clone())
-            .collect::<Vec<_>>();
+        // (loading index, uri, raw asset, whether it is a fallback of the failed asset)
+        let mut raw_assets = Vec::with_capacity(loaded.len());
+        let mut results: Vec<Option<Result<Arc<AssetHandle>, AssetLoadError>>> = (0..workers.len()).map(|_| None).collect();
 
-        let tasks_iter = tasks.into_iter()
-            .map(|loaded_raw| {
-                let process_asset = AssetProcessor::new(loaded_raw.key.0.clone(), loaded_raw.raw_asset.clone());
-                executor::spawn(process_asset.process().unwrap().eval(&self.lazy_cache))
-            })
-            .collect::<Vec<_>>();
+        for (idx, (worker, loaded_raw)) in workers.iter().zip(loaded.into_iter()).enumerate() {
+            let uri = worker.get_load_uri();
 
-        let tasks = smol::block_on(futures::future::try_join_all(tasks_iter))?;
+            match loaded_raw {
+                Ok(loaded_raw) => raw_assets.push((idx, uri, loaded_raw.raw_asset.clone(), false)),
+                Err(err) => {
+                    let err = to_load_error(&uri, &err);
 
-        let tasks_iter = tasks.iter().cloned().zip(uris.into_iter())
-            .map(|(asset, uri)| {
-                let baker = AssetBaker::new(asset, uri);
+                    if let Some(fallback) = worker.fallback() {
+                        glog::warn!("{} Use the placeholder instead.", err);
+                        raw_assets.push((idx, uri, fallback, true));
+                    } else {
+                        results[idx] = Some(Err(err));
+                    }
+                }
+            }
+        }
+
+        let tasks_iter = raw_assets.iter()
+            .map(|(_, uri, raw_asset, _)| {
+                let process_asset = AssetProcessor::new(uri.clone(), raw_asset.clone());
+                let lazy = process_asset.process();
+                let lazy_cache = self.lazy_cache.clone();
+
+                executor::spawn(async move { lazy?.eval(&lazy_cache).await })
+            });
+
+        let processed = smol::block_on(futures::future::join_all(tasks_iter));
+
+        let mut handles = Vec::with_capacity(processed.len());
+        for ((idx, uri, _, is_fallback), asset) in raw_assets.into_iter().zip(processed.into_iter()) {
+            match asset {
+                Ok(asset) => handles.push((idx, uri, asset, is_fallback)),
+                Err(err) => results[idx] = Some(Err(AssetLoadError::ParseFailure { uri, reason: err.to_string() })),
+            }
+        }
+
+        // the fallbacks are not baked, so that the missing assets are reported again next time
+        let tasks_iter = handles.iter()
+            .filter(|(_, _, _, is_fallback)| !is_fallback)
+            .map(|(_, uri, asset, _)| {
+                let bake_hash = self.bake_hashes.lock().remove(uri);
+                let baker = AssetBaker::new(asset.clone(), uri.clone(), bake_hash);
                 executor::spawn(baker.into_lazy().eval(&self.lazy_cache))
             });
 
-        smol::block_on(futures::future::try_join_all(tasks_iter))?;
+        // the asset is still usable even if it failed to bake
+        for baked in smol::block_on(futures::future::join_all(tasks_iter)) {
+            if let Err(err) = baked {
+                glog::warn!("Failed to bake asset: {}", err);
+            }
+        }
+
+        for (idx, _, asset, _) in handles {
+            results[idx] = Some(Ok(asset));
+        }
 
-        Ok(tasks)
+        results.into_iter()
+            .map(|result| result.expect("Every load task must produce a result!"))
+            .collect()
     }
 
-    fn mmap_baked_asset(baked_path: &PathBuf, uri: &PathBuf) -> anyhow::Result<(), AssetPipelineError> {
-        let file = std::fs::File::open(baked_path.clone()).expect("Failed to open the baked file path!");
-        let mmap = unsafe {
-            MmapOptions::new().map(&file).map_err(|err| AssetPipelineError::LoadFailure { err })
-        }?;
+    /// Map the baked file (relative to the Baked folder), the mounted pak is searched first, then the loose baked files.
+    fn mmap_baked_asset(baked_name: &PathBuf, uri: &PathBuf) -> Result<(), AssetLoadError> {
+        let mmap = raven_filesystem::map_file(baked_name, raven_filesystem::ProjectFolder::Baked)
+            .map_err(|err| AssetLoadError::ReadFailure { uri: uri.clone(), reason: err.to_string() })?;
 
         // use origin uri here
         ASSETS_MMAP.lock().entry(uri.clone()).or_insert_with(|| mmap);
@@ -202,7 +268,9 @@ impl AssetManager {
         Ok(())
     }
 
-    fn is_baked(&self, uri: &PathBuf) -> Option<PathBuf> {
+    /// Return the baked asset name if the asset is baked and up to date.
+    /// When the source exists, its bake hash must match the one stored with the baked asset, otherwise it is re-baked.
+    fn is_baked(&self, uri: &PathBuf, bake_hash: Option<u64>) -> Option<PathBuf> {
         let load_asset_type = extract_asset_type(uri);
         let mut baked_asset_name = get_uri_bake_stem(uri);
 
@@ -216,13 +284,31 @@ impl AssetManager {
             _ => unimplemented!()
         }
 
-        if raven_filesystem::exist(&baked_asset_name, raven_filesystem::ProjectFolder::Baked).unwrap() {
-            let folder = raven_filesystem::get_project_folder_path_absolute(raven_filesystem::ProjectFolder::Baked).unwrap();
-            let file = folder.join(baked_asset_name);
+        if !raven_filesystem::exist(&baked_asset_name, raven_filesystem::ProjectFolder::Baked).unwrap() {
+            return None;
+        }
+
+        match bake_hash {
+            Some(hash) if asset_baker::read_bake_hash(&baked_asset_name) != Some(hash) => {
+                glog::debug!("Source of {:?} changed, re-baking it.", uri);
+                None
+            }
+            _ => Some(baked_asset_name),
+        }
+    }
+}
 
-            Some(file)
-        } else {
-            None
+/// Keep the typed errors reported by the loaders, the others are parsing errors. (e.g. invalid gltf)
+fn to_load_error(uri: &PathBuf, err: &anyhow::Error) -> AssetLoadError {
+    match err.downcast_ref::<AssetLoadError>() {
+        Some(AssetLoadError::NotFound { uri }) => AssetLoadError::NotFound { uri: uri.clone() },
+        Some(AssetLoadError::UnsupportedFormat { uri }) => AssetLoadError::UnsupportedFormat { uri: uri.clone() },
+        _ => {
+            if let Some(io_err) = err.downcast_ref::<std::io::Error>() {
+                AssetLoadError::ReadFailure { uri: uri.clone(), reason: io_err.to_string() }
+            } else {
+                AssetLoadError::ParseFailure { uri: uri.clone(), reason: err.to_string() }
+            }
         }
     }
 }
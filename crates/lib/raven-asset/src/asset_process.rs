@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::hash::Hasher;
 use std::path::PathBuf;
 use std::marker::PhantomData;
@@ -121,6 +122,75 @@ impl RawMeshProcess {
     }
 }
 
+/// Generate the indices of the coarser LODs by clustering the vertices into a uniform grid.
+/// Each cluster collapses into the vertex closest to its average position, and the degenerated triangles are removed,
+/// so the LODs share the vertices of LOD 0 and only the indices are rebuilt.
+/// 
+/// The grid of the first LOD has `LOD_BASE_GRID_RESOLUTION` cells on the longest axis, and every next LOD halves it.
+/// Vertices of different materials are never merged, so that the material boundaries are kept.
+pub(crate) fn generate_lod_indices(positions: &[[f32; 3]], material_ids: &[u32], indices: &[u32], aabb: &AABB, lod_count: u32) -> Vec<Vec<u32>> {
+    const LOD_BASE_GRID_RESOLUTION: u32 = 64;
+
+    let extent = aabb.max - aabb.min;
+    let longest_axis = extent.max_element();
+
+    let mut lods: Vec<Vec<u32>> = Vec::new();
+    if positions.is_empty() || longest_axis <= 0.0 {
+        return lods;
+    }
+
+    for lod in 0..lod_count {
+        let resolution = (LOD_BASE_GRID_RESOLUTION >> lod).max(1);
+        let cell_size = longest_axis / resolution as f32;
+
+        // cluster key -> (position sum, vertex count)
+        let mut clusters: HashMap<(u32, [u32; 3]), (Vec3, u32)> = HashMap::new();
+        let cluster_keys = positions.iter().enumerate()
+            .map(|(idx, pos)| {
+                let cell = (Vec3::from(*pos) - aabb.min) / cell_size;
+                let key = (
+                    material_ids.get(idx).copied().unwrap_or(0),
+                    [cell.x as u32, cell.y as u32, cell.z as u32],
+                );
+
+                let cluster = clusters.entry(key).or_insert((Vec3::ZERO, 0));
+                cluster.0 += Vec3::from(*pos);
+                cluster.1 += 1;
+
+                key
+            })
+            .collect::<Vec<_>>();
+
+        // the representative vertex of each cluster
+        let mut representatives: HashMap<(u32, [u32; 3]), (u32, f32)> = HashMap::new();
+        for (idx, key) in cluster_keys.iter().enumerate() {
+            let (sum, count) = clusters[key];
+            let distance = Vec3::from(positions[idx]).distance_squared(sum / count as f32);
+
+            let representative = representatives.entry(*key).or_insert((idx as u32, distance));
+            if distance < representative.1 {
+                *representative = (idx as u32, distance);
+            }
+        }
+
+        let lod_indices = indices.chunks_exact(3)
+            .map(|tri| tri.iter().map(|&idx| representatives[&cluster_keys[idx as usize]].0).collect::<Vec<_>>())
+            .filter(|tri| tri[0] != tri[1] && tri[1] != tri[2] && tri[0] != tri[2])
+            .flatten()
+            .collect::<Vec<_>>();
+
+        // nothing more to simplify, or the mesh collapsed entirely
+        let prev_index_count = lods.last().map_or(indices.len(), |prev| prev.len());
+        if lod_indices.is_empty() || lod_indices.len() >= prev_index_count {
+            break;
+        }
+
+        lods.push(lod_indices);
+    }
+
+    lods
+}
+
 #[async_trait]
 impl LazyWorker for RawMeshProcess {
     type Output = anyhow::Result<AssetHandle>;
@@ -130,6 +200,10 @@ impl LazyWorker for RawMeshProcess {
         let mut packed_vertex = Vec::with_capacity(self.raw.positions.len());
 
         let aabb = Self::calculate_mesh_aabb(&self.raw.positions);
+        let lod_indices = generate_lod_indices(
+            &self.raw.positions, &self.raw.material_ids, &self.raw.indices,
+            &aabb, self.raw.lod_count
+        );
 
         for (idx, pos) in self.raw.positions.iter().enumerate() {
             let [nx, ny, nz] = self.raw.normals[idx];
@@ -190,6 +264,7 @@ impl LazyWorker for RawMeshProcess {
             materials: materials,
             material_textures: textures,
             material_ids: self.raw.material_ids,
+            lod_indices,
         });
 
         let asset_registry = super::asset_registry::get_runtime_asset_registry();
@@ -221,6 +296,18 @@ impl RawTextureProcess {
             handle
         }
     }
+
+    fn placeholder_storage(color: [u8; 4]) -> Box<Texture::Storage> {
+        Box::new(Texture::Storage {
+            extent: [1, 1, 1],
+            lod_groups: vec![Bytes::copy_from_slice(&color).to_vec()],
+            desc: TextureDesc {
+                gamma_space: TextureGammaSpace::Linear,
+                use_mipmap: false,
+                channel_count: 4,
+            }
+        })
+    }
 }
 
 #[async_trait]
@@ -228,75 +315,93 @@ impl LazyWorker for RawTextureProcess {
     type Output = anyhow::Result<AssetHandle>;
 
     async fn run(mut self, _cx: RunContext) -> Self::Output {
-        let bytes = match self.raw.source {
+        let storage = match self.raw.source {
             TextureSource::Empty => unreachable!(),
             TextureSource::Placeholder(pc) => {
-                let bytes = vec![Bytes::copy_from_slice(&pc).to_vec()];
-
-                let storage = Box::new(Texture::Storage {
-                    extent: [1, 1, 1],
-                    lod_groups: bytes,
-                    desc: TextureDesc {
-                        gamma_space: TextureGammaSpace::Linear,
-                        use_mipmap: false,
-                    }
-                });
-        
-                let asset_registry = super::asset_registry::get_runtime_asset_registry();
-                asset_registry.write().update_asset(&mut self.handle, storage);
-
-                return Ok(self.handle);
+                Self::placeholder_storage(pc)
             }
             TextureSource::Bytes(bytes) => {
-                bytes
+                match decode_texture(&bytes, &self.raw.desc) {
+                    Ok((extent, lod_groups, desc)) => Box::new(Texture::Storage {
+                        extent,
+                        lod_groups,
+                        desc,
+                    }),
+                    // keep running with a visible placeholder
+                    Err(err) => {
+                        glog::warn!("Failed to decode texture: {}! Use the placeholder instead.", err);
+                        Self::placeholder_storage(super::MISSING_TEXTURE_COLOR)
+                    }
+                }
             },
         };
 
-        let tex_desc = &self.raw.desc;
-        let image = image::load_from_memory(&bytes)?;
-        let extent = [image.width(), image.height(), 1];
+        let asset_registry = super::asset_registry::get_runtime_asset_registry();
+        asset_registry.write().update_asset(&mut self.handle, storage);
 
-        let down_sample_func = |image: &DynamicImage| {
-            let width = image.width() >> 1;
-            let height = image.height() >> 1;
+        Ok(self.handle)
+    }
+}
 
-            image.resize_exact(width, height, FilterType::Lanczos3)
-        };
+/// Decode the image and generate the mipmaps if needed, every mip level keeps only the channels of the desc.
+/// Returns the extent, the bytes of the mip levels and the desc actually stored.
+pub(crate) fn decode_texture(bytes: &[u8], desc: &TextureDesc) -> anyhow::Result<([u32; 3], Vec<Vec<u8>>, TextureDesc)> {
+    let mut desc = *desc;
+    assert!(matches!(desc.channel_count, 1 | 2 | 4), "Unsupported texture channel count: {}", desc.channel_count);
 
-        // generate mipmap bytes
-        let lod_groups = if tex_desc.use_mipmap {
-            let mipmap_level = raven_math::max_mipmap_level_2d(extent[0], extent[1]);
-
-            let mut mips = Vec::new();
-            // level 0
-            let mut image = {
-                let mip = down_sample_func(&image);
-                mips.push(image.into_rgba8().into_raw());
-                mip
-            };
-
-            for _ in 1..mipmap_level {
-                let next = down_sample_func(&image);
-                let mip = std::mem::replace(&mut image, next);
-                mips.push(mip.into_rgba8().into_raw());
-            }
+    // data textures are never color, the srgb curve would distort the values
+    if desc.channel_count < 4 && matches!(desc.gamma_space, TextureGammaSpace::Srgb) {
+        glog::warn!("Texture with {} channels can not be in srgb space, use linear space instead!", desc.channel_count);
+        desc.gamma_space = TextureGammaSpace::Linear;
+    }
 
-            mips
+    let image = image::load_from_memory(bytes)?;
+    let extent = [image.width(), image.height(), 1];
+    let channel_count = desc.channel_count as usize;
+
+    // grayscale images are expanded into rgb, so the first channel is always the value
+    let to_bytes = |image: DynamicImage| -> Vec<u8> {
+        let rgba = image.into_rgba8().into_raw();
+        if channel_count == 4 {
+            rgba
         } else {
-            vec![image.into_rgba8().into_raw()]
+            rgba.chunks_exact(4)
+                .flat_map(|pixel| pixel[..channel_count].iter().copied())
+                .collect()
+        }
+    };
+
+    let down_sample_func = |image: &DynamicImage| {
+        let width = (image.width() >> 1).max(1);
+        let height = (image.height() >> 1).max(1);
+
+        image.resize_exact(width, height, FilterType::Lanczos3)
+    };
+
+    // generate mipmap bytes
+    let lod_groups = if desc.use_mipmap {
+        let mipmap_level = raven_math::max_mipmap_level_2d(extent[0], extent[1]);
+
+        let mut mips = Vec::new();
+        // level 0
+        let mut image = {
+            let mip = down_sample_func(&image);
+            mips.push(to_bytes(image));
+            mip
         };
 
-        let storage = Box::new(Texture::Storage {
-            extent,
-            lod_groups,
-            desc: tex_desc.clone(),
-        });
+        for _ in 1..mipmap_level {
+            let next = down_sample_func(&image);
+            let mip = std::mem::replace(&mut image, next);
+            mips.push(to_bytes(mip));
+        }
 
-        let asset_registry = super::asset_registry::get_runtime_asset_registry();
-        asset_registry.write().update_asset(&mut self.handle, storage);
+        mips
+    } else {
+        vec![to_bytes(image)]
+    };
 
-        Ok(self.handle)
-    }
+    Ok((extent, lod_groups, desc))
 }
 
 #[derive(Clone)]
@@ -333,8 +438,13 @@ impl LazyWorker for RawMaterialProcess {
             roughness: self.raw.roughness,
             base_color: self.raw.base_color,
             emissive: self.raw.emissive,
+            emissive_intensity: self.raw.emissive_intensity,
             texture_mapping: self.raw.texture_mapping,
             texture_transform: self.raw.texture_transform,
+            alpha_blend: self.raw.alpha_blend,
+            unlit: self.raw.unlit,
+            normal_strength: self.raw.normal_strength,
+            normal_flip_green: self.raw.normal_flip_green,
         });
 
         let asset_registry = super::asset_registry::get_runtime_asset_registry();
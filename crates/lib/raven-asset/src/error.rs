@@ -1,7 +1,10 @@
 use std::io::Error;
+use std::path::PathBuf;
 
 use thiserror::Error as ThisError;
 
+use raven_core::result::CombinableError;
+
 #[derive(Debug, ThisError)]
 pub enum AssetPipelineError {
     #[error("Asset pipeline failed on loading RawAsset with {err:?}!")]
@@ -13,4 +16,49 @@ pub enum AssetPipelineError {
 
     #[error("Asset pipeline failed on baking StorageAsset to PackedAsset!")]
     BakeFailure,
-}
\ No newline at end of file
+}
+
+/// Failure of loading the assets, reported per asset.
+#[derive(Debug, ThisError)]
+pub enum AssetLoadError {
+    #[error("Asset {uri:?} is not found!")]
+    NotFound { uri: PathBuf },
+
+    #[error("Failed to read asset {uri:?}: {reason}")]
+    ReadFailure { uri: PathBuf, reason: String },
+
+    #[error("Failed to parse asset {uri:?}: {reason}")]
+    ParseFailure { uri: PathBuf, reason: String },
+
+    #[error("Unsupported asset format: {uri:?}")]
+    UnsupportedFormat { uri: PathBuf },
+
+    #[error("{} asset(s) failed to load:{}", .errors.len(), format_errors(.errors))]
+    Multiple { errors: Vec<AssetLoadError> },
+}
+
+fn format_errors(errors: &[AssetLoadError]) -> String {
+    errors.iter()
+        .map(|err| format!("\n    {}", err))
+        .collect()
+}
+
+impl AssetLoadError {
+    fn into_errors(self) -> Vec<AssetLoadError> {
+        match self {
+            AssetLoadError::Multiple { errors } => errors,
+            err => vec![err],
+        }
+    }
+}
+
+impl CombinableError for AssetLoadError {
+    fn combine(&mut self, other: Self) {
+        let this = std::mem::replace(self, AssetLoadError::Multiple { errors: Vec::new() });
+
+        let mut errors = this.into_errors();
+        errors.extend(other.into_errors());
+
+        *self = AssetLoadError::Multiple { errors };
+    }
+}
@@ -10,7 +10,8 @@ mod pack_unpack;
 mod util;
 mod error;
 
-pub use asset_manager::{AssetManager, AssetLoadDesc};
+pub use asset_manager::{AssetManager, AssetLoadDesc, DEFAULT_MESH_LOD_COUNT, MISSING_TEXTURE_COLOR};
+pub use error::AssetLoadError;
 
 use std::path::PathBuf;
 use std::sync::Arc;
@@ -511,6 +512,7 @@ define_asset!{
         materials         { Vec(Material::Raw) }
         material_textures { Vec(Texture::Raw) }
         material_ids      { Vec(u32) }
+        lod_count         { u32 }       // number of the coarser LODs generated in mesh processing
     }
     // storage
     {
@@ -524,6 +526,7 @@ define_asset!{
         materials         { Vec(Asset(Material)) }
         material_textures { Vec(Asset(Texture)) }
         material_ids      { Vec(u32) }
+        lod_indices       { Vec(Vec(u32)) } // indices of the coarser LODs (LOD 1, 2, ...), sharing the vertices of LOD 0
     }
     Mesh
 }
@@ -553,6 +556,9 @@ pub enum TextureGammaSpace {
 pub struct TextureDesc {
     pub gamma_space: TextureGammaSpace,
     pub use_mipmap: bool,
+    /// Number of the 8 bits channels stored, 1 (R), 2 (RG) or 4 (RGBA).
+    /// Single and dual channel textures hold data (e.g. roughness, metallic) and are always in linear space.
+    pub channel_count: u32,
 }
 
 impl Default for TextureDesc {
@@ -562,10 +568,30 @@ impl Default for TextureDesc {
             //ty: LoadAssetTextureType::Unknown,
             gamma_space: TextureGammaSpace::Linear,
             use_mipmap: false,
+            channel_count: 4,
         }
     }
 }
 
+impl TextureDesc {
+    #[inline]
+    pub fn bytes_per_pixel(&self) -> u32 {
+        self.channel_count
+    }
+
+    /// Row pitch in bytes of the mip level of a texture, the rows are tightly packed.
+    #[inline]
+    pub fn row_pitch_in_bytes(&self, extent: [u32; 3], mip_level: u32) -> u32 {
+        (extent[0] >> mip_level).max(1) * self.bytes_per_pixel()
+    }
+
+    /// Size in bytes of the mip level of a 2d texture.
+    #[inline]
+    pub fn mip_size_in_bytes(&self, extent: [u32; 3], mip_level: u32) -> usize {
+        self.row_pitch_in_bytes(extent, mip_level) as usize * (extent[1] >> mip_level).max(1) as usize
+    }
+}
+
 define_asset!{
     // raw
     #[derive(Default, Debug, Clone, Hash)]
@@ -587,26 +613,55 @@ define_asset!{
     // raw
     #[derive(Default, Copy, Clone, Debug)]
     Material {
-        metallic          { f32 }
-        roughness         { f32 }
-        base_color        { [f32; 4] }
-        emissive          { [f32; 3] }
-        texture_mapping   { [u32; 4] }      // textures to be used in this material [albedo, normal, specular, emissive]
-        texture_transform { [[f32; 6]; 4] } // the corresponding 2D transform of the texture
+        metallic           { f32 }
+        roughness          { f32 }
+        base_color         { [f32; 4] }
+        emissive           { [f32; 3] }
+        emissive_intensity { f32 }           // hdr multiplier of the emissive color, can be far beyond 1.0 to drive the bloom
+        texture_mapping    { [u32; 4] }      // textures to be used in this material [albedo, normal, specular, emissive]
+        texture_transform  { [[f32; 6]; 4] } // the corresponding 2D transform of the texture
+        alpha_blend        { bool }          // whether this material is transparent and need to be alpha blended
+        unlit              { bool }          // whether this material skips lighting and emits its base color as it is
+        normal_strength    { f32 }           // scale of the normal map perturbation, 0.0 means no perturbation
+        normal_flip_green  { bool }          // whether the normal map is authored in DirectX convention (i.e. green channel points down)
     }
     // storage
     #[derive(Default, Copy, Clone, Debug)]
     {
-        metallic          { f32 }
-        roughness         { f32 }
-        base_color        { [f32; 4] }
-        emissive          { [f32; 3] }
-        texture_mapping   { [u32; 4] }      // textures to be used in this material [albedo, normal, specular, emissive]
-        texture_transform { [[f32; 6]; 4] } // the corresponding 2D transform of the texture
+        metallic           { f32 }
+        roughness          { f32 }
+        base_color         { [f32; 4] }
+        emissive           { [f32; 3] }
+        emissive_intensity { f32 }           // hdr multiplier of the emissive color, can be far beyond 1.0 to drive the bloom
+        texture_mapping    { [u32; 4] }      // textures to be used in this material [albedo, normal, specular, emissive]
+        texture_transform  { [[f32; 6]; 4] } // the corresponding 2D transform of the texture
+        alpha_blend        { bool }          // whether this material is transparent and need to be alpha blended
+        unlit              { bool }          // whether this material skips lighting and emits its base color as it is
+        normal_strength    { f32 }           // scale of the normal map perturbation, 0.0 means no perturbation
+        normal_flip_green  { bool }          // whether the normal map is authored in DirectX convention (i.e. green channel points down)
     }
     Material
 }
 
+impl Material::Raw {
+    /// A material which skips lighting and emits its base color as it is.
+    /// Textures are mapped the same as the loaded materials, i.e. [albedo, normal, specular, emissive] of the mesh textures.
+    /// Useful for debug markers, UI in world and glowing objects.
+    pub fn unlit(base_color: [f32; 4]) -> Self {
+        Self {
+            base_color,
+            roughness: 1.0,
+            texture_mapping: [0, 1, 2, 3],
+            texture_transform: [[1.0, 0.0, 0.0, 1.0, 0.0, 0.0]; 4],
+            alpha_blend: base_color[3] < 1.0,
+            unlit: true,
+            normal_strength: 1.0,
+            emissive_intensity: 1.0,
+            ..Default::default()
+        }
+    }
+}
+
 fn get_uri_bake_stem(uri: &PathBuf) -> PathBuf {
     let asset_ty = loader::extract_asset_type(uri);
 
@@ -646,6 +701,7 @@ fn test_vec_array_pack_unpack() {
         desc: TextureDesc {
             gamma_space: TextureGammaSpace::Linear,
             use_mipmap: false,
+            channel_count: 4,
         }
     };
 
@@ -689,4 +745,146 @@ fn test_vec_array_pack_unpack() {
         println!("{:?}", lod);
         assert_eq!(texture.lod_groups[i], lod);
     }
-}
\ No newline at end of file
+}
+#[test]
+fn test_bake_hash() {
+    use asset_baker::{calc_bake_hash, BakeParams};
+
+    let source = b"raven baked asset source";
+    let params = BakeParams { generate_mipmap: true, mesh_lod_count: 0 };
+
+    // deterministic between calls
+    assert_eq!(calc_bake_hash(source, &[], &params), calc_bake_hash(source, &[], &params));
+    // changed source or bake parameters must trigger a rebake
+    assert_ne!(calc_bake_hash(source, &[], &params), calc_bake_hash(b"raven baked asset sourcf", &[], &params));
+    assert_ne!(calc_bake_hash(source, &[], &params), calc_bake_hash(source, &[], &BakeParams { generate_mipmap: false, mesh_lod_count: 0 }));
+    assert_ne!(calc_bake_hash(source, &[], &params), calc_bake_hash(source, &[], &BakeParams { generate_mipmap: true, mesh_lod_count: 2 }));
+    // so does a changed dependency (e.g. the .bin buffer of a gltf), even if the bytes are only moved between the files
+    let dependencies = vec![b"buffer".to_vec(), b"image".to_vec()];
+    assert_ne!(calc_bake_hash(source, &[], &params), calc_bake_hash(source, &dependencies, &params));
+    assert_ne!(calc_bake_hash(source, &dependencies, &params), calc_bake_hash(source, &[b"buffer".to_vec(), b"imagf".to_vec()], &params));
+    assert_ne!(calc_bake_hash(source, &dependencies, &params), calc_bake_hash(source, &[b"bufferi".to_vec(), b"mage".to_vec()], &params));
+}
+
+#[test]
+fn test_generate_lod_indices() {
+    // two grids of 64x64 quads on the same plane, using different materials
+    const QUADS: u32 = 64;
+    let grid_vertex_count = (QUADS + 1) * (QUADS + 1);
+
+    let grid_positions = (0..=QUADS)
+        .flat_map(|z| (0..=QUADS).map(move |x| [x as f32 / QUADS as f32, 0.0, z as f32 / QUADS as f32]))
+        .collect::<Vec<_>>();
+    let positions = [grid_positions.clone(), grid_positions].concat();
+    let material_ids = (0..2 * grid_vertex_count).map(|idx| idx / grid_vertex_count).collect::<Vec<_>>();
+
+    let mut indices = Vec::new();
+    for base in [0, grid_vertex_count] {
+        for z in 0..QUADS {
+            for x in 0..QUADS {
+                let v0 = base + z * (QUADS + 1) + x;
+                let v1 = v0 + QUADS + 1;
+                indices.extend_from_slice(&[v0, v1, v0 + 1, v0 + 1, v1, v1 + 1]);
+            }
+        }
+    }
+
+    // padded like the mesh processing
+    let mut aabb = AABB::new();
+    for pos in &positions {
+        aabb.merge_point_f32(pos);
+    }
+    aabb.min -= raven_math::Vec3::splat(0.01);
+    aabb.max += raven_math::Vec3::splat(0.01);
+
+    let lods = asset_process::generate_lod_indices(&positions, &material_ids, &indices, &aabb, 3);
+    assert!(!lods.is_empty() && lods.len() <= 3);
+
+    let mut prev_index_count = indices.len();
+    for lod in &lods {
+        // every LOD is coarser than the previous one
+        assert_eq!(lod.len() % 3, 0);
+        assert!(lod.len() < prev_index_count);
+        prev_index_count = lod.len();
+
+        for tri in lod.chunks_exact(3) {
+            assert!(tri.iter().all(|&idx| (idx as usize) < positions.len()));
+            assert!(tri[0] != tri[1] && tri[1] != tri[2] && tri[0] != tri[2]);
+            // vertices of different materials are never merged
+            assert!(tri.iter().all(|&idx| material_ids[idx as usize] == material_ids[tri[0] as usize]));
+        }
+    }
+
+    assert!(asset_process::generate_lod_indices(&positions, &material_ids, &indices, &aabb, 0).is_empty());
+    assert!(asset_process::generate_lod_indices(&[], &[], &[], &aabb, 3).is_empty());
+
+    // a single big triangle can't be simplified
+    let positions = [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]];
+    let mut aabb = AABB::new();
+    for pos in &positions {
+        aabb.merge_point_f32(pos);
+    }
+    assert!(asset_process::generate_lod_indices(&positions, &[0; 3], &[0, 1, 2], &aabb, 3).is_empty());
+}
+
+#[test]
+fn test_decode_texture_channels() {
+    use std::io::Cursor;
+    use image::{DynamicImage, ImageOutputFormat, Luma, Rgba};
+
+    let encode_png = |image: DynamicImage| {
+        let mut bytes = Vec::new();
+        image.write_to(&mut Cursor::new(&mut bytes), ImageOutputFormat::Png).unwrap();
+        bytes
+    };
+
+    // albedo, rgba in srgb space
+    let albedo = encode_png(DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(4, 4, Rgba([255, 128, 0, 255]))));
+    let desc = TextureDesc { gamma_space: TextureGammaSpace::Srgb, use_mipmap: true, channel_count: 4 };
+    let (extent, lod_groups, stored_desc) = asset_process::decode_texture(&albedo, &desc).unwrap();
+
+    assert_eq!(extent, [4, 4, 1]);
+    assert!(matches!(stored_desc.gamma_space, TextureGammaSpace::Srgb));
+    assert_eq!(lod_groups.len(), 3);
+    for (level, mip) in lod_groups.iter().enumerate() {
+        assert_eq!(mip.len(), stored_desc.mip_size_in_bytes(extent, level as u32));
+    }
+    assert_eq!(&lod_groups[0][..4], &[255, 128, 0, 255]);
+
+    // roughness, single channel data is always linear
+    let roughness = encode_png(DynamicImage::ImageLuma8(image::GrayImage::from_pixel(3, 2, Luma([77]))));
+    let desc = TextureDesc { gamma_space: TextureGammaSpace::Srgb, use_mipmap: false, channel_count: 1 };
+    let (extent, lod_groups, stored_desc) = asset_process::decode_texture(&roughness, &desc).unwrap();
+
+    assert_eq!(extent, [3, 2, 1]);
+    assert!(matches!(stored_desc.gamma_space, TextureGammaSpace::Linear));
+    assert_eq!(stored_desc.row_pitch_in_bytes(extent, 0), 3);
+    assert_eq!(lod_groups, vec![vec![77; 6]]);
+}
+
+#[test]
+fn test_combine_load_errors() {
+    use raven_core::result::ResultFlattener;
+
+    let results: Vec<Result<u32, AssetLoadError>> = vec![
+        Ok(0),
+        Err(AssetLoadError::NotFound { uri: PathBuf::from("mesh/missing.gltf") }),
+        Ok(1),
+        Err(AssetLoadError::UnsupportedFormat { uri: PathBuf::from("mesh/model.fbx") }),
+    ];
+
+    let err = results.into_iter()
+        .fold(ResultFlattener::default(), ResultFlattener::fold)
+        .finish()
+        .unwrap_err();
+
+    match &err {
+        AssetLoadError::Multiple { errors } => {
+            assert_eq!(errors.len(), 2);
+            assert!(matches!(errors[0], AssetLoadError::NotFound { .. }));
+            assert!(matches!(errors[1], AssetLoadError::UnsupportedFormat { .. }));
+        }
+        _ => panic!("Expect combined errors, found {:?}", err),
+    }
+    assert!(err.to_string().starts_with("2 asset(s) failed to load:"));
+}
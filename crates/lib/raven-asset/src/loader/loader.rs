@@ -15,6 +15,7 @@ pub enum LoadAssetTextureType {
 
 #[derive(Debug, Clone, Hash)]
 pub enum LoadAssetMeshType {
+    Unknown,
     Gltf,
     Obj,
 }
@@ -107,4 +108,10 @@ pub trait AssetLoader {
     fn load(&self) -> anyhow::Result<Arc<dyn RawAsset>>;
 
     fn get_load_uri(&self) -> PathBuf;
+
+    /// Raw asset used in place of this asset when it failed to load, so that the app can keep running.
+    /// Return None to report the failure instead.
+    fn fallback(&self) -> Option<Arc<dyn RawAsset>> {
+        None
+    }
 }
\ No newline at end of file
@@ -14,12 +14,13 @@ use bytes::Bytes;
 
 use raven_math::{Mat4, Vec3, Vec4};
 use raven_filesystem::{self, ProjectFolder};
-use crate::{loader::loader::LoadAssetMeshType, Mesh, RawAsset, Material, TextureDesc, TextureGammaSpace, Texture, TextureSource};
+use crate::{loader::loader::LoadAssetMeshType, AssetLoadError, Mesh, RawAsset, Material, TextureDesc, TextureGammaSpace, Texture, TextureSource};
 use super::super::loader::{self, AssetLoader};
 use crate::util::GenTangentContext;
 
 pub struct GltfMeshLoader {
     path: PathBuf,
+    lod_count: u32,
 }
 
 impl GltfMeshLoader {
@@ -29,15 +30,23 @@ impl GltfMeshLoader {
 
         Self {
             path,
+            lod_count: 0,
         }
     }
+
+    pub fn generate_lods(mut self, lod_count: u32) -> Self {
+        self.lod_count = lod_count;
+        self
+    }
 }
 
 impl AssetLoader for GltfMeshLoader {
     fn load(&self) -> anyhow::Result<Arc<dyn RawAsset>> {
         let dir = raven_filesystem::get_project_folder_path_absolute(ProjectFolder::Assets)?;
         let path = dir.join(self.path.clone());
-        assert!(path.is_file(), "Path may not exists or this path is not a file! {:?}", path);
+        if !path.is_file() {
+            return Err(AssetLoadError::NotFound { uri: self.path.clone() }.into());
+        }
 
         let file = fs::File::open(&path)?;
         let reader = io::BufReader::new(file);
@@ -54,7 +63,8 @@ impl AssetLoader for GltfMeshLoader {
         let buffers = extract_document_buffers(&document, &base_path, &mut blob)?;
         let images = extract_document_images(&document, &base_path, &buffers)?;
 
-        let raw_asset = load_gltf_default_scene(&document, &buffers, &images)?;
+        let mut raw_asset = load_gltf_default_scene(&document, &buffers, &images)?;
+        raw_asset.lod_count = self.lod_count;
 
         Ok(Arc::new(raw_asset))
     }
@@ -291,8 +301,15 @@ fn load_gltf_material(mat: &GltfMaterial, images: &[Bytes]) -> anyhow::Result<(V
         roughness: mat.pbr_metallic_roughness().roughness_factor(),
         base_color: mat.pbr_metallic_roughness().base_color_factor(),
         emissive: mat.emissive_factor(),
+        // KHR_materials_emissive_strength scales the emissive factor beyond 1.0
+        emissive_intensity: mat.emissive_strength().unwrap_or(1.0),
         texture_mapping: [0, 1, 2, 3],
         texture_transform: [albedo_tex_xform, DEFAULT_TEX_XFORM, specular_tex_xform, emissive_tex_xform],
+        alpha_blend: mat.alpha_mode() == gltf::material::AlphaMode::Blend,
+        unlit: mat.unlit(),
+        normal_strength: mat.normal_texture().map_or(1.0, |tex| tex.scale()),
+        // gltf normal maps are always in OpenGL convention
+        normal_flip_green: false,
     };
 
     Ok((vec![albedo_tex, normal_tex, specular_tex, emissive_tex], material))
@@ -464,4 +481,32 @@ fn test_asset_load_gltf() {
     println!("{:?}", mesh_asset.material_ids);
     println!("{:#?}", mesh_asset.materials);
     println!("{:#?}", mesh_asset.material_textures);
-}
\ No newline at end of file
+}
+#[test]
+fn test_asset_load_gltf_emissive_strength() {
+    let json = br#"{
+        "asset": { "version": "2.0" },
+        "extensionsUsed": [ "KHR_materials_emissive_strength" ],
+        "materials": [
+            {
+                "emissiveFactor": [ 1.0, 0.5, 0.25 ],
+                "extensions": {
+                    "KHR_materials_emissive_strength": { "emissiveStrength": 8.0 }
+                }
+            },
+            {
+                "emissiveFactor": [ 1.0, 1.0, 1.0 ]
+            }
+        ]
+    }"#;
+    let gltf = Gltf::from_slice(json).unwrap();
+    let materials = gltf.document.materials().collect::<Vec<_>>();
+
+    let (_, strong) = load_gltf_material(&materials[0], &[]).unwrap();
+    assert_eq!(strong.emissive, [1.0, 0.5, 0.25]);
+    assert_eq!(strong.emissive_intensity, 8.0);
+
+    // without the extension the emissive factor is used as it is
+    let (_, plain) = load_gltf_material(&materials[1], &[]).unwrap();
+    assert_eq!(plain.emissive_intensity, 1.0);
+}
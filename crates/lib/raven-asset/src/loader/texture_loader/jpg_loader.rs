@@ -5,7 +5,7 @@ use std::sync::Arc;
 
 use bytes::Bytes;
 
-use crate::{Texture, TextureSource, TextureDesc, TextureGammaSpace, loader};
+use crate::{Texture, TextureSource, TextureDesc, TextureGammaSpace, AssetLoadError, MISSING_TEXTURE_COLOR, loader};
 use crate::loader::{AssetLoader, LoadAssetTextureType};
 
 use raven_filesystem::{self, ProjectFolder};
@@ -36,7 +36,9 @@ impl AssetLoader for JpgTextureLoader {
     fn load(&self) -> anyhow::Result<Arc<dyn crate::RawAsset>> {
         let folder = raven_filesystem::get_project_folder_path_absolute(ProjectFolder::Assets)?;
         let path = folder.join(self.path.clone());
-        assert!(path.is_file());
+        if !path.is_file() {
+            return Err(AssetLoadError::NotFound { uri: self.path.clone() }.into());
+        }
         let mut file = File::open(path)?;
         
         let mut bytes = Vec::new();
@@ -49,6 +51,7 @@ impl AssetLoader for JpgTextureLoader {
                 //ty: LoadAssetTextureType::Jpg,
                 gamma_space: TextureGammaSpace::Linear,
                 use_mipmap: self.need_gen_mipmap,
+                channel_count: 4,
             },
         }))
     }
@@ -56,4 +59,11 @@ impl AssetLoader for JpgTextureLoader {
     fn get_load_uri(&self) -> PathBuf {
         self.path.clone()
     }
+
+    fn fallback(&self) -> Option<Arc<dyn crate::RawAsset>> {
+        Some(Arc::new(Texture::Raw {
+            source: TextureSource::Placeholder(MISSING_TEXTURE_COLOR),
+            desc: TextureDesc::default(),
+        }))
+    }
 }
\ No newline at end of file
@@ -23,6 +23,23 @@ struct ByteSectionPatch {
     section_idx: usize,
 }
 
+/// Where a packed section is in the packed bytes, and which child sections it patched.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct PackedSection {
+    /// Offset from the beginning of the packed bytes.
+    pub offset: usize,
+    pub len: usize,
+    /// Patch addresses (relative to this section) and the indices of the child sections.
+    pub childs: Vec<(usize, usize)>,
+}
+
+/// Layout of the bytes written by [`TreeByteBuffer::write_packed`], the root section is the first one.
+/// The packed bytes don't know where the sections are, so this is needed to walk the packed tree back.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct PackedTreeLayout {
+    pub sections: Vec<PackedSection>,
+}
+
 #[derive(Default, Clone)]
 struct ByteSection {
     bytes: Vec<u8>,
@@ -56,7 +73,7 @@ impl TreeByteBuffer {
         }
     }
 
-    pub fn write_packed(mut self, writer: &mut impl std::io::Write) {
+    pub fn write_packed(mut self, writer: &mut impl std::io::Write) -> PackedTreeLayout {
         let section_count = self.allocate_section_idx();
 
         let mut sections = vec![Default::default(); section_count];
@@ -114,9 +131,23 @@ impl TreeByteBuffer {
             }
         }
 
+        let layout = PackedTreeLayout {
+            sections: sections.iter().zip(sections_base_addr.iter())
+                .map(|(section, base_addr)| PackedSection {
+                    offset: *base_addr,
+                    len: section.bytes.len(),
+                    childs: section.patches.iter()
+                        .map(|patch| (patch.patch_addr, patch.section_idx))
+                        .collect(),
+                })
+                .collect(),
+        };
+
         // write the final binaries
         for section in sections {
             writer.write_all(section.bytes.as_slice()).unwrap();
         }
+
+        layout
     }
 }
\ No newline at end of file
@@ -0,0 +1,163 @@
+use std::fmt::Write;
+
+use thiserror::Error;
+
+use crate::byte_buffer::PackedTreeLayout;
+
+#[derive(Debug, Error)]
+pub enum TreeByteBufferReadError {
+    #[error("Packed tree layout has no root section")]
+    NoRootSection,
+    #[error("Section index {section_idx} is out of the layout (with {section_count} sections)")]
+    InvalidSection {
+        section_idx: usize,
+        section_count: usize,
+    },
+    #[error("Section {section_idx} is reached more than once, the layout is not a tree")]
+    CyclicSection {
+        section_idx: usize,
+    },
+    #[error("Section {section_idx} at {offset} with {len} bytes is out of the packed bytes (length {bytes_len})")]
+    SectionOutOfBounds {
+        section_idx: usize,
+        offset: usize,
+        len: usize,
+        bytes_len: usize,
+    },
+    #[error("Patch address {patch_addr} is out of the section {section_idx}")]
+    PatchOutOfBounds {
+        section_idx: usize,
+        patch_addr: usize,
+    },
+    #[error("Patched offset at {absolute_patch_addr} points to {target_addr}, but the child section {section_idx} starts at {section_offset}")]
+    OffsetMismatch {
+        absolute_patch_addr: usize,
+        target_addr: usize,
+        section_idx: usize,
+        section_offset: usize,
+    },
+}
+
+/// One section of the packed tree read back from the packed bytes.
+pub struct PackedTreeNode<'a> {
+    pub section_idx: usize,
+    /// Offset from the beginning of the packed bytes.
+    pub offset: usize,
+    /// Bytes of this section, the child offsets are already patched.
+    pub bytes: &'a [u8],
+    pub childs: Vec<PackedTreeChild<'a>>,
+}
+
+pub struct PackedTreeChild<'a> {
+    /// Patch address relative to the parent section.
+    pub patch_addr: usize,
+    /// Offset stored in the packed bytes, relative to the patch address.
+    pub patched_offset: u64,
+    pub node: PackedTreeNode<'a>,
+}
+
+impl<'a> PackedTreeNode<'a> {
+    /// Dump the hierarchy into human readable lines, one section per line.
+    pub fn dump(&self) -> String {
+        let mut output = String::new();
+        self.dump_inner(&mut output, 0, None);
+        output
+    }
+
+    fn dump_inner(&self, output: &mut String, depth: usize, patch: Option<(usize, u64)>) {
+        write!(output, "{:indent$}section {} [{}, {}) {} bytes", "", self.section_idx,
+            self.offset, self.offset + self.bytes.len(), self.bytes.len(), indent = depth * 4).unwrap();
+
+        if let Some((patch_addr, patched_offset)) = patch {
+            write!(output, " (patched at +{} with offset {})", patch_addr, patched_offset).unwrap();
+        }
+        output.push('\n');
+
+        for child in &self.childs {
+            child.node.dump_inner(output, depth + 1, Some((child.patch_addr, child.patched_offset)));
+        }
+    }
+}
+
+/// Walk the bytes written by [`crate::TreeByteBuffer::write_packed`] back into the node hierarchy.
+/// Each patched child offset is read from the bytes and checked against the layout.
+pub struct TreeByteBufferReader<'a> {
+    bytes: &'a [u8],
+    layout: &'a PackedTreeLayout,
+}
+
+impl<'a> TreeByteBufferReader<'a> {
+    pub fn new(bytes: &'a [u8], layout: &'a PackedTreeLayout) -> Self {
+        Self {
+            bytes,
+            layout,
+        }
+    }
+
+    /// Read the whole tree from the root section.
+    pub fn read(&self) -> anyhow::Result<PackedTreeNode<'a>, TreeByteBufferReadError> {
+        if self.layout.sections.is_empty() {
+            return Err(TreeByteBufferReadError::NoRootSection);
+        }
+
+        // every section can only be reached once from the root, otherwise the layout is corrupted
+        let mut visited = vec![false; self.layout.sections.len()];
+        self.read_section(0, &mut visited)
+    }
+
+    fn read_section(&self, section_idx: usize, visited: &mut [bool]) -> anyhow::Result<PackedTreeNode<'a>, TreeByteBufferReadError> {
+        let section = self.layout.sections.get(section_idx)
+            .ok_or(TreeByteBufferReadError::InvalidSection { section_idx, section_count: self.layout.sections.len() })?;
+
+        if std::mem::replace(&mut visited[section_idx], true) {
+            return Err(TreeByteBufferReadError::CyclicSection { section_idx });
+        }
+
+        let out_of_bounds = TreeByteBufferReadError::SectionOutOfBounds {
+            section_idx,
+            offset: section.offset,
+            len: section.len,
+            bytes_len: self.bytes.len(),
+        };
+        let bytes = section.offset.checked_add(section.len)
+            .and_then(|end| self.bytes.get(section.offset..end))
+            .ok_or(out_of_bounds)?;
+
+        let childs = section.childs.iter()
+            .map(|&(patch_addr, child_idx)| {
+                let offset_bytes = patch_addr.checked_add(8)
+                    .and_then(|end| bytes.get(patch_addr..end))
+                    .ok_or(TreeByteBufferReadError::PatchOutOfBounds { section_idx, patch_addr })?;
+                let patched_offset = u64::from_ne_bytes(offset_bytes.try_into().unwrap());
+
+                let absolute_patch_addr = section.offset + patch_addr;
+                let target_addr = absolute_patch_addr.wrapping_add(patched_offset as usize);
+
+                let child_offset = self.layout.sections.get(child_idx)
+                    .ok_or(TreeByteBufferReadError::InvalidSection { section_idx: child_idx, section_count: self.layout.sections.len() })?
+                    .offset;
+                if target_addr != child_offset {
+                    return Err(TreeByteBufferReadError::OffsetMismatch {
+                        absolute_patch_addr,
+                        target_addr,
+                        section_idx: child_idx,
+                        section_offset: child_offset,
+                    });
+                }
+
+                Ok(PackedTreeChild {
+                    patch_addr,
+                    patched_offset,
+                    node: self.read_section(child_idx, visited)?,
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(PackedTreeNode {
+            section_idx,
+            offset: section.offset,
+            bytes,
+            childs,
+        })
+    }
+}
@@ -1,8 +1,10 @@
 mod temp_list;
 mod byte_buffer;
+mod byte_buffer_reader;
 mod once_queue;
 pub mod as_bytes;
 
 pub use temp_list::TempList;
-pub use byte_buffer::{TreeByteBuffer, TreeByteBufferNode};
-pub use once_queue::{OnceQueue};
\ No newline at end of file
+pub use byte_buffer::{TreeByteBuffer, TreeByteBufferNode, PackedTreeLayout, PackedSection};
+pub use byte_buffer_reader::{TreeByteBufferReader, TreeByteBufferReadError, PackedTreeNode, PackedTreeChild};
+pub use once_queue::{OnceQueue, OnceQueueError};
\ No newline at end of file
@@ -1,9 +1,14 @@
 use std::collections::{VecDeque};
+use std::sync::mpsc;
+use std::panic::{self, AssertUnwindSafe};
 
 use parking_lot::{Once, OnceState};
 use thiserror::Error;
 
-type OnceJobFunc = Box<dyn FnOnce() -> anyhow::Result<()> + 'static>;
+use raven_core::result::{ResultFlattener, CombinableError};
+use raven_thread::ThreadPool;
+
+type OnceJobFunc = Box<dyn FnOnce() -> anyhow::Result<()> + Send + 'static>;
 
 #[derive(Debug, Error)]
 pub enum OnceQueueError {
@@ -11,6 +16,37 @@ pub enum OnceQueueError {
     ExecutionPoisoned {
         func_name: String,
     },
+    #[error("Once queue execution failed on {} job(s):{}", .failures.len(), format_failures(.failures))]
+    ExecutionFailed {
+        /// Function names and error messages of all the failed jobs.
+        failures: Vec<(String, String)>,
+    },
+}
+
+fn format_failures(failures: &[(String, String)]) -> String {
+    failures.iter()
+        .map(|(func_name, error)| format!("\n    {}: {}", func_name, error))
+        .collect()
+}
+
+impl OnceQueueError {
+    fn into_failures(self) -> Vec<(String, String)> {
+        match self {
+            OnceQueueError::ExecutionPoisoned { func_name } => vec![(func_name, "poisoned".to_owned())],
+            OnceQueueError::ExecutionFailed { failures } => failures,
+        }
+    }
+}
+
+impl CombinableError for OnceQueueError {
+    fn combine(&mut self, other: Self) {
+        let this = std::mem::replace(self, OnceQueueError::ExecutionFailed { failures: Vec::new() });
+
+        let mut failures = this.into_failures();
+        failures.extend(other.into_failures());
+
+        *self = OnceQueueError::ExecutionFailed { failures };
+    }
 }
 
 struct OnceJob {
@@ -35,8 +71,8 @@ impl OnceQueue {
 
     pub fn push_job<F1, F2>(&mut self, init_func: F1, shutdown_func: F2)
     where
-        F1: FnOnce() -> anyhow::Result<()> + 'static,
-        F2: FnOnce() -> anyhow::Result<()> + 'static,
+        F1: FnOnce() -> anyhow::Result<()> + Send + 'static,
+        F2: FnOnce() -> anyhow::Result<()> + Send + 'static,
     {
         let init_func_name = std::any::type_name::<F1>().to_string();
         let shutdown_func_name = std::any::type_name::<F2>().to_string();
@@ -64,6 +100,52 @@ impl OnceQueue {
         Self::execute_impl(queue)
     }
 
+    /// Dispatch all the initialize jobs to the thread pool and wait for them to be done.
+    /// Unlike initialize(), the jobs are not executed in order and all the jobs will be executed even if some of them failed.
+    /// All the failed jobs are reported in the returned error.
+    pub fn initialize_parallel(&mut self, thread_pool: &ThreadPool) -> anyhow::Result<(), OnceQueueError> {
+        let queue = self.init_queue.drain(..);
+        Self::execute_parallel_impl(queue, thread_pool)
+    }
+
+    fn execute_parallel_impl(iter: impl Iterator<Item = OnceJob>, thread_pool: &ThreadPool) -> anyhow::Result<(), OnceQueueError> {
+        let (sender, receiver) = mpsc::channel();
+
+        let handles = iter.enumerate()
+            .map(|(idx, job)| {
+                let sender = sender.clone();
+                let (job_func, name) = (job.job, job.job_name);
+
+                thread_pool.add_job(move || {
+                    // catch the panic here, or the result will never be sent back
+                    let result = match panic::catch_unwind(AssertUnwindSafe(job_func)) {
+                        Ok(Ok(())) => Ok(()),
+                        Ok(Err(err)) => Err(OnceQueueError::ExecutionFailed { failures: vec![(name, err.to_string())] }),
+                        Err(_) => Err(OnceQueueError::ExecutionPoisoned { func_name: name }),
+                    };
+
+                    // the receiver will not be dropped until all the jobs are done
+                    sender.send((idx, result)).unwrap();
+                })
+            })
+            .collect::<Vec<_>>();
+        drop(sender);
+
+        for handle in &handles {
+            handle.wait();
+        }
+
+        // report the failures in the pushing order
+        let mut results = receiver.into_iter().collect::<Vec<_>>();
+        results.sort_by_key(|(idx, _)| *idx);
+
+        results.into_iter()
+            .map(|(_, result)| result)
+            .fold(ResultFlattener::default(), ResultFlattener::fold)
+            .finish()
+            .map(|_| ())
+    }
+
     fn execute_impl(iter: impl Iterator<Item = OnceJob>) -> anyhow::Result<(), OnceQueueError> {
         for job in iter {
             let (job_func, name) = (job.job, job.job_name);
@@ -53,4 +53,35 @@ impl<T> TempList<T> {
             }
         }
     } 
+
+    /// Iterate all the values in the order they are added.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        let mut chunks = Vec::new();
+        let mut chunk = unsafe { &*self.inner.get() };
+
+        loop {
+            chunks.push(chunk);
+
+            match &chunk.next_ptr {
+                Some(next) => chunk = unsafe { &*next.inner.get() },
+                None => break,
+            }
+        }
+
+        // the newest chunk is always at the front
+        chunks.into_iter().rev().flat_map(|chunk| chunk.array.iter())
+    }
+
+    /// Move all the values out in the order they are added, the list is empty after this.
+    pub fn drain(&mut self) -> impl Iterator<Item = T> {
+        let mut chunks = Vec::new();
+        let mut next = Some(std::mem::take(self.inner.get_mut()));
+
+        while let Some(mut chunk) = next {
+            next = chunk.next_ptr.take().map(|list| list.inner.into_inner());
+            chunks.push(chunk.array);
+        }
+
+        chunks.into_iter().rev().flatten()
+    }
 }
\ No newline at end of file
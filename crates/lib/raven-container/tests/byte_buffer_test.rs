@@ -0,0 +1,118 @@
+use raven_container::{TreeByteBuffer, TreeByteBufferNode, TreeByteBufferReader, TreeByteBufferReadError};
+
+// same as the FlatVec header in the packed asset, length then offset.
+fn push_header(bytes: &mut Vec<u8>, len: u64) -> usize {
+    bytes.extend_from_slice(&len.to_ne_bytes());
+    bytes.extend_from_slice(&0u64.to_ne_bytes());
+    bytes.len() - 8
+}
+
+#[test]
+fn test_tree_byte_buffer_round_trip() {
+    let mut root = TreeByteBuffer::new();
+    root.bytes.extend_from_slice(&[1, 2, 3, 4]);
+
+    // child with a grandchild
+    let mut child_a = TreeByteBufferNode::new();
+    child_a.patch_addr = push_header(&mut root.bytes, 2);
+    let mut grandchild = TreeByteBufferNode::new();
+    grandchild.patch_addr = push_header(&mut child_a.buffer.bytes, 3);
+    grandchild.buffer.bytes.extend_from_slice(&[7, 8, 9]);
+    child_a.buffer.bytes.extend_from_slice(&[5, 6]);
+    child_a.buffer.childs.push(grandchild);
+
+    let mut child_b = TreeByteBufferNode::new();
+    child_b.patch_addr = push_header(&mut root.bytes, 1);
+    child_b.buffer.bytes.push(10);
+
+    root.childs.push(child_a);
+    root.childs.push(child_b);
+
+    let mut packed = Vec::new();
+    let layout = root.write_packed(&mut packed);
+
+    let reader = TreeByteBufferReader::new(&packed, &layout);
+    let root_node = reader.read().unwrap();
+
+    assert_eq!(root_node.offset, 0);
+    assert_eq!(&root_node.bytes[..4], &[1, 2, 3, 4]);
+    assert_eq!(root_node.childs.len(), 2);
+
+    let child_a = &root_node.childs[0];
+    assert_eq!(child_a.patch_addr, 4 + 8);
+    assert_eq!(child_a.patch_addr as u64 + child_a.patched_offset, child_a.node.offset as u64);
+    assert_eq!(&child_a.node.bytes[16..], &[5, 6]);
+    assert_eq!(child_a.node.childs.len(), 1);
+
+    let grandchild = &child_a.node.childs[0];
+    assert_eq!(grandchild.node.bytes, &[7, 8, 9]);
+    assert!(grandchild.node.childs.is_empty());
+
+    let child_b = &root_node.childs[1];
+    assert_eq!(child_b.patch_addr, 4 + 16 + 8);
+    assert_eq!(child_b.node.bytes, &[10]);
+
+    // each section is dumped into one line
+    assert_eq!(root_node.dump().lines().count(), 4);
+}
+
+#[test]
+fn test_tree_byte_buffer_reader_detects_corruption() {
+    let mut root = TreeByteBuffer::new();
+
+    let mut child = TreeByteBufferNode::new();
+    child.patch_addr = push_header(&mut root.bytes, 1);
+    child.buffer.bytes.push(1);
+    root.childs.push(child);
+
+    let mut packed = Vec::new();
+    let layout = root.write_packed(&mut packed);
+
+    // break the patched offset
+    packed[8] = packed[8].wrapping_add(1);
+
+    assert!(TreeByteBufferReader::new(&packed, &layout).read().is_err());
+}
+
+#[test]
+fn test_tree_byte_buffer_reader_rejects_corrupted_layout() {
+    let mut root = TreeByteBuffer::new();
+
+    let mut child = TreeByteBufferNode::new();
+    child.patch_addr = push_header(&mut root.bytes, 1);
+    child.buffer.bytes.push(1);
+    root.childs.push(child);
+
+    let mut packed = Vec::new();
+    let layout = root.write_packed(&mut packed);
+    assert!(TreeByteBufferReader::new(&packed, &layout).read().is_ok());
+
+    let (patch_addr, child_idx) = layout.sections[0].childs[0];
+
+    // child index out of the layout
+    let mut invalid_child = layout.clone();
+    invalid_child.sections[0].childs[0].1 = layout.sections.len();
+    assert!(matches!(TreeByteBufferReader::new(&packed, &invalid_child).read(),
+        Err(TreeByteBufferReadError::InvalidSection { section_idx, .. }) if section_idx == layout.sections.len()));
+
+    // section end overflows
+    let mut overflow = layout.clone();
+    overflow.sections[child_idx].len = usize::MAX;
+    assert!(matches!(TreeByteBufferReader::new(&packed, &overflow).read(),
+        Err(TreeByteBufferReadError::SectionOutOfBounds { section_idx, .. }) if section_idx == child_idx));
+
+    // patch address overflows
+    let mut patch_overflow = layout.clone();
+    patch_overflow.sections[0].childs[0].0 = usize::MAX - 4;
+    assert!(matches!(TreeByteBufferReader::new(&packed, &patch_overflow).read(),
+        Err(TreeByteBufferReadError::PatchOutOfBounds { section_idx: 0, .. })));
+
+    // the root points back to itself, the patched offset is fixed up so that only the cycle is left to detect
+    let mut cyclic = layout.clone();
+    cyclic.sections[0].childs[0].1 = 0;
+    let mut cyclic_packed = packed.clone();
+    let back_offset = (layout.sections[0].offset as u64).wrapping_sub(patch_addr as u64);
+    cyclic_packed[patch_addr..patch_addr + 8].copy_from_slice(&back_offset.to_ne_bytes());
+    assert!(matches!(TreeByteBufferReader::new(&cyclic_packed, &cyclic).read(),
+        Err(TreeByteBufferReadError::CyclicSection { section_idx: 0 })));
+}
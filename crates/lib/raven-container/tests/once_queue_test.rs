@@ -0,0 +1,59 @@
+use raven_container::{OnceQueue, OnceQueueError};
+use raven_thread::ThreadPool;
+
+fn init_ok() -> anyhow::Result<()> {
+    Ok(())
+}
+
+fn init_failed_a() -> anyhow::Result<()> {
+    anyhow::bail!("failed a")
+}
+
+fn init_failed_b() -> anyhow::Result<()> {
+    anyhow::bail!("failed b")
+}
+
+#[test]
+fn once_queue_parallel_reports_all_failures() {
+    let mut pool = ThreadPool::new(1);
+    pool.spawn_workers();
+
+    let mut queue = OnceQueue::new();
+    queue.push_job(init_ok, init_ok);
+    queue.push_job(init_failed_a, init_ok);
+    queue.push_job(init_ok, init_ok);
+    queue.push_job(init_failed_b, init_ok);
+
+    let result = queue.initialize_parallel(&pool);
+    assert!(queue.all_initialized());
+
+    match result {
+        Err(OnceQueueError::ExecutionFailed { failures }) => {
+            assert_eq!(failures.len(), 2);
+
+            assert!(failures[0].0.contains("init_failed_a"));
+            assert_eq!(failures[0].1, "failed a");
+            assert!(failures[1].0.contains("init_failed_b"));
+            assert_eq!(failures[1].1, "failed b");
+        }
+        _ => panic!("Expect all the failed jobs to be reported!"),
+    }
+
+    pool.terminate_until_finished();
+}
+
+#[test]
+fn once_queue_parallel_succeeds() {
+    let mut pool = ThreadPool::new(1);
+    pool.spawn_workers();
+
+    let mut queue = OnceQueue::new();
+    for _ in 0..10 {
+        queue.push_job(init_ok, init_ok);
+    }
+
+    assert!(queue.initialize_parallel(&pool).is_ok());
+    assert!(queue.shutdown().is_ok());
+
+    pool.terminate_until_finished();
+}
@@ -13,4 +13,21 @@ pub fn test_temp_list_add() {
         assert_eq!(index, *reference);
         index += 1;
     }
-}
\ No newline at end of file
+}
+
+#[test]
+pub fn test_temp_list_iter_and_drain() {
+    let mut temp_list = TempList::new();
+
+    for i in 0..100 {
+        temp_list.add(i);
+    }
+
+    assert!(temp_list.iter().copied().eq(0..100));
+    assert!(temp_list.drain().eq(0..100));
+    assert_eq!(temp_list.iter().count(), 0);
+
+    // still usable after draining
+    temp_list.add(42);
+    assert!(temp_list.iter().copied().eq(std::iter::once(42)));
+}
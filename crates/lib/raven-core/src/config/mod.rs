@@ -0,0 +1,52 @@
+use serde::Deserialize;
+
+/// Relative paths of the project folders to the root path.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct MountPointsConfig {
+    pub baked: Option<String>,
+    pub assets: Option<String>,
+    pub shader_source: Option<String>,
+}
+
+/// Engine startup configuration, usually read from raven.ron in the project root.
+/// Every field is optional, only the present ones override the engine defaults.
+///
+/// ```ron
+/// (
+///     mount_points: (assets: Some("../../resource/assets/")),
+///     resolution: Some((1280, 720)),
+///     vsync: Some(true),
+///     log_level: Some("info"),
+///     initial_assets: ["texture/skybox/right.jpg"],
+/// )
+/// ```
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct EngineConfig {
+    pub mount_points: MountPointsConfig,
+    /// Logical window resolution.
+    pub resolution: Option<(u32, u32)>,
+    pub vsync: Option<bool>,
+    /// Maximum frames per second of the main loop, unbounded if not specified.
+    pub frame_rate_cap: Option<u32>,
+    /// Present in HDR if the display supports it.
+    pub hdr_output: Option<bool>,
+    /// Number of frames the CPU can record ahead of the GPU (1 to 3).
+    pub frames_in_flight: Option<usize>,
+    /// Warn once this fraction (0 to 1) of the GPU memory is used.
+    pub memory_warning_threshold: Option<f32>,
+    /// Abort with a backtrace on any vulkan validation error.
+    pub break_on_validation_error: Option<bool>,
+    /// Log level (trace, debug, info, warn, error, off).
+    pub log_level: Option<String>,
+    /// Assets (relative to the assets folder) to load before the app initializes.
+    pub initial_assets: Vec<String>,
+}
+
+impl EngineConfig {
+    /// Parse the config from a RON string.
+    pub fn from_ron_str(source: &str) -> anyhow::Result<Self> {
+        Ok(ron::from_str(source)?)
+    }
+}
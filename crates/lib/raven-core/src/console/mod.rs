@@ -1,32 +1,165 @@
 use structopt::StructOpt;
 
+use crate::config::EngineConfig;
+
+const DEFAULT_LOG_LEVEL: &str = "debug";
+const DEFAULT_WINDOW_RESOLUTION: [u32; 2] = [1920, 1080];
+const DEFAULT_FRAMES_IN_FLIGHT: usize = 2;
+
+/// Parse log level filter from string (trace, debug, info, warn, error).
+pub fn parse_level_filter(level: &str) -> Option<log::LevelFilter> {
+    match level.to_lowercase().trim() {
+        "trace" => Some(log::LevelFilter::Trace),
+        "debug" => Some(log::LevelFilter::Debug),
+        "info" => Some(log::LevelFilter::Info),
+        "warn" => Some(log::LevelFilter::Warn),
+        "error" => Some(log::LevelFilter::Error),
+        "off" => Some(log::LevelFilter::Off),
+        _ => None,
+    }
+}
+
 /// Collect console configuration into a struct.
 pub fn from_args() -> ConsoleVars {
+    from_args_with_config(&EngineConfig::default())
+}
+
+/// Collect console configuration into a struct, the values not specified in the console fall back to the config, then the defaults.
+pub fn from_args_with_config(config: &EngineConfig) -> ConsoleVars {
     let console_var = ConsoleVarsImpl::from_args();
 
-    let level = match console_var.level.to_lowercase().trim() {
-        "trace" => log::LevelFilter::Trace,
-        "debug" => log::LevelFilter::Debug,
-        "info" => log::LevelFilter::Info,
-        "warn" => log::LevelFilter::Warn,
-        "error" => log::LevelFilter::Error,
-        _ => panic!("Unknown log level!"),
-    };
+    let level = console_var.level.as_deref()
+        .or(config.log_level.as_deref())
+        .unwrap_or(DEFAULT_LOG_LEVEL);
+    let level = parse_level_filter(level)
+        .expect("Unknown log level!");
+
+    let module_levels = console_var.module_level.iter()
+        .map(|module_level| {
+            let (module, level) = module_level.split_once('=')
+                .unwrap_or_else(|| panic!("Module log level must be in the form of <module>=<level>, but got {}!", module_level));
+            let level = parse_level_filter(level)
+                .unwrap_or_else(|| panic!("Unknown log level of module {}!", module));
+
+            (module.trim().to_owned(), level)
+        })
+        .collect();
+
+    let (config_width, config_height) = config.resolution
+        .unwrap_or((DEFAULT_WINDOW_RESOLUTION[0], DEFAULT_WINDOW_RESOLUTION[1]));
+    let width = console_var.width.unwrap_or(config_width);
+    let height = console_var.height.unwrap_or(config_height);
+
+    if width == 0 || height == 0 {
+        panic!("Window resolution must be positive, but got {}x{}!", width, height);
+    }
 
     ConsoleVars {
-        level: level
+        level: level,
+        module_levels,
+
+        window_resolution: [width, height],
+        vsync: console_var.vsync
+            .or(config.vsync)
+            .unwrap_or(false),
+        frame_rate_cap: console_var.frame_rate_cap
+            .or(config.frame_rate_cap),
+        hdr_output: console_var.hdr
+            .or(config.hdr_output)
+            .unwrap_or(false),
+        fullscreen: console_var.fullscreen,
+        // the gpu is selected either by index or by name
+        gpu_index: console_var.gpu.as_deref().and_then(|gpu| gpu.parse().ok()),
+        gpu_name: console_var.gpu.filter(|gpu| gpu.parse::<usize>().is_err()),
+        dynamic_rendering: console_var.dynamic_rendering,
+        frames_in_flight: console_var.frames_in_flight
+            .or(config.frames_in_flight)
+            .unwrap_or(DEFAULT_FRAMES_IN_FLIGHT),
+        break_on_validation_error: console_var.break_on_validation_error || config.break_on_validation_error.unwrap_or(false),
+        memory_warning_threshold: console_var.memory_warning_threshold
+            .or(config.memory_warning_threshold),
     }
 }
 
 /// Console variables collect from console commands.
 pub struct ConsoleVars {
     pub level: log::LevelFilter,
+    pub module_levels: Vec<(String, log::LevelFilter)>,
+
+    /// Logical window resolution, ignored in fullscreen.
+    pub window_resolution: [u32; 2],
+    pub vsync: bool,
+    /// Maximum frames per second of the main loop, None to run unbounded.
+    pub frame_rate_cap: Option<u32>,
+    /// Present in HDR if the display supports it.
+    pub hdr_output: bool,
+    pub fullscreen: bool,
+    /// Index of the physical device to use, None to pick the most suitable one.
+    pub gpu_index: Option<usize>,
+    /// Substring of the name of the physical device to use, ignored if gpu_index is specified.
+    pub gpu_name: Option<String>,
+    /// Use VK_KHR_dynamic_rendering instead of render pass objects if supported.
+    pub dynamic_rendering: bool,
+    /// Number of frames the CPU can record ahead of the GPU.
+    pub frames_in_flight: usize,
+    /// Abort with a backtrace on any vulkan validation error.
+    pub break_on_validation_error: bool,
+    /// Warn once this fraction of the GPU memory is used, None to never warn.
+    pub memory_warning_threshold: Option<f32>,
 }
 
 #[derive(Debug, StructOpt)]
 #[structopt(name = "raven engine", about = "A small game engine.")]
 struct ConsoleVarsImpl {
-    /// log level (please choose from trace, debug, info, warn, error)
-    #[structopt(short, long, default_value = "debug")]
-    level: String,
-} 
\ No newline at end of file
+    /// log level (please choose from trace, debug, info, warn, error, debug by default)
+    #[structopt(short, long)]
+    level: Option<String>,
+
+    /// per-module log level in the form of <module>=<level> (e.g. raven_rhi=trace), can be specified multiple times
+    #[structopt(long)]
+    module_level: Vec<String>,
+
+    /// window width (logical size, 1920 by default)
+    #[structopt(long)]
+    width: Option<u32>,
+
+    /// window height (logical size, 1080 by default)
+    #[structopt(long)]
+    height: Option<u32>,
+
+    /// enable (true) or disable (false) vertical synchronization, overrides the config (off by default)
+    #[structopt(long)]
+    vsync: Option<bool>,
+
+    /// limit the main loop to this many frames per second (unbounded by default)
+    #[structopt(long)]
+    frame_rate_cap: Option<u32>,
+
+    /// present in HDR (HDR10 or scRGB) if the display supports it (true or false), overrides the config (off by default)
+    #[structopt(long)]
+    hdr: Option<bool>,
+
+    /// use a borderless fullscreen window on the primary monitor
+    #[structopt(long)]
+    fullscreen: bool,
+
+    /// index or name (substring, case insensitive) of the gpu to use, discrete gpus are preferred if not specified
+    #[structopt(long)]
+    gpu: Option<String>,
+
+    /// use vulkan dynamic rendering instead of render passes if the gpu supports it
+    #[structopt(long)]
+    dynamic_rendering: bool,
+
+    /// number of frames the cpu can record ahead of the gpu (1 to 3, 2 by default)
+    #[structopt(long)]
+    frames_in_flight: Option<usize>,
+
+    /// abort with a backtrace on any vulkan validation error (useful for CI)
+    #[structopt(long)]
+    break_on_validation_error: bool,
+
+    /// warn with a GPU memory report once this fraction (0 to 1) of the GPU memory is used
+    #[structopt(long)]
+    memory_warning_threshold: Option<f32>,
+}
\ No newline at end of file
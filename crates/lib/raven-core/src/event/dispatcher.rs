@@ -1,37 +1,60 @@
 use std::collections::{HashMap, hash_map::Entry};
 
-use super::{event::{EventTypeHash, Event, CallbackFn, UntypedEvent, EventFuncPool, EventCallbackFn}};
+use super::{event::{EventTypeHash, Event, CallbackFn, UntypedEvent, QueuedEvent, EventHandle, EventFuncPool, EventCallbackFn}};
 
-pub struct EventDispatcher<'event> {
-    func_pools: HashMap<EventTypeHash, EventFuncPool<'event>>,
+#[derive(Default)]
+pub struct EventDispatcher {
+    func_pools: HashMap<EventTypeHash, EventFuncPool>,
     //receiver_pools: HashMap<ReceiverHash, HashMap<EventTypeHash, EventMethodPool<'event>>>,
+
+    /// Events waiting to be dispatched at dispatch_queued().
+    queued_events: Vec<QueuedEvent>,
+    next_callback_id: u64,
 }
 
-impl<'event> EventDispatcher<'event> {
+impl EventDispatcher {
     pub fn new() -> Self {
-        Self {
-            func_pools: HashMap::new(),
-            //receiver_pools: HashMap::new(),
-        }
+        Self::default()
     }
 
-    pub fn connect_func<E>(&mut self, callback: CallbackFn<'event, E>)
+    pub fn connect_func<E>(&mut self, callback: CallbackFn<E>) -> EventHandle
     where
         E: Event,
-        CallbackFn<'event, E>: Into<EventCallbackFn<'event>>
+        CallbackFn<E>: Into<EventCallbackFn>
     {
         let type_hash = E::type_hash();
+        let id = self.next_callback_id;
+        self.next_callback_id += 1;
 
         match self.func_pools.entry(type_hash) {
             Entry::Vacant(entry) => {
                 let mut new_pool = EventFuncPool::new();
-                new_pool.add_callback(callback.into());
+                new_pool.add_callback(id, callback.into());
                 entry.insert(new_pool);
             }
             Entry::Occupied(mut entry) => {
-                entry.get_mut().add_callback(callback.into());
+                entry.get_mut().add_callback(id, callback.into());
             }
         }
+
+        EventHandle {
+            type_hash,
+            id,
+        }
+    }
+
+    /// Disconnect a previously connected callback.
+    /// Return false if the callback had already been disconnected.
+    pub fn disconnect(&mut self, handle: EventHandle) -> bool {
+        if let Entry::Occupied(mut entry) = self.func_pools.entry(handle.type_hash) {
+            let removed = entry.get_mut().remove_callback(handle.id);
+            if entry.get().is_empty() {
+                entry.remove();
+            }
+            removed
+        } else {
+            false
+        }
     }
 
     // pub fn connect_method<T, E>(&mut self, receiver: &'obj T, callback: CallbackMethod<'event, T, E>)
@@ -55,7 +78,7 @@ impl<'event> EventDispatcher<'event> {
     //     }
     // }
 
-    pub fn trigger<E>(&self, event: &'event E)
+    pub fn trigger<E>(&self, event: &E)
     where
         E: Event
     {
@@ -65,4 +88,28 @@ impl<'event> EventDispatcher<'event> {
             pool.trigger_all(untyped);
         }
     }
-}
\ No newline at end of file
+
+    /// Queue an event to be triggered later in dispatch_queued(), instead of triggering it immediately.
+    pub fn queue_event<E>(&mut self, event: E)
+    where
+        E: Event
+    {
+        self.queued_events.push(QueuedEvent::new(event));
+    }
+
+    /// Trigger all the queued events in the order they were queued.
+    pub fn dispatch_queued(&mut self) {
+        let queued_events = std::mem::take(&mut self.queued_events);
+
+        for event in queued_events.iter() {
+            if let Some(pool) = self.func_pools.get(&event.type_hash) {
+                pool.trigger_all(UntypedEvent::from_queued(event));
+            }
+        }
+    }
+
+    #[inline]
+    pub fn num_queued_events(&self) -> usize {
+        self.queued_events.len()
+    }
+}
@@ -15,31 +15,54 @@ pub trait Event: 'static {
     fn type_name() -> &'static str;
 }
 
-pub type CallbackFn<'event, E> = fn(&'event E);
+pub type CallbackFn<E> = fn(&E);
 // pub type CallbackMethod<'event, 'obj, T, E> = fn(&'obj T, &'event E);
 
 // pub struct CallbackFnBoxed<'event, E: Event>(pub Box<CallbackFn<'event, E>>);
 // pub struct CallbackMethodBoxed<'event, 'obj, T: 'static, E: Event>(pub Box<CallbackMethod<'event, 'obj, T, E>>);
 
-pub struct EventFuncPool<'event>(Vec<EventCallbackFn<'event>>);
+/// Identify a callback connected to the [`EventDispatcher`](super::dispatcher::EventDispatcher).
+/// Use it to disconnect the callback.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct EventHandle {
+    pub(crate) type_hash: EventTypeHash,
+    pub(crate) id: u64,
+}
 
-impl<'event> EventFuncPool<'event> {
+pub struct EventFuncPool(Vec<(u64, EventCallbackFn)>);
+
+impl EventFuncPool {
     pub fn new() -> Self {
         Self(Vec::new())
     }
 
-    pub fn add_callback(&mut self, callback: EventCallbackFn<'event>) {
-        self.0.push(callback);
+    pub fn add_callback(&mut self, id: u64, callback: EventCallbackFn) {
+        self.0.push((id, callback));
     }
 
-    pub fn trigger_all(&self, event: UntypedEvent<'event>)
+    /// Return true if the callback is found and removed.
+    pub fn remove_callback(&mut self, id: u64) -> bool {
+        // keep the connection order
+        if let Some(idx) = self.0.iter().position(|(cb_id, _)| *cb_id == id) {
+            self.0.remove(idx);
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn trigger_all(&self, event: UntypedEvent<'_>)
     {
         let type_name = event.event_type_name;
         
         match type_name {
             "EMessage" => {
                 let concrete = event.untyped.downcast_ref::<EMessage>().unwrap();
-                for callback in self.0.iter() {
+                for (_, callback) in self.0.iter() {
                     if let EventCallbackFn::EMessageCallback(call) = callback {
                         call(concrete);
                     } else {
@@ -49,7 +72,7 @@ impl<'event> EventFuncPool<'event> {
             }
             "EHello" => {
                 let concrete = event.untyped.downcast_ref::<EHello>().unwrap();
-                for callback in self.0.iter() {
+                for (_, callback) in self.0.iter() {
                     if let EventCallbackFn::EHelloCallback(call) = callback {
                         call(concrete);
                     } else {
@@ -74,6 +97,30 @@ impl<'event> UntypedEvent<'event> {
             untyped: event,
         }
     } 
+
+    pub fn from_queued(event: &'event QueuedEvent) -> Self {
+        Self {
+            event_type_name: event.event_type_name,
+            untyped: event.event.as_ref(),
+        }
+    }
+}
+
+/// Owned event waiting in the deferred queue of the dispatcher.
+pub struct QueuedEvent {
+    pub(crate) type_hash: EventTypeHash,
+    event_type_name: &'static str,
+    event: Box<dyn Any>,
+}
+
+impl QueuedEvent {
+    pub fn new<E: Event>(event: E) -> Self {
+        Self {
+            type_hash: E::type_hash(),
+            event_type_name: E::type_name(),
+            event: Box::new(event),
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -92,8 +139,6 @@ impl Event for EMessage {
     }
 }
 
-pub struct EMessageStorage(Vec<EMessage>);
-
 #[derive(Clone)]
 pub struct EHello {
     pub hello_str: String,
@@ -109,26 +154,19 @@ impl Event for EHello {
     }
 }
 
-pub struct EHelloStorage(Vec<EHello>);
-
-pub enum EventStorage {
-    EMessageStorage(EMessageStorage),
-    EHelloStorage(EHelloStorage),
-}
-
-pub enum EventCallbackFn<'event> {
-    EMessageCallback(CallbackFn<'event, EMessage>),
-    EHelloCallback(CallbackFn<'event, EHello>),
+pub enum EventCallbackFn {
+    EMessageCallback(CallbackFn<EMessage>),
+    EHelloCallback(CallbackFn<EHello>),
 }
 
-impl<'event> From<CallbackFn<'event, EMessage>> for EventCallbackFn<'event> {
-    fn from(value: CallbackFn<'event, EMessage>) -> Self {
+impl From<CallbackFn<EMessage>> for EventCallbackFn {
+    fn from(value: CallbackFn<EMessage>) -> Self {
         Self::EMessageCallback(value)
     }
 }
 
-impl<'event> From<CallbackFn<'event, EHello>> for EventCallbackFn<'event> {
-    fn from(value: CallbackFn<'event, EHello>) -> Self {
+impl From<CallbackFn<EHello>> for EventCallbackFn {
+    fn from(value: CallbackFn<EHello>) -> Self {
         Self::EHelloCallback(value)
     }
 }
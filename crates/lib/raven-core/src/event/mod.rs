@@ -2,9 +2,14 @@ mod event;
 mod receiver;
 mod dispatcher;
 
+pub use event::{Event, EventHandle};
+pub use dispatcher::EventDispatcher;
+
 #[cfg(test)]
 mod tests {
-    use super::{event::{EMessage}, dispatcher::EventDispatcher};
+    use std::sync::{Mutex, atomic::{AtomicU32, Ordering}};
+
+    use super::{event::{EMessage, EHello}, dispatcher::EventDispatcher};
 
     fn callback(event: &EMessage) {
         println!("Message Received: ({}, {})", event.msg, event.count);
@@ -29,4 +34,63 @@ mod tests {
         dispatcher.trigger(&e1);
         dispatcher.trigger(&e1_1);
     }
-}
\ No newline at end of file
+
+    static DISCONNECT_COUNTER: AtomicU32 = AtomicU32::new(0);
+
+    fn counting_callback(event: &EMessage) {
+        DISCONNECT_COUNTER.fetch_add(event.count, Ordering::SeqCst);
+    }
+
+    #[test]
+    fn test_disconnect_func() {
+        let mut dispatcher = EventDispatcher::new();
+
+        let handle = dispatcher.connect_func::<EMessage>(counting_callback);
+
+        let e = EMessage {
+            count: 1,
+            msg: String::from("counting"),
+        };
+
+        dispatcher.trigger(&e);
+        assert_eq!(DISCONNECT_COUNTER.load(Ordering::SeqCst), 1);
+
+        assert!(dispatcher.disconnect(handle));
+        dispatcher.trigger(&e);
+        assert_eq!(DISCONNECT_COUNTER.load(Ordering::SeqCst), 1);
+
+        // already disconnected
+        assert!(!dispatcher.disconnect(handle));
+    }
+
+    static QUEUED_ORDER: Mutex<Vec<u32>> = Mutex::new(Vec::new());
+
+    fn queued_message_callback(event: &EMessage) {
+        QUEUED_ORDER.lock().unwrap().push(event.count);
+    }
+
+    fn queued_hello_callback(event: &EHello) {
+        QUEUED_ORDER.lock().unwrap().push(event.hello_str.parse().unwrap());
+    }
+
+    #[test]
+    fn test_queued_events_order() {
+        let mut dispatcher = EventDispatcher::new();
+
+        dispatcher.connect_func::<EMessage>(queued_message_callback);
+        dispatcher.connect_func::<EHello>(queued_hello_callback);
+
+        dispatcher.queue_event(EMessage { count: 0, msg: String::new() });
+        dispatcher.queue_event(EHello { hello_str: String::from("1") });
+        dispatcher.queue_event(EMessage { count: 2, msg: String::new() });
+
+        // nothing is triggered before dispatching
+        assert!(QUEUED_ORDER.lock().unwrap().is_empty());
+        assert_eq!(dispatcher.num_queued_events(), 3);
+
+        dispatcher.dispatch_queued();
+
+        assert_eq!(*QUEUED_ORDER.lock().unwrap(), vec![0, 1, 2]);
+        assert_eq!(dispatcher.num_queued_events(), 0);
+    }
+}
@@ -1,6 +1,8 @@
 extern crate log as glog; // to avoid name collision with my log module
 
 pub mod console;
+pub mod config;
 pub mod result;
 pub mod ptr;
-pub mod event;
\ No newline at end of file
+pub mod event;
+pub mod time;
\ No newline at end of file
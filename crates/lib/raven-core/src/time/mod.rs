@@ -0,0 +1,111 @@
+use std::time::{Duration, Instant};
+
+/// The OS sleep may overshoot by a scheduler quantum (a few milliseconds on some platforms),
+/// so the last part of a precise sleep is spun instead.
+const SPIN_THRESHOLD: Duration = Duration::from_millis(2);
+
+/// Sleep until the deadline without waking up late, only the last SPIN_THRESHOLD is busy-waited.
+pub fn precise_sleep_until(deadline: Instant) {
+    loop {
+        let now = Instant::now();
+        if now >= deadline {
+            return;
+        }
+
+        let remaining = deadline - now;
+        if remaining > SPIN_THRESHOLD {
+            std::thread::sleep(remaining - SPIN_THRESHOLD);
+        } else {
+            std::hint::spin_loop();
+        }
+    }
+}
+
+/// Hold the main loop to a target frame rate.
+///
+/// Frames are scheduled one period after the previous scheduled start instead of after the wait returns,
+/// so a frame waking up late is compensated by the next one and the average frame time stays on target.
+#[derive(Debug, Default)]
+pub struct FrameRateLimiter {
+    cap: Option<u32>,
+    next_frame_start: Option<Instant>,
+}
+
+impl FrameRateLimiter {
+    pub fn new(cap: Option<u32>) -> Self {
+        Self {
+            cap: cap.filter(|cap| *cap > 0),
+            next_frame_start: None,
+        }
+    }
+
+    /// Set the target frame rate, None (or zero) to run unbounded. Return true if the cap changed.
+    pub fn set_cap(&mut self, cap: Option<u32>) -> bool {
+        let cap = cap.filter(|cap| *cap > 0);
+        if self.cap == cap {
+            return false;
+        }
+
+        self.cap = cap;
+        self.next_frame_start = None;
+        true
+    }
+
+    #[inline]
+    pub fn cap(&self) -> Option<u32> {
+        self.cap
+    }
+
+    /// Wait until the next frame should start, returns immediately if there is no cap.
+    pub fn wait(&mut self) {
+        let Some(cap) = self.cap else {
+            return;
+        };
+
+        let period = Duration::from_secs_f64(1.0 / cap as f64);
+        let now = Instant::now();
+
+        let frame_start = match self.next_frame_start {
+            Some(deadline) if deadline > now => {
+                precise_sleep_until(deadline);
+                deadline
+            }
+            // the first frame, or the frame took longer than the period, do not try to catch up
+            _ => now,
+        };
+        self.next_frame_start = Some(frame_start + period);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{precise_sleep_until, FrameRateLimiter};
+
+    #[test]
+    fn test_precise_sleep_until() {
+        let deadline = Instant::now() + Duration::from_millis(5);
+        precise_sleep_until(deadline);
+        assert!(Instant::now() >= deadline);
+
+        // a deadline in the past returns immediately
+        precise_sleep_until(Instant::now() - Duration::from_millis(5));
+    }
+
+    #[test]
+    fn test_frame_rate_limiter() {
+        let mut limiter = FrameRateLimiter::new(Some(200));
+
+        let start = Instant::now();
+        // the first wait only starts the schedule
+        for _ in 0..11 {
+            limiter.wait();
+        }
+        assert!(start.elapsed() >= Duration::from_millis(50));
+
+        assert!(limiter.set_cap(None));
+        assert!(!limiter.set_cap(Some(0)));
+        assert_eq!(limiter.cap(), None);
+    }
+}
@@ -4,23 +4,26 @@ mod user;
 pub mod prelude;
 
 use std::{collections::VecDeque};
+use std::sync::Arc;
 
 use winit::{
-    event::{WindowEvent, Event, ElementState, VirtualKeyCode},
+    event::{WindowEvent, Event, VirtualKeyCode},
     platform::run_return::EventLoopExtRunReturn
 };
 
-use raven_facade::{log, input, render::{LightFrameConstants, FrameConstants}};
-use raven_facade::asset::{self, AssetApi};
+use raven_facade::{log, input, render::{FrameConstants}};
+use raven_facade::asset::{self, AssetApi, AssetLoadDesc, AssetHandle};
 use raven_facade::scene::{persistence::{PersistStates, IsStatesChanged}};
-use raven_facade::input::{InputApi, MouseButton};
+use raven_facade::input::{InputApi, InputBinding, KeyCode, MouseButton};
 
-use raven_facade::core::{self, console, CoreApi};
+use raven_facade::core::{self, console, config::EngineConfig, time::FrameRateLimiter, CoreApi};
 use raven_facade::filesystem::{self, ProjectFolder};
 
-use raven_facade::render::{self, RenderApi};
-#[cfg(feature = "gpu_ray_tracing")]
-use raven_facade::render::RenderMode;
+use raven_facade::render::{self, RenderApi, RenderMode};
+
+/// Input binding key which requests the engine to exit.
+/// Bound to Escape by default, rebind or remove it through the input api.
+pub const EXIT_BINDING: &str = "raven_exit";
 
 static mut ENGINE_CONTEXT: Option<EngineContext> = None;
 
@@ -32,34 +35,73 @@ struct EngineContext {
     render_api: RenderApi,
 
     asset_api: AssetApi,
+    /// Assets listed in the engine config, kept alive until shutdown.
+    initial_assets: Vec<Arc<AssetHandle>>,
 
     app: Box<dyn user::App>,
 }
 
-fn init_filesystem() -> anyhow::Result<()> {
-    filesystem::set_default_root_path()?;
-    
-    filesystem::set_custom_mount_point(ProjectFolder::Baked, "../../resource/baked/")?;
-    filesystem::set_custom_mount_point(ProjectFolder::Assets, "../../resource/assets/")?;
-    filesystem::set_custom_mount_point(ProjectFolder::ShaderSource, "../../resource/shader_src/")?;
+fn init_filesystem(config: &EngineConfig) -> anyhow::Result<()> {
+    let mount_points = &config.mount_points;
+
+    filesystem::set_custom_mount_point(ProjectFolder::Baked, mount_points.baked.as_deref().unwrap_or("../../resource/baked/"))?;
+    filesystem::set_custom_mount_point(ProjectFolder::Assets, mount_points.assets.as_deref().unwrap_or("../../resource/assets/"))?;
+    filesystem::set_custom_mount_point(ProjectFolder::ShaderSource, mount_points.shader_source.as_deref().unwrap_or("../../resource/shader_src/"))?;
 
     Ok(())
 }
 
-fn init_log() -> anyhow::Result<()> {
-    let console_var = console::from_args();
+/// Load the assets listed in the engine config, the ones failed to load are logged and skipped.
+fn load_initial_assets(config: &EngineConfig) -> anyhow::Result<Vec<Arc<AssetHandle>>> {
+    if config.initial_assets.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let asset_api = asset::get().read();
+    for path in &config.initial_assets {
+        let load_desc = match std::path::Path::new(path).extension().and_then(|ext| ext.to_str()) {
+            Some("gltf") | Some("glb") => AssetLoadDesc::load_mesh(path),
+            Some("jpg") | Some("jpeg") | Some("png") => AssetLoadDesc::load_texture(path),
+            _ => anyhow::bail!("Unknown initial asset type: {}", path),
+        };
+        asset_api.load_asset(load_desc)?;
+    }
+
+    let mut handles = Vec::new();
+    for result in asset_api.dispatch_load_tasks() {
+        match result {
+            Ok(handle) => handles.push(handle),
+            Err(err) => glog::error!("Failed to load initial asset: {}", err),
+        }
+    }
+    glog::trace!("Loaded {} initial assets!", handles.len());
+
+    Ok(handles)
+}
 
+fn init_log(console_vars: &console::ConsoleVars) -> anyhow::Result<()> {
     log::init_log(log::LogConfig {
-        level: console_var.level,
+        level: console_vars.level,
+        file_mode: log::FileLogMode::default(),
+        file_format: log::FileLogFormat::default(),
     })?;
 
+    for (module, level) in &console_vars.module_levels {
+        log::set_module_level(module.clone(), *level);
+    }
+
     Ok(())
 }
 
 /// Initialize raven engine.
 pub fn init(app: Box<dyn user::App>) -> anyhow::Result<()> {
-    init_filesystem()?;
-    init_log()?;
+    // the config file is in the root path
+    filesystem::set_default_root_path()?;
+    let config = core::load_engine_config()?;
+    let console_vars = console::from_args_with_config(&config);
+
+    init_filesystem(&config)?;
+    init_log(&console_vars)?;
 
     let core_api = core::CoreApi::new();
     let input_api = input::InputApi::new();
@@ -74,21 +116,27 @@ pub fn init(app: Box<dyn user::App>) -> anyhow::Result<()> {
             render_api,
     
             asset_api,
+            initial_assets: Vec::new(),
     
             app,
         });
     
         if let Some(ctx) = &mut ENGINE_CONTEXT {
-            ctx.core_api.init();
+            ctx.core_api.init(&console_vars);
             core::connect(&mut ctx.core_api);
 
             ctx.asset_api.init();
             asset::connect(&mut ctx.asset_api);
+            ctx.initial_assets = load_initial_assets(&config)?;
 
             ctx.input_api.init();
             input::connect(&mut ctx.input_api);
+            ctx.input_api.write().add_binding(
+                KeyCode::vkcode(VirtualKeyCode::Escape),
+                InputBinding::new(EXIT_BINDING, 1.0)
+            );
 
-            ctx.render_api.init();
+            ctx.render_api.init(&console_vars);
             render::connect(&mut ctx.render_api);
 
             ctx.app.init()?;
@@ -98,6 +146,14 @@ pub fn init(app: Box<dyn user::App>) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Handles of the assets listed in the engine config (in the order they are listed, without the ones failed to load).
+/// They are loaded before App::init() and stay alive until shutdown.
+pub fn initial_assets() -> &'static [Arc<AssetHandle>] {
+    unsafe {
+        ENGINE_CONTEXT.as_ref().map_or(&[], |ctx| ctx.initial_assets.as_slice())
+    }
+}
+
 /// Start engine main loop.
 pub fn main_loop() {
     unsafe {
@@ -107,6 +163,7 @@ pub fn main_loop() {
             render_api,
 
             asset_api: _,
+            initial_assets: _,
 
             app,
         } = ENGINE_CONTEXT.as_mut().unwrap();
@@ -116,18 +173,29 @@ pub fn main_loop() {
         let mut last_frame_time = std::time::Instant::now();
         const FILTER_FRAME_COUNT: usize = 10;
         let mut dt_filter_queue = VecDeque::with_capacity(FILTER_FRAME_COUNT);
+        let mut frame_rate_limiter = FrameRateLimiter::default();
 
-        //let mut frame_index: u32 = 0;
         let mut persist_states = PersistStates::new();
 
         #[cfg(feature = "gpu_ray_tracing")]
         let mut use_reference_mode = false;
+        // raster mode or one of the gbuffer debug views, restored when leaving the reference mode
+        let mut raster_render_mode = RenderMode::Raster;
+
+        let mut exit_binding_active = false;
 
         let mut running = true;
         // main loop start
         while running {
+            // hold the frame rate before measuring the delta time, so the sleep is counted in the frame time
+            if frame_rate_limiter.set_cap(core::get().read().get_frame_rate_cap()) {
+                // the frame times of the old cap would drag the filtered dt for a few frames
+                dt_filter_queue.clear();
+            }
+            frame_rate_limiter.wait();
+
             // filter delta time to get a smooth dt for simulation and rendering
-            let dt = {
+            let (raw_dt, dt) = {
                 let now = std::time::Instant::now();
                 let delta = now - last_frame_time;
                 last_frame_time = now;
@@ -139,12 +207,24 @@ pub fn main_loop() {
                 }
                 dt_filter_queue.push_back(delta_desc);
 
-                dt_filter_queue.iter().copied().sum::<f32>() / (dt_filter_queue.len() as f32)
+                (delta_desc, dt_filter_queue.iter().copied().sum::<f32>() / (dt_filter_queue.len() as f32))
             };
 
+            let frame_index = render_api.read().current_frame_index();
+            let tick_logic = {
+                let mut core_api = core::get().write();
+                core_api.begin_frame(frame_index, raw_dt, dt);
+                core_api.take_logic_tick()
+            };
+            // nothing should animate while the simulation is paused
+            let sim_dt = if tick_logic { dt } else { 0.0 };
+
             // tick logic begin
             let frame_constants = {
                 let old_persist_states = persist_states.clone();
+                let mut close_requested = false;
+                let mut resized_to = None;
+                let mut focus_changed = false;
 
                 // collect system messages
                 {
@@ -160,21 +240,15 @@ pub fn main_loop() {
                                 event,
                                 ..
                             } => match event {
-                                WindowEvent::KeyboardInput { 
-                                    input,
-                                    ..
-                                } => {
-                                    if Some(VirtualKeyCode::Escape) == input.virtual_keycode && input.state == ElementState::Released {
-                                        control_flow.set_exit();
-                                        running = false;
-                                    }
-                                }
                                 WindowEvent::CloseRequested => {
-                                    control_flow.set_exit();
-                                    running = false;
+                                    close_requested = true;
                                 }
                                 WindowEvent::Resized(physical_size) => {
                                     glog::trace!("Window resized (Physical): [{}, {}]", physical_size.width, physical_size.height);
+                                    resized_to = Some(*physical_size);
+                                }
+                                WindowEvent::Focused(_) => {
+                                    focus_changed = true;
                                 }
                                 _ => {}
                             },
@@ -192,15 +266,33 @@ pub fn main_loop() {
                     let mut input_api = input_api.write();
                     let mut render_api = render_api.write();
 
-                    input_api.update(&static_events);
+                    // the rendered image is scaled to the window, so the camera keeps the aspect ratio of the render resolution.
+                    // a minimized window has zero size, skip it.
+                    if resized_to.map_or(false, |size| size.width > 0 && size.height > 0) {
+                        let render_res = render_api.get_render_resolution();
+                        render_api.set_camera_aspect(render_res[0] as f32 / render_res[1] as f32);
+                    }
+
+                    input_api.update(&static_events, raw_dt);
                     let input = input_api.map(dt);
+
+                    // only request on the rising edge, so that holding the key won't keep firing a vetoed close
+                    let exit_active = input.get(EXIT_BINDING).map_or(false, |v| *v > 0.0);
+                    if exit_active && !exit_binding_active {
+                        close_requested = true;
+                    }
+                    exit_binding_active = exit_active;
+
                     let mouse_delta = input_api.mouse_pos_delta() * dt;
         
                     // TODO: update this using event system
-                    render_api.update_camera(
-                        mouse_delta, input_api.is_mouse_button_hold(MouseButton::LEFT), &input
-                    );
-                    let cam_matrices = render_api.get_camera_render_data();
+                    // camera is frozen while paused
+                    if tick_logic {
+                        render_api.update_camera(
+                            mouse_delta, input_api.is_mouse_button_hold(MouseButton::LEFT), &input, dt
+                        );
+                    }
+                    let cam_matrices = render_api.prepare_camera_render_data();
         
                     persist_states.camera.position = render_api.get_camera_position();
                     persist_states.camera.rotation = render_api.get_camera_rotation();
@@ -212,15 +304,40 @@ pub fn main_loop() {
                         if use_reference_mode {
                             render_api.set_render_mode(RenderMode::GpuPathTracing);
                         } else {
-                            render_api.set_render_mode(RenderMode::Raster);
+                            render_api.set_render_mode(raster_render_mode);
+                        }
+                    }
+
+                    if input_api.is_keyboard_just_pressed(VirtualKeyCode::G) {
+                        raster_render_mode = raster_render_mode.next_debug_view();
+                        glog::info!("Render mode: {:?}", raster_render_mode);
+
+                        #[cfg(feature = "gpu_ray_tracing")]
+                        {
+                            use_reference_mode = false;
                         }
+                        render_api.set_render_mode(raster_render_mode);
                     }
 
                     cam_matrices
                 };
 
+                // release the cursor grab while the window is not focused, and restore it on regain
+                if focus_changed {
+                    if let Err(err) = input_api.on_focus_changed() {
+                        glog::warn!("Failed to update the cursor grab: {:?}", err);
+                    }
+                }
+                input_api.recenter_grabbed_cursor();
+
+                if close_requested && app.on_close_requested() {
+                    running = false;
+                }
+
                 // user-side app tick
-                app.tick_logic(dt);
+                if tick_logic {
+                    app.tick_logic(dt);
+                }
 
                 static_events.clear();
 
@@ -229,35 +346,35 @@ pub fn main_loop() {
                     render_api.write().reset_path_tracing_accumulation();
                 }
 
-                let mut light_constants: [LightFrameConstants; 10] = Default::default();
-                light_constants[0] = LightFrameConstants {
-                    color: [1.0, 1.0, 1.0],
-                    shadowed: 1, // true
-                    direction: [-0.32803, 0.90599, 0.26749],
-                    intensity: 1.0
-                };
+                let (light_constants, directional_light_count) = render_api.read().get_light_frame_constants();
+                let sky_rotation = render_api.read().get_sky_rotation();
+                let sky_radiance_scale = render_api.read().get_sky_radiance_scale();
+                let ibl_radiance_scale = render_api.read().get_ibl_radiance_scale();
 
                 FrameConstants {
                     cam_matrices,
                     light_constants,
 
-                    frame_index: render_api.read().current_frame_index(),
+                    frame_index,
                     // TODO: this should be delayed
                     pre_exposure_mult: 1.0,
                     pre_exposure_prev_frame_mult: 1.0,
                     pre_exposure_delta: 1.0,
 
-                    // TODO: add scene, no hardcode here
-                    directional_light_count: 1,
+                    directional_light_count,
                     pad0: 0,
                     pad1: 0,
                     pad2: 0,
+
+                    sky_rotation: sky_rotation.to_array(),
+                    sky_radiance_scale: sky_radiance_scale.extend(1.0).to_array(),
+                    ibl_radiance_scale: ibl_radiance_scale.extend(1.0).to_array(),
                 }
             };
             // tick render end
             
             // tick render begin
-            render_api.write().prepare_frame(dt);
+            render_api.write().prepare_frame_with(sim_dt, |rg, targets| app.on_build_render_graph(rg, targets));
             render_api.write().draw_frame(frame_constants);
             // tick render end
         } // main loop end
@@ -276,6 +393,7 @@ pub fn shutdown() {
                 render_api,
     
                 asset_api,
+                initial_assets,
     
                 mut app,
             } = engine_ctx;
@@ -286,6 +404,7 @@ pub fn shutdown() {
 
             render_api.shutdown();
             input_api.shutdown();
+            drop(initial_assets);
             asset_api.shutdown();
             core_api.shutdown();
         }
@@ -6,7 +6,7 @@ pub use crate::raven_main;
 // core module
 pub mod core {
     pub use crate::core::{
-        CoreApi,
+        CoreApi, FrameTiming,
         get,
     };
 }
@@ -19,6 +19,8 @@ pub mod asset {
         AssetType, AsConcreteRawAsset, AsConcreteAsset,
         get,
     };
+
+    pub use crate::initial_assets;
 }
 
 // input module
@@ -28,6 +30,8 @@ pub mod input {
         KeyCode, MouseButton, VirtualKeyCode,
         get,
     };
+
+    pub use crate::EXIT_BINDING;
 }
 
 // render module
@@ -36,8 +40,17 @@ pub mod render {
         RenderApi, RhiConfig,
         LightFrameConstants, FrameConstants,
         MeshHandle, MeshInstanceHandle, RgHandle,
+        EnvCubemapHandle, EnvCaptureMode,
+        // custom render passes, App::on_build_render_graph() adds them to the render graph builder directly
+        RenderGraphBuilder, FrameRenderTargets, IntoPipelineDescriptorBindings, RenderGraphPassBindable, RenderGraphPassBinding,
+        AccessType, Image, ImageDesc, image_clear,
         get,
     };
+
+    // only the vulkan types needed to describe the images of the custom passes
+    pub mod vk {
+        pub use crate::render::vk::{Format, ImageUsageFlags};
+    }
 }
 
 // math module
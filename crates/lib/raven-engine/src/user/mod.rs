@@ -1,7 +1,22 @@
+use raven_facade::render::{RenderGraphBuilder, FrameRenderTargets};
+
 pub trait App {
     fn init(&mut self) -> anyhow::Result<()>;
     fn tick_logic(&mut self, dt: f32);
     fn shutdown(&mut self);
+
+    /// Called when the user asks to close the window or triggers the exit input binding.
+    /// Return false to veto the close (e.g. to open an in-game menu instead).
+    fn on_close_requested(&mut self) -> bool {
+        true
+    }
+
+    /// Called every frame while the render graph is built, after the engine passes and before the final blit to the swapchain.
+    /// Add custom passes (e.g. post effects or debug overlays) reading or drawing on top of the targets of the main view.
+    ///
+    /// The render api is write-locked while this is called, so calling `render::get()` (e.g. to query the camera)
+    /// in here deadlocks. Read whatever is needed from the render api in tick_logic() and keep it in the app instead.
+    fn on_build_render_graph(&mut self, _rg: &mut RenderGraphBuilder, _targets: &mut FrameRenderTargets) {}
 }
 
 #[macro_export]
@@ -16,4 +31,4 @@ macro_rules! raven_main {
             raven_engine::shutdown();
         }
     };
-}
\ No newline at end of file
+}
@@ -0,0 +1,94 @@
+use std::any::TypeId;
+use std::marker::PhantomData;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use anyhow::Context;
+use serde::de::DeserializeSeed;
+
+use raven_filesystem::{ProjectFolder, WatchHandle, WatchEvent};
+use raven_reflect::{Reflect, TypedReflectDeserializer};
+use raven_reflect::type_registry::TypeRegistry;
+
+/// A reflected config object backed by a ron file, edits to the file are assigned onto the live object.
+///
+/// The watch can not hold the object itself (the file is watched on another thread),
+/// so call apply() on the object every frame, it only does work after the file is changed.
+pub struct ConfigWatch<T: Reflect> {
+    path: PathBuf,
+    registry: TypeRegistry,
+    is_dirty: Arc<AtomicBool>,
+    handle: WatchHandle,
+    _marker: PhantomData<fn() -> T>,
+}
+
+/// Watch a ron config file in the project folder.
+/// The ron file contains the value of T without the type name, e.g. `(walk_speed: 2.0, fov: 60.0)`,
+/// fields missing in the file keep their live values.
+/// T and the types of its fields must be registered in the registry.
+/// The first apply() loads the file, so the initial values can be read in the same way.
+pub fn watch_config<T: Reflect>(path: impl Into<PathBuf>, folder: ProjectFolder, registry: TypeRegistry) -> anyhow::Result<ConfigWatch<T>> {
+    let path = path.into();
+    if registry.registration(TypeId::of::<T>()).is_none() {
+        anyhow::bail!("Config type {} is not registered!", std::any::type_name::<T>());
+    }
+
+    let is_dirty = Arc::new(AtomicBool::new(true));
+    let handle = {
+        let is_dirty = is_dirty.clone();
+        raven_filesystem::watch(path.clone(), folder, move |event| {
+            // editors may replace the file instead of writing to it
+            if matches!(event, WatchEvent::Write(_) | WatchEvent::Create(_)) {
+                is_dirty.store(true, Ordering::Release);
+            }
+        })?
+    };
+
+    Ok(ConfigWatch {
+        path: raven_filesystem::resolve_path(&path, folder)?,
+        registry,
+        is_dirty,
+        handle,
+        _marker: PhantomData,
+    })
+}
+
+impl<T: Reflect> ConfigWatch<T> {
+    /// Assign the values in the file onto the config if the file is changed since the last call.
+    /// Return true if the config is updated. A corrupted file is skipped with a warning, the config keeps its values.
+    pub fn apply(&mut self, config: &mut T) -> bool {
+        if !self.is_dirty.swap(false, Ordering::Acquire) {
+            return false;
+        }
+
+        match self.load() {
+            Ok(reflected) => {
+                config.assign(&*reflected);
+                glog::info!("Config reloaded from {}.", self.path.display());
+                true
+            }
+            Err(err) => {
+                glog::warn!("{:?}", err);
+                false
+            }
+        }
+    }
+
+    fn load(&self) -> anyhow::Result<Box<dyn Reflect>> {
+        let source = std::fs::read_to_string(&self.path)
+            .with_context(|| format!("Failed to read config {}!", self.path.display()))?;
+
+        let registration = self.registry.registration(TypeId::of::<T>()).unwrap();
+        let mut deserializer = ron::de::Deserializer::from_str(&source)?;
+        TypedReflectDeserializer::new(registration, &self.registry)
+            .deserialize(&mut deserializer)
+            .with_context(|| format!("Failed to parse config {}!", self.path.display()))
+    }
+
+    /// Stop watching the file.
+    #[inline]
+    pub fn unwatch(self) -> anyhow::Result<()> {
+        self.handle.unwatch()
+    }
+}
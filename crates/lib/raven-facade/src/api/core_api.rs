@@ -1,4 +1,10 @@
 use raven_core::ptr::SingletonRefPtr;
+use raven_core::config::EngineConfig;
+
+/// Name of the engine config file in the project root.
+pub const ENGINE_CONFIG_FILE_NAME: &str = "raven.ron";
+
+pub use super::config_watch::{ConfigWatch, watch_config};
 
 #[cfg(feature = "default_core_api")]
 pub use super::default_core_api::*;
@@ -16,4 +22,18 @@ pub fn connect(core_api: &mut CoreApi) {
     }
 }
 
+/// Load the engine config from the project root, the default config is returned if the file doesn't exist.
+/// The root path must be set before calling this.
+pub fn load_engine_config() -> anyhow::Result<EngineConfig> {
+    let config_path = raven_filesystem::root_path()?.join(ENGINE_CONFIG_FILE_NAME);
+
+    if !config_path.is_file() {
+        return Ok(EngineConfig::default());
+    }
+
+    let source = std::fs::read_to_string(&config_path)?;
+    EngineConfig::from_ron_str(&source)
+        .map_err(|err| anyhow::anyhow!("Failed to parse engine config {}: {}", config_path.display(), err))
+}
+
 static mut CORE_API: SingletonRefPtr<CoreApi> = SingletonRefPtr::new_empty();
\ No newline at end of file
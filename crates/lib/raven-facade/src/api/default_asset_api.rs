@@ -5,7 +5,7 @@ use parking_lot::RwLock;
 
 use raven_asset::{AssetManager};
 
-pub use raven_asset::{AssetLoadDesc, AssetType, asset_registry::AssetHandle, AsConcreteAsset, AsConcreteRawAsset};
+pub use raven_asset::{AssetLoadDesc, AssetLoadError, AssetType, asset_registry::AssetHandle, AsConcreteAsset, AsConcreteRawAsset};
 
 pub struct AssetApiInner {
     asset_manager: AssetManager,
@@ -25,12 +25,13 @@ impl AssetApiInner {
     }
 
     #[inline]
-    pub fn load_asset(&self, load_desc: AssetLoadDesc) -> anyhow::Result<()> {
+    pub fn load_asset(&self, load_desc: AssetLoadDesc) -> Result<(), AssetLoadError> {
         self.asset_manager.load_asset(load_desc)
     }
 
+    /// The results are in the loading order, a failed asset doesn't stop the others from loading.
     #[inline]
-    pub fn dispatch_load_tasks(&self) -> anyhow::Result<Vec<Arc<AssetHandle>>> {
+    pub fn dispatch_load_tasks(&self) -> Vec<Result<Arc<AssetHandle>, AssetLoadError>> {
         self.asset_manager.dispatch_load_tasks()
     }
 }
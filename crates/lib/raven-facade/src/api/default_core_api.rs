@@ -1,14 +1,38 @@
 use std::sync::{Arc};
 use std::ops::Deref;
+use std::time::Instant;
 
 use parking_lot::RwLock;
-use winit::{dpi::{LogicalSize, LogicalPosition}, window::WindowBuilder};
+use winit::{dpi::{LogicalSize, LogicalPosition}, window::{WindowBuilder, Fullscreen}};
 use winit::{window::Window, event_loop::EventLoop};
 
+use raven_core::console::ConsoleVars;
+
+/// Timing of the current frame, updated by the engine at the beginning of every frame.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FrameTiming {
+    /// Same as the frame index in the frame constants.
+    pub frame_index: u32,
+    /// Unfiltered delta time of the last frame in seconds.
+    pub raw_dt: f32,
+    /// Delta time averaged over the last few frames in seconds, this is the dt passed to the app.
+    pub smoothed_dt: f32,
+    /// Wall-clock time in seconds since the engine started.
+    pub total_elapsed_time: f64,
+}
+
 #[non_exhaustive]
 pub struct CoreApiInner {
     event_loop: EventLoop<()>,
     main_window: Window,
+
+    start_time: Instant,
+    frame_timing: FrameTiming,
+
+    paused: bool,
+    step_requested: bool,
+
+    frame_rate_cap: Option<u32>,
 }
 
 impl std::fmt::Debug for CoreApiInner {
@@ -18,7 +42,7 @@ impl std::fmt::Debug for CoreApiInner {
 }
 
 impl CoreApiInner {
-    pub fn new() -> Self {
+    pub fn new(console_vars: &ConsoleVars) -> Self {
         let event_loop = EventLoop::new();
         let primary_monitor = event_loop.primary_monitor()
             .expect("Must have at least one monitor!");
@@ -30,17 +54,24 @@ impl CoreApiInner {
         let monitor_resolution = primary_monitor.size().to_logical::<f64>(scale_factor);
 
         let window_resolution = LogicalSize::new(
-            1920.0,
-            1080.0
+            console_vars.window_resolution[0] as f64,
+            console_vars.window_resolution[1] as f64,
         );
         let window_position = LogicalPosition::new (
             (monitor_resolution.width - window_resolution.width) / 2.0,
             (monitor_resolution.height - window_resolution.height) / 2.0,
         );  
 
+        let fullscreen = if console_vars.fullscreen {
+            Some(Fullscreen::Borderless(Some(primary_monitor)))
+        } else {
+            None
+        };
+
         let main_window = WindowBuilder::new()
             .with_inner_size(window_resolution)
             .with_position(window_position)
+            .with_fullscreen(fullscreen)
             .with_resizable(false)
             .with_title("Raven Engine")
             .build(&event_loop)
@@ -49,9 +80,88 @@ impl CoreApiInner {
         Self {
             event_loop,
             main_window,
+
+            start_time: Instant::now(),
+            frame_timing: FrameTiming::default(),
+
+            paused: false,
+            step_requested: false,
+
+            frame_rate_cap: console_vars.frame_rate_cap,
         }
     }
 
+    /// Pause or resume the simulation, rendering continues while paused.
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+        self.step_requested = false;
+    }
+
+    #[inline]
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Advance exactly one logic tick while paused. Does nothing if the simulation is running.
+    pub fn step_frame(&mut self) {
+        if self.paused {
+            self.step_requested = true;
+        }
+    }
+
+    /// Limit the main loop to the frames per second, None to run unbounded.
+    /// The main loop sleeps at the beginning of each frame, so the delta time stays close to 1 / cap.
+    /// It can be combined with vsync, then the lower rate wins.
+    pub fn set_frame_rate_cap(&mut self, cap: Option<u32>) {
+        self.frame_rate_cap = cap.filter(|cap| *cap > 0);
+    }
+
+    #[inline]
+    pub fn get_frame_rate_cap(&self) -> Option<u32> {
+        self.frame_rate_cap
+    }
+
+    /// Called by the engine once per frame to decide whether the logic should be ticked in this frame.
+    /// A requested step is consumed.
+    pub fn take_logic_tick(&mut self) -> bool {
+        !self.paused || std::mem::take(&mut self.step_requested)
+    }
+
+    /// Called by the engine at the beginning of every frame.
+    pub fn begin_frame(&mut self, frame_index: u32, raw_dt: f32, smoothed_dt: f32) {
+        self.frame_timing = FrameTiming {
+            frame_index,
+            raw_dt,
+            smoothed_dt,
+            total_elapsed_time: self.start_time.elapsed().as_secs_f64(),
+        };
+    }
+
+    #[inline]
+    pub fn frame_timing(&self) -> FrameTiming {
+        self.frame_timing
+    }
+
+    #[inline]
+    pub fn frame_index(&self) -> u32 {
+        self.frame_timing.frame_index
+    }
+
+    #[inline]
+    pub fn raw_dt(&self) -> f32 {
+        self.frame_timing.raw_dt
+    }
+
+    #[inline]
+    pub fn smoothed_dt(&self) -> f32 {
+        self.frame_timing.smoothed_dt
+    }
+
+    #[inline]
+    pub fn total_elapsed_time(&self) -> f64 {
+        self.frame_timing.total_elapsed_time
+    }
+
     #[inline]
     pub fn main_window(&self) -> &Window {
         &self.main_window
@@ -93,8 +203,8 @@ impl CoreApiImpl {
         Self(None)
     }
 
-    pub fn init(&mut self) {
-        self.0 = Some(Arc::new(RwLock::new(CoreApiInner::new())));
+    pub fn init(&mut self, console_vars: &ConsoleVars) {
+        self.0 = Some(Arc::new(RwLock::new(CoreApiInner::new(console_vars))));
     }
 
     pub fn shutdown(mut self) {
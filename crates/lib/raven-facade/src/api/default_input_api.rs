@@ -1,8 +1,9 @@
 use std::{ops::Deref, sync::{Arc}};
 
 use parking_lot::RwLock;
+use winit::{window::{Window, CursorGrabMode}, dpi::PhysicalPosition};
 
-pub use raven_input::{InputBinding, KeyCode, MouseButton, VirtualKeyCode};
+pub use raven_input::{InputBinding, InputAxis, AxisResponseCurve, KeyCode, KeyModifiers, MouseButton, VirtualKeyCode};
 
 use raven_input::InputManager;
 
@@ -46,4 +47,66 @@ impl InputApiImpl {
             panic!("Try to shutdown render apis before initializing!");
         }
     }
-}
\ No newline at end of file
+}
+
+impl InputApiImpl {
+    /// Hide the cursor and lock it in the main window (e.g. for the first person camera), mouse_pos_delta() keeps reporting the relative motion.
+    /// The grab is released while the window is not focused, and restored when it regains the focus.
+    pub fn set_cursor_grab(&self, grab: bool) -> anyhow::Result<()> {
+        self.write().set_cursor_grab(grab);
+        self.apply_cursor_state()
+    }
+
+    /// Show or hide the cursor when it is not grabbed, a grabbed cursor is always hidden.
+    pub fn set_cursor_visible(&self, visible: bool) -> anyhow::Result<()> {
+        self.write().set_cursor_visible(visible);
+        self.apply_cursor_state()
+    }
+
+    /// Called by the engine after the main window gains or loses the focus.
+    pub fn on_focus_changed(&self) -> anyhow::Result<()> {
+        self.apply_cursor_state()
+    }
+
+    /// Keep the grabbed cursor at the window center, on the platforms which can only confine the cursor in the window.
+    pub fn recenter_grabbed_cursor(&self) {
+        {
+            let input = self.read();
+            if !input.is_cursor_grab() || !input.is_window_focused() {
+                return;
+            }
+        }
+
+        let core_api = crate::core::get().read();
+        let window = core_api.main_window();
+        let _ = window.set_cursor_position(window_center(window));
+    }
+
+    fn apply_cursor_state(&self) -> anyhow::Result<()> {
+        let (grab, visible) = {
+            let input = self.read();
+            let grab = input.is_cursor_grab() && input.is_window_focused();
+            (grab, input.is_cursor_visible() && !grab)
+        };
+
+        let core_api = crate::core::get().read();
+        let window = core_api.main_window();
+
+        if grab {
+            // locking is not supported on some platforms (e.g. windows, x11), confine the cursor then
+            window.set_cursor_grab(CursorGrabMode::Locked)
+                .or_else(|_| window.set_cursor_grab(CursorGrabMode::Confined))?;
+            let _ = window.set_cursor_position(window_center(window));
+        } else {
+            window.set_cursor_grab(CursorGrabMode::None)?;
+        }
+        window.set_cursor_visible(visible);
+
+        Ok(())
+    }
+}
+
+fn window_center(window: &Window) -> PhysicalPosition<u32> {
+    let size = window.inner_size();
+    PhysicalPosition::new(size.width / 2, size.height / 2)
+}
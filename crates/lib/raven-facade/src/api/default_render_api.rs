@@ -9,13 +9,26 @@ pub use raven_rhi::{RhiConfig};
 pub use raven_rg::{RgHandle, LightFrameConstants, FrameConstants};
 pub use raven_render::{*};
 
-use raven_rhi::{Rhi, backend::AccessType};
-use raven_rg::{GraphExecutor, IntoPipelineDescriptorBindings, RenderGraphPassBindable};
-use raven_math::{Vec2, Vec3, Quat, Affine3A};
+// the render graph surface to build custom passes in prepare_frame_with()
+pub use raven_rg::{RenderGraphBuilder, IntoPipelineDescriptorBindings, RenderGraphPassBindable, RenderGraphPassBinding, image_clear};
+pub use raven_rhi::backend::{AccessType, Image, ImageDesc};
+pub use ash::vk;
+
+use raven_container::as_bytes;
+use raven_core::console::ConsoleVars;
+use raven_rhi::{Rhi, backend::{AdapterSelection, SwapchainEncoding}};
+use raven_rg::GraphExecutor;
+use raven_math::{Vec2, Vec3, Quat, Affine3A, AABB, Frustum, Ray};
 use raven_scene::camera::{CameraFrameConstants, Camera, controller::FirstPersonController};
+use raven_scene::persistence::{PersistStates, CameraPersistState, DirectionalLightPersistState};
 
 type PrepareFrameResult = anyhow::Result<()>;
 
+/// Luminance (in nits) of 1.0 in the rendered image when presenting in HDR.
+const DEFAULT_HDR_PAPER_WHITE_NITS: f32 = 200.0;
+/// Vulkan can not query the luminance of the display, so assume a common HDR monitor.
+const DEFAULT_HDR_MAX_NITS: f32 = 1000.0;
+
 #[non_exhaustive]
 pub struct RenderApiInner {
     rhi: Rhi,
@@ -24,6 +37,9 @@ pub struct RenderApiInner {
 
     prepare_frame_result: Option<PrepareFrameResult>,
     frame_index: u32,
+
+    hdr_paper_white_nits: f32,
+    hdr_max_nits: f32,
 }
 
 impl std::fmt::Debug for RenderApiInner {
@@ -33,7 +49,7 @@ impl std::fmt::Debug for RenderApiInner {
 }
 
 impl RenderApiInner {
-    fn new() -> Self {
+    fn new(console_vars: &ConsoleVars) -> Self {
         let core_api = crate::core::get();
         
         let read_guard = core_api.read();
@@ -43,8 +59,18 @@ impl RenderApiInner {
 
         let rhi_config = RhiConfig {
             enable_debug: true,
-            enable_vsync: false,
+            break_on_validation_error: console_vars.break_on_validation_error,
+            enable_vsync: console_vars.vsync,
+            hdr_output: console_vars.hdr_output,
             swapchain_extent: main_window.inner_size().into(),
+            adapter: match (console_vars.gpu_index, &console_vars.gpu_name) {
+                (Some(index), _) => AdapterSelection::Index(index),
+                (None, Some(name)) => AdapterSelection::Name(name.clone()),
+                (None, None) => AdapterSelection::PreferDiscrete,
+            },
+            enable_dynamic_rendering: console_vars.dynamic_rendering,
+            frames_in_flight: console_vars.frames_in_flight,
+            memory_warning_threshold: console_vars.memory_warning_threshold,
         };
 
         let rhi = Rhi::new(rhi_config, main_window)
@@ -60,6 +86,9 @@ impl RenderApiInner {
 
             prepare_frame_result: None,
             frame_index: 0,
+
+            hdr_paper_white_nits: DEFAULT_HDR_PAPER_WHITE_NITS,
+            hdr_max_nits: DEFAULT_HDR_MAX_NITS,
         }
     }
 
@@ -68,6 +97,114 @@ impl RenderApiInner {
         self.renderer.add_cubemap_split(&self.rhi, asset_handles)
     }
 
+    /// Switch between the cubemap sky and the procedural sky. It waits for the device to be idle.
+    #[inline]
+    pub fn set_sky_mode(&mut self, mode: SkyMode) {
+        self.renderer.set_sky_mode(&self.rhi, mode)
+    }
+
+    /// Set the direction pointing to the sun (e.g. for time of day), both the lighting and the procedural sky follow it.
+    #[inline]
+    pub fn set_sun_direction(&mut self, direction: Vec3) {
+        self.renderer.set_sun_direction(direction)
+    }
+
+    #[inline]
+    pub fn get_sun_direction(&self) -> Vec3 {
+        self.renderer.get_sun_direction()
+    }
+
+    /// Rotate the cubemap sky, the image based lighting is regenerated to match it. The default rotation is identity.
+    #[inline]
+    pub fn set_sky_rotation(&mut self, rotation: Quat) {
+        self.renderer.set_sky_rotation(rotation)
+    }
+
+    #[inline]
+    pub fn get_sky_rotation(&self) -> Quat {
+        self.renderer.get_sky_rotation()
+    }
+
+    /// Scale the brightness of the visible sky (1.0 by default), the image based lighting is not affected.
+    #[inline]
+    pub fn set_sky_intensity(&mut self, intensity: f32) {
+        self.renderer.set_sky_intensity(intensity)
+    }
+
+    /// Linear color multiplied to the visible sky (white by default).
+    #[inline]
+    pub fn set_sky_tint(&mut self, tint: Vec3) {
+        self.renderer.set_sky_tint(tint)
+    }
+
+    #[inline]
+    pub fn get_sky_intensity(&self) -> f32 {
+        self.renderer.get_sky_intensity()
+    }
+
+    #[inline]
+    pub fn get_sky_radiance_scale(&self) -> Vec3 {
+        self.renderer.get_sky_radiance_scale()
+    }
+
+    /// Scale the diffuse and specular image based lighting (1.0 by default), the visible sky is not affected.
+    #[inline]
+    pub fn set_ibl_intensity(&mut self, intensity: f32) {
+        self.renderer.set_ibl_intensity(intensity)
+    }
+
+    /// Linear color multiplied to the image based lighting (white by default).
+    #[inline]
+    pub fn set_ibl_tint(&mut self, tint: Vec3) {
+        self.renderer.set_ibl_tint(tint)
+    }
+
+    #[inline]
+    pub fn get_ibl_intensity(&self) -> f32 {
+        self.renderer.get_ibl_intensity()
+    }
+
+    #[inline]
+    pub fn get_ibl_radiance_scale(&self) -> Vec3 {
+        self.renderer.get_ibl_radiance_scale()
+    }
+
+    /// Render the scene into a cubemap from the position and use it as the image based lighting instead of the sky (e.g. a local reflection probe).
+    /// It is captured in the next raster frame, and the ibl switches to it in the frame after.
+    #[inline]
+    pub fn capture_environment_cubemap(&mut self, position: Vec3, resolution: u32, mode: EnvCaptureMode) -> EnvCubemapHandle {
+        self.renderer.capture_environment_cubemap(&self.rhi, position, resolution, mode)
+    }
+
+    /// Capture the environment cubemap again (e.g. after the scene around it changed), return false if it is static or removed.
+    #[inline]
+    pub fn refresh_environment_cubemap(&mut self, handle: EnvCubemapHandle) -> bool {
+        self.renderer.refresh_environment_cubemap(handle)
+    }
+
+    /// Remove the environment cubemap, the ibl falls back to the sky if it is the active one.
+    #[inline]
+    pub fn remove_environment_cubemap(&mut self, handle: EnvCubemapHandle) -> bool {
+        self.renderer.remove_environment_cubemap(&self.rhi, handle)
+    }
+
+    /// Switch the image based lighting to the environment cubemap, or back to the sky with None.
+    #[inline]
+    pub fn set_active_environment_cubemap(&mut self, handle: Option<EnvCubemapHandle>) -> bool {
+        self.renderer.set_active_environment_cubemap(handle)
+    }
+
+    #[inline]
+    pub fn get_active_environment_cubemap(&self) -> Option<EnvCubemapHandle> {
+        self.renderer.get_active_environment_cubemap()
+    }
+
+    /// Light constants of the directional lights and the directional light count for the frame constants.
+    #[inline]
+    pub fn get_light_frame_constants(&self) -> ([LightFrameConstants; 10], u32) {
+        self.renderer.get_light_frame_constants()
+    }
+
     #[inline]
     pub fn add_mesh(&mut self, asset_handle: &Arc<AssetHandle>) -> MeshHandle {
         self.renderer.add_mesh(asset_handle)
@@ -78,24 +215,203 @@ impl RenderApiInner {
         self.renderer.add_mesh_instance(handle, transform)
     }
 
+    /// Remove the mesh and all of its instances, return false if the mesh is already removed.
+    /// Its gpu memory is reused by the meshes added later.
+    #[inline]
+    pub fn remove_mesh(&mut self, handle: MeshHandle) -> bool {
+        self.renderer.remove_mesh(&self.rhi, handle)
+    }
+
+    /// Usage and fragmentation of the gpu buffer containing the vertices and indices of all the meshes.
+    #[inline]
+    pub fn get_draw_data_stats(&self) -> DrawDataStats {
+        self.renderer.get_draw_data_stats()
+    }
+
+    /// Pack the mesh data to remove the holes left by the removed meshes, it waits for the device to be idle.
+    #[inline]
+    pub fn compact_mesh_data(&mut self) {
+        self.renderer.compact_mesh_data()
+    }
+
+    /// Pick the mesh instance under the screen coordinate in the last drawn frame (e.g. selecting objects with the mouse).
+    /// Returns None for background. It waits for the device to be idle.
+    #[inline]
+    pub fn pick(&self, screen_xy: [u32; 2]) -> Option<MeshInstanceHandle> {
+        // map from the window to the internal render resolution
+        let window_res = self.get_window_resolution();
+        let render_res = self.renderer.get_render_resolution();
+
+        if screen_xy[0] >= window_res[0] || screen_xy[1] >= window_res[1] {
+            return None;
+        }
+
+        let pixel = [
+            (screen_xy[0] as u64 * render_res[0] as u64 / window_res[0] as u64) as u32,
+            (screen_xy[1] as u64 * render_res[1] as u64 / window_res[1] as u64) as u32,
+        ];
+        self.renderer.pick(pixel)
+    }
+
+    /// Mesh instances whose bounds are (conservatively) inside the frustum, e.g. from Camera::get_camera_frustum().
+    #[inline]
+    pub fn query_frustum(&mut self, frustum: &Frustum) -> Vec<MeshInstanceHandle> {
+        self.renderer.query_frustum(frustum)
+    }
+
+    /// The mesh instance whose bounds are hit first by the ray, and the hit distance to the bounds.
+    /// Unlike pick(), it doesn't wait for the gpu, but only tests the instance bounds.
+    #[inline]
+    pub fn raycast(&mut self, ray: &Ray) -> Option<(MeshInstanceHandle, f32)> {
+        self.renderer.raycast(ray)
+    }
+
+    /// Probe the scene luminance (without exposure) under the screen coordinate, e.g. to validate the auto exposure under the cursor.
+    /// The luminance of the last probed pixel is returned, it lags behind a few frames because of the gpu latency.
+    /// Return 0.0 without probing if the window is minimized (i.e. zero sized).
+    pub fn debug_luminance_probe(&mut self, screen_xy: [u32; 2]) -> f32 {
+        // map from the window to the internal render resolution
+        let window_res = self.get_window_resolution();
+        let render_res = self.renderer.get_render_resolution();
+        if window_res.contains(&0) || render_res.contains(&0) {
+            return 0.0;
+        }
+
+        let pixel = [
+            (screen_xy[0].min(window_res[0] - 1) as u64 * render_res[0] as u64 / window_res[0] as u64) as u32,
+            (screen_xy[1].min(window_res[1] - 1) as u64 * render_res[1] as u64 / window_res[1] as u64) as u32,
+        ];
+        self.renderer.debug_luminance_probe(pixel)
+    }
+
+    /// Scene luminance (without exposure) of the screen center, only probed after debug_luminance_probe() is called.
+    #[inline]
+    pub fn debug_center_luminance(&self) -> f32 {
+        self.renderer.debug_center_luminance()
+    }
+
+    /// Remove all the meshes and mesh instances, and release their gpu resources (e.g. when switching levels).
+    /// It waits for the device to be idle, then new meshes can be added immediately.
+    #[inline]
+    pub fn clear_scene(&mut self) {
+        self.renderer.clear_scene(&self.rhi)
+    }
+
     #[inline]
     pub fn get_render_resolution(&self) -> [u32; 2] {
         self.renderer.get_render_resolution()
     }
 
+    /// Window (swapchain) resolution which the rendered image is presented to.
+    #[inline]
+    pub fn get_window_resolution(&self) -> [u32; 2] {
+        let extent = self.rhi.swapchain.extent;
+        [extent.width, extent.height]
+    }
+
+    /// How the final image is presented, it is SDR if HDR output is not requested or not supported by the display.
+    #[inline]
+    pub fn get_output_encoding(&self) -> SwapchainEncoding {
+        self.rhi.swapchain.encoding
+    }
+
+    /// Luminance (in nits) of the paper white and the peak of the display, only used when presenting in HDR.
+    /// The display luminance can not be queried through vulkan, so it should come from the user settings.
+    pub fn set_hdr_luminance(&mut self, paper_white_nits: f32, max_nits: f32) {
+        assert!(paper_white_nits > 0.0 && max_nits > 0.0, "Invalid HDR luminance: {} {}", paper_white_nits, max_nits);
+
+        self.hdr_max_nits = max_nits;
+        self.hdr_paper_white_nits = paper_white_nits.min(max_nits);
+    }
+
+    /// Luminance (in nits) of the paper white and the peak of the display.
+    #[inline]
+    pub fn get_hdr_luminance(&self) -> (f32, f32) {
+        (self.hdr_paper_white_nits, self.hdr_max_nits)
+    }
+
+    /// Render internally at a fixed resolution, the rendered image is upsampled (or downsampled if it is larger) to the window when presenting.
+    /// The aspect ratio of the main camera follows this resolution.
+    #[inline]
+    pub fn set_internal_resolution(&mut self, resolution: [u32; 2]) {
+        self.renderer.set_render_resolution(&self.rhi, resolution)
+    }
+
+    /// Render internally at the window resolution multiplied by the scale (e.g. 0.5 for upsampling, 2.0 for supersampling).
+    pub fn set_render_scale(&mut self, scale: f32) {
+        assert!(scale > 0.0, "Invalid render scale: {}", scale);
+
+        let window_res = self.get_window_resolution();
+        let resolution = [
+            ((window_res[0] as f32 * scale).round() as u32).max(1),
+            ((window_res[1] as f32 * scale).round() as u32).max(1),
+        ];
+
+        self.set_internal_resolution(resolution)
+    }
+
     #[inline]
     pub fn set_main_camera(&mut self, camera: Camera, controller: FirstPersonController) {
         self.renderer.set_main_camera(camera, controller)
     }
 
+    /// Render the scene from another camera into the viewport (normalized, origin at the top-left) of the output image,
+    /// e.g. for split-screen, minimaps or picture-in-picture.
+    /// 
+    /// Every camera re-runs the gbuffer, lighting and post process passes in its viewport resolution, 
+    /// so the cost grows with the total viewport area. Meshes, acceleration structures, shadow maps and the sky are shared.
+    #[inline]
+    pub fn add_camera(&mut self, camera: Camera, controller: FirstPersonController, viewport: Viewport) -> CameraId {
+        self.renderer.add_camera(camera, controller, viewport)
+    }
+
+    /// Returns false if the camera does not exist.
+    #[inline]
+    pub fn remove_camera(&mut self, id: CameraId) -> bool {
+        self.renderer.remove_camera(id)
+    }
+
+    /// The camera and the controller of a camera added by add_camera(), the caller drives its controller.
+    #[inline]
+    pub fn get_camera_mut(&mut self, id: CameraId) -> Option<(&mut Camera, &mut FirstPersonController)> {
+        self.renderer.get_camera_mut(id)
+    }
+
     #[inline]
-    pub fn update_camera(&mut self, mouse_delta: Vec2, is_left_mouse_holding: bool, input: &HashMap<&str, f32>) {
-        self.renderer.update_camera(mouse_delta, is_left_mouse_holding, input)
+    pub fn set_camera_viewport(&mut self, id: CameraId, viewport: Viewport) -> bool {
+        self.renderer.set_camera_viewport(id, viewport)
     }
 
+    /// Change the vertical field of view (in degrees) of the main camera (e.g. zooming).
+    /// It is clamped to a sane range.
     #[inline]
-    pub fn get_camera_render_data(&self) -> CameraFrameConstants{
-        self.renderer.get_camera_render_data()
+    pub fn set_camera_fov(&mut self, fov_vertical_degrees: f32) {
+        self.renderer.set_camera_fov(fov_vertical_degrees)
+    }
+
+    /// Change the aspect ratio (width / height) of the main camera.
+    /// The engine resets it to the aspect ratio of the render resolution when the window is resized.
+    #[inline]
+    pub fn set_camera_aspect(&mut self, aspect_ratio: f32) {
+        self.renderer.set_camera_aspect(aspect_ratio)
+    }
+
+    /// Change the near plane distance of the main camera.
+    /// The projection uses infinite reverse-z, a larger near plane gives better depth precision to the distant objects.
+    #[inline]
+    pub fn set_camera_near(&mut self, near_plane: f32) {
+        self.renderer.set_camera_near(near_plane)
+    }
+
+    #[inline]
+    pub fn update_camera(&mut self, mouse_delta: Vec2, is_left_mouse_holding: bool, input: &HashMap<&str, f32>, dt: f32) {
+        self.renderer.update_camera(mouse_delta, is_left_mouse_holding, input, dt)
+    }
+
+    /// Camera matrices of this frame with the TAA jitter, call it once per frame.
+    #[inline]
+    pub fn prepare_camera_render_data(&mut self) -> CameraFrameConstants{
+        self.renderer.prepare_camera_render_data()
     }
 
     #[inline]
@@ -108,26 +424,297 @@ impl RenderApiInner {
         self.renderer.get_camera_rotation()
     }
 
+    /// Move the main camera to the transform immediately, without smoothing.
+    #[inline]
+    pub fn set_camera_transform(&mut self, position: Vec3, rotation: Quat) {
+        self.renderer.set_camera_transform(position, rotation)
+    }
+
+    /// Bounds of all the mesh instances in world space, it is empty when there is no instance.
+    #[inline]
+    pub fn get_scene_aabb(&self) -> AABB {
+        self.renderer.get_scene_aabb()
+    }
+
+    /// Move the main camera to see the whole scene (e.g. the focus command of a model viewer), keeping its rotation.
+    /// Returns false and leaves the camera unchanged if the scene is empty.
+    #[inline]
+    pub fn frame_scene(&mut self) -> bool {
+        self.renderer.frame_scene()
+    }
+
+    /// Draw the depth of the opaque meshes in a position only prepass, then the gbuffer pass only shades the visible pixels.
+    /// It helps the scenes with a lot of overdraw, and is disabled by default since simple scenes pay for an extra geometry pass.
+    #[inline]
+    pub fn set_depth_prepass(&mut self, enabled: bool) {
+        self.renderer.set_depth_prepass(enabled)
+    }
+
+    #[inline]
+    pub fn is_depth_prepass_enabled(&self) -> bool {
+        self.renderer.is_depth_prepass_enabled()
+    }
+
+    /// Set the distances where the coarser mesh LODs start, use MeshLodSettings::disabled() to always draw the full detail.
+    /// The number of the LODs is chosen when loading the mesh (see AssetLoadDesc::lod_count()).
+    #[inline]
+    pub fn set_mesh_lod_settings(&mut self, settings: MeshLodSettings) {
+        self.renderer.set_mesh_lod_settings(settings)
+    }
+
+    #[inline]
+    pub fn get_mesh_lod_settings(&self) -> &MeshLodSettings {
+        self.renderer.get_mesh_lod_settings()
+    }
+
+    /// Set whether the instances of the mesh cast shadow, meshes with only unlit materials do not cast shadow by default.
+    #[inline]
+    pub fn set_mesh_cast_shadow(&mut self, handle: MeshHandle, cast_shadow: bool) {
+        self.renderer.set_mesh_cast_shadow(handle, cast_shadow)
+    }
+
+    /// Transforms of all the mesh instances, in the order they are added.
+    #[inline]
+    pub fn get_mesh_instance_transforms(&self) -> Vec<(MeshInstanceHandle, Affine3A)> {
+        self.renderer.get_mesh_instance_transforms()
+    }
+
+    /// Move a mesh instance, return false if the instance doesn't exist.
+    #[inline]
+    pub fn set_mesh_instance_transform(&mut self, handle: MeshInstanceHandle, transform: Affine3A) -> bool {
+        self.renderer.set_mesh_instance_transform(handle, transform)
+    }
+
+    /// Render a mesh instance with the material factors (base color, metallic, roughness) instead of the ones of its mesh.
+    /// The textures of the mesh are not overridden, they are still multiplied with the factors.
+    /// Return false if the instance doesn't exist.
+    #[inline]
+    pub fn override_instance_material(&mut self, handle: MeshInstanceHandle, material: MaterialOverride) -> bool {
+        self.renderer.override_instance_material(handle, material)
+    }
+
+    /// Restore the mesh materials of a mesh instance, return false if the instance doesn't exist.
+    #[inline]
+    pub fn clear_instance_material_override(&mut self, handle: MeshInstanceHandle) -> bool {
+        self.renderer.clear_instance_material_override(handle)
+    }
+
+    /// Multiply the base color of a mesh instance by the tint, a lighter alternative to the material override
+    /// for the per-instance variations (e.g. team colors, damage flashes). White is no tint.
+    /// Return false if the instance doesn't exist.
+    #[inline]
+    pub fn set_instance_tint(&mut self, handle: MeshInstanceHandle, tint: [f32; 4]) -> bool {
+        self.renderer.set_instance_tint(handle, tint)
+    }
+
+    /// All the directional lights (including the sun), in the order they are added.
+    #[inline]
+    pub fn get_directional_lights(&self) -> Vec<(LightHandle, DirectionalLight)> {
+        self.renderer.get_directional_lights()
+    }
+
+    /// Change the direction and the radiance of a directional light, the intensity is in the current unit of the light.
+    #[inline]
+    pub fn update_directional_light(&mut self, handle: LightHandle, direction: Quat, color: Vec3, intensity: f32) {
+        self.renderer.update_directional_light(handle, direction, color, intensity)
+    }
+
+    /// Change the intensity and its unit of a directional light, e.g. light_units::SUNNY_DAY_LUX with LightUnit::Lux.
+    #[inline]
+    pub fn set_directional_light_intensity(&mut self, handle: LightHandle, intensity: f32, unit: LightUnit) {
+        self.renderer.set_directional_light_intensity(handle, intensity, unit)
+    }
+
+    /// Handle of the sun, the primary directional light.
+    #[inline]
+    pub fn get_sun_light(&self) -> LightHandle {
+        self.renderer.get_sun_light()
+    }
+
+    /// Snapshot the camera, the mesh instance transforms and the directional lights of the scene.
+    pub fn get_persist_states(&self) -> PersistStates {
+        PersistStates {
+            camera: CameraPersistState {
+                position: self.get_camera_position(),
+                rotation: self.get_camera_rotation(),
+            },
+            mesh_instances: self.get_mesh_instance_transforms().into_iter()
+                .map(|(_, transform)| transform)
+                .collect(),
+            directional_lights: self.get_directional_lights().into_iter()
+                .map(|(_, light)| DirectionalLightPersistState {
+                    direction: light.direction,
+                    color: light.color,
+                    intensity: light.intensity,
+                    is_lux: light.unit == LightUnit::Lux,
+                })
+                .collect(),
+        }
+    }
+
+    /// Restore the scene from the snapshot.
+    /// Mesh instances and lights are matched by the order they are added, the ones which don't exist are ignored.
+    pub fn apply_persist_states(&mut self, states: &PersistStates) {
+        self.set_camera_transform(states.camera.position, states.camera.rotation);
+
+        let instances = self.get_mesh_instance_transforms();
+        if instances.len() != states.mesh_instances.len() {
+            glog::warn!("Persist states have {} mesh instances, but the scene has {}!", states.mesh_instances.len(), instances.len());
+        }
+        for ((handle, _), transform) in instances.into_iter().zip(states.mesh_instances.iter()) {
+            self.set_mesh_instance_transform(handle, *transform);
+        }
+
+        let lights = self.get_directional_lights();
+        if lights.len() != states.directional_lights.len() {
+            glog::warn!("Persist states have {} directional lights, but the scene has {}!", states.directional_lights.len(), lights.len());
+        }
+        for ((handle, _), light) in lights.into_iter().zip(states.directional_lights.iter()) {
+            let unit = if light.is_lux { LightUnit::Lux } else { LightUnit::Relative };
+            self.update_directional_light(handle, light.direction, light.color, light.intensity);
+            self.set_directional_light_intensity(handle, light.intensity, unit);
+        }
+    }
+
+    /// Draw a debug line in the current frame, the line will be cleared after this frame.
+    #[inline]
+    pub fn draw_debug_line(&mut self, start: Vec3, end: Vec3, color: Vec3, depth_test: bool) {
+        self.renderer.draw_debug_line(start, end, color, depth_test)
+    }
+
+    /// Draw a debug AABB in the current frame, the AABB will be cleared after this frame.
+    #[inline]
+    pub fn draw_debug_aabb(&mut self, aabb: &AABB, color: Vec3, depth_test: bool) {
+        self.renderer.draw_debug_aabb(aabb, color, depth_test)
+    }
+
+    /// Draw the frustum of a camera in the current frame, the frustum will be cleared after this frame.
+    #[inline]
+    pub fn draw_debug_frustum(&mut self, camera: &Camera, color: Vec3, depth_test: bool) {
+        self.renderer.draw_debug_frustum(camera, color, depth_test)
+    }
+
+    /// Run the deferred lighting at a fraction of the render resolution for performance (e.g. 0.5 for half resolution),
+    /// the gbuffer stays in full resolution and the lighting is upsampled keeping the geometry edges. 1.0 disables the upsampling.
+    #[inline]
+    pub fn set_lighting_resolution_scale(&mut self, scale: f32) {
+        self.renderer.set_lighting_resolution_scale(scale);
+    }
+
+    #[inline]
+    pub fn get_lighting_resolution_scale(&self) -> f32 {
+        self.renderer.get_lighting_resolution_scale()
+    }
+
+    /// Weight of the current frame in TAA, lower values are smoother but ghost more.
+    #[inline]
+    pub fn set_taa_blend_factor(&mut self, blend_factor: f32) {
+        self.renderer.set_taa_blend_factor(blend_factor);
+    }
+
+    #[inline]
+    pub fn get_taa_blend_factor(&self) -> f32 {
+        self.renderer.get_taa_blend_factor()
+    }
+
+    /// Enable or disable a render feature (e.g. shadows, ibl, bloom) for debugging, the passes of disabled features are skipped.
+    #[inline]
+    pub fn set_feature(&mut self, feature: RenderFeature, enabled: bool) {
+        self.renderer.set_feature(feature, enabled)
+    }
+
+    #[inline]
+    pub fn is_feature_enabled(&self, feature: RenderFeature) -> bool {
+        self.renderer.is_feature_enabled(feature)
+    }
+
+    /// Switch between raster, path tracing (only with the gpu_ray_tracing feature) and the gbuffer debug views.
     #[inline]
-    #[cfg(feature = "gpu_ray_tracing")]
     pub fn set_render_mode(&mut self, mode: RenderMode) {
         self.renderer.set_render_mode(mode)
     }
 
+    #[inline]
+    pub fn get_render_mode(&self) -> RenderMode {
+        self.renderer.get_render_mode()
+    }
+
     #[inline]
     #[cfg(feature = "gpu_ray_tracing")]
     pub fn reset_path_tracing_accumulation(&mut self) {
         self.renderer.reset_path_tracing_accumulation()
     }
 
+    /// Set the max number of bounces of each path (clamped to [1, 32]), the accumulation restarts if it changed.
+    #[inline]
+    #[cfg(feature = "gpu_ray_tracing")]
+    pub fn set_path_tracing_bounces(&mut self, bounces: u32) {
+        self.renderer.set_path_tracing_bounces(bounces)
+    }
+
+    /// Set the number of samples traced per pixel in each frame (clamped to [1, 16]), the accumulation restarts if it changed.
+    #[inline]
+    #[cfg(feature = "gpu_ray_tracing")]
+    pub fn set_samples_per_pixel_per_frame(&mut self, samples: u32) {
+        self.renderer.set_samples_per_pixel_per_frame(samples)
+    }
+
+    #[inline]
+    #[cfg(feature = "gpu_ray_tracing")]
+    pub fn get_path_tracing_bounces(&self) -> u32 {
+        self.renderer.get_path_tracing_bounces()
+    }
+
+    #[inline]
+    #[cfg(feature = "gpu_ray_tracing")]
+    pub fn get_samples_per_pixel_per_frame(&self) -> u32 {
+        self.renderer.get_samples_per_pixel_per_frame()
+    }
+
+    /// Number of samples accumulated in each pixel so far, it stops growing at 1000 samples.
+    #[inline]
+    #[cfg(feature = "gpu_ray_tracing")]
+    pub fn get_path_tracing_sample_count(&self) -> u32 {
+        self.renderer.get_path_tracing_sample_count()
+    }
+
+    /// Save the accumulated HDR result to an OpenEXR file (with the sample count in the "sampleCount" attribute).
+    /// The file is written after the next path tracing frame, nothing is saved if no sample is accumulated yet.
+    #[inline]
+    #[cfg(feature = "gpu_ray_tracing")]
+    pub fn save_path_tracing_result(&mut self, path: impl Into<std::path::PathBuf>) {
+        self.renderer.save_path_tracing_result(path)
+    }
+
+    #[inline]
     pub fn prepare_frame(&mut self, dt: f32) {
-        let resolution = self.renderer.get_render_resolution();
+        self.prepare_frame_with(dt, |_, _| {});
+    }
+
+    /// Prepare the frame, and call `build_custom_passes` to add custom passes (e.g. post effects or debug overlays)
+    /// after the engine passes and before the final blit to the swapchain.
+    ///
+    /// The passes can read the final color and the depth of the main view in `FrameRenderTargets`,
+    /// and draw on top of the final color or replace it with a new image in the render resolution.
+    /// Shaders are loaded from the shader source folder like the engine shaders, and the frame constants are bound the same way.
+    pub fn prepare_frame_with(&mut self, dt: f32, build_custom_passes: impl FnOnce(&mut RenderGraphBuilder, &mut FrameRenderTargets)) {
+        let render_res = self.renderer.get_render_resolution();
+        let window_res = self.get_window_resolution();
+        let output_encoding = match self.get_output_encoding() {
+            SwapchainEncoding::Sdr => 0_u32,
+            SwapchainEncoding::Hdr10 => 1,
+            SwapchainEncoding::ScRgb => 2,
+        };
+        let (paper_white_nits, max_nits) = self.get_hdr_luminance();
 
         let prepare_result = self.rg_executor.prepare(|rg| {
-            let main_img = self.renderer.prepare_rg(rg, dt);
+            let mut targets = self.renderer.prepare_rg(rg, dt);
+            build_custom_passes(rg, &mut targets);
+            let main_img = targets.color;
 
-            // copy final image to swapchain
-            let mut swapchain_img = rg.get_swapchain(resolution);
+            // copy final image to swapchain, and rescale it if the render resolution is different from the window.
+            // it is also encoded to PQ (HDR10) or linear scRGB here when presenting in HDR.
+            let mut swapchain_img = rg.get_swapchain(window_res);
             
             let mut pass = rg.add_pass("final blit");
             let pipeline = pass.register_compute_pipeline("image_blit.hlsl");
@@ -143,7 +730,13 @@ impl RenderApiInner {
                     ])
                 )?;
 
-                bound_pipeline.dispatch([resolution[0], resolution[1], 1]);
+                let push_constants = [
+                    render_res[0], render_res[1], window_res[0], window_res[1],
+                    output_encoding, paper_white_nits.to_bits(), max_nits.to_bits(),
+                ];
+                bound_pipeline.push_constants(vk::ShaderStageFlags::COMPUTE, 0, as_bytes::as_byte_slice_val(&push_constants));
+
+                bound_pipeline.dispatch([window_res[0], window_res[1], 1]);
 
                 Ok(())
             });
@@ -213,8 +806,8 @@ impl RenderApiImpl {
         Self(None)
     }
 
-    pub fn init(&mut self) {
-        self.0 = Some(Arc::new(RwLock::new(RenderApiInner::new())));
+    pub fn init(&mut self, console_vars: &ConsoleVars) {
+        self.0 = Some(Arc::new(RwLock::new(RenderApiInner::new(console_vars))));
 
     }
 
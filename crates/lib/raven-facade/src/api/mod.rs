@@ -2,7 +2,9 @@ pub mod core_api;
 pub mod input_api;
 pub mod render_api;
 pub mod asset_api;
+pub mod scene_api;
 
+mod config_watch;
 mod default_core_api;
 mod default_input_api;
 mod default_asset_api;
@@ -0,0 +1,22 @@
+use std::path::Path;
+
+use raven_scene::persistence::PersistStates;
+
+use crate::render;
+
+/// Save the camera, the mesh instance transforms and the directional lights of the scene to a ron file.
+pub fn save(path: impl AsRef<Path>) -> anyhow::Result<()> {
+    let states = render::get().read().get_persist_states();
+    states.save(path)
+}
+
+/// Restore the scene saved by save(), the mesh instances and the lights must be added in the same order as they were saved.
+/// A missing or corrupted file keeps the current scene untouched and returns false.
+pub fn load(path: impl AsRef<Path>) -> bool {
+    if let Some(states) = PersistStates::load(path) {
+        render::get().write().apply_persist_states(&states);
+        true
+    } else {
+        false
+    }
+}
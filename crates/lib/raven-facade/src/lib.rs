@@ -1,7 +1,11 @@
+extern crate log as glog;
+
 mod api;
 
 pub mod core {
     pub use raven_core::console;
+    pub use raven_core::config;
+    pub use raven_core::time;
     pub use crate::api::core_api::*;
 }
 
@@ -39,6 +43,7 @@ pub mod asset {
 
 pub mod scene {
     pub use raven_scene::*;
+    pub use crate::api::scene_api::*;
 }
 
 // pub mod rhi {
@@ -0,0 +1,95 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::Context;
+use parking_lot::Mutex;
+use raven_thread::{ThreadPool, JobHandle};
+
+use super::{ProjectFolder, resolve_path, pak};
+
+type ReadResult = anyhow::Result<Vec<u8>>;
+
+/// Handle to the result of read_async().
+pub struct ReadHandle {
+    /// None if the path failed to resolve, and the error is already in the result.
+    job: Option<JobHandle>,
+    result: Arc<Mutex<Option<ReadResult>>>,
+}
+
+impl ReadHandle {
+    /// If the read is finished (or failed).
+    pub fn is_complete(&self) -> bool {
+        self.job.as_ref().is_none_or(|job| job.is_complete())
+    }
+
+    /// Cancel the read if it is not started yet.
+    pub fn cancel(&self) {
+        if let Some(job) = &self.job {
+            job.cancel();
+        }
+    }
+
+    /// Take the result out if the read is finished, otherwise return None.
+    pub fn try_take(&self) -> Option<ReadResult> {
+        if self.is_complete() {
+            Some(self.take_result())
+        } else {
+            None
+        }
+    }
+
+    /// Wait for the read to finish and take the result.
+    /// It will block current thread until the thread pool finished the read.
+    pub fn wait(self) -> ReadResult {
+        if let Some(job) = &self.job {
+            job.wait();
+        }
+        self.take_result()
+    }
+
+    fn take_result(&self) -> ReadResult {
+        self.result.lock().take()
+            .unwrap_or_else(|| Err(anyhow::anyhow!("The read is cancelled or its result is already taken!")))
+    }
+}
+
+/// Read the whole file in the project folder on the thread pool, the bytes are delivered by the returned handle.
+/// The path is resolved on current thread with the same mount points (and the mounted pak) as the other helpers,
+/// and all the errors are reported by the result.
+pub fn read_async(thread_pool: &ThreadPool, file: impl Into<PathBuf>, folder: ProjectFolder) -> ReadHandle {
+    let result = Arc::new(Mutex::new(None));
+    let file = file.into();
+
+    // files in the mounted pak take precedence over the loose files
+    if let Some(entry) = pak::pak_entry(&file, folder) {
+        let job_result = result.clone();
+        let job = thread_pool.add_job(move || {
+            *job_result.lock() = Some(Ok(entry.to_vec()));
+        });
+
+        return ReadHandle {
+            job: Some(job),
+            result,
+        };
+    }
+
+    let path = match resolve_path(&file, folder) {
+        Ok(path) => path,
+        Err(err) => {
+            *result.lock() = Some(Err(err));
+            return ReadHandle { job: None, result };
+        }
+    };
+
+    let job_result = result.clone();
+    let job = thread_pool.add_job(move || {
+        let bytes = std::fs::read(&path)
+            .with_context(|| format!("Failed to read file: {}", path.display()));
+        *job_result.lock() = Some(bytes);
+    });
+
+    ReadHandle {
+        job: Some(job),
+        result,
+    }
+}
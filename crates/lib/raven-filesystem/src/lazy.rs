@@ -1,12 +1,21 @@
+use std::collections::HashMap;
 use std::path::PathBuf;
 use std::fs::File;
 use std::io::Write;
 
 use anyhow::Context;
 use bytes::Bytes;
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
 use turbosloth::*;
 
-use super::{FILE_HOT_WATCHER, ProjectFolder};
+use super::ProjectFolder;
+use super::watch::{watch_absolute, WatchHandle, WatchEvent};
+
+lazy_static! {
+    /// Only the latest LoadFile of a path needs to be invalidated, the older watches are replaced.
+    static ref LOAD_FILE_WATCHES: Mutex<HashMap<PathBuf, WatchHandle>> = Mutex::new(HashMap::new());
+}
 
 #[derive(Clone, Hash)]
 pub struct LoadFile {
@@ -30,19 +39,20 @@ impl LazyWorker for LoadFile {
     async fn run(self, ctx: RunContext) -> Self::Output {
         let invalidate_trigger = ctx.get_invalidation_trigger();
 
-        FILE_HOT_WATCHER
-            .lock()
-            .watch(self.path.clone(), move |event| {
-                if matches!(event, hotwatch::Event::Write(_)) {
-                    // The period between LoadFile begin to run on another thread and loading from the file,
-                    // the file might be changed by someone accidentally.
-                    // When this happened, we need to invalidate this loading operation, and start a new one.
-                    invalidate_trigger();
-
-                    // will this watch forever?
-                }
-            })
-            .with_context(|| format!("Failed to watch file {:?}!", self.path))?;
+        let handle = watch_absolute(self.path.clone(), move |event| {
+            if matches!(event, WatchEvent::Write(_)) {
+                // The period between LoadFile begin to run on another thread and loading from the file,
+                // the file might be changed by someone accidentally.
+                // When this happened, we need to invalidate this loading operation, and start a new one.
+                invalidate_trigger();
+            }
+        })?;
+
+        // replace the watch of the outdated loading operation
+        let old_handle = LOAD_FILE_WATCHES.lock().insert(self.path.clone(), handle);
+        if let Some(old_handle) = old_handle {
+            old_handle.unwatch()?;
+        }
 
         let mut buffer = Vec::new();
         std::io::Read::read_to_end(&mut File::open(&self.path)?, &mut buffer)
@@ -1,4 +1,4 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::env;
 use std::time::Duration;
 
@@ -8,8 +8,14 @@ use lazy_static::lazy_static;
 use hotwatch::Hotwatch;
 
 mod project;
+mod async_read;
+mod pak;
+mod watch;
 pub mod lazy;
 pub use project::ProjectFolder as ProjectFolder;
+pub use async_read::{read_async, ReadHandle};
+pub use pak::{PakArchive, PakEntry, MappedFile, mount_pak, unmount_pak, map_file};
+pub use watch::{watch, WatchHandle, WatchEvent};
 use project::CUSTUM_MOUNT_POINT;
 
 use self::project::get_project_folder_path_direct;
@@ -99,17 +105,28 @@ pub fn exist_or_create(pf: ProjectFolder) -> anyhow::Result<()> {
     Ok(())
 }
 
-/// Check if a file exists.
+/// Check if a file exists, either in the pak mounted to the folder or as a loose file.
 pub fn exist(file: &PathBuf, folder: ProjectFolder) -> anyhow::Result<bool> {
     assert!(!file.is_dir());
 
-    let mut folder_path = get_project_folder_path_absolute(folder)?;
-    folder_path.extend(file.iter());
+    if pak::pak_entry(file, folder).is_some() {
+        return Ok(true);
+    }
+
+    let folder_path = resolve_path(file, folder)?;
 
     // to avoid symbolic links changed maliciously by someone
     Ok(std::path::Path::try_exists(&folder_path)?)
 }
 
+/// Get the absolute path of a file in ProjectFolder folder.
+pub fn resolve_path(file: &Path, folder: ProjectFolder) -> anyhow::Result<PathBuf> {
+    let mut folder_path = get_project_folder_path_absolute(folder)?;
+    folder_path.extend(file.iter());
+
+    Ok(folder_path)
+}
+
 /// Mount engine root path to p.
 fn mount(p: impl Into<PathBuf>) -> anyhow::Result<()> {
     let p = p.into();
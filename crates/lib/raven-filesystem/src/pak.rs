@@ -0,0 +1,238 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{bail, Context};
+use lazy_static::lazy_static;
+use memmap2::{Mmap, MmapOptions};
+use parking_lot::Mutex;
+
+use super::{ProjectFolder, resolve_path};
+
+lazy_static! {
+    static ref MOUNTED_PAKS: Mutex<HashMap<ProjectFolder, Arc<PakArchive>>> = Mutex::new(HashMap::new());
+}
+
+const PAK_MAGIC: &[u8; 4] = b"RPAK";
+const PAK_VERSION: u32 = 1;
+
+// Pak layout (all integers are little endian):
+// magic: [u8; 4], version: u32, entry_count: u32,
+// entries: [name_len: u32, name: [u8; name_len], offset: u64, size: u64; entry_count],
+// blobs: concatenated file contents, offset is counted from the beginning of the pak.
+
+/// A single file archive containing an index and the concatenated files.
+/// The whole archive is memory mapped, so reading an entry does not copy anything.
+pub struct PakArchive {
+    mmap: Arc<Mmap>,
+    /// Entry name (relative path with '/' separators) to (offset, size).
+    entries: HashMap<String, (usize, usize)>,
+}
+
+impl PakArchive {
+    pub fn open(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let file = File::open(path)
+            .with_context(|| format!("Failed to open pak: {}", path.display()))?;
+        let mmap = unsafe { MmapOptions::new().map(&file) }
+            .with_context(|| format!("Failed to map pak: {}", path.display()))?;
+
+        let entries = Self::read_index(&mmap)
+            .with_context(|| format!("Invalid pak: {}", path.display()))?;
+
+        Ok(Self {
+            mmap: Arc::new(mmap),
+            entries,
+        })
+    }
+
+    fn read_index(bytes: &[u8]) -> anyhow::Result<HashMap<String, (usize, usize)>> {
+        let mut cursor = 0;
+        let mut read = |len: usize| -> anyhow::Result<&[u8]> {
+            let data = bytes.get(cursor..cursor + len).context("Unexpected end of the pak index!")?;
+            cursor += len;
+            Ok(data)
+        };
+
+        if read(4)? != PAK_MAGIC {
+            bail!("Not a pak file!");
+        }
+        let version = u32::from_le_bytes(read(4)?.try_into()?);
+        if version != PAK_VERSION {
+            bail!("Unsupported pak version {}!", version);
+        }
+
+        let entry_count = u32::from_le_bytes(read(4)?.try_into()?);
+        let mut entries = HashMap::with_capacity(entry_count as usize);
+        for _ in 0..entry_count {
+            let name_len = u32::from_le_bytes(read(4)?.try_into()?) as usize;
+            let name = std::str::from_utf8(read(name_len)?)?.to_owned();
+            let offset = u64::from_le_bytes(read(8)?.try_into()?) as usize;
+            let size = u64::from_le_bytes(read(8)?.try_into()?) as usize;
+
+            if offset.checked_add(size).is_none_or(|end| end > bytes.len()) {
+                bail!("Pak entry {} is out of range!", name);
+            }
+            entries.insert(name, (offset, size));
+        }
+
+        Ok(entries)
+    }
+
+    /// Pack all the files under src_dir (recursively) into a pak at pak_path, entries are named by their paths relative to src_dir.
+    pub fn pack_dir(src_dir: impl AsRef<Path>, pak_path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let src_dir = src_dir.as_ref();
+        let pak_path = pak_path.as_ref();
+
+        let mut files = Vec::new();
+        collect_files(src_dir, &mut files)?;
+        // do not pack an old pak into the new one
+        files.retain(|file| file != pak_path);
+        files.sort();
+
+        let names = files.iter()
+            .map(|file| entry_name(file.strip_prefix(src_dir).unwrap()))
+            .collect::<Vec<_>>();
+
+        let index_size = 12 + names.iter().map(|name| 4 + name.len() + 16).sum::<usize>();
+
+        let mut writer = BufWriter::new(File::create(pak_path)?);
+        writer.write_all(PAK_MAGIC)?;
+        writer.write_all(&PAK_VERSION.to_le_bytes())?;
+        writer.write_all(&(files.len() as u32).to_le_bytes())?;
+
+        let mut offset = index_size as u64;
+        for (file, name) in files.iter().zip(names.iter()) {
+            let size = std::fs::metadata(file)?.len();
+
+            writer.write_all(&(name.len() as u32).to_le_bytes())?;
+            writer.write_all(name.as_bytes())?;
+            writer.write_all(&offset.to_le_bytes())?;
+            writer.write_all(&size.to_le_bytes())?;
+            offset += size;
+        }
+
+        for file in &files {
+            let mut reader = File::open(file)?;
+            std::io::copy(&mut reader, &mut writer)?;
+        }
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    /// Get an entry by its path relative to the packed directory.
+    pub fn entry(&self, file: &Path) -> Option<PakEntry> {
+        self.entries.get(&entry_name(file))
+            .map(|&(offset, size)| PakEntry {
+                mmap: self.mmap.clone(),
+                offset,
+                size,
+            })
+    }
+
+    #[inline]
+    pub fn contains(&self, file: &Path) -> bool {
+        self.entries.contains_key(&entry_name(file))
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// Bytes of a file in a pak, it keeps the pak mapped while alive.
+#[derive(Clone)]
+pub struct PakEntry {
+    mmap: Arc<Mmap>,
+    offset: usize,
+    size: usize,
+}
+
+impl Deref for PakEntry {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.mmap[self.offset..self.offset + self.size]
+    }
+}
+
+/// Memory mapped bytes of a file, either from a mounted pak or from a loose file.
+pub enum MappedFile {
+    Loose(Mmap),
+    Pak(PakEntry),
+}
+
+impl Deref for MappedFile {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Self::Loose(mmap) => mmap,
+            Self::Pak(entry) => entry,
+        }
+    }
+}
+
+/// Mount a pak to the project folder, files in the pak take precedence over the loose files in the folder.
+/// Mounting another pak to the same folder replaces the old one.
+pub fn mount_pak(folder: ProjectFolder, pak_path: impl AsRef<Path>) -> anyhow::Result<()> {
+    let pak = PakArchive::open(pak_path)?;
+    MOUNTED_PAKS.lock().insert(folder, Arc::new(pak));
+    Ok(())
+}
+
+/// Unmount the pak of the project folder, entries already mapped stay valid.
+pub fn unmount_pak(folder: ProjectFolder) {
+    MOUNTED_PAKS.lock().remove(&folder);
+}
+
+/// Get the file from the pak mounted to the project folder.
+pub(crate) fn pak_entry(file: &Path, folder: ProjectFolder) -> Option<PakEntry> {
+    MOUNTED_PAKS.lock().get(&folder)
+        .and_then(|pak| pak.entry(file))
+}
+
+/// Memory map a file in the project folder, the mounted pak is searched first, then the loose files.
+pub fn map_file(file: &Path, folder: ProjectFolder) -> anyhow::Result<MappedFile> {
+    if let Some(entry) = pak_entry(file, folder) {
+        return Ok(MappedFile::Pak(entry));
+    }
+
+    let path = resolve_path(file, folder)?;
+    let file = File::open(&path)
+        .with_context(|| format!("Failed to open file: {}", path.display()))?;
+    let mmap = unsafe { MmapOptions::new().map(&file) }
+        .with_context(|| format!("Failed to map file: {}", path.display()))?;
+
+    Ok(MappedFile::Loose(mmap))
+}
+
+/// Pak entries always use '/' as the separator, so that paks are portable across platforms.
+fn entry_name(file: &Path) -> String {
+    file.components()
+        .map(|comp| comp.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> anyhow::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,102 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use anyhow::Context;
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+
+use super::{FILE_HOT_WATCHER, ProjectFolder, resolve_path};
+
+pub use hotwatch::Event as WatchEvent;
+
+type WatchCallback = Arc<Mutex<dyn FnMut(&WatchEvent) + Send>>;
+type WatchCallbacks = Arc<Mutex<Vec<(u64, WatchCallback)>>>;
+
+lazy_static! {
+    /// All the callbacks of a watched path, the path is only registered to the hot watcher once.
+    static ref WATCHED_PATHS: Mutex<HashMap<PathBuf, WatchCallbacks>> = Mutex::new(HashMap::new());
+}
+
+static NEXT_WATCH_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Handle to a registered watch callback.
+/// Dropping the handle does not stop watching, call unwatch() to remove the callback.
+pub struct WatchHandle {
+    path: PathBuf,
+    id: u64,
+}
+
+impl WatchHandle {
+    #[inline]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Remove the callback, the path is unwatched once no callback is left.
+    pub fn unwatch(self) -> anyhow::Result<()> {
+        let mut watched = WATCHED_PATHS.lock();
+
+        let is_empty = if let Some(callbacks) = watched.get(&self.path) {
+            let mut callbacks = callbacks.lock();
+            callbacks.retain(|(id, _)| *id != self.id);
+            callbacks.is_empty()
+        } else {
+            false
+        };
+
+        if is_empty {
+            watched.remove(&self.path);
+            FILE_HOT_WATCHER.lock().unwatch(&self.path)
+                .with_context(|| format!("Failed to unwatch {:?}!", self.path))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Watch a file or a directory (recursively) in the project folder, callback is called on the hot watcher thread when it changed.
+/// Events are debounced by the shared hot watcher (200ms), and multiple watches on the same path share one registration.
+/// The callback must not watch or unwatch by itself, the hot watcher is locked while dispatching the events.
+pub fn watch<F>(path: impl Into<PathBuf>, folder: ProjectFolder, callback: F) -> anyhow::Result<WatchHandle>
+where
+    F: FnMut(&WatchEvent) + Send + 'static,
+{
+    let path = resolve_path(&path.into(), folder)?;
+    watch_absolute(path, callback)
+}
+
+pub(crate) fn watch_absolute<F>(path: PathBuf, callback: F) -> anyhow::Result<WatchHandle>
+where
+    F: FnMut(&WatchEvent) + Send + 'static,
+{
+    // the same file may be referred by different paths
+    let path = path.canonicalize().unwrap_or(path);
+    let id = NEXT_WATCH_ID.fetch_add(1, Ordering::Relaxed);
+
+    let mut watched = WATCHED_PATHS.lock();
+    if let Some(callbacks) = watched.get(&path) {
+        callbacks.lock().push((id, Arc::new(Mutex::new(callback))));
+    } else {
+        let callbacks: WatchCallbacks = Arc::new(Mutex::new(vec![(id, Arc::new(Mutex::new(callback)) as WatchCallback)]));
+
+        let dispatch_callbacks = callbacks.clone();
+        FILE_HOT_WATCHER.lock()
+            .watch(&path, move |event| {
+                // do not hold the lock while calling back, a slow callback should not block the registration
+                let callbacks = dispatch_callbacks.lock().iter()
+                    .map(|(_, callback)| callback.clone())
+                    .collect::<Vec<_>>();
+
+                for callback in callbacks {
+                    (callback.lock())(&event);
+                }
+            })
+            .with_context(|| format!("Failed to watch {:?}!", path))?;
+
+        watched.insert(path.clone(), callbacks);
+    }
+
+    Ok(WatchHandle { path, id })
+}
@@ -0,0 +1,30 @@
+use std::path::Path;
+
+use raven_filesystem::PakArchive;
+
+#[test]
+fn pak_round_trip() {
+    let dir = std::env::temp_dir().join("raven_pak_test");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("sub")).unwrap();
+
+    std::fs::write(dir.join("a.mesh"), b"mesh data").unwrap();
+    std::fs::write(dir.join("sub").join("b.tex"), b"texture data").unwrap();
+    std::fs::write(dir.join("empty.mat"), b"").unwrap();
+
+    let pak_path = dir.join("baked.pak");
+    PakArchive::pack_dir(&dir, &pak_path).unwrap();
+    // packing again must not include the old pak
+    PakArchive::pack_dir(&dir, &pak_path).unwrap();
+
+    let pak = PakArchive::open(&pak_path).unwrap();
+    assert_eq!(pak.len(), 3);
+
+    assert_eq!(&*pak.entry(Path::new("a.mesh")).unwrap(), b"mesh data");
+    assert_eq!(&*pak.entry(&Path::new("sub").join("b.tex")).unwrap(), b"texture data");
+    assert!(pak.entry(Path::new("empty.mat")).unwrap().is_empty());
+    assert!(pak.entry(Path::new("missing.tex")).is_none());
+    assert!(!pak.contains(Path::new("baked.pak")));
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
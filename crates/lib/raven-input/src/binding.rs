@@ -1,17 +1,52 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+
+use once_cell::sync::Lazy;
+use serde::{Serialize, Deserialize};
 
 use crate::manager::KeyCode;
 
-use super::{mouse::{MouseInputState}, keyboard::KeyboardInputState};
+use super::{mouse::{MouseInputState}, keyboard::{KeyboardInputState, KeyModifiers}};
 
 pub type InputBindingKey = &'static str;
-pub type InputMap = HashMap<InputBindingKey, f32>;
+
+/// Mapped values of the actions (bound by [`InputBinding`]) and the axes (bound by [`InputAxis`]) in this frame.
+/// It derefs to the action values.
+#[derive(Default, Debug, Clone)]
+pub struct InputMap {
+    actions: HashMap<InputBindingKey, f32>,
+    axes: HashMap<InputBindingKey, f32>,
+}
+
+impl InputMap {
+    /// Value of the action, zero if it is not bound.
+    #[inline]
+    pub fn action(&self, key: &str) -> f32 {
+        self.actions.get(key).copied().unwrap_or(0.0)
+    }
+
+    /// Value of the axis in [-1.0, 1.0], zero if it is not bound.
+    #[inline]
+    pub fn axis(&self, key: &str) -> f32 {
+        self.axes.get(key).copied().unwrap_or(0.0)
+    }
+}
+
+impl std::ops::Deref for InputMap {
+    type Target = HashMap<InputBindingKey, f32>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.actions
+    }
+}
 
 pub struct InputBinding {
     key: InputBindingKey,
     /// Range from -1.0 to 1.0
     multiplier: f32,
     activation_time: f32,
+    /// Modifier keys which must be held with the key (i.e. a key chord like Ctrl+S).
+    modifiers: KeyModifiers,
 
     curr_activation_time: f32,
 }
@@ -23,6 +58,7 @@ impl InputBinding {
             key,
             multiplier,
             activation_time: 0.0,
+            modifiers: KeyModifiers::NONE,
             curr_activation_time: 0.0,
         }
     }
@@ -31,69 +67,161 @@ impl InputBinding {
         self.activation_time = activation_time;
         self
     }
+
+    /// Require the modifier keys to be held with the key, e.g. `KeyModifiers::CTRL` for Ctrl+S.
+    pub fn modifiers(mut self, modifiers: KeyModifiers) -> Self {
+        self.modifiers = modifiers;
+        self
+    }
+
+    /// Create an axis which aggregates the negative and the positive sources into one value.
+    pub fn axis(key: impl Into<InputBindingKey>) -> InputAxis {
+        InputAxis::new(key)
+    }
+}
+
+/// How the axis value (after the deadzone) is mapped to the output.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AxisResponseCurve {
+    #[default]
+    Linear,
+    /// Finer control around zero.
+    Quadratic,
+}
+
+/// A value in [-1.0, 1.0] driven by the negative and the positive sources, e.g. S/W for walking.
+pub struct InputAxis {
+    key: InputBindingKey,
+    negative: Vec<KeyCode>,
+    positive: Vec<KeyCode>,
+    /// Symmetric deadzone in [0.0, 1.0), values inside are zero and the rest is rescaled to the full range.
+    deadzone: f32,
+    curve: AxisResponseCurve,
+    /// Time to ramp the raw value from zero to one, zero to follow the sources immediately.
+    activation_time: f32,
+
+    curr_raw_value: f32,
+}
+
+impl InputAxis {
+    pub fn new(key: impl Into<InputBindingKey>) -> Self {
+        Self {
+            key: key.into(),
+            negative: Vec::new(),
+            positive: Vec::new(),
+            deadzone: 0.0,
+            curve: AxisResponseCurve::Linear,
+            activation_time: 0.0,
+            curr_raw_value: 0.0,
+        }
+    }
+
+    pub fn negative(mut self, keycode: KeyCode) -> Self {
+        self.negative.push(keycode);
+        self
+    }
+
+    pub fn positive(mut self, keycode: KeyCode) -> Self {
+        self.positive.push(keycode);
+        self
+    }
+
+    pub fn deadzone(mut self, deadzone: f32) -> Self {
+        self.deadzone = deadzone.clamp(0.0, 0.99);
+        self
+    }
+
+    pub fn curve(mut self, curve: AxisResponseCurve) -> Self {
+        self.curve = curve;
+        self
+    }
+
+    pub fn activation_time(mut self, activation_time: f32) -> Self {
+        self.activation_time = activation_time;
+        self
+    }
+
+    /// Apply the deadzone and the response curve to the raw value.
+    fn shape(&self, raw_value: f32) -> f32 {
+        let magnitude = raw_value.abs();
+        if magnitude <= self.deadzone {
+            return 0.0;
+        }
+
+        let magnitude = ((magnitude - self.deadzone) / (1.0 - self.deadzone)).min(1.0);
+        let magnitude = match self.curve {
+            AxisResponseCurve::Linear => magnitude,
+            AxisResponseCurve::Quadratic => magnitude * magnitude,
+        };
+        magnitude.copysign(raw_value)
+    }
 }
 
 pub struct InputBindingMap {
     bindings: Vec<(KeyCode, InputBinding)>,
+    axes: Vec<InputAxis>,
 }
 
 impl InputBindingMap {
     pub fn new() -> Self {
         Self {
             bindings: Default::default(),
+            axes: Default::default(),
         }
     }
 
+    /// Add an axis, an axis with the same key is replaced.
+    pub fn bind_axis(&mut self, axis: InputAxis) {
+        self.axes.retain(|old| old.key != axis.key);
+        self.axes.push(axis);
+    }
+
+    pub fn unbind_axis(&mut self, key: impl Into<InputBindingKey>) {
+        let key = key.into();
+        self.axes.retain(|axis| axis.key != key);
+    }
+
     pub fn bind(&mut self, keycode: KeyCode, binding: InputBinding) {
         self.bindings.push((keycode, binding));
     }
 
-    #[allow(dead_code)]
     pub fn unbind(&mut self, keycode: KeyCode) {
         // TODO: when we have multiple keycode in one bindings, we just remove one arbitrary element.
-        let mut idx = 0;
-        for (binding_idx, (kcode, _)) in self.bindings.iter().enumerate() {
-            if keycode == *kcode {
-                idx = binding_idx;
-                break;
-            }
+        if let Some(idx) = self.bindings.iter().position(|(kcode, _)| keycode == *kcode) {
+            self.bindings.swap_remove(idx);
         }
-
-        self.bindings.swap_remove(idx);
     }
 
-    #[allow(dead_code)]
     pub fn unbind_all(&mut self, key: impl Into<InputBindingKey>) {
         let key = key.into();
         self.bindings.retain(|(_, binding)| binding.key != key);
     }
 
     pub fn map_with_input(&mut self, vkinput: &KeyboardInputState, mouse_input: &MouseInputState, dt: f32) -> InputMap {
-        let mut result: InputMap = HashMap::new();
+        let mut result = InputMap::default();
+        let modifiers = vkinput.modifiers();
+
+        // the longest matched chord of each key wins, e.g. S is not triggered when Ctrl+S is bound and matched
+        let mut longest_chords: HashMap<KeyCode, u32> = HashMap::new();
+        for (keycode, binding) in self.bindings.iter() {
+            if modifiers.contains(binding.modifiers) {
+                let longest = longest_chords.entry(*keycode).or_default();
+                *longest = (*longest).max(binding.modifiers.count());
+            }
+        }
 
         for (ref keycode, binding) in self.bindings.iter_mut() {
+            let key_pressed = is_key_pressed(keycode, vkinput, mouse_input);
+            let chord_matched = modifiers.contains(binding.modifiers)
+                && longest_chords.get(keycode) == Some(&binding.modifiers.count());
+            let activated = key_pressed && chord_matched;
+
             let curr_activation_time = if binding.activation_time > 1e-10 {
-                let dt = match keycode {
-                    KeyCode::VirtualKeyCode(vk) => {
-                        if vkinput.is_keyboard_pressed(*vk) { dt } else { -dt }
-                    }
-                    KeyCode::Mouse(mouse) => {
-                        if mouse_input.is_button_hold(*mouse) { dt } else { -dt }
-                    }
-                };
+                let dt = if activated { dt } else { -dt };
 
                 binding.curr_activation_time = (binding.curr_activation_time + dt).clamp(0.0, binding.activation_time);
                 binding.curr_activation_time / binding.activation_time
             } else { // no activation time
-                let activated = match keycode {
-                    KeyCode::VirtualKeyCode(vk) => {
-                        if vkinput.is_keyboard_pressed(*vk) { true } else { false }
-                    }
-                    KeyCode::Mouse(mouse) => {
-                        if mouse_input.is_button_hold(*mouse) { true } else { false }
-                    }
-                };
-
                 if activated {
                     binding.curr_activation_time = 1.0;
                     1.0
@@ -103,11 +231,160 @@ impl InputBindingMap {
                 }
             };
 
-            let value = result.entry(binding.key).or_default();
+            let value = result.actions.entry(binding.key).or_default();
             *value += curr_activation_time.powi(2) * binding.multiplier;
             *value = value.clamp(-1.0, 1.0);
         }
 
+        for axis in self.axes.iter_mut() {
+            let is_any_pressed = |keycodes: &[KeyCode]| keycodes.iter()
+                .any(|keycode| is_key_pressed(keycode, vkinput, mouse_input));
+            let target = (is_any_pressed(&axis.positive) as i32 - is_any_pressed(&axis.negative) as i32) as f32;
+
+            axis.curr_raw_value = if axis.activation_time > 1e-10 {
+                let max_step = dt / axis.activation_time;
+                axis.curr_raw_value + (target - axis.curr_raw_value).clamp(-max_step, max_step)
+            } else {
+                target
+            };
+
+            result.axes.insert(axis.key, axis.shape(axis.curr_raw_value));
+        }
+
         result
     }
+}
+
+/// Serialized [`InputBinding`], the keys and the modifiers are stored by their names (see [`KeyCode::name`]).
+#[derive(Serialize, Deserialize)]
+struct InputBindingDesc {
+    key: String,
+    action: String,
+    multiplier: f32,
+    #[serde(default)]
+    activation_time: f32,
+    #[serde(default)]
+    modifiers: Vec<String>,
+}
+
+/// Serialized [`InputAxis`].
+#[derive(Serialize, Deserialize)]
+struct InputAxisDesc {
+    action: String,
+    #[serde(default)]
+    negative: Vec<String>,
+    #[serde(default)]
+    positive: Vec<String>,
+    #[serde(default)]
+    deadzone: f32,
+    #[serde(default)]
+    curve: AxisResponseCurve,
+    #[serde(default)]
+    activation_time: f32,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+struct InputBindingMapDesc {
+    bindings: Vec<InputBindingDesc>,
+    axes: Vec<InputAxisDesc>,
+}
+
+impl InputBindingMap {
+    /// Serialize the bindings and the axes into a ron string, the runtime states (e.g. the activation progress) are not saved.
+    pub fn to_ron(&self) -> anyhow::Result<String> {
+        let desc = InputBindingMapDesc {
+            bindings: self.bindings.iter()
+                .map(|(keycode, binding)| InputBindingDesc {
+                    key: keycode.name(),
+                    action: binding.key.to_owned(),
+                    multiplier: binding.multiplier,
+                    activation_time: binding.activation_time,
+                    modifiers: binding.modifiers.names().into_iter().map(str::to_owned).collect(),
+                })
+                .collect(),
+            axes: self.axes.iter()
+                .map(|axis| InputAxisDesc {
+                    action: axis.key.to_owned(),
+                    negative: axis.negative.iter().map(KeyCode::name).collect(),
+                    positive: axis.positive.iter().map(KeyCode::name).collect(),
+                    deadzone: axis.deadzone,
+                    curve: axis.curve,
+                    activation_time: axis.activation_time,
+                })
+                .collect(),
+        };
+
+        Ok(ron::ser::to_string_pretty(&desc, ron::ser::PrettyConfig::default())?)
+    }
+
+    /// Deserialize the bindings and the axes from a ron string serialized by to_ron().
+    /// A binding with an unknown key or modifier name is skipped, so is an unknown key of an axis, with a warning.
+    pub fn from_ron(source: &str) -> anyhow::Result<Self> {
+        let desc: InputBindingMapDesc = ron::from_str(source)?;
+        let mut map = Self::new();
+
+        for binding in desc.bindings {
+            let Some(keycode) = KeyCode::from_name(&binding.key) else {
+                glog::warn!("Unknown key {} bound to {}, skip the binding.", binding.key, binding.action);
+                continue;
+            };
+
+            let modifiers = binding.modifiers.iter()
+                .map(|name| KeyModifiers::from_name(name).ok_or(name))
+                .collect::<Result<Vec<_>, _>>();
+            let modifiers = match modifiers {
+                Ok(modifiers) => modifiers.into_iter().fold(KeyModifiers::NONE, |acc, modifier| acc | modifier),
+                Err(name) => {
+                    glog::warn!("Unknown modifier key {} bound to {}, skip the binding.", name, binding.action);
+                    continue;
+                }
+            };
+
+            map.bind(keycode, InputBinding::new(intern_binding_key(&binding.action), binding.multiplier)
+                .activation_time(binding.activation_time)
+                .modifiers(modifiers));
+        }
+
+        for axis_desc in desc.axes {
+            let mut axis = InputAxis::new(intern_binding_key(&axis_desc.action))
+                .deadzone(axis_desc.deadzone)
+                .curve(axis_desc.curve)
+                .activation_time(axis_desc.activation_time);
+
+            for (names, keycodes) in [(&axis_desc.negative, &mut axis.negative), (&axis_desc.positive, &mut axis.positive)] {
+                for name in names {
+                    match KeyCode::from_name(name) {
+                        Some(keycode) => keycodes.push(keycode),
+                        None => glog::warn!("Unknown key {} of axis {}, skip the key.", name, axis_desc.action),
+                    }
+                }
+            }
+
+            map.bind_axis(axis);
+        }
+
+        Ok(map)
+    }
+}
+
+/// Leak the loaded action names to be binding keys, each name is leaked only once.
+fn intern_binding_key(name: &str) -> InputBindingKey {
+    static INTERNED_KEYS: Lazy<Mutex<HashSet<InputBindingKey>>> = Lazy::new(Default::default);
+
+    let mut interned = INTERNED_KEYS.lock().unwrap();
+    if let Some(key) = interned.get(name) {
+        return key;
+    }
+
+    let key: InputBindingKey = Box::leak(name.to_owned().into_boxed_str());
+    interned.insert(key);
+    key
+}
+
+fn is_key_pressed(keycode: &KeyCode, vkinput: &KeyboardInputState, mouse_input: &MouseInputState) -> bool {
+    match keycode {
+        KeyCode::VirtualKeyCode(vk) => vkinput.is_keyboard_pressed(*vk),
+        KeyCode::Mouse(mouse) => mouse_input.is_button_hold(*mouse),
+    }
 }
\ No newline at end of file
@@ -5,6 +5,69 @@ use winit::event::{Event, WindowEvent, ElementState};
 
 pub use winit::event::VirtualKeyCode as VirtualKeyCode; 
 
+/// Modifier keys held with the other keys, left and right keys are not distinguished.
+#[derive(Copy, Clone, Debug, Default, Hash, PartialEq, Eq)]
+pub struct KeyModifiers(u8);
+
+impl KeyModifiers {
+    pub const NONE  : Self = Self(0);
+    pub const CTRL  : Self = Self(1 << 0);
+    pub const SHIFT : Self = Self(1 << 1);
+    pub const ALT   : Self = Self(1 << 2);
+
+    /// Whether all the modifiers in other are held.
+    #[inline]
+    pub fn contains(&self, other: Self) -> bool {
+        (self.0 & other.0) == other.0
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Number of the modifier keys.
+    #[inline]
+    pub fn count(&self) -> u32 {
+        self.0.count_ones()
+    }
+
+    /// Names of the modifier keys, used to serialize the bindings.
+    pub fn names(&self) -> Vec<&'static str> {
+        [(Self::CTRL, "Ctrl"), (Self::SHIFT, "Shift"), (Self::ALT, "Alt")].into_iter()
+            .filter(|(modifier, _)| self.contains(*modifier))
+            .map(|(_, name)| name)
+            .collect()
+    }
+
+    /// Parse one modifier key from the name returned by names().
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "Ctrl" => Some(Self::CTRL),
+            "Shift" => Some(Self::SHIFT),
+            "Alt" => Some(Self::ALT),
+            _ => None,
+        }
+    }
+
+    fn from_vkcode(vk: VirtualKeyCode) -> Self {
+        match vk {
+            VirtualKeyCode::LControl | VirtualKeyCode::RControl => Self::CTRL,
+            VirtualKeyCode::LShift | VirtualKeyCode::RShift => Self::SHIFT,
+            VirtualKeyCode::LAlt | VirtualKeyCode::RAlt => Self::ALT,
+            _ => Self::NONE,
+        }
+    }
+}
+
+impl std::ops::BitOr for KeyModifiers {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
 #[derive(Copy, Clone)]
 struct KeyState {
     tick_count: u32,
@@ -30,6 +93,12 @@ impl KeyboardInputState {
         self.input_record_map.contains_key(&vk)
     }
 
+    /// Modifier keys which are being held.
+    pub fn modifiers(&self) -> KeyModifiers {
+        self.input_record_map.keys()
+            .fold(KeyModifiers::NONE, |modifiers, vk| modifiers | KeyModifiers::from_vkcode(*vk))
+    }
+
     pub fn update(&mut self, events: &[Event<'_, ()>]) {
         for event in events {
             if let Event::WindowEvent { event, .. } = event {
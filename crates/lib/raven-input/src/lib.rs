@@ -1,3 +1,5 @@
+extern crate log as glog;
+
 mod keyboard;
 mod mouse;
 mod binding;
@@ -5,8 +7,8 @@ mod manager;
 
 pub use manager::InputManager;
 
-pub use mouse::MouseButton;
-pub use keyboard::VirtualKeyCode;
+pub use mouse::{MouseButton, DEFAULT_DOUBLE_CLICK_THRESHOLD};
+pub use keyboard::{VirtualKeyCode, KeyModifiers};
 pub use manager::KeyCode;
 
-pub use binding::{InputMap, InputBindingKey, InputBinding};
\ No newline at end of file
+pub use binding::{InputMap, InputBindingKey, InputBinding, InputAxis, AxisResponseCurve};
\ No newline at end of file
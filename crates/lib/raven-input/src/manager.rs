@@ -1,13 +1,18 @@
+use std::path::Path;
+
+use anyhow::Context;
+use serde::Deserialize;
+use serde::de::{IntoDeserializer, value::StrDeserializer};
 use winit::event::Event;
 
 use raven_math::Vec2;
 
-use crate::InputMap;
-use crate::{binding::InputBindingMap, InputBinding};
+use crate::{InputMap, InputBindingKey};
+use crate::{binding::InputBindingMap, InputBinding, InputAxis};
 use crate::keyboard::KeyboardInputState;
-use crate::mouse::MouseInputState;
+use crate::mouse::{MouseInputState, MAX_MOUSE_BUTTON_COUNT};
 
-use super::{VirtualKeyCode, MouseButton};
+use super::{VirtualKeyCode, KeyModifiers, MouseButton};
 
 #[derive(Hash, Copy, Clone)]
 pub enum KeyCode {
@@ -47,6 +52,39 @@ impl KeyCode {
     pub fn mouse(mouse: MouseButton) -> Self {
         Self::Mouse(mouse)
     }
+
+    /// Stable name of the key, e.g. "W", "LControl" or "MouseLeft", used to serialize the bindings.
+    pub fn name(&self) -> String {
+        match self {
+            Self::VirtualKeyCode(vk) => format!("{:?}", vk),
+            Self::Mouse(mouse) => match *mouse {
+                MouseButton::LEFT => "MouseLeft".to_owned(),
+                MouseButton::MIDDLE => "MouseMiddle".to_owned(),
+                MouseButton::RIGHT => "MouseRight".to_owned(),
+                _ => format!("Mouse{}", mouse.as_usize()),
+            }
+        }
+    }
+
+    /// Parse the key from the name returned by name(), None if the name is unknown.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "MouseLeft" => return Some(Self::Mouse(MouseButton::LEFT)),
+            "MouseMiddle" => return Some(Self::Mouse(MouseButton::MIDDLE)),
+            "MouseRight" => return Some(Self::Mouse(MouseButton::RIGHT)),
+            _ => {}
+        }
+
+        if let Some(button) = name.strip_prefix("Mouse").and_then(|idx| idx.parse().ok())
+            .filter(|idx| *idx < MAX_MOUSE_BUTTON_COUNT)
+        {
+            return Some(Self::Mouse(MouseButton::from_usize(button)));
+        }
+
+        // the names of the virtual keys are their variant names, the same as what serde expects
+        let deserializer: StrDeserializer<'_, serde::de::value::Error> = name.into_deserializer();
+        VirtualKeyCode::deserialize(deserializer).ok().map(Self::VirtualKeyCode)
+    }
 }
 
 
@@ -57,6 +95,10 @@ pub struct InputManager {
     mouse_input: MouseInputState,
 
     bindings: InputBindingMap,
+
+    /// Requested cursor states, they are temporarily released while the window is not focused.
+    cursor_grab: bool,
+    cursor_visible: bool,
 }
 
 impl std::fmt::Debug for InputManager {
@@ -72,6 +114,9 @@ impl InputManager {
             mouse_input: MouseInputState::new(),
 
             bindings: InputBindingMap::new(),
+
+            cursor_grab: false,
+            cursor_visible: true,
         }
     }
 
@@ -80,9 +125,52 @@ impl InputManager {
         self.bindings.bind(keycode, binding);
     }
 
-    pub fn update(&mut self, events: &[Event<'_, ()>]) {
+    /// Remove one binding bound to this keycode.
+    #[inline]
+    pub fn remove_binding(&mut self, keycode: KeyCode) {
+        self.bindings.unbind(keycode);
+    }
+
+    /// Add an axis, see [`InputBinding::axis`]. An axis with the same key is replaced.
+    #[inline]
+    pub fn add_axis(&mut self, axis: InputAxis) {
+        self.bindings.bind_axis(axis);
+    }
+
+    #[inline]
+    pub fn remove_axis(&mut self, key: impl Into<InputBindingKey>) {
+        self.bindings.unbind_axis(key);
+    }
+
+    /// Remove all the bindings which map to this binding key.
+    #[inline]
+    pub fn remove_bindings(&mut self, key: impl Into<InputBindingKey>) {
+        self.bindings.unbind_all(key);
+    }
+
+    /// Save the bindings and the axes to a ron file.
+    pub fn save_bindings(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        std::fs::write(path, self.bindings.to_ron()?)
+            .with_context(|| format!("Failed to write input bindings to {}!", path.display()))
+    }
+
+    /// Load the bindings and the axes from a ron file saved by save_bindings(), they replace all the current ones.
+    /// The entries with unknown key names are skipped with a warning. Nothing is replaced if the file can't be parsed.
+    pub fn load_bindings(&mut self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read input bindings from {}!", path.display()))?;
+
+        self.bindings = InputBindingMap::from_ron(&source)
+            .with_context(|| format!("Failed to parse input bindings from {}!", path.display()))?;
+        Ok(())
+    }
+
+    /// Consume the window events of this frame, dt is the (unfiltered) frame time to measure the clicks.
+    pub fn update(&mut self, events: &[Event<'_, ()>], dt: f32) {
         self.keyboard_input.update(events);
-        self.mouse_input.update(events);
+        self.mouse_input.update(events, dt);
     }
 
     pub fn map(&mut self, dt: f32) -> InputMap {
@@ -99,6 +187,12 @@ impl InputManager {
         self.keyboard_input.is_keyboard_just_pressed(vk)
     }
 
+    /// Modifier keys (Ctrl/Shift/Alt) which are being held.
+    #[inline]
+    pub fn keyboard_modifiers(&self) -> KeyModifiers {
+        self.keyboard_input.modifiers()
+    }
+
     #[inline]
     pub fn is_mouse_just_pressed(&self, mb: MouseButton) -> bool {
         self.mouse_input.is_button_just_pressed(mb)
@@ -114,6 +208,57 @@ impl InputManager {
         self.mouse_input.is_button_just_released(mb)
     }
 
+    #[inline]
+    pub fn is_mouse_double_click(&self, mb: MouseButton) -> bool {
+        self.mouse_input.is_double_click(mb)
+    }
+
+    /// How long (in seconds) the mouse button has been held.
+    #[inline]
+    pub fn mouse_press_duration(&self, mb: MouseButton) -> f32 {
+        self.mouse_input.press_duration(mb)
+    }
+
+    /// Set the max time (in seconds) between two presses to be a double-click.
+    #[inline]
+    pub fn set_double_click_threshold(&mut self, threshold: f32) {
+        self.mouse_input.set_double_click_threshold(threshold)
+    }
+
+    #[inline]
+    pub fn double_click_threshold(&self) -> f32 {
+        self.mouse_input.double_click_threshold()
+    }
+
+    /// Record whether the cursor is requested to be grabbed (i.e. hidden and locked in the window).
+    /// It doesn't touch the window, the input api applies it to the window.
+    #[inline]
+    pub fn set_cursor_grab(&mut self, grab: bool) {
+        self.cursor_grab = grab;
+    }
+
+    #[inline]
+    pub fn is_cursor_grab(&self) -> bool {
+        self.cursor_grab
+    }
+
+    /// Record whether the cursor is requested to be visible when it is not grabbed.
+    #[inline]
+    pub fn set_cursor_visible(&mut self, visible: bool) {
+        self.cursor_visible = visible;
+    }
+
+    #[inline]
+    pub fn is_cursor_visible(&self) -> bool {
+        self.cursor_visible
+    }
+
+    /// Whether the window has the focus according to the events consumed so far.
+    #[inline]
+    pub fn is_window_focused(&self) -> bool {
+        self.mouse_input.is_focused()
+    }
+
     #[inline]
     pub fn mouse_pos_delta(&self) -> Vec2 {
         self.mouse_input.position_delta()
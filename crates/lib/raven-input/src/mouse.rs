@@ -15,12 +15,17 @@ impl MouseButton {
         self.0
     }
 
-    #[allow(dead_code)]
     pub fn from_usize(v: usize) -> Self {
         Self(v)
     }
 }
 
+/// Buttons are stored as bits of u8.
+pub(crate) const MAX_MOUSE_BUTTON_COUNT: usize = 8;
+
+/// Default max time (in seconds) between two presses to be a double-click.
+pub const DEFAULT_DOUBLE_CLICK_THRESHOLD: f32 = 0.3;
+
 pub struct MouseInputState {
     physical_position: PhysicalPosition<f64>,
     position_delta: Vec2,
@@ -28,6 +33,16 @@ pub struct MouseInputState {
     button_hold: u8,
     button_press: u8,
     button_release: u8,
+    button_double_click: u8,
+
+    /// How long each button has been held, kept in the frame it is released.
+    press_durations: [f32; MAX_MOUSE_BUTTON_COUNT],
+    /// Time since the last press which can start a double-click, None if there is no such press.
+    since_last_press: [Option<f32>; MAX_MOUSE_BUTTON_COUNT],
+    double_click_threshold: f32,
+
+    /// Device motion is reported even if the window is not focused, ignore it then.
+    is_focused: bool,
 }
 
 impl MouseInputState {
@@ -39,14 +54,36 @@ impl MouseInputState {
             button_hold: 0,
             button_press: 0,
             button_release: 0,
+            button_double_click: 0,
+
+            press_durations: [0.0; MAX_MOUSE_BUTTON_COUNT],
+            since_last_press: [None; MAX_MOUSE_BUTTON_COUNT],
+            double_click_threshold: DEFAULT_DOUBLE_CLICK_THRESHOLD,
+
+            is_focused: true,
         }
     }
 
+    /// Set the max time (in seconds) between two presses to be a double-click.
+    pub fn set_double_click_threshold(&mut self, threshold: f32) {
+        self.double_click_threshold = threshold.max(0.0);
+    }
+
+    pub fn double_click_threshold(&self) -> f32 {
+        self.double_click_threshold
+    }
+
     #[allow(dead_code)]
     pub fn physical_position(&self) -> Vec2 {
         Vec2::new(self.physical_position.x as f32, self.physical_position.y as f32)
     }
 
+    pub fn is_focused(&self) -> bool {
+        self.is_focused
+    }
+
+    /// Raw relative motion of the mouse device in this frame.
+    /// It is not derived from the cursor position, so it keeps working when the cursor is grabbed or reaches the screen edges.
     pub fn position_delta(&self) -> Vec2 {
         self.position_delta
     }
@@ -84,9 +121,36 @@ impl MouseInputState {
         }
     }
 
-    pub fn update(&mut self, events: &[Event<'_, ()>]) {
+    /// Whether the button is pressed again within the double-click threshold in this frame.
+    /// A third press starts a new click, so a triple-click is not two double-clicks.
+    pub fn is_double_click(&self, button: MouseButton) -> bool {
+        let button = button.as_usize();
+        (self.button_double_click & (1 << button)) != 0
+    }
+
+    /// How long (in seconds) the button has been held, or had been held in the frame it is released.
+    /// Zero if the button is not held.
+    pub fn press_duration(&self, button: MouseButton) -> f32 {
+        self.press_durations.get(button.as_usize()).copied().unwrap_or(0.0)
+    }
+
+    pub fn update(&mut self, events: &[Event<'_, ()>], dt: f32) {
+        // advance the timers of the last frame before handling the new events
+        for button in 0..MAX_MOUSE_BUTTON_COUNT {
+            if (self.button_hold & (1 << button)) != 0 {
+                self.press_durations[button] += dt;
+            } else {
+                self.press_durations[button] = 0.0;
+            }
+
+            self.since_last_press[button] = self.since_last_press[button]
+                .map(|elapsed| elapsed + dt)
+                .filter(|elapsed| *elapsed <= self.double_click_threshold);
+        }
+
         self.button_press = 0;
         self.button_release = 0;
+        self.button_double_click = 0;
         self.position_delta = Vec2::ZERO;
         self.wheel_delta = Vec2::ZERO;
 
@@ -112,6 +176,13 @@ impl MouseInputState {
                             if *state == ElementState::Pressed {
                                 self.button_press |= 1 << button;
                                 self.button_hold |= 1 << button;
+                                self.press_durations[button] = 0.0;
+
+                                if self.since_last_press[button].take().is_some() {
+                                    self.button_double_click |= 1 << button;
+                                } else {
+                                    self.since_last_press[button] = Some(0.0);
+                                }
                             } else {
                                 self.button_hold &= !(1 << button);
                                 self.button_release |= 1 << button;
@@ -126,12 +197,15 @@ impl MouseInputState {
                         WindowEvent::CursorMoved { position, .. } => {
                             self.physical_position = *position;
                         }
+                        WindowEvent::Focused(focused) => {
+                            self.is_focused = *focused;
+                        }
                         _ => {},
                     }
                 }
                 Event::DeviceEvent { device_id: _, event } => {
                     match event {
-                        DeviceEvent::MouseMotion { delta } => {
+                        DeviceEvent::MouseMotion { delta } if self.is_focused => {
                             let delta = *delta;
                             self.position_delta += Vec2::new(delta.0 as f32, delta.1 as f32);
                         }
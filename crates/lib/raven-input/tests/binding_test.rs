@@ -0,0 +1,185 @@
+use winit::event::{Event, WindowEvent, DeviceId, ElementState, KeyboardInput, ModifiersState};
+use winit::window::WindowId;
+
+use raven_input::{InputManager, InputBinding, AxisResponseCurve, KeyCode, KeyModifiers, MouseButton, VirtualKeyCode};
+
+#[allow(deprecated)]
+fn key(vk: VirtualKeyCode, state: ElementState) -> Event<'static, ()> {
+    Event::WindowEvent {
+        window_id: unsafe { WindowId::dummy() },
+        event: WindowEvent::KeyboardInput {
+            device_id: unsafe { DeviceId::dummy() },
+            input: KeyboardInput {
+                scancode: 0,
+                state,
+                virtual_keycode: Some(vk),
+                modifiers: ModifiersState::empty(),
+            },
+            is_synthetic: false,
+        },
+    }
+}
+
+fn input_with_chords() -> InputManager {
+    let mut input = InputManager::new();
+    input.add_binding(KeyCode::vkcode(VirtualKeyCode::S), InputBinding::new("walk", -1.0));
+    input.add_binding(KeyCode::vkcode(VirtualKeyCode::S), InputBinding::new("save", 1.0).modifiers(KeyModifiers::CTRL));
+    input.add_binding(KeyCode::vkcode(VirtualKeyCode::S), InputBinding::new("save_as", 1.0).modifiers(KeyModifiers::CTRL | KeyModifiers::SHIFT));
+    input
+}
+
+#[test]
+fn test_plain_key() {
+    let mut input = input_with_chords();
+
+    input.update(&[key(VirtualKeyCode::S, ElementState::Pressed)], 0.016);
+    let map = input.map(0.016);
+    assert_eq!(map["walk"], -1.0);
+    assert_eq!(map["save"], 0.0);
+    assert_eq!(map["save_as"], 0.0);
+}
+
+#[test]
+fn test_chord_longest_match_wins() {
+    let mut input = input_with_chords();
+
+    input.update(&[key(VirtualKeyCode::LControl, ElementState::Pressed), key(VirtualKeyCode::S, ElementState::Pressed)], 0.016);
+    assert_eq!(input.keyboard_modifiers(), KeyModifiers::CTRL);
+    let map = input.map(0.016);
+    assert_eq!(map["walk"], 0.0);
+    assert_eq!(map["save"], 1.0);
+    assert_eq!(map["save_as"], 0.0);
+
+    input.update(&[key(VirtualKeyCode::RShift, ElementState::Pressed)], 0.016);
+    let map = input.map(0.016);
+    assert_eq!(map["walk"], 0.0);
+    assert_eq!(map["save"], 0.0);
+    assert_eq!(map["save_as"], 1.0);
+
+    input.update(&[key(VirtualKeyCode::LControl, ElementState::Released), key(VirtualKeyCode::RShift, ElementState::Released)], 0.016);
+    assert!(input.keyboard_modifiers().is_empty());
+    let map = input.map(0.016);
+    assert_eq!(map["walk"], -1.0);
+    assert_eq!(map["save"], 0.0);
+}
+
+#[test]
+fn test_unmatched_chord_falls_back() {
+    let mut input = input_with_chords();
+
+    // no Alt+S binding, the plain S binding still fires
+    input.update(&[key(VirtualKeyCode::LAlt, ElementState::Pressed), key(VirtualKeyCode::S, ElementState::Pressed)], 0.016);
+    let map = input.map(0.016);
+    assert_eq!(map["walk"], -1.0);
+    assert_eq!(map["save"], 0.0);
+}
+
+fn walk_axis(input: &mut InputManager, curve: AxisResponseCurve, activation_time: f32) {
+    input.add_axis(InputBinding::axis("walk")
+        .negative(KeyCode::vkcode(VirtualKeyCode::S))
+        .positive(KeyCode::vkcode(VirtualKeyCode::W))
+        .positive(KeyCode::vkcode(VirtualKeyCode::Up))
+        .deadzone(0.2)
+        .curve(curve)
+        .activation_time(activation_time));
+}
+
+#[test]
+fn test_axis() {
+    let mut input = InputManager::new();
+    walk_axis(&mut input, AxisResponseCurve::Linear, 0.0);
+
+    let map = input.map(0.016);
+    assert_eq!(map.axis("walk"), 0.0);
+    assert_eq!(map.axis("unknown"), 0.0);
+
+    input.update(&[key(VirtualKeyCode::W, ElementState::Pressed)], 0.016);
+    assert_eq!(input.map(0.016).axis("walk"), 1.0);
+
+    // opposite sources cancel out
+    input.update(&[key(VirtualKeyCode::S, ElementState::Pressed)], 0.016);
+    assert_eq!(input.map(0.016).axis("walk"), 0.0);
+
+    input.update(&[key(VirtualKeyCode::W, ElementState::Released)], 0.016);
+    assert_eq!(input.map(0.016).axis("walk"), -1.0);
+}
+
+#[test]
+fn test_axis_deadzone_and_curve() {
+    for (curve, expected) in [(AxisResponseCurve::Linear, 0.5), (AxisResponseCurve::Quadratic, 0.25)] {
+        let mut input = InputManager::new();
+        walk_axis(&mut input, curve, 1.0);
+
+        input.update(&[key(VirtualKeyCode::Up, ElementState::Pressed)], 0.016);
+        // raw value 0.1 is inside the deadzone
+        assert_eq!(input.map(0.1).axis("walk"), 0.0);
+        // raw value 0.6 is rescaled to 0.5 by the deadzone
+        assert!((input.map(0.5).axis("walk") - expected).abs() < 1e-5);
+        assert_eq!(input.map(1.0).axis("walk"), 1.0);
+
+        input.update(&[key(VirtualKeyCode::Up, ElementState::Released)], 0.016);
+        // ramps down through the same response on release
+        assert!((input.map(0.4).axis("walk") - expected).abs() < 1e-5);
+        assert_eq!(input.map(1.0).axis("walk"), 0.0);
+
+        // symmetric for the negative side
+        input.update(&[key(VirtualKeyCode::S, ElementState::Pressed)], 0.016);
+        assert_eq!(input.map(0.1).axis("walk"), 0.0);
+        assert!((input.map(0.5).axis("walk") + expected).abs() < 1e-5);
+        assert_eq!(input.map(1.0).axis("walk"), -1.0);
+    }
+}
+
+#[test]
+fn test_save_and_load_bindings() {
+    let path = std::env::temp_dir().join("raven_input_bindings_test.ron");
+
+    let mut saved = input_with_chords();
+    saved.add_binding(KeyCode::mouse(MouseButton::RIGHT), InputBinding::new("aim", 1.0).activation_time(0.5));
+    walk_axis(&mut saved, AxisResponseCurve::Quadratic, 1.0);
+    saved.save_bindings(&path).unwrap();
+
+    // loading replaces the current bindings
+    let mut input = InputManager::new();
+    input.add_binding(KeyCode::vkcode(VirtualKeyCode::Space), InputBinding::new("jump", 1.0));
+    input.load_bindings(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    input.update(&[key(VirtualKeyCode::Space, ElementState::Pressed), key(VirtualKeyCode::LControl, ElementState::Pressed), key(VirtualKeyCode::S, ElementState::Pressed)], 0.016);
+    let map = input.map(0.016);
+    assert_eq!(map.action("jump"), 0.0);
+    assert_eq!(map["walk"], 0.0);
+    assert_eq!(map["save"], 1.0);
+    assert_eq!(map["save_as"], 0.0);
+    assert_eq!(map["aim"], 0.0);
+    // raw value -0.6 with the deadzone and the quadratic curve
+    assert!((input.map(0.584).axis("walk") + 0.25).abs() < 1e-5);
+}
+
+#[test]
+fn test_load_bindings_with_unknown_keys() {
+    let path = std::env::temp_dir().join("raven_input_unknown_keys_test.ron");
+    std::fs::write(&path, r#"(
+        bindings: [
+            (key: "NotAKey", action: "walk", multiplier: 1.0),
+            (key: "S", action: "save", multiplier: 1.0, modifiers: ["Hyper"]),
+            (key: "MouseLeft", action: "fire", multiplier: 1.0),
+        ],
+        axes: [
+            (action: "walk", negative: ["S"], positive: ["W", "NotAKey"]),
+        ],
+    )"#).unwrap();
+
+    let mut input = InputManager::new();
+    input.load_bindings(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    input.update(&[key(VirtualKeyCode::W, ElementState::Pressed), key(VirtualKeyCode::S, ElementState::Pressed)], 0.016);
+    let map = input.map(0.016);
+    assert!(!map.contains_key("save"));
+    assert_eq!(map["fire"], 0.0);
+    assert_eq!(map.axis("walk"), 0.0);
+
+    input.update(&[key(VirtualKeyCode::S, ElementState::Released)], 0.016);
+    assert_eq!(input.map(0.016).axis("walk"), 1.0);
+}
@@ -0,0 +1,114 @@
+use winit::event::{Event, WindowEvent, DeviceEvent, DeviceId, ElementState, MouseButton as WinitMouseButton, ModifiersState};
+use winit::window::WindowId;
+
+use raven_input::{InputManager, MouseButton};
+
+#[allow(deprecated)]
+fn left_button(state: ElementState) -> Event<'static, ()> {
+    Event::WindowEvent {
+        window_id: unsafe { WindowId::dummy() },
+        event: WindowEvent::MouseInput {
+            device_id: unsafe { DeviceId::dummy() },
+            state,
+            button: WinitMouseButton::Left,
+            modifiers: ModifiersState::empty(),
+        },
+    }
+}
+
+fn click(input: &mut InputManager, dt: f32) {
+    input.update(&[left_button(ElementState::Pressed)], dt);
+    input.update(&[left_button(ElementState::Released)], dt);
+}
+
+#[test]
+fn test_double_click() {
+    let mut input = InputManager::new();
+    input.set_double_click_threshold(0.3);
+
+    click(&mut input, 0.016);
+    assert!(!input.is_mouse_double_click(MouseButton::LEFT));
+
+    input.update(&[left_button(ElementState::Pressed)], 0.1);
+    assert!(input.is_mouse_double_click(MouseButton::LEFT));
+
+    // only reported in the frame of the second press
+    input.update(&[left_button(ElementState::Released)], 0.016);
+    assert!(!input.is_mouse_double_click(MouseButton::LEFT));
+
+    // the third press starts a new click
+    input.update(&[left_button(ElementState::Pressed)], 0.016);
+    assert!(!input.is_mouse_double_click(MouseButton::LEFT));
+}
+
+#[test]
+fn test_double_click_threshold_expired() {
+    let mut input = InputManager::new();
+    input.set_double_click_threshold(0.3);
+
+    click(&mut input, 0.016);
+
+    // wait until the threshold expires
+    input.update(&[], 0.2);
+    input.update(&[], 0.2);
+
+    input.update(&[left_button(ElementState::Pressed)], 0.016);
+    assert!(input.is_mouse_just_pressed(MouseButton::LEFT));
+    assert!(!input.is_mouse_double_click(MouseButton::LEFT));
+
+    // but it can start a new double-click
+    input.update(&[left_button(ElementState::Released)], 0.016);
+    input.update(&[left_button(ElementState::Pressed)], 0.016);
+    assert!(input.is_mouse_double_click(MouseButton::LEFT));
+}
+
+#[test]
+fn test_press_duration() {
+    let mut input = InputManager::new();
+
+    input.update(&[left_button(ElementState::Pressed)], 0.5);
+    assert_eq!(input.mouse_press_duration(MouseButton::LEFT), 0.0);
+
+    input.update(&[], 0.25);
+    input.update(&[], 0.25);
+    assert!((input.mouse_press_duration(MouseButton::LEFT) - 0.5).abs() < 1e-5);
+
+    // the duration is kept in the frame of release
+    input.update(&[left_button(ElementState::Released)], 0.25);
+    assert!(input.is_mouse_button_just_released(MouseButton::LEFT));
+    assert!((input.mouse_press_duration(MouseButton::LEFT) - 0.75).abs() < 1e-5);
+
+    input.update(&[], 0.25);
+    assert_eq!(input.mouse_press_duration(MouseButton::LEFT), 0.0);
+    assert_eq!(input.mouse_press_duration(MouseButton::RIGHT), 0.0);
+}
+
+fn focused(focused: bool) -> Event<'static, ()> {
+    Event::WindowEvent {
+        window_id: unsafe { WindowId::dummy() },
+        event: WindowEvent::Focused(focused),
+    }
+}
+
+fn mouse_motion(x: f64, y: f64) -> Event<'static, ()> {
+    Event::DeviceEvent {
+        device_id: unsafe { DeviceId::dummy() },
+        event: DeviceEvent::MouseMotion { delta: (x, y) },
+    }
+}
+
+#[test]
+fn test_mouse_motion_ignored_without_focus() {
+    let mut input = InputManager::new();
+
+    input.update(&[mouse_motion(3.0, -2.0), mouse_motion(1.0, 0.0)], 0.016);
+    assert_eq!(input.mouse_pos_delta().to_array(), [4.0, -2.0]);
+
+    input.update(&[focused(false), mouse_motion(3.0, -2.0)], 0.016);
+    assert!(!input.is_window_focused());
+    assert_eq!(input.mouse_pos_delta().to_array(), [0.0, 0.0]);
+
+    input.update(&[focused(true), mouse_motion(5.0, 5.0)], 0.016);
+    assert!(input.is_window_focused());
+    assert_eq!(input.mouse_pos_delta().to_array(), [5.0, 5.0]);
+}
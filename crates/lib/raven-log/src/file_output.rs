@@ -0,0 +1,230 @@
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// How the log file is written.
+#[derive(Copy, Clone, Debug)]
+pub enum FileLogMode {
+    /// Truncate log/log.txt on every run.
+    Truncate,
+    /// Write every run into a new file named with the starting time (e.g. log/log_2023-01-01_12-00-00.txt).
+    PerRun,
+    /// Append to log/log.txt, and when it exceeds max_size bytes, rotate it into log.1.txt, log.2.txt, ...
+    /// At most max_files rotated files are retained, the oldest one is removed.
+    Rotate {
+        max_size: u64,
+        max_files: u32,
+    },
+}
+
+impl Default for FileLogMode {
+    fn default() -> Self {
+        Self::Rotate {
+            max_size: 16 * 1024 * 1024,
+            max_files: 4,
+        }
+    }
+}
+
+const LOG_DIR: &str = "log";
+const LOG_FILE_STEM: &str = "log";
+
+/// Open the log file output according to the mode.
+pub(crate) fn open_file_output(mode: FileLogMode) -> anyhow::Result<Box<dyn Write + Send>> {
+    let dir = Path::new(LOG_DIR);
+
+    let output: Box<dyn Write + Send> = match mode {
+        FileLogMode::Truncate => {
+            Box::new(OpenOptions::new()
+                .create(true)
+                .write(true)
+                .truncate(true)
+                .open(dir.join(format!("{}.txt", LOG_FILE_STEM)))?)
+        }
+        FileLogMode::PerRun => {
+            let file_name = format!("{}_{}.txt", LOG_FILE_STEM, chrono::Local::now().format("%Y-%m-%d_%H-%M-%S"));
+            Box::new(OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(dir.join(file_name))?)
+        }
+        FileLogMode::Rotate { max_size, max_files } => {
+            Box::new(RotatingFile::open(dir, max_size, max_files)?)
+        }
+    };
+
+    Ok(output)
+}
+
+/// Log file which rotates itself when exceeding the max size.
+///
+/// A record may be written in several pieces, so the pieces are buffered until flush(), which ends every record.
+/// Then the file only rotates between the records, and one record never spans two files.
+struct RotatingFile {
+    dir: PathBuf,
+    file: File,
+    written: u64,
+    /// The record being written, not in the file yet.
+    record: Vec<u8>,
+
+    max_size: u64,
+    max_files: u32,
+}
+
+impl RotatingFile {
+    fn open(dir: &Path, max_size: u64, max_files: u32) -> std::io::Result<Self> {
+        let path = Self::file_path(dir, 0);
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)?;
+        let written = file.metadata()?.len();
+
+        Ok(Self {
+            dir: dir.to_owned(),
+            file,
+            written,
+            record: Vec::new(),
+
+            max_size,
+            max_files,
+        })
+    }
+
+    /// log.txt for index 0, log.{index}.txt for the rotated ones.
+    fn file_path(dir: &Path, index: u32) -> PathBuf {
+        if index == 0 {
+            dir.join(format!("{}.txt", LOG_FILE_STEM))
+        } else {
+            dir.join(format!("{}.{}.txt", LOG_FILE_STEM, index))
+        }
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        self.file.flush()?;
+
+        if self.max_files == 0 {
+            // nothing to retain, just start over
+            self.file.set_len(0)?;
+            self.written = 0;
+            return Ok(());
+        }
+
+        // drop the oldest one and shift the others: log.{n-1}.txt -> log.{n}.txt, ..., log.txt -> log.1.txt
+        let oldest = Self::file_path(&self.dir, self.max_files);
+        if oldest.exists() {
+            std::fs::remove_file(&oldest)?;
+        }
+        for index in (0..self.max_files).rev() {
+            let from = Self::file_path(&self.dir, index);
+            if from.exists() {
+                std::fs::rename(&from, Self::file_path(&self.dir, index + 1))?;
+            }
+        }
+
+        self.file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(Self::file_path(&self.dir, 0))?;
+        self.written = 0;
+
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.record.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    /// Write the buffered record into the file, rotate before it if the file would exceed the max size.
+    /// A record larger than the max size still goes into one file.
+    fn flush(&mut self) -> std::io::Result<()> {
+        if !self.record.is_empty() {
+            if self.written > 0 && self.written + self.record.len() as u64 > self.max_size {
+                self.rotate()?;
+            }
+
+            self.file.write_all(&self.record)?;
+            self.written += self.record.len() as u64;
+            self.record.clear();
+        }
+
+        self.file.flush()
+    }
+}
+
+impl Drop for RotatingFile {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use std::path::Path;
+
+    use super::RotatingFile;
+
+    fn write_record(file: &mut RotatingFile, idx: u32) {
+        // written in pieces like write!() does
+        write!(file, "record {:02} ", idx).unwrap();
+        writeln!(file, "{}", "x".repeat(10)).unwrap();
+        file.flush().unwrap();
+    }
+
+    fn read_log(dir: &Path, index: u32) -> Option<String> {
+        std::fs::read_to_string(RotatingFile::file_path(dir, index)).ok()
+    }
+
+    #[test]
+    fn test_rotating_file() {
+        let dir = std::env::temp_dir().join(format!("raven_log_rotate_test_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        // 3 records fit in one file
+        let record_len = "record 00 xxxxxxxxxx\n".len() as u64;
+        let mut file = RotatingFile::open(&dir, record_len * 3, 2).unwrap();
+
+        // nothing is written before the record ends
+        write!(file, "record").unwrap();
+        assert_eq!(read_log(&dir, 0).unwrap(), "");
+        file.record.clear();
+
+        for idx in 0..10 {
+            write_record(&mut file, idx);
+        }
+
+        // log.txt has the latest records, then log.1.txt and log.2.txt, the older ones are removed
+        let expected = |records: std::ops::Range<u32>| records
+            .map(|idx| format!("record {:02} xxxxxxxxxx\n", idx))
+            .collect::<String>();
+        assert_eq!(read_log(&dir, 0).unwrap(), expected(9..10));
+        assert_eq!(read_log(&dir, 1).unwrap(), expected(6..9));
+        assert_eq!(read_log(&dir, 2).unwrap(), expected(3..6));
+        assert!(read_log(&dir, 3).is_none());
+
+        // appends to the existing log.txt when opened again
+        drop(file);
+        let mut file = RotatingFile::open(&dir, record_len * 3, 2).unwrap();
+        write_record(&mut file, 10);
+        assert_eq!(read_log(&dir, 0).unwrap(), expected(9..11));
+
+        // a record larger than the max size is still written into one file
+        drop(file);
+        let mut file = RotatingFile::open(&dir, 4, 0).unwrap();
+        write_record(&mut file, 11);
+        assert_eq!(read_log(&dir, 0).unwrap(), expected(11..12));
+        // without retained files, the file just starts over
+        write_record(&mut file, 12);
+        assert_eq!(read_log(&dir, 0).unwrap(), expected(12..13));
+        assert!(read_log(&dir, 3).is_none());
+
+        drop(file);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,113 @@
+use std::cell::RefCell;
+use std::fmt::{self, Display, Write};
+
+use glog::Level;
+
+thread_local! {
+    /// Key/value fields of the log record which is being dispatched in this thread.
+    /// The dispatch is synchronous, so the formatters can read the fields of the current record from here.
+    static CURRENT_FIELDS: RefCell<Vec<(&'static str, String)>> = const { RefCell::new(Vec::new()) };
+}
+
+/// How the log file is formatted.
+#[derive(Copy, Clone, Debug, Default)]
+pub enum FileLogFormat {
+    /// Human-readable lines, the same as the console output.
+    #[default]
+    Text,
+    /// One json object per line, for machine parsing.
+    JsonLines,
+}
+
+/// Log a message with key/value fields, used by log_kv!().
+#[doc(hidden)]
+pub fn log_with_fields(level: Level, target: &str, message: &str, fields: &[(&'static str, &dyn Display)]) {
+    if level > glog::max_level() {
+        return;
+    }
+
+    // format the values before borrowing, in case the values log something in their Display
+    let fields = fields.iter()
+        .map(|(key, value)| (*key, value.to_string()))
+        .collect::<Vec<_>>();
+    CURRENT_FIELDS.with(|current| *current.borrow_mut() = fields);
+
+    glog::log!(target: target, level, "{}", message);
+
+    CURRENT_FIELDS.with(|current| current.borrow_mut().clear());
+}
+
+/// Format the fields of the current record as " key=value key=value", values with spaces or quotes are quoted.
+pub(crate) fn fmt_text_fields(out: &mut String) {
+    CURRENT_FIELDS.with(|current| {
+        for (key, value) in current.borrow().iter() {
+            if value.is_empty() || value.contains(|c: char| c.is_whitespace() || c == '"' || c == '=') {
+                let _ = write!(out, " {}={:?}", key, value);
+            } else {
+                let _ = write!(out, " {}={}", key, value);
+            }
+        }
+    });
+}
+
+/// Format the current record as a single line json object.
+pub(crate) fn fmt_json_record(out: &mut String, time: &dyn Display, level: Level, target: &str, message: &fmt::Arguments) {
+    out.push_str("{\"time\":");
+    push_json_string(out, &time.to_string());
+    out.push_str(",\"level\":");
+    push_json_string(out, level.as_str());
+    out.push_str(",\"target\":");
+    push_json_string(out, target);
+    out.push_str(",\"message\":");
+    push_json_string(out, &message.to_string());
+
+    CURRENT_FIELDS.with(|current| {
+        for (key, value) in current.borrow().iter() {
+            out.push(',');
+            push_json_string(out, key);
+            out.push(':');
+            push_json_string(out, value);
+        }
+    });
+    out.push('}');
+}
+
+fn push_json_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => { let _ = write!(out, "\\u{:04x}", c as u32); }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// Log a message with key/value fields, which are rendered consistently in the console and the log file.
+///
+/// ```ignore
+/// log_kv!(info, "loaded asset", uri = path.display(), bytes = data.len());
+/// ```
+///
+/// The values only need to implement Display, they are only formatted when the level is enabled.
+#[macro_export]
+macro_rules! log_kv {
+    (error, $($rest:tt)+) => { $crate::log_kv!(@level $crate::Level::Error, $($rest)+) };
+    (warn,  $($rest:tt)+) => { $crate::log_kv!(@level $crate::Level::Warn,  $($rest)+) };
+    (info,  $($rest:tt)+) => { $crate::log_kv!(@level $crate::Level::Info,  $($rest)+) };
+    (debug, $($rest:tt)+) => { $crate::log_kv!(@level $crate::Level::Debug, $($rest)+) };
+    (trace, $($rest:tt)+) => { $crate::log_kv!(@level $crate::Level::Trace, $($rest)+) };
+    (@level $level:expr, $message:expr $(, $key:ident = $value:expr)* $(,)?) => {
+        $crate::log_with_fields(
+            $level,
+            module_path!(),
+            &$message,
+            &[$((stringify!($key), &$value as &dyn ::std::fmt::Display)),*],
+        )
+    };
+}
@@ -1,11 +1,20 @@
 extern crate log as glog;
 
-use std::collections::HashSet;
+mod file_output;
+mod kv;
+
+use std::collections::{HashSet, HashMap};
+use std::sync::RwLock;
 
 use anyhow::Ok;
 use fern::colors::{Color, ColoredLevelConfig};
 
 pub use log::LevelFilter as LevelFilter;
+pub use log::Level as Level;
+pub use file_output::FileLogMode;
+pub use kv::FileLogFormat;
+#[doc(hidden)]
+pub use kv::log_with_fields;
 
 lazy_static::lazy_static! {
     static ref GLOBAL_MUTE_MODULE_NAMES: HashSet<&'static str> = HashSet::from([
@@ -15,12 +24,44 @@ lazy_static::lazy_static! {
         "gpu_allocator::allocator::dedicated_block_allocator",
         "async_io::driver",
     ]);
+
+    /// Base log level and the per-module log levels, can be changed at runtime.
+    static ref RUNTIME_LEVELS: RwLock<RuntimeLevels> = RwLock::new(RuntimeLevels {
+        base: LevelFilter::Info,
+        modules: HashMap::new(),
+    });
+}
+
+struct RuntimeLevels {
+    base: LevelFilter,
+    modules: HashMap<String, LevelFilter>,
+}
+
+impl RuntimeLevels {
+    /// Find the level of the most specific module which this target belongs to.
+    fn level_of(&self, target: &str) -> LevelFilter {
+        self.modules.iter()
+            .filter(|(module, _)| {
+                target == module.as_str() ||
+                (target.starts_with(module.as_str()) && target[module.len()..].starts_with("::"))
+            })
+            .max_by_key(|(module, _)| module.len())
+            .map_or(self.base, |(_, level)| *level)
+    }
+
+    fn max_level(&self) -> LevelFilter {
+        self.modules.values()
+            .copied()
+            .fold(self.base, |max, level| max.max(level))
+    }
 }
 
 /// Log configuration.
 #[derive(Copy)]
 pub struct LogConfig {
     pub level: LevelFilter,
+    pub file_mode: FileLogMode,
+    pub file_format: FileLogFormat,
 }
 
 impl Clone for LogConfig {
@@ -33,12 +74,39 @@ impl Clone for LogConfig {
 /// Initialize log module.
 pub fn init_log(config: LogConfig) -> anyhow::Result<()> {
     raven_filesystem::exist_or_create(raven_filesystem::ProjectFolder::Log)?;
+    RUNTIME_LEVELS.write().unwrap().base = config.level;
     setup_logger(config).expect("Failed to initialize log module!");
+    update_max_level();
     
     glog::trace!("log initialized!");
     Ok(())
 }
 
+/// Set the log level of all the modules which have no specific level.
+pub fn set_level(level: LevelFilter) {
+    RUNTIME_LEVELS.write().unwrap().base = level;
+    update_max_level();
+}
+
+/// Set the log level of a module and all its sub modules (e.g. "raven_rhi" or "raven_rhi::backend").
+/// This overrides the base log level.
+pub fn set_module_level(module: impl Into<String>, level: LevelFilter) {
+    RUNTIME_LEVELS.write().unwrap().modules.insert(module.into(), level);
+    update_max_level();
+}
+
+/// Remove the specific log level of a module, the module will use the base log level again.
+pub fn reset_module_level(module: &str) {
+    RUNTIME_LEVELS.write().unwrap().modules.remove(module);
+    update_max_level();
+}
+
+/// Filter out all the log messages which no module will accept, so that the filter closure is not called on every message.
+fn update_max_level() {
+    let max_level = RUNTIME_LEVELS.read().unwrap().max_level();
+    log::set_max_level(max_level);
+}
+
 fn setup_logger(config: LogConfig) -> anyhow::Result<()> {
     // setup colors
     let colors = ColoredLevelConfig::new()
@@ -67,36 +135,53 @@ fn setup_logger(config: LogConfig) -> anyhow::Result<()> {
     // console output with the colors
     let console_output = fern::Dispatch::new()
         .format(move |out, message, record| {
+            let mut fields = String::new();
+            kv::fmt_text_fields(&mut fields);
+
             out.finish(format_args!(
-                "{}[{}][{}] {}",
+                "{}[{}][{}] {}{}",
                 chrono::Local::now().format("[%Y-%m-%d][%H:%M:%S]"),
                 record.target(),
                 colors.color(record.level()),
-                message
+                message,
+                fields
             ))
         })
         .chain(stdout)
         .chain(stderr);
 
+    let file_format = config.file_format;
     let file_output = fern::Dispatch::new()
-        .format(|out, message, record| {
-            out.finish(format_args!(
-                "{}[{}][{}] {}",
-                chrono::Local::now().format("[%Y-%m-%d][%H:%M:%S]"),
-                record.target(),
-                record.level(),
-                message
-            ))
+        .format(move |out, message, record| {
+            match file_format {
+                FileLogFormat::Text => {
+                    let mut fields = String::new();
+                    kv::fmt_text_fields(&mut fields);
+
+                    out.finish(format_args!(
+                        "{}[{}][{}] {}{}",
+                        chrono::Local::now().format("[%Y-%m-%d][%H:%M:%S]"),
+                        record.target(),
+                        record.level(),
+                        message,
+                        fields
+                    ))
+                }
+                FileLogFormat::JsonLines => {
+                    let mut line = String::new();
+                    kv::fmt_json_record(&mut line, &chrono::Local::now().to_rfc3339(), record.level(), record.target(), message);
+
+                    out.finish(format_args!("{}", line))
+                }
+            }
         })
-        .chain(std::fs::OpenOptions::new() // global file output
-            .create(true)
-            .write(true)
-            .truncate(true)
-            .open("log/log.txt")?);
+        .chain(file_output::open_file_output(config.file_mode)?); // global file output
 
     // final apply to all the dispatches
     fern::Dispatch::new()
-        .level(config.level) // setup base log level from user
+        .filter(|meta| { // log levels can be changed at runtime
+            meta.level() <= RUNTIME_LEVELS.read().unwrap().level_of(meta.target())
+        })
         .chain(console_output)
         .chain(file_output)
         .apply()?;
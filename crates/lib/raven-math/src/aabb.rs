@@ -1,4 +1,4 @@
-use super::{Vec3, Mat4};
+use super::{Vec3, Vec4, Mat3, Mat4};
 
 /// Axis-Aligned Bounding Box
 #[derive(Copy, Clone, Debug)]
@@ -31,6 +31,11 @@ impl AABB {
         self.min.cmplt(self.max).all()
     }
 
+    /// Nothing is merged into the AABB yet, a flat AABB (e.g. a plane) is not empty.
+    pub fn is_empty(&self) -> bool {
+        self.min.cmpgt(self.max).any()
+    }
+
     pub fn merge_aabb(&mut self, other: &AABB) {
         self.min = self.min.min(other.min);
         self.max = self.max.max(other.max);
@@ -54,8 +59,34 @@ impl AABB {
         ));
     }
 
+    /// Transform the AABB and enclose the result in a new AABB.
+    /// 
+    /// Affine matrices (including rotation and non-uniform scale) give the tight bounds of the transformed box,
+    /// projective matrices transform the 8 corners instead.
     pub fn transform(&mut self, matrix: Mat4) {
-        // TODO: optimize it
+        // empty AABB stays empty
+        if self.is_empty() {
+            return;
+        }
+
+        if matrix.row(3) == Vec4::W {
+            // each axis of the new extent is the sum of the absolute contributions of the old extent
+            let center = (self.min + self.max) * 0.5;
+            let extent = (self.max - self.min) * 0.5;
+
+            let new_center = matrix.transform_point3(center);
+            let abs_matrix = Mat3::from_cols(
+                matrix.x_axis.truncate().abs(),
+                matrix.y_axis.truncate().abs(),
+                matrix.z_axis.truncate().abs(),
+            );
+            let new_extent = abs_matrix * extent;
+
+            self.min = new_center - new_extent;
+            self.max = new_center + new_extent;
+            return;
+        }
+
         let corners: [Vec3; 8] = [
 			self.min,
 			Vec3::from((self.min.x, self.min.y, self.max.z)),
@@ -80,13 +111,70 @@ impl AABB {
     pub fn get_center(&self) -> Vec3 {
         debug_assert!(self.is_valid());
 
-        (self.min + self.max) * 0.5
+        self.center()
     }
 
     /// Return the extent of the AABB (half the length to the axis border).
     pub fn get_extent(&self) -> Vec3 {
         debug_assert!(self.is_valid());
 
+        self.half_extents()
+    }
+
+    /// Center of the AABB, zero for an empty AABB.
+    #[inline]
+    pub fn center(&self) -> Vec3 {
+        if self.is_empty() {
+            return Vec3::ZERO;
+        }
+
+        (self.min + self.max) * 0.5
+    }
+
+    /// Half of the size on each axis, zero for an empty AABB.
+    #[inline]
+    pub fn half_extents(&self) -> Vec3 {
+        if self.is_empty() {
+            return Vec3::ZERO;
+        }
+
         (self.max - self.min) * 0.5
     }
-}
\ No newline at end of file
+
+    /// Area of the six faces (e.g. for the surface area heuristic), zero for an empty AABB.
+    pub fn surface_area(&self) -> f32 {
+        let size = self.half_extents() * 2.0;
+        2.0 * (size.x * size.y + size.y * size.z + size.z * size.x)
+    }
+
+    /// Grow the AABB by the margin on every side, a negative margin shrinks it.
+    /// An empty AABB stays empty, and shrinking past the center makes it empty.
+    pub fn expand(&mut self, margin: f32) {
+        if self.is_empty() {
+            return;
+        }
+
+        self.min -= Vec3::splat(margin);
+        self.max += Vec3::splat(margin);
+    }
+
+    /// Whether the point is inside or on the border of the AABB, an empty AABB contains nothing.
+    #[inline]
+    pub fn contains_point(&self, point: Vec3) -> bool {
+        self.min.cmple(point).all() && point.cmple(self.max).all()
+    }
+
+    /// Whether the other AABB is entirely inside this AABB, empty AABBs neither contain nor are contained.
+    #[inline]
+    pub fn contains_aabb(&self, other: &AABB) -> bool {
+        !other.is_empty() && self.contains_point(other.min) && self.contains_point(other.max)
+    }
+
+    /// Whether the two AABBs overlap, touching borders count as overlapping. An empty AABB intersects nothing.
+    #[inline]
+    pub fn intersects(&self, other: &AABB) -> bool {
+        !self.is_empty() && !other.is_empty() &&
+            self.min.cmple(other.max).all() && other.min.cmple(self.max).all()
+    }
+}
+
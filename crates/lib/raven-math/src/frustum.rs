@@ -0,0 +1,58 @@
+use super::{Vec3, Vec4, Mat4, AABB};
+
+/// Convex volume bounded by the planes of a projection, e.g. the camera view frustum.
+/// Each plane is (normal, distance) with the normal pointing inwards, i.e. dot(normal, p) + distance >= 0 inside.
+#[derive(Copy, Clone, Debug)]
+pub struct Frustum {
+    pub planes: [Vec4; 6],
+}
+
+impl Frustum {
+    /// Extract the planes from a world to clip matrix with the depth in [0, 1] (either normal or reversed z).
+    ///
+    /// An infinite far plane (e.g. infinite reversed z) has no normal, so it is replaced by a plane enclosing everything.
+    pub fn from_world_to_clip(world_to_clip: Mat4) -> Self {
+        let (r0, r1, r2, r3) = (world_to_clip.row(0), world_to_clip.row(1), world_to_clip.row(2), world_to_clip.row(3));
+
+        let planes = [
+            r3 + r0, // left
+            r3 - r0, // right
+            r3 + r1, // bottom
+            r3 - r1, // top
+            r2,      // z >= 0
+            r3 - r2, // z <= w
+        ]
+        .map(|plane| {
+            let length = plane.truncate().length();
+            if length > f32::EPSILON {
+                plane / length
+            } else {
+                Vec4::new(0.0, 0.0, 0.0, 1.0)
+            }
+        });
+
+        Self { planes }
+    }
+
+    #[inline]
+    pub fn contains_point(&self, point: Vec3) -> bool {
+        self.planes.iter()
+            .all(|plane| plane.truncate().dot(point) + plane.w >= 0.0)
+    }
+
+    /// Conservative test of whether the AABB is (partially) inside the frustum.
+    /// AABBs near the corners of the frustum may pass the test while being outside, an empty AABB never intersects.
+    pub fn intersects_aabb(&self, aabb: &AABB) -> bool {
+        if aabb.is_empty() {
+            return false;
+        }
+
+        self.planes.iter().all(|plane| {
+            let normal = plane.truncate();
+            // the corner furthest along the normal
+            let positive_corner = Vec3::select(normal.cmpge(Vec3::ZERO), aabb.max, aabb.min);
+            normal.dot(positive_corner) + plane.w >= 0.0
+        })
+    }
+}
+
@@ -1,10 +1,14 @@
 mod sh;
 mod aabb;
+mod ray;
+mod frustum;
 
 pub use glam::*;
 
 pub use sh::SHBasis9;
 pub use aabb::AABB;
+pub use ray::Ray;
+pub use frustum::Frustum;
 
 #[inline]
 pub fn min_value_align_to(size: usize, alignment: usize) -> usize {
@@ -27,6 +31,20 @@ pub fn max_mipmap_level_3d(width: u32, height: u32, depth: u32) -> u16 {
     max_mipmap_level_1d(width).max(max_mipmap_level_1d(height)).max(max_mipmap_level_1d(depth))
 }
 
+/// The index-th element (starts from 1) of the Halton low discrepancy sequence in the base, it is in range [0, 1).
+pub fn halton(mut index: u32, base: u32) -> f32 {
+    let mut result = 0.0;
+    let mut fraction = 1.0;
+
+    while index > 0 {
+        fraction /= base as f32;
+        result += fraction * (index % base) as f32;
+        index /= base;
+    }
+
+    result
+}
+
 #[inline]
 pub fn from_rgb8_to_color(r: u8, g: u8, b: u8) -> Vec3 {
     Vec3::from_array([
@@ -0,0 +1,108 @@
+use super::{Vec3, AABB};
+
+/// Ray with a origin and a direction.
+/// The direction is not required to be normalized, the hit distance t is measured in the unit of the direction.
+#[derive(Copy, Clone, Debug)]
+pub struct Ray {
+    pub origin: Vec3,
+    pub dir: Vec3,
+}
+
+impl Ray {
+    const EPSILON: f32 = 1e-8;
+
+    pub fn new(origin: Vec3, dir: Vec3) -> Self {
+        Self {
+            origin,
+            dir,
+        }
+    }
+
+    /// Return the point at distance t along the ray.
+    #[inline]
+    pub fn at(&self, t: f32) -> Vec3 {
+        self.origin + self.dir * t
+    }
+
+    /// Intersect with an AABB using the slab method.
+    /// Return the nearest non-negative hit distance, or 0.0 if the origin is inside the AABB.
+    pub fn intersect_aabb(&self, aabb: &AABB) -> Option<f32> {
+        let mut t_min = 0.0_f32;
+        let mut t_max = f32::MAX;
+
+        for axis in 0..3 {
+            let origin = self.origin[axis];
+            let dir = self.dir[axis];
+            let (min, max) = (aabb.min[axis], aabb.max[axis]);
+
+            if dir.abs() < Self::EPSILON {
+                // parallel to this slab, must start between the two planes
+                if origin < min || origin > max {
+                    return None;
+                }
+            } else {
+                let inv_dir = 1.0 / dir;
+                let mut t0 = (min - origin) * inv_dir;
+                let mut t1 = (max - origin) * inv_dir;
+                if t0 > t1 {
+                    std::mem::swap(&mut t0, &mut t1);
+                }
+
+                t_min = t_min.max(t0);
+                t_max = t_max.min(t1);
+
+                if t_min > t_max {
+                    return None;
+                }
+            }
+        }
+
+        Some(t_min)
+    }
+
+    /// Intersect with a triangle (a, b, c) using Möller–Trumbore algorithm.
+    /// Return the hit distance t and the barycentric coordinates (u, v) of b and c.
+    /// Triangles are double-sided, back faces (clockwise when looking along the ray) are hit too.
+    pub fn intersect_triangle(&self, a: Vec3, b: Vec3, c: Vec3) -> Option<(f32, f32, f32)> {
+        let edge1 = b - a;
+        let edge2 = c - a;
+
+        let p = self.dir.cross(edge2);
+        let det = edge1.dot(p);
+
+        // ray is parallel to the triangle plane
+        if det.abs() < Self::EPSILON {
+            return None;
+        }
+
+        let inv_det = 1.0 / det;
+        let s = self.origin - a;
+        let u = s.dot(p) * inv_det;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+
+        let q = s.cross(edge1);
+        let v = self.dir.dot(q) * inv_det;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+
+        let t = edge2.dot(q) * inv_det;
+        if t < 0.0 {
+            return None;
+        }
+
+        Some((t, u, v))
+    }
+
+    /// Same as intersect_triangle(), but the back faces are culled.
+    pub fn intersect_triangle_front(&self, a: Vec3, b: Vec3, c: Vec3) -> Option<(f32, f32, f32)> {
+        let normal = (b - a).cross(c - a);
+        if normal.dot(self.dir) >= 0.0 {
+            return None;
+        }
+
+        self.intersect_triangle(a, b, c)
+    }
+}
@@ -1,6 +1,6 @@
 use std::f32::consts::PI;
 
-use super::Vec3;
+use super::{Vec3, Mat3, Quat};
 
 #[derive(Debug, Clone)]
 pub struct SHBasis9 {
@@ -111,6 +111,66 @@ impl SHBasis9 {
         }
     }
 
+    /// Evaluate the function represented by this SH at the direction.
+    pub fn evaluate(&self, direction: Vec3) -> f32 {
+        let basis = Self::from_direction_cartesian(direction);
+
+        self.to_f32_array().iter()
+            .zip(basis.to_f32_array().iter())
+            .map(|(coeff, basis)| coeff * basis)
+            .sum()
+    }
+
+    /// Evaluate the RGB SH (one SHBasis9 per channel) at the direction.
+    pub fn evaluate_rgb(basis: &[SHBasis9; 3], direction: Vec3) -> Vec3 {
+        Vec3::new(
+            basis[0].evaluate(direction),
+            basis[1].evaluate(direction),
+            basis[2].evaluate(direction),
+        )
+    }
+
+    /// Rotate the function represented by this SH, so that rotated.evaluate(rotation * dir) == self.evaluate(dir).
+    /// 
+    /// Band 1 is rotated as a vector, band 2 is rotated as a symmetric traceless 3x3 matrix (R * M * R^T).
+    pub fn rotate(&self, rotation: Quat) -> SHBasis9 {
+        let y2n = 0.5 * (15.0 / PI).sqrt();
+        let y20n = 0.25 * (5.0 / PI).sqrt();
+        let y22n = 0.25 * (15.0 / PI).sqrt();
+
+        let rot = Mat3::from_quat(rotation);
+
+        // band 1
+        let band1 = rot * Vec3::new(self.y11, self.y1_1, self.y10);
+
+        // band 2
+        let xy = 0.5 * y2n * self.y2_2;
+        let yz = 0.5 * y2n * self.y2_1;
+        let xz = 0.5 * y2n * self.y21;
+        let xx = -y20n * self.y20 + y22n * self.y22;
+        let yy = -y20n * self.y20 - y22n * self.y22;
+        let zz = 2.0 * y20n * self.y20;
+
+        let band2 = Mat3::from_cols(
+            Vec3::new(xx, xy, xz),
+            Vec3::new(xy, yy, yz),
+            Vec3::new(xz, yz, zz),
+        );
+        let band2 = rot * band2 * rot.transpose();
+
+        SHBasis9 {
+            y00  : self.y00,
+            y1_1 : band1.y,
+            y10  : band1.z,
+            y11  : band1.x,
+            y2_2 : 2.0 * band2.y_axis.x / y2n,
+            y2_1 : 2.0 * band2.z_axis.y / y2n,
+            y20  : band2.z_axis.z / (2.0 * y20n),
+            y21  : 2.0 * band2.z_axis.x / y2n,
+            y22  : (band2.x_axis.x - band2.y_axis.y) / (2.0 * y22n),
+        }
+    }
+
     pub fn to_f32_array(&self) -> [f32; 9] {
         [
             self.y00,
@@ -0,0 +1,121 @@
+use raven_math::{AABB, Mat4, Vec3, Quat, EulerRot};
+
+fn assert_vec3_near(a: Vec3, b: Vec3) {
+    assert!((a - b).abs().max_element() < 1e-5, "{} != {}", a, b);
+}
+
+#[test]
+fn test_aabb_transform_rotation() {
+    let mut aabb = AABB::from_min_max(Vec3::splat(-0.5), Vec3::splat(0.5));
+    aabb.transform(Mat4::from_rotation_y(45_f32.to_radians()));
+
+    // the diagonal of the unit square in the xz plane
+    let half_diagonal = 0.5 * 2_f32.sqrt();
+    assert_vec3_near(aabb.min, Vec3::new(-half_diagonal, -0.5, -half_diagonal));
+    assert_vec3_near(aabb.max, Vec3::new(half_diagonal, 0.5, half_diagonal));
+}
+
+#[test]
+fn test_aabb_transform_non_uniform_scale() {
+    let mut aabb = AABB::from_min_max(Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 2.0, 3.0));
+    aabb.transform(Mat4::from_scale_rotation_translation(Vec3::new(2.0, -1.0, 0.5), Quat::IDENTITY, Vec3::new(1.0, 1.0, 1.0)));
+
+    // negative scale flips the box
+    assert_vec3_near(aabb.min, Vec3::new(1.0, -1.0, 1.0));
+    assert_vec3_near(aabb.max, Vec3::new(3.0, 1.0, 2.5));
+}
+
+#[test]
+fn test_aabb_transform_tight_bounds() {
+    let original = AABB::from_min_max(Vec3::new(-1.0, -2.0, 0.5), Vec3::new(2.0, 1.0, 1.5));
+    let matrix = Mat4::from_scale_rotation_translation(
+        Vec3::new(3.0, 0.5, 2.0),
+        Quat::from_euler(EulerRot::YXZ, 0.7, -0.3, 1.2),
+        Vec3::new(5.0, -2.0, 1.0),
+    );
+
+    let mut aabb = original;
+    aabb.transform(matrix);
+
+    // the bounds of the transformed corners, every corner is inside and every face touches one
+    let mut corners_aabb = AABB::new();
+    for i in 0..8 {
+        let corner = Vec3::new(
+            if i & 1 == 0 { original.min.x } else { original.max.x },
+            if i & 2 == 0 { original.min.y } else { original.max.y },
+            if i & 4 == 0 { original.min.z } else { original.max.z },
+        );
+        corners_aabb.merge_point_vec3(matrix.transform_point3(corner));
+    }
+
+    assert_vec3_near(aabb.min, corners_aabb.min);
+    assert_vec3_near(aabb.max, corners_aabb.max);
+}
+
+#[test]
+fn test_aabb_transform_empty() {
+    let mut aabb = AABB::new();
+    aabb.transform(Mat4::from_rotation_x(1.0));
+
+    assert_eq!(aabb.min, Vec3::splat(f32::MAX));
+    assert_eq!(aabb.max, Vec3::splat(f32::MIN));
+}
+
+fn unit_box() -> AABB {
+    AABB::from_min_max(Vec3::ZERO, Vec3::ONE)
+}
+
+#[test]
+fn test_aabb_queries() {
+    let aabb = unit_box();
+
+    assert_eq!(aabb.center(), Vec3::splat(0.5));
+    assert_eq!(aabb.half_extents(), Vec3::splat(0.5));
+    assert_eq!(aabb.surface_area(), 6.0);
+
+    assert!(aabb.contains_point(Vec3::splat(0.5)));
+    assert!(aabb.contains_point(Vec3::ONE));
+    assert!(!aabb.contains_point(Vec3::new(0.5, 1.5, 0.5)));
+
+    assert!(aabb.contains_aabb(&AABB::from_min_max(Vec3::splat(0.25), Vec3::splat(0.75))));
+    assert!(!aabb.contains_aabb(&AABB::from_min_max(Vec3::splat(0.5), Vec3::splat(1.5))));
+
+    assert!(aabb.intersects(&AABB::from_min_max(Vec3::splat(0.5), Vec3::splat(1.5))));
+    assert!(aabb.intersects(&AABB::from_min_max(Vec3::ONE, Vec3::splat(2.0))));
+    assert!(!aabb.intersects(&AABB::from_min_max(Vec3::new(2.0, 0.0, 0.0), Vec3::new(3.0, 1.0, 1.0))));
+}
+
+#[test]
+fn test_aabb_expand() {
+    let mut aabb = unit_box();
+    aabb.expand(1.0);
+    assert_eq!(aabb.min, Vec3::splat(-1.0));
+    assert_eq!(aabb.max, Vec3::splat(2.0));
+
+    aabb.expand(-2.0);
+    assert!(aabb.is_empty());
+}
+
+#[test]
+fn test_empty_aabb() {
+    let mut empty = AABB::new();
+    let aabb = unit_box();
+
+    assert_eq!(empty.center(), Vec3::ZERO);
+    assert_eq!(empty.half_extents(), Vec3::ZERO);
+    assert_eq!(empty.surface_area(), 0.0);
+
+    assert!(!empty.contains_point(Vec3::ZERO));
+    assert!(!empty.contains_aabb(&aabb));
+    assert!(!aabb.contains_aabb(&empty));
+    assert!(!empty.intersects(&aabb));
+    assert!(!aabb.intersects(&empty));
+    assert!(!empty.intersects(&AABB::new()));
+
+    empty.expand(1.0);
+    assert!(empty.is_empty());
+
+    // merging into an empty AABB gives the merged one
+    empty.merge_aabb(&aabb);
+    assert_eq!(empty.center(), aabb.center());
+}
@@ -0,0 +1,35 @@
+use raven_math::{Frustum, Mat4, Vec3, AABB};
+
+fn test_frustum() -> Frustum {
+    // looking at -Z from the origin
+    let view_to_clip = Mat4::perspective_infinite_reverse_rh(90.0_f32.to_radians(), 1.0, 0.1);
+    Frustum::from_world_to_clip(view_to_clip)
+}
+
+#[test]
+fn test_frustum_contains_point() {
+    let frustum = test_frustum();
+
+    assert!(frustum.contains_point(Vec3::new(0.0, 0.0, -1.0)));
+    assert!(frustum.contains_point(Vec3::new(0.0, 0.0, -1.0e6)));
+    assert!(!frustum.contains_point(Vec3::new(0.0, 0.0, 1.0)));
+    // behind the near plane
+    assert!(!frustum.contains_point(Vec3::new(0.0, 0.0, -0.05)));
+    // outside of the 90 degrees field of view
+    assert!(!frustum.contains_point(Vec3::new(2.0, 0.0, -1.0)));
+}
+
+#[test]
+fn test_frustum_intersects_aabb() {
+    let frustum = test_frustum();
+
+    assert!(frustum.intersects_aabb(&AABB::from_min_max(Vec3::new(-1.0, -1.0, -6.0), Vec3::new(1.0, 1.0, -4.0))));
+    // partially inside
+    assert!(frustum.intersects_aabb(&AABB::from_min_max(Vec3::new(-1.0, -1.0, -1.0), Vec3::new(1.0, 1.0, 1.0))));
+    // behind the camera
+    assert!(!frustum.intersects_aabb(&AABB::from_min_max(Vec3::new(-1.0, -1.0, 1.0), Vec3::new(1.0, 1.0, 2.0))));
+    // on the right side
+    assert!(!frustum.intersects_aabb(&AABB::from_min_max(Vec3::new(5.0, -1.0, -2.0), Vec3::new(6.0, 1.0, -1.0))));
+
+    assert!(!frustum.intersects_aabb(&AABB::new()));
+}
@@ -0,0 +1,29 @@
+use raven_math::halton;
+
+#[test]
+fn test_halton_base_2() {
+    let expected = [0.5, 0.25, 0.75, 0.125, 0.625, 0.375, 0.875];
+
+    for (i, value) in expected.iter().enumerate() {
+        assert!((halton(i as u32 + 1, 2) - value).abs() < 1e-6);
+    }
+}
+
+#[test]
+fn test_halton_base_3() {
+    let expected = [1.0 / 3.0, 2.0 / 3.0, 1.0 / 9.0, 4.0 / 9.0, 7.0 / 9.0];
+
+    for (i, value) in expected.iter().enumerate() {
+        assert!((halton(i as u32 + 1, 3) - value).abs() < 1e-6);
+    }
+}
+
+#[test]
+fn test_halton_range() {
+    assert_eq!(halton(0, 2), 0.0);
+
+    for i in 1..256 {
+        let value = halton(i, 3);
+        assert!((0.0..1.0).contains(&value));
+    }
+}
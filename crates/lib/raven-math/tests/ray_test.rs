@@ -0,0 +1,57 @@
+use raven_math::{Ray, AABB, Vec3};
+
+#[test]
+fn test_ray_hit_aabb() {
+    let aabb = AABB::from_min_max(Vec3::splat(-1.0), Vec3::splat(1.0));
+
+    let ray = Ray::new(Vec3::new(0.0, 0.0, -5.0), Vec3::Z);
+    let t = ray.intersect_aabb(&aabb).unwrap();
+    assert!((t - 4.0).abs() < 1e-5);
+
+    // direction with zero components
+    let ray = Ray::new(Vec3::new(0.5, 0.5, 5.0), Vec3::NEG_Z);
+    assert!(ray.intersect_aabb(&aabb).is_some());
+
+    // origin inside
+    let ray = Ray::new(Vec3::ZERO, Vec3::X);
+    assert_eq!(ray.intersect_aabb(&aabb), Some(0.0));
+}
+
+#[test]
+fn test_ray_miss_aabb() {
+    let aabb = AABB::from_min_max(Vec3::splat(-1.0), Vec3::splat(1.0));
+
+    // parallel to the slab, outside of it
+    let ray = Ray::new(Vec3::new(2.0, 0.0, -5.0), Vec3::Z);
+    assert!(ray.intersect_aabb(&aabb).is_none());
+
+    // pointing away
+    let ray = Ray::new(Vec3::new(0.0, 0.0, -5.0), Vec3::NEG_Z);
+    assert!(ray.intersect_aabb(&aabb).is_none());
+
+    let ray = Ray::new(Vec3::new(0.0, 0.0, -5.0), Vec3::new(1.0, 1.0, 1.0).normalize());
+    assert!(ray.intersect_aabb(&aabb).is_none());
+}
+
+#[test]
+fn test_ray_triangle() {
+    // counter-clockwise when looking down -Z
+    let a = Vec3::new(-1.0, -1.0, 0.0);
+    let b = Vec3::new(1.0, -1.0, 0.0);
+    let c = Vec3::new(0.0, 1.0, 0.0);
+
+    let front_ray = Ray::new(Vec3::new(0.0, 0.0, 2.0), Vec3::NEG_Z);
+    let (t, u, v) = front_ray.intersect_triangle(a, b, c).unwrap();
+    assert!((t - 2.0).abs() < 1e-5);
+    assert!(u >= 0.0 && v >= 0.0 && u + v <= 1.0);
+    assert!(front_ray.intersect_triangle_front(a, b, c).is_some());
+
+    // back face
+    let back_ray = Ray::new(Vec3::new(0.0, 0.0, -2.0), Vec3::Z);
+    assert!(back_ray.intersect_triangle(a, b, c).is_some());
+    assert!(back_ray.intersect_triangle_front(a, b, c).is_none());
+
+    // miss
+    let miss_ray = Ray::new(Vec3::new(5.0, 0.0, 2.0), Vec3::NEG_Z);
+    assert!(miss_ray.intersect_triangle(a, b, c).is_none());
+}
@@ -0,0 +1,27 @@
+use raven_math::{SHBasis9, Vec3, Quat};
+
+fn assert_sh_eq(lhs: &SHBasis9, rhs: &SHBasis9) {
+    for (l, r) in lhs.to_f32_array().iter().zip(rhs.to_f32_array().iter()) {
+        assert!((l - r).abs() < 1e-5, "{:?} != {:?}", lhs, rhs);
+    }
+}
+
+#[test]
+fn test_sh_rotate_identity() {
+    let sh = SHBasis9::from_direction_cartesian(Vec3::new(1.0, 2.0, 3.0).normalize())
+        .add(&SHBasis9::from_direction_cartesian(Vec3::NEG_Y).mul_scaler(0.5));
+
+    assert_sh_eq(&sh.rotate(Quat::IDENTITY), &sh);
+}
+
+#[test]
+fn test_sh_rotate_lobe_around_y() {
+    let sh = SHBasis9::from_direction_cartesian(Vec3::X);
+
+    // rotate +X by 90 degrees around +Y ends up in -Z
+    let rotated = sh.rotate(Quat::from_rotation_y(90_f32.to_radians()));
+    assert_sh_eq(&rotated, &SHBasis9::from_direction_cartesian(Vec3::NEG_Z));
+
+    assert!(rotated.evaluate(Vec3::NEG_Z) > rotated.evaluate(Vec3::X));
+    assert!((rotated.evaluate(Vec3::NEG_Z) - sh.evaluate(Vec3::X)).abs() < 1e-5);
+}
@@ -7,6 +7,7 @@ use crate::REFLECT_ATTR;
 pub(crate) static IGNORE_SERIALIZATION_ATTR: &str = "no_serialization";
 pub(crate) static IGNORE_ALL_ATTR: &str = "transparent";
 pub(crate) static DEFAULT_ATTR: &str = "default";
+pub(crate) static SKIP_IF_DEFAULT_ATTR: &str = "skip_if_default";
 
 /// Enum to define field should be ignore for serialization or reflection.
 /// 
@@ -75,6 +76,9 @@ pub(crate) enum DefaultBehavior {
 pub(crate) struct ReflectFieldAttr {
     pub ignore_behavior: ReflectIgnoreBehavior,
     pub default_behavior: DefaultBehavior,
+    /// Skip the serialization of this field when it equals its default value.
+    /// Only honored for the fields of named structs.
+    pub skip_if_default: bool,
 }
 
 pub(crate) fn parse_field_attributes(attrs: &[Attribute]) -> anyhow::Result<ReflectFieldAttr, syn::Error> {
@@ -122,6 +126,10 @@ fn parse_attribute_meta(reflect_attr: &mut ReflectFieldAttr, meta: &Meta) -> any
             reflect_attr.default_behavior = DefaultBehavior::Default;
             Ok(())
         }
+        Meta::Path(path) if path.is_ident(SKIP_IF_DEFAULT_ATTR) => {
+            reflect_attr.skip_if_default = true;
+            Ok(())
+        }
         Meta::Path(path) => Err(
             syn::Error::new_spanned(path, format!("Unknown reflect attributes: {}", path.to_token_stream()))
         ),
@@ -4,23 +4,49 @@ use syn::{Path, Generics};
 
 use quote::quote;
 
+use crate::{quoted::QuotedBox, trait_attributes::ReflectTraits};
+
 pub(crate) fn gen_type_registration(
     type_name: &Ident,
     reflect_crate_path: &Path,
-    trait_idents: &[Ident],
+    traits: &ReflectTraits,
     generics: &Generics,
     serialization_denylist: Option<&BitSet<u32>>,
+    field_defaults: &[(usize, TokenStream)],
+    skip_if_default_fields: &[usize],
 ) -> TokenStream {
     let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
 
     let serialization_data = serialization_denylist.map(|denylist| {
         let denylist = denylist.into_iter();
+        let field_defaults = field_defaults.iter().map(|(index, value)| {
+            quote! {
+                (#index, (|| #QuotedBox::new(#value) as #QuotedBox<dyn #reflect_crate_path::Reflect>) as #reflect_crate_path::serde::FieldDefaultFn)
+            }
+        });
         quote! {
             let ignored_indices_iter = ::core::iter::IntoIterator::into_iter([#(#denylist),*]);
-            registration.insert::<#reflect_crate_path::serde::SerializationData>(#reflect_crate_path::serde::SerializationData::new(ignored_indices_iter));
+            let field_defaults_iter = ::core::iter::IntoIterator::into_iter([#(#field_defaults),*]);
+            let skip_if_default_iter = ::core::iter::IntoIterator::into_iter([#(#skip_if_default_fields),*]);
+            registration.insert::<#reflect_crate_path::serde::SerializationData>(
+                #reflect_crate_path::serde::SerializationData::new(ignored_indices_iter, field_defaults_iter, skip_if_default_iter)
+            );
         }
     });
 
+    let trait_idents = traits.idents();
+    // special traits are implemented inside Reflect, register their type metas so that they can be queried from the registry
+    let hash_data = traits.has_hash().then(|| quote! {
+        registration.insert::<#reflect_crate_path::ReflectHash>(
+            #reflect_crate_path::type_registry::FromType::<#type_name #ty_generics>::from_type()
+        );
+    });
+    let partial_eq_data = traits.has_partial_eq().then(|| quote! {
+        registration.insert::<#reflect_crate_path::ReflectPartialEq>(
+            #reflect_crate_path::type_registry::FromType::<#type_name #ty_generics>::from_type()
+        );
+    });
+
     quote! {
         #[allow(unused_mut)]
         impl #impl_generics #reflect_crate_path::type_registry::GetTypeRegistration for #type_name #ty_generics #where_clause {
@@ -32,6 +58,9 @@ pub(crate) fn gen_type_registration(
                 );
                 // SerializationData
                 #serialization_data
+                // ReflectHash and ReflectPartialEq
+                #hash_data
+                #partial_eq_data
                 // Reflected Traits (i.e. Default -> ReflectDefault, Serialize -> ReflectSerialize)
                 #(registration.insert::<#trait_idents>(#reflect_crate_path::type_registry::FromType::<#type_name #ty_generics>::from_type());)*
                 registration
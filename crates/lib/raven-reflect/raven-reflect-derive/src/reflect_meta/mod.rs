@@ -93,9 +93,11 @@ impl<'a> ReflectMeta<'a> {
         reflect_gen::gen_type_registration(
             self.type_name,
             &self.reflect_crate_path,
-            self.traits.idents(),
+            &self.traits,
             self.generics,
             None,
+            &[],
+            &[],
         )
     }
 }
@@ -1,7 +1,8 @@
-use crate::{field_attributes::ReflectFieldAttr, reflect_gen};
+use crate::{field_attributes::{ReflectFieldAttr, DefaultBehavior}, reflect_gen, quoted::QuotedDefault};
 
 use super::ReflectMeta;
 
+use quote::quote;
 use syn::{Field};
 use bit_set::BitSet;
 
@@ -43,12 +44,42 @@ impl<'a> StructMetaInfo<'a> {
         reflect_gen::gen_type_registration(
             self.meta.type_name(),
             reflect_crate_path,
-            self.meta.traits().idents(),
+            self.meta.traits(),
             self.meta.generics(),
             Some(&self.serialization_denylist),
+            &self.field_defaults(),
+            &self.skip_if_default_fields(),
         )
     }
 
+    /// Get the default value expressions of the fields marked with `#[reflect(default)]` or `#[reflect(skip_if_default)]`,
+    /// indexed the same as the reflected fields (i.e. transparent fields are not counted).
+    pub fn field_defaults(&self) -> Vec<(usize, proc_macro2::TokenStream)> {
+        self.opaque_fields()
+            .enumerate()
+            .filter_map(|(index, field)| {
+                let ty = &field.field.ty;
+                match &field.attrs.default_behavior {
+                    // a skipped field must be filled back while deserializing
+                    DefaultBehavior::Required if field.attrs.skip_if_default => Some((index, quote! { <#ty as #QuotedDefault>::default() })),
+                    DefaultBehavior::Required => None,
+                    DefaultBehavior::Default => Some((index, quote! { <#ty as #QuotedDefault>::default() })),
+                    DefaultBehavior::Func(path) => Some((index, quote! { #path() })),
+                }
+            })
+            .collect()
+    }
+
+    /// Get the indices of the fields marked with `#[reflect(skip_if_default)]`,
+    /// indexed the same as the reflected fields (i.e. transparent fields are not counted).
+    pub fn skip_if_default_fields(&self) -> Vec<usize> {
+        self.opaque_fields()
+            .enumerate()
+            .filter(|(_, field)| field.attrs.skip_if_default)
+            .map(|(index, _)| index)
+            .collect()
+    }
+
     /// Get a collection of types of opaque fields.
     pub fn opaque_types(&self) -> Vec<syn::Type> {
         self.fields.iter()
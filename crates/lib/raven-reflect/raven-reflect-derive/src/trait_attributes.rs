@@ -159,6 +159,16 @@ impl ReflectTraits {
         &self.idents
     }
 
+    /// Returns true if `Hash` is registered for this type, either derived or with a custom function.
+    pub fn has_hash(&self) -> bool {
+        !matches!(self.hash_impl, TraitImplStatus::NotImplemented)
+    }
+
+    /// Returns true if `PartialEq` is registered for this type, either derived or with a custom function.
+    pub fn has_partial_eq(&self) -> bool {
+        !matches!(self.partial_eq_impl, TraitImplStatus::NotImplemented)
+    }
+
     /// Generate implementation for special trait Debug.
     pub fn gen_debug_impl(&self) -> Option<TokenStream> {
         match &self.debug_impl {
@@ -0,0 +1,258 @@
+use std::any::TypeId;
+
+use thiserror::Error as ThisError;
+
+use crate::{Reflect, ReflectRef, TypeInfo, Enum, VariantForm, VariantInfo};
+
+/// Structural mismatch found by [`Reflect::assign_checked`].
+///
+/// `path` is the location of the offending value relative to the assigned value (e.g. `transform.scale[2]`),
+/// it is empty if the mismatch is on the assigned value itself.
+#[derive(Debug, Clone, PartialEq, Eq, ThisError)]
+pub enum ReflectApplyError {
+    #[error("Mismatched type at `{path}`: expected `{expected}`, found `{found}`")]
+    MismatchedType { path: String, expected: &'static str, found: &'static str },
+
+    #[error("Field `{field}` of `{type_name}` at `{path}` is missing")]
+    MissingField { path: String, field: String, type_name: &'static str },
+
+    #[error("Field `{field}` does not exist on `{type_name}` at `{path}`")]
+    UnknownField { path: String, field: String, type_name: &'static str },
+
+    #[error("Variant `{variant}` does not exist on `{type_name}` at `{path}`")]
+    UnknownVariant { path: String, variant: String, type_name: &'static str },
+
+    #[error("Mismatched length at `{path}`: expected {expected}, found {found}")]
+    MismatchedLength { path: String, expected: usize, found: usize },
+}
+
+/// Check if `source` can be assigned to `target` without losing or misplacing any value.
+/// This is a read-only pass, so nothing is modified when a mismatch is found.
+pub(crate) fn check_assign(target: &dyn Reflect, source: &dyn Reflect) -> Result<(), ReflectApplyError> {
+    check_value(target, source, &mut String::new())
+}
+
+fn check_value(target: &dyn Reflect, source: &dyn Reflect, path: &mut String) -> Result<(), ReflectApplyError> {
+    match (target.reflect_ref(), source.reflect_ref()) {
+        (ReflectRef::Struct(lhs), ReflectRef::Struct(rhs)) => {
+            for (i, value) in rhs.iter().enumerate() {
+                let name = rhs.field_name_at(i).unwrap();
+                let field = lhs.field(name)
+                    .ok_or_else(|| unknown_field(path, name, target))?;
+                with_field(path, name, |path| check_value(field, value, path))?;
+            }
+
+            for i in 0..lhs.num_fields() {
+                let name = lhs.field_name_at(i).unwrap();
+                if rhs.field(name).is_none() {
+                    return Err(missing_field(path, name, target));
+                }
+            }
+            Ok(())
+        }
+        (ReflectRef::TupleStruct(lhs), ReflectRef::TupleStruct(rhs)) => {
+            check_unnamed_fields(
+                target, path, lhs.num_fields(), rhs.num_fields(),
+                |i| lhs.field_at(i), |i| rhs.field_at(i),
+            )
+        }
+        (ReflectRef::Tuple(lhs), ReflectRef::Tuple(rhs)) => {
+            check_unnamed_fields(
+                target, path, lhs.num_fields(), rhs.num_fields(),
+                |i| lhs.field_at(i), |i| rhs.field_at(i),
+            )
+        }
+        (ReflectRef::Enum(lhs), ReflectRef::Enum(rhs)) => check_enum(lhs, rhs, path),
+        (ReflectRef::Array(lhs), ReflectRef::Array(rhs)) => {
+            if lhs.len() != rhs.len() {
+                return Err(ReflectApplyError::MismatchedLength { path: path.clone(), expected: lhs.len(), found: rhs.len() });
+            }
+
+            for (i, (lhs, rhs)) in lhs.iter().zip(rhs.iter()).enumerate() {
+                with_index(path, i, |path| check_value(lhs, rhs, path))?;
+            }
+            Ok(())
+        }
+        (ReflectRef::List(lhs), ReflectRef::List(rhs)) => {
+            for (i, value) in rhs.iter().enumerate() {
+                if let Some(element) = lhs.get(i) {
+                    with_index(path, i, |path| check_value(element, value, path))?;
+                } else if let TypeInfo::List(info) = target.get_type_info() {
+                    // new elements are pushed, they must be convertible to the item type
+                    with_index(path, i, |path| check_type(info.item_type_id(), info.item_type_name(), value, path))?;
+                }
+            }
+            Ok(())
+        }
+        (ReflectRef::Map(lhs), ReflectRef::Map(rhs)) => {
+            for (key, value) in rhs.iter() {
+                if let Some(entry) = lhs.get(key) {
+                    let segment = format!("[{key:?}]");
+                    let len = path.len();
+                    path.push_str(&segment);
+                    let res = check_value(entry, value, path);
+                    path.truncate(len);
+                    res?;
+                } else if let TypeInfo::Map(info) = target.get_type_info() {
+                    check_type(info.key_type_id(), info.key_type_name(), key, path)?;
+                    check_type(info.value_type_id(), info.value_type_name(), value, path)?;
+                }
+            }
+            Ok(())
+        }
+        (ReflectRef::Primitive(lhs), ReflectRef::Primitive(rhs)) => {
+            if lhs.type_id() == rhs.type_id() {
+                Ok(())
+            } else {
+                Err(mismatched_type(path, target, source))
+            }
+        }
+        _ => Err(mismatched_type(path, target, source)),
+    }
+}
+
+fn check_enum(target: &dyn Enum, source: &dyn Enum, path: &mut String) -> Result<(), ReflectApplyError> {
+    if target.variant_name() == source.variant_name() {
+        // same variant, the fields are assigned one by one
+        return match source.variant_form() {
+            VariantForm::Struct => {
+                for field in source.iter() {
+                    let name = field.name().unwrap();
+                    let lhs = target.field(name)
+                        .ok_or_else(|| unknown_field(path, name, target.as_reflect()))?;
+                    with_field(path, name, |path| check_value(lhs, field.value(), path))?;
+                }
+
+                for i in 0..target.num_fields() {
+                    let name = target.field_name_at(i).unwrap();
+                    if source.field(name).is_none() {
+                        return Err(missing_field(path, name, target.as_reflect()));
+                    }
+                }
+                Ok(())
+            }
+            VariantForm::Tuple => {
+                check_unnamed_fields(
+                    target.as_reflect(), path, target.num_fields(), source.num_fields(),
+                    |i| target.field_at(i), |i| source.field_at(i),
+                )
+            }
+            VariantForm::Unit => Ok(()),
+        };
+    }
+
+    // different variant, the variant is constructed from the fields of the source
+    let info = match target.get_type_info() {
+        TypeInfo::Enum(info) => info,
+        // dynamic enums can switch to any variant
+        _ => return Ok(()),
+    };
+
+    let variant = info.variant(source.variant_name())
+        .ok_or_else(|| ReflectApplyError::UnknownVariant {
+            path: path.clone(),
+            variant: source.variant_name().to_string(),
+            type_name: target.type_name(),
+        })?;
+
+    match variant {
+        VariantInfo::Struct(variant) => {
+            for field in variant.iter() {
+                let value = source.field(field.name())
+                    .ok_or_else(|| missing_field(path, field.name(), target.as_reflect()))?;
+                with_field(path, field.name(), |path| check_type(field.type_id(), field.type_name(), value, path))?;
+            }
+
+            for field in source.iter() {
+                let name = field.name().unwrap_or_default();
+                if variant.field(name).is_none() {
+                    return Err(unknown_field(path, name, target.as_reflect()));
+                }
+            }
+        }
+        VariantInfo::Tuple(variant) => {
+            if variant.num_fields() != source.num_fields() {
+                return Err(ReflectApplyError::MismatchedLength {
+                    path: path.clone(),
+                    expected: variant.num_fields(),
+                    found: source.num_fields(),
+                });
+            }
+
+            for (i, field) in variant.iter().enumerate() {
+                let value = source.field_at(i).unwrap();
+                with_index(path, i, |path| check_type(field.type_id(), field.type_name(), value, path))?;
+            }
+        }
+        VariantInfo::Unit(_) => {
+            if source.num_fields() != 0 {
+                return Err(ReflectApplyError::MismatchedLength { path: path.clone(), expected: 0, found: source.num_fields() });
+            }
+        }
+    }
+    Ok(())
+}
+
+fn check_unnamed_fields<'a>(
+    target: &dyn Reflect,
+    path: &mut String,
+    target_len: usize,
+    source_len: usize,
+    target_field: impl Fn(usize) -> Option<&'a dyn Reflect>,
+    source_field: impl Fn(usize) -> Option<&'a dyn Reflect>,
+) -> Result<(), ReflectApplyError> {
+    if source_len > target_len {
+        return Err(unknown_field(path, &target_len.to_string(), target));
+    } else if source_len < target_len {
+        return Err(missing_field(path, &source_len.to_string(), target));
+    }
+
+    for i in 0..target_len {
+        let (lhs, rhs) = (target_field(i).unwrap(), source_field(i).unwrap());
+        with_field(path, &i.to_string(), |path| check_value(lhs, rhs, path))?;
+    }
+    Ok(())
+}
+
+/// Values which will be converted into a new value of the expected type.
+/// Dynamic values are converted by their structure, so only concrete values can be checked here.
+fn check_type(expected_id: TypeId, expected_name: &'static str, value: &dyn Reflect, path: &str) -> Result<(), ReflectApplyError> {
+    if matches!(value.get_type_info(), TypeInfo::Dynamic(_)) || value.type_id() == expected_id {
+        Ok(())
+    } else {
+        Err(ReflectApplyError::MismatchedType { path: path.to_string(), expected: expected_name, found: value.type_name() })
+    }
+}
+
+fn with_field<R>(path: &mut String, name: &str, f: impl FnOnce(&mut String) -> R) -> R {
+    let len = path.len();
+    if !path.is_empty() {
+        path.push('.');
+    }
+    path.push_str(name);
+
+    let res = f(path);
+    path.truncate(len);
+    res
+}
+
+fn with_index<R>(path: &mut String, index: usize, f: impl FnOnce(&mut String) -> R) -> R {
+    let len = path.len();
+    path.push_str(&format!("[{index}]"));
+
+    let res = f(path);
+    path.truncate(len);
+    res
+}
+
+fn mismatched_type(path: &str, target: &dyn Reflect, source: &dyn Reflect) -> ReflectApplyError {
+    ReflectApplyError::MismatchedType { path: path.to_string(), expected: target.type_name(), found: source.type_name() }
+}
+
+fn missing_field(path: &str, field: &str, target: &dyn Reflect) -> ReflectApplyError {
+    ReflectApplyError::MissingField { path: path.to_string(), field: field.to_string(), type_name: target.type_name() }
+}
+
+fn unknown_field(path: &str, field: &str, target: &dyn Reflect) -> ReflectApplyError {
+    ReflectApplyError::UnknownField { path: path.to_string(), field: field.to_string(), type_name: target.type_name() }
+}
@@ -62,7 +62,7 @@ impl<T: FromReflect> FromType<T> for ReflectFromReflect {
 #[cfg(test)]
 mod tests {
     use crate::{self as raven_reflect, FromReflect, DynamicStruct};
-    use raven_reflect_derive::{Reflect, FromReflect};
+    use raven_reflect_derive::Reflect;
 
     #[test]
     fn test_from_reflect_struct() {
@@ -0,0 +1,24 @@
+use raven_math::{Vec2, Vec3, Vec4, Quat, Mat3, Mat4, Affine3A};
+
+use crate::{self as raven_reflect};
+use raven_reflect_derive::{impl_reflect_primitive, impl_from_reflect_primitive};
+
+use crate::std_traits::ReflectDefault;
+use crate::serde::{ReflectSerialize, ReflectDeserialize};
+
+// Math types are reflected as primitives (i.e. opaque values), they are serialized by glam's serde implementations.
+impl_reflect_primitive!(Vec2(Debug, PartialEq, Serialize, Deserialize, Default));
+impl_reflect_primitive!(Vec3(Debug, PartialEq, Serialize, Deserialize, Default));
+impl_reflect_primitive!(Vec4(Debug, PartialEq, Serialize, Deserialize, Default));
+impl_reflect_primitive!(Quat(Debug, PartialEq, Serialize, Deserialize, Default));
+impl_reflect_primitive!(Mat3(Debug, PartialEq, Serialize, Deserialize, Default));
+impl_reflect_primitive!(Mat4(Debug, PartialEq, Serialize, Deserialize, Default));
+impl_reflect_primitive!(Affine3A(Debug, PartialEq, Serialize, Deserialize, Default));
+
+impl_from_reflect_primitive!(Vec2);
+impl_from_reflect_primitive!(Vec3);
+impl_from_reflect_primitive!(Vec4);
+impl_from_reflect_primitive!(Quat);
+impl_from_reflect_primitive!(Mat3);
+impl_from_reflect_primitive!(Mat4);
+impl_from_reflect_primitive!(Affine3A);
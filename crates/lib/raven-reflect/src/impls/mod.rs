@@ -1 +1,2 @@
-mod std;
\ No newline at end of file
+mod std;
+mod glam;
\ No newline at end of file
@@ -6,9 +6,10 @@ use std::path::PathBuf;
 use std::time::{Duration, Instant};
 
 use crate::{
-    Reflect, FromReflect, Typed, TypeInfo, GenericTypeInfoOnceCell, ListTypeInfo,
+    Reflect, FromReflect, Typed, TypeInfo, GenericTypeInfoOnceCell, ListTypeInfo, ArrayTypeInfo,
     type_registry::{GetTypeRegistration, TypeRegistration, ReflectFromPtr, FromType},
     Array, ArrayIter, List,
+    Tuple, TupleFieldIter, TupleTypeInfo, DynamicTuple,
     ReflectRef, ReflectRefMut, ReflectOwned,
 };
 use crate::{self as raven_reflect, UnnamedField, VariantInfo, UnitVariantInfo, TupleVariantInfo, EnumTypeInfo, Enum, DynamicEnum, VariantForm, VariantFieldIter};
@@ -65,6 +66,9 @@ impl_reflect_primitive!(RangeTo<T: Clone + Send + Sync + 'static>());
 impl_reflect_primitive!(RangeToInclusive<T: Clone + Send + Sync + 'static>());
 impl_reflect_primitive!(RangeFull());
 
+// Duration goes through its serde implementation, which is a struct of whole seconds and the
+// remaining nanoseconds, i.e. `(secs: 1, nanos: 500000000)` in ron. Tools should read and write
+// these two fields instead of a float to keep the value lossless.
 impl_reflect_primitive!(Duration(
     Debug,
     Hash,
@@ -131,7 +135,7 @@ impl_from_reflect_primitive!(NonZeroU16);
 impl_from_reflect_primitive!(NonZeroU8);
 
 macro_rules! impl_reflect_veclike {
-    ($ty:ty, $push:expr, $pop:expr, $insert:expr, $remove:expr, $sub:ty) => {
+    ($ty:ty, $push:expr, $pop:expr, $insert:expr, $remove:expr, $clear:expr, $sub:ty) => {
         impl<T: FromReflect> Typed for $ty {
             fn type_info() -> &'static TypeInfo {
                 static TYPE_INFO_CELL: GenericTypeInfoOnceCell = GenericTypeInfoOnceCell::new();
@@ -208,6 +212,11 @@ macro_rules! impl_reflect_veclike {
             fn pop(&mut self) -> Option<Box<dyn Reflect>> {
                 $pop(self).map(|value| Box::new(value) as Box<dyn Reflect>)
             }
+
+            #[inline]
+            fn clear(&mut self) {
+                $clear(self);
+            }
         }
 
         impl<T: FromReflect> Reflect for $ty {
@@ -282,14 +291,287 @@ macro_rules! impl_reflect_veclike {
     };
 }
 
-impl_reflect_veclike!(Vec<T>, Vec::push, Vec::pop, Vec::insert, Vec::remove, [T]);
+impl_reflect_veclike!(Vec<T>, Vec::push, Vec::pop, Vec::insert, Vec::remove, Vec::clear, [T]);
 impl_reflect_veclike!(
     VecDeque<T>,
     VecDeque::push_back, VecDeque::pop_back,
-    VecDeque::insert, VecDeque::remove,
+    VecDeque::insert,
+    // VecDeque::remove returns an Option, keep the same panicking behavior as Vec::remove
+    |deque: &mut VecDeque<T>, index| deque.remove(index).expect("Removal index is out of bounds!"),
+    VecDeque::clear,
     VecDeque::<T>
 );
 
+// fixed size arrays are reflected as Array, the elements can be mutated in place but not pushed or removed
+impl<T: FromReflect, const N: usize> Typed for [T; N] {
+    fn type_info() -> &'static TypeInfo {
+        static TYPE_INFO_CELL: GenericTypeInfoOnceCell = GenericTypeInfoOnceCell::new();
+        TYPE_INFO_CELL.get_or_insert::<Self, _>(|| TypeInfo::Array(ArrayTypeInfo::new::<Self, T>(N)))
+    }
+}
+
+impl<T: FromReflect, const N: usize> GetTypeRegistration for [T; N] {
+    fn get_type_registration() -> TypeRegistration {
+        let mut registration = TypeRegistration::type_of::<[T; N]>();
+        registration.insert::<ReflectFromPtr>(FromType::<[T; N]>::from_type());
+        registration
+    }
+}
+
+impl<T: FromReflect, const N: usize> Array for [T; N] {
+    #[inline]
+    fn get(&self, index: usize) -> Option<&dyn Reflect> {
+        <[T]>::get(self, index).map(|value| value as &dyn Reflect)
+    }
+
+    #[inline]
+    fn get_mut(&mut self, index: usize) -> Option<&mut dyn Reflect> {
+        <[T]>::get_mut(self, index).map(|value| value as &mut dyn Reflect)
+    }
+
+    #[inline]
+    fn len(&self) -> usize {
+        N
+    }
+
+    #[inline]
+    fn iter(&self) -> ArrayIter {
+        ArrayIter::new(self)
+    }
+
+    #[inline]
+    fn drain(self: Box<Self>) -> Vec<Box<dyn Reflect>> {
+        self.into_iter()
+            .map(|value| Box::new(value) as Box<dyn Reflect>)
+            .collect()
+    }
+}
+
+impl<T: FromReflect, const N: usize> Reflect for [T; N] {
+    #[inline]
+    fn type_name(&self) -> &'static str {
+        ::core::any::type_name::<Self>()
+    }
+
+    #[inline]
+    fn get_type_info(&self) -> &'static TypeInfo {
+        <Self as Typed>::type_info()
+    }
+
+    #[inline]
+    fn into_reflect(self: Box<Self>) -> Box<dyn Reflect> {
+        self
+    }
+
+    #[inline]
+    fn as_reflect(&self) -> &dyn Reflect {
+        self
+    }
+
+    #[inline]
+    fn as_reflect_mut(&mut self) -> &mut dyn Reflect {
+        self
+    }
+
+    #[inline]
+    fn clone_value(&self) -> Box<dyn Reflect> {
+        Box::new(Array::clone_dynamic(self))
+    }
+
+    fn assign(&mut self, value: &dyn Reflect) {
+        match value.reflect_ref() {
+            ReflectRef::Array(array) => {
+                if array.len() != N {
+                    panic!("Attempted to apply different sized `Array` types.");
+                }
+                for (i, value) in array.iter().enumerate() {
+                    self[i].assign(value);
+                }
+            }
+            _ => panic!("Attempted to apply a non-`Array` type to an `Array` type."),
+        }
+    }
+
+    fn reflect_ref(&self) -> ReflectRef {
+        ReflectRef::Array(self)
+    }
+
+    fn reflect_ref_mut(&mut self) -> ReflectRefMut {
+        ReflectRefMut::Array(self)
+    }
+
+    fn reflect_owned(self: Box<Self>) -> ReflectOwned {
+        ReflectOwned::Array(self)
+    }
+
+    fn reflect_hash(&self) -> Option<u64> {
+        crate::special_traits::hash::array_hash(self)
+    }
+
+    fn reflect_partial_eq(&self, value: &dyn Reflect) -> Option<bool> {
+        crate::special_traits::partial_eq::array_partial_eq(self, value)
+    }
+}
+
+impl<T: FromReflect, const N: usize> FromReflect for [T; N] {
+    fn from_reflect(reflect: &dyn Reflect) -> Option<Self> {
+        if let ReflectRef::Array(ref_array) = reflect.reflect_ref() {
+            let values = ref_array.iter()
+                .map(T::from_reflect)
+                .collect::<Option<Vec<_>>>()?;
+            values.try_into().ok()
+        } else {
+            None
+        }
+    }
+}
+
+macro_rules! impl_reflect_tuple {
+    {$($index:tt : $name:tt),*} => {
+        impl<$($name: FromReflect),*> Typed for ($($name,)*) {
+            fn type_info() -> &'static TypeInfo {
+                static TYPE_INFO_CELL: GenericTypeInfoOnceCell = GenericTypeInfoOnceCell::new();
+                TYPE_INFO_CELL.get_or_insert::<Self, _>(|| {
+                    let fields = [
+                        $(UnnamedField::new::<$name>($index),)*
+                    ];
+                    TypeInfo::Tuple(TupleTypeInfo::new::<Self>(&fields))
+                })
+            }
+        }
+
+        impl<$($name: FromReflect),*> GetTypeRegistration for ($($name,)*) {
+            fn get_type_registration() -> TypeRegistration {
+                let mut registration = TypeRegistration::type_of::<($($name,)*)>();
+                registration.insert::<ReflectFromPtr>(FromType::<($($name,)*)>::from_type());
+                registration
+            }
+        }
+
+        impl<$($name: FromReflect),*> Tuple for ($($name,)*) {
+            #[inline]
+            fn field_at(&self, index: usize) -> Option<&dyn Reflect> {
+                match index {
+                    $($index => Some(&self.$index as &dyn Reflect),)*
+                    _ => None,
+                }
+            }
+
+            #[inline]
+            fn field_at_mut(&mut self, index: usize) -> Option<&mut dyn Reflect> {
+                match index {
+                    $($index => Some(&mut self.$index as &mut dyn Reflect),)*
+                    _ => None,
+                }
+            }
+
+            #[inline]
+            fn num_fields(&self) -> usize {
+                [$($index,)*].len()
+            }
+
+            #[inline]
+            fn iter(&self) -> TupleFieldIter {
+                TupleFieldIter::new(self)
+            }
+
+            #[inline]
+            fn drain(self: Box<Self>) -> Vec<Box<dyn Reflect>> {
+                vec![
+                    $(Box::new(self.$index),)*
+                ]
+            }
+
+            fn clone_dynamic(&self) -> DynamicTuple {
+                let mut dyn_tuple = DynamicTuple::default();
+                for field in Tuple::iter(self) {
+                    dyn_tuple.add_field_boxed(field.clone_value());
+                }
+                dyn_tuple
+            }
+        }
+
+        impl<$($name: FromReflect),*> Reflect for ($($name,)*) {
+            #[inline]
+            fn type_name(&self) -> &'static str {
+                ::core::any::type_name::<Self>()
+            }
+
+            #[inline]
+            fn get_type_info(&self) -> &'static TypeInfo {
+                <Self as Typed>::type_info()
+            }
+
+            #[inline]
+            fn into_reflect(self: Box<Self>) -> Box<dyn Reflect> {
+                self
+            }
+
+            #[inline]
+            fn as_reflect(&self) -> &dyn Reflect {
+                self
+            }
+
+            #[inline]
+            fn as_reflect_mut(&mut self) -> &mut dyn Reflect {
+                self
+            }
+
+            #[inline]
+            fn clone_value(&self) -> Box<dyn Reflect> {
+                Box::new(Tuple::clone_dynamic(self))
+            }
+
+            fn assign(&mut self, value: &dyn Reflect) {
+                crate::type_info::tuple_assign(self, value);
+            }
+
+            fn reflect_ref(&self) -> ReflectRef {
+                ReflectRef::Tuple(self)
+            }
+
+            fn reflect_ref_mut(&mut self) -> ReflectRefMut {
+                ReflectRefMut::Tuple(self)
+            }
+
+            fn reflect_owned(self: Box<Self>) -> ReflectOwned {
+                ReflectOwned::Tuple(self)
+            }
+
+            fn reflect_partial_eq(&self, value: &dyn Reflect) -> Option<bool> {
+                crate::special_traits::partial_eq::tuple_partial_eq(self, value)
+            }
+
+            fn debug(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                crate::special_traits::debug::tuple_debug(self, f)
+            }
+        }
+
+        impl<$($name: FromReflect),*> FromReflect for ($($name,)*) {
+            fn from_reflect(reflect: &dyn Reflect) -> Option<Self> {
+                if let ReflectRef::Tuple(ref_tuple) = reflect.reflect_ref() {
+                    Some(
+                        (
+                            $($name::from_reflect(ref_tuple.field_at($index)?)?,)*
+                        )
+                    )
+                } else {
+                    None
+                }
+            }
+        }
+    };
+}
+
+impl_reflect_tuple! {0: A}
+impl_reflect_tuple! {0: A, 1: B}
+impl_reflect_tuple! {0: A, 1: B, 2: C}
+impl_reflect_tuple! {0: A, 1: B, 2: C, 3: D}
+impl_reflect_tuple! {0: A, 1: B, 2: C, 3: D, 4: E}
+impl_reflect_tuple! {0: A, 1: B, 2: C, 3: D, 4: E, 5: F}
+impl_reflect_tuple! {0: A, 1: B, 2: C, 3: D, 4: E, 5: F, 6: G}
+impl_reflect_tuple! {0: A, 1: B, 2: C, 3: D, 4: E, 5: F, 6: G, 7: H}
+
 
 impl<T: FromReflect> Typed for Option<T> {
     fn type_info() -> &'static TypeInfo {
@@ -1,4 +1,5 @@
-use wyhash::WyHash as ReflectHasher;
+#[doc(hidden)]
+pub use wyhash::WyHash as ReflectHasher;
 
 extern crate log as glog; // to avoid name collision with my log module
 
@@ -6,27 +7,35 @@ mod type_info_cell;
 
 mod reflect;
 mod from_reflect;
+mod apply;
 
 mod std_traits;
 mod impls;
-mod type_info;
-mod type_registry;
-mod serde;
-
-mod special_traits {
-    pub(crate) mod debug;
-    pub(crate) mod hash;
-    pub(crate) mod partial_eq;
+pub mod type_info;
+pub mod type_registry;
+pub mod serde;
+
+// used by the generated code of the derive macros
+#[doc(hidden)]
+pub mod special_traits {
+    pub mod debug;
+    pub mod hash;
+    pub mod partial_eq;
 }
 
 pub use reflect::{Reflect, ReflectRef, ReflectRefMut, ReflectOwned};
 pub use from_reflect::*;
+pub use apply::ReflectApplyError;
+pub use std_traits::{ReflectDefault, ReflectHash, ReflectPartialEq};
 
 pub use type_info_cell::*;
 pub use type_info::*;
 
 pub use crate::serde::*;
 
+// so that other crates can derive reflection without depending on the derive crate
+pub use raven_reflect_derive::{Reflect, FromReflect};
+
 #[cfg(test)]
 mod tests {
     use std::collections::{VecDeque};
@@ -129,4 +138,366 @@ mod tests {
         assert_eq!(*field_1, String::from("Hello!"));
         assert_eq!(*field_2, vec![-85, 69, 15]);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_reflect_list_mutation() {
+        #[derive(Reflect)]
+        struct Test {
+            values: Vec<u32>,
+            deque: VecDeque<u32>,
+            fixed: [u32; 2],
+        }
+
+        let mut test = Test {
+            values: vec![1, 2],
+            deque: VecDeque::from([3, 4]),
+            fixed: [5, 6],
+        };
+
+        match test.field_mut("values").unwrap().reflect_ref_mut() {
+            ReflectRefMut::List(list) => {
+                list.push(Box::new(3_u32));
+                list.insert(0, Box::new(0_u32));
+                assert_eq!(list.len(), 4);
+            }
+            _ => panic!("Vec<u32> is not reflected as a list"),
+        }
+        assert_eq!(test.values, vec![0, 1, 2, 3]);
+
+        match test.field_mut("values").unwrap().reflect_ref_mut() {
+            ReflectRefMut::List(list) => {
+                assert_eq!(list.remove(1).downcast_ref::<u32>(), Some(&1));
+                assert_eq!(list.pop().unwrap().downcast_ref::<u32>(), Some(&3));
+            }
+            _ => unreachable!(),
+        }
+        assert_eq!(test.values, vec![0, 2]);
+
+        match test.field_mut("deque").unwrap().reflect_ref_mut() {
+            ReflectRefMut::List(list) => {
+                assert_eq!(list.remove(0).downcast_ref::<u32>(), Some(&3));
+                list.push(Box::new(5_u32));
+                assert_eq!(test.deque, VecDeque::from([4, 5]));
+            }
+            _ => panic!("VecDeque<u32> is not reflected as a list"),
+        }
+
+        match test.field_mut("deque").unwrap().reflect_ref_mut() {
+            ReflectRefMut::List(list) => list.clear(),
+            _ => unreachable!(),
+        }
+        assert!(test.deque.is_empty());
+
+        // fixed size arrays can only be mutated in place
+        match test.field_mut("fixed").unwrap().reflect_ref_mut() {
+            ReflectRefMut::Array(array) => {
+                *array.get_mut(1).unwrap().downcast_mut::<u32>().unwrap() = 7;
+                assert!(array.get_mut(2).is_none());
+            }
+            _ => panic!("[u32; 2] is not reflected as an array"),
+        }
+        assert_eq!(test.fixed, [5, 7]);
+    }
+
+    #[test]
+    fn test_assign_checked() {
+        #[derive(Reflect, Debug, PartialEq)]
+        struct Inner {
+            scale: [f32; 3],
+            name: String,
+        }
+
+        #[derive(Reflect, Debug, PartialEq)]
+        enum Shape {
+            Point,
+            Circle(f32),
+        }
+
+        #[derive(Reflect, Debug, PartialEq)]
+        struct Test {
+            a: u32,
+            inner: Inner,
+            shape: Shape,
+        }
+
+        let mut test = Test {
+            a: 1,
+            inner: Inner { scale: [1.0; 3], name: "inner".into() },
+            shape: Shape::Point,
+        };
+
+        // concrete to concrete
+        let other = Test {
+            a: 2,
+            inner: Inner { scale: [2.0; 3], name: "other".into() },
+            shape: Shape::Circle(0.5),
+        };
+        test.assign_checked(&other).unwrap();
+        assert_eq!(test, other);
+
+        // dynamic to concrete
+        let mut dyn_inner = DynamicStruct::default();
+        dyn_inner.add_field("scale", [3.0_f32; 3]);
+        dyn_inner.add_field("name", String::from("dynamic"));
+        let mut dyn_test = DynamicStruct::default();
+        dyn_test.add_field("a", 3_u32);
+        dyn_test.add_field("inner", dyn_inner);
+        dyn_test.add_field("shape", Shape::Point);
+        test.assign_checked(&dyn_test).unwrap();
+        assert_eq!(test.a, 3);
+        assert_eq!(test.inner.name, "dynamic");
+        assert_eq!(test.shape, Shape::Point);
+
+        // wrong field type, nothing is assigned
+        let mut wrong_type = test.clone_dynamic();
+        wrong_type.add_field("a", 4_i64);
+        assert_eq!(
+            test.assign_checked(&wrong_type),
+            Err(ReflectApplyError::MismatchedType {
+                path: "a".into(),
+                expected: std::any::type_name::<u32>(),
+                found: std::any::type_name::<i64>(),
+            })
+        );
+        assert_eq!(test.a, 3);
+
+        // extra field
+        let mut extra = test.clone_dynamic();
+        extra.add_field("b", 0_u32);
+        assert!(matches!(
+            test.assign_checked(&extra),
+            Err(ReflectApplyError::UnknownField { field, .. }) if field == "b"
+        ));
+
+        // missing nested field
+        let mut missing_inner = DynamicStruct::default();
+        missing_inner.add_field("scale", [3.0_f32; 3]);
+        let mut missing = test.clone_dynamic();
+        missing.add_field("inner", missing_inner);
+        assert!(matches!(
+            test.assign_checked(&missing),
+            Err(ReflectApplyError::MissingField { path, field, .. }) if path == "inner" && field == "name"
+        ));
+
+        // wrong array length
+        let mut short_inner = test.inner.clone_dynamic();
+        short_inner.add_field("scale", [3.0_f32; 2]);
+        let mut short = test.clone_dynamic();
+        short.add_field("inner", short_inner);
+        assert!(matches!(
+            test.assign_checked(&short),
+            Err(ReflectApplyError::MismatchedLength { path, expected: 3, found: 2 }) if path == "inner.scale"
+        ));
+
+        // wrong variant field type
+        let mut wrong_variant = test.clone_dynamic();
+        let mut circle = DynamicTuple::default();
+        circle.add_field(1_u32);
+        wrong_variant.add_field("shape", DynamicEnum::new("Shape", "Circle", DynamicVariant::Tuple(circle)));
+        assert!(matches!(
+            test.assign_checked(&wrong_variant),
+            Err(ReflectApplyError::MismatchedType { path, .. }) if path == "shape[0]"
+        ));
+        assert_eq!(test.shape, Shape::Point);
+
+        // unknown variant
+        let mut unknown_variant = test.clone_dynamic();
+        unknown_variant.add_field("shape", DynamicEnum::new("Shape", "Square", DynamicVariant::Unit));
+        assert!(matches!(
+            test.assign_checked(&unknown_variant),
+            Err(ReflectApplyError::UnknownVariant { variant, .. }) if variant == "Square"
+        ));
+    }
+
+    #[test]
+    fn test_construct_default() {
+        use crate::{type_registry::TypeRegistry, std_traits::ReflectDefault};
+
+        #[derive(Reflect, Default, Debug, PartialEq)]
+        #[reflect(Default)]
+        struct TestStruct {
+            a: u32,
+            b: String,
+        }
+
+        #[derive(Reflect)]
+        struct NoDefault {
+            a: u32,
+        }
+
+        let mut registry = TypeRegistry::default();
+        registry.register::<TestStruct>();
+        registry.register::<NoDefault>();
+
+        let by_id = registry.construct_default(std::any::TypeId::of::<TestStruct>()).unwrap();
+        assert_eq!(by_id.downcast_ref::<TestStruct>(), Some(&TestStruct::default()));
+
+        let by_name = registry.construct_default_by_name("TestStruct").unwrap();
+        assert_eq!(by_name.downcast_ref::<TestStruct>(), Some(&TestStruct::default()));
+
+        // registered without ReflectDefault
+        assert!(registry.construct_default(std::any::TypeId::of::<NoDefault>()).is_none());
+        assert!(registry.construct_default_by_name("NoDefault").is_none());
+        // not registered
+        assert!(registry.construct_default_by_name("Unknown").is_none());
+    }
+
+    #[test]
+    fn test_construct_default_enum() {
+        use crate::{type_registry::TypeRegistry, std_traits::ReflectDefault, type_info::Enum};
+
+        #[derive(Reflect, Default, Debug, PartialEq)]
+        #[reflect(Default)]
+        enum TestEnum {
+            First,
+            Second(u32),
+            #[default]
+            Third,
+            Fourth { a: f32 },
+        }
+
+        let mut registry = TypeRegistry::default();
+        registry.register::<TestEnum>();
+
+        let by_id = registry.construct_default(std::any::TypeId::of::<TestEnum>()).unwrap();
+        assert_eq!(by_id.downcast_ref::<TestEnum>(), Some(&TestEnum::Third));
+
+        let by_name = registry.construct_default_by_name("TestEnum").unwrap();
+        let by_name = by_name.downcast_ref::<TestEnum>().unwrap();
+        assert_eq!(by_name, &TestEnum::Third);
+        assert_eq!(by_name.variant_name(), "Third");
+        assert_eq!(by_name.variant_index(), 2);
+    }
+
+    #[test]
+    fn test_construct_from_ptr() {
+        use std::any::TypeId;
+        use raven_core::ptr::{Ptr, OwningPtr};
+        use crate::type_registry::{TypeRegistry, ReflectFromPtr};
+
+        #[derive(Reflect, Debug, PartialEq)]
+        struct TestStruct {
+            a: u32,
+            b: String,
+        }
+
+        let mut registry = TypeRegistry::default();
+        registry.register::<TestStruct>();
+
+        let from_ptr = registry.type_meta::<ReflectFromPtr>(TypeId::of::<TestStruct>()).unwrap();
+        assert_eq!(from_ptr.type_id(), TypeId::of::<TestStruct>());
+
+        let value = TestStruct { a: 3, b: "raven".to_owned() };
+        let reflected = unsafe { registry.reflect_from_ptr(TypeId::of::<TestStruct>(), Ptr::from(&value)) }.unwrap();
+        assert_eq!(reflected.downcast_ref::<TestStruct>(), Some(&value));
+
+        let constructed = OwningPtr::make(value, |ptr| unsafe {
+            registry.construct_from_ptr(TypeId::of::<TestStruct>(), ptr)
+        }).unwrap();
+        let test = constructed.downcast_ref::<TestStruct>().unwrap();
+        assert_eq!(test.a, 3);
+        assert_eq!(test.b, "raven");
+        match constructed.reflect_ref() {
+            ReflectRef::Struct(value) => assert_eq!(value.field("a").unwrap().downcast_ref::<u32>(), Some(&3)),
+            _ => panic!("TestStruct is not reflected as a struct"),
+        }
+
+        // not registered
+        struct Unknown;
+        let unknown = Unknown;
+        assert!(unsafe { registry.reflect_from_ptr(TypeId::of::<Unknown>(), Ptr::from(&unknown)) }.is_none());
+    }
+
+    #[test]
+    fn test_reflect_hash_partial_eq() {
+        use std::any::TypeId;
+        use crate::type_registry::TypeRegistry;
+
+        #[derive(Reflect, Hash, PartialEq)]
+        #[reflect(Hash, PartialEq)]
+        struct Comparable {
+            a: u32,
+            b: String,
+        }
+
+        #[derive(Reflect)]
+        struct Opaque {
+            a: u32,
+        }
+
+        let mut registry = TypeRegistry::default();
+        registry.register::<Comparable>();
+        registry.register::<Opaque>();
+
+        let lhs: Box<dyn Reflect> = Box::new(Comparable { a: 1, b: "raven".to_owned() });
+        let same: Box<dyn Reflect> = Box::new(Comparable { a: 1, b: "raven".to_owned() });
+        let other: Box<dyn Reflect> = Box::new(Comparable { a: 2, b: "raven".to_owned() });
+
+        assert_eq!(lhs.reflect_hash(), same.reflect_hash());
+        assert_ne!(lhs.reflect_hash(), other.reflect_hash());
+        assert_eq!(lhs.reflect_partial_eq(same.as_ref()), Some(true));
+        assert_eq!(lhs.reflect_partial_eq(other.as_ref()), Some(false));
+
+        let reflect_hash = registry.type_meta::<ReflectHash>(TypeId::of::<Comparable>()).unwrap();
+        let reflect_partial_eq = registry.type_meta::<ReflectPartialEq>(TypeId::of::<Comparable>()).unwrap();
+        assert_eq!(reflect_hash.hash(lhs.as_ref()), lhs.reflect_hash());
+        assert_eq!(reflect_partial_eq.partial_eq(lhs.as_ref(), same.as_ref()), Some(true));
+        assert_eq!(reflect_partial_eq.partial_eq(lhs.as_ref(), other.as_ref()), Some(false));
+        assert_eq!(registry.hash_value(lhs.as_ref()), lhs.reflect_hash());
+        assert_eq!(registry.partial_eq_values(lhs.as_ref(), other.as_ref()), Some(false));
+
+        // did not opt into hashing and equality
+        let opaque: Box<dyn Reflect> = Box::new(Opaque { a: 1 });
+        assert!(opaque.reflect_hash().is_none());
+        assert!(registry.type_meta::<ReflectHash>(TypeId::of::<Opaque>()).is_none());
+        assert!(registry.type_meta::<ReflectPartialEq>(TypeId::of::<Opaque>()).is_none());
+        assert!(registry.hash_value(opaque.as_ref()).is_none());
+        assert!(registry.partial_eq_values(opaque.as_ref(), opaque.as_ref()).is_none());
+        // mismatched type
+        assert!(reflect_hash.hash(opaque.as_ref()).is_none());
+    }
+
+    #[test]
+    fn test_export_manifest() {
+        use crate::type_registry::TypeRegistry;
+
+        #[derive(Reflect)]
+        struct Zeta {
+            b: u32,
+            a: f32,
+        }
+
+        #[derive(Reflect)]
+        enum Alpha {
+            Unit,
+            Tuple(u8),
+            Compound { value: i32 },
+        }
+
+        let mut registry = TypeRegistry::empty();
+        registry.register::<Zeta>();
+        registry.register::<Alpha>();
+        registry.register::<u32>();
+
+        let manifest = registry.export_manifest();
+        // stable between calls
+        assert_eq!(manifest, registry.export_manifest());
+
+        // sorted by full type name
+        let alpha = manifest.find("\"short_name\": \"Alpha\"").unwrap();
+        let zeta = manifest.find("\"short_name\": \"Zeta\"").unwrap();
+        let primitive = manifest.find("\"name\": \"u32\"").unwrap();
+        assert!(alpha < zeta && zeta < primitive);
+
+        // fields keep the declaration order
+        let b = manifest.find("{ \"name\": \"b\", \"type\": \"u32\" }").unwrap();
+        let a = manifest.find("{ \"name\": \"a\", \"type\": \"f32\" }").unwrap();
+        assert!(b < a);
+
+        assert!(manifest.contains("\"kind\": \"enum\""));
+        assert!(manifest.contains("\"form\": \"unit\""));
+        assert!(manifest.contains("{ \"index\": 0, \"type\": \"u8\" }"));
+        assert!(manifest.contains("{ \"name\": \"value\", \"type\": \"i32\" }"));
+        assert!(manifest.contains("\"kind\": \"primitive\""));
+    }
+}
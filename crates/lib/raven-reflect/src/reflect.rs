@@ -3,6 +3,7 @@ use std::{fmt::Debug};
 use downcast_rs::{DowncastSync, impl_downcast};
 
 use crate::{type_info::{Struct, PrimitiveTypeInfo}, TypeInfo, special_traits, NonGenericTypeInfoOnceCell, Typed, Enum, Tuple, TupleStruct, List, Map, Array};
+use crate::apply::{self, ReflectApplyError};
 
 /// Wrapper enum to get a immutable reference of reflected data conveniently.
 /// This helper class classify reflected data for user. 
@@ -58,6 +59,16 @@ pub trait Reflect: DowncastSync {
 
     fn assign(&mut self, reflected: &dyn Reflect);
 
+    /// Same as [`Reflect::assign`], but check the structure of `reflected` first.
+    ///
+    /// Returns an error naming the first offending field (e.g. unknown or missing fields, mismatched types)
+    /// instead of ignoring it or panicking, and `self` is left untouched on error.
+    fn assign_checked(&mut self, reflected: &dyn Reflect) -> Result<(), ReflectApplyError> {
+        apply::check_assign(self.as_reflect(), reflected)?;
+        self.assign(reflected);
+        Ok(())
+    }
+
     /// Clones the value as a `Reflect` trait object.
     ///
     /// When deriving `Reflect` for a struct, tuple struct or enum, the value is
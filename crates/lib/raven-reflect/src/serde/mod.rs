@@ -9,21 +9,20 @@ mod visitors;
 mod field_ty_registration;
 
 pub use serializer::ReflectSerializer;
-pub use reflect_ser::{SerializationData, ReflectSerialize};
+pub use reflect_ser::{SerializationData, ReflectSerialize, FieldDefaultFn};
 
 pub use deserializer::{UntypedReflectDeserializer, TypedReflectDeserializer};
 pub use reflect_de::ReflectDeserialize;
 
 #[cfg(test)]
 mod tests {
-    use std::{ops::Range, collections::VecDeque};
+    use std::{ops::Range, collections::VecDeque, time::Duration};
 
     use crate::{self as raven_reflect,
         type_registry::TypeRegistry,
         serde::{ReflectSerializer, deserializer::UntypedReflectDeserializer},
-        Reflect, Typed, std_traits::ReflectDefault
+        Reflect, Typed, FromReflect, Tuple, std_traits::ReflectDefault
     };
-    use raven_reflect_derive::Reflect;
     use ron::ser::PrettyConfig;
     use serde::de::DeserializeSeed;
 
@@ -130,4 +129,183 @@ mod tests {
         default_test.assign(&*reflected);
         println!("Assigned TestEnum: {default_test:#?}");
     }
+
+    #[test]
+    fn test_serialize_tuple() {
+        let mut registry = TypeRegistry::default();
+        registry.register::<(i32, bool, String)>();
+        registry.register::<String>();
+
+        let test_tuple = (-7, true, String::from("Tuple!"));
+
+        assert_eq!(test_tuple.num_fields(), 3);
+        assert_eq!(*test_tuple.field_at(0).unwrap().downcast_ref::<i32>().unwrap(), -7);
+        assert!(*test_tuple.field_at(1).unwrap().downcast_ref::<bool>().unwrap());
+        assert!(test_tuple.field_at(3).is_none());
+
+        let serializer = ReflectSerializer::new(&test_tuple, &registry);
+        let sered_str = ron::ser::to_string_pretty(&serializer, PrettyConfig::default())
+            .expect("Failed to serialize reflected tuple!");
+
+        println!("Reflected:  {:#?}", test_tuple.as_reflect());
+        println!("Serialized: {sered_str}");
+
+        let mut deserializer = ron::de::Deserializer::from_str(&sered_str)
+            .expect("Failed to parse ron!");
+        let reflect_deserializer = UntypedReflectDeserializer::new(&registry);
+        let reflected = reflect_deserializer.deserialize(&mut deserializer)
+            .expect("Failed to deserialize reflected tuple!");
+
+        let from_reflect = <(i32, bool, String) as FromReflect>::from_reflect(&*reflected)
+            .expect("Failed to construct tuple from reflected value!");
+        assert_eq!(from_reflect, test_tuple);
+    }
+
+    #[test]
+    fn test_serialize_duration() {
+        #[derive(Reflect, Debug, PartialEq)]
+        struct TestConfig {
+            cooldown: Duration,
+            count: u32,
+        }
+
+        let mut registry = TypeRegistry::default();
+        registry.register::<TestConfig>();
+
+        let test_config = TestConfig {
+            cooldown: Duration::new(1, 500_000_000),
+            count: 3,
+        };
+
+        let serializer = ReflectSerializer::new(&test_config, &registry);
+        let sered_str = ron::ser::to_string(&serializer)
+            .expect("Failed to serialize reflected struct `TestConfig`!");
+
+        println!("Serialized: {sered_str}");
+        assert!(sered_str.contains("cooldown:(secs:1,nanos:500000000)"));
+
+        let mut deserializer = ron::de::Deserializer::from_str(&sered_str)
+            .expect("Failed to parse ron!");
+        let reflect_deserializer = UntypedReflectDeserializer::new(&registry);
+        let reflected = reflect_deserializer.deserialize(&mut deserializer)
+            .expect("Failed to deserialize reflected struct!");
+
+        let mut deserialized = TestConfig {
+            cooldown: Duration::ZERO,
+            count: 0,
+        };
+        deserialized.assign(&*reflected);
+        assert_eq!(deserialized, test_config);
+    }
+
+    #[test]
+    fn test_deserialize_missing_default_fields() {
+        #[derive(Reflect, Debug, PartialEq)]
+        struct TestSave {
+            name: String,
+            #[reflect(default)]
+            level: u32,
+            #[reflect(default = "get_health_default")]
+            health: f32,
+        }
+
+        fn get_health_default() -> f32 {
+            100.0
+        }
+
+        let mut registry = TypeRegistry::default();
+        registry.register::<TestSave>();
+        registry.register::<String>();
+
+        let test_save = TestSave {
+            name: String::from("Save!"),
+            level: 7,
+            health: 35.0,
+        };
+
+        let serializer = ReflectSerializer::new(&test_save, &registry);
+        let sered_str = ron::ser::to_string(&serializer)
+            .expect("Failed to serialize reflected struct `TestSave`!");
+
+        // simulate an old save file which does not have these fields yet
+        let old_sered_str = sered_str
+            .replace("level:7,", "")
+            .replace(",health:35.0", "");
+        assert!(!old_sered_str.contains("level") && !old_sered_str.contains("health"));
+        println!("Serialized: {old_sered_str}");
+
+        let mut deserializer = ron::de::Deserializer::from_str(&old_sered_str)
+            .expect("Failed to parse ron!");
+        let reflect_deserializer = UntypedReflectDeserializer::new(&registry);
+        let reflected = reflect_deserializer.deserialize(&mut deserializer)
+            .expect("Failed to deserialize reflected struct with missing fields!");
+
+        let mut assigned = TestSave {
+            name: String::new(),
+            level: 1,
+            health: 1.0,
+        };
+        assigned.assign(&*reflected);
+
+        let expected = TestSave {
+            name: String::from("Save!"),
+            level: 0,
+            health: 100.0,
+        };
+        assert_eq!(assigned, expected);
+    }
+
+    #[test]
+    fn test_serialize_skip_if_default() {
+        #[derive(Reflect, Debug, PartialEq)]
+        struct TestLight {
+            name: String,
+            #[reflect(skip_if_default)]
+            intensity: f32,
+            #[reflect(skip_if_default)]
+            #[reflect(default = "get_range_default")]
+            range: f32,
+            #[reflect(skip_if_default)]
+            tag: String,
+        }
+
+        fn get_range_default() -> f32 {
+            10.0
+        }
+
+        let mut registry = TypeRegistry::default();
+        registry.register::<TestLight>();
+        registry.register::<String>();
+
+        let test_light = TestLight {
+            name: String::from("Light!"),
+            intensity: 0.0,
+            range: 10.0,
+            tag: String::from("Sun"),
+        };
+
+        let serializer = ReflectSerializer::new(&test_light, &registry);
+        let sered_str = ron::ser::to_string(&serializer)
+            .expect("Failed to serialize reflected struct `TestLight`!");
+
+        println!("Serialized: {sered_str}");
+        assert!(!sered_str.contains("intensity"));
+        assert!(!sered_str.contains("range"));
+        assert!(sered_str.contains("tag:\"Sun\""));
+
+        let mut deserializer = ron::de::Deserializer::from_str(&sered_str)
+            .expect("Failed to parse ron!");
+        let reflect_deserializer = UntypedReflectDeserializer::new(&registry);
+        let reflected = reflect_deserializer.deserialize(&mut deserializer)
+            .expect("Failed to deserialize reflected struct!");
+
+        let mut deserialized = TestLight {
+            name: String::new(),
+            intensity: 1.0,
+            range: 1.0,
+            tag: String::new(),
+        };
+        deserialized.assign(&*reflected);
+        assert_eq!(deserialized, test_light);
+    }
 }
\ No newline at end of file
@@ -1,4 +1,4 @@
-use std::collections::HashSet;
+use std::collections::{HashSet, HashMap};
 
 use crate::{Reflect, type_registry::{FromType, TypeRegistry}};
 
@@ -62,15 +62,27 @@ impl ReflectSerialize {
     }
 }
 
+/// Constructor of the default value of a field, used to fill the field missing from serialized data.
+pub type FieldDefaultFn = fn() -> Box<dyn Reflect>;
+
 #[derive(Debug, Clone)]
 pub struct SerializationData {
     ignore_indices: HashSet<usize>,
+    field_defaults: HashMap<usize, FieldDefaultFn>,
+    skip_if_default_indices: HashSet<usize>,
 }
 
 impl SerializationData {
-    pub fn new<I: Iterator<Item = usize>>(ignore_indices_iter: I) -> Self {
+    pub fn new<I, D, S>(ignore_indices_iter: I, field_defaults_iter: D, skip_if_default_iter: S) -> Self
+    where
+        I: Iterator<Item = usize>,
+        D: Iterator<Item = (usize, FieldDefaultFn)>,
+        S: Iterator<Item = usize>,
+    {
         Self {
-            ignore_indices: ignore_indices_iter.collect()
+            ignore_indices: ignore_indices_iter.collect(),
+            field_defaults: field_defaults_iter.collect(),
+            skip_if_default_indices: skip_if_default_iter.collect(),
         }
     }
 
@@ -81,4 +93,22 @@ impl SerializationData {
     pub fn num_ignore_fields(&self) -> usize {
         self.ignore_indices.len()
     }
+
+    /// Construct the default value of the field marked with `#[reflect(default)]` or `#[reflect(default = "func")]`.
+    /// Return None if the field is required.
+    pub fn field_default(&self, index: usize) -> Option<Box<dyn Reflect>> {
+        self.field_defaults.get(&index).map(|default_func| default_func())
+    }
+
+    /// Return true if the field is marked with `#[reflect(skip_if_default)]` and the value equals its default value.
+    /// Values can not be compared (i.e. reflect_partial_eq() returns None) are always serialized.
+    pub fn is_skipped_default_field(&self, index: usize, value: &dyn Reflect) -> bool {
+        if !self.skip_if_default_indices.contains(&index) {
+            return false;
+        }
+
+        self.field_default(index)
+            .and_then(|default| value.reflect_partial_eq(default.as_ref()))
+            .unwrap_or(false)
+    }
 }
\ No newline at end of file
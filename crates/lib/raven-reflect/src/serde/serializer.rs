@@ -156,21 +156,23 @@ impl<'a> Serialize for StructSerializer<'a> {
             .registry
             .registration(type_info.type_id())
             .and_then(|registration| registration.type_meta::<SerializationData>());
-        let num_ignore_fields = serialization_data.map(|data| data.num_ignore_fields()).unwrap_or(0);
+
+        // fields which are ignored, or equal to their default values with #[reflect(skip_if_default)], are not serialized
+        let serialized_fields = self.reflected_struct.iter()
+            .enumerate()
+            .filter(|(index, reflected)| {
+                serialization_data
+                    .map(|data| !data.is_ignore_field(*index) && !data.is_skipped_default_field(*index, *reflected))
+                    .unwrap_or(true)
+            })
+            .collect::<Vec<_>>();
 
         let mut state = serializer.serialize_struct(
             struct_info.name(),
-            self.reflected_struct.num_fields() - num_ignore_fields,
+            serialized_fields.len(),
         )?;
 
-        for (index, reflected) in self.reflected_struct.iter().enumerate() {
-            if serialization_data
-                .map(|data| data.is_ignore_field(index))
-                .unwrap_or(false)
-            {
-                continue;
-            }
-
+        for (index, reflected) in serialized_fields {
             let key = struct_info.field_at(index).unwrap().name();
             state.serialize_field(key, &TypedReflectSerializer::new(reflected, self.registry))?;
         }
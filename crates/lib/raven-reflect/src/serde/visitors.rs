@@ -45,7 +45,8 @@ impl<'a, 'de> Visitor<'de> for StructVisitor<'a> {
     where
         A: serde::de::MapAccess<'de>
     {
-        visit_struct(&mut map, self.ty_info, self.registry)
+        let serialization_data = self.registration.type_meta::<SerializationData>();
+        visit_struct(&mut map, self.ty_info, serialization_data, self.registry)
     }
 
     fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
@@ -55,8 +56,8 @@ impl<'a, 'de> Visitor<'de> for StructVisitor<'a> {
         let mut index = 0usize;
         let mut output = DynamicStruct::default();
 
-        let ignored_field_count = self.registration
-            .type_meta::<SerializationData>()
+        let serialization_data = self.registration.type_meta::<SerializationData>();
+        let ignored_field_count = serialization_data
             .map(|data| data.num_ignore_fields())
             .unwrap_or(0);
         let field_len = self.ty_info.num_fields().saturating_sub(ignored_field_count);
@@ -80,6 +81,7 @@ impl<'a, 'de> Visitor<'de> for StructVisitor<'a> {
             }
         }
 
+        fill_default_fields(&mut output, self.ty_info, serialization_data);
         Ok(output)
     }
 }
@@ -409,7 +411,7 @@ impl<'a, 'de> Visitor<'de> for StructVariantVisitor<'a> {
     where
         V: MapAccess<'de>,
     {
-        visit_struct(&mut map, self.struct_info, self.registry)
+        visit_struct(&mut map, self.struct_info, None, self.registry)
     }
 
     fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
@@ -592,6 +594,7 @@ impl<'a, 'de> Visitor<'de> for ArrayVisitor<'a> {
 pub(super) fn visit_struct<'de, T, V>(
     map: &mut V,
     info: &'static T,
+    serialization_data: Option<&SerializationData>,
     registry: &TypeRegistry,
 ) -> Result<DynamicStruct, V::Error>
 where
@@ -615,9 +618,33 @@ where
         dynamic_struct.add_field_boxed(&key, value);
     }
 
+    fill_default_fields(&mut dynamic_struct, info, serialization_data);
     Ok(dynamic_struct)
 }
 
+/// Fill the fields missing from the serialized data with their default values.
+/// Fields without `#[reflect(default)]` are left missing, so that `FromReflect` can still reject them.
+fn fill_default_fields<T: StructLikeTypeInfo>(
+    dynamic_struct: &mut DynamicStruct,
+    info: &T,
+    serialization_data: Option<&SerializationData>,
+) {
+    let serialization_data = match serialization_data {
+        Some(data) => data,
+        None => return,
+    };
+
+    for (index, field) in info.iter().enumerate() {
+        if dynamic_struct.index_of(field.name()).is_some() {
+            continue;
+        }
+
+        if let Some(value) = serialization_data.field_default(index) {
+            dynamic_struct.add_field_boxed(field.name(), value);
+        }
+    }
+}
+
 
 fn visit_tuple<'de, T, V>(
     seq: &mut V,
@@ -7,7 +7,6 @@ pub struct ReflectDefault {
 }
 
 impl ReflectDefault {
-    #[allow(dead_code)]
     pub fn default(&self) -> Box<dyn Reflect> {
         (self.default_func)()
     }
@@ -19,4 +18,48 @@ impl<T: Reflect + Default> FromType<T> for ReflectDefault {
             default_func: || Box::<T>::default(),
         }
     }
-}
\ No newline at end of file
+}
+
+/// Reflect traits to provide [`Hash`](std::hash::Hash) behavior to reflected types,
+/// registered for the types which derive `#[reflect(Hash)]`.
+#[derive(Clone)]
+pub struct ReflectHash {
+    hash_func: fn(&dyn Reflect) -> Option<u64>,
+}
+
+impl ReflectHash {
+    /// Returns the hash of the value, None() if the value is not of the registered type.
+    pub fn hash(&self, value: &dyn Reflect) -> Option<u64> {
+        (self.hash_func)(value)
+    }
+}
+
+impl<T: Reflect> FromType<T> for ReflectHash {
+    fn from_type() -> Self {
+        ReflectHash {
+            hash_func: |value| value.downcast_ref::<T>().and_then(|value| value.reflect_hash()),
+        }
+    }
+}
+
+/// Reflect traits to provide [`PartialEq`] behavior to reflected types,
+/// registered for the types which derive `#[reflect(PartialEq)]`.
+#[derive(Clone)]
+pub struct ReflectPartialEq {
+    partial_eq_func: fn(&dyn Reflect, &dyn Reflect) -> Option<bool>,
+}
+
+impl ReflectPartialEq {
+    /// Compares two values, None() if `lhs` is not of the registered type.
+    pub fn partial_eq(&self, lhs: &dyn Reflect, rhs: &dyn Reflect) -> Option<bool> {
+        (self.partial_eq_func)(lhs, rhs)
+    }
+}
+
+impl<T: Reflect> FromType<T> for ReflectPartialEq {
+    fn from_type() -> Self {
+        ReflectPartialEq {
+            partial_eq_func: |lhs, rhs| lhs.downcast_ref::<T>().and_then(|lhs| lhs.reflect_partial_eq(rhs)),
+        }
+    }
+}
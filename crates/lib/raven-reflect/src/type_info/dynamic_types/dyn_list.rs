@@ -111,6 +111,11 @@ impl List for DynamicList {
         self.values.pop()
     }
 
+    #[inline]
+    fn clear(&mut self) {
+        self.values.clear()
+    }
+
     fn clone_dynamic(&self) -> DynamicList {
         DynamicList {
             name: self.name.clone(),
@@ -131,15 +131,7 @@ impl Reflect for DynamicTuple {
 
     #[inline]
     fn assign(&mut self, reflected: &dyn Reflect) {
-        if let ReflectRef::Tuple(tuple_value) = reflected.reflect_ref() {
-            for (i, value) in tuple_value.iter().enumerate() {
-                if let Some(v) = self.field_at_mut(i) {
-                    v.assign(value);
-                }
-            }
-        } else {
-            panic!("Attempted to assign non-tuple type to tuple type.");
-        }
+        tuple_assign(self, reflected);
     }
 
     fn reflect_ref<'a>(&'a self) -> ReflectRef<'a> {
@@ -165,4 +157,17 @@ impl Reflect for DynamicTuple {
         debug::tuple_debug(self, f)?;
         write!(f, ")")
     }
-}
\ No newline at end of file
+}
+
+#[inline]
+pub(crate) fn tuple_assign(lhs: &mut dyn Tuple, rhs: &dyn Reflect) {
+    if let ReflectRef::Tuple(tuple_value) = rhs.reflect_ref() {
+        for (i, value) in tuple_value.iter().enumerate() {
+            if let Some(v) = lhs.field_at_mut(i) {
+                v.assign(value);
+            }
+        }
+    } else {
+        panic!("Attempted to assign non-tuple type to tuple type.");
+    }
+}
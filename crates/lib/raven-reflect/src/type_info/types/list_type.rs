@@ -23,10 +23,20 @@ pub trait List: Reflect + Array {
     /// Panics if `index` is out of bounds.
     fn remove(&mut self, index: usize) -> Box<dyn Reflect>;
 
+    /// Appends an element to the back of the list.
+    /// 
+    /// # Panics
+    /// Panics if the value can not be converted into the element type.
     fn push(&mut self, value: Box<dyn Reflect>);
 
+    /// Removes the last element and returns it, `None` if the list is empty.
     fn pop(&mut self) -> Option<Box<dyn Reflect>>;
 
+    /// Removes all the elements in the list.
+    fn clear(&mut self) {
+        while self.pop().is_some() {}
+    }
+
     /// Clones the list, producing a [`DynamicList`].
     fn clone_dynamic(&self) -> DynamicList {
         DynamicList::new(
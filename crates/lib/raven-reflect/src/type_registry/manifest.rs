@@ -0,0 +1,150 @@
+use std::fmt::Write;
+
+use crate::{TypeInfo, VariantInfo, NamedField, UnnamedField};
+
+use super::{TypeRegistry, TypeRegistration};
+
+impl TypeRegistry {
+    /// Export all the registered types and their layouts as a json manifest,
+    /// so that external tools (e.g. the editor) can inspect the types without compiling against the engine.
+    ///
+    /// Types are sorted by their full names and fields keep their declaration order, so the output is stable between runs.
+    /// Types are referred by names instead of TypeId, because TypeId is not stable across builds.
+    pub fn export_manifest(&self) -> String {
+        let mut registrations = self.iter().collect::<Vec<_>>();
+        registrations.sort_by_key(|registration| registration.type_name());
+
+        let mut out = String::from("{\n  \"types\": [");
+        for (i, registration) in registrations.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            out.push_str("\n    {");
+            write_type(&mut out, registration);
+            out.push_str("\n    }");
+        }
+        if !registrations.is_empty() {
+            out.push_str("\n  ");
+        }
+        out.push_str("]\n}\n");
+        out
+    }
+}
+
+const TYPE_INDENT: &str = "\n      ";
+const VARIANT_INDENT: &str = "\n          ";
+
+fn write_type(out: &mut String, registration: &TypeRegistration) {
+    let _ = write!(out, "{}\"name\": {},", TYPE_INDENT, json_string(registration.type_name()));
+    let _ = write!(out, "{}\"short_name\": {},", TYPE_INDENT, json_string(registration.short_name()));
+
+    match registration.type_info() {
+        TypeInfo::Struct(info) => {
+            write_kind(out, "struct");
+            write_named_fields(out, TYPE_INDENT, info.iter());
+        }
+        TypeInfo::TupleStruct(info) => {
+            write_kind(out, "tuple_struct");
+            write_unnamed_fields(out, TYPE_INDENT, info.iter());
+        }
+        TypeInfo::Tuple(info) => {
+            write_kind(out, "tuple");
+            write_unnamed_fields(out, TYPE_INDENT, info.iter());
+        }
+        TypeInfo::Enum(info) => {
+            write_kind(out, "enum");
+            let _ = write!(out, ",{}\"variants\": [", TYPE_INDENT);
+            for (i, variant) in info.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_variant(out, variant);
+            }
+            if info.num_variants() > 0 {
+                out.push_str(TYPE_INDENT);
+            }
+            out.push(']');
+        }
+        TypeInfo::Array(info) => {
+            write_kind(out, "array");
+            let _ = write!(out, ",{}\"item\": {}", TYPE_INDENT, json_string(info.item_type_name()));
+            let _ = write!(out, ",{}\"capacity\": {}", TYPE_INDENT, info.capacity());
+        }
+        TypeInfo::List(info) => {
+            write_kind(out, "list");
+            let _ = write!(out, ",{}\"item\": {}", TYPE_INDENT, json_string(info.item_type_name()));
+        }
+        TypeInfo::Map(info) => {
+            write_kind(out, "map");
+            let _ = write!(out, ",{}\"key\": {}", TYPE_INDENT, json_string(info.key_type_name()));
+            let _ = write!(out, ",{}\"value\": {}", TYPE_INDENT, json_string(info.value_type_name()));
+        }
+        TypeInfo::Primitive(_) => write_kind(out, "primitive"),
+        TypeInfo::Dynamic(_) => write_kind(out, "dynamic"),
+    }
+}
+
+fn write_kind(out: &mut String, kind: &str) {
+    let _ = write!(out, "{}\"kind\": {}", TYPE_INDENT, json_string(kind));
+}
+
+fn write_variant(out: &mut String, variant: &VariantInfo) {
+    let _ = write!(out, "{}  {{{}\"name\": {}", TYPE_INDENT, VARIANT_INDENT, json_string(variant.name()));
+    match variant {
+        VariantInfo::Struct(info) => {
+            let _ = write!(out, ",{}\"form\": \"struct\"", VARIANT_INDENT);
+            write_named_fields(out, VARIANT_INDENT, info.iter());
+        }
+        VariantInfo::Tuple(info) => {
+            let _ = write!(out, ",{}\"form\": \"tuple\"", VARIANT_INDENT);
+            write_unnamed_fields(out, VARIANT_INDENT, info.iter());
+        }
+        VariantInfo::Unit(_) => {
+            let _ = write!(out, ",{}\"form\": \"unit\"", VARIANT_INDENT);
+        }
+    }
+    let _ = write!(out, "{}  }}", TYPE_INDENT);
+}
+
+fn write_named_fields<'a>(out: &mut String, indent: &str, fields: impl Iterator<Item = &'a NamedField>) {
+    let fields = fields
+        .map(|field| format!("{{ \"name\": {}, \"type\": {} }}", json_string(field.name()), json_string(field.type_name())))
+        .collect::<Vec<_>>();
+    write_fields(out, indent, &fields);
+}
+
+fn write_unnamed_fields<'a>(out: &mut String, indent: &str, fields: impl Iterator<Item = &'a UnnamedField>) {
+    let fields = fields
+        .map(|field| format!("{{ \"index\": {}, \"type\": {} }}", field.index(), json_string(field.type_name())))
+        .collect::<Vec<_>>();
+    write_fields(out, indent, &fields);
+}
+
+fn write_fields(out: &mut String, indent: &str, fields: &[String]) {
+    let _ = write!(out, ",{}\"fields\": [", indent);
+    for (i, field) in fields.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        let _ = write!(out, "{}  {}", indent, field);
+    }
+    if !fields.is_empty() {
+        out.push_str(indent);
+    }
+    out.push(']');
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => { let _ = write!(out, "\\u{:04x}", c as u32); }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
@@ -1,6 +1,7 @@
 mod storage;
 mod source;
 mod registry;
+mod manifest;
 
 pub use storage::*;
 pub use source::*;
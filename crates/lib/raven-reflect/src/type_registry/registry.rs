@@ -1,8 +1,11 @@
-use std::{collections::{HashMap, HashSet}, any::TypeId};
+use std::{collections::{HashMap, HashSet}, any::TypeId, time::Duration};
 
-use crate::{Reflect};
+use raven_core::ptr::{Ptr, OwningPtr};
+use raven_math::{Vec2, Vec3, Vec4, Quat, Mat3, Mat4, Affine3A};
 
-use super::{TypeRegistration, GetTypeRegistration, TypeMeta, FromType};
+use crate::{Reflect, std_traits::{ReflectDefault, ReflectHash, ReflectPartialEq}};
+
+use super::{TypeRegistration, GetTypeRegistration, TypeMeta, FromType, ReflectFromPtr};
 
 /// Registry for all reflected types.
 pub struct TypeRegistry {
@@ -31,7 +34,7 @@ impl TypeRegistry {
         }
     }
 
-    /// Create a type registry with default registrations for primitive types.
+    /// Create a type registry with default registrations for primitive types and math types.
     pub fn new() -> Self {
         let mut registry = Self::empty();
         registry.register::<char>();
@@ -50,6 +53,14 @@ impl TypeRegistry {
         registry.register::<isize>();
         registry.register::<f32>();
         registry.register::<f64>();
+        registry.register::<Duration>();
+        registry.register::<Vec2>();
+        registry.register::<Vec3>();
+        registry.register::<Vec4>();
+        registry.register::<Quat>();
+        registry.register::<Mat3>();
+        registry.register::<Mat4>();
+        registry.register::<Affine3A>();
         registry
     }
 
@@ -100,6 +111,11 @@ impl TypeRegistry {
         data.insert(D::from_type());
     }
 
+    /// Iterate over all the type registrations, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = &TypeRegistration> {
+        self.registrations.values()
+    }
+
     /// Get type registration for certain type id immutably.
     pub fn registration(&self, type_id: TypeId) -> Option<&TypeRegistration> {
         self.registrations.get(&type_id)
@@ -153,6 +169,59 @@ impl TypeRegistry {
             .and_then(|registration| registration.type_meta::<D>())
     }
 
+    /// Construct a default value of the type with its registered ReflectDefault,
+    /// None() if the type doesn't exist or doesn't register ReflectDefault.
+    pub fn construct_default(&self, type_id: TypeId) -> Option<Box<dyn Reflect>> {
+        self.type_meta::<ReflectDefault>(type_id)
+            .map(|reflect_default| reflect_default.default())
+    }
+
+    /// Construct a default value of the type by its short name (or full name if the short name is ambiguous),
+    /// None() if the type doesn't exist or doesn't register ReflectDefault.
+    pub fn construct_default_by_name(&self, type_name: &str) -> Option<Box<dyn Reflect>> {
+        self.registration_with_short_name(type_name)
+            .or_else(|| self.registration_with_full_name(type_name))
+            .and_then(|registration| registration.type_meta::<ReflectDefault>())
+            .map(|reflect_default| reflect_default.default())
+    }
+
+    /// Hash a reflected value with its registered ReflectHash,
+    /// None() if the type of the value doesn't exist or didn't opt into hashing.
+    pub fn hash_value(&self, value: &dyn Reflect) -> Option<u64> {
+        self.type_meta::<ReflectHash>(value.type_id())
+            .and_then(|reflect_hash| reflect_hash.hash(value))
+    }
+
+    /// Compare two reflected values with the registered ReflectPartialEq of `lhs`,
+    /// None() if the type of `lhs` doesn't exist or didn't opt into equality.
+    pub fn partial_eq_values(&self, lhs: &dyn Reflect, rhs: &dyn Reflect) -> Option<bool> {
+        self.type_meta::<ReflectPartialEq>(lhs.type_id())
+            .and_then(|reflect_partial_eq| reflect_partial_eq.partial_eq(lhs, rhs))
+    }
+
+    /// Reinterpret a type-erased pointer as a reflected value of the type with its registered ReflectFromPtr,
+    /// None() if the type doesn't exist or doesn't register ReflectFromPtr.
+    ///
+    /// # Safety
+    ///
+    /// `val` must point to a valid value of the type of `type_id`.
+    pub unsafe fn reflect_from_ptr<'a>(&self, type_id: TypeId, val: Ptr<'a>) -> Option<&'a dyn Reflect> {
+        self.type_meta::<ReflectFromPtr>(type_id)
+            .map(|from_ptr| from_ptr.as_reflect_ptr(val))
+    }
+
+    /// Move a type-erased value into a boxed reflected value of the type with its registered ReflectFromPtr,
+    /// None() if the type doesn't exist or doesn't register ReflectFromPtr, in which case the value is left untouched.
+    ///
+    /// # Safety
+    ///
+    /// `val` must point to a valid value of the type of `type_id`, and the value must not be used or dropped again
+    /// if Some() is returned.
+    pub unsafe fn construct_from_ptr(&self, type_id: TypeId, val: OwningPtr<'_>) -> Option<Box<dyn Reflect>> {
+        self.type_meta::<ReflectFromPtr>(type_id)
+            .map(|from_ptr| from_ptr.from_owning_ptr(val))
+    }
+
     /// Return Some() typed meta by its type id,
     /// None() if this type doesn't exist.
     pub fn type_meta_mut<D: TypeMeta>(&mut self, type_id: TypeId) -> Option<&mut D> {
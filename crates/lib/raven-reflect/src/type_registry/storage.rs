@@ -2,7 +2,7 @@ use std::{collections::HashMap, any::TypeId, fmt::Debug};
 
 use downcast_rs::{Downcast, impl_downcast};
 
-use raven_core::ptr::{Ptr, PtrMut};
+use raven_core::ptr::{Ptr, PtrMut, OwningPtr};
 
 use crate::{TypeInfo, Reflect, Typed, type_info_cell};
 
@@ -115,6 +115,7 @@ pub struct ReflectFromPtr {
     type_id: TypeId,
     to_reflect: for<'a> unsafe fn(Ptr<'a>) -> &'a dyn Reflect,
     to_reflect_mut: for<'a> unsafe fn(PtrMut<'a>) -> &'a mut dyn Reflect,
+    to_reflect_owned: for<'a> unsafe fn(OwningPtr<'a>) -> Box<dyn Reflect>,
 }
 
 impl ReflectFromPtr {
@@ -141,6 +142,18 @@ impl ReflectFromPtr {
     pub unsafe fn as_reflect_ptr_mut<'a>(&self, val: PtrMut<'a>) -> &'a mut dyn Reflect {
         (self.to_reflect_mut)(val)
     }
+
+    /// Moves the value out of the type-erased pointer into a boxed reflected value.
+    /// The pointed memory is not freed, it is only read from.
+    ///
+    /// # Safety
+    ///
+    /// `val` must be a pointer to a value of the type that the [`ReflectFromPtr`] was constructed for,
+    /// and the value must not be used or dropped again after this call.
+    /// This can be verified by checking that the type id returned by [`ReflectFromPtr::type_id`] is the expected one.
+    pub unsafe fn from_owning_ptr(&self, val: OwningPtr<'_>) -> Box<dyn Reflect> {
+        (self.to_reflect_owned)(val)
+    }
 }
 
 impl<T: Reflect> FromType<T> for ReflectFromPtr {
@@ -157,6 +170,10 @@ impl<T: Reflect> FromType<T> for ReflectFromPtr {
                 // and `as_reflect_ptr_mut`, where the caller promises to call it with type `T`
                 unsafe { ptr.deref_mut::<T>() as &mut dyn Reflect }
             },
+            to_reflect_owned: |ptr| {
+                // SAFE: only called from `from_owning_ptr`, where the caller promises to call it with type `T`
+                Box::new(unsafe { ptr.read::<T>() })
+            },
         }
     }
 }
\ No newline at end of file
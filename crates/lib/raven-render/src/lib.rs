@@ -2,14 +2,17 @@ extern crate log as glog; // to avoid name collision with my log module
 
 mod renderer;
 
-pub use renderer::mesh_renderer::{MeshRenderer, MeshRasterScheme, MeshShadingContext, MeshHandle, MeshInstanceHandle};
-pub use renderer::light_renderer::{LightRenderer};
-pub use renderer::sky_renderer::{SkyRenderer};
+pub use renderer::mesh_renderer::{MeshRenderer, MeshRasterScheme, MeshShadingContext, MeshHandle, MeshInstanceHandle, MaterialOverride, MeshLodSettings};
+pub use renderer::draw_data_allocator::DrawDataStats;
+pub use renderer::light_renderer::{LightRenderer, DirectionalLight, LightHandle};
+pub use renderer::light_units::{self, LightUnit};
+pub use renderer::sky_renderer::{SkyRenderer, SkyMode};
 pub use renderer::ibl_renderer::{IblRenderer};
+pub use renderer::env_capture_renderer::{EnvCaptureRenderer, EnvCubemapHandle, EnvCaptureMode};
 
 pub use renderer::debug_renderer::{DebugRenderer};
 
 mod auto_exposure;
 mod world_renderer;
 
-pub use world_renderer::{WorldRenderer, RenderMode};
\ No newline at end of file
+pub use world_renderer::{WorldRenderer, RenderMode, RenderFeature, RenderFeatures, CameraId, Viewport, FrameRenderTargets};
\ No newline at end of file
@@ -12,10 +12,34 @@ use raven_rhi::{backend::{
 }, Rhi};
 
 const MAX_DEBUG_AABBS: usize = 32;
+/// Must fit in the max dynamic storage buffer range.
+const MAX_DEBUG_LINE_VERTICES: usize = 1024 * 1024 / std::mem::size_of::<DebugLineVertex>();
+
+/// Same in debug/debug_draw_colored_lines.hlsl
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct DebugLineVertex {
+    position: [f32; 4],
+    color: [f32; 4],
+}
+
+impl DebugLineVertex {
+    #[inline]
+    fn new(position: Vec3, color: Vec3) -> Self {
+        Self {
+            position: position.extend(1.0).to_array(),
+            color: color.extend(1.0).to_array(),
+        }
+    }
+}
 
 pub struct DebugRenderer {
     debug_aabbs: Vec<AABB>,
 
+    /// Lines drawn only in the current frame.
+    lines: Vec<DebugLineVertex>,
+    overlay_lines: Vec<DebugLineVertex>,
+
     draw_data_buffer: Arc<Buffer>,
     line_lists_buffers: Vec<Arc<Buffer>>,
 
@@ -52,6 +76,9 @@ impl DebugRenderer {
         Self {
             debug_aabbs: Vec::new(),
 
+            lines: Vec::new(),
+            overlay_lines: Vec::new(),
+
             draw_data_buffer: Arc::new(vb_buffer),
             line_lists_buffers: Vec::new(),
 
@@ -86,6 +113,40 @@ impl DebugRenderer {
         self.debug_aabbs.clear();
     }
 
+    /// Draw a line in the current frame.
+    /// If depth_test is false, the line is drawn on top of everything.
+    pub fn add_line(&mut self, start: Vec3, end: Vec3, color: Vec3, depth_test: bool) {
+        let lines = if depth_test { &mut self.lines } else { &mut self.overlay_lines };
+
+        lines.push(DebugLineVertex::new(start, color));
+        lines.push(DebugLineVertex::new(end, color));
+    }
+
+    /// Draw line lists (every two points form a line) in the current frame.
+    pub fn add_line_lists(&mut self, line_lists: &[Vec3], color: Vec3, depth_test: bool) {
+        assert!(line_lists.len() % 2 == 0);
+
+        let lines = if depth_test { &mut self.lines } else { &mut self.overlay_lines };
+        lines.extend(line_lists.iter().map(|point| DebugLineVertex::new(*point, color)));
+    }
+
+    /// Draw the 12 edges of the AABB in the current frame.
+    pub fn add_aabb_lines(&mut self, aabb: &AABB, color: Vec3, depth_test: bool) {
+        let center = aabb.get_center();
+        let extent = aabb.get_extent();
+
+        let line_lists = generate_debug_box_vb_data().into_iter()
+            .map(|unit| center + unit * extent)
+            .collect::<Vec<_>>();
+        self.add_line_lists(&line_lists, color, depth_test);
+    }
+
+    /// Remove all the lines of the current frame.
+    pub fn clear_lines(&mut self) {
+        self.lines.clear();
+        self.overlay_lines.clear();
+    }
+
     pub fn prepare_rg(&mut self,
         rg: &mut RenderGraphBuilder,
         input: &mut RgHandle<Image>,
@@ -243,6 +304,118 @@ impl DebugRenderer {
 
             Ok(())
         });
+
+        self.prepare_rg_lines(rg, input, depth);
+    }
+
+    fn prepare_rg_lines(&mut self,
+        rg: &mut RenderGraphBuilder,
+        input: &mut RgHandle<Image>,
+        depth: &mut RgHandle<Image>
+    ) {
+        if self.lines.is_empty() && self.overlay_lines.is_empty() {
+            return;
+        }
+
+        let mut vertices = std::mem::take(&mut self.lines);
+        let depth_tested_count = vertices.len();
+        vertices.append(&mut self.overlay_lines);
+
+        if vertices.len() > MAX_DEBUG_LINE_VERTICES {
+            glog::warn!("Too many debug lines ({} vertices), only {} vertices will be drawn!", vertices.len(), MAX_DEBUG_LINE_VERTICES);
+            vertices.truncate(MAX_DEBUG_LINE_VERTICES);
+        }
+        let depth_tested_count = depth_tested_count.min(vertices.len()) as u32;
+        let overlay_count = vertices.len() as u32 - depth_tested_count;
+
+        let mut pass = rg.add_pass("debug lines");
+        let shaders = [
+            PipelineShaderDesc::builder()
+                .source("debug/debug_draw_colored_lines.hlsl")
+                .stage(PipelineShaderStage::Vertex)
+                .entry("vs_main")
+                .build().unwrap(),
+            PipelineShaderDesc::builder()
+                .source("debug/debug_draw_colored_lines.hlsl")
+                .stage(PipelineShaderStage::Pixel)
+                .entry("ps_main")
+                .build().unwrap(),
+        ];
+        let depth_tested_pipeline = pass.register_raster_pipeline(
+            &shaders,
+            RasterPipelineDesc::builder()
+                .render_pass(self.renderpass.clone())
+                .cull_mode(RasterPipelineCullMode::None)
+                .depth_write(false)
+                .topology(RasterPipelinePrimitiveTopology::LineList)
+                .build().unwrap()
+        );
+        let overlay_pipeline = pass.register_raster_pipeline(
+            &shaders,
+            RasterPipelineDesc::builder()
+                .render_pass(self.renderpass.clone())
+                .cull_mode(RasterPipelineCullMode::None)
+                .depth_test(false)
+                .depth_write(false)
+                .topology(RasterPipelinePrimitiveTopology::LineList)
+                .build().unwrap()
+        );
+
+        let depth_ref = pass.raster_write(depth, AccessType::DepthAttachmentWriteStencilReadOnly);
+        let input_ref = pass.raster_write(input, AccessType::ColorAttachmentWrite);
+
+        let renderpass = self.renderpass.clone();
+        let extent = input.desc().extent;
+        let extent = [extent[0], extent[1]];
+
+        pass.render(move |ctx| {
+            let vertices_offset = ctx.global_dynamic_buffer().push_from_iter(vertices.into_iter());
+
+            ctx.begin_render_pass(
+                &renderpass,
+                extent,
+                &[
+                    (input_ref, &ImageViewDesc::default()),
+                ],
+                Some((
+                    depth_ref,
+                    &ImageViewDesc::builder().aspect_mask(vk::ImageAspectFlags::DEPTH).build().unwrap()
+                ))
+            )?;
+            ctx.set_default_viewport_and_scissor(extent);
+
+            // depth tested lines come first in the buffer, then the overlay lines
+            for (pipeline, vertex_count, first_vertex) in [
+                (depth_tested_pipeline, depth_tested_count, 0),
+                (overlay_pipeline, overlay_count, depth_tested_count),
+            ] {
+                if vertex_count == 0 {
+                    continue;
+                }
+
+                ctx.bind_raster_pipeline(pipeline.into_bindings()
+                    .descriptor_set(0, &[
+                        RenderGraphPassBinding::DynamicStorageBuffer(vertices_offset),
+                    ])
+                )?;
+
+                let raw_device = &ctx.device().raw;
+
+                unsafe {
+                    raw_device.cmd_draw(
+                        ctx.cb.raw,
+                        vertex_count,
+                        1,
+                        first_vertex,
+                        0
+                    );
+                }
+            }
+
+            ctx.end_render_pass();
+
+            Ok(())
+        });
     }
 
     pub fn clean(self) {
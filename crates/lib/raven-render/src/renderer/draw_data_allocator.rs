@@ -0,0 +1,314 @@
+/// Fragmentation statistics of the draw data buffer.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct DrawDataStats {
+    /// Size of the draw data buffer in bytes.
+    pub capacity: u64,
+    /// Bytes allocated by the uploaded meshes.
+    pub used: u64,
+    /// Bytes freed by the removed meshes, but still possibly read by the frames in flight.
+    pub pending_free: u64,
+    /// Number of the free regions, one if there is no hole.
+    pub free_region_count: usize,
+    /// Size of the largest free region, i.e. the largest mesh data which can be uploaded without compaction.
+    pub largest_free_region: u64,
+    /// 0.0 if all the free space is one region, and approaches 1.0 as the free space is scattered into small holes.
+    pub fragmentation: f32,
+}
+
+/// First-fit free-list suballocator of the draw data buffer.
+///
+/// Freed regions are merged with their neighbors, and are only reused once the frames in flight
+/// which may still read them are done.
+pub(crate) struct DrawDataAllocator {
+    capacity: u64,
+    alignment: u64,
+    /// Free regions as (offset, size), sorted by offset and never adjacent to each other.
+    free_regions: Vec<(u64, u64)>,
+    /// Regions freed in the frame number, as (frame number, offset, size).
+    pending_frees: Vec<(u64, u64, u64)>,
+    used: u64,
+}
+
+impl DrawDataAllocator {
+    pub fn new(capacity: u64, alignment: u64) -> Self {
+        assert!(alignment.is_power_of_two());
+
+        Self {
+            capacity,
+            alignment,
+            free_regions: vec![(0, capacity)],
+            pending_frees: Vec::new(),
+            used: 0,
+        }
+    }
+
+    #[inline]
+    pub fn align_size(&self, size: u64) -> u64 {
+        (size + self.alignment - 1) & !(self.alignment - 1)
+    }
+
+    /// Allocate a region of the size (aligned to the allocator alignment) and return its offset.
+    /// Returns None if no free region is large enough, the data may still fit after compaction.
+    pub fn allocate(&mut self, size: u64) -> Option<u64> {
+        let size = self.align_size(size);
+        let idx = self.free_regions.iter().position(|(_, region_size)| *region_size >= size)?;
+
+        let (offset, region_size) = self.free_regions[idx];
+        if region_size == size {
+            self.free_regions.remove(idx);
+        } else {
+            self.free_regions[idx] = (offset + size, region_size - size);
+        }
+
+        self.used += size;
+        Some(offset)
+    }
+
+    /// Free the region once the frame number is completed in the GPU.
+    pub fn free(&mut self, offset: u64, size: u64, frame_number: u64) {
+        let size = self.align_size(size);
+        self.used -= size;
+        self.pending_frees.push((frame_number, offset, size));
+    }
+
+    /// Make the regions freed up to (and including) the completed frame number available for allocation.
+    pub fn reclaim(&mut self, completed_frame_number: u64) {
+        let mut idx = 0;
+        while idx < self.pending_frees.len() {
+            let (frame_number, offset, size) = self.pending_frees[idx];
+
+            if frame_number <= completed_frame_number {
+                self.pending_frees.swap_remove(idx);
+                self.insert_free_region(offset, size);
+            } else {
+                idx += 1;
+            }
+        }
+    }
+
+    /// Pack the live regions to the start of the buffer.
+    /// `live_regions` are (offset, size) of all the allocated regions, returns the new offset of each of them in the same order.
+    /// All the pending frees are reclaimed, so the caller must make sure the GPU is not using the buffer anymore.
+    pub fn compact(&mut self, live_regions: &[(u64, u64)]) -> Vec<u64> {
+        let mut order = (0..live_regions.len()).collect::<Vec<_>>();
+        order.sort_by_key(|idx| live_regions[*idx].0);
+
+        // regions only move towards the start of the buffer, so they never pass each other
+        let mut new_offsets = vec![0; live_regions.len()];
+        let mut offset = 0;
+        for idx in order {
+            new_offsets[idx] = offset;
+            offset += self.align_size(live_regions[idx].1);
+        }
+
+        self.pending_frees.clear();
+        self.used = offset;
+        self.free_regions.clear();
+        if offset < self.capacity {
+            self.free_regions.push((offset, self.capacity - offset));
+        }
+
+        new_offsets
+    }
+
+    /// Forget all the allocations, the caller must make sure the GPU is not using the buffer anymore.
+    pub fn reset(&mut self) {
+        self.free_regions = vec![(0, self.capacity)];
+        self.pending_frees.clear();
+        self.used = 0;
+    }
+
+    pub fn stats(&self) -> DrawDataStats {
+        let total_free: u64 = self.free_regions.iter().map(|(_, size)| size).sum();
+        let largest_free_region = self.free_regions.iter().map(|(_, size)| *size).max().unwrap_or(0);
+
+        DrawDataStats {
+            capacity: self.capacity,
+            used: self.used,
+            pending_free: self.pending_frees.iter().map(|(_, _, size)| size).sum(),
+            free_region_count: self.free_regions.len(),
+            largest_free_region,
+            fragmentation: if total_free == 0 {
+                0.0
+            } else {
+                1.0 - largest_free_region as f32 / total_free as f32
+            },
+        }
+    }
+
+    fn insert_free_region(&mut self, offset: u64, size: u64) {
+        let idx = self.free_regions.partition_point(|(region_offset, _)| *region_offset < offset);
+
+        let merge_prev = idx > 0 && {
+            let (prev_offset, prev_size) = self.free_regions[idx - 1];
+            prev_offset + prev_size == offset
+        };
+        let merge_next = idx < self.free_regions.len() && offset + size == self.free_regions[idx].0;
+
+        match (merge_prev, merge_next) {
+            (true, true) => {
+                let (_, next_size) = self.free_regions.remove(idx);
+                self.free_regions[idx - 1].1 += size + next_size;
+            }
+            (true, false) => self.free_regions[idx - 1].1 += size,
+            (false, true) => {
+                let next = &mut self.free_regions[idx];
+                *next = (offset, size + next.1);
+            }
+            (false, false) => self.free_regions.insert(idx, (offset, size)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DrawDataAllocator, DrawDataStats};
+
+    #[test]
+    fn test_allocate_aligned() {
+        let mut allocator = DrawDataAllocator::new(1024, 16);
+
+        assert_eq!(allocator.align_size(0), 0);
+        assert_eq!(allocator.align_size(1), 16);
+        assert_eq!(allocator.align_size(16), 16);
+        assert_eq!(allocator.align_size(17), 32);
+
+        // sizes are rounded up to the alignment, so the offsets stay aligned
+        assert_eq!(allocator.allocate(10), Some(0));
+        assert_eq!(allocator.allocate(20), Some(16));
+        assert_eq!(allocator.allocate(16), Some(48));
+        assert_eq!(allocator.stats().used, 64);
+
+        // too large for the remaining space
+        assert_eq!(allocator.allocate(1024), None);
+        assert_eq!(allocator.allocate(1024 - 64), Some(64));
+        assert_eq!(allocator.allocate(1), None);
+    }
+
+    #[test]
+    fn test_free_is_deferred_until_reclaimed() {
+        let mut allocator = DrawDataAllocator::new(64, 16);
+        let a = allocator.allocate(32).unwrap();
+        let _b = allocator.allocate(32).unwrap();
+
+        allocator.free(a, 32, 5);
+        let stats = allocator.stats();
+        assert_eq!(stats.used, 32);
+        assert_eq!(stats.pending_free, 32);
+        // the freed region may still be read by the frames in flight
+        assert_eq!(allocator.allocate(16), None);
+
+        allocator.reclaim(4);
+        assert_eq!(allocator.allocate(16), None);
+
+        allocator.reclaim(5);
+        assert_eq!(allocator.stats().pending_free, 0);
+        assert_eq!(allocator.allocate(16), Some(a));
+    }
+
+    #[test]
+    fn test_reclaim_coalesces_neighbors() {
+        let mut allocator = DrawDataAllocator::new(64, 16);
+        let offsets = (0..4).map(|_| allocator.allocate(16).unwrap()).collect::<Vec<_>>();
+        assert_eq!(offsets, vec![0, 16, 32, 48]);
+        // exact fit removes the last free region
+        assert_eq!(allocator.stats().free_region_count, 0);
+
+        // two separated holes
+        allocator.free(offsets[0], 16, 0);
+        allocator.free(offsets[2], 16, 0);
+        allocator.reclaim(0);
+        let stats = allocator.stats();
+        assert_eq!(stats.free_region_count, 2);
+        assert_eq!(stats.largest_free_region, 16);
+        assert_eq!(stats.fragmentation, 0.5);
+
+        // the middle one merges with both the previous and the next hole
+        allocator.free(offsets[1], 16, 1);
+        allocator.reclaim(1);
+        let stats = allocator.stats();
+        assert_eq!(stats.free_region_count, 1);
+        assert_eq!(stats.largest_free_region, 48);
+        assert_eq!(stats.fragmentation, 0.0);
+
+        // merge with the previous one only
+        allocator.free(offsets[3], 16, 2);
+        allocator.reclaim(2);
+        assert_eq!(allocator.stats().free_region_count, 1);
+        assert_eq!(allocator.stats().largest_free_region, 64);
+        assert_eq!(allocator.allocate(64), Some(0));
+    }
+
+    #[test]
+    fn test_reclaim_merges_with_next() {
+        let mut allocator = DrawDataAllocator::new(64, 16);
+        let a = allocator.allocate(16).unwrap();
+        let b = allocator.allocate(16).unwrap();
+
+        // b merges with the free tail, then a merges with b
+        allocator.free(b, 16, 0);
+        allocator.reclaim(0);
+        assert_eq!(allocator.stats().free_region_count, 1);
+        allocator.free(a, 16, 0);
+        allocator.reclaim(0);
+        assert_eq!(allocator.stats().free_region_count, 1);
+        assert_eq!(allocator.stats().largest_free_region, 64);
+    }
+
+    #[test]
+    fn test_first_fit_and_exact_fit() {
+        let mut allocator = DrawDataAllocator::new(128, 16);
+        let a = allocator.allocate(32).unwrap();
+        let _b = allocator.allocate(16).unwrap();
+        let c = allocator.allocate(16).unwrap();
+        let _d = allocator.allocate(16).unwrap();
+
+        allocator.free(a, 32, 0);
+        allocator.free(c, 16, 0);
+        allocator.reclaim(0);
+        assert_eq!(allocator.stats().free_region_count, 3);
+
+        // the first hole large enough is used
+        assert_eq!(allocator.allocate(16), Some(a));
+        // exact fit of the second hole removes it
+        assert_eq!(allocator.allocate(16), Some(a + 16));
+        assert_eq!(allocator.stats().free_region_count, 2);
+        assert_eq!(allocator.allocate(16), Some(c));
+        assert_eq!(allocator.stats().free_region_count, 1);
+    }
+
+    #[test]
+    fn test_compact() {
+        let mut allocator = DrawDataAllocator::new(256, 16);
+        let a = allocator.allocate(16).unwrap();
+        let b = allocator.allocate(40).unwrap();
+        let c = allocator.allocate(32).unwrap();
+        let d = allocator.allocate(16).unwrap();
+
+        allocator.free(a, 16, 0);
+        allocator.reclaim(0);
+        allocator.free(c, 32, 1);
+
+        // live regions in any order, the new offsets are in the same order
+        let new_offsets = allocator.compact(&[(d, 16), (b, 40)]);
+        assert_eq!(new_offsets, vec![48, 0]);
+
+        assert_eq!(allocator.stats(), DrawDataStats {
+            capacity: 256,
+            used: 64,
+            pending_free: 0,
+            free_region_count: 1,
+            largest_free_region: 192,
+            fragmentation: 0.0,
+        });
+        assert_eq!(allocator.allocate(192), Some(64));
+
+        // a full buffer has no free region left after compaction
+        let mut allocator = DrawDataAllocator::new(32, 16);
+        let a = allocator.allocate(16).unwrap();
+        let b = allocator.allocate(16).unwrap();
+        assert_eq!(allocator.compact(&[(a, 16), (b, 16)]), vec![0, 16]);
+        assert_eq!(allocator.stats().free_region_count, 0);
+        assert_eq!(allocator.stats().largest_free_region, 0);
+    }
+}
@@ -0,0 +1,235 @@
+use std::sync::Arc;
+
+use ash::vk;
+
+use raven_math::{Vec3, Quat, Mat3};
+use raven_rg::{RenderGraphBuilder, RgHandle, IntoPipelineDescriptorBindings, RenderGraphPassBindable, RenderGraphPassBinding};
+use raven_rhi::{backend::{Image, ImageDesc, AccessType}, Rhi};
+use raven_scene::camera::{Camera, CameraBody, CameraLens};
+
+pub const MAX_ENV_CUBEMAP_RESOLUTION: u32 = 2048;
+
+/// Near plane of the face cameras, surfaces closer than it to the capture position are clipped.
+const ENV_CAPTURE_NEAR_PLANE: f32 = 0.05;
+
+/// Handle of an environment cubemap, see WorldRenderer::capture_environment_cubemap().
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct EnvCubemapHandle(u32);
+
+/// How an environment cubemap is kept up to date.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum EnvCaptureMode {
+    /// Captured once, e.g. baked after the static scene is loaded. Refresh requests are ignored.
+    Static,
+    /// Captured again on every refresh request, e.g. after the scene around the probe changed.
+    OnDemand,
+}
+
+struct EnvCubemap {
+    handle: EnvCubemapHandle,
+    position: Vec3,
+    mode: EnvCaptureMode,
+
+    cubemap: Arc<Image>,
+    access: AccessType,
+
+    /// Sky rotation the faces are captured with, None if the cubemap has never been captured.
+    captured_sky_rotation: Option<Quat>,
+    needs_capture: bool,
+}
+
+/// Cubemaps of the scene rendered from a point, used as the source of the image based lighting instead of the sky.
+///
+/// The cubemaps are stored in the sky space like the sky cubemap, so the ibl passes sample them the same way.
+/// Because of that they are captured again when the sky rotates, even the static ones.
+pub struct EnvCaptureRenderer {
+    cubemaps: Vec<EnvCubemap>,
+    next_handle: u32,
+}
+
+impl EnvCaptureRenderer {
+    pub fn new() -> Self {
+        Self {
+            cubemaps: Vec::new(),
+            next_handle: 0,
+        }
+    }
+
+    /// Create a cubemap which is captured in the next raster frame.
+    pub fn add(&mut self, rhi: &Rhi, position: Vec3, resolution: u32, mode: EnvCaptureMode) -> EnvCubemapHandle {
+        assert!(resolution > 0 && resolution <= MAX_ENV_CUBEMAP_RESOLUTION, "Invalid environment cubemap resolution: {}", resolution);
+
+        let cubemap = rhi.device.create_image(
+            ImageDesc::new_cube(resolution, vk::Format::R16G16B16A16_SFLOAT)
+                .usage_flags(vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::STORAGE),
+            None
+        ).expect("Failed to create environment cubemap!");
+        rhi.device.set_debug_name(cubemap.raw, "environment cubemap");
+
+        let handle = EnvCubemapHandle(self.next_handle);
+        self.next_handle += 1;
+
+        self.cubemaps.push(EnvCubemap {
+            handle,
+            position,
+            mode,
+
+            cubemap: Arc::new(cubemap),
+            access: AccessType::Nothing,
+
+            captured_sky_rotation: None,
+            needs_capture: true,
+        });
+
+        handle
+    }
+
+    /// Capture the cubemap again in the next raster frame, return false if the cubemap is static or removed.
+    pub fn refresh(&mut self, handle: EnvCubemapHandle) -> bool {
+        match self.cubemaps.iter_mut().find(|cubemap| cubemap.handle == handle) {
+            Some(cubemap) if cubemap.mode == EnvCaptureMode::OnDemand => {
+                cubemap.needs_capture = true;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Remove the cubemap, its image is released once the frames in flight are done.
+    pub fn remove(&mut self, rhi: &Rhi, handle: EnvCubemapHandle) -> bool {
+        let Some(idx) = self.cubemaps.iter().position(|cubemap| cubemap.handle == handle) else {
+            return false;
+        };
+
+        let cubemap = self.cubemaps.remove(idx);
+        let image = Arc::try_unwrap(cubemap.cubemap)
+            .unwrap_or_else(|_| panic!("Failed to release environment cubemap, someone is still using it!"));
+        rhi.device.defer_release(image);
+
+        true
+    }
+
+    #[inline]
+    pub fn contains(&self, handle: EnvCubemapHandle) -> bool {
+        self.cubemaps.iter().any(|cubemap| cubemap.handle == handle)
+    }
+
+    /// The cubemaps to be captured in this frame, the ones captured with another sky rotation are included.
+    pub fn pending_captures(&mut self, sky_rotation: Quat) -> Vec<EnvCubemapHandle> {
+        self.cubemaps.iter_mut()
+            .filter_map(|cubemap| {
+                let is_rotated = cubemap.captured_sky_rotation
+                    .map_or(false, |captured| !captured.abs_diff_eq(sky_rotation, 1e-6));
+                cubemap.needs_capture |= is_rotated;
+
+                cubemap.needs_capture.then_some(cubemap.handle)
+            })
+            .collect()
+    }
+
+    /// Import the cubemap to be read, None if it is removed or has never been captured.
+    /// A cubemap must be imported at most once in a frame, the same handle is passed to the capture.
+    pub fn import_captured(&self, rg: &mut RenderGraphBuilder, handle: EnvCubemapHandle) -> Option<RgHandle<Image>> {
+        self.cubemaps.iter()
+            .find(|cubemap| cubemap.handle == handle && cubemap.captured_sky_rotation.is_some())
+            .map(|cubemap| rg.import(cubemap.cubemap.clone(), cubemap.access))
+    }
+
+    /// Import the cubemap to be written, if it is not imported by import_captured() in this frame.
+    pub fn import_for_capture(&self, rg: &mut RenderGraphBuilder, handle: EnvCubemapHandle) -> RgHandle<Image> {
+        let cubemap = self.cubemaps.iter()
+            .find(|cubemap| cubemap.handle == handle)
+            .expect("Failed to find the environment cubemap to capture!");
+
+        rg.import(cubemap.cubemap.clone(), cubemap.access)
+    }
+
+    #[inline]
+    pub fn get_position(&self, handle: EnvCubemapHandle) -> Option<Vec3> {
+        self.cubemaps.iter()
+            .find(|cubemap| cubemap.handle == handle)
+            .map(|cubemap| cubemap.position)
+    }
+
+    /// Export the captured cubemap, so that it can be sampled from the next frame.
+    pub fn end_capture(&mut self, rg: &mut RenderGraphBuilder, handle: EnvCubemapHandle, image: RgHandle<Image>, sky_rotation: Quat) {
+        let cubemap = self.cubemaps.iter_mut()
+            .find(|cubemap| cubemap.handle == handle)
+            .expect("Failed to find the environment cubemap to capture!");
+
+        rg.export(image, AccessType::AnyShaderReadSampledImageOrUniformTexelBuffer);
+        cubemap.access = AccessType::AnyShaderReadSampledImageOrUniformTexelBuffer;
+        cubemap.captured_sky_rotation = Some(sky_rotation);
+        cubemap.needs_capture = false;
+    }
+
+    pub fn clean(self, rhi: &Rhi) {
+        for cubemap in self.cubemaps {
+            let image = Arc::try_unwrap(cubemap.cubemap)
+                .unwrap_or_else(|_| panic!("Failed to release environment cubemap, someone is still using it!"));
+            rhi.device.destroy_image(image);
+        }
+    }
+}
+
+/// Camera rendering the face (+X, -X, +Y, -Y, +Z, -Z) of a cubemap in the sky space.
+///
+/// The cubemap faces are left-handed, while the camera is right-handed, so the camera right axis points
+/// to the opposite of the face u axis and the rendered image is flipped horizontally by copy_face_rg().
+pub fn face_camera(position: Vec3, sky_rotation: Quat, face: u32) -> Camera {
+    // (right, up) of the camera, the camera looks at the direction of up x right
+    let (right, up) = match face {
+        0 => (Vec3::Z, Vec3::Y),
+        1 => (Vec3::NEG_Z, Vec3::Y),
+        2 => (Vec3::NEG_X, Vec3::NEG_Z),
+        3 => (Vec3::NEG_X, Vec3::Z),
+        4 => (Vec3::NEG_X, Vec3::Y),
+        5 => (Vec3::X, Vec3::Y),
+        _ => panic!("Cubemap face index out of range! {}", face),
+    };
+    let face_rotation = Quat::from_mat3(&Mat3::from_cols(right, up, right.cross(up)));
+
+    Camera {
+        body: CameraBody {
+            position,
+            rotation: (sky_rotation * face_rotation).normalize(),
+        },
+        lens: CameraLens {
+            aspect_ratio: 1.0,
+            fov_vertical_degrees: 90.0,
+            near_plane: ENV_CAPTURE_NEAR_PLANE,
+        },
+    }
+}
+
+/// Copy the lit image of a face camera into its layer of the cubemap, the pre-exposure is removed to store the radiance.
+pub fn copy_face_rg(rg: &mut RenderGraphBuilder, face_img: &RgHandle<Image>, cubemap: &mut RgHandle<Image>, face: u32) {
+    let resolution = cubemap.desc().extent[0];
+
+    let mut pass = rg.add_pass("environment capture face");
+    let pipeline = pass.register_compute_pipeline("pbr/ibl/env_capture_face.hlsl");
+
+    let face_img_ref = pass.read(face_img, AccessType::ComputeShaderReadSampledImageOrUniformTexelBuffer);
+    let cubemap_ref = pass.write(cubemap, AccessType::ComputeShaderWrite);
+
+    let push_values = [face, resolution];
+
+    pass.render(move |ctx| {
+        let offset = ctx.global_dynamic_buffer().push(&push_values);
+
+        let mut cubemap_binding = cubemap_ref.bind();
+        cubemap_binding.with_image_view(vk::ImageViewType::TYPE_2D_ARRAY);
+
+        let bound_pipeline = ctx.bind_compute_pipeline(pipeline.into_bindings()
+            .descriptor_set(0, &[
+                face_img_ref.bind(),
+                cubemap_binding,
+                RenderGraphPassBinding::DynamicBuffer(offset),
+            ])
+        )?;
+
+        bound_pipeline.dispatch([resolution, resolution, 1]);
+
+        Ok(())
+    });
+}
@@ -1,13 +1,16 @@
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use ash::vk;
 
+use raven_container::as_bytes;
 use raven_asset::PackedVertex;
 use raven_rg::{RenderGraphBuilder, RgHandle, IntoPipelineDescriptorBindings, RenderGraphPassBindable};
 use raven_rhi::{
     backend::{
         Device,
         Image,
+        Buffer, BufferDesc,
         RayTracingAccelerationStructure, RayTracingAccelerationScratchBuffer,
         RayTracingBlasBuildDesc, RayTracingGeometry, RayTracingGeometryType, RayTracingSubGeometry,
         RayTracingTlasBuildDesc, RayTracingBlasInstance, AccessType,
@@ -21,11 +24,28 @@ use super::mesh_renderer::MeshHandle;
 
 const TLAS_PREALLOCATED_BYTES: usize = 32 * 1024 * 1024;
 
+const MAX_PATH_TRACING_BOUNCES: u32 = 32;
+const MAX_PATH_TRACING_SAMPLES_PER_FRAME: u32 = 16;
+/// Pixels stop accumulating once they reach this sample count.
+const MAX_PATH_TRACING_ACCUMULATED_SAMPLES: u32 = 1000;
+
 pub struct GpuPathTracingRenderer {
-    mesh_blas: Vec<Arc<RayTracingAccelerationStructure>>,
+    /// Indexed by the mesh handle, None for the removed meshes.
+    mesh_blas: Vec<Option<Arc<RayTracingAccelerationStructure>>>,
     tlas: Option<Arc<RayTracingAccelerationStructure>>,
     tlas_scratch_buffer: RayTracingAccelerationScratchBuffer,
 
+    max_bounces: u32,
+    samples_per_frame: u32,
+    // every pixel is traced with the same number of samples, so one count is enough
+    accumulated_samples: u32,
+
+    /// Path to save the accumulation image to, it is copied to the readback buffer in the next frame.
+    save_request: Option<PathBuf>,
+    /// The copy recorded in the last frame, written to the file once the frame is finished.
+    pending_readback: Option<AccumReadback>,
+    accum_readback_buffer: Option<Arc<Buffer>>,
+
     device: Arc<Device>,
 }
 
@@ -46,6 +66,14 @@ impl GpuPathTracingRenderer {
             tlas,
             tlas_scratch_buffer,
 
+            max_bounces: 16,
+            samples_per_frame: 1,
+            accumulated_samples: 0,
+
+            save_request: None,
+            pending_readback: None,
+            accum_readback_buffer: None,
+
             device: rhi.device.clone(),
         }
     }
@@ -62,19 +90,44 @@ impl GpuPathTracingRenderer {
                     vb_address: uploaded_mesh.vertex_packed_address,
                     /// Referenced index buffer address.
                     ib_address: uploaded_mesh.index_buffer_address,
+                    index_buffer_count: uploaded_mesh.index_count as usize,
                     vertex_format: vk::Format::R32G32B32_SFLOAT,
                     vertex_stride: std::mem::size_of::<PackedVertex>(),
                     sub_geometries: vec![RayTracingSubGeometry {
                         index_count: uploaded_mesh.index_count as usize,
                         index_offset: 0,
+                        first_vertex: 0,
                         max_vertex: uploaded_mesh.max_vertex,
                     }],
                 }
-            ]
+            ],
+            // meshes are static, the blas is never rebuilt
+            compact: true,
         })
         .expect("Failed to create new mesh blas!");
 
-        self.mesh_blas.push(Arc::new(blas));
+        self.mesh_blas.push(Some(Arc::new(blas)));
+    }
+
+    /// Release the blas of the removed mesh once the frames in flight are done.
+    pub fn remove_mesh(&mut self, handle: MeshHandle, rhi: &Rhi) {
+        if let Some(blas) = self.mesh_blas[handle.id as usize].take() {
+            let blas = Arc::try_unwrap(blas)
+                .expect("Failed to release blas, someone is still using it!");
+
+            rhi.device.defer_release(blas);
+        }
+    }
+
+    /// Release all the mesh blas once the frames in flight are done.
+    /// The tlas is rebuilt from the mesh instances every frame, so it is kept.
+    pub fn clear_meshes(&mut self, rhi: &Rhi) {
+        for blas in self.mesh_blas.drain(..).flatten() {
+            let blas = Arc::try_unwrap(blas)
+                .expect("Failed to release blas, someone is still using it!");
+
+            rhi.device.defer_release(blas);
+        }
     }
 
     pub fn update_tlas(&mut self, rg: &mut RenderGraphBuilder, mesh_renderer: &MeshRenderer) -> RgHandle<RayTracingAccelerationStructure> {
@@ -82,7 +135,8 @@ impl GpuPathTracingRenderer {
 
         let blas_instances = mesh_instances.iter()
             .map(|inst| RayTracingBlasInstance {
-                blas: self.mesh_blas[inst.handle.id as usize].clone(),
+                blas: self.mesh_blas[inst.handle.id as usize].clone()
+                    .expect("Instance of a removed mesh!"),
                 affine_xform: inst.transform,
                 mesh_index: inst.handle.id as u32,
             })
@@ -125,12 +179,162 @@ impl GpuPathTracingRenderer {
         tlas
     }
 
+    /// Set the max number of path segments traced per sample, clamped to [1, MAX_PATH_TRACING_BOUNCES].
+    /// Return true if the value changed, the accumulation should be reset then.
+    pub fn set_max_bounces(&mut self, bounces: u32) -> bool {
+        let bounces = bounces.clamp(1, MAX_PATH_TRACING_BOUNCES);
+        let changed = self.max_bounces != bounces;
+        self.max_bounces = bounces;
+        changed
+    }
+
+    #[inline]
+    pub fn get_max_bounces(&self) -> u32 {
+        self.max_bounces
+    }
+
+    /// Set the number of samples traced per pixel in one frame, clamped to [1, MAX_PATH_TRACING_SAMPLES_PER_FRAME].
+    /// Return true if the value changed, the accumulation should be reset then.
+    pub fn set_samples_per_frame(&mut self, samples: u32) -> bool {
+        let samples = samples.clamp(1, MAX_PATH_TRACING_SAMPLES_PER_FRAME);
+        let changed = self.samples_per_frame != samples;
+        self.samples_per_frame = samples;
+        changed
+    }
+
+    #[inline]
+    pub fn get_samples_per_frame(&self) -> u32 {
+        self.samples_per_frame
+    }
+
+    /// Number of samples accumulated in each pixel since the last reset.
+    #[inline]
+    pub fn get_accumulated_samples(&self) -> u32 {
+        self.accumulated_samples
+    }
+
+    #[inline]
+    pub fn reset_accumulated_samples(&mut self) {
+        self.accumulated_samples = 0;
+    }
+
+    /// Save the accumulation image to an OpenEXR file, it is read back after the next path tracing frame is finished.
+    /// Return false if nothing is accumulated yet.
+    pub fn request_save_result(&mut self, path: PathBuf) -> bool {
+        if self.accumulated_samples == 0 {
+            return false;
+        }
+
+        self.save_request = Some(path);
+        true
+    }
+
+    /// Write the result copied in the last frame to the file, then copy the accumulation image if another save is requested.
+    pub fn save_result_if_requested(&mut self, rg: &mut RenderGraphBuilder, accum_img: &RgHandle<Image>) {
+        if let Some(readback) = self.pending_readback.take() {
+            self.write_readback_to_exr(readback);
+        }
+
+        let Some(path) = self.save_request.take() else {
+            return;
+        };
+
+        let extent = accum_img.desc().extent;
+        let size_bytes = (extent[0] * extent[1]) as usize * std::mem::size_of::<[f32; 4]>();
+
+        if self.accum_readback_buffer.as_ref().map_or(true, |buf| buf.desc.size != size_bytes) {
+            if let Some(old_buffer) = self.accum_readback_buffer.take() {
+                // the old buffer may still be used by the frames in flight
+                let old_buffer = Arc::try_unwrap(old_buffer)
+                    .unwrap_or_else(|_| panic!("Failed to release path tracing readback buffer, someone is still using it!"));
+                self.device.defer_release(old_buffer);
+            }
+
+            let buffer = self.device.create_buffer(
+                BufferDesc::new_gpu_only(size_bytes, vk::BufferUsageFlags::TRANSFER_DST | vk::BufferUsageFlags::TRANSFER_SRC),
+                "path tracing accum readback buffer"
+            ).expect("Failed to create path tracing readback buffer!");
+            self.accum_readback_buffer = Some(Arc::new(buffer));
+        }
+
+        let mut readback_buf = rg.import(self.accum_readback_buffer.as_ref().unwrap().clone(), AccessType::Nothing);
+
+        let mut pass = rg.add_pass("path tracing accum copy");
+        let accum_ref = pass.read(accum_img, AccessType::TransferRead);
+        let readback_ref = pass.write(&mut readback_buf, AccessType::TransferWrite);
+
+        pass.render(move |ctx| {
+            let image = ctx.registry.get_image(accum_ref);
+            let buffer = ctx.registry.get_buffer(readback_ref);
+
+            unsafe {
+                ctx.registry.execution_params.device.raw.cmd_copy_image_to_buffer(
+                    ctx.cb.raw,
+                    image.raw,
+                    vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                    buffer.raw,
+                    &[vk::BufferImageCopy::builder()
+                        .buffer_offset(0)
+                        .image_subresource(vk::ImageSubresourceLayers {
+                            aspect_mask: vk::ImageAspectFlags::COLOR,
+                            mip_level: 0,
+                            base_array_layer: 0,
+                            layer_count: 1,
+                        })
+                        .image_extent(vk::Extent3D { width: extent[0], height: extent[1], depth: 1 })
+                        .build()
+                    ]
+                );
+            }
+
+            Ok(())
+        });
+
+        self.pending_readback = Some(AccumReadback {
+            path,
+            extent: [extent[0], extent[1]],
+            sample_count: self.accumulated_samples,
+        });
+    }
+
+    /// Called in the frames which are not path traced. The result already copied is still written,
+    /// but the save request not copied yet is dropped with a warning.
+    pub fn flush_save_request(&mut self) {
+        if let Some(readback) = self.pending_readback.take() {
+            self.write_readback_to_exr(readback);
+        }
+
+        if let Some(path) = self.save_request.take() {
+            glog::warn!("Render mode left path tracing before the result is copied, {} is not saved!", path.display());
+        }
+    }
+
+    fn write_readback_to_exr(&self, readback: AccumReadback) {
+        // make sure the copy in the last frame is finished
+        self.device.wait_idle();
+
+        let buffer = self.accum_readback_buffer.as_ref().unwrap();
+        let pixels = match self.device.read_buffer_to_vec::<[f32; 4]>(buffer, ..) {
+            Ok(pixels) => pixels,
+            Err(err) => {
+                glog::warn!("Failed to read back path tracing result: {:?}", err);
+                return;
+            }
+        };
+
+        match write_exr(&readback.path, readback.extent, &pixels, readback.sample_count) {
+            Ok(()) => glog::info!("Path tracing result ({} samples) is saved to {}.", readback.sample_count, readback.path.display()),
+            Err(err) => glog::warn!("Failed to save path tracing result to {}: {}", readback.path.display(), err),
+        }
+    }
+
     pub fn path_tracing_accum(
         &mut self,
         rg: &mut RenderGraphBuilder,
         tlas: &RgHandle<RayTracingAccelerationStructure>,
         accum_img: &mut RgHandle<Image>,
         env_map: &RgHandle<Image>,
+        is_env_map_linear: bool,
         bindless_descriptor_set: vk::DescriptorSet,
     ) {
         let mut pass = rg.add_pass("path tracing");
@@ -162,6 +366,10 @@ impl GpuPathTracingRenderer {
 
         let accum_img_extent = accum_img.desc().extent;
 
+        let max_bounces = self.max_bounces;
+        let samples_per_frame = self.samples_per_frame;
+        self.accumulated_samples = (self.accumulated_samples + samples_per_frame).min(MAX_PATH_TRACING_ACCUMULATED_SAMPLES);
+
         pass.render(move |ctx| {
             let bound_pipeline = ctx.bind_ray_tracing_pipeline(
                 pipeline.into_bindings()
@@ -173,6 +381,14 @@ impl GpuPathTracingRenderer {
                     .raw_descriptor_set(1, bindless_descriptor_set)
             )?; 
 
+            let push_constants = [
+                is_env_map_linear as u32,
+                max_bounces,
+                samples_per_frame,
+                MAX_PATH_TRACING_ACCUMULATED_SAMPLES,
+            ];
+            bound_pipeline.push_constants(vk::ShaderStageFlags::ALL, 0, as_bytes::as_byte_slice_val(&push_constants));
+
             bound_pipeline.trace_rays(accum_img_extent);
 
             Ok(())
@@ -195,5 +411,34 @@ impl GpuPathTracingRenderer {
         }
 
         rhi.device.destroy_ray_tracing_scratch_buffer(self.tlas_scratch_buffer);
+
+        if let Some(buffer) = self.accum_readback_buffer {
+            let buffer = Arc::try_unwrap(buffer)
+                .unwrap_or_else(|_| panic!("Failed to release path tracing readback buffer, someone is still using it!"));
+
+            rhi.device.destroy_buffer(buffer);
+        }
     }
+}
+
+struct AccumReadback {
+    path: PathBuf,
+    extent: [u32; 2],
+    sample_count: u32,
+}
+
+fn write_exr(path: &Path, extent: [u32; 2], pixels: &[[f32; 4]], sample_count: u32) -> exr::error::UnitResult {
+    use exr::prelude::*;
+    use exr::meta::attribute::{AttributeValue, Text};
+
+    let channels = SpecificChannels::rgba(|Vec2(x, y): Vec2<usize>| {
+        // the w channel of the accumulation image is the per pixel sample count, not the alpha
+        let [r, g, b, _] = pixels[y * extent[0] as usize + x];
+        (r, g, b, 1.0_f32)
+    });
+
+    let mut image = Image::from_channels((extent[0] as usize, extent[1] as usize), channels);
+    image.attributes.other.insert(Text::from("sampleCount"), AttributeValue::I32(sample_count as i32));
+
+    image.write().to_file(path)
 }
\ No newline at end of file
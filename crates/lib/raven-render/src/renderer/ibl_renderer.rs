@@ -3,7 +3,7 @@ use std::sync::Arc;
 use ash::vk;
 
 use raven_container::as_bytes;
-use raven_math::{self, SHBasis9};
+use raven_math::{self, SHBasis9, Vec3};
 use raven_rg::{RenderGraphBuilder, RgHandle, RenderGraphPassBindable, IntoPipelineDescriptorBindings};
 use raven_rhi::{backend::{Image, AccessType, ImageDesc, Buffer, BufferDesc}, Rhi, copy_engine::CopyEngine};
 
@@ -16,6 +16,10 @@ pub struct IblRenderer {
     prefilter_cubemap: Arc<Image>,
 
     ibl_resources_prepared: bool,
+
+    /// Scale of the diffuse and specular environment lighting, the prefiltered resources are not affected.
+    intensity: f32,
+    tint: Vec3,
 }
 
 impl IblRenderer {
@@ -29,6 +33,7 @@ impl IblRenderer {
             .mipmap_level(raven_math::max_mipmap_level_2d(PREFILTER_CUBEMAP_RESOLUTION as _, PREFILTER_CUBEMAP_RESOLUTION as _))
             .usage_flags(vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::STORAGE), None)
             .expect("Failed to create prefilter cubamap!");
+        rhi.device.set_debug_name(prefilter.raw, "ibl prefilter cubemap");
 
         let sh_buffer = rhi.device.create_buffer(
             BufferDesc::new_gpu_only(3 * 9 * std::mem::size_of::<f32>(),
@@ -42,9 +47,41 @@ impl IblRenderer {
             prefilter_cubemap: Arc::new(prefilter),
 
             ibl_resources_prepared: true,
+
+            intensity: 1.0,
+            tint: Vec3::ONE,
         }
     }
 
+    /// Scale the diffuse and specular environment lighting, it is applied when shading.
+    #[inline]
+    pub fn set_intensity(&mut self, intensity: f32) {
+        assert!(intensity >= 0.0 && intensity.is_finite(), "Invalid ibl intensity: {}", intensity);
+        self.intensity = intensity;
+    }
+
+    #[inline]
+    pub fn get_intensity(&self) -> f32 {
+        self.intensity
+    }
+
+    /// Linear color multiplied to the environment lighting.
+    #[inline]
+    pub fn set_tint(&mut self, tint: Vec3) {
+        self.tint = tint.max(Vec3::ZERO);
+    }
+
+    #[inline]
+    pub fn get_tint(&self) -> Vec3 {
+        self.tint
+    }
+
+    /// The tint multiplied by the intensity.
+    #[inline]
+    pub fn radiance_scale(&self) -> Vec3 {
+        self.tint * self.intensity
+    }
+
     pub fn update_sh(&mut self, rhi: &Rhi, basis: [SHBasis9; 3]) {
         let values = [
             basis[0].to_f32_array(),
@@ -58,6 +95,12 @@ impl IblRenderer {
         copy_engine.upload(&rhi.device, &self.sh_buffer, 0).expect("Failed to upload sh buffer data!");
     }
 
+    /// Regenerate the ibl resources (sh and prefilter cubemap) from the sky cubemap in the next prepare_ibl_if_needed().
+    #[inline]
+    pub fn request_update(&mut self) {
+        self.ibl_resources_prepared = true;
+    }
+
     pub fn prepare_ibl_if_needed(&mut self, rg: &mut RenderGraphBuilder, cubemap: &RgHandle<Image>) -> (RgHandle<Buffer>, RgHandle<Image>) {
         let (mut sh_buffer, mut prefilter_cubemap) = if self.ibl_resources_prepared {
             (
@@ -0,0 +1,320 @@
+use raven_math::{AABB, Frustum, Ray};
+
+use super::mesh_renderer::MeshInstanceHandle;
+
+/// Instances in a leaf before the node is split.
+const MAX_LEAF_INSTANCES: usize = 4;
+
+#[derive(Copy, Clone)]
+struct BvhNode {
+    aabb: AABB,
+    /// Leaf: index of the first item. Interior: index of the left child, the right child follows it.
+    first: u32,
+    /// Number of the items in the leaf, zero for interior nodes.
+    count: u32,
+}
+
+impl BvhNode {
+    #[inline]
+    fn is_leaf(&self) -> bool {
+        self.count > 0
+    }
+}
+
+/// Bounding volume hierarchy over the world space AABBs of the mesh instances, built with the surface area heuristic.
+///
+/// It only serves the CPU queries (MeshRenderer::query_frustum() and MeshRenderer::raycast()),
+/// the instance submission doesn't use it and still draws all the instances.
+/// It is rebuilt from scratch on the next query whenever the instances change, which is cheap enough for a few thousand instances.
+#[derive(Default)]
+pub(crate) struct InstanceBvh {
+    nodes: Vec<BvhNode>,
+    /// Instances reordered so that every leaf references a contiguous range.
+    items: Vec<(MeshInstanceHandle, AABB)>,
+}
+
+impl InstanceBvh {
+    pub fn build(instances: impl Iterator<Item = (MeshInstanceHandle, AABB)>) -> Self {
+        let mut bvh = Self {
+            nodes: Vec::new(),
+            // instances without bounds can not be hit or seen by the queries
+            items: instances.filter(|(_, aabb)| !aabb.is_empty()).collect(),
+        };
+
+        if !bvh.items.is_empty() {
+            bvh.nodes.reserve(bvh.items.len() * 2);
+            bvh.nodes.push(BvhNode { aabb: AABB::new(), first: 0, count: 0 });
+            bvh.build_node(0, 0, bvh.items.len());
+        }
+
+        bvh
+    }
+
+    fn build_node(&mut self, node_idx: usize, first: usize, count: usize) {
+        let items = &mut self.items[first..first + count];
+
+        let mut aabb = AABB::new();
+        for (_, item_aabb) in items.iter() {
+            aabb.merge_aabb(item_aabb);
+        }
+        self.nodes[node_idx].aabb = aabb;
+
+        if count <= MAX_LEAF_INSTANCES {
+            self.nodes[node_idx].first = first as u32;
+            self.nodes[node_idx].count = count as u32;
+            return;
+        }
+
+        let left_count = Self::find_split(items);
+        let left = self.nodes.len();
+        self.nodes.push(BvhNode { aabb: AABB::new(), first: 0, count: 0 });
+        self.nodes.push(BvhNode { aabb: AABB::new(), first: 0, count: 0 });
+        self.nodes[node_idx].first = left as u32;
+
+        self.build_node(left, first, left_count);
+        self.build_node(left + 1, first + left_count, count - left_count);
+    }
+
+    /// Sort the items along the axis of the largest centroid spread, and return the number of the items in the left child
+    /// with the lowest surface area cost. There are at least two items.
+    fn find_split(items: &mut [(MeshInstanceHandle, AABB)]) -> usize {
+        let mut centroid_bounds = AABB::new();
+        for (_, aabb) in items.iter() {
+            centroid_bounds.merge_point_vec3(aabb.center());
+        }
+
+        let spread = centroid_bounds.max - centroid_bounds.min;
+        let axis = if spread.x >= spread.y && spread.x >= spread.z {
+            0
+        } else if spread.y >= spread.z {
+            1
+        } else {
+            2
+        };
+        items.sort_unstable_by(|(_, lhs), (_, rhs)| lhs.center()[axis].total_cmp(&rhs.center()[axis]));
+
+        // surface areas of the left side of each split position, swept from the left
+        let mut left_areas = Vec::with_capacity(items.len());
+        let mut left_aabb = AABB::new();
+        for (_, aabb) in items.iter() {
+            left_aabb.merge_aabb(aabb);
+            left_areas.push(left_aabb.surface_area());
+        }
+
+        // then sweep from the right, the split at i puts items[..i] into the left child
+        let mut best_split = items.len() / 2;
+        let mut best_cost = f32::MAX;
+        let mut right_aabb = AABB::new();
+        for i in (1..items.len()).rev() {
+            right_aabb.merge_aabb(&items[i].1);
+            let cost = left_areas[i - 1] * i as f32 + right_aabb.surface_area() * (items.len() - i) as f32;
+
+            // prefer the balanced split among the equal costs (e.g. all the instances have the same bounds)
+            let is_more_balanced = i.abs_diff(items.len() / 2) < best_split.abs_diff(items.len() / 2);
+            if cost < best_cost || (cost == best_cost && is_more_balanced) {
+                best_split = i;
+                best_cost = cost;
+            }
+        }
+
+        best_split
+    }
+
+    /// Instances whose bounds are (conservatively) inside the frustum.
+    pub fn query_frustum(&self, frustum: &Frustum) -> Vec<MeshInstanceHandle> {
+        let mut result = Vec::new();
+        if self.nodes.is_empty() {
+            return result;
+        }
+
+        let mut stack = vec![0_usize];
+        while let Some(node_idx) = stack.pop() {
+            let node = &self.nodes[node_idx];
+            if !frustum.intersects_aabb(&node.aabb) {
+                continue;
+            }
+
+            if node.is_leaf() {
+                let items = &self.items[node.first as usize..(node.first + node.count) as usize];
+                result.extend(items.iter()
+                    .filter(|(_, aabb)| frustum.intersects_aabb(aabb))
+                    .map(|(handle, _)| *handle));
+            } else {
+                stack.push(node.first as usize);
+                stack.push(node.first as usize + 1);
+            }
+        }
+
+        result
+    }
+
+    /// The instance whose bounds are hit first by the ray, and the hit distance to the bounds.
+    pub fn raycast(&self, ray: &Ray) -> Option<(MeshInstanceHandle, f32)> {
+        let root_t = ray.intersect_aabb(&self.nodes.first()?.aabb)?;
+
+        let mut closest: Option<(MeshInstanceHandle, f32)> = None;
+        let mut stack = vec![(0_usize, root_t)];
+        while let Some((node_idx, node_t)) = stack.pop() {
+            // a closer hit was found after this node was pushed
+            if closest.map_or(false, |(_, t)| node_t >= t) {
+                continue;
+            }
+
+            let node = &self.nodes[node_idx];
+            if node.is_leaf() {
+                for (handle, aabb) in &self.items[node.first as usize..(node.first + node.count) as usize] {
+                    if let Some(t) = ray.intersect_aabb(aabb) {
+                        if closest.map_or(true, |(_, closest_t)| t < closest_t) {
+                            closest = Some((*handle, t));
+                        }
+                    }
+                }
+            } else {
+                let left = node.first as usize;
+                let left_t = ray.intersect_aabb(&self.nodes[left].aabb);
+                let right_t = ray.intersect_aabb(&self.nodes[left + 1].aabb);
+
+                // push the farther child first, so that the nearer one is visited first
+                match (left_t, right_t) {
+                    (Some(lt), Some(rt)) if lt <= rt => {
+                        stack.push((left + 1, rt));
+                        stack.push((left, lt));
+                    }
+                    (Some(lt), Some(rt)) => {
+                        stack.push((left, lt));
+                        stack.push((left + 1, rt));
+                    }
+                    (Some(lt), None) => stack.push((left, lt)),
+                    (None, Some(rt)) => stack.push((left + 1, rt)),
+                    (None, None) => {}
+                }
+            }
+        }
+
+        closest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use raven_math::{AABB, Frustum, Mat4, Ray, Vec3};
+
+    use super::{InstanceBvh, MeshInstanceHandle};
+
+    /// Deterministic xorshift random numbers in [0, 1).
+    struct Random(u32);
+
+    impl Random {
+        fn next(&mut self) -> f32 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 17;
+            self.0 ^= self.0 << 5;
+            (self.0 >> 8) as f32 / (1 << 24) as f32
+        }
+
+        fn next_vec3(&mut self, min: f32, max: f32) -> Vec3 {
+            Vec3::new(self.next(), self.next(), self.next()) * (max - min) + Vec3::splat(min)
+        }
+    }
+
+    fn random_instances(random: &mut Random, count: u32) -> Vec<(MeshInstanceHandle, AABB)> {
+        (0..count)
+            .map(|i| {
+                let min = random.next_vec3(-100.0, 100.0);
+                let aabb = AABB::from_min_max(min, min + random.next_vec3(0.1, 10.0));
+                (MeshInstanceHandle(i), aabb)
+            })
+            .collect()
+    }
+
+    fn random_frustum(random: &mut Random) -> Frustum {
+        let eye = random.next_vec3(-150.0, 150.0);
+        let target = random.next_vec3(-50.0, 50.0);
+        let view_to_clip = Mat4::perspective_infinite_reverse_rh((30.0 + random.next() * 60.0).to_radians(), 1.5, 0.1);
+        Frustum::from_world_to_clip(view_to_clip * Mat4::look_at_rh(eye, target, Vec3::Y))
+    }
+
+    fn random_ray(random: &mut Random) -> Ray {
+        let origin = random.next_vec3(-150.0, 150.0);
+        let target = random.next_vec3(-100.0, 100.0);
+        Ray::new(origin, (target - origin).normalize())
+    }
+
+    fn brute_force_frustum(instances: &[(MeshInstanceHandle, AABB)], frustum: &Frustum) -> Vec<MeshInstanceHandle> {
+        let mut result: Vec<_> = instances.iter()
+            .filter(|(_, aabb)| frustum.intersects_aabb(aabb))
+            .map(|(handle, _)| *handle)
+            .collect();
+        result.sort();
+        result
+    }
+
+    fn brute_force_raycast(instances: &[(MeshInstanceHandle, AABB)], ray: &Ray) -> Option<f32> {
+        instances.iter()
+            .filter_map(|(_, aabb)| ray.intersect_aabb(aabb))
+            .min_by(|lhs, rhs| lhs.total_cmp(rhs))
+    }
+
+    fn check_queries(instances: &[(MeshInstanceHandle, AABB)], random: &mut Random) {
+        let bvh = InstanceBvh::build(instances.iter().copied());
+
+        for _ in 0..64 {
+            let frustum = random_frustum(random);
+            let mut result = bvh.query_frustum(&frustum);
+            result.sort();
+            assert_eq!(result, brute_force_frustum(instances, &frustum));
+
+            let ray = random_ray(random);
+            let hit = bvh.raycast(&ray);
+            assert_eq!(hit.map(|(_, t)| t), brute_force_raycast(instances, &ray));
+            if let Some((handle, t)) = hit {
+                // the returned instance is really hit at that distance (instances may tie with each other)
+                let aabb = instances.iter().find(|(h, _)| *h == handle).unwrap().1;
+                assert_eq!(ray.intersect_aabb(&aabb), Some(t));
+            }
+        }
+    }
+
+    #[test]
+    fn test_instance_bvh_random() {
+        let mut random = Random(0x1234_5678);
+
+        for count in [1, 3, 4, 5, 17, 100, 1000] {
+            let instances = random_instances(&mut random, count);
+            check_queries(&instances, &mut random);
+        }
+    }
+
+    #[test]
+    fn test_instance_bvh_empty() {
+        let mut random = Random(0x9abc_def0);
+
+        let bvh = InstanceBvh::build(std::iter::empty());
+        assert!(bvh.query_frustum(&random_frustum(&mut random)).is_empty());
+        assert!(bvh.raycast(&random_ray(&mut random)).is_none());
+
+        // instances without bounds are skipped
+        let bvh = InstanceBvh::build((0..8).map(|i| (MeshInstanceHandle(i), AABB::new())));
+        assert!(bvh.nodes.is_empty());
+        let ray = Ray::new(Vec3::ZERO, Vec3::X);
+        assert!(bvh.raycast(&ray).is_none());
+    }
+
+    #[test]
+    fn test_instance_bvh_identical_aabbs() {
+        let mut random = Random(0x0bad_cafe);
+
+        let aabb = AABB::from_min_max(Vec3::splat(-1.0), Vec3::splat(1.0));
+        let instances: Vec<_> = (0..100).map(|i| (MeshInstanceHandle(i), aabb)).collect();
+        check_queries(&instances, &mut random);
+
+        let bvh = InstanceBvh::build(instances.iter().copied());
+        // looking at the boxes from +Z
+        let view_to_clip = Mat4::perspective_infinite_reverse_rh(60.0_f32.to_radians(), 1.0, 0.1);
+        let frustum = Frustum::from_world_to_clip(view_to_clip * Mat4::look_at_rh(Vec3::new(0.0, 0.0, 10.0), Vec3::ZERO, Vec3::Y));
+        assert_eq!(bvh.query_frustum(&frustum).len(), 100);
+
+        let (_, t) = bvh.raycast(&Ray::new(Vec3::new(0.0, 0.0, 10.0), Vec3::NEG_Z)).unwrap();
+        assert!((t - 9.0).abs() < 1e-5);
+    }
+}
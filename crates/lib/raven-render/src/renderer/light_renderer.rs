@@ -3,7 +3,7 @@ use std::sync::Arc;
 use ash::vk;
 
 use raven_math::{Vec3, Mat4, Quat, AABB};
-use raven_rg::{RgHandle, RenderGraphBuilder};
+use raven_rg::{RgHandle, RenderGraphBuilder, LightFrameConstants};
 use raven_rhi::{
     Rhi,
     backend::{
@@ -12,6 +12,7 @@ use raven_rhi::{
 };
 
 use crate::MeshRenderer;
+use super::light_units::LightUnit;
 
 const MAX_DIRECTIONAL_LIGHT_COUNT: usize = 10;
 
@@ -20,8 +21,9 @@ const MAX_DIRECTIONAL_LIGHT_COUNT: usize = 10;
 pub struct DirectionalLight {
     pub direction: Quat,
     pub color: Vec3,
-    // TODO: use lux as the light intensity parameter exposed to user
+    /// Intensity in the unit, Lux or Relative.
     pub intensity: f32,
+    pub unit: LightUnit,
     pub shadowed: bool,
 }
 
@@ -71,6 +73,7 @@ impl LightRenderer {
                     None
                 )
                 .expect("Failed to create shadow map for directional lights!");
+                self.device.set_debug_name(shadow_map.raw, "directional light shadow map");
                 
                 self.directional_light_maps.push((next_directional_light_index as u32, Arc::new(shadow_map)));
             }
@@ -86,6 +89,67 @@ impl LightRenderer {
         }
     }
 
+    /// Set the direction of a directional light, the direction points from the scene to the light.
+    pub fn set_directional_light_direction(&mut self, handle: LightHandle, direction: Vec3) {
+        if let Some(light) = self.directional_lights.get_mut(handle.0 as usize) {
+            light.direction = Quat::from_rotation_arc(Vec3::new(0.0, 0.0, -1.0), direction.normalize());
+        } else {
+            glog::warn!("Invalid directional light handle: {:?}", handle);
+        }
+    }
+
+    /// Get the direction of a directional light, the direction points from the scene to the light.
+    pub fn get_directional_light_direction(&self, handle: LightHandle) -> Option<Vec3> {
+        self.directional_lights.get(handle.0 as usize)
+            .map(|light| light.direction.mul_vec3(Vec3::new(0.0, 0.0, -1.0)))
+    }
+
+    /// All the directional lights, in the order they are added.
+    pub fn get_directional_lights(&self) -> impl Iterator<Item = (LightHandle, &DirectionalLight)> {
+        self.directional_lights.iter()
+            .enumerate()
+            .map(|(idx, light)| (LightHandle(idx as u32), light))
+    }
+
+    /// Change the intensity and its unit of a directional light, e.g. switch to the physical units with LightUnit::Lux.
+    pub fn set_directional_light_intensity(&mut self, handle: LightHandle, intensity: f32, unit: LightUnit) {
+        if let Some(light) = self.directional_lights.get_mut(handle.0 as usize) {
+            light.intensity = intensity;
+            light.unit = unit;
+        } else {
+            glog::warn!("Invalid directional light handle: {:?}", handle);
+        }
+    }
+
+    /// Change the direction and the radiance of a directional light, whether it is shadowed can not be changed after added.
+    /// The intensity is in the current unit of the light.
+    pub fn update_directional_light(&mut self, handle: LightHandle, direction: Quat, color: Vec3, intensity: f32) {
+        if let Some(light) = self.directional_lights.get_mut(handle.0 as usize) {
+            light.direction = direction.normalize();
+            light.color = color;
+            light.intensity = intensity;
+        } else {
+            glog::warn!("Invalid directional light handle: {:?}", handle);
+        }
+    }
+
+    /// Light constants of all the directional lights and the directional light count.
+    pub fn light_frame_constants(&self) -> ([LightFrameConstants; MAX_DIRECTIONAL_LIGHT_COUNT], u32) {
+        let mut constants: [LightFrameConstants; MAX_DIRECTIONAL_LIGHT_COUNT] = Default::default();
+
+        for (constant, light) in constants.iter_mut().zip(self.directional_lights.iter()) {
+            *constant = LightFrameConstants {
+                color: light.color.to_array(),
+                shadowed: light.shadowed as u32,
+                direction: light.direction.mul_vec3(Vec3::new(0.0, 0.0, -1.0)).to_array(),
+                // the shaders work in the scaled luminance, see LightUnit::to_shader_intensity()
+                intensity: light.unit.to_shader_intensity(light.intensity),
+            };
+        }
+
+        (constants, self.directional_lights.len() as u32)
+    }
+
     pub fn prepare_render_data(
         &self,
         rg: &mut RenderGraphBuilder,
@@ -0,0 +1,59 @@
+/// Luminance (in cd/m^2) of 1.0 in the shaders, i.e. the scale between the photometric units and the rendered values.
+///
+/// It is the same scale as the procedural sky (SKY_LUMINANCE_SCALE in sky/procedural_sky.hlsl maps 1 kcd/m^2 to 0.05),
+/// so the physical lights and the sky are balanced against each other, and the exposure adapts to both of them.
+pub const PHYSICAL_LUMINANCE_UNIT: f32 = 20_000.0;
+
+/// Illuminance of the direct sunlight at noon on a clear day.
+pub const SUNNY_DAY_LUX: f32 = 100_000.0;
+/// Illuminance of the daylight under a thick overcast sky.
+pub const OVERCAST_DAY_LUX: f32 = 1_000.0;
+/// Illuminance of the sun close to the horizon.
+pub const SUNRISE_LUX: f32 = 400.0;
+/// Illuminance of the full moon on a clear night.
+pub const FULL_MOON_LUX: f32 = 0.25;
+
+/// Unit of the intensity of a light.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LightUnit {
+    /// Unitless scale of the light color, passed to the shaders as it is.
+    Relative,
+    /// Illuminance (lm/m^2) on a surface facing the light, for the directional lights.
+    Lux,
+}
+
+impl LightUnit {
+    /// Convert the intensity in this unit to the intensity in the light frame constants.
+    ///
+    /// The shaders compute the reflected radiance as brdf * color * intensity * cos(theta).
+    ///
+    /// Lux: a lambertian surface with the albedo a lit by the illuminance E reflects the luminance a * E / pi (cd/m^2),
+    /// and its brdf is a / pi, so the intensity is E / PHYSICAL_LUMINANCE_UNIT.
+    pub fn to_shader_intensity(self, intensity: f32) -> f32 {
+        match self {
+            LightUnit::Relative => intensity,
+            LightUnit::Lux => intensity / PHYSICAL_LUMINANCE_UNIT,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relative_to_shader_intensity() {
+        assert_eq!(LightUnit::Relative.to_shader_intensity(0.0), 0.0);
+        assert_eq!(LightUnit::Relative.to_shader_intensity(1.0), 1.0);
+        assert_eq!(LightUnit::Relative.to_shader_intensity(PHYSICAL_LUMINANCE_UNIT), PHYSICAL_LUMINANCE_UNIT);
+    }
+
+    #[test]
+    fn test_lux_to_shader_intensity() {
+        assert_eq!(LightUnit::Lux.to_shader_intensity(0.0), 0.0);
+        // PHYSICAL_LUMINANCE_UNIT lux is 1.0 in the shaders
+        assert_eq!(LightUnit::Lux.to_shader_intensity(PHYSICAL_LUMINANCE_UNIT), 1.0);
+        assert_eq!(LightUnit::Lux.to_shader_intensity(SUNNY_DAY_LUX), 5.0);
+        assert!((LightUnit::Lux.to_shader_intensity(FULL_MOON_LUX) - 0.25 / 20_000.0).abs() < f32::EPSILON);
+    }
+}
@@ -18,6 +18,7 @@ impl ImageLutComputer for BrdfLutComputer {
         let brdf = rhi.device.create_image(ImageDesc::new_2d([BRDF_LUT_IMAGE_RESOLUTION, BRDF_LUT_IMAGE_RESOLUTION], vk::Format::R16G16_SFLOAT)
             .usage_flags(vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::STORAGE), None)
             .expect("Failed to create brdf lut!");
+        rhi.device.set_debug_name(brdf.raw, "brdf lut");
 
         brdf
     }
@@ -1,24 +1,26 @@
 use std::{sync::Arc};
-use std::collections::BTreeSet;
+use std::collections::{BTreeSet, HashMap};
 
 use ash::vk;
 
 use raven_container::as_bytes;
-use raven_asset::{TextureGammaSpace, AsConcreteAsset, asset_registry::{AssetHandle, get_runtime_asset_registry}, PackedVertex, VecArrayQueryParam};
-use raven_math::{AABB, Affine3A};
-use raven_rg::{RenderGraphBuilder, RgHandle, IntoPipelineDescriptorBindings, RenderGraphPassBinding, image_clear};
+use raven_asset::{TextureDesc, TextureGammaSpace, AsConcreteAsset, asset_registry::{AssetHandle, get_runtime_asset_registry}, PackedVertex, VecArrayQueryParam};
+use raven_math::{AABB, Affine3A, Mat4, Vec3, Frustum, Ray};
+use raven_rg::{RenderGraphBuilder, RgHandle, IntoPipelineDescriptorBindings, RenderGraphPassBinding, RenderGraphPassBindable, image_clear};
 use raven_rhi::backend::{RasterPipelineCullMode, descriptor};
 use raven_rhi::{
     backend::{
-        Device, ImageDesc, Image, BufferDesc, Buffer,
+        Device, ImageDesc, Image, BufferDesc, Buffer, MemoryCategory,
         renderpass, RenderPass,
         RasterPipelineDesc, PipelineShaderDesc, PipelineShaderStage, 
         AccessType, ImageViewDesc, ImageSubResource
     },
-    Rhi, copy_engine::CopyEngine
+    Rhi, copy_engine::CopyEngine, dynamic_buffer::DynamicBuffer
 };
 
 use super::light_renderer::{LightRenderData, self};
+use super::draw_data_allocator::{DrawDataAllocator, DrawDataStats};
+use super::instance_bvh::InstanceBvh;
 
 #[allow(dead_code)]
 pub const TEXTURE_MASK_ALBEDO_BIT: u32   = 1 << 0;
@@ -32,8 +34,23 @@ pub const TEXTURE_MASK_EMISSIVE_BIT: u32 = 1 << 3;
 const GBUFFER_PACK_FORMAT: vk::Format = vk::Format::R32G32B32A32_SFLOAT;
 const GBUFFER_DEPTH_FORMAT: vk::Format = vk::Format::D32_SFLOAT;
 const GBUFFER_GEOMETRIC_NORMAL_FORMAT: vk::Format = vk::Format::A2R10G10B10_UNORM_PACK32;
+const GBUFFER_INSTANCE_ID_FORMAT: vk::Format = vk::Format::R32_UINT;
+const GBUFFER_MOTION_VECTOR_FORMAT: vk::Format = vk::Format::R16G16_SFLOAT;
+const FORWARD_TRANSPARENCY_COLOR_FORMAT: vk::Format = vk::Format::R32G32B32A32_SFLOAT;
 
 const MAX_GPU_MESH_COUNT: usize = 1024;
+const DRAW_DATA_BUFFER_SIZE: usize = 1024 * 1024 * 512; // 512 MB
+/// Alignment of the mesh data in the draw data buffer, enough for any vertex attribute and index.
+const DRAW_DATA_ALIGNMENT: u64 = 16;
+
+/// Textures bound in place of the missing material textures, in the order of the material texture mapping.
+/// i.e. white albedo, flat normal, black specular and black emissive.
+const DEFAULT_MATERIAL_TEXTURES: [(&str, [u8; 4]); 4] = [
+    ("default albedo texture", [255, 255, 255, 255]),
+    ("default normal texture", [128, 128, 255, 255]),
+    ("default specular texture", [0, 0, 0, 255]),
+    ("default emissive texture", [0, 0, 0, 255]),
+];
 
 pub enum MeshRasterScheme {
     Forward,
@@ -70,13 +87,43 @@ struct GpuMesh {
     texture_mask: u32,
 }
 
+impl GpuMesh {
+    /// Move the offsets along with the mesh data, from the old start of the mesh data to the new one.
+    fn rebase(&mut self, old_base: u32, new_base: u32) {
+        for offset in [
+            &mut self.vertex_offset, &mut self.color_offset, &mut self.uv_offset, &mut self.tangent_offset,
+            &mut self.index_offset, &mut self.mat_id_offset, &mut self.mat_data_offset,
+        ] {
+            *offset = *offset - old_base + new_base;
+        }
+    }
+}
+
 #[derive(Clone)]
 pub(crate) struct UploadedMesh {
     pub(crate) index_buffer_offset: u32,
     pub(crate) index_count: u32,
+    /// Indices of the coarser LODs (LOD 1, 2, ...), they share the vertices with LOD 0.
+    pub(crate) lods: Vec<MeshLod>,
 
     /// Mesh aabb in object space.
     pub(crate) aabb: AABB,
+    /// Whether this mesh contains any alpha blended material, which is drawn in the forward transparency pass.
+    pub(crate) alpha_blend: bool,
+    /// Whether this mesh contains any material not alpha blended, which is drawn in gbuffer rasterization.
+    /// A mesh mixing both is drawn in both passes, each of them discards the pixels of the other materials.
+    pub(crate) opaque: bool,
+    /// Whether this mesh is drawn in the shadow maps, meshes with only unlit materials do not cast shadow by default.
+    pub(crate) cast_shadow: bool,
+    /// Whether this mesh may discard pixels by the albedo alpha in gbuffer rasterization.
+    /// The depth prepass does not sample the textures, so these meshes are skipped in it.
+    pub(crate) alpha_tested: bool,
+
+    /// Start and size of all the data of this mesh in the draw data buffer.
+    pub(crate) draw_data_offset: u32,
+    pub(crate) draw_data_size: u32,
+    /// Removed meshes keep their slot, so that the handles of the other meshes stay valid.
+    pub(crate) is_removed: bool,
 
     // data necessary for building blas
     #[cfg(feature = "gpu_ray_tracing")]
@@ -87,60 +134,321 @@ pub(crate) struct UploadedMesh {
     pub(crate) max_vertex: u32,
 }
 
+#[derive(Copy, Clone)]
+pub(crate) struct MeshLod {
+    pub(crate) index_buffer_offset: u32,
+    pub(crate) index_count: u32,
+}
+
+impl UploadedMesh {
+    /// Index buffer offset and index count of the LOD, the coarsest LOD is used if the mesh has fewer LODs.
+    #[inline]
+    pub(crate) fn lod_indices(&self, lod: u32) -> (u32, u32) {
+        match (lod as usize).checked_sub(1).map(|idx| idx.min(self.lods.len().saturating_sub(1))) {
+            Some(idx) if !self.lods.is_empty() => (self.lods[idx].index_buffer_offset, self.lods[idx].index_count),
+            _ => (self.index_buffer_offset, self.index_count),
+        }
+    }
+
+    /// Number of the LODs including LOD 0.
+    #[inline]
+    pub(crate) fn lod_count(&self) -> u32 {
+        self.lods.len() as u32 + 1
+    }
+
+    /// Move the offsets and addresses along with the mesh data, from the old start of the mesh data to the new one.
+    fn rebase(&mut self, new_base: u32) {
+        let old_base = self.draw_data_offset;
+
+        self.index_buffer_offset = self.index_buffer_offset - old_base + new_base;
+        for lod in self.lods.iter_mut() {
+            lod.index_buffer_offset = lod.index_buffer_offset - old_base + new_base;
+        }
+
+        #[cfg(feature = "gpu_ray_tracing")]
+        {
+            self.vertex_packed_address = self.vertex_packed_address - old_base as u64 + new_base as u64;
+            self.index_buffer_address = self.index_buffer_address - old_base as u64 + new_base as u64;
+        }
+
+        self.draw_data_offset = new_base;
+    }
+}
+
+/// Distance based mesh LOD selection.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MeshLodSettings {
+    /// Distances from the camera to the instance bounds where the coarser LODs start, in ascending order.
+    /// (i.e. LOD n + 1 is used beyond distances[n])
+    /// Meshes with fewer LODs stay at their coarsest one.
+    pub distances: Vec<f32>,
+}
+
+impl Default for MeshLodSettings {
+    fn default() -> Self {
+        Self {
+            distances: vec![15.0, 40.0, 100.0],
+        }
+    }
+}
+
+impl MeshLodSettings {
+    /// No LOD switching, every mesh is drawn in full detail.
+    pub fn disabled() -> Self {
+        Self {
+            distances: Vec::new(),
+        }
+    }
+
+    /// Select the LOD of an instance seen from the view position.
+    pub fn select_lod(&self, aabb: &AABB, view_position: Vec3) -> u32 {
+        // distance to the nearest point of the bounds, so that the big meshes near the camera stay detailed
+        let distance = view_position.clamp(aabb.min, aabb.max).distance(view_position);
+        self.distances.iter()
+            .take_while(|threshold| distance >= **threshold)
+            .count() as u32
+    }
+}
+
 #[derive(Clone)]
 pub struct MeshInstance {
     pub(crate) transform: Affine3A,
     pub(crate) handle: MeshHandle,
+    pub(crate) instance_handle: MeshInstanceHandle,
     /// mesh aabb in world space (i.e. transformed)
-    pub(crate) _aabb: AABB,
+    pub(crate) aabb: AABB,
+    pub(crate) material_override: Option<MaterialOverride>,
+    /// Multiplied into the base color of the instance, white means no tint.
+    pub(crate) tint: [f32; 4],
 }
 
+// Instances are sorted by mesh first, then multiple instances of the same mesh are distinguished by the instance handle.
 impl std::hash::Hash for MeshInstance {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        self.handle.hash(state)
+        self.handle.hash(state);
+        self.instance_handle.hash(state);
     }
 }
 
 impl PartialEq for MeshInstance {
     fn eq(&self, other: &Self) -> bool {
-        self.handle.eq(&other.handle)
+        self.handle.eq(&other.handle) && self.instance_handle.eq(&other.instance_handle)
     }
 }
 impl Eq for MeshInstance {}
 
 impl PartialOrd for MeshInstance {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        self.handle.partial_cmp(&other.handle)
+        Some(self.cmp(other))
     }
 }
 
 impl Ord for MeshInstance {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
         self.handle.cmp(&other.handle)
+            .then_with(|| self.instance_handle.cmp(&other.instance_handle))
     }
 }
 
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct MeshInstanceHandle(pub(crate) u32);
+
+/// Material factors of a mesh instance used instead of the ones of the mesh materials.
+/// Only the factors are overridden, the textures of the mesh materials are still sampled and multiplied with them
+/// (i.e. base color tints the albedo map, metallic and roughness scale the specular map).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MaterialOverride {
+    pub base_color: [f32; 4],
+    pub metallic: f32,
+    pub roughness: f32,
+}
+
+impl Default for MaterialOverride {
+    fn default() -> Self {
+        Self {
+            base_color: [1.0; 4],
+            metallic: 0.0,
+            roughness: 0.5,
+        }
+    }
+}
+
+// Same in shader_src/common/material.hlsl InstanceMaterialOverride
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct GpuMaterialOverride {
+    base_color: [f32; 4],
+    metalness: f32,
+    roughness: f32,
+    is_enabled: u32,
+    _padding: u32,
+    tint: [f32; 4],
+}
+
+impl GpuMaterialOverride {
+    fn new(mat_override: Option<MaterialOverride>, tint: [f32; 4]) -> Self {
+        match mat_override {
+            Some(mat_override) => Self {
+                base_color: mat_override.base_color,
+                metalness: mat_override.metallic,
+                roughness: mat_override.roughness,
+                is_enabled: 1,
+                _padding: 0,
+                tint,
+            },
+            None => Self {
+                base_color: [1.0; 4],
+                metalness: 0.0,
+                roughness: 0.0,
+                is_enabled: 0,
+                _padding: 0,
+                tint,
+            },
+        }
+    }
+}
+
+/// Transforms of the mesh instances in the last gbuffer rasterization, used to output the motion vectors.
+#[derive(Default)]
+struct PrevInstanceTransforms {
+    transforms: HashMap<MeshInstanceHandle, Affine3A>,
+}
+
+impl PrevInstanceTransforms {
+    /// Previous transforms of the instances in the same order, and remember the current ones for the next frame.
+    /// New instances have no motion in their first frame, i.e. their previous transforms are the current ones.
+    fn advance(&mut self, instances: impl Iterator<Item = (MeshInstanceHandle, Affine3A)> + Clone) -> Vec<Affine3A> {
+        let prev_transforms = instances.clone()
+            .map(|(handle, transform)| self.transforms.get(&handle).copied().unwrap_or(transform))
+            .collect();
+        self.transforms = instances.collect();
+
+        prev_transforms
+    }
+
+    #[inline]
+    fn clear(&mut self) {
+        self.transforms.clear();
+    }
+}
+
+/// Consecutive instances of the same mesh and LOD, drawn with a single instanced draw call.
 #[derive(Copy, Clone)]
-pub struct MeshInstanceHandle(u32);
+struct MeshInstanceBatch {
+    handle: MeshHandle,
+    lod: u32,
+    /// Index of the first instance in the instance transforms buffer.
+    first_instance: u32,
+    instance_count: u32,
+}
+
+/// Gpu format of the texture asset, only the color textures (rgba) can be in srgb space.
+fn texture_format(desc: &TextureDesc) -> vk::Format {
+    match (desc.channel_count, desc.gamma_space) {
+        (1, TextureGammaSpace::Linear) => vk::Format::R8_UNORM,
+        (2, TextureGammaSpace::Linear) => vk::Format::R8G8_UNORM,
+        (4, TextureGammaSpace::Linear) => vk::Format::R8G8B8A8_UNORM,
+        (4, TextureGammaSpace::Srgb) => vk::Format::R8G8B8A8_SRGB,
+        (channel_count, gamma_space) => panic!("Unsupported texture with {} channels in {:?} space!", channel_count, gamma_space),
+    }
+}
+
+/// Transpose to row-major matrix to be used in shader.
+fn to_row_major_transform(transform: &Affine3A) -> [f32; 12] {
+    [
+        transform.x_axis.x,
+        transform.y_axis.x,
+        transform.z_axis.x,
+        transform.translation.x,
+        transform.x_axis.y,
+        transform.y_axis.y,
+        transform.z_axis.y,
+        transform.translation.y,
+        transform.x_axis.z,
+        transform.y_axis.z,
+        transform.z_axis.z,
+        transform.translation.z,
+    ]
+}
+
+/// Push the per-instance data of a pass into the dynamic buffer.
+/// Returns None if the dynamic buffer overflows in this frame (i.e. too many instances), the pass should skip drawing then.
+fn try_push_instance_data<T: Copy>(dynamic_buffer: &mut DynamicBuffer, pass_name: &str, iter: impl ExactSizeIterator<Item = T>) -> Option<u32> {
+    dynamic_buffer.try_push_from_iter(iter)
+        .map_err(|err| glog::warn!("Skip drawing {}: {}", pass_name, err))
+        .ok()
+}
+
+/// Instances are sorted by mesh, so every mesh ends up in one batch per LOD in the common case.
+/// The instance index is the same as the index of the transform uploaded in mesh raster pass.
+fn batch_mesh_instances(mesh_instances: &BTreeSet<MeshInstance>, lod_of: impl Fn(&MeshInstance) -> u32) -> Vec<MeshInstanceBatch> {
+    let mut batches: Vec<MeshInstanceBatch> = Vec::new();
+
+    for (instance_idx, mesh_ins) in mesh_instances.iter().enumerate() {
+        let lod = lod_of(mesh_ins);
+
+        match batches.last_mut() {
+            Some(batch) if batch.handle == mesh_ins.handle && batch.lod == lod => {
+                batch.instance_count += 1;
+            }
+            _ => {
+                batches.push(MeshInstanceBatch {
+                    handle: mesh_ins.handle,
+                    lod,
+                    first_instance: instance_idx as u32,
+                    instance_count: 1,
+                });
+            }
+        }
+    }
+
+    batches
+}
 
 pub struct MeshRenderer {
     shadow_renderpass: Arc<RenderPass>,
+    depth_prepass_renderpass: Arc<RenderPass>,
     raster_renderpass: Arc<RenderPass>,
+    transparency_renderpass: Arc<RenderPass>,
     bindless_descriptor_set: vk::DescriptorSet,
 
     scheme: MeshRasterScheme,
     resolution: [u32; 2],
+    /// Fill the depth of the opaque meshes before gbuffer rasterization, so that the gbuffer pass only shades the visible pixels.
+    depth_prepass: bool,
+    lod_settings: MeshLodSettings,
+    /// Position the LODs are selected from, i.e. the camera of the view being rasterized.
+    lod_view_position: Vec3,
 
     meshes: Vec<UploadedMesh>, // mesh data used for CPU-side to submit draw call
     mesh_instances: BTreeSet<MeshInstance>, // BTree in Rust have better cache coherency (i.e. less cache miss), and it is sorted
-
-    current_draw_data_offset: u64,
+    /// Spatial queries of the instance bounds, rebuilt lazily by the next query after the instances changed.
+    instance_bvh: InstanceBvh,
+    is_instance_bvh_dirty: bool,
+
+    /// Instance index (plus one, zero means background) of each pixel written in gbuffer rasterization, used to pick mesh instances.
+    instance_id_img: Arc<Image>,
+    /// Access type of the instance id image after the last frame, Nothing means it is not rendered yet.
+    instance_id_img_access: AccessType,
+    /// Mesh instances in the order of the last gbuffer rasterization, indexed by the instance index.
+    rastered_instances: Vec<MeshInstanceHandle>,
+    prev_instance_transforms: PrevInstanceTransforms,
+
+    /// Instance handles are never reused (not even after clear_meshes()), since the instances can be removed along with their meshes.
+    next_instance_handle: u32,
+
+    draw_data_allocator: DrawDataAllocator,
     draw_data_buffer: Arc<Buffer>,
     mesh_buffer: Arc<Buffer>,
     bindless_tex_sizes_buffer: Buffer,
 
     bindless_images: Vec<Arc<Image>>,
     next_bindless_texture_idx: u32,
+    /// Bindless images (and the bindless slots) after these indices belong to the meshes, and are released when clearing meshes.
+    /// The slots may outnumber the images, since the missing textures are bound to the default textures.
+    mesh_bindless_images_start: Option<(usize, u32)>,
+    /// Reserved bindless textures of DEFAULT_MATERIAL_TEXTURES.
+    default_material_textures: Vec<(BindlessTexHandle, vk::ImageView)>,
 
     scene_aabb: AABB,
 
@@ -151,6 +459,8 @@ pub struct GBuffer {
     pub packed_gbuffer: RgHandle<Image>,
     pub geometric_normal: RgHandle<Image>,
     pub depth: RgHandle<Image>,
+    /// Screen space uv offset from the last frame to this frame (without jitter), only written where the depth is not zero (i.e. not the sky).
+    pub motion_vector: RgHandle<Image>,
 }
 
 pub enum MeshShadingContext {
@@ -169,8 +479,13 @@ struct UploadMaterial {
     roughness         : f32,
     base_color        : [f32; 4],
     emissive          : [f32; 3],
+    emissive_intensity: f32,
     texture_mapping   : [u32; 4],
     texture_transform : [[f32; 6]; 4],
+    alpha_blend       : u32,
+    unlit             : u32,
+    normal_strength   : f32,
+    normal_flip_green : u32,
 }
 
 impl MeshRenderer {
@@ -184,6 +499,13 @@ impl MeshRenderer {
                 )
             }
         );
+        let depth_prepass_renderpass = renderpass::create_render_pass(
+            &rhi.device,
+            renderpass::RenderPassDesc {
+                color_attachments: &[],
+                depth_attachment: Some(renderpass::RenderPassAttachmentDesc::new(GBUFFER_DEPTH_FORMAT)),
+            }
+        );
         let raster_renderpass = renderpass::create_render_pass(&rhi.device, 
             renderpass::RenderPassDesc {
                 color_attachments: &[
@@ -191,6 +513,19 @@ impl MeshRenderer {
                     renderpass::RenderPassAttachmentDesc::new(GBUFFER_PACK_FORMAT).useless_input(),
                     // geometric normal
                     renderpass::RenderPassAttachmentDesc::new(GBUFFER_GEOMETRIC_NORMAL_FORMAT).useless_input(),
+                    // instance id (cleared before rasterization)
+                    renderpass::RenderPassAttachmentDesc::new(GBUFFER_INSTANCE_ID_FORMAT),
+                    // motion vector
+                    renderpass::RenderPassAttachmentDesc::new(GBUFFER_MOTION_VECTOR_FORMAT).useless_input(),
+                ],
+                depth_attachment: Some(renderpass::RenderPassAttachmentDesc::new(GBUFFER_DEPTH_FORMAT)),
+            }
+        );
+        // blend onto the lit image, and only test against the depth of the opaque meshes
+        let transparency_renderpass = renderpass::create_render_pass(&rhi.device,
+            renderpass::RenderPassDesc {
+                color_attachments: &[
+                    renderpass::RenderPassAttachmentDesc::new(FORWARD_TRANSPARENCY_COLOR_FORMAT),
                 ],
                 depth_attachment: Some(renderpass::RenderPassAttachmentDesc::new(GBUFFER_DEPTH_FORMAT)),
             }
@@ -201,23 +536,27 @@ impl MeshRenderer {
             vk::BufferUsageFlags::INDEX_BUFFER |
             vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS |
             vk::BufferUsageFlags::TRANSFER_DST |
+            vk::BufferUsageFlags::TRANSFER_SRC |
             vk::BufferUsageFlags::ACCELERATION_STRUCTURE_BUILD_INPUT_READ_ONLY_KHR;
         #[cfg(not(feature = "gpu_ray_tracing"))]
         let universal_draw_data_buffer_usage_flag = vk::BufferUsageFlags::STORAGE_BUFFER |
             vk::BufferUsageFlags::INDEX_BUFFER |
             vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS |
-            vk::BufferUsageFlags::TRANSFER_DST;
+            vk::BufferUsageFlags::TRANSFER_DST |
+            vk::BufferUsageFlags::TRANSFER_SRC;
 
         // create giant buffers to contain mesh vertex data and index data
         let universal_draw_data_buffer_desc: BufferDesc = BufferDesc::new_gpu_only(
-            1024 * 1024 * 512, // 512 MB
+            DRAW_DATA_BUFFER_SIZE,
             universal_draw_data_buffer_usage_flag
-        );
+        )
+        .category(MemoryCategory::MeshBuffer);
         
         let universal_mesh_buffer_desc: BufferDesc = BufferDesc::new_cpu_to_gpu(
             MAX_GPU_MESH_COUNT * std::mem::size_of::<GpuMesh>(),
             vk::BufferUsageFlags::STORAGE_BUFFER
-        );
+        )
+        .category(MemoryCategory::MeshBuffer);
 
         let texture_sizes_buffer_desc: BufferDesc = BufferDesc::new_cpu_to_gpu(
             rhi.device.max_bindless_descriptor_count() as usize * std::mem::size_of::<[f32; 4]>(),
@@ -238,24 +577,42 @@ impl MeshRenderer {
             .create_buffer(texture_sizes_buffer_desc, "bindless textures sizes")
             .unwrap();
 
+        let instance_id_img = Self::create_instance_id_image(&rhi.device, resolution);
+
         Self {
             shadow_renderpass,
+            depth_prepass_renderpass,
             raster_renderpass,
+            transparency_renderpass,
             bindless_descriptor_set: vk::DescriptorSet::null(),
 
             scheme,
             resolution,
+            depth_prepass: false,
+            lod_settings: MeshLodSettings::default(),
+            lod_view_position: Vec3::ZERO,
 
             meshes: Default::default(),
             mesh_instances: Default::default(),
+            instance_bvh: InstanceBvh::default(),
+            is_instance_bvh_dirty: false,
+
+            instance_id_img,
+            instance_id_img_access: AccessType::Nothing,
+            rastered_instances: Vec::new(),
+            prev_instance_transforms: PrevInstanceTransforms::default(),
+
+            next_instance_handle: 0,
 
-            current_draw_data_offset: 0,
+            draw_data_allocator: DrawDataAllocator::new(DRAW_DATA_BUFFER_SIZE as u64, DRAW_DATA_ALIGNMENT),
             draw_data_buffer,
             mesh_buffer,
             bindless_tex_sizes_buffer,
 
             bindless_images: Vec::new(),
             next_bindless_texture_idx: 0,
+            mesh_bindless_images_start: None,
+            default_material_textures: Vec::new(),
 
             scene_aabb: AABB::new(),
 
@@ -263,6 +620,35 @@ impl MeshRenderer {
         }
     }
 
+    fn create_instance_id_image(device: &Device, resolution: [u32; 2]) -> Arc<Image> {
+        let image = device.create_image(
+            ImageDesc::new_2d(resolution, GBUFFER_INSTANCE_ID_FORMAT)
+                .usage_flags(vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::TRANSFER_SRC | vk::ImageUsageFlags::TRANSFER_DST),
+            None
+        ).unwrap();
+        device.set_debug_name(image.raw, "gbuffer instance id");
+
+        Arc::new(image)
+    }
+
+    /// Change the resolution of the gbuffer.
+    /// The old resolution resources are released once the frames in flight are done.
+    pub fn set_resolution(&mut self, rhi: &Rhi, resolution: [u32; 2]) {
+        if self.resolution == resolution {
+            return;
+        }
+
+        let new_img = Self::create_instance_id_image(&rhi.device, resolution);
+        let old_img = Arc::try_unwrap(std::mem::replace(&mut self.instance_id_img, new_img))
+            .expect("Failed to release instance id image, someone is still using it!");
+        rhi.device.defer_release(old_img);
+
+        self.instance_id_img_access = AccessType::Nothing;
+        self.rastered_instances.clear();
+        self.prev_instance_transforms.clear();
+        self.resolution = resolution;
+    }
+
     pub fn add_bindless_image(&mut self, image: Arc<Image>) -> BindlessTexHandle {
         let extent = image.desc.extent;
         let extent_inv_extent = [
@@ -293,17 +679,22 @@ impl MeshRenderer {
             let read_guard = registry.read();
             
             if let Some(asset) = read_guard.get_asset(&handle) {
-                let (extent, img_subresources, gamma_space) = if let Some(tex_asset) = asset.as_texture() {
-                    // TODO: identify image type
+                let (extent, img_subresources, desc) = if let Some(tex_asset) = asset.as_texture() {
+                    let desc = tex_asset.desc;
                     let uploads = tex_asset.lod_groups.iter()
-                        .map(|mip| ImageSubResource {
-                            data: mip.as_slice(),
-                            row_pitch_in_bytes: tex_asset.extent[0] * 4,
-                            base_layer: 0,
+                        .enumerate()
+                        .map(|(level, mip)| {
+                            assert_eq!(mip.len(), desc.mip_size_in_bytes(tex_asset.extent, level as u32), "Texture mip {} size mismatch!", level);
+
+                            ImageSubResource {
+                                data: mip.as_slice(),
+                                row_pitch_in_bytes: desc.row_pitch_in_bytes(tex_asset.extent, level as u32),
+                                base_layer: 0,
+                            }
                         })
                         .collect::<Vec<_>>();
 
-                    (tex_asset.extent, uploads, tex_asset.desc.gamma_space)
+                    (tex_asset.extent, uploads, desc)
                 } else if let Some(baked_tex) = asset.as_baked() {
                     let tex_field_reader = read_guard.get_baked_texture_asset(baked_tex);
                     let desc = tex_field_reader.desc();
@@ -315,15 +706,16 @@ impl MeshRenderer {
                     for i in 0..lod_length {
                         let lod = tex_field_reader.lod_groups(VecArrayQueryParam::index(i)).array();
 
+                        assert_eq!(lod.len(), desc.mip_size_in_bytes(extent, i as u32), "Texture mip {} size mismatch!", i);
+
                         uploads.push(ImageSubResource {
                             data: lod,
-                            // TODO: no hardcode
-                            row_pitch_in_bytes: extent[0] * 4,
+                            row_pitch_in_bytes: desc.row_pitch_in_bytes(extent, i as u32),
                             base_layer: 0,
                         });
                     }
 
-                    (extent, uploads, desc.gamma_space)
+                    (extent, uploads, desc)
                 } else {
                     panic!("Expect texture asset handle!");
                 };
@@ -333,16 +725,31 @@ impl MeshRenderer {
                     (extent[0] as f32).recip(), (extent[1] as f32).recip()
                 ];
 
-                let img_format = match gamma_space {
-                    TextureGammaSpace::Linear => vk::Format::R8G8B8A8_UNORM,
-                    TextureGammaSpace::Srgb => vk::Format::R8G8B8A8_SRGB,
-                };
+                let img_format = texture_format(&desc);
+
+                // only the base level is provided, generate the mipmaps on the gpu
+                let mut need_gen_mipmaps = desc.use_mipmap && img_subresources.len() == 1;
+                if need_gen_mipmaps && !self.device.supports_linear_blit(img_format) {
+                    glog::warn!("Format {:?} doesn't support linear blit, the texture is created without mipmaps!", img_format);
+                    need_gen_mipmaps = false;
+                }
 
                 // create gpu image
-                let image_desc = ImageDesc::new_2d([extent[0], extent[1]], img_format)
-                    .mipmap_level(img_subresources.len() as _)
-                    .usage_flags(vk::ImageUsageFlags::SAMPLED);
-                let image = Arc::new(self.device.create_image(image_desc, Some(img_subresources)).unwrap());
+                let image_desc = if need_gen_mipmaps {
+                    ImageDesc::new_2d([extent[0], extent[1]], img_format)
+                        .full_mipmap_levels()
+                        .usage_flags(vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_SRC | vk::ImageUsageFlags::TRANSFER_DST)
+                } else {
+                    ImageDesc::new_2d([extent[0], extent[1]], img_format)
+                        .mipmap_level(img_subresources.len() as _)
+                        .usage_flags(vk::ImageUsageFlags::SAMPLED)
+                };
+                let image = self.device.create_image(image_desc, Some(img_subresources)).unwrap();
+                if need_gen_mipmaps {
+                    self.device.generate_mipmaps(&image, AccessType::AnyShaderReadSampledImageOrUniformTexelBuffer)
+                        .expect("Failed to generate mipmaps for bindless image!");
+                }
+                let image = Arc::new(image);
                 let image_view = image.view(&self.device, &ImageViewDesc::default()).unwrap();
 
                 let handle = self.add_bindless_image_view(image_view);
@@ -363,6 +770,44 @@ impl MeshRenderer {
         BindlessTexHandle(u32::MAX)
     }
 
+    /// Reserve the bindless slots of the default material textures, must be called before adding any mesh.
+    pub(crate) fn add_default_material_textures(&mut self) {
+        assert!(self.default_material_textures.is_empty() && self.mesh_bindless_images_start.is_none());
+
+        for (name, texel) in DEFAULT_MATERIAL_TEXTURES {
+            let image = self.device.create_image(
+                ImageDesc::new_2d([1, 1], vk::Format::R8G8B8A8_UNORM)
+                    .usage_flags(vk::ImageUsageFlags::SAMPLED),
+                Some(vec![ImageSubResource {
+                    data: &texel,
+                    row_pitch_in_bytes: 4,
+                    base_layer: 0,
+                }])
+            ).unwrap();
+            self.device.set_debug_name(image.raw, name);
+
+            let view = image.view(&self.device, &ImageViewDesc::default()).unwrap();
+            let handle = self.add_bindless_image(Arc::new(image));
+
+            self.default_material_textures.push((handle, view));
+        }
+    }
+
+    /// Bind the default texture of the material texture slot into a new bindless slot, so that the indices of the material textures are kept.
+    fn add_default_material_texture(&mut self, tex_slot: usize) -> BindlessTexHandle {
+        let (_, view) = self.default_material_textures[tex_slot % DEFAULT_MATERIAL_TEXTURES.len()];
+        let handle = self.add_bindless_image_view(view);
+
+        bytemuck::checked::cast_slice_mut::<u8, [f32; 4]>(
+            self.bindless_tex_sizes_buffer
+                .allocation
+                .mapped_slice_mut()
+                .unwrap()
+        )[handle.0 as usize] = [1.0; 4];
+
+        handle
+    }
+
     pub(crate) fn add_bindless_image_view(&mut self, view: vk::ImageView) -> BindlessTexHandle {
         let handle = BindlessTexHandle(self.next_bindless_texture_idx);
         self.next_bindless_texture_idx += 1;
@@ -403,6 +848,9 @@ impl MeshRenderer {
                     let tangents = mesh_asset.tangents.as_slice();
                     let indices = mesh_asset.indices.as_slice();
                     let mat_ids = mesh_asset.material_ids.as_slice();
+                    let lod_indices = mesh_asset.lod_indices.iter()
+                        .map(|lod| lod.as_slice())
+                        .collect::<Vec<_>>();
 
                     let mut upload_materials = Vec::new();
                     for mat_ref in mesh_asset.materials.iter() {
@@ -418,8 +866,13 @@ impl MeshRenderer {
                             roughness: material.roughness,
                             base_color: material.base_color,
                             emissive: material.emissive,
+                            emissive_intensity: material.emissive_intensity,
                             texture_mapping: texture_mapping,
                             texture_transform: material.texture_transform,
+                            alpha_blend: material.alpha_blend as u32,
+                            unlit: material.unlit as u32,
+                            normal_strength: material.normal_strength,
+                            normal_flip_green: material.normal_flip_green as u32,
                         };
 
                         upload_materials.push(upload);
@@ -428,7 +881,7 @@ impl MeshRenderer {
                     let mesh_tex_mask = self.add_mesh_bindless_textures(&handle);
 
                     return self.upload_gpu_mesh_data(
-                        packed, colors, uvs, tangents, indices, mat_ids,
+                        packed, colors, uvs, tangents, indices, &lod_indices, mat_ids,
                         &upload_materials, mesh_tex_mask, mesh_asset.aabb
                     );
                 } else if let Some(baked) = asset.as_baked() {
@@ -440,6 +893,10 @@ impl MeshRenderer {
                     let tangents = field_reader.tangents();
                     let indices = field_reader.indices();
                     let mat_ids = field_reader.material_ids();
+                    let lod_count = field_reader.lod_indices(VecArrayQueryParam::length()).length();
+                    let lod_indices = (0..lod_count)
+                        .map(|idx| field_reader.lod_indices(VecArrayQueryParam::index(idx)).array())
+                        .collect::<Vec<_>>();
 
                     let mat_refs = read_guard.get_asset_relative_materials(handle)
                         .expect(format!("Failed to get mesh relative materials: {:?}", handle).as_str());
@@ -461,8 +918,13 @@ impl MeshRenderer {
                             roughness: mat_field_reader.roughness(),
                             base_color: mat_field_reader.base_color(),
                             emissive: mat_field_reader.emissive(),
+                            emissive_intensity: mat_field_reader.emissive_intensity(),
                             texture_mapping: texture_mapping,
                             texture_transform: mat_field_reader.texture_transform(),
+                            alpha_blend: mat_field_reader.alpha_blend() as u32,
+                            unlit: mat_field_reader.unlit() as u32,
+                            normal_strength: mat_field_reader.normal_strength(),
+                            normal_flip_green: mat_field_reader.normal_flip_green() as u32,
                         };
 
                         upload_materials.push(upload);
@@ -471,7 +933,7 @@ impl MeshRenderer {
                     let mesh_tex_mask = self.add_mesh_bindless_textures(&handle);
 
                     return self.upload_gpu_mesh_data(
-                        packed, colors, uvs, tangents, indices, mat_ids,
+                        packed, colors, uvs, tangents, indices, &lod_indices, mat_ids,
                         &upload_materials, mesh_tex_mask, field_reader.aabb()
                     );
                 } else {
@@ -484,6 +946,10 @@ impl MeshRenderer {
     }
 
     fn add_mesh_bindless_textures(&mut self, handle: &Arc<AssetHandle>) -> u32 {
+        if self.mesh_bindless_images_start.is_none() {
+            self.mesh_bindless_images_start = Some((self.bindless_images.len(), self.next_bindless_texture_idx));
+        }
+
         let read_guard = get_runtime_asset_registry().read();
 
         let mut mesh_tex_mask = 0;
@@ -506,9 +972,13 @@ impl MeshRenderer {
                     // since we guess which kind of texture it is to fill the mask.
                     mesh_tex_mask |= 1 << idx;
                 }
+
+                let _bindless_tex_handle = self.add_bindless_image_asset(tex_ref.handle());
+            } else {
+                // the texture failed to load, keep the slot with the default texture and skip sampling it
+                glog::warn!("Missing texture {} of mesh {:?}, use the default texture instead.", idx, handle);
+                let _bindless_tex_handle = self.add_default_material_texture(idx);
             }
-            
-            let _bindless_tex_handle = self.add_bindless_image_asset(tex_ref.handle());
         }
 
         mesh_tex_mask
@@ -516,7 +986,9 @@ impl MeshRenderer {
 
     pub fn add_mesh_instance(&mut self, handle: MeshHandle, transform: Affine3A) -> MeshInstanceHandle {
         debug_assert!(MeshHandle::is_valid(handle));
-        let instance_handle = MeshInstanceHandle(self.mesh_instances.len() as _);
+        debug_assert!(!self.meshes[handle.id as usize].is_removed, "Can not add instances of a removed mesh!");
+        let instance_handle = MeshInstanceHandle(self.next_instance_handle);
+        self.next_instance_handle += 1;
 
         let mut mesh_aabb = self.meshes[handle.id as usize].aabb;
         mesh_aabb.transform(transform.into());
@@ -526,39 +998,189 @@ impl MeshRenderer {
         self.mesh_instances.insert(MeshInstance {
             transform,
             handle,
-            _aabb: mesh_aabb
+            instance_handle,
+            aabb: mesh_aabb,
+            material_override: None,
+            tint: [1.0; 4],
         });
+        self.is_instance_bvh_dirty = true;
+
         instance_handle
     }
 
+    /// Transforms of all the mesh instances, in the order they are added.
+    pub fn get_mesh_instance_transforms(&self) -> Vec<(MeshInstanceHandle, Affine3A)> {
+        let mut transforms = self.mesh_instances.iter()
+            .map(|inst| (inst.instance_handle, inst.transform))
+            .collect::<Vec<_>>();
+        transforms.sort_by_key(|(handle, _)| *handle);
+        transforms
+    }
+
+    /// Move a mesh instance, return false if the instance doesn't exist.
+    pub fn set_mesh_instance_transform(&mut self, instance_handle: MeshInstanceHandle, transform: Affine3A) -> bool {
+        let Some(mut instance) = self.mesh_instances.iter()
+            .find(|inst| inst.instance_handle == instance_handle)
+            .cloned() else {
+            return false;
+        };
+
+        // transform doesn't take part in the ordering, but the set element can not be mutated in place
+        self.mesh_instances.remove(&instance);
+
+        instance.transform = transform;
+        instance.aabb = self.meshes[instance.handle.id as usize].aabb;
+        instance.aabb.transform(transform.into());
+        self.mesh_instances.insert(instance);
+
+        // the old aabb may shrink, so merge it from scratch
+        self.scene_aabb.reset();
+        for inst in self.mesh_instances.iter() {
+            self.scene_aabb.merge_aabb(&inst.aabb);
+        }
+        self.is_instance_bvh_dirty = true;
+
+        true
+    }
+
+    /// Mesh instances whose world space bounds are (conservatively) inside the frustum, e.g. from Camera::get_camera_frustum().
+    pub fn query_frustum(&mut self, frustum: &Frustum) -> Vec<MeshInstanceHandle> {
+        self.instance_bvh().query_frustum(frustum)
+    }
+
+    /// The mesh instance whose world space bounds are hit first by the ray, and the hit distance in the unit of the ray direction.
+    /// Only the bounds are tested, so the hit may be in the empty space of an instance.
+    /// Use pick() for the exact instance under a pixel.
+    pub fn raycast(&mut self, ray: &Ray) -> Option<(MeshInstanceHandle, f32)> {
+        self.instance_bvh().raycast(ray)
+    }
+
+    fn instance_bvh(&mut self) -> &InstanceBvh {
+        if self.is_instance_bvh_dirty {
+            self.instance_bvh = InstanceBvh::build(self.mesh_instances.iter().map(|inst| (inst.instance_handle, inst.aabb)));
+            self.is_instance_bvh_dirty = false;
+        }
+
+        &self.instance_bvh
+    }
+
+    /// Draw the depth of the opaque meshes in a position only pass before gbuffer rasterization, 
+    /// then the gbuffer pass tests the depth with EQUAL and shades every pixel at most once.
+    /// 
+    /// It pays off in the scenes with a lot of overdraw, and costs an extra geometry pass in the simple scenes.
+    /// Meshes with albedo textures may discard pixels by the alpha, so they are skipped in the prepass.
+    #[inline]
+    pub fn set_depth_prepass(&mut self, enabled: bool) {
+        self.depth_prepass = enabled;
+    }
+
+    #[inline]
+    pub fn set_lod_settings(&mut self, settings: MeshLodSettings) {
+        self.lod_settings = settings;
+    }
+
+    #[inline]
+    pub fn get_lod_settings(&self) -> &MeshLodSettings {
+        &self.lod_settings
+    }
+
+    /// Batches of the instances with the LODs selected from the current view position.
+    /// All the passes of a view must use the same batches, the depth prepass relies on the exactly same triangles.
+    fn batch_instances_with_lod(&self, view_position: Vec3) -> Vec<MeshInstanceBatch> {
+        let meshes = &self.meshes;
+        let lod_settings = &self.lod_settings;
+
+        batch_mesh_instances(&self.mesh_instances, |ins| {
+            let lod_count = meshes[ins.handle.id as usize].lod_count();
+            lod_settings.select_lod(&ins.aabb, view_position).min(lod_count - 1)
+        })
+    }
+
+    #[inline]
+    pub fn is_depth_prepass_enabled(&self) -> bool {
+        self.depth_prepass
+    }
+
+    /// Set whether the instances of the mesh are drawn in the shadow maps.
+    pub fn set_mesh_cast_shadow(&mut self, handle: MeshHandle, cast_shadow: bool) {
+        debug_assert!(MeshHandle::is_valid(handle));
+        self.meshes[handle.id as usize].cast_shadow = cast_shadow;
+    }
+
+    /// Override the material factors of a mesh instance, the mesh data is not re-uploaded.
+    /// Pass None to restore the mesh materials. Return false if the instance doesn't exist.
+    pub fn override_instance_material(&mut self, instance_handle: MeshInstanceHandle, material: Option<MaterialOverride>) -> bool {
+        let Some(mut instance) = self.mesh_instances.iter()
+            .find(|inst| inst.instance_handle == instance_handle)
+            .cloned() else {
+            return false;
+        };
+
+        self.mesh_instances.remove(&instance);
+        instance.material_override = material;
+        self.mesh_instances.insert(instance);
+
+        true
+    }
+
+    /// Multiply the base color of a mesh instance by the tint, on top of its material or material override.
+    /// White restores the untinted instance. Return false if the instance doesn't exist.
+    pub fn set_instance_tint(&mut self, instance_handle: MeshInstanceHandle, tint: [f32; 4]) -> bool {
+        let Some(mut instance) = self.mesh_instances.iter()
+            .find(|inst| inst.instance_handle == instance_handle)
+            .cloned() else {
+            return false;
+        };
+
+        self.mesh_instances.remove(&instance);
+        instance.tint = tint;
+        self.mesh_instances.insert(instance);
+
+        true
+    }
+
     fn upload_gpu_mesh_data(&mut self,
         packed: &[PackedVertex], colors: &[[f32; 4]],
         uvs: &[[f32; 2]], tangents: &[[f32; 4]],
-        indices: &[u32], mat_ids: &[u32],
+        indices: &[u32], lod_indices: &[&[u32]], mat_ids: &[u32],
         upload_materials: &[UploadMaterial],
         mesh_tex_mask: u32, aabb: AABB,
     ) -> MeshHandle {
-        let curr_global_offset = self.current_draw_data_offset as u32;
-
-        // copy upload
+        // copy upload, the offsets are relative to the start of the mesh data until it is allocated
         let mut copy_engine = CopyEngine::new();
         
-        let packed_offset  = copy_engine.copy(&packed)   + curr_global_offset;
-        let color_offset   = copy_engine.copy(&colors)   + curr_global_offset;
-        let uv_offset      = copy_engine.copy(&uvs)      + curr_global_offset;
-        let tangent_offset = copy_engine.copy(&tangents) + curr_global_offset;
-        let index_offset   = copy_engine.copy(&indices)  + curr_global_offset;
-        let mat_id_offset  = copy_engine.copy(&mat_ids)  + curr_global_offset;
-        let mat_data_offset = copy_engine.copy(&upload_materials) + curr_global_offset;
-
-        let totol_size_bytes = copy_engine.current_offset();
+        let packed_offset  = copy_engine.copy(&packed);
+        let color_offset   = copy_engine.copy(&colors);
+        let uv_offset      = copy_engine.copy(&uvs);
+        let tangent_offset = copy_engine.copy(&tangents);
+        let index_offset   = copy_engine.copy(&indices);
+        let mat_id_offset  = copy_engine.copy(&mat_ids);
+        let mat_data_offset = copy_engine.copy(&upload_materials);
+        let mut lods = lod_indices.iter()
+            .map(|lod| MeshLod {
+                index_buffer_offset: copy_engine.copy(lod),
+                index_count: lod.len() as u32,
+            })
+            .collect::<Vec<_>>();
+
+        let total_size_bytes = copy_engine.current_offset();
+        let curr_global_offset = self.allocate_draw_data(total_size_bytes as u64) as u32;
         copy_engine.upload(
             &self.device,
             &self.draw_data_buffer, 
             curr_global_offset
         ).expect("Failed to upload mesh data with copy engine!");
 
-        self.current_draw_data_offset += totol_size_bytes as u64;
+        let packed_offset  = packed_offset  + curr_global_offset;
+        let color_offset   = color_offset   + curr_global_offset;
+        let uv_offset      = uv_offset      + curr_global_offset;
+        let tangent_offset = tangent_offset + curr_global_offset;
+        let index_offset   = index_offset   + curr_global_offset;
+        let mat_id_offset  = mat_id_offset  + curr_global_offset;
+        let mat_data_offset = mat_data_offset + curr_global_offset;
+        for lod in lods.iter_mut() {
+            lod.index_buffer_offset += curr_global_offset;
+        }
 
         let mesh_id = self.meshes.len();
 
@@ -591,8 +1213,17 @@ impl MeshRenderer {
         self.meshes.push(UploadedMesh {
             index_count: indices.len() as u32,
             index_buffer_offset: index_offset,
+            lods,
 
             aabb,
+            alpha_blend: upload_materials.iter().any(|mat| mat.alpha_blend != 0),
+            opaque: !upload_materials.iter().all(|mat| mat.alpha_blend != 0),
+            cast_shadow: !upload_materials.iter().all(|mat| mat.unlit != 0),
+            alpha_tested: mesh_tex_mask & TEXTURE_MASK_ALBEDO_BIT != 0,
+
+            draw_data_offset: curr_global_offset,
+            draw_data_size: total_size_bytes,
+            is_removed: false,
 
             #[cfg(feature = "gpu_ray_tracing")]
             vertex_packed_address,
@@ -621,6 +1252,173 @@ impl MeshRenderer {
         self.mesh_instances.clone()
     }
 
+    /// Allocate the draw data of a new mesh, the draw data buffer is compacted if the free space is only fragmented.
+    fn allocate_draw_data(&mut self, size: u64) -> u64 {
+        if let Some(offset) = self.draw_data_allocator.allocate(size) {
+            return offset;
+        }
+
+        // uploading waits for the device to be idle anyway, so the stall of compaction is not noticeable here
+        self.compact_draw_data();
+        self.draw_data_allocator.allocate(size)
+            .unwrap_or_else(|| panic!("Out of draw data memory to upload a mesh of {} bytes! {:?}", size, self.draw_data_allocator.stats()))
+    }
+
+    /// Remove the mesh and all of its instances. Return false if the mesh is already removed.
+    ///
+    /// The draw data of the mesh is reused by the new meshes once the frames in flight are done,
+    /// the handle of the mesh is not reused.
+    pub fn remove_mesh(&mut self, handle: MeshHandle) -> bool {
+        assert!((handle.id as usize) < self.meshes.len() && MeshHandle::is_valid(handle), "Invalid mesh handle!");
+
+        let mesh = &mut self.meshes[handle.id as usize];
+        if mesh.is_removed {
+            return false;
+        }
+        mesh.is_removed = true;
+
+        self.draw_data_allocator.free(
+            mesh.draw_data_offset as u64,
+            mesh.draw_data_size as u64,
+            self.device.current_frame_number()
+        );
+
+        self.mesh_instances.retain(|inst| inst.handle != handle);
+        self.scene_aabb.reset();
+        for inst in self.mesh_instances.iter() {
+            self.scene_aabb.merge_aabb(&inst.aabb);
+        }
+        self.is_instance_bvh_dirty = true;
+
+        true
+    }
+
+    /// Move the draw data of all the meshes to the start of the draw data buffer, so that the free space becomes one region.
+    /// It is done automatically when a new mesh doesn't fit in any free region.
+    ///
+    /// This waits for the device to be idle. The meshes keep their handles, and their offsets and addresses are updated.
+    /// The built blas are not affected, since they own a copy of the geometry.
+    pub fn compact_draw_data(&mut self) {
+        self.device.wait_idle();
+
+        let live_meshes = self.meshes.iter()
+            .enumerate()
+            .filter(|(_, mesh)| !mesh.is_removed)
+            .map(|(idx, mesh)| (idx, mesh.draw_data_offset as u64, mesh.draw_data_size as u64))
+            .collect::<Vec<_>>();
+        let live_regions = live_meshes.iter()
+            .map(|(_, offset, size)| (*offset, *size))
+            .collect::<Vec<_>>();
+        let new_offsets = self.draw_data_allocator.compact(&live_regions);
+
+        // (mesh index, old offset, new offset, size)
+        let moves = live_meshes.iter()
+            .zip(new_offsets)
+            .filter(|((_, old_offset, _), new_offset)| old_offset != new_offset)
+            .map(|((idx, old_offset, size), new_offset)| (*idx, *old_offset, new_offset, *size))
+            .collect::<Vec<_>>();
+
+        if moves.is_empty() {
+            return;
+        }
+
+        // the old and new regions may overlap, so the data goes through a temporary buffer
+        let mut temp_size = 0;
+        let (to_temp_regions, from_temp_regions): (Vec<_>, Vec<_>) = moves.iter()
+            .map(|(_, old_offset, new_offset, size)| {
+                let to_temp = vk::BufferCopy::builder()
+                    .src_offset(*old_offset)
+                    .dst_offset(temp_size)
+                    .size(*size)
+                    .build();
+                let from_temp = vk::BufferCopy::builder()
+                    .src_offset(temp_size)
+                    .dst_offset(*new_offset)
+                    .size(*size)
+                    .build();
+
+                temp_size += size;
+                (to_temp, from_temp)
+            })
+            .unzip();
+
+        let temp_buffer = self.device.create_buffer(
+            BufferDesc::new_gpu_only(
+                temp_size as usize,
+                vk::BufferUsageFlags::TRANSFER_SRC | vk::BufferUsageFlags::TRANSFER_DST
+            ),
+            "draw data compaction buffer"
+        ).expect("Failed to create draw data compaction buffer!");
+
+        self.device.with_setup_commands(|cb| unsafe {
+            self.device.raw.cmd_copy_buffer(cb, self.draw_data_buffer.raw, temp_buffer.raw, &to_temp_regions);
+
+            self.device.raw.cmd_pipeline_barrier(
+                cb,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::DependencyFlags::empty(),
+                &[vk::MemoryBarrier::builder()
+                    .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+                    .dst_access_mask(vk::AccessFlags::TRANSFER_READ | vk::AccessFlags::TRANSFER_WRITE)
+                    .build()],
+                &[],
+                &[],
+            );
+
+            self.device.raw.cmd_copy_buffer(cb, temp_buffer.raw, self.draw_data_buffer.raw, &from_temp_regions);
+        }).expect("Failed to compact draw data!");
+
+        self.device.destroy_buffer(temp_buffer);
+
+        let gpu_meshes = unsafe {
+            let ptr = self.mesh_buffer.allocation.mapped_ptr().unwrap().as_ptr() as *mut GpuMesh;
+            std::slice::from_raw_parts_mut(ptr, MAX_GPU_MESH_COUNT)
+        };
+        for (idx, old_offset, new_offset, _) in moves.iter() {
+            gpu_meshes[*idx].rebase(*old_offset as u32, *new_offset as u32);
+            self.meshes[*idx].rebase(*new_offset as u32);
+        }
+
+        glog::info!("Compacted draw data buffer, moved {} meshes ({:.1} MB).", moves.len(), temp_size as f64 / (1024.0 * 1024.0));
+    }
+
+    /// Usage and fragmentation of the draw data buffer, which contains the vertices and indices of all the meshes.
+    #[inline]
+    pub fn draw_data_stats(&self) -> DrawDataStats {
+        self.draw_data_allocator.stats()
+    }
+
+    /// Remove all the meshes and mesh instances, and release the gpu resources they use.
+    /// Bindless images not added by meshes (e.g. luts) are kept.
+    /// 
+    /// The images are released once the frames in flight are done, but the draw data buffer is reused right away,
+    /// so caller must make sure GPU is not using it anymore (e.g. wait device idle).
+    pub fn clear_meshes(&mut self, rhi: &Rhi) {
+        self.mesh_instances.clear();
+        self.instance_bvh = InstanceBvh::default();
+        self.is_instance_bvh_dirty = false;
+        self.meshes.clear();
+        self.rastered_instances.clear();
+        // otherwise the instances added after this would get the motion of the removed ones
+        self.prev_instance_transforms.clear();
+
+        // the whole draw data buffer can be reused
+        self.draw_data_allocator.reset();
+        // keep counting the instance handles, so that the handles held by the callers never refer to the new instances
+        self.scene_aabb.reset();
+
+        if let Some((image_start, slot_start)) = self.mesh_bindless_images_start.take() {
+            for img in self.bindless_images.drain(image_start..) {
+                let img = Arc::try_unwrap(img)
+                    .expect("Failed to release mesh bindless images, someone is still using it!");
+                rhi.device.defer_release(img);
+            }
+
+            self.next_bindless_texture_idx = slot_start;
+        }
+    }
+
     #[inline]
     pub fn get_scene_aabb(&self) -> AABB {
         self.scene_aabb
@@ -652,25 +1450,145 @@ impl MeshRenderer {
         &mut self,
         rg: &mut RenderGraphBuilder,
         light_render_data: LightRenderData,
+        draw_shadow: bool,
+        view_position: Vec3,
     ) -> (MeshShadingContext, Vec<RgHandle<Image>>) {
-        let mesh_shading_context = self.draw_mesh_raster(rg);
-        let shadow_maps = self.draw_shadow_map(rg, light_render_data);
+        // draw data of the removed meshes is free to reuse once no frame in flight draws them
+        self.draw_data_allocator.reclaim(self.device.completed_frame_number());
+
+        self.lod_view_position = view_position;
+        let mesh_shading_context = self.draw_mesh_raster(rg, self.resolution, true);
+        // the shadow maps are still returned to be bound, but they are not sampled when the shadows are disabled
+        let shadow_maps = if draw_shadow {
+            self.draw_shadow_map(rg, light_render_data)
+        } else {
+            light_render_data.light_maps
+        };
 
         (mesh_shading_context, shadow_maps)
     }
 
+    /// Rasterize the gbuffer of another view (e.g. another camera viewport) in the resolution.
+    /// 
+    /// The camera comes from the frame constants, so set the camera override of the render graph before calling it.
+    /// Only the main view can be picked, and the motion vectors of the other views contain only the camera motion.
+    pub fn prepare_view_rg(&mut self, rg: &mut RenderGraphBuilder, resolution: [u32; 2], view_position: Vec3) -> MeshShadingContext {
+        self.lod_view_position = view_position;
+        self.draw_mesh_raster(rg, resolution, false)
+    }
+
+    /// Draw the depth of the opaque meshes which are not alpha tested, with the position only shadow mapping shader.
+    fn draw_depth_prepass(&self, rg: &mut RenderGraphBuilder, depth: &mut RgHandle<Image>, resolution: [u32; 2]) {
+        let bindless_descriptor = self.bindless_descriptor_set;
+        let prepass_renderpass = self.depth_prepass_renderpass.clone();
+
+        let mut pass = rg.add_pass("depth prepass");
+        let pipeline = pass.register_raster_pipeline(&[
+                PipelineShaderDesc::builder()
+                    .stage(PipelineShaderStage::Vertex)
+                    .source("shadow/shadow_mapping.hlsl")
+                    .entry("vs_depth_prepass")
+                    .build().unwrap(),
+                PipelineShaderDesc::builder()
+                    .stage(PipelineShaderStage::Pixel)
+                    .source("shadow/shadow_mapping.hlsl")
+                    .entry("ps_main")
+                    .build().unwrap(),
+            ],
+            RasterPipelineDesc::builder()
+                .render_pass(prepass_renderpass.clone())
+                .build().unwrap()
+        );
+
+        let depth_ref = pass.raster_write(depth, AccessType::DepthAttachmentWriteStencilReadOnly);
+
+        let draw_data_buffer = self.draw_data_buffer.clone();
+        let meshes = self.meshes.to_owned();
+        let mesh_instances = self.mesh_instances.to_owned();
+        // same batches as the mesh raster pass, so the instanced draws match
+        let batches = self.batch_instances_with_lod(self.lod_view_position);
+
+        pass.render(move |ctx| {
+            let xform_iter = mesh_instances.iter()
+                .map(|ins| to_row_major_transform(&ins.transform));
+            let instance_xform_offset = match try_push_instance_data(ctx.global_dynamic_buffer(), "depth prepass", xform_iter) {
+                Some(offset) => offset,
+                None => return Ok(()),
+            };
+
+            ctx.begin_render_pass(
+                &prepass_renderpass,
+                resolution,
+                &[],
+                Some((depth_ref, &ImageViewDesc::builder()
+                    .aspect_mask(vk::ImageAspectFlags::DEPTH)
+                    .build().unwrap()
+                ))
+            )?;
+            ctx.set_default_viewport_and_scissor(resolution);
+
+            // binding 0 (the light transforms) is not used in the prepass
+            let bound_pipeline = ctx.bind_raster_pipeline(pipeline.into_bindings()
+                .descriptor_set(0, &[
+                    RenderGraphPassBinding::DynamicStorageBuffer(instance_xform_offset),
+                    RenderGraphPassBinding::DynamicStorageBuffer(instance_xform_offset),
+                ])
+                .raw_descriptor_set(1, bindless_descriptor)
+            )?;
+
+            for batch in &batches {
+                let mesh = &meshes[batch.handle.id as usize];
+
+                if mesh.alpha_blend || mesh.alpha_tested {
+                    continue;
+                }
+
+                unsafe {
+                    let raw = &ctx.device().raw;
+
+                    raw.cmd_bind_index_buffer(
+                        ctx.cb.raw, 
+                        draw_data_buffer.raw,
+                        mesh.lod_indices(batch.lod).0 as u64,
+                        vk::IndexType::UINT32,
+                    );
+
+                    // the light index is not used
+                    let push_constants = [batch.handle.id, batch.first_instance, 0];
+                    bound_pipeline.push_constants(
+                        vk::ShaderStageFlags::ALL_GRAPHICS,
+                        0,
+                        as_bytes::as_byte_slice_val(&push_constants)
+                    );
+
+                    raw.cmd_draw_indexed(ctx.cb.raw,
+                        mesh.lod_indices(batch.lod).1,
+                        batch.instance_count, 0, 0, 0
+                    );
+                }
+            }
+
+            ctx.end_render_pass();
+
+            Ok(())
+        });
+    }
+
     fn draw_mesh_raster(
         &mut self,
         rg: &mut RenderGraphBuilder,
+        resolution: [u32; 2],
+        is_main_view: bool,
     ) -> MeshShadingContext {
         let bindless_descriptor = self.bindless_descriptor_set;
 
         // create shading context (GBuffer etc.)
         let mut shading_context = match self.scheme {
             MeshRasterScheme::Deferred => {
-                let packed = rg.new_resource(ImageDesc::new_2d(self.resolution, GBUFFER_PACK_FORMAT));
-                let geo_normal = rg.new_resource(ImageDesc::new_2d(self.resolution, GBUFFER_GEOMETRIC_NORMAL_FORMAT));
-                let mut depth = rg.new_resource(ImageDesc::new_2d(self.resolution, GBUFFER_DEPTH_FORMAT));
+                let packed = rg.new_resource(ImageDesc::new_2d(resolution, GBUFFER_PACK_FORMAT));
+                let geo_normal = rg.new_resource(ImageDesc::new_2d(resolution, GBUFFER_GEOMETRIC_NORMAL_FORMAT));
+                let mut depth = rg.new_resource(ImageDesc::new_2d(resolution, GBUFFER_DEPTH_FORMAT));
+                let motion_vector = rg.new_resource(ImageDesc::new_2d(resolution, GBUFFER_MOTION_VECTOR_FORMAT));
                 
                 image_clear::clear_depth_stencil(rg, &mut depth);
 
@@ -678,13 +1596,32 @@ impl MeshRenderer {
                     packed_gbuffer: packed, 
                     geometric_normal: geo_normal, 
                     depth, 
+                    motion_vector,
                 })
             },
             _ => unimplemented!("MeshRasterScheme"),
         };
 
+        // only the main view is picked, the other views write into a throwaway image
+        let mut instance_id_img = if is_main_view {
+            let mut instance_id_img = rg.import(self.instance_id_img.clone(), self.instance_id_img_access);
+            image_clear::clear_color(rg, &mut instance_id_img, [0.0; 4]);
+            instance_id_img
+        } else {
+            rg.new_resource(ImageDesc::new_2d(resolution, GBUFFER_INSTANCE_ID_FORMAT))
+        };
+
+        if self.depth_prepass {
+            match &mut shading_context {
+                MeshShadingContext::Defer(gbuffer) => {
+                    self.draw_depth_prepass(rg, &mut gbuffer.depth, resolution);
+                }
+                _ => unimplemented!(),
+            }
+        }
+
         {
-            let extent = [self.resolution[0], self.resolution[1]];
+            let extent = [resolution[0], resolution[1]];
             let raster_renderpass = self.raster_renderpass.clone();
 
             let mut pass = rg.add_pass("mesh raster");
@@ -704,46 +1641,81 @@ impl MeshRenderer {
                 .build().unwrap()
             );
 
+            // the meshes in the depth prepass only need to shade the pixels at the prepass depth
+            let depth_prepass = self.depth_prepass;
+            let prepassed_pipeline = depth_prepass.then(|| pass.register_raster_pipeline(&[
+                PipelineShaderDesc::builder()
+                    .source("defer/defer_raster.hlsl")
+                    .entry("vs_main")
+                    .stage(PipelineShaderStage::Vertex)
+                    .build().unwrap(),
+                PipelineShaderDesc::builder()
+                    .source("defer/defer_raster.hlsl")
+                    .entry("ps_main")
+                    .stage(PipelineShaderStage::Pixel)
+                    .build().unwrap()
+            ], RasterPipelineDesc::builder()
+                .render_pass(raster_renderpass.clone())
+                .depth_write(false)
+                .depth_equal(true)
+                .build().unwrap()
+            ));
+
             match &mut shading_context {
                 MeshShadingContext::Defer(gbuffer) => {
                     let depth_ref = pass.raster_write(&mut gbuffer.depth, AccessType::DepthAttachmentWriteStencilReadOnly);
                     let gbuffer_ref = pass.raster_write(&mut gbuffer.packed_gbuffer, AccessType::ColorAttachmentWrite);
                     let geo_normal_ref = pass.raster_write(&mut gbuffer.geometric_normal, AccessType::ColorAttachmentWrite);
+                    let instance_id_ref = pass.raster_write(&mut instance_id_img, AccessType::ColorAttachmentWrite);
+                    let motion_vector_ref = pass.raster_write(&mut gbuffer.motion_vector, AccessType::ColorAttachmentWrite);
 
                     let draw_data_buffer = self.draw_data_buffer.clone();
                     // TODO: this would be copied every frame, any better idea?
                     let meshes = self.meshes.to_owned();
                     let mesh_instances = self.mesh_instances.to_owned();
+                    let batches = self.batch_instances_with_lod(self.lod_view_position);
+
+                    let prev_transforms = if is_main_view {
+                        self.rastered_instances = mesh_instances.iter()
+                            .map(|ins| ins.instance_handle)
+                            .collect();
+
+                        self.prev_instance_transforms.advance(mesh_instances.iter().map(|ins| (ins.instance_handle, ins.transform)))
+                    } else {
+                        mesh_instances.iter()
+                            .map(|ins| ins.transform)
+                            .collect::<Vec<_>>()
+                    };
 
                     pass.render(move |ctx| {
+                        // push the overrides before the transforms, the shadow map pass reuses the last pushed transforms
+                        let mat_override_iter = mesh_instances.iter()
+                            .map(|ins| GpuMaterialOverride::new(ins.material_override, ins.tint));
+                        let prev_xform_iter = prev_transforms.iter()
+                            .map(to_row_major_transform);
                         let xform_iter = mesh_instances.iter()
-                            .map(|ins| {
-                                // transpose to row-major matrix to be used in shader
-                                let transform = [
-                                    ins.transform.x_axis.x,
-                                    ins.transform.y_axis.x,
-                                    ins.transform.z_axis.x,
-                                    ins.transform.translation.x,
-                                    ins.transform.x_axis.y,
-                                    ins.transform.y_axis.y,
-                                    ins.transform.z_axis.y,
-                                    ins.transform.translation.y,
-                                    ins.transform.x_axis.z,
-                                    ins.transform.y_axis.z,
-                                    ins.transform.z_axis.z,
-                                    ins.transform.translation.z,
-                                ];
-
-                                transform
-                            });
-                        let instance_xform_offset = ctx.global_dynamic_buffer().push_from_iter(xform_iter);
+                            .map(|ins| to_row_major_transform(&ins.transform));
+
+                        let dynamic_buffer = ctx.global_dynamic_buffer();
+                        let (mat_override_offset, prev_instance_xform_offset, instance_xform_offset) = match (
+                            try_push_instance_data(dynamic_buffer, "mesh rasterization", mat_override_iter),
+                            try_push_instance_data(dynamic_buffer, "mesh rasterization", prev_xform_iter),
+                            try_push_instance_data(dynamic_buffer, "mesh rasterization", xform_iter),
+                        ) {
+                            (Some(mat_override_offset), Some(prev_instance_xform_offset), Some(instance_xform_offset)) => {
+                                (mat_override_offset, prev_instance_xform_offset, instance_xform_offset)
+                            },
+                            _ => return Ok(()),
+                        };
 
                         ctx.begin_render_pass(
                             &*raster_renderpass, 
                             extent, 
                             &[
                                 (gbuffer_ref, &ImageViewDesc::default()),
-                                (geo_normal_ref, &ImageViewDesc::default())
+                                (geo_normal_ref, &ImageViewDesc::default()),
+                                (instance_id_ref, &ImageViewDesc::default()),
+                                (motion_vector_ref, &ImageViewDesc::default()),
                             ],
                             Some((depth_ref, &ImageViewDesc::builder()
                                 .aspect_mask(vk::ImageAspectFlags::DEPTH)
@@ -752,36 +1724,56 @@ impl MeshRenderer {
                         )?;
                         ctx.set_default_viewport_and_scissor(extent);
 
-                        let bound_pipeline = ctx.bind_raster_pipeline(pipeline.into_bindings()
-                            .descriptor_set(0, &[RenderGraphPassBinding::DynamicStorageBuffer(instance_xform_offset)])
-                            .raw_descriptor_set(1, bindless_descriptor)
-                        )?;
-
-                        // do drawing
-                        for (instance_idx, mesh_ins) in mesh_instances.iter().enumerate() {
-                            let mesh = &meshes[mesh_ins.handle.id as usize];
+                        // the meshes in the depth prepass first, then the others with the normal depth test
+                        let draw_pipelines = prepassed_pipeline.map(|pipeline| (pipeline, true))
+                            .into_iter()
+                            .chain(std::iter::once((pipeline, false)));
+
+                        for (pipeline, draw_prepassed) in draw_pipelines {
+                            let bound_pipeline = ctx.bind_raster_pipeline(pipeline.into_bindings()
+                                .descriptor_set(0, &[
+                                    RenderGraphPassBinding::DynamicStorageBuffer(instance_xform_offset),
+                                    RenderGraphPassBinding::DynamicStorageBuffer(mat_override_offset),
+                                    RenderGraphPassBinding::DynamicStorageBuffer(prev_instance_xform_offset),
+                                ])
+                                .raw_descriptor_set(1, bindless_descriptor)
+                            )?;
+
+                            // do drawing
+                            for batch in &batches {
+                                let mesh = &meshes[batch.handle.id as usize];
+
+                                // fully transparent meshes are drawn in the forward transparency pass
+                                if !mesh.opaque {
+                                    continue;
+                                }
+                                // the depth prepass doesn't discard the transparent pixels of the mixed meshes
+                                if (depth_prepass && !mesh.alpha_tested && !mesh.alpha_blend) != draw_prepassed {
+                                    continue;
+                                }
                 
-                            unsafe {
-                                let raw = &ctx.device().raw;
+                                unsafe {
+                                    let raw = &ctx.device().raw;
                 
-                                raw.cmd_bind_index_buffer(
-                                    ctx.cb.raw, 
-                                    draw_data_buffer.raw,
-                                    mesh.index_buffer_offset as u64,
-                                    vk::IndexType::UINT32,
-                                );
+                                    raw.cmd_bind_index_buffer(
+                                        ctx.cb.raw, 
+                                        draw_data_buffer.raw,
+                                        mesh.lod_indices(batch.lod).0 as u64,
+                                        vk::IndexType::UINT32,
+                                    );
                 
-                                let push_constants = [mesh_ins.handle.id, instance_idx as u32];
-                                bound_pipeline.push_constants(
-                                    vk::ShaderStageFlags::ALL_GRAPHICS, 
-                                    0,
-                                    as_bytes::as_byte_slice_val(&push_constants)
-                                );
+                                    let push_constants = [batch.handle.id, batch.first_instance];
+                                    bound_pipeline.push_constants(
+                                        vk::ShaderStageFlags::ALL_GRAPHICS, 
+                                        0,
+                                        as_bytes::as_byte_slice_val(&push_constants)
+                                    );
                 
-                                raw.cmd_draw_indexed(ctx.cb.raw,
-                                    mesh.index_count,
-                                    1, 0, 0, 0
-                                );
+                                    raw.cmd_draw_indexed(ctx.cb.raw,
+                                        mesh.lod_indices(batch.lod).1,
+                                        batch.instance_count, 0, 0, 0
+                                    );
+                                }
                             }
                         }
 
@@ -794,9 +1786,85 @@ impl MeshRenderer {
             }
         }
 
+        // keep the instance id image in transfer source layout after this frame, so that it can be read back by pick_instance()
+        if is_main_view {
+            rg.export(instance_id_img, AccessType::TransferRead);
+            self.instance_id_img_access = AccessType::TransferRead;
+        }
+
         shading_context
     }
 
+    /// Pick the mesh instance rendered at the pixel in the last frame.
+    /// Returns None if the pixel is background or out of the render resolution.
+    /// 
+    /// This will wait for the device to be idle. Only the opaque meshes drawn in gbuffer rasterization can be picked.
+    pub fn pick_instance(&self, pixel: [u32; 2]) -> Option<MeshInstanceHandle> {
+        if self.instance_id_img_access == AccessType::Nothing {
+            return None;
+        }
+
+        if pixel[0] >= self.resolution[0] || pixel[1] >= self.resolution[1] {
+            return None;
+        }
+
+        // make sure the last frame is finished
+        self.device.wait_idle();
+
+        let readback_buffer = self.device.create_buffer(
+            BufferDesc::new_gpu_to_cpu(std::mem::size_of::<u32>(), vk::BufferUsageFlags::TRANSFER_DST),
+            "instance id readback buffer"
+        ).unwrap();
+
+        self.device.with_setup_commands(|cb| {
+            unsafe {
+                self.device.raw.cmd_copy_image_to_buffer(
+                    cb,
+                    self.instance_id_img.raw,
+                    vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                    readback_buffer.raw,
+                    &[vk::BufferImageCopy::builder()
+                        .buffer_offset(0)
+                        .image_subresource(vk::ImageSubresourceLayers {
+                            aspect_mask: vk::ImageAspectFlags::COLOR,
+                            mip_level: 0,
+                            base_array_layer: 0,
+                            layer_count: 1,
+                        })
+                        .image_offset(vk::Offset3D { x: pixel[0] as i32, y: pixel[1] as i32, z: 0 })
+                        .image_extent(vk::Extent3D { width: 1, height: 1, depth: 1 })
+                        .build()
+                    ]
+                );
+
+                self.device.raw.cmd_pipeline_barrier(
+                    cb,
+                    vk::PipelineStageFlags::TRANSFER,
+                    vk::PipelineStageFlags::HOST,
+                    vk::DependencyFlags::empty(),
+                    &[vk::MemoryBarrier::builder()
+                        .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+                        .dst_access_mask(vk::AccessFlags::HOST_READ)
+                        .build()],
+                    &[],
+                    &[],
+                );
+            }
+        }).expect("Failed to read back instance id!");
+
+        let instance_id = bytemuck::pod_read_unaligned::<u32>(
+            &readback_buffer.allocation.mapped_slice().unwrap()[0..std::mem::size_of::<u32>()]
+        );
+        self.device.destroy_buffer(readback_buffer);
+
+        // zero means background
+        if instance_id == 0 {
+            None
+        } else {
+            self.rastered_instances.get(instance_id as usize - 1).copied()
+        }
+    }
+
     fn draw_shadow_map(
         &mut self,
         rg: &mut RenderGraphBuilder,
@@ -845,7 +1913,8 @@ impl MeshRenderer {
 
             let draw_data_buffer = self.draw_data_buffer.clone();
             let meshes = self.meshes.to_owned();
-            let mesh_instances = self.mesh_instances.to_owned();
+            // same batches as the mesh raster pass, the instance transforms are shared
+            let batches = self.batch_instances_with_lod(self.lod_view_position);
 
             pass.render(move |ctx| {
                 let instance_xform_offset = ctx.global_dynamic_buffer().previous_pushed_data_offset();
@@ -879,8 +1948,12 @@ impl MeshRenderer {
                             .raw_descriptor_set(1, bindless_descriptor)
                     )?;
 
-                    for (instance_idx, mesh_inst) in mesh_instances.iter().enumerate() {
-                        let mesh = &meshes[mesh_inst.handle.id as usize];
+                    for batch in &batches {
+                        let mesh = &meshes[batch.handle.id as usize];
+
+                        if !mesh.cast_shadow {
+                            continue;
+                        }
             
                         unsafe {
                             let raw = &ctx.device().raw;
@@ -888,13 +1961,13 @@ impl MeshRenderer {
                             raw.cmd_bind_index_buffer(
                                 ctx.cb.raw, 
                                 draw_data_buffer.raw,
-                                mesh.index_buffer_offset as u64,
+                                mesh.lod_indices(batch.lod).0 as u64,
                                 vk::IndexType::UINT32,
                             );
 
                             let push_constants = [
-                                mesh_inst.handle.id,
-                                instance_idx as u32,
+                                batch.handle.id,
+                                batch.first_instance,
                                 light_idx as u32,
                             ];
                             bound_pipeline.push_constants(
@@ -904,8 +1977,8 @@ impl MeshRenderer {
                             );
             
                             raw.cmd_draw_indexed(ctx.cb.raw,
-                                mesh.index_count,
-                                1, 0, 0, 0
+                                mesh.lod_indices(batch.lod).1,
+                                batch.instance_count, 0, 0, 0
                             );
                         }
                     }
@@ -920,6 +1993,152 @@ impl MeshRenderer {
         light_maps
     }
 
+    /// Forward shade all the transparent mesh instances and alpha blend them onto the lit image.
+    /// Depth of the opaque meshes is used for occlusion, but the transparent meshes will NOT write depth.
+    /// 
+    /// Note: the transparent instances are sorted back-to-front by the distance from the camera to their world space aabb center.
+    /// The sorting is per-instance, not per-triangle, so intersecting or self-overlapping transparent meshes may blend in the wrong order.
+    pub fn draw_transparency(
+        &mut self,
+        rg: &mut RenderGraphBuilder,
+        lit_img: &mut RgHandle<Image>,
+        depth: &mut RgHandle<Image>,
+        light_maps: &[RgHandle<Image>],
+        light_matrices: &[Mat4],
+        camera_position: Vec3,
+    ) {
+        let meshes = self.meshes.to_owned();
+        let light_matrices = light_matrices.to_owned();
+
+        let mut transparent_instances = self.mesh_instances.iter()
+            .filter(|ins| meshes[ins.handle.id as usize].alpha_blend)
+            .map(|ins| (ins.clone(), ins.aabb.get_center().distance_squared(camera_position)))
+            .collect::<Vec<_>>();
+        let lod_settings = self.lod_settings.clone();
+
+        if transparent_instances.is_empty() {
+            return;
+        }
+
+        // back-to-front
+        transparent_instances.sort_by(|(_, lhs), (_, rhs)| rhs.total_cmp(lhs));
+        let transparent_instances = transparent_instances.into_iter()
+            .map(|(ins, _)| ins)
+            .collect::<Vec<_>>();
+
+        let bindless_descriptor = self.bindless_descriptor_set;
+        let transparency_renderpass = self.transparency_renderpass.clone();
+
+        let mut pass = rg.add_pass("forward transparency");
+        let pipeline = pass.register_raster_pipeline(&[
+                PipelineShaderDesc::builder()
+                    .source("forward/forward_transparent.hlsl")
+                    .entry("vs_main")
+                    .stage(PipelineShaderStage::Vertex)
+                    .build().unwrap(),
+                PipelineShaderDesc::builder()
+                    .source("forward/forward_transparent.hlsl")
+                    .entry("ps_main")
+                    .stage(PipelineShaderStage::Pixel)
+                    .build().unwrap()
+            ], RasterPipelineDesc::builder()
+                .render_pass(transparency_renderpass.clone())
+                // transparent surfaces can be seen from both sides
+                .cull_mode(RasterPipelineCullMode::None)
+                .depth_write(false)
+                .alpha_blend(true)
+                .build().unwrap()
+        );
+
+        let depth_ref = pass.raster_write(depth, AccessType::DepthAttachmentWriteStencilReadOnly);
+        let lit_img_ref = pass.raster_write(lit_img, AccessType::ColorAttachmentWrite);
+        let light_map_refs = light_maps.iter()
+            .map(|map| {
+                pass.read(map, AccessType::FragmentShaderReadSampledImageOrUniformTexelBuffer)
+            })
+            .collect::<Vec<_>>();
+
+        let extent = lit_img.desc().extent;
+        let extent = [extent[0], extent[1]];
+        let draw_data_buffer = self.draw_data_buffer.clone();
+
+        pass.render(move |ctx| {
+            let light_mat_iter = light_matrices.iter()
+                .map(|mat| mat.transpose().to_cols_array());
+            let light_mat_offset = ctx.global_dynamic_buffer().push_from_iter(light_mat_iter);
+
+            let xform_iter = transparent_instances.iter()
+                .map(|ins| to_row_major_transform(&ins.transform));
+            let mat_override_iter = transparent_instances.iter()
+                .map(|ins| GpuMaterialOverride::new(ins.material_override, ins.tint));
+
+            let dynamic_buffer = ctx.global_dynamic_buffer();
+            let (instance_xform_offset, mat_override_offset) = match (
+                try_push_instance_data(dynamic_buffer, "forward transparency", xform_iter),
+                try_push_instance_data(dynamic_buffer, "forward transparency", mat_override_iter),
+            ) {
+                (Some(instance_xform_offset), Some(mat_override_offset)) => (instance_xform_offset, mat_override_offset),
+                _ => return Ok(()),
+            };
+
+            ctx.begin_render_pass(
+                &*transparency_renderpass,
+                extent,
+                &[(lit_img_ref, &ImageViewDesc::default())],
+                Some((depth_ref, &ImageViewDesc::builder()
+                    .aspect_mask(vk::ImageAspectFlags::DEPTH)
+                    .build().unwrap()
+                ))
+            )?;
+            ctx.set_default_viewport_and_scissor(extent);
+
+            let mut light_map_binding = light_map_refs.bind();
+            light_map_binding.with_aspect(vk::ImageAspectFlags::DEPTH);
+
+            let bound_pipeline = ctx.bind_raster_pipeline(pipeline.into_bindings()
+                .descriptor_set(0, &[
+                    RenderGraphPassBinding::DynamicStorageBuffer(instance_xform_offset),
+                    light_map_binding,
+                    RenderGraphPassBinding::DynamicStorageBuffer(light_mat_offset),
+                    RenderGraphPassBinding::DynamicStorageBuffer(mat_override_offset),
+                ])
+                .raw_descriptor_set(1, bindless_descriptor)
+            )?;
+
+            for (instance_idx, mesh_ins) in transparent_instances.iter().enumerate() {
+                let mesh = &meshes[mesh_ins.handle.id as usize];
+                let lod = lod_settings.select_lod(&mesh_ins.aabb, camera_position);
+
+                unsafe {
+                    let raw = &ctx.device().raw;
+
+                    raw.cmd_bind_index_buffer(
+                        ctx.cb.raw,
+                        draw_data_buffer.raw,
+                        mesh.lod_indices(lod).0 as u64,
+                        vk::IndexType::UINT32,
+                    );
+
+                    let push_constants = [mesh_ins.handle.id, instance_idx as u32];
+                    bound_pipeline.push_constants(
+                        vk::ShaderStageFlags::ALL_GRAPHICS,
+                        0,
+                        as_bytes::as_byte_slice_val(&push_constants)
+                    );
+
+                    raw.cmd_draw_indexed(ctx.cb.raw,
+                        mesh.lod_indices(lod).1,
+                        1, 0, 0, 0
+                    );
+                }
+            }
+
+            ctx.end_render_pass();
+
+            Ok(())
+        });
+    }
+
     pub fn clean(self, rhi: &Rhi) {
         for img in self.bindless_images {
             let img = Arc::try_unwrap(img)
@@ -927,6 +2146,10 @@ impl MeshRenderer {
             rhi.device.destroy_image(img);
         }
 
+        let instance_id_img = Arc::try_unwrap(self.instance_id_img)
+            .expect("Failed to clean instance id image!");
+        rhi.device.destroy_image(instance_id_img);
+
         let draw_data_buffer = Arc::try_unwrap(self.draw_data_buffer).unwrap();
         let mesh_buffer = Arc::try_unwrap(self.mesh_buffer).unwrap();
 
@@ -934,4 +2157,74 @@ impl MeshRenderer {
         rhi.device.destroy_buffer(mesh_buffer);
         rhi.device.destroy_buffer(self.bindless_tex_sizes_buffer);
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use ash::vk;
+    use raven_asset::{TextureDesc, TextureGammaSpace};
+    use raven_math::{AABB, Affine3A, Quat, Vec3};
+
+    use super::{MeshLodSettings, MeshInstanceHandle, PrevInstanceTransforms, texture_format};
+
+    #[test]
+    fn test_select_lod() {
+        let settings = MeshLodSettings { distances: vec![10.0, 20.0] };
+        let aabb = AABB::from_min_max(Vec3::splat(-1.0), Vec3::splat(1.0));
+
+        // inside the bounds
+        assert_eq!(settings.select_lod(&aabb, Vec3::ZERO), 0);
+        assert_eq!(settings.select_lod(&aabb, Vec3::new(5.0, 0.0, 0.0)), 0);
+        // the coarser LOD starts at the threshold
+        assert_eq!(settings.select_lod(&aabb, Vec3::new(11.0, 0.0, 0.0)), 1);
+        assert_eq!(settings.select_lod(&aabb, Vec3::new(0.0, -15.0, 0.0)), 1);
+        assert_eq!(settings.select_lod(&aabb, Vec3::new(0.0, 0.0, 25.0)), 2);
+        // beyond the last distance it stays at the coarsest LOD
+        assert_eq!(settings.select_lod(&aabb, Vec3::splat(1000.0)), 2);
+
+        // the distance is measured to the nearest point of the bounds, not the center
+        let big_aabb = AABB::from_min_max(Vec3::splat(-50.0), Vec3::splat(50.0));
+        assert_eq!(settings.select_lod(&big_aabb, Vec3::new(55.0, 0.0, 0.0)), 0);
+        assert_eq!(settings.select_lod(&big_aabb, Vec3::new(60.0, 0.0, 0.0)), 1);
+
+        assert_eq!(MeshLodSettings::disabled().select_lod(&aabb, Vec3::splat(1000.0)), 0);
+    }
+
+    #[test]
+    fn test_texture_format() {
+        let desc = |channel_count, gamma_space| TextureDesc { channel_count, gamma_space, ..Default::default() };
+
+        assert_eq!(texture_format(&desc(1, TextureGammaSpace::Linear)), vk::Format::R8_UNORM);
+        assert_eq!(texture_format(&desc(2, TextureGammaSpace::Linear)), vk::Format::R8G8_UNORM);
+        assert_eq!(texture_format(&desc(4, TextureGammaSpace::Linear)), vk::Format::R8G8B8A8_UNORM);
+        assert_eq!(texture_format(&desc(4, TextureGammaSpace::Srgb)), vk::Format::R8G8B8A8_SRGB);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_texture_format_srgb_data_texture() {
+        // the data textures are always linear
+        texture_format(&TextureDesc { channel_count: 1, gamma_space: TextureGammaSpace::Srgb, ..Default::default() });
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_texture_format_unsupported_channel_count() {
+        texture_format(&TextureDesc { channel_count: 3, gamma_space: TextureGammaSpace::Linear, ..Default::default() });
+    }
+
+    #[test]
+    fn test_prev_instance_transforms() {
+        let first = Affine3A::from_translation(Vec3::new(1.0, 0.0, 0.0));
+        let moved = Affine3A::from_rotation_translation(Quat::from_rotation_y(0.5), Vec3::new(2.0, 0.0, 0.0));
+
+        let mut prev_transforms = PrevInstanceTransforms::default();
+        // no motion in the first frame
+        assert_eq!(prev_transforms.advance([(MeshInstanceHandle(0), first)].into_iter()), vec![first]);
+        assert_eq!(prev_transforms.advance([(MeshInstanceHandle(0), moved)].into_iter()), vec![first]);
+        assert_eq!(prev_transforms.advance([(MeshInstanceHandle(0), moved)].into_iter()), vec![moved]);
+
+        // the instance added after clearing the scene starts without motion, even with the same handle
+        prev_transforms.clear();
+        assert_eq!(prev_transforms.advance([(MeshInstanceHandle(0), first)].into_iter()), vec![first]);
+    }
+}
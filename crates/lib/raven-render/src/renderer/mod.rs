@@ -1,12 +1,16 @@
 pub mod image_lut;
 pub mod lut_renderer;
 
+pub mod draw_data_allocator;
+pub mod instance_bvh;
 pub mod mesh_renderer;
 pub mod sky_renderer;
 pub mod ibl_renderer;
+pub mod env_capture_renderer;
 pub mod post_process_renderer;
 
 pub mod light_renderer;
+pub mod light_units;
 
 pub mod debug_renderer;
 
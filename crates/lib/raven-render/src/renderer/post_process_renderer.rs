@@ -2,8 +2,8 @@ use std::sync::Arc;
 
 use ash::vk;
 
-use raven_rg::{RenderGraphBuilder, RgHandle, IntoPipelineDescriptorBindings, RenderGraphPassBindable, RenderGraphPassBinding};
-use raven_rhi::{backend::{Image, Buffer, BufferDesc, AccessType}, Rhi};
+use raven_rg::{RenderGraphBuilder, RgHandle, IntoPipelineDescriptorBindings, RenderGraphPassBindable, RenderGraphPassBinding, TemporalHistory};
+use raven_rhi::{backend::{Image, ImageDesc, Buffer, BufferDesc, AccessType}, Rhi};
 
 const LUMINANCE_HISTOGRAM_BIN_COUNT: usize = 256;
 pub const LUMINANCE_HISTOGRAM_MIN_LOG2: f64 = -16.0;
@@ -11,6 +11,12 @@ pub const LUMINANCE_HISTOGRAM_MAX_LOG2: f64 =  16.0;
 
 pub const ENABLE_AUTO_EXPOSURE: bool = false;
 
+/// Weight of the current frame when blending with the history in TAA.
+pub const DEFAULT_TAA_BLEND_FACTOR: f32 = 0.1;
+
+/// The probe pixel and the screen center.
+const LUMINANCE_PROBE_COUNT: usize = 2;
+
 pub fn blur_pyramid(rg: &mut RenderGraphBuilder, input_img: &RgHandle<Image>) -> RgHandle<Image> {
     let mut output_img_desc = input_img.desc()
         .half_resolution() // start with mipmap level 1
@@ -85,6 +91,35 @@ pub fn blur_pyramid(rg: &mut RenderGraphBuilder, input_img: &RgHandle<Image>) ->
     pyramid
 }
 
+/// Copy the image of a viewport camera into the output image, with its top-left corner at the offset (in pixels).
+pub fn composite_viewport(rg: &mut RenderGraphBuilder, input_img: &RgHandle<Image>, output_img: &mut RgHandle<Image>, offset: [u32; 2]) {
+    let extent = input_img.desc().extent;
+
+    let mut pass = rg.add_pass("viewport composite");
+    let pipeline = pass.register_compute_pipeline("post_processing/viewport_composite.hlsl");
+
+    let input_ref = pass.read(input_img, AccessType::ComputeShaderReadSampledImageOrUniformTexelBuffer);
+    let output_ref = pass.write(output_img, AccessType::ComputeShaderWrite);
+
+    let push_values = (offset[0], offset[1], extent[0], extent[1]);
+
+    pass.render(move |ctx| {
+        let offset = ctx.global_dynamic_buffer().push(&push_values);
+
+        let bound_pipeline = ctx.bind_compute_pipeline(pipeline.into_bindings()
+            .descriptor_set(0, &[
+                input_ref.bind(),
+                output_ref.bind(),
+                RenderGraphPassBinding::DynamicBuffer(offset),
+            ])
+        )?;
+
+        bound_pipeline.dispatch(extent);
+
+        Ok(())
+    });
+}
+
 pub fn reverse_blur_pyramid(rg: &mut RenderGraphBuilder, in_pyramid: &RgHandle<Image>) -> RgHandle<Image> {
     let mut rev_pyramid = rg.new_resource(*in_pyramid.desc());
 
@@ -151,6 +186,17 @@ pub fn reverse_blur_pyramid(rg: &mut RenderGraphBuilder, in_pyramid: &RgHandle<I
 pub struct PostProcessRenderer {
     luminance_histogram_buffer: Arc<Buffer>,
     image_log2_luminance: f32,
+
+    luminance_probe_buffer: Arc<Buffer>,
+    /// Pixel to probe the luminance, None means probing is disabled.
+    luminance_probe_pixel: Option<[u32; 2]>,
+    /// Luminance of the probe pixel and the screen center read back from the gpu.
+    probe_luminances: [f32; LUMINANCE_PROBE_COUNT],
+
+    /// Output linear HDR (1.0 is paper white) instead of tonemapped SDR, the final blit encodes it for the display.
+    hdr_output: bool,
+
+    taa_blend_factor: f32,
 }
 
 impl PostProcessRenderer {    
@@ -161,12 +207,155 @@ impl PostProcessRenderer {
             ), "luminance histogram buffer")
             .expect("Failed to create luminance histogram buffer!");
 
+        let probe_buffer = rhi.device.create_buffer(BufferDesc::new_gpu_to_cpu(
+                std::mem::size_of::<f32>() * LUMINANCE_PROBE_COUNT,
+                vk::BufferUsageFlags::STORAGE_BUFFER
+            ), "luminance probe buffer")
+            .expect("Failed to create luminance probe buffer!");
+
         Self {
             luminance_histogram_buffer: Arc::new(lum_buffer),
             image_log2_luminance: 0.0,
+
+            luminance_probe_buffer: Arc::new(probe_buffer),
+            luminance_probe_pixel: None,
+            probe_luminances: [0.0; LUMINANCE_PROBE_COUNT],
+
+            hdr_output: rhi.swapchain.encoding.is_hdr(),
+
+            taa_blend_factor: DEFAULT_TAA_BLEND_FACTOR,
         }
     }
 
+    /// Start probing the luminance of the pixel (in render resolution) in the following frames.
+    #[inline]
+    pub fn set_luminance_probe_pixel(&mut self, pixel: [u32; 2]) {
+        self.luminance_probe_pixel = Some(pixel);
+    }
+
+    /// Luminance (without exposure) of the probe pixel, it lags behind a few frames because of the gpu latency.
+    #[inline]
+    pub fn probe_luminance(&self) -> f32 {
+        self.probe_luminances[0]
+    }
+
+    /// Luminance (without exposure) of the screen center, only probed after a probe pixel is set.
+    #[inline]
+    pub fn center_luminance(&self) -> f32 {
+        self.probe_luminances[1]
+    }
+
+    /// Weight of the current frame in TAA, lower values are smoother but ghost more.
+    #[inline]
+    pub fn set_taa_blend_factor(&mut self, blend_factor: f32) {
+        self.taa_blend_factor = blend_factor.clamp(0.0, 1.0);
+    }
+
+    #[inline]
+    pub fn taa_blend_factor(&self) -> f32 {
+        self.taa_blend_factor
+    }
+
+    /// Temporal anti-aliasing resolve, the input must be rendered with the jitter in the camera frame constants.
+    /// 
+    /// The last frame's result is reprojected with the motion vectors (or the depth for the sky), clamped to the current neighborhood and blended with the input.
+    pub fn taa_resolve(
+        &self,
+        rg: &mut RenderGraphBuilder,
+        input_img: &RgHandle<Image>,
+        depth_img: &RgHandle<Image>,
+        motion_vector_img: &RgHandle<Image>,
+    ) -> RgHandle<Image> {
+        let extent = input_img.desc().extent;
+
+        // temporal resources are not resized, so use a new one when the render resolution changed
+        let TemporalHistory { mut current, previous, is_first_frame } = rg.get_or_create_temporal_history(
+            format!("taa history {}x{}", extent[0], extent[1]),
+            ImageDesc::new_2d([extent[0], extent[1]], vk::Format::R16G16B16A16_SFLOAT)
+                .usage_flags(vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::STORAGE)
+        ).expect("Failed to create taa history images!");
+
+        let mut pass = rg.add_pass("taa resolve");
+        let pipeline = pass.register_compute_pipeline("post_processing/taa_resolve.hlsl");
+
+        let input_ref = pass.read(input_img, AccessType::ComputeShaderReadSampledImageOrUniformTexelBuffer);
+        let history_ref = pass.read(&previous, AccessType::ComputeShaderReadSampledImageOrUniformTexelBuffer);
+        let depth_ref = pass.read(depth_img, AccessType::ComputeShaderReadSampledImageOrUniformTexelBuffer);
+        let motion_vector_ref = pass.read(motion_vector_img, AccessType::ComputeShaderReadSampledImageOrUniformTexelBuffer);
+        let output_ref = pass.write(&mut current, AccessType::ComputeShaderWrite);
+
+        let push_values = (
+            extent[0] as f32, extent[1] as f32, 1.0 / extent[0] as f32, 1.0 / extent[1] as f32,
+            self.taa_blend_factor, !is_first_frame as u32,
+        );
+
+        pass.render(move |ctx| {
+            let offset = ctx.global_dynamic_buffer().push(&push_values);
+
+            let mut depth_binding = depth_ref.bind();
+            depth_binding.with_aspect(vk::ImageAspectFlags::DEPTH);
+
+            let bound_pipeline = ctx.bind_compute_pipeline(pipeline.into_bindings()
+                .descriptor_set(0, &[
+                    input_ref.bind(),
+                    history_ref.bind(),
+                    depth_binding,
+                    output_ref.bind(),
+                    RenderGraphPassBinding::DynamicBuffer(offset),
+                    motion_vector_ref.bind(),
+                ])
+            )?;
+
+            bound_pipeline.dispatch(extent);
+
+            Ok(())
+        });
+
+        current
+    }
+
+    /// Readback the probed luminances written in the previous frames.
+    fn readback_luminance_probe_buffer(&mut self) {
+        if let Some(bytes) = self.luminance_probe_buffer.allocation.mapped_slice() {
+            let bytes = &bytes[..std::mem::size_of::<f32>() * LUMINANCE_PROBE_COUNT];
+            self.probe_luminances.copy_from_slice(bytemuck::checked::cast_slice::<u8, f32>(bytes));
+        }
+    }
+
+    fn probe_luminance_pass(&mut self, rg: &mut RenderGraphBuilder, input_img: &RgHandle<Image>, probe_pixel: [u32; 2]) {
+        let extent = input_img.desc().extent;
+        let probe_pixels = [
+            probe_pixel[0].min(extent[0] - 1),
+            probe_pixel[1].min(extent[1] - 1),
+            extent[0] / 2,
+            extent[1] / 2,
+        ];
+
+        let mut probe_buf = rg.import(self.luminance_probe_buffer.clone(), AccessType::Nothing);
+
+        let mut pass = rg.add_pass("luminance probe");
+        let pipeline = pass.register_compute_pipeline("post_processing/luminance_probe.hlsl");
+
+        let input_ref = pass.read(input_img, AccessType::ComputeShaderReadSampledImageOrUniformTexelBuffer);
+        let probe_buf_ref = pass.write(&mut probe_buf, AccessType::ComputeShaderWrite);
+
+        pass.render(move |ctx| {
+            let offset = ctx.global_dynamic_buffer().push(&probe_pixels);
+
+            let bound_pipeline = ctx.bind_compute_pipeline(pipeline.into_bindings()
+                .descriptor_set(0, &[
+                    input_ref.bind(),
+                    probe_buf_ref.bind(),
+                    RenderGraphPassBinding::DynamicBuffer(offset)
+                ])
+            )?;
+
+            bound_pipeline.dispatch([LUMINANCE_PROBE_COUNT as u32, 1, 1]);
+
+            Ok(())
+        });
+    }
+
     /// Readback the luminance histogram data calculated from compute shader.
     fn readback_histogram_buffer(&mut self) {
         let mut histogram_data = [0_u32; LUMINANCE_HISTOGRAM_BIN_COUNT];
@@ -421,18 +610,56 @@ impl PostProcessRenderer {
         input_image: RgHandle<Image>,
         post_exposure_mult: f32,
         contrast: f32,
+        enable_bloom: bool,
+    ) -> RgHandle<Image> {
+        self.prepare_rg_impl(rg, input_image, post_exposure_mult, contrast, enable_bloom, true)
+    }
+
+    /// Post process the image of another view (e.g. another camera viewport).
+    /// 
+    /// The auto exposure and the luminance probes only measure the main view, so they are skipped here.
+    pub fn prepare_view_rg(
+        &mut self,
+        rg: &mut RenderGraphBuilder,
+        input_image: RgHandle<Image>,
+        post_exposure_mult: f32,
+        contrast: f32,
+        enable_bloom: bool,
+    ) -> RgHandle<Image> {
+        self.prepare_rg_impl(rg, input_image, post_exposure_mult, contrast, enable_bloom, false)
+    }
+
+    fn prepare_rg_impl(
+        &mut self,
+        rg: &mut RenderGraphBuilder,
+        input_image: RgHandle<Image>,
+        post_exposure_mult: f32,
+        contrast: f32,
+        enable_bloom: bool,
+        is_main_view: bool,
     ) -> RgHandle<Image> {
         let output_img_desc = input_image.desc().format(vk::Format::B10G11R11_UFLOAT_PACK32);
         //dbg!(input_image.desc());
         //dbg!(output_img_desc);
         let mut output = rg.new_resource(output_img_desc);
 
-        let bloom_output = self.bloom(rg, &input_image, 0.92);
-        let bloom_mip_level = bloom_output.desc().mip_levels as u32;
+        let bloom_output = if enable_bloom {
+            Some(self.bloom(rg, &input_image, 0.92))
+        } else {
+            None
+        };
+        // zero mip level skips the bloom in the shader
+        let bloom_mip_level = bloom_output.as_ref().map_or(0, |bloom| bloom.desc().mip_levels as u32);
 
         let input_extent = input_image.desc().extent;
+        let hdr_output = self.hdr_output as u32;
+
+        if let (Some(probe_pixel), true) = (self.luminance_probe_pixel, is_main_view) {
+            self.readback_luminance_probe_buffer();
+            self.probe_luminance_pass(rg, &input_image, probe_pixel);
+        }
 
-        if ENABLE_AUTO_EXPOSURE {
+        if ENABLE_AUTO_EXPOSURE && is_main_view {
             self.readback_histogram_buffer();
     
             let blur_pyramid = blur_pyramid(rg, &input_image);
@@ -444,7 +671,8 @@ impl PostProcessRenderer {
                 let pipeline = pass.register_compute_pipeline("post_processing/post_combine.hlsl");
     
                 let input_ref = pass.read(&input_image, AccessType::ComputeShaderReadSampledImageOrUniformTexelBuffer);
-                let bloom_ref = pass.read(&bloom_output, AccessType::ComputeShaderReadSampledImageOrUniformTexelBuffer);
+                // bindings are positional, so bind the blur pyramid in place of the bloom when it is disabled (it is not sampled)
+                let bloom_ref = pass.read(bloom_output.as_ref().unwrap_or(&blur_pyramid), AccessType::ComputeShaderReadSampledImageOrUniformTexelBuffer);
                 let pyramid_ref = pass.read(&blur_pyramid, AccessType::ComputeShaderReadSampledImageOrUniformTexelBuffer);
                 let rev_pyramid_ref = pass.read(&reverse_blur_pyramid, AccessType::ComputeShaderReadSampledImageOrUniformTexelBuffer);
                 let histogram_ref = pass.read(&temp_histogram_buf, AccessType::ComputeShaderReadUniformBuffer);
@@ -454,6 +682,7 @@ impl PostProcessRenderer {
                 let push_values = (
                     input_extent[0] as f32, input_extent[1] as f32, 1.0 / input_extent[0] as f32, 1.0 / input_extent[1] as f32,
                     post_exposure_mult, contrast, 1_u32, bloom_mip_level,
+                    hdr_output,
                 );
     
                 pass.render(move|ctx| {
@@ -483,24 +712,30 @@ impl PostProcessRenderer {
             let pipeline = pass.register_compute_pipeline("post_processing/post_combine.hlsl");
 
             let input_ref = pass.read(&input_image, AccessType::ComputeShaderReadSampledImageOrUniformTexelBuffer);
-            let bloom_ref = pass.read(&bloom_output, AccessType::ComputeShaderReadSampledImageOrUniformTexelBuffer);
+            let bloom_ref = bloom_output.as_ref()
+                .map(|bloom| pass.read(bloom, AccessType::ComputeShaderReadSampledImageOrUniformTexelBuffer));
             let output_ref = pass.write(&mut output, AccessType::ComputeShaderWrite);
 
             let push_values = (
                 input_extent[0] as f32, input_extent[1] as f32, 1.0 / input_extent[0] as f32, 1.0 / input_extent[1] as f32,
                 1.0_f32, 1.0_f32, 0_u32, bloom_mip_level,
+                hdr_output,
             );
 
             pass.render(move|ctx| {
                 let offset = ctx.global_dynamic_buffer().push(&push_values);
 
+                let mut bindings = vec![
+                    input_ref.bind(),
+                    output_ref.bind(),
+                    RenderGraphPassBinding::DynamicBuffer(offset),
+                ];
+                if let Some(bloom_ref) = bloom_ref {
+                    bindings.push(bloom_ref.bind());
+                }
+
                 let bound_pipeline = ctx.bind_compute_pipeline(pipeline.into_bindings()
-                    .descriptor_set(0, &[
-                        input_ref.bind(),
-                        output_ref.bind(),
-                        RenderGraphPassBinding::DynamicBuffer(offset),
-                        bloom_ref.bind(),
-                    ])
+                    .descriptor_set(0, &bindings)
                 )?;
 
                 bound_pipeline.dispatch(input_extent);
@@ -517,5 +752,10 @@ impl PostProcessRenderer {
             .unwrap_or_else(|_| panic!("Failed to release histogram buffer, someone is still using it!"));
 
         rhi.device.destroy_buffer(histogram_buffer);
+
+        let probe_buffer = Arc::try_unwrap(self.luminance_probe_buffer)
+            .unwrap_or_else(|_| panic!("Failed to release luminance probe buffer, someone is still using it!"));
+
+        rhi.device.destroy_buffer(probe_buffer);
     }
 }
\ No newline at end of file
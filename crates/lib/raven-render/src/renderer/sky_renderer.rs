@@ -2,21 +2,70 @@ use std::{sync::Arc, f32::consts::PI};
 
 use ash::vk;
 
-use raven_math::{Vec3, SHBasis9, from_rgb8_to_color};
+use raven_math::{Vec3, Quat, SHBasis9, from_rgb8_to_color};
 use raven_asset::{asset_registry::{AssetHandle, get_runtime_asset_registry}, AssetType, VecArrayQueryParam, AsConcreteAsset};
 use raven_rg::{RenderGraphBuilder, RgHandle, IntoPipelineDescriptorBindings, RenderGraphPassBindable, RenderGraphPassBinding};
 use raven_rhi::{backend::{Image, ImageDesc, ImageSubResource, AccessType}, Rhi};
 
 use crate::MeshShadingContext;
 
+const PROCEDURAL_SKY_CUBEMAP_RESOLUTION: u32 = 256;
+
+/// Where the sky comes from. The sky is drawn as the background and used as the source of the image based lighting.
+pub enum SkyMode {
+    /// Cubemap split into six faces (+X, -X, +Y, -Y, +Z, -Z).
+    Cubemap([Arc<AssetHandle>; 6]),
+    /// Preetham analytic sky, the sun direction comes from the primary directional light.
+    Procedural {
+        /// Haziness of the atmosphere, in range [2.0, 10.0] (2.0 is a clear sky).
+        turbidity: f32,
+        /// Scale of the sky radiance.
+        intensity: f32,
+    },
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct ProceduralSkyConstants {
+    sun_direction: [f32; 3],
+    turbidity: f32,
+    intensity: f32,
+    cube_map_res: u32,
+}
+
+struct ProceduralSky {
+    cubemap: Arc<Image>,
+    turbidity: f32,
+    intensity: f32,
+    /// Sun direction which the cubemap is generated with, None if the cubemap need to be regenerated.
+    baked_sun_direction: Option<Vec3>,
+}
+
 pub struct SkyRenderer {
     cubemap: Option<Arc<Image>>,
+    procedural: Option<ProceduralSky>,
+    use_procedural: bool,
+    /// Rotation from the sky local space to the world space, only applied to the cubemap sky.
+    rotation: Quat,
+    /// Scale of the visible sky, it does not affect the image based lighting.
+    intensity: f32,
+    tint: Vec3,
+
+    /// The sky has changed since the last frame, resources depending on it (e.g. ibl) need to be regenerated.
+    is_changed: bool,
 }
 
 impl SkyRenderer {
     pub fn new() -> Self {
         Self {
             cubemap: None,
+            procedural: None,
+            use_procedural: false,
+            rotation: Quat::IDENTITY,
+            intensity: 1.0,
+            tint: Vec3::ONE,
+
+            is_changed: false,
         }
     }
 
@@ -34,6 +83,13 @@ impl SkyRenderer {
 
         Self {
             cubemap: Some(cubemap),
+            procedural: None,
+            use_procedural: false,
+            rotation: Quat::IDENTITY,
+            intensity: 1.0,
+            tint: Vec3::ONE,
+
+            is_changed: true,
         }
     }
 
@@ -42,6 +98,102 @@ impl SkyRenderer {
         // override the old one
         // TODO: delete the old one
         self.cubemap = Some(cubemap);
+        self.use_procedural = false;
+        self.is_changed = true;
+    }
+
+    /// Switch the sky mode. The device must be idle when switching from a cubemap to another one.
+    pub fn set_mode(&mut self, rhi: &Rhi, mode: SkyMode) {
+        match mode {
+            SkyMode::Cubemap(assets) => {
+                self.add_cubemap_split(rhi, &assets);
+            }
+            SkyMode::Procedural { turbidity, intensity } => {
+                if self.procedural.is_none() {
+                    let cubemap = rhi.device.create_image(
+                        ImageDesc::new_cube(PROCEDURAL_SKY_CUBEMAP_RESOLUTION, vk::Format::R16G16B16A16_SFLOAT)
+                            .usage_flags(vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::STORAGE),
+                        None
+                    ).expect("Failed to create procedural sky cubemap!");
+                    rhi.device.set_debug_name(cubemap.raw, "procedural sky cubemap");
+
+                    self.procedural = Some(ProceduralSky {
+                        cubemap: Arc::new(cubemap),
+                        turbidity,
+                        intensity,
+                        baked_sun_direction: None,
+                    });
+                }
+
+                let procedural = self.procedural.as_mut().unwrap();
+                procedural.turbidity = turbidity;
+                procedural.intensity = intensity;
+                procedural.baked_sun_direction = None;
+
+                self.use_procedural = true;
+                self.is_changed = true;
+            }
+        }
+    }
+
+    /// The procedural sky is in linear hdr color space, while the cubemaps are in srgb.
+    #[inline]
+    pub fn is_cubemap_linear(&self) -> bool {
+        self.use_procedural
+    }
+
+    /// Rotate the cubemap sky, the ibl resources are regenerated to match the new orientation.
+    pub fn set_rotation(&mut self, rotation: Quat) {
+        let rotation = rotation.normalize();
+        if !self.rotation.abs_diff_eq(rotation, 1e-6) {
+            self.rotation = rotation;
+            self.is_changed = true;
+        }
+    }
+
+    /// Rotation applied to the environment lookups of the current sky mode.
+    /// The procedural sky is generated in world space to follow the sun, so it is never rotated.
+    #[inline]
+    pub fn get_rotation(&self) -> Quat {
+        if self.use_procedural {
+            Quat::IDENTITY
+        } else {
+            self.rotation
+        }
+    }
+
+    /// Scale the brightness of the visible sky, it is applied when drawing and the cubemap is not regenerated.
+    #[inline]
+    pub fn set_intensity(&mut self, intensity: f32) {
+        assert!(intensity >= 0.0 && intensity.is_finite(), "Invalid sky intensity: {}", intensity);
+        self.intensity = intensity;
+    }
+
+    #[inline]
+    pub fn get_intensity(&self) -> f32 {
+        self.intensity
+    }
+
+    /// Linear color multiplied to the visible sky.
+    #[inline]
+    pub fn set_tint(&mut self, tint: Vec3) {
+        self.tint = tint.max(Vec3::ZERO);
+    }
+
+    #[inline]
+    pub fn get_tint(&self) -> Vec3 {
+        self.tint
+    }
+
+    /// The tint multiplied by the intensity.
+    #[inline]
+    pub fn radiance_scale(&self) -> Vec3 {
+        self.tint * self.intensity
+    }
+
+    /// Returns true once if the sky has changed since the last call (e.g. the sky mode switched or the sun moved).
+    pub fn take_changed(&mut self) -> bool {
+        std::mem::replace(&mut self.is_changed, false)
     }
 
     // cubemap split sequence:
@@ -67,12 +219,13 @@ impl SkyRenderer {
                         // each face's extent must be the same
                         extent = tex.extent;
                         assert_eq!(extent[0], extent[1]); // width height must be the same
+                        assert_eq!(tex.desc.channel_count, 4, "Sky cubemap faces must be rgba textures!");
 
                         let uploads = tex.lod_groups.iter()
-                            .map(|mip| ImageSubResource {
+                            .enumerate()
+                            .map(|(level, mip)| ImageSubResource {
                                 data: mip.as_slice(),
-                                // TODO: no hardcode
-                                row_pitch_in_bytes: extent[0] * 4,
+                                row_pitch_in_bytes: tex.desc.row_pitch_in_bytes(extent, level as u32),
                                 base_layer: face,
                             })
                             .collect::<Vec<_>>();
@@ -83,6 +236,7 @@ impl SkyRenderer {
                             AssetType::Texture => {
                                 let field_reader = read_guard.get_baked_texture_asset(baked);
                                 let tex_extent = field_reader.extent();
+                                let desc = field_reader.desc();
 
                                 if face != 0 {
                                     assert_eq!(extent[0], tex_extent[0]);
@@ -92,6 +246,7 @@ impl SkyRenderer {
                                 // each face's extent must be the same
                                 extent = tex_extent;
                                 assert_eq!(extent[0], extent[1]); // width height must be the same
+                                assert_eq!(desc.channel_count, 4, "Sky cubemap faces must be rgba textures!");
 
                                 let mips_len = field_reader.lod_groups(VecArrayQueryParam::length()).length();
 
@@ -101,8 +256,7 @@ impl SkyRenderer {
                                     
                                         ImageSubResource {
                                             data: mip,
-                                            // TODO: no hardcode
-                                            row_pitch_in_bytes: extent[0] * 4,
+                                            row_pitch_in_bytes: desc.row_pitch_in_bytes(extent, idx as u32),
                                             base_layer: face,
                                         }
                                     })
@@ -123,6 +277,7 @@ impl SkyRenderer {
                     .mipmap_level(upload_faces[0].len() as _),
                 Some(vec![])
             ).expect("Failed to create sky renderer cubemap texture!");
+            device.set_debug_name(cubemap.raw, "sky cubemap");
 
             device.upload_image_data(&cubemap, &upload_faces,
                 AccessType::AnyShaderReadSampledImageOrUniformTexelBuffer)
@@ -238,9 +393,69 @@ impl SkyRenderer {
         dir
     }
 
+    /// Get the cubemap of the current sky mode.
     #[inline]
-    pub fn get_cubemap(&self) -> &Option<Arc<Image>> {
-        &self.cubemap
+    pub fn get_cubemap(&self) -> Option<&Arc<Image>> {
+        if self.use_procedural {
+            self.procedural.as_ref().map(|procedural| &procedural.cubemap)
+        } else {
+            self.cubemap.as_ref()
+        }
+    }
+
+    /// Import the cubemap of the current sky mode, the procedural sky is regenerated if it is outdated or the sun has moved.
+    /// sun_direction is the normalized direction pointing to the sun.
+    pub fn prepare_cubemap(&mut self, rg: &mut RenderGraphBuilder, sun_direction: Vec3) -> Option<RgHandle<Image>> {
+        if !self.use_procedural {
+            return self.cubemap.as_ref()
+                .map(|cubemap| rg.import(cubemap.clone(), AccessType::AnyShaderReadSampledImageOrUniformTexelBuffer));
+        }
+
+        let procedural = self.procedural.as_mut()?;
+        let is_outdated = procedural.baked_sun_direction
+            .map_or(true, |baked| !baked.abs_diff_eq(sun_direction, 1e-5));
+
+        if !is_outdated {
+            return Some(rg.import(procedural.cubemap.clone(), AccessType::AnyShaderReadSampledImageOrUniformTexelBuffer));
+        }
+
+        let mut cubemap = rg.import(procedural.cubemap.clone(), AccessType::Nothing);
+        {
+            let mut pass = rg.add_pass("procedural sky");
+            let pipeline = pass.register_compute_pipeline("sky/procedural_sky.hlsl");
+
+            let cubemap_ref = pass.write(&mut cubemap, AccessType::ComputeShaderWrite);
+
+            let constants = ProceduralSkyConstants {
+                sun_direction: sun_direction.to_array(),
+                turbidity: procedural.turbidity,
+                intensity: procedural.intensity,
+                cube_map_res: PROCEDURAL_SKY_CUBEMAP_RESOLUTION,
+            };
+
+            pass.render(move |ctx| {
+                let constants_offset = ctx.global_dynamic_buffer().push(&constants);
+
+                let mut cubemap_binding = cubemap_ref.bind();
+                cubemap_binding.with_image_view(vk::ImageViewType::TYPE_2D_ARRAY);
+
+                let bound_pipeline = ctx.bind_compute_pipeline(pipeline.into_bindings()
+                    .descriptor_set(0, &[
+                        cubemap_binding,
+                        RenderGraphPassBinding::DynamicBuffer(constants_offset),
+                    ])
+                )?;
+
+                bound_pipeline.dispatch([PROCEDURAL_SKY_CUBEMAP_RESOLUTION, PROCEDURAL_SKY_CUBEMAP_RESOLUTION, 6]);
+
+                Ok(())
+            });
+        }
+
+        procedural.baked_sun_direction = Some(sun_direction);
+        self.is_changed = true;
+
+        Some(cubemap)
     }
 
     pub fn prepare_rg(&self, rg: &mut RenderGraphBuilder, shading_context: &MeshShadingContext, output_img: &mut RgHandle<Image>) {
@@ -292,5 +507,11 @@ impl SkyRenderer {
 
             rhi.device.destroy_image(cubemap);
         }
+
+        if let Some(procedural) = self.procedural {
+            let cubemap = Arc::try_unwrap(procedural.cubemap).unwrap_or_else(|_| panic!("Failed to release procedural sky cubemap, someone is still using it!"));
+
+            rhi.device.destroy_image(cubemap);
+        }
     }
 }
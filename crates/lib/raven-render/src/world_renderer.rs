@@ -3,39 +3,203 @@ use std::{sync::Arc, collections::HashMap};
 use ash::vk;
 
 use raven_container::as_bytes;
-use raven_math::{Affine3A, Vec2, Vec3, Quat};
+use raven_math::{Affine3A, Vec2, Vec3, Quat, Mat4, AABB, Frustum, Ray};
 use raven_asset::asset_registry::AssetHandle;
-use raven_scene::camera::{Camera, controller::FirstPersonController, CameraFrameConstants};
-use raven_rg::{RenderGraphBuilder, RgHandle, IntoPipelineDescriptorBindings, RenderGraphPassBindable, RenderGraphPassBinding};
-use raven_rhi::{Rhi, backend::{ImageDesc, Image, AccessType}, global_bindless_descriptor};
+use raven_scene::camera::{Camera, CameraLens, controller::FirstPersonController, CameraFrameConstants};
+use raven_rg::{RenderGraphBuilder, RgHandle, LightFrameConstants, IntoPipelineDescriptorBindings, RenderGraphPassBindable, RenderGraphPassBinding};
+use raven_rhi::{Rhi, backend::{ImageDesc, Image, Buffer, AccessType}, global_bindless_descriptor};
 
 use crate::{
     MeshRenderer, IblRenderer, SkyRenderer,
     MeshRasterScheme, MeshShadingContext,
     renderer::{
-        mesh_renderer::{MeshHandle, MeshInstanceHandle},
-        post_process_renderer::{PostProcessRenderer}, image_lut::ImageLut, lut_renderer::BrdfLutComputer, light_renderer::DirectionalLight,
+        mesh_renderer::{MeshHandle, MeshInstanceHandle, MaterialOverride, MeshLodSettings, GBuffer}, draw_data_allocator::DrawDataStats,
+        post_process_renderer::{self, PostProcessRenderer}, image_lut::ImageLut, lut_renderer::BrdfLutComputer, light_renderer::{DirectionalLight, LightHandle}, light_units::{self, LightUnit}, sky_renderer::SkyMode,
+        env_capture_renderer::{self, EnvCaptureRenderer, EnvCubemapHandle, EnvCaptureMode},
     }, LightRenderer, DebugRenderer, auto_exposure::{AutoExposureAdjustment, ExposureState}
 };
 #[cfg(feature = "gpu_ray_tracing")]
 use crate::renderer::gpu_path_tracing_renderer::GpuPathTracingRenderer;
 
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum RenderMode {
     Raster,
     GpuPathTracing,
+    /// Decoded gbuffer albedo, without any lighting.
+    DebugAlbedo,
+    /// World space shading normal remapped to [0, 1].
+    DebugNormal,
+    /// View distance of the depth buffer, the near surfaces are bright.
+    DebugDepth,
+    /// Perceptual roughness in red and metalness in green.
+    DebugRoughnessMetallic,
+}
+
+impl RenderMode {
+    /// Cycle through the raster mode and the gbuffer debug views, the path tracing mode goes back to raster.
+    pub fn next_debug_view(self) -> Self {
+        match self {
+            RenderMode::Raster => RenderMode::DebugAlbedo,
+            RenderMode::DebugAlbedo => RenderMode::DebugNormal,
+            RenderMode::DebugNormal => RenderMode::DebugDepth,
+            RenderMode::DebugDepth => RenderMode::DebugRoughnessMetallic,
+            RenderMode::DebugRoughnessMetallic | RenderMode::GpuPathTracing => RenderMode::Raster,
+        }
+    }
+
+    // Same in defer/gbuffer_debug.hlsl
+    fn gbuffer_debug_view(self) -> Option<u32> {
+        match self {
+            RenderMode::DebugAlbedo => Some(0),
+            RenderMode::DebugNormal => Some(1),
+            RenderMode::DebugDepth => Some(2),
+            RenderMode::DebugRoughnessMetallic => Some(3),
+            RenderMode::Raster | RenderMode::GpuPathTracing => None,
+        }
+    }
+}
+
+/// Render features which can be toggled at runtime in raster mode, e.g. for A/B debugging.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum RenderFeature {
+    /// Directional light shadow maps.
+    Shadows,
+    /// Image based (indirect) lighting, it also requires the sky or an active environment cubemap.
+    Ibl,
+    /// Sky background, when disabled the background is black and the ibl is disabled too (unless an environment cubemap is active).
+    Sky,
+    Bloom,
+    /// Forward pass of the alpha blended meshes.
+    Transparency,
+    /// Debug lines and aabbs.
+    DebugLines,
+    /// Temporal anti-aliasing, the camera is jittered every frame.
+    Taa,
+}
+
+/// Set of enabled render features, the passes of the disabled features are not added into the render graph at all.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RenderFeatures(u32);
+
+impl RenderFeatures {
+    pub const NONE: Self = Self(0);
+    pub const ALL : Self = Self(u32::MAX);
+
+    #[inline]
+    pub fn contains(&self, feature: RenderFeature) -> bool {
+        (self.0 & Self::bit(feature)) != 0
+    }
+
+    #[inline]
+    pub fn set(&mut self, feature: RenderFeature, enabled: bool) {
+        if enabled {
+            self.0 |= Self::bit(feature);
+        } else {
+            self.0 &= !Self::bit(feature);
+        }
+    }
+
+    #[inline]
+    fn bit(feature: RenderFeature) -> u32 {
+        1 << feature as u32
+    }
+}
+
+/// All the features are enabled by default.
+impl Default for RenderFeatures {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct CameraId(u32);
+
+/// Render graph resources of the main view at the end of a frame, custom passes can read them or draw on top of them
+/// before they are blitted to the swapchain.
+pub struct FrameRenderTargets {
+    /// Final color in the render resolution (B10G11R11_UFLOAT_PACK32), after the post processing and the debug lines.
+    /// It is tonemapped when presenting in SDR, and linear (1.0 is the paper white) when presenting in HDR.
+    /// Write a new image and assign it here to replace the output.
+    pub color: RgHandle<Image>,
+    /// Depth of the main camera in the render resolution (D32_SFLOAT, reversed z, 0.0 is the sky).
+    /// None in the path tracing mode, which doesn't rasterize the gbuffer.
+    pub depth: Option<RgHandle<Image>>,
+}
+
+/// Rectangle of the output image, normalized to [0, 1] with the origin at the top-left corner.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Viewport {
+    pub offset: [f32; 2],
+    pub extent: [f32; 2],
+}
+
+impl Viewport {
+    pub fn new(offset: [f32; 2], extent: [f32; 2]) -> Self {
+        Self { offset, extent }
+    }
+
+    /// The whole output image.
+    pub fn full() -> Self {
+        Self::new([0.0, 0.0], [1.0, 1.0])
+    }
+
+    /// Offset and extent in pixels of the resolution, clipped to the image.
+    fn to_pixels(&self, resolution: [u32; 2]) -> ([u32; 2], [u32; 2]) {
+        let mut offset = [0; 2];
+        let mut extent = [0; 2];
+
+        for axis in 0..2 {
+            let res = resolution[axis] as f32;
+            let start = (self.offset[axis].clamp(0.0, 1.0) * res).round();
+            let end = ((self.offset[axis] + self.extent[axis]).clamp(0.0, 1.0) * res).round();
+
+            offset[axis] = start as u32;
+            extent[axis] = (end - start).max(0.0) as u32;
+        }
+
+        (offset, extent)
+    }
+}
+
+struct ViewportCamera {
+    id: CameraId,
+    camera: Camera,
+    controller: FirstPersonController,
+    viewport: Viewport,
+}
+
+/// Lighting resources shared by all the views in a frame.
+struct RasterLighting {
+    cubemap: Option<RgHandle<Image>>,
+    is_cubemap_linear: bool,
+    sh_buffer: Option<RgHandle<Buffer>>,
+    prefilter_cubemap: Option<RgHandle<Image>>,
+    light_maps: Vec<RgHandle<Image>>,
+    light_matrices: Vec<Mat4>,
 }
 
 pub struct WorldRenderer {
     // TODO: remove this, renderer only do render jobs
     main_camera: Option<(Camera, FirstPersonController)>,
+    /// Cameras rendered on top of the main camera, in the order they were added.
+    viewport_cameras: Vec<ViewportCamera>,
+    next_camera_id: u32,
 
     render_resolution: [u32; 2],
+    /// Resolution of the deferred lighting relative to the gbuffer, see set_lighting_resolution_scale().
+    lighting_resolution_scale: f32,
 
     sky_renderer: SkyRenderer,
     ibl_renderer: IblRenderer,
+    env_capture_renderer: EnvCaptureRenderer,
+    /// Environment cubemap used as the source of the ibl instead of the sky.
+    active_env_cubemap: Option<EnvCubemapHandle>,
 
     mesh_renderer: MeshRenderer,
     light_renderer: LightRenderer,
+    /// Primary directional light, which drives the procedural sky.
+    sun_light: LightHandle,
 
     exposure_state: ExposureState,
     auto_exposure: AutoExposureAdjustment,
@@ -43,6 +207,8 @@ pub struct WorldRenderer {
 
     debug_renderer: DebugRenderer,
 
+    features: RenderFeatures,
+
     image_luts: Vec<ImageLut>,
     bindless_descriptor_set: vk::DescriptorSet, // global bindless resources descriptor
 
@@ -52,8 +218,16 @@ pub struct WorldRenderer {
     need_reset_accum: bool,
 
     render_mode: RenderMode,
+
+    /// Index in the TAA jitter sequence.
+    taa_jitter_index: u32,
+    prev_world_to_clip: Option<Mat4>,
+    prev_jitter: Vec2,
 }
 
+/// Length of the Halton(2, 3) jitter sequence.
+const TAA_JITTER_SAMPLE_COUNT: u32 = 8;
+
 impl WorldRenderer {
     pub fn new(rhi: &Rhi, render_res: [u32; 2]) -> Self {
         let mut mesh_renderer = MeshRenderer::new(rhi, MeshRasterScheme::Deferred, render_res);
@@ -66,26 +240,35 @@ impl WorldRenderer {
         
         let handle = mesh_renderer.add_bindless_image(brdf_lut.get_backing_image().clone());
         assert_eq!(handle.0, 0);
+        mesh_renderer.add_default_material_textures();
 
         image_luts.push(brdf_lut);
 
         let mut light_renderer = LightRenderer::new(rhi);
-        let _light_handle = light_renderer.add_directional_light(DirectionalLight {
+        let sun_light = light_renderer.add_directional_light(DirectionalLight {
             direction: Quat::from_rotation_arc(Vec3::from((0.0, 0.0, -1.0)), Vec3::from((-0.32803, 0.90599, 0.26749))),
             color: Vec3::new(1.0, 1.0, 1.0),
-            intensity: 1.0,
+            // 1.0 in the shaders, the sun of a clear day (light_units::SUNNY_DAY_LUX) is several times brighter
+            intensity: light_units::PHYSICAL_LUMINANCE_UNIT,
+            unit: LightUnit::Lux,
             shadowed: true,
         });
 
         Self {
             main_camera: None,
+            viewport_cameras: Vec::new(),
+            next_camera_id: 0,
             render_resolution: render_res,
+            lighting_resolution_scale: 1.0,
 
             sky_renderer: SkyRenderer::new(),
             ibl_renderer: IblRenderer::new(rhi),
+            env_capture_renderer: EnvCaptureRenderer::new(),
+            active_env_cubemap: None,
 
             mesh_renderer,
             light_renderer,
+            sun_light,
 
             auto_exposure: AutoExposureAdjustment::new(),
             exposure_state: Default::default(),
@@ -93,6 +276,8 @@ impl WorldRenderer {
 
             debug_renderer: DebugRenderer::new(rhi),
 
+            features: RenderFeatures::default(),
+
             image_luts,
             bindless_descriptor_set,
 
@@ -102,16 +287,21 @@ impl WorldRenderer {
             need_reset_accum: true,
 
             render_mode: RenderMode::Raster,
+
+            taa_jitter_index: 0,
+            prev_world_to_clip: None,
+            prev_jitter: Vec2::ZERO,
         }
     }
 
     // TODO: remove this, renderer only do render jobs
-    pub fn update_camera(&mut self, mouse_delta: Vec2, is_left_mouse_holding: bool, input: &HashMap<&str, f32>) {
+    pub fn update_camera(&mut self, mouse_delta: Vec2, is_left_mouse_holding: bool, input: &HashMap<&str, f32>, dt: f32) {
         if let Some((cam, controller)) = &mut self.main_camera {
             controller.update(
                 cam, mouse_delta,
                 is_left_mouse_holding,
-                input["walk"], input["strafe"], input["lift"]
+                input["walk"], input["strafe"], input["lift"],
+                dt
             );
         }
     }
@@ -126,6 +316,60 @@ impl WorldRenderer {
         }
     }
 
+    /// Camera matrices of this frame with the TAA jitter and the last frame's matrices, call it once per frame.
+    pub fn prepare_camera_render_data(&mut self) -> CameraFrameConstants {
+        let render_data = self.get_camera_render_data();
+        let world_to_clip = render_data.unjittered_world_to_clip();
+
+        let jitter = if self.is_taa_active() {
+            self.taa_jitter_index = (self.taa_jitter_index + 1) % TAA_JITTER_SAMPLE_COUNT;
+
+            // halton sequence starts from 1, a pixel is 2 / resolution in clip space
+            let sample = Vec2::new(raven_math::halton(self.taa_jitter_index + 1, 2), raven_math::halton(self.taa_jitter_index + 1, 3));
+            (sample - 0.5) * 2.0 / Vec2::new(self.render_resolution[0] as f32, self.render_resolution[1] as f32)
+        } else {
+            Vec2::ZERO
+        };
+
+        let mut render_data = render_data.with_jitter(jitter);
+        render_data.prev_world_to_clip = self.prev_world_to_clip.replace(world_to_clip).unwrap_or(world_to_clip);
+        render_data.jitter.z = self.prev_jitter.x;
+        render_data.jitter.w = self.prev_jitter.y;
+        self.prev_jitter = jitter;
+
+        render_data
+    }
+
+    #[inline]
+    fn is_taa_active(&self) -> bool {
+        matches!(self.render_mode, RenderMode::Raster) && self.features.contains(RenderFeature::Taa)
+    }
+
+    /// Run the deferred lighting at a fraction of the render resolution (clamped to [0.25, 1.0]), e.g. 0.5 for half resolution.
+    ///
+    /// The gbuffer stays in full resolution, the lighting is upsampled with a filter guided by the depth and the geometric normals,
+    /// so the geometry edges stay sharp. But the shading details smaller than a lighting pixel (e.g. normal maps, shadow edges, specular highlights)
+    /// are blurred, and thin geometry missed by the lighting borrows the lighting of its neighbors. 1.0 shades every pixel without upsampling.
+    pub fn set_lighting_resolution_scale(&mut self, scale: f32) {
+        self.lighting_resolution_scale = scale.clamp(0.25, 1.0);
+    }
+
+    #[inline]
+    pub fn get_lighting_resolution_scale(&self) -> f32 {
+        self.lighting_resolution_scale
+    }
+
+    /// Weight of the current frame in TAA, lower values are smoother but ghost more.
+    #[inline]
+    pub fn set_taa_blend_factor(&mut self, blend_factor: f32) {
+        self.post_process_renderer.set_taa_blend_factor(blend_factor);
+    }
+
+    #[inline]
+    pub fn get_taa_blend_factor(&self) -> f32 {
+        self.post_process_renderer.taa_blend_factor()
+    }
+
     // TODO: remove this, renderer only do render jobs
     #[inline]
     pub fn get_camera_position(&self) -> Vec3 {
@@ -146,21 +390,336 @@ impl WorldRenderer {
         }
     }
 
+    /// Change the vertical field of view of the main camera, clamped to [`CameraLens::MIN_FOV_VERTICAL_DEGREES`, `CameraLens::MAX_FOV_VERTICAL_DEGREES`].
+    pub fn set_camera_fov(&mut self, fov_vertical_degrees: f32) {
+        if let Some((cam, _)) = &mut self.main_camera {
+            cam.lens.fov_vertical_degrees = fov_vertical_degrees.clamp(CameraLens::MIN_FOV_VERTICAL_DEGREES, CameraLens::MAX_FOV_VERTICAL_DEGREES);
+            self.on_camera_lens_changed();
+        }
+    }
+
+    /// Change the aspect ratio (width / height) of the main camera.
+    pub fn set_camera_aspect(&mut self, aspect_ratio: f32) {
+        assert!(aspect_ratio > 0.0 && aspect_ratio.is_finite(), "Invalid camera aspect ratio: {}", aspect_ratio);
+
+        if let Some((cam, _)) = &mut self.main_camera {
+            cam.lens.aspect_ratio = aspect_ratio;
+            self.on_camera_lens_changed();
+        }
+    }
+
+    /// Change the near plane distance of the main camera.
+    /// 
+    /// The projection uses infinite reverse-z, most of the depth precision is around the near plane,
+    /// so a larger near plane gives better precision to the distant objects (less z-fighting).
+    pub fn set_camera_near(&mut self, near_plane: f32) {
+        assert!(near_plane > 0.0 && near_plane.is_finite(), "Invalid camera near plane: {}", near_plane);
+
+        if let Some((cam, _)) = &mut self.main_camera {
+            cam.lens.near_plane = near_plane;
+            self.on_camera_lens_changed();
+        }
+    }
+
+    fn on_camera_lens_changed(&mut self) {
+        // camera matrices are computed from the lens every frame, only the accumulated image is stale
+        #[cfg(feature = "gpu_ray_tracing")]
+        {
+            self.need_reset_accum = true;
+        }
+    }
+
     // TODO: remove this, renderer only do render jobs
     #[inline]
     pub fn set_render_mode(&mut self, mode: RenderMode) {
         self.render_mode = mode;
     }
 
+    #[inline]
+    pub fn get_render_mode(&self) -> RenderMode {
+        self.render_mode
+    }
+
+    /// Enable or disable the forward transparency pass.
+    /// When disabled, transparent meshes are not drawn in raster mode.
+    #[inline]
+    pub fn set_transparency_enabled(&mut self, enabled: bool) {
+        self.set_feature(RenderFeature::Transparency, enabled);
+    }
+
+    /// Enable or disable a render feature, it takes effect when building the render graph of the next frame.
+    #[inline]
+    pub fn set_feature(&mut self, feature: RenderFeature, enabled: bool) {
+        self.features.set(feature, enabled);
+    }
+
+    #[inline]
+    pub fn is_feature_enabled(&self, feature: RenderFeature) -> bool {
+        self.features.contains(feature)
+    }
+
+    #[inline]
+    pub fn get_features(&self) -> RenderFeatures {
+        self.features
+    }
+
     #[inline]
     pub fn get_render_resolution(&self) -> [u32; 2] {
         self.render_resolution
     }
 
+    /// Change the internal render resolution, which is independent of the window (swapchain) size.
+    /// The aspect ratio of the main camera will follow the new resolution.
+    /// 
+    /// The old resolution resources are released once the frames in flight are done, so this does not stall the GPU.
+    pub fn set_render_resolution(&mut self, rhi: &Rhi, render_res: [u32; 2]) {
+        assert!(render_res[0] > 0 && render_res[1] > 0, "Invalid render resolution: {:?}", render_res);
+
+        if self.render_resolution == render_res {
+            return;
+        }
+
+        self.mesh_renderer.set_resolution(rhi, render_res);
+        self.render_resolution = render_res;
+
+        if let Some((cam, _)) = &mut self.main_camera {
+            cam.lens.aspect_ratio = render_res[0] as f32 / render_res[1] as f32;
+        }
+
+        #[cfg(feature = "gpu_ray_tracing")]
+        {
+            self.need_reset_accum = true;
+        }
+    }
+
     pub fn add_cubemap_split(&mut self, rhi: &Rhi, asset_handles: &[Arc<AssetHandle>; 6]) {
         self.sky_renderer.add_cubemap_split(rhi, asset_handles);
     }
 
+    pub fn set_sky_mode(&mut self, rhi: &Rhi, mode: SkyMode) {
+        rhi.device.wait_idle();
+        self.sky_renderer.set_mode(rhi, mode);
+
+        #[cfg(feature = "gpu_ray_tracing")]
+        {
+            self.need_reset_accum = true;
+        }
+    }
+
+    /// Set the direction pointing to the sun (the primary directional light), both the lighting and the procedural sky follow it.
+    pub fn set_sun_direction(&mut self, direction: Vec3) {
+        self.light_renderer.set_directional_light_direction(self.sun_light, direction);
+
+        #[cfg(feature = "gpu_ray_tracing")]
+        {
+            self.need_reset_accum = true;
+        }
+    }
+
+    pub fn get_sun_direction(&self) -> Vec3 {
+        self.light_renderer.get_directional_light_direction(self.sun_light)
+            .expect("Sun light must exist!")
+    }
+
+    /// Rotate the cubemap sky (e.g. to align the sun of a hdri with the scene).
+    /// Both the sky background and the image based lighting follow the rotation.
+    pub fn set_sky_rotation(&mut self, rotation: Quat) {
+        self.sky_renderer.set_rotation(rotation);
+
+        #[cfg(feature = "gpu_ray_tracing")]
+        {
+            self.need_reset_accum = true;
+        }
+    }
+
+    /// Rotation from the sky space to the world space, the procedural sky is never rotated.
+    #[inline]
+    pub fn get_sky_rotation(&self) -> Quat {
+        self.sky_renderer.get_rotation()
+    }
+
+    /// Scale the brightness of the visible sky, independent of the image based lighting.
+    pub fn set_sky_intensity(&mut self, intensity: f32) {
+        self.sky_renderer.set_intensity(intensity);
+        self.on_environment_scale_changed();
+    }
+
+    /// Linear color multiplied to the visible sky.
+    pub fn set_sky_tint(&mut self, tint: Vec3) {
+        self.sky_renderer.set_tint(tint);
+        self.on_environment_scale_changed();
+    }
+
+    /// Scale the diffuse and specular image based lighting, independent of the visible sky.
+    pub fn set_ibl_intensity(&mut self, intensity: f32) {
+        self.ibl_renderer.set_intensity(intensity);
+        self.on_environment_scale_changed();
+    }
+
+    /// Linear color multiplied to the image based lighting.
+    pub fn set_ibl_tint(&mut self, tint: Vec3) {
+        self.ibl_renderer.set_tint(tint);
+        self.on_environment_scale_changed();
+    }
+
+    #[inline]
+    pub fn get_sky_intensity(&self) -> f32 {
+        self.sky_renderer.get_intensity()
+    }
+
+    #[inline]
+    pub fn get_ibl_intensity(&self) -> f32 {
+        self.ibl_renderer.get_intensity()
+    }
+
+    /// Tint multiplied by the intensity of the visible sky for the frame constants.
+    #[inline]
+    pub fn get_sky_radiance_scale(&self) -> Vec3 {
+        self.sky_renderer.radiance_scale()
+    }
+
+    /// Tint multiplied by the intensity of the image based lighting for the frame constants.
+    #[inline]
+    pub fn get_ibl_radiance_scale(&self) -> Vec3 {
+        self.ibl_renderer.radiance_scale()
+    }
+
+    /// Render the scene into a cubemap from the position and use it as the source of the image based lighting instead of the sky,
+    /// e.g. a local reflection probe of an indoor scene.
+    /// 
+    /// The six faces are rendered with the deferred pipeline in the next raster frame, and the ibl switches to it in the frame after.
+    /// The faces are lit without the image based lighting, so the reflective surfaces do not sample the cubemap itself.
+    /// Static cubemaps are captured once, the on demand ones are captured again by refresh_environment_cubemap().
+    pub fn capture_environment_cubemap(&mut self, rhi: &Rhi, position: Vec3, resolution: u32, mode: EnvCaptureMode) -> EnvCubemapHandle {
+        let handle = self.env_capture_renderer.add(rhi, position, resolution, mode);
+        self.set_active_environment_cubemap(Some(handle));
+        handle
+    }
+
+    /// Capture the environment cubemap again in the next raster frame, return false if it is static or removed.
+    #[inline]
+    pub fn refresh_environment_cubemap(&mut self, handle: EnvCubemapHandle) -> bool {
+        self.env_capture_renderer.refresh(handle)
+    }
+
+    /// Remove the environment cubemap, the ibl falls back to the sky if it is the active one.
+    pub fn remove_environment_cubemap(&mut self, rhi: &Rhi, handle: EnvCubemapHandle) -> bool {
+        if self.active_env_cubemap == Some(handle) {
+            self.set_active_environment_cubemap(None);
+        }
+        self.env_capture_renderer.remove(rhi, handle)
+    }
+
+    /// Switch the source of the image based lighting to the environment cubemap, or back to the sky with None.
+    /// Returns false if the cubemap is removed.
+    pub fn set_active_environment_cubemap(&mut self, handle: Option<EnvCubemapHandle>) -> bool {
+        if handle.map_or(false, |handle| !self.env_capture_renderer.contains(handle)) {
+            return false;
+        }
+
+        if self.active_env_cubemap != handle {
+            self.active_env_cubemap = handle;
+            self.ibl_renderer.request_update();
+        }
+        true
+    }
+
+    #[inline]
+    pub fn get_active_environment_cubemap(&self) -> Option<EnvCubemapHandle> {
+        self.active_env_cubemap
+    }
+
+    fn on_environment_scale_changed(&mut self) {
+        // the scales are applied when shading, only the accumulated image is stale
+        #[cfg(feature = "gpu_ray_tracing")]
+        {
+            self.need_reset_accum = true;
+        }
+    }
+
+    /// Light constants of the directional lights and the directional light count for the frame constants.
+    #[inline]
+    pub fn get_light_frame_constants(&self) -> ([LightFrameConstants; 10], u32) {
+        let (mut constants, count) = self.light_renderer.light_frame_constants();
+
+        // shadow maps are not drawn, so do not sample them
+        if !self.features.contains(RenderFeature::Shadows) {
+            for constant in constants.iter_mut() {
+                constant.shadowed = 0;
+            }
+        }
+
+        (constants, count)
+    }
+
+    /// All the directional lights (including the sun), in the order they are added.
+    #[inline]
+    pub fn get_directional_lights(&self) -> Vec<(LightHandle, DirectionalLight)> {
+        self.light_renderer.get_directional_lights()
+            .map(|(handle, light)| (handle, light.clone()))
+            .collect()
+    }
+
+    /// Change the direction and the radiance of a directional light, the intensity is in the current unit of the light.
+    pub fn update_directional_light(&mut self, handle: LightHandle, direction: Quat, color: Vec3, intensity: f32) {
+        self.light_renderer.update_directional_light(handle, direction, color, intensity);
+
+        #[cfg(feature = "gpu_ray_tracing")]
+        {
+            self.need_reset_accum = true;
+        }
+    }
+
+    /// Change the intensity and its unit of a directional light, e.g. light_units::SUNNY_DAY_LUX with LightUnit::Lux.
+    pub fn set_directional_light_intensity(&mut self, handle: LightHandle, intensity: f32, unit: LightUnit) {
+        self.light_renderer.set_directional_light_intensity(handle, intensity, unit);
+
+        #[cfg(feature = "gpu_ray_tracing")]
+        {
+            self.need_reset_accum = true;
+        }
+    }
+
+    /// Handle of the primary directional light, which drives the procedural sky.
+    #[inline]
+    pub fn get_sun_light(&self) -> LightHandle {
+        self.sun_light
+    }
+
+    /// Move the main camera to the transform immediately.
+    pub fn set_camera_transform(&mut self, position: Vec3, rotation: Quat) {
+        if let Some((cam, controller)) = &mut self.main_camera {
+            controller.teleport_to(cam, position, rotation);
+        } else {
+            panic!("Main camera not set yet!");
+        }
+    }
+
+    /// Bounds of all the mesh instances in world space, it is empty (see AABB::is_empty()) when there is no instance.
+    #[inline]
+    pub fn get_scene_aabb(&self) -> AABB {
+        self.mesh_renderer.get_scene_aabb()
+    }
+
+    /// Move the main camera backwards until the whole scene fits in the view, the rotation is unchanged.
+    /// Returns false and leaves the camera unchanged if the scene is empty.
+    pub fn frame_scene(&mut self) -> bool {
+        let scene_aabb = self.get_scene_aabb();
+        if scene_aabb.is_empty() {
+            glog::warn!("Nothing to frame, the scene has no mesh instance!");
+            return false;
+        }
+
+        if let Some((cam, controller)) = &mut self.main_camera {
+            let position = cam.get_framing_position(&scene_aabb);
+            let rotation = cam.body.rotation;
+            controller.teleport_to(cam, position, rotation);
+            true
+        } else {
+            panic!("Main camera not set yet!");
+        }
+    }
+
     pub fn add_mesh(&mut self, asset_handle: &Arc<AssetHandle>) -> MeshHandle {
         let handle = self.mesh_renderer.add_asset_mesh(asset_handle);
 
@@ -170,17 +729,225 @@ impl WorldRenderer {
         handle
     }
 
+    /// Remove the mesh and all of its instances, return false if the mesh is already removed.
+    /// The gpu memory of the mesh is reused by the meshes added later, see get_draw_data_stats().
+    pub fn remove_mesh(&mut self, rhi: &Rhi, handle: MeshHandle) -> bool {
+        let is_removed = self.mesh_renderer.remove_mesh(handle);
+
+        #[cfg(feature = "gpu_ray_tracing")]
+        if is_removed {
+            self.gpu_ray_tracing_renderer.remove_mesh(handle, rhi);
+            self.need_reset_accum = true;
+        }
+        #[cfg(not(feature = "gpu_ray_tracing"))]
+        let _ = rhi;
+
+        is_removed
+    }
+
+    /// Usage and fragmentation of the gpu buffer containing the vertices and indices of all the meshes.
+    #[inline]
+    pub fn get_draw_data_stats(&self) -> DrawDataStats {
+        self.mesh_renderer.draw_data_stats()
+    }
+
+    /// Pack the mesh data to remove the holes left by the removed meshes, this waits for the device to be idle.
+    /// It also happens automatically when a new mesh only fits after packing.
+    #[inline]
+    pub fn compact_mesh_data(&mut self) {
+        self.mesh_renderer.compact_draw_data();
+    }
+
     #[inline]
     pub fn add_mesh_instance(&mut self, handle: MeshHandle, transform: Affine3A) -> MeshInstanceHandle {
         self.mesh_renderer.add_mesh_instance(handle, transform)
     }
 
+    /// Fill the depth before gbuffer rasterization to reduce the overdraw, disabled by default.
+    #[inline]
+    pub fn set_depth_prepass(&mut self, enabled: bool) {
+        self.mesh_renderer.set_depth_prepass(enabled);
+    }
+
+    #[inline]
+    pub fn is_depth_prepass_enabled(&self) -> bool {
+        self.mesh_renderer.is_depth_prepass_enabled()
+    }
+
+    /// Set the distances where the coarser mesh LODs start, the LODs are selected per instance from each view's camera.
+    #[inline]
+    pub fn set_mesh_lod_settings(&mut self, settings: MeshLodSettings) {
+        self.mesh_renderer.set_lod_settings(settings);
+    }
+
+    #[inline]
+    pub fn get_mesh_lod_settings(&self) -> &MeshLodSettings {
+        self.mesh_renderer.get_lod_settings()
+    }
+
+    /// Set whether the instances of the mesh cast shadow, meshes with only unlit materials do not cast shadow by default.
+    #[inline]
+    pub fn set_mesh_cast_shadow(&mut self, handle: MeshHandle, cast_shadow: bool) {
+        self.mesh_renderer.set_mesh_cast_shadow(handle, cast_shadow)
+    }
+
+    /// Transforms of all the mesh instances, in the order they are added.
+    #[inline]
+    pub fn get_mesh_instance_transforms(&self) -> Vec<(MeshInstanceHandle, Affine3A)> {
+        self.mesh_renderer.get_mesh_instance_transforms()
+    }
+
+    /// Move a mesh instance, return false if the instance doesn't exist.
+    pub fn set_mesh_instance_transform(&mut self, handle: MeshInstanceHandle, transform: Affine3A) -> bool {
+        let is_moved = self.mesh_renderer.set_mesh_instance_transform(handle, transform);
+
+        #[cfg(feature = "gpu_ray_tracing")]
+        if is_moved {
+            self.need_reset_accum = true;
+        }
+
+        is_moved
+    }
+
+    /// Render a mesh instance with the material factors instead of the ones of its mesh, return false if the instance doesn't exist.
+    /// Textures are not overridden, they are still multiplied with the factors. Only affects the raster render mode.
+    #[inline]
+    pub fn override_instance_material(&mut self, handle: MeshInstanceHandle, material: MaterialOverride) -> bool {
+        self.mesh_renderer.override_instance_material(handle, Some(material))
+    }
+
+    /// Restore the mesh materials of a mesh instance, return false if the instance doesn't exist.
+    #[inline]
+    pub fn clear_instance_material_override(&mut self, handle: MeshInstanceHandle) -> bool {
+        self.mesh_renderer.override_instance_material(handle, None)
+    }
+
+    /// Tint the base color of a mesh instance (e.g. team colors), white is no tint. Return false if the instance doesn't exist.
+    /// Only affects the raster render mode.
+    #[inline]
+    pub fn set_instance_tint(&mut self, handle: MeshInstanceHandle, tint: [f32; 4]) -> bool {
+        self.mesh_renderer.set_instance_tint(handle, tint)
+    }
+
+    /// Pick the mesh instance rendered at the pixel (in render resolution) in the last frame.
+    /// Returns None for background. Only works in raster render mode.
+    #[inline]
+    pub fn pick(&self, pixel: [u32; 2]) -> Option<MeshInstanceHandle> {
+        self.mesh_renderer.pick_instance(pixel)
+    }
+
+    /// Mesh instances whose bounds are (conservatively) inside the frustum, e.g. from Camera::get_camera_frustum().
+    #[inline]
+    pub fn query_frustum(&mut self, frustum: &Frustum) -> Vec<MeshInstanceHandle> {
+        self.mesh_renderer.query_frustum(frustum)
+    }
+
+    /// The mesh instance whose bounds are hit first by the ray, and the hit distance to the bounds.
+    /// Unlike pick(), it works in any render mode and doesn't wait for the gpu, but only tests the instance bounds.
+    #[inline]
+    pub fn raycast(&mut self, ray: &Ray) -> Option<(MeshInstanceHandle, f32)> {
+        self.mesh_renderer.raycast(ray)
+    }
+
+    /// Probe the scene luminance (without exposure) of the pixel (in render resolution) for debugging exposure.
+    /// Returns the luminance of the last probed pixel, the value lags behind a few frames because of the gpu latency.
+    #[inline]
+    pub fn debug_luminance_probe(&mut self, pixel: [u32; 2]) -> f32 {
+        self.post_process_renderer.set_luminance_probe_pixel(pixel);
+        self.post_process_renderer.probe_luminance()
+    }
+
+    /// Scene luminance (without exposure) of the screen center, only probed after debug_luminance_probe() is called.
+    #[inline]
+    pub fn debug_center_luminance(&self) -> f32 {
+        self.post_process_renderer.center_luminance()
+    }
+
+    /// Remove all the meshes and mesh instances in the scene and release their gpu resources.
+    /// New meshes can be added right after this call to load a new scene.
+    /// 
+    /// This will wait for the device to be idle, so that no in-flight frames are referencing the released resources.
+    pub fn clear_scene(&mut self, rhi: &Rhi) {
+        rhi.device.wait_idle();
+
+        #[cfg(feature = "gpu_ray_tracing")]
+        {
+            self.gpu_ray_tracing_renderer.clear_meshes(rhi);
+            self.need_reset_accum = true;
+        }
+
+        self.mesh_renderer.clear_meshes(rhi);
+        self.debug_renderer.clear_lines();
+    }
+
     // TODO: move to scene
     #[inline]
-    pub fn set_main_camera(&mut self, camera: Camera, controller: FirstPersonController) {
+    pub fn set_main_camera(&mut self, mut camera: Camera, controller: FirstPersonController) {
+        // aspect ratio always follows the render resolution, not the window
+        camera.lens.aspect_ratio = self.render_resolution[0] as f32 / self.render_resolution[1] as f32;
         self.main_camera = Some((camera, controller));
     }
 
+    /// Render the scene from another camera into the viewport of the output image, on top of the main camera.
+    /// 
+    /// Each camera re-runs the gbuffer, lighting, transparency and post process passes in the viewport resolution,
+    /// so the gpu cost grows roughly with the total viewport area. 
+    /// The meshes, the acceleration structures, the shadow maps, the sky and the ibl resources are shared by all the cameras.
+    /// Viewport cameras have no TAA, debug lines or picking, and the path tracing mode ignores them.
+    pub fn add_camera(&mut self, camera: Camera, controller: FirstPersonController, viewport: Viewport) -> CameraId {
+        let id = CameraId(self.next_camera_id);
+        self.next_camera_id += 1;
+
+        self.viewport_cameras.push(ViewportCamera {
+            id,
+            camera,
+            controller,
+            viewport,
+        });
+        id
+    }
+
+    /// Returns false if the camera does not exist.
+    pub fn remove_camera(&mut self, id: CameraId) -> bool {
+        let len = self.viewport_cameras.len();
+        self.viewport_cameras.retain(|view| view.id != id);
+        self.viewport_cameras.len() != len
+    }
+
+    /// The camera and the controller of a viewport camera, the aspect ratio always follows its viewport.
+    pub fn get_camera_mut(&mut self, id: CameraId) -> Option<(&mut Camera, &mut FirstPersonController)> {
+        self.viewport_cameras.iter_mut()
+            .find(|view| view.id == id)
+            .map(|view| (&mut view.camera, &mut view.controller))
+    }
+
+    pub fn set_camera_viewport(&mut self, id: CameraId, viewport: Viewport) -> bool {
+        if let Some(view) = self.viewport_cameras.iter_mut().find(|view| view.id == id) {
+            view.viewport = viewport;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Draw a debug line only in the current frame.
+    #[inline]
+    pub fn draw_debug_line(&mut self, start: Vec3, end: Vec3, color: Vec3, depth_test: bool) {
+        self.debug_renderer.add_line(start, end, color, depth_test);
+    }
+
+    /// Draw a debug AABB only in the current frame.
+    #[inline]
+    pub fn draw_debug_aabb(&mut self, aabb: &AABB, color: Vec3, depth_test: bool) {
+        self.debug_renderer.add_aabb_lines(aabb, color, depth_test);
+    }
+
+    /// Draw the frustum of the camera only in the current frame.
+    #[inline]
+    pub fn draw_debug_frustum(&mut self, camera: &Camera, color: Vec3, depth_test: bool) {
+        self.debug_renderer.add_line_lists(&camera.get_camera_frustum_line_lists(), color, depth_test);
+    }
+
     pub fn update_pre_exposure(&mut self, dt: f32) {
         self.auto_exposure.update_ev(-self.post_process_renderer.image_log2_luminance(), dt);
         
@@ -217,84 +984,372 @@ impl WorldRenderer {
         self.need_reset_accum = true;
     }
 
-    pub fn prepare_rg(&mut self, rg: &mut RenderGraphBuilder, dt: f32) -> RgHandle<Image> {
+    /// Set the max number of bounces of each path, the accumulation is reset if it changed.
+    #[cfg(feature = "gpu_ray_tracing")]
+    pub fn set_path_tracing_bounces(&mut self, bounces: u32) {
+        if self.gpu_ray_tracing_renderer.set_max_bounces(bounces) {
+            self.need_reset_accum = true;
+        }
+    }
+
+    /// Set the number of samples traced per pixel in each frame, the accumulation is reset if it changed.
+    #[cfg(feature = "gpu_ray_tracing")]
+    pub fn set_samples_per_pixel_per_frame(&mut self, samples: u32) {
+        if self.gpu_ray_tracing_renderer.set_samples_per_frame(samples) {
+            self.need_reset_accum = true;
+        }
+    }
+
+    #[inline]
+    #[cfg(feature = "gpu_ray_tracing")]
+    pub fn get_path_tracing_bounces(&self) -> u32 {
+        self.gpu_ray_tracing_renderer.get_max_bounces()
+    }
+
+    #[inline]
+    #[cfg(feature = "gpu_ray_tracing")]
+    pub fn get_samples_per_pixel_per_frame(&self) -> u32 {
+        self.gpu_ray_tracing_renderer.get_samples_per_frame()
+    }
+
+    /// Number of samples accumulated in each pixel since the last reset.
+    #[inline]
+    #[cfg(feature = "gpu_ray_tracing")]
+    pub fn get_path_tracing_sample_count(&self) -> u32 {
+        self.gpu_ray_tracing_renderer.get_accumulated_samples()
+    }
+
+    /// Save the accumulated path tracing result to an OpenEXR file, it is written after the next path tracing frame.
+    /// The request is dropped with a warning if the render mode leaves path tracing before it is copied.
+    #[cfg(feature = "gpu_ray_tracing")]
+    pub fn save_path_tracing_result(&mut self, path: impl Into<std::path::PathBuf>) {
+        if !matches!(self.render_mode, RenderMode::GpuPathTracing) {
+            glog::warn!("Not in path tracing mode, nothing is saved!");
+        } else if !self.gpu_ray_tracing_renderer.request_save_result(path.into()) {
+            glog::warn!("Path tracing has not accumulated any sample yet, nothing is saved!");
+        }
+    }
+
+    pub fn prepare_rg(&mut self, rg: &mut RenderGraphBuilder, dt: f32) -> FrameRenderTargets {
         self.update_pre_exposure(dt);
         self.compute_image_lut_if_needed(rg);
 
+        // the accumulation image is not kept up to date out of path tracing, so a save request can't wait for it
+        #[cfg(feature = "gpu_ray_tracing")]
+        if !matches!(self.render_mode, RenderMode::GpuPathTracing) {
+            self.gpu_ray_tracing_renderer.flush_save_request();
+        }
+
         let output = match self.render_mode {
             RenderMode::Raster => self.prepare_rg_raster(rg),
             RenderMode::GpuPathTracing => self.prepare_rg_gpu_path_tracing(rg),
+            mode => self.prepare_rg_gbuffer_debug(rg, mode.gbuffer_debug_view().unwrap()),
         };
+        // debug lines only live for one frame
+        self.debug_renderer.clear_lines();
 
         output
     }
 
-    fn prepare_rg_raster(&mut self, rg: &mut RenderGraphBuilder) -> RgHandle<Image> {
-        let bindless_descriptor_set = self.bindless_descriptor_set;
-
-        let main_img_desc = ImageDesc::new_2d(self.render_resolution, vk::Format::R32G32B32A32_SFLOAT);
-        let mut main_img = rg.new_resource(main_img_desc);
-
-        let cubemap = self.sky_renderer.get_cubemap();
-        
-        let is_cubemap_exist = cubemap.is_some();
-        let cubemap_handle = if let Some(cubemap) = cubemap.as_ref() {
-            Some(rg.import(cubemap.clone(), AccessType::AnyShaderReadSampledImageOrUniformTexelBuffer))
+    fn prepare_rg_raster(&mut self, rg: &mut RenderGraphBuilder) -> FrameRenderTargets {
+        let cubemap = if self.features.contains(RenderFeature::Sky) {
+            self.sky_renderer.prepare_cubemap(rg, self.get_sun_direction())
         } else {
             None
         };
 
-        let (sh_buffer, prefilter_cubemap) = if is_cubemap_exist {
-            let (sh, prefilter) = self.ibl_renderer.prepare_ibl_if_needed(rg, cubemap_handle.as_ref().unwrap());
-            (Some(sh), Some(prefilter))
-        } else {
-            (None, None)
+        // regenerate the ibl resources when the sky changed
+        if self.sky_renderer.take_changed() {
+            self.ibl_renderer.request_update();
+        }
+
+        // the active environment cubemap replaces the sky once it is captured
+        let env_cubemap = self.active_env_cubemap
+            .and_then(|handle| self.env_capture_renderer.import_captured(rg, handle));
+        let (sh_buffer, prefilter_cubemap) = match env_cubemap.as_ref().or(cubemap.as_ref()) {
+            Some(ibl_source) if self.features.contains(RenderFeature::Ibl) => {
+                let (sh, prefilter) = self.ibl_renderer.prepare_ibl_if_needed(rg, ibl_source);
+                (Some(sh), Some(prefilter))
+            }
+            _ => (None, None),
         };
 
         // shadow mapping
         let light_render_data = self.light_renderer.prepare_render_data(
             rg, &self.mesh_renderer
         );
+        let light_matrices = light_render_data.light_matrices.clone();
         
         // mesh rasterization
+        let camera_position = self.get_camera_position();
         let (mut shading_context, light_maps) = self.mesh_renderer.prepare_rg(
-            rg, light_render_data,
+            rg, light_render_data, self.features.contains(RenderFeature::Shadows), camera_position,
+        );
+
+        // the sky, the ibl resources and the shadow maps are shared by all the views
+        let mut lighting = RasterLighting {
+            cubemap,
+            is_cubemap_linear: self.sky_renderer.is_cubemap_linear(),
+            sh_buffer,
+            prefilter_cubemap,
+            light_maps,
+            light_matrices,
+        };
+
+        let mut main_img = self.shade_view_rg(rg, &mut shading_context, &lighting, camera_position);
+
+        self.capture_environment_cubemaps_rg(rg, env_cubemap, &mut lighting);
+
+        if self.is_taa_active() {
+            main_img = match &shading_context {
+                MeshShadingContext::Defer(gbuffer) => {
+                    self.post_process_renderer.taa_resolve(rg, &main_img, &gbuffer.depth, &gbuffer.motion_vector)
+                }
+                _ => unimplemented!(),
+            };
+        }
+        
+        let mut post_img = self.post_process_renderer.prepare_rg(
+            rg, main_img,
+            self.exposure_state.post_mult, 1.0,
+            self.features.contains(RenderFeature::Bloom),
+        );
+
+        // match (&self.frame_count, &self.main_camera) {
+            //     (0, Some(main_cam)) => {
+                //         self.debug_renderer.add_debug_line_lists(main_cam.0.get_camera_frustum_line_lists());
+                
+                //         self.cam_aabb = main_cam.0.get_camera_frustum_aabb();
+                //         self.debug_renderer.add_debug_aabb(self.cam_aabb);
+                //     }
+                
+                //     (_, Some(_)) => {
+                    //         self.debug_renderer.add_debug_aabb(self.cam_aabb);
+                    //     }
+                    
+        //     _ => {}
+        // }
+        
+        if self.features.contains(RenderFeature::DebugLines) {
+            self.debug_renderer.add_debug_aabb(self.mesh_renderer.get_scene_aabb());
+            match &mut shading_context {
+                MeshShadingContext::Defer(gbuffer) => {
+                    self.debug_renderer.prepare_rg(rg, &mut post_img, &mut gbuffer.depth);
+                }
+                _ => unimplemented!(),
+            }
+        }
+        self.debug_renderer.remove_all_aabbs();
+
+        self.prepare_rg_viewport_cameras(rg, &lighting, &mut post_img);
+
+        FrameRenderTargets {
+            color: post_img,
+            depth: match shading_context {
+                MeshShadingContext::Defer(gbuffer) => Some(gbuffer.depth),
+                _ => None,
+            },
+        }
+    }
+
+    /// Rasterize the gbuffer and show one of its decoded channels, without the lighting and the post processing.
+    fn prepare_rg_gbuffer_debug(&mut self, rg: &mut RenderGraphBuilder, debug_view: u32) -> FrameRenderTargets {
+        // the shadow maps are not needed, but the light data is still required by the mesh passes
+        let light_render_data = self.light_renderer.prepare_render_data(
+            rg, &self.mesh_renderer
+        );
+
+        let camera_position = self.get_camera_position();
+        let (shading_context, _) = self.mesh_renderer.prepare_rg(
+            rg, light_render_data, false, camera_position,
         );
 
-        match &shading_context {
+        let gbuffer = match &shading_context {
+            MeshShadingContext::Defer(gbuffer) => gbuffer,
+            _ => unimplemented!(),
+        };
+
+        let extent = gbuffer.packed_gbuffer.desc().extent;
+        let mut output_img = rg.new_resource(ImageDesc::new_2d([extent[0], extent[1]], vk::Format::B10G11R11_UFLOAT_PACK32));
+
+        let mut pass = rg.add_pass("gbuffer debug view");
+        let pipeline = pass.register_compute_pipeline("defer/gbuffer_debug.hlsl");
+
+        let gbuffer_img_ref = pass.read(&gbuffer.packed_gbuffer, AccessType::ComputeShaderReadSampledImageOrUniformTexelBuffer);
+        let depth_img_ref = pass.read(&gbuffer.depth, AccessType::ComputeShaderReadSampledImageOrUniformTexelBuffer);
+        let output_img_ref = pass.write(&mut output_img, AccessType::ComputeShaderWrite);
+
+        pass.render(move |ctx| {
+            let mut depth_img_binding = depth_img_ref.bind();
+            depth_img_binding.with_aspect(vk::ImageAspectFlags::DEPTH);
+
+            let bound_pipeline = ctx.bind_compute_pipeline(pipeline.into_bindings()
+                .descriptor_set(0, &[
+                    gbuffer_img_ref.bind(),
+                    depth_img_binding,
+                    output_img_ref.bind(),
+                ])
+            )?;
+
+            let push_constants = [extent[0], extent[1], debug_view];
+            bound_pipeline.push_constants(vk::ShaderStageFlags::COMPUTE, 0, as_bytes::as_byte_slice_val(&push_constants));
+
+            bound_pipeline.dispatch(extent);
+
+            Ok(())
+        });
+
+        FrameRenderTargets {
+            color: output_img,
+            depth: match shading_context {
+                MeshShadingContext::Defer(gbuffer) => Some(gbuffer.depth),
+                _ => None,
+            },
+        }
+    }
+
+    /// Render every viewport camera and composite it into its rectangle of the output image.
+    /// 
+    /// Each view runs its own gbuffer, lighting, transparency and post process passes, 
+    /// but without the TAA and the debug lines.
+    fn prepare_rg_viewport_cameras(&mut self, rg: &mut RenderGraphBuilder, lighting: &RasterLighting, output_img: &mut RgHandle<Image>) {
+        let render_res = self.render_resolution;
+
+        for idx in 0..self.viewport_cameras.len() {
+            let (offset, resolution) = self.viewport_cameras[idx].viewport.to_pixels(render_res);
+            if resolution[0] == 0 || resolution[1] == 0 {
+                continue;
+            }
+
+            let view = &mut self.viewport_cameras[idx];
+            view.camera.lens.aspect_ratio = resolution[0] as f32 / resolution[1] as f32;
+            let camera_position = view.camera.body.position;
+
+            // all the passes of this view see this camera in the frame constants
+            rg.set_camera_override(Some(view.camera.get_camera_render_data()));
+
+            let mut shading_context = self.mesh_renderer.prepare_view_rg(rg, resolution, camera_position);
+            let view_img = self.shade_view_rg(rg, &mut shading_context, lighting, camera_position);
+            let view_post_img = self.post_process_renderer.prepare_view_rg(
+                rg, view_img,
+                self.exposure_state.post_mult, 1.0,
+                self.features.contains(RenderFeature::Bloom),
+            );
+
+            rg.set_camera_override(None);
+
+            post_process_renderer::composite_viewport(rg, &view_post_img, output_img, offset);
+        }
+    }
+
+    /// Render the six faces of the environment cubemaps waiting to be captured, with the shadow maps of the main view.
+    /// imported_active_cubemap is the active environment cubemap if it is already imported in this frame.
+    fn capture_environment_cubemaps_rg(&mut self, rg: &mut RenderGraphBuilder, mut imported_active_cubemap: Option<RgHandle<Image>>, lighting: &mut RasterLighting) {
+        let sky_rotation = self.sky_renderer.get_rotation();
+        let pending = self.env_capture_renderer.pending_captures(sky_rotation);
+        if pending.is_empty() {
+            return;
+        }
+
+        // no image based lighting, otherwise the reflective surfaces would sample the cubemap being captured
+        let sh_buffer = lighting.sh_buffer.take();
+        let prefilter_cubemap = lighting.prefilter_cubemap.take();
+
+        for handle in pending {
+            let position = self.env_capture_renderer.get_position(handle).unwrap();
+            // the ibl passes of this frame read the old capture before it is overwritten
+            let mut cubemap = match imported_active_cubemap.take() {
+                Some(cubemap) if self.active_env_cubemap == Some(handle) => cubemap,
+                other => {
+                    imported_active_cubemap = other;
+                    self.env_capture_renderer.import_for_capture(rg, handle)
+                }
+            };
+            let resolution = cubemap.desc().extent[0];
+
+            for face in 0..6 {
+                let camera = env_capture_renderer::face_camera(position, sky_rotation, face);
+                rg.set_camera_override(Some(camera.get_camera_render_data()));
+
+                let mut shading_context = self.mesh_renderer.prepare_view_rg(rg, [resolution, resolution], position);
+                let face_img = self.shade_view_rg(rg, &mut shading_context, lighting, position);
+
+                rg.set_camera_override(None);
+
+                env_capture_renderer::copy_face_rg(rg, &face_img, &mut cubemap, face);
+            }
+
+            self.env_capture_renderer.end_capture(rg, handle, cubemap, sky_rotation);
+            glog::info!("Captured environment cubemap {:?} at {} ({}x{})", handle, position, resolution, resolution);
+
+            // the ibl is regenerated from the new capture in the next frame
+            if self.active_env_cubemap == Some(handle) {
+                self.ibl_renderer.request_update();
+            }
+        }
+
+        lighting.sh_buffer = sh_buffer;
+        lighting.prefilter_cubemap = prefilter_cubemap;
+    }
+
+    /// Lighting and transparency of a view, returns the lit hdr image.
+    fn shade_view_rg(
+        &mut self,
+        rg: &mut RenderGraphBuilder,
+        shading_context: &mut MeshShadingContext,
+        lighting: &RasterLighting,
+        camera_position: Vec3,
+    ) -> RgHandle<Image> {
+        let bindless_descriptor_set = self.bindless_descriptor_set;
+
+        let is_cubemap_exist = lighting.cubemap.is_some();
+        let is_cubemap_linear = lighting.is_cubemap_linear;
+        let is_ibl_enabled = lighting.sh_buffer.is_some();
+        let lighting_resolution_scale = self.lighting_resolution_scale;
+
+        let mut main_img = match &*shading_context {
             // defer lighting
-            MeshShadingContext::Defer(gbuffer) => {        
+            MeshShadingContext::Defer(gbuffer) => {
+                let extent = gbuffer.packed_gbuffer.desc().extent;
+                let lighting_extent = [
+                    ((extent[0] as f32 * lighting_resolution_scale).ceil() as u32).max(1),
+                    ((extent[1] as f32 * lighting_resolution_scale).ceil() as u32).max(1),
+                ];
+                let mut main_img = rg.new_resource(ImageDesc::new_2d(lighting_extent, vk::Format::R32G32B32A32_SFLOAT));
+
                 let mut pass = rg.add_pass("gbuffer lighting");
                 let pipeline = pass.register_compute_pipeline("defer/defer_lighting.hlsl");
 
                 let gbuffer_img_ref = pass.read(&gbuffer.packed_gbuffer, AccessType::ComputeShaderReadSampledImageOrUniformTexelBuffer);
                 let depth_img_ref = pass.read(&gbuffer.depth, AccessType::ComputeShaderReadSampledImageOrUniformTexelBuffer);
-                let cubemap_ref = if let Some(cubemap) = cubemap_handle {
-                    Some(pass.read(&cubemap, AccessType::AnyShaderReadSampledImageOrUniformTexelBuffer))
+                let cubemap_ref = if let Some(cubemap) = &lighting.cubemap {
+                    Some(pass.read(cubemap, AccessType::AnyShaderReadSampledImageOrUniformTexelBuffer))
                 } else {
                     None
                 };
-                let sh_buffer_ref = if let Some(sh_buffer) = sh_buffer {
-                    Some(pass.read(&sh_buffer, AccessType::AnyShaderReadUniformBuffer))
+                let sh_buffer_ref = if let Some(sh_buffer) = &lighting.sh_buffer {
+                    Some(pass.read(sh_buffer, AccessType::AnyShaderReadUniformBuffer))
                 } else {
                     None
                 };
-                let prefilter_cubemap_ref = if let Some(prefilter_cubemap) = prefilter_cubemap {
-                    Some(pass.read(&prefilter_cubemap, AccessType::AnyShaderReadSampledImageOrUniformTexelBuffer))
+                let prefilter_cubemap_ref = if let Some(prefilter_cubemap) = &lighting.prefilter_cubemap {
+                    Some(pass.read(prefilter_cubemap, AccessType::AnyShaderReadSampledImageOrUniformTexelBuffer))
                 } else {
                     None
                 };
                 let main_img_ref = pass.write(&mut main_img, AccessType::ComputeShaderWrite);
                 
-                let light_map_refs = light_maps.iter()
+                let light_map_refs = lighting.light_maps.iter()
                     .map(|map| {
-                        pass.read(&map, AccessType::ComputeShaderReadSampledImageOrUniformTexelBuffer)
+                        pass.read(map, AccessType::ComputeShaderReadSampledImageOrUniformTexelBuffer)
                     })
                     .collect::<Vec<_>>(); 
+                let light_matrices = lighting.light_matrices.clone();
 
-                let extent = gbuffer.packed_gbuffer.desc().extent;
                 pass.render(move |ctx| {
-                    let light_mat_offset = ctx.global_dynamic_buffer().previous_pushed_data_offset();
+                    let light_mat_iter = light_matrices.iter()
+                        .map(|mat| mat.transpose().to_cols_array());
+                    let light_mat_offset = ctx.global_dynamic_buffer().push_from_iter(light_mat_iter);
 
                     let mut depth_img_binding = depth_img_ref.bind();
                     depth_img_binding.with_aspect(vk::ImageAspectFlags::DEPTH);
@@ -302,110 +1357,147 @@ impl WorldRenderer {
                     let mut light_map_binding = light_map_refs.bind();
                     light_map_binding.with_aspect(vk::ImageAspectFlags::DEPTH);
 
+                    // the sky and the ibl resources are only bound when they are enabled
+                    let mut bindings = vec![
+                        gbuffer_img_ref.bind(),
+                        depth_img_binding,
+                        main_img_ref.bind(),
+                        light_map_binding,
+                        RenderGraphPassBinding::DynamicStorageBuffer(light_mat_offset),
+                    ];
+                    if let Some(cubemap_ref) = cubemap_ref {
+                        bindings.push(cubemap_ref.bind());
+                    }
+                    if let (Some(sh_buffer_ref), Some(prefilter_cubemap_ref)) = (sh_buffer_ref, prefilter_cubemap_ref) {
+                        bindings.push(sh_buffer_ref.bind());
+                        bindings.push(prefilter_cubemap_ref.bind());
+                    }
+
                     // bind pipeline and descriptor set
-                    let bound_pipeline = if is_cubemap_exist {
-                        ctx.bind_compute_pipeline(pipeline.into_bindings()
-                            .descriptor_set(0, &[
-                                gbuffer_img_ref.bind(),
-                                depth_img_binding,
-                                main_img_ref.bind(),
-                                light_map_binding,
-                                RenderGraphPassBinding::DynamicStorageBuffer(light_mat_offset),
-                                cubemap_ref.unwrap().bind(),
-                                sh_buffer_ref.unwrap().bind(),
-                                prefilter_cubemap_ref.unwrap().bind(),
-                            ])
-                            .raw_descriptor_set(1, bindless_descriptor_set)
-                        )?
-                    } else {
-                        ctx.bind_compute_pipeline(pipeline.into_bindings()
-                            .descriptor_set(0, &[
-                                gbuffer_img_ref.bind(),
-                                depth_img_binding,
-                                main_img_ref.bind(),
-                                light_map_binding,
-                                RenderGraphPassBinding::DynamicStorageBuffer(light_mat_offset),
-                            ])
-                            .raw_descriptor_set(1, bindless_descriptor_set)
-                        )?
-                    };
-
-                    let push_constants = [extent[0], extent[1]];
+                    let bound_pipeline = ctx.bind_compute_pipeline(pipeline.into_bindings()
+                        .descriptor_set(0, &bindings)
+                        .raw_descriptor_set(1, bindless_descriptor_set)
+                    )?;
+
+                    let feature_flags = is_cubemap_exist as u32 | ((is_ibl_enabled as u32) << 1);
+                    let push_constants = [
+                        extent[0], extent[1], is_cubemap_linear as u32, feature_flags,
+                        lighting_extent[0], lighting_extent[1],
+                    ];
                     bound_pipeline.push_constants(vk::ShaderStageFlags::COMPUTE, 0, as_bytes::as_byte_slice_val(&push_constants));
                     
-                    bound_pipeline.dispatch(extent);
+                    bound_pipeline.dispatch([lighting_extent[0], lighting_extent[1], 1]);
 
                     Ok(())
                 });
+
+                if lighting_extent != [extent[0], extent[1]] {
+                    Self::upsample_lighting_rg(rg, &main_img, gbuffer, [extent[0], extent[1]])
+                } else {
+                    main_img
+                }
             },
             _ => unimplemented!(),
-        }
-        
-        let mut post_img = self.post_process_renderer.prepare_rg(
-            rg, main_img,
-            self.exposure_state.post_mult, 1.0
-        );
+        };
 
-        // match (&self.frame_count, &self.main_camera) {
-            //     (0, Some(main_cam)) => {
-                //         self.debug_renderer.add_debug_line_lists(main_cam.0.get_camera_frustum_line_lists());
-                
-                //         self.cam_aabb = main_cam.0.get_camera_frustum_aabb();
-                //         self.debug_renderer.add_debug_aabb(self.cam_aabb);
-                //     }
-                
-                //     (_, Some(_)) => {
-                    //         self.debug_renderer.add_debug_aabb(self.cam_aabb);
-                    //     }
-                    
-        //     _ => {}
-        // }
-        
-        self.debug_renderer.add_debug_aabb(self.mesh_renderer.get_scene_aabb());
-        match &mut shading_context {
-            MeshShadingContext::Defer(gbuffer) => {
-                self.debug_renderer.prepare_rg(rg, &mut post_img, &mut gbuffer.depth);
+        // forward transparency
+        if self.features.contains(RenderFeature::Transparency) {
+            match shading_context {
+                MeshShadingContext::Defer(gbuffer) => {
+                    self.mesh_renderer.draw_transparency(
+                        rg, &mut main_img, &mut gbuffer.depth,
+                        &lighting.light_maps, &lighting.light_matrices, camera_position
+                    );
+                }
+                _ => unimplemented!(),
             }
-            _ => unimplemented!(),
         }
-        self.debug_renderer.remove_all_aabbs();
 
-        post_img
+        main_img
+    }
+
+    /// Upsample the lighting to the gbuffer resolution, the lighting samples on different surfaces
+    /// (told apart by the depth and the geometric normal) are rejected to keep the edges.
+    fn upsample_lighting_rg(rg: &mut RenderGraphBuilder, lighting_img: &RgHandle<Image>, gbuffer: &GBuffer, extent: [u32; 2]) -> RgHandle<Image> {
+        let lighting_extent = lighting_img.desc().extent;
+        let mut output_img = rg.new_resource(ImageDesc::new_2d(extent, vk::Format::R32G32B32A32_SFLOAT));
+
+        let mut pass = rg.add_pass("lighting upsample");
+        let pipeline = pass.register_compute_pipeline("defer/lighting_upsample.hlsl");
+
+        let lighting_img_ref = pass.read(lighting_img, AccessType::ComputeShaderReadSampledImageOrUniformTexelBuffer);
+        let depth_img_ref = pass.read(&gbuffer.depth, AccessType::ComputeShaderReadSampledImageOrUniformTexelBuffer);
+        let geo_normal_img_ref = pass.read(&gbuffer.geometric_normal, AccessType::ComputeShaderReadSampledImageOrUniformTexelBuffer);
+        let output_img_ref = pass.write(&mut output_img, AccessType::ComputeShaderWrite);
+
+        pass.render(move |ctx| {
+            let mut depth_img_binding = depth_img_ref.bind();
+            depth_img_binding.with_aspect(vk::ImageAspectFlags::DEPTH);
+
+            let bound_pipeline = ctx.bind_compute_pipeline(pipeline.into_bindings()
+                .descriptor_set(0, &[
+                    lighting_img_ref.bind(),
+                    depth_img_binding,
+                    geo_normal_img_ref.bind(),
+                    output_img_ref.bind(),
+                ])
+            )?;
+
+            let push_constants = [extent[0], extent[1], lighting_extent[0], lighting_extent[1]];
+            bound_pipeline.push_constants(vk::ShaderStageFlags::COMPUTE, 0, as_bytes::as_byte_slice_val(&push_constants));
+
+            bound_pipeline.dispatch([extent[0], extent[1], 1]);
+
+            Ok(())
+        });
+
+        output_img
     }
 
     #[cfg(feature = "gpu_ray_tracing")]
-    fn prepare_rg_gpu_path_tracing(&mut self, rg: &mut RenderGraphBuilder) -> RgHandle<Image> {
+    fn prepare_rg_gpu_path_tracing(&mut self, rg: &mut RenderGraphBuilder) -> FrameRenderTargets {
         use raven_rg::{GetOrCreateTemporal, image_clear};
 
+        // temporal resources are not resized, so use a new one when the render resolution changed
         let mut accum_img = rg.get_or_create_temporal(
-            "path tracing accum image",
+            format!("path tracing accum image {}x{}", self.render_resolution[0], self.render_resolution[1]),
             ImageDesc::new_2d(self.render_resolution, vk::Format::R32G32B32A32_SFLOAT)
-                .usage_flags(vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::STORAGE | vk::ImageUsageFlags::TRANSFER_DST)
+                .usage_flags(vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::STORAGE | vk::ImageUsageFlags::TRANSFER_DST | vk::ImageUsageFlags::TRANSFER_SRC)
         ).expect("Failed to create path tracing accumulation image!");
 
         if self.need_reset_accum {
             image_clear::clear_color(rg, &mut accum_img, [0.0, 0.0, 0.0, 0.0]);
+            self.gpu_ray_tracing_renderer.reset_accumulated_samples();
             self.need_reset_accum = false;
         }
 
-        let cubemap = rg.import(
-            self.sky_renderer.get_cubemap().as_ref().unwrap().clone(),
-            AccessType::AnyShaderReadSampledImageOrUniformTexelBuffer
-        );
+        let cubemap = self.sky_renderer.prepare_cubemap(rg, self.get_sun_direction())
+            .expect("Path tracing requires a sky!");
+        // ibl is not used in path tracing, but it still needs to be regenerated when switching back to raster
+        if self.sky_renderer.take_changed() {
+            self.ibl_renderer.request_update();
+        }
+
+        let is_cubemap_linear = self.sky_renderer.is_cubemap_linear();
 
         let tlas = self.gpu_ray_tracing_renderer.update_tlas(rg, &self.mesh_renderer);
-        self.gpu_ray_tracing_renderer.path_tracing_accum(rg, &tlas, &mut accum_img, &cubemap, self.bindless_descriptor_set);
+        self.gpu_ray_tracing_renderer.path_tracing_accum(rg, &tlas, &mut accum_img, &cubemap, is_cubemap_linear, self.bindless_descriptor_set);
+        self.gpu_ray_tracing_renderer.save_result_if_requested(rg, &accum_img);
 
         let post_img = self.post_process_renderer.prepare_rg(
             rg, accum_img,
-            self.exposure_state.post_mult, 1.0
+            self.exposure_state.post_mult, 1.0,
+            self.features.contains(RenderFeature::Bloom),
         );
 
-        post_img
+        FrameRenderTargets {
+            color: post_img,
+            depth: None,
+        }
     }
 
     #[cfg(not(feature = "gpu_ray_tracing"))]
-    fn prepare_rg_gpu_path_tracing(&mut self, _rg: &mut RenderGraphBuilder) -> RgHandle<Image> {
+    fn prepare_rg_gpu_path_tracing(&mut self, _rg: &mut RenderGraphBuilder) -> FrameRenderTargets {
         panic!("gpu ray tracing is not support!");
     }
 
@@ -416,6 +1508,7 @@ impl WorldRenderer {
         self.post_process_renderer.clean(rhi);
 
         self.ibl_renderer.clean(rhi);
+        self.env_capture_renderer.clean(rhi);
         self.sky_renderer.clean(rhi);
 
         #[cfg(feature = "gpu_ray_tracing")]
@@ -1,5 +1,6 @@
 use std::sync::Arc;
 use std::cell::Cell;
+use std::collections::HashSet;
 
 use raven_rhi::{
     backend::{Image, Buffer, AccessType},
@@ -99,6 +100,14 @@ impl CompiledRenderGraph {
     ) -> ExecutingRenderGraph<'exec, 'dynamic> {
         let device = execution_params.device;
 
+        // only the resources created for the async compute passes are shared with the compute queue
+        let mut async_compute_resources = HashSet::new();
+        if device.has_async_compute_queue() {
+            for pass in self.render_graph.passes.iter().filter(|pass| pass.async_compute) {
+                async_compute_resources.extend(pass.inputs.iter().chain(pass.outputs.iter()).map(|pass_ref| pass_ref.handle.id as usize));
+            }
+        }
+
         let registered_resources = self.render_graph.resources.iter()
             .enumerate()
             .map(|(idx, resource)| {
@@ -111,6 +120,7 @@ impl CompiledRenderGraph {
                                 } else {
                                     panic!("Expect image description, but not found in the analyzed resource infos!");
                                 }
+                                desc.concurrent_sharing = async_compute_resources.contains(&idx);
 
                                 // get image from the cache of the last frame
                                 let image = if let Some(image) = cache.get_image(&desc) {
@@ -131,6 +141,7 @@ impl CompiledRenderGraph {
                                 } else {
                                     panic!("Expect buffer description, but not found in the analyzed resource infos!");
                                 }
+                                desc.concurrent_sharing = async_compute_resources.contains(&idx);
 
                                 let buffer = if let Some(buffer) = cache.get_buffer(&desc) {
                                     buffer
@@ -1,4 +1,5 @@
 use std::borrow::Borrow;
+use std::collections::HashSet;
 use std::sync::Arc;
 
 use ash::vk;
@@ -20,6 +21,77 @@ use crate::{
 
 const MAX_TRANSITION_PER_BATCH: usize = 64;
 
+/// Command buffers used when the device has a dedicated compute queue.
+pub(crate) struct AsyncComputeCommandBuffers<'a> {
+    /// Command buffer submitted to the async compute queue.
+    pub(crate) compute_cb: &'a CommandBuffer,
+    /// Command buffer submitted to the graphic queue after the async compute commands are done.
+    pub(crate) post_compute_cb: &'a CommandBuffer,
+}
+
+struct AsyncComputeSchedule {
+    async_compute_passes: Vec<Pass>,
+    graphic_passes: Vec<Pass>,
+    post_compute_passes: Vec<Pass>,
+}
+
+impl AsyncComputeSchedule {
+    fn has_async_compute(&self) -> bool {
+        !self.async_compute_passes.is_empty()
+    }
+}
+
+/// Split passes into async compute passes, graphic passes which can overlap with them,
+/// and graphic passes which must wait for them.
+/// 
+/// An async compute pass can only be scheduled on the compute queue if none of its resources
+/// are touched by the earlier graphic passes and can_run_on_compute_queue() accepts it,
+/// otherwise it falls back to the graphic queue.
+/// Once a graphic pass touches any resource of the async compute passes, it and all the passes after it
+/// will wait for the async compute queue.
+fn schedule_async_compute_passes(passes: Vec<Pass>, can_run_on_compute_queue: impl Fn(&Pass) -> bool) -> AsyncComputeSchedule {
+    let mut schedule = AsyncComputeSchedule {
+        async_compute_passes: Vec::new(),
+        graphic_passes: Vec::new(),
+        post_compute_passes: Vec::new(),
+    };
+
+    let mut graphic_resources = HashSet::new();
+    let mut async_compute_resources = HashSet::new();
+    let mut joined = false;
+
+    for pass in passes {
+        let resources = pass.inputs.iter().chain(pass.outputs.iter())
+            .map(|pass_res| pass_res.handle.id)
+            .collect::<Vec<_>>();
+
+        if pass.async_compute && !joined && resources.iter().all(|id| !graphic_resources.contains(id)) {
+            if can_run_on_compute_queue(&pass) {
+                async_compute_resources.extend(resources);
+                schedule.async_compute_passes.push(pass);
+                continue;
+            }
+
+            glog::debug!("Pass {} uses resources not shared with the compute queue, fallback to graphic queue.", pass.name);
+        } else if pass.async_compute {
+            glog::debug!("Pass {} depends on graphic passes, fallback to graphic queue.", pass.name);
+        }
+
+        if !joined && resources.iter().any(|id| async_compute_resources.contains(id)) {
+            joined = true;
+        }
+
+        if joined {
+            schedule.post_compute_passes.push(pass);
+        } else {
+            graphic_resources.extend(resources);
+            schedule.graphic_passes.push(pass);
+        }
+    }
+
+    schedule
+}
+
 pub(crate) struct ExecutingRenderGraph<'exec, 'dynamic> {
     pub(crate) execution_params: ExecutionParams<'exec>,
 
@@ -34,54 +106,124 @@ pub(crate) struct ExecutingRenderGraph<'exec, 'dynamic> {
 }
 
 impl<'exec, 'dynamic> ExecutingRenderGraph<'exec, 'dynamic> {
-    pub fn record_commands(
+    /// Record all the passes before the present pass.
+    /// 
+    /// If async compute command buffers is provided, passes marked as async compute will be recorded into it,
+    /// and graphic passes depending on them will be recorded into the post compute command buffer.
+    /// Returns true if any pass is recorded into the async compute command buffer.
+    pub(crate) fn record_commands(
         &mut self,
         cb: &CommandBuffer,
-    ) {
+        async_compute_cbs: Option<AsyncComputeCommandBuffers>,
+    ) -> bool {
         let first_present_pass = self.find_first_present_pass();
 
         // consume all the passes and be ready for executing
         let mut passes: Vec<_> = std::mem::take(&mut self.passes).into();
+        let remain_passes = passes.split_off(first_present_pass);
+
+        let schedule = match &async_compute_cbs {
+            Some(_) => schedule_async_compute_passes(passes, |pass| self.can_run_on_compute_queue(pass)),
+            None => AsyncComputeSchedule {
+                async_compute_passes: Vec::new(),
+                graphic_passes: passes,
+                post_compute_passes: Vec::new(),
+            },
+        };
+        let has_async_compute = schedule.has_async_compute();
 
-        // transition all the resources to the first access type to reduce some pipeline bubbles
-        {
-            let mut transition_resources = Vec::new();
-
-            for pass in &mut passes[..first_present_pass] {
-                for pass_ref in pass.inputs.iter_mut().chain(pass.outputs.iter_mut()) {
-                    let registered_res = self.registered_resources[pass_ref.handle.id as usize].borrow();
+        let AsyncComputeSchedule {
+            mut async_compute_passes,
+            mut graphic_passes,
+            post_compute_passes,
+        } = schedule;
 
-                    transition_resources.push((registered_res, PassResourceAccessType {
-                        access_type: pass_ref.access.access_type,
-                        // Force to reduce pipeline bubbles
-                        skip_sync_if_same: true,
-                        #[cfg(debug_assertions)]
-                        debug_pass_name: pass.name.clone(),
-                    }));
+        if let Some(async_compute_cbs) = &async_compute_cbs {
+            self.transition_to_first_access(async_compute_cbs.compute_cb, &mut async_compute_passes);
 
-                    // skip when encounter this resource again!
-                    pass_ref.access.skip_sync_if_same = true;
-                }
+            for pass in async_compute_passes {
+                self.record_pass_commands(async_compute_cbs.compute_cb, pass);
             }
+        }
 
-            for (transition_resource, access) in transition_resources {
-                self.resource_transition(&cb, transition_resource, access);
+        // transition all the resources to the first access type to reduce some pipeline bubbles
+        self.transition_to_first_access(cb, &mut graphic_passes);
+
+        // record commands
+        // leave only the present pass remain.
+        for pass in graphic_passes {
+            self.record_pass_commands(&cb, pass);
+        }
+
+        // passes which depend on async compute passes.
+        // they are recorded with normal synchronization, because the resources are touched by the compute queue.
+        if let Some(async_compute_cbs) = &async_compute_cbs {
+            for pass in post_compute_passes {
+                self.record_pass_commands(async_compute_cbs.post_compute_cb, pass);
             }
+        }
 
-            // TODO: transition all resources in batched have a problem
-            // when you try to transitioned same image twice in the one single barrier.
-            // you will received validation error from vulkan
+        self.passes = remain_passes.into();
+        has_async_compute
+    }
 
-            // self.resource_transition_batched(&cb, transition_resources);
+    /// Whether all the resources of the pass are shared with the compute queue, and all the barriers
+    /// recorded for them (including the one from the current access) only touch the compute stages.
+    fn can_run_on_compute_queue(&self, pass: &Pass) -> bool {
+        pass.inputs.iter().chain(pass.outputs.iter())
+            .all(|pass_ref| {
+                let registered_res = &self.registered_resources[pass_ref.handle.id as usize];
+
+                let is_shared = match &registered_res.resource {
+                    GraphPreparedResource::Delayed(_) => false,
+                    resource => match resource.borrow() {
+                        GraphPreparedResourceRef::Image(image) => image.desc.concurrent_sharing,
+                        GraphPreparedResourceRef::Buffer(buffer) => buffer.desc.concurrent_sharing,
+                        #[cfg(feature = "gpu_ray_tracing")]
+                        GraphPreparedResourceRef::RayTracingAccelStruct(_) => false,
+                    }
+                };
+
+                is_shared &&
+                barrier::is_compute_queue_access(&pass_ref.access.access_type) &&
+                barrier::is_compute_queue_access(&registered_res.get_current_access())
+            })
+    }
+
+    fn transition_to_first_access(
+        &self,
+        cb: &CommandBuffer,
+        passes: &mut [Pass],
+    ) {
+        let mut transition_resources = Vec::new();
+
+        // disabled passes are transitioned when clearing their outputs
+        for pass in passes.iter_mut().filter(|pass| pass.enabled) {
+            for pass_ref in pass.inputs.iter_mut().chain(pass.outputs.iter_mut()) {
+                let registered_res = self.registered_resources[pass_ref.handle.id as usize].borrow();
+
+                transition_resources.push((registered_res, PassResourceAccessType {
+                    access_type: pass_ref.access.access_type,
+                    // Force to reduce pipeline bubbles
+                    skip_sync_if_same: true,
+                    #[cfg(debug_assertions)]
+                    debug_pass_name: pass.name.clone(),
+                }));
+
+                // skip when encounter this resource again!
+                pass_ref.access.skip_sync_if_same = true;
+            }
         }
 
-        // record commands
-        // leave only the present pass remain.
-        for pass in passes.drain(..first_present_pass) {
-            self.record_pass_commands(&cb, pass);
+        for (transition_resource, access) in transition_resources {
+            self.resource_transition(&cb, transition_resource, access);
         }
 
-        self.passes = passes.into();
+        // TODO: transition all resources in batched have a problem
+        // when you try to transitioned same image twice in the one single barrier.
+        // you will received validation error from vulkan
+
+        // self.resource_transition_batched(&cb, transition_resources);
     }
 
     pub(crate) fn record_present_commands(
@@ -163,6 +305,11 @@ impl<'exec, 'dynamic> ExecutingRenderGraph<'exec, 'dynamic> {
         //glog::debug!("Recording {} pass", pass.name);
         // TODO: add pass performance ticker and debug marker!
 
+        if !pass.enabled {
+            self.record_disabled_pass_commands(cb, pass);
+            return;
+        }
+
         // transition all the pass resources to dst access
         let transition_resources = pass.inputs.iter().chain(pass.outputs.iter())
             .map(|pass_res| (&self.registered_resources[pass_res.handle.id as usize], pass_res.access.clone()))
@@ -173,6 +320,12 @@ impl<'exec, 'dynamic> ExecutingRenderGraph<'exec, 'dynamic> {
         // }
         self.resource_transition_batched(&cb, transition_resources);
 
+        let frame_constants_layout = &self.execution_params.draw_frame_context_layout;
+        let frame_constants_offset = match pass.camera {
+            Some(camera) => frame_constants_layout.camera_frame_constants_offsets[camera as usize],
+            None => frame_constants_layout.frame_constants_offset,
+        };
+
         let mut context = PassContext {
             cb: cb,
             registry: GraphResourceRegistry {
@@ -181,14 +334,87 @@ impl<'exec, 'dynamic> ExecutingRenderGraph<'exec, 'dynamic> {
                 pipelines: &self.pipelines,
                 registered_resources: &self.registered_resources,
                 global_dynamic_buffer: &mut self.global_dynamic_buffer,
+                frame_constants_offset,
             },
         };
 
+        let device = self.execution_params.device;
+        device.cmd_begin_debug_label(cb.raw, &pass.name);
+
         if let Some(callback) = pass.render_func {
             if let Err(err) = callback(&mut context) {
                 panic!("Error occurs when executing pass {} with {:?}", pass.name, err);
             }
         }
+
+        device.cmd_end_debug_label(cb.raw);
+    }
+
+    /// Clear the outputs created in this render graph, so that the following passes do not read garbage.
+    /// Imported outputs are not touched to keep their previous contents.
+    fn record_disabled_pass_commands(
+        &self,
+        cb: &CommandBuffer,
+        pass: Pass,
+    ) {
+        let device = self.execution_params.device;
+        device.cmd_begin_debug_label(cb.raw, &format!("{} (disabled)", pass.name));
+
+        for output in pass.outputs.iter() {
+            let resource = &self.registered_resources[output.handle.id as usize];
+            let clear_access = PassResourceAccessType {
+                access_type: AccessType::TransferWrite,
+                skip_sync_if_same: false,
+                #[cfg(debug_assertions)]
+                debug_pass_name: pass.name.clone(),
+            };
+
+            match &resource.resource {
+                GraphPreparedResource::CreatedImage(image) => {
+                    self.resource_transition(cb, resource, clear_access);
+
+                    let aspect_mask = aspect_flag_from_image_format(image.desc.format);
+                    let range = vk::ImageSubresourceRange {
+                        aspect_mask,
+                        base_mip_level: 0,
+                        level_count: image.desc.mip_levels as u32,
+                        base_array_layer: 0,
+                        layer_count: image.desc.array_layer_count(),
+                    };
+
+                    unsafe {
+                        if aspect_mask.contains(vk::ImageAspectFlags::COLOR) {
+                            device.raw.cmd_clear_color_image(
+                                cb.raw,
+                                image.raw,
+                                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                                &vk::ClearColorValue { float32: [0.0; 4] },
+                                std::slice::from_ref(&range),
+                            );
+                        } else {
+                            device.raw.cmd_clear_depth_stencil_image(
+                                cb.raw,
+                                image.raw,
+                                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                                // we use inverse depth, zero is infinitely far away
+                                &vk::ClearDepthStencilValue { depth: 0.0, stencil: 0 },
+                                std::slice::from_ref(&range),
+                            );
+                        }
+                    }
+                }
+                GraphPreparedResource::CreatedBuffer(buffer) => {
+                    self.resource_transition(cb, resource, clear_access);
+
+                    unsafe {
+                        device.raw.cmd_fill_buffer(cb.raw, buffer.raw, 0, vk::WHOLE_SIZE, 0);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        device.cmd_end_debug_label(cb.raw);
     }
 
     #[allow(dead_code)]
@@ -392,7 +618,7 @@ impl<'exec, 'dynamic> ExecutingRenderGraph<'exec, 'dynamic> {
 }
 
 // TEMPORARY: it is not the best way to get vk::ImageAspectFlags  
-fn aspect_flag_from_image_format(format: vk::Format) -> vk::ImageAspectFlags {
+pub(crate) fn aspect_flag_from_image_format(format: vk::Format) -> vk::ImageAspectFlags {
     match format {
         vk::Format::D16_UNORM           => vk::ImageAspectFlags::DEPTH,
         vk::Format::X8_D24_UNORM_PACK32 => vk::ImageAspectFlags::DEPTH,
@@ -409,4 +635,89 @@ fn aspect_flag_from_image_format(format: vk::Format) -> vk::ImageAspectFlags {
         }
         _ => vk::ImageAspectFlags::COLOR,
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use raven_rhi::backend::ImageDesc;
+
+    use crate::graph::RenderGraph;
+    use super::*;
+
+    fn pass_names(passes: &[Pass]) -> Vec<&str> {
+        passes.iter().map(|pass| pass.name.as_str()).collect()
+    }
+
+    fn image_desc() -> ImageDesc {
+        ImageDesc::new_2d([16, 16], vk::Format::R8G8B8A8_UNORM)
+    }
+
+    #[test]
+    fn schedule_async_compute_on_compute_queue() {
+        let mut rg = RenderGraph::new();
+
+        let mut ao = {
+            let mut pass = rg.add_pass("ssao");
+            let mut ao = pass.create(image_desc());
+            pass.write(&mut ao, AccessType::ComputeShaderWrite);
+            pass.async_compute();
+            ao
+        };
+        let shadow = {
+            let mut pass = rg.add_pass("shadow");
+            let mut shadow = pass.create(image_desc());
+            pass.write(&mut shadow, AccessType::ComputeShaderWrite);
+            shadow
+        };
+        {
+            let mut pass = rg.add_pass("lighting");
+            pass.read(&shadow, AccessType::ComputeShaderReadSampledImageOrUniformTexelBuffer);
+            pass.write(&mut ao, AccessType::ComputeShaderWrite);
+        }
+        {
+            let mut pass = rg.add_pass("tonemap");
+            let mut output = pass.create(image_desc());
+            pass.write(&mut output, AccessType::ComputeShaderWrite);
+        }
+
+        let schedule = schedule_async_compute_passes(std::mem::take(&mut rg.passes), |_| true);
+
+        // the async pass is submitted to the compute queue and signals the semaphore,
+        // the passes touching its resources (and all the passes after them) wait for it in the post compute command buffer.
+        assert!(schedule.has_async_compute());
+        assert_eq!(pass_names(&schedule.async_compute_passes), ["ssao"]);
+        assert_eq!(pass_names(&schedule.graphic_passes), ["shadow"]);
+        assert_eq!(pass_names(&schedule.post_compute_passes), ["lighting", "tonemap"]);
+    }
+
+    #[test]
+    fn schedule_async_compute_fallback_to_graphic_queue() {
+        let mut rg = RenderGraph::new();
+
+        let mut depth = {
+            let mut pass = rg.add_pass("depth");
+            let mut depth = pass.create(image_desc());
+            pass.write(&mut depth, AccessType::ComputeShaderWrite);
+            depth
+        };
+        // depends on an earlier graphic pass
+        {
+            let mut pass = rg.add_pass("hi-z");
+            pass.write(&mut depth, AccessType::ComputeShaderWrite);
+            pass.async_compute();
+        }
+        // rejected by the resource check (e.g. not shared with the compute queue)
+        {
+            let mut pass = rg.add_pass("ssao");
+            let mut ao = pass.create(image_desc());
+            pass.write(&mut ao, AccessType::ComputeShaderWrite);
+            pass.async_compute();
+        }
+
+        let schedule = schedule_async_compute_passes(std::mem::take(&mut rg.passes), |pass| pass.name != "ssao");
+
+        // nothing to wait for
+        assert!(!schedule.has_async_compute());
+        assert_eq!(pass_names(&schedule.graphic_passes), ["depth", "hi-z", "ssao"]);
+        assert!(schedule.post_compute_passes.is_empty());
+    }
+}
@@ -8,6 +8,7 @@ use raven_rhi::backend::{ImageDesc, Image, Buffer, barrier, ImageType, AccessTyp
 #[cfg(feature = "gpu_ray_tracing")]
 use raven_rhi::backend::RayTracingAccelerationStructure;
 use raven_rhi::pipeline_cache::{PipelineCache};
+use raven_scene::camera::CameraFrameConstants;
 
 use crate::graph_resource::{
     GraphResource, GraphResourceHandle, 
@@ -152,6 +153,11 @@ pub struct RenderGraph {
     pub(crate) compute_pipelines: Vec<RenderGraphComputePipeline>,
     #[cfg(feature = "gpu_ray_tracing")]
     pub(crate) ray_tracing_pipelines: Vec<RenderGraphRayTracingPipeline>,
+
+    /// Cameras used by the passes instead of the main camera in the frame constants.
+    pub(crate) cameras: Vec<CameraFrameConstants>,
+    /// Index of the camera in cameras used by the newly added passes.
+    camera_override: Option<u32>,
 }
 
 impl RenderGraph {
@@ -165,16 +171,33 @@ impl RenderGraph {
             compute_pipelines: Vec::new(),
             #[cfg(feature = "gpu_ray_tracing")]
             ray_tracing_pipelines: Vec::new(),
+
+            cameras: Vec::new(),
+            camera_override: None,
         }
     }
 
+    /// Passes added after this read the camera instead of the main camera from the frame constants (e.g. to render another viewport),
+    /// until it is set back to None.
+    /// 
+    /// The other frame constants are the same, every camera pushes one more copy of the frame constants each frame.
+    pub fn set_camera_override(&mut self, camera: Option<CameraFrameConstants>) {
+        self.camera_override = camera.map(|camera| {
+            self.cameras.push(camera);
+            self.cameras.len() as u32 - 1
+        });
+    }
+
     /// Add a new render pass to the render graph.
     pub fn add_pass<'rg>(&'rg mut self, name: &str) -> PassBuilder<'rg> {
         let curr_pass_idx = self.passes.len();
 
+        let mut pass = Pass::new_empty(curr_pass_idx, name.to_string());
+        pass.camera = self.camera_override;
+
         PassBuilder {
             rg: self,
-            pass: Some(Pass::new_empty(curr_pass_idx, name.to_string())),
+            pass: Some(pass),
         }
     }
 
@@ -257,6 +280,7 @@ impl RenderGraph {
                 tiling: vk::ImageTiling::OPTIMAL,
                 array_elements: 1,
                 mip_levels: 1,
+                concurrent_sharing: false,
             },
             _marker: PhantomData,
         }
@@ -342,8 +366,19 @@ impl RenderGraph {
 
         // iterate over all passes' resources
         for (pass_idx, pass) in self.passes.iter().enumerate() {
-            for pass_resource_handle in pass.inputs.iter().chain(pass.outputs.iter()) {
-                let resource_index = pass_resource_handle.handle.id as usize;
+            // disabled passes only clear their outputs, see PassBuilder::add_if()
+            let pass_accesses: Vec<_> = if pass.enabled {
+                pass.inputs.iter().chain(pass.outputs.iter())
+                    .map(|res| (res.handle, res.access.access_type))
+                    .collect()
+            } else {
+                pass.outputs.iter()
+                    .map(|res| (res.handle, AccessType::TransferWrite))
+                    .collect()
+            };
+
+            for (handle, access_type) in pass_accesses {
+                let resource_index = handle.id as usize;
 
                 let lifetime = &mut lifetimes[resource_index];
                 // update the last access pass
@@ -353,7 +388,7 @@ impl RenderGraph {
                         .unwrap_or(pass_idx),
                 );
 
-                let access_mask = barrier::get_access_info(pass_resource_handle.access.access_type).access_mask;
+                let access_mask = barrier::get_access_info(access_type).access_mask;
 
                 match &self.resources[resource_index] {
                     // image usage flags update
@@ -2,6 +2,7 @@ use std::sync::Arc;
 use std::collections::{hash_map, HashMap};
 use anyhow::Context;
 
+use ash::vk;
 use raven_rhi::{
     backend::{Device, Image, ImageDesc, Buffer, BufferDesc, AccessType},
 };
@@ -11,10 +12,13 @@ use crate::{
     graph_resource::{ExportedResourceHandle, ExportableGraphResource, Handle},
     graph::RenderGraph,
     retired_graph::RetiredRenderGraph,
+    graph_dot::DotResourceAnnotations,
+    executing_graph::aspect_flag_from_image_format,
+    helper::image_clear,
 };
 
 #[derive(Hash, PartialEq, Eq, Debug, Clone)]
-pub struct TemporalResourceKey(String);
+pub struct TemporalResourceKey(pub(crate) String);
 
 impl<'a> From<&'a str> for TemporalResourceKey {
     fn from(s: &'a str) -> Self {
@@ -28,6 +32,16 @@ impl From<String> for TemporalResourceKey {
     }
 }
 
+impl TemporalResourceKey {
+    /// Keys of the two images of a temporal history, (current, previous).
+    pub(crate) fn history_slots(&self) -> (Self, Self) {
+        (
+            TemporalResourceKey(format!("{} [current]", self.0)),
+            TemporalResourceKey(format!("{} [previous]", self.0)),
+        )
+    }
+}
+
 #[derive(Default)]
 pub struct TemporalResourceRegistry(pub(crate) HashMap<TemporalResourceKey, TemporalResourceState>);
 
@@ -77,6 +91,39 @@ impl TemporalResourceRegistry {
                 .collect()
         )
     }
+
+    /// Swap the resources of the two history slots, so that the last frame's current image becomes this frame's previous image.
+    /// 
+    /// Return false if the history is not created yet.
+    pub(crate) fn swap_history_slots(&mut self, current: &TemporalResourceKey, previous: &TemporalResourceKey) -> bool {
+        match (self.0.remove(current), self.0.remove(previous)) {
+            (Some(last_current), Some(last_previous)) => {
+                self.0.insert(current.clone(), last_previous);
+                self.0.insert(previous.clone(), last_current);
+                true
+            }
+            (last_current, last_previous) => {
+                // put them back untouched
+                if let Some(state) = last_current {
+                    self.0.insert(current.clone(), state);
+                }
+                if let Some(state) = last_previous {
+                    self.0.insert(previous.clone(), state);
+                }
+                false
+            }
+        }
+    }
+}
+
+/// Current and previous frame's images of a temporal history, see RenderGraphBuilder::get_or_create_temporal_history().
+pub struct TemporalHistory {
+    /// Image to write this frame's result into, it will be the previous image of the next frame.
+    pub current: Handle<Image>,
+    /// Image written by the last frame, it is cleared to zero when there is no history yet.
+    pub previous: Handle<Image>,
+    /// Whether the history is just created (e.g. the first frame), passes can use it to skip the reprojection.
+    pub is_first_frame: bool,
 }
 
 /// Render Graph Builder.
@@ -117,6 +164,51 @@ impl RenderGraphBuilder {
     pub fn device(&self) -> &Device {
         self.device.as_ref()
     }
+
+    /// Get or create a pair of temporal images used as a history (e.g. TAA or SSAO accumulation).
+    /// 
+    /// The two images are swapped automatically every time this is called, so the image written as `current` this frame
+    /// is returned as `previous` in the next frame. When the history is just created, `previous` is cleared to zero.
+    /// 
+    /// Like get_or_create_temporal(), the images are not recreated when `desc` changes, so put the resolution into the name if it can change.
+    pub fn get_or_create_temporal_history(
+        &mut self,
+        name: impl Into<TemporalResourceKey>,
+        desc: ImageDesc,
+    ) -> anyhow::Result<TemporalHistory> {
+        let (current_key, previous_key) = name.into().history_slots();
+        // the previous image may be cleared
+        let desc = desc.usage_flags(desc.usage | vk::ImageUsageFlags::TRANSFER_DST);
+
+        let is_first_frame = !self.temporal_resources.swap_history_slots(&current_key, &previous_key);
+
+        let current = self.get_or_create_temporal(current_key, desc)?;
+        let mut previous = self.get_or_create_temporal(previous_key, desc)?;
+
+        if is_first_frame {
+            if aspect_flag_from_image_format(desc.format).contains(vk::ImageAspectFlags::COLOR) {
+                image_clear::clear_color(&mut self.render_graph, &mut previous, [0.0, 0.0, 0.0, 0.0]);
+            } else {
+                image_clear::clear_depth_stencil(&mut self.render_graph, &mut previous);
+            }
+        }
+
+        Ok(TemporalHistory {
+            current,
+            previous,
+            is_first_frame,
+        })
+    }
+
+    /// Export the render graph built so far into Graphviz DOT format.
+    /// 
+    /// Passes are nodes and resources accesses are edges.
+    pub fn export_dot(&self) -> String {
+        self.render_graph.export_dot_with_annotations(&DotResourceAnnotations {
+            temporal_names: self.temporal_resources.referenced_resource_names(),
+            ..Default::default()
+        })
+    }
 }
 
 /// Resource that can be got or created by the render graph.
@@ -355,4 +447,77 @@ impl ExportedTemporalResources {
 
         registry
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inert_image() -> (Arc<Image>, TemporalResourceState) {
+        // never touched by the device
+        let image = Arc::new(Image {
+            raw: vk::Image::null(),
+            allocation: None,
+            desc: ImageDesc::new_2d([1, 1], vk::Format::R8G8B8A8_UNORM),
+            views: Default::default(),
+        });
+
+        let state = TemporalResourceState::Inert {
+            resource: TemporalResource::Image(image.clone()),
+            access: AccessType::Nothing,
+        };
+        (image, state)
+    }
+
+    fn slot_image(registry: &TemporalResourceRegistry, key: &TemporalResourceKey) -> Arc<Image> {
+        match &registry.0[key] {
+            TemporalResourceState::Inert { resource: TemporalResource::Image(image), .. } => image.clone(),
+            _ => panic!("Expect an inert image in {:?}", key),
+        }
+    }
+
+    #[test]
+    fn history_slots_are_distinct() {
+        let (current, previous) = TemporalResourceKey::from("taa").history_slots();
+        assert_ne!(current, previous);
+
+        let (other_current, _) = TemporalResourceKey::from("ssao").history_slots();
+        assert_ne!(current, other_current);
+    }
+
+    #[test]
+    fn swap_history_slots_without_history() {
+        let (current, previous) = TemporalResourceKey::from("taa").history_slots();
+        let mut registry = TemporalResourceRegistry::default();
+
+        assert!(!registry.swap_history_slots(&current, &previous));
+        assert!(registry.0.is_empty());
+
+        // a half created history is kept untouched
+        let (image, state) = inert_image();
+        registry.0.insert(current.clone(), state);
+
+        assert!(!registry.swap_history_slots(&current, &previous));
+        assert!(Arc::ptr_eq(&slot_image(&registry, &current), &image));
+        assert!(!registry.0.contains_key(&previous));
+    }
+
+    #[test]
+    fn swap_history_slots_ping_pong() {
+        let (current, previous) = TemporalResourceKey::from("taa").history_slots();
+        let mut registry = TemporalResourceRegistry::default();
+
+        let (image_a, state_a) = inert_image();
+        let (image_b, state_b) = inert_image();
+        registry.0.insert(current.clone(), state_a);
+        registry.0.insert(previous.clone(), state_b);
+
+        // last frame's current image becomes this frame's previous image
+        assert!(registry.swap_history_slots(&current, &previous));
+        assert!(Arc::ptr_eq(&slot_image(&registry, &previous), &image_a));
+        assert!(Arc::ptr_eq(&slot_image(&registry, &current), &image_b));
+
+        assert!(registry.swap_history_slots(&current, &previous));
+        assert!(Arc::ptr_eq(&slot_image(&registry, &previous), &image_b));
+        assert!(Arc::ptr_eq(&slot_image(&registry, &current), &image_a));
+    }
+}
@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use raven_rhi::backend::AccessType;
+
+use crate::{
+    graph::{RenderGraph, ResourceUsage},
+    graph_resource::{GraphResource, GraphResourceDesc, GraphResourceImportedData, ExportedResourceHandle},
+    graph_builder::{TemporalResourceRegistry, TemporalResourceState},
+    compiled_graph::CompiledRenderGraph,
+    transient_resource_cache::TransientResourceCache,
+};
+
+/// Extra informations of the resources which the render graph itself doesn't know.
+#[derive(Default)]
+pub(crate) struct DotResourceAnnotations {
+    /// Temporal resource names, indexed by the resource id.
+    pub(crate) temporal_names: HashMap<u32, String>,
+    /// Whether the created resources will reuse the memory in the transient resource cache, indexed by the resource id.
+    pub(crate) cache_reuses: HashMap<u32, bool>,
+}
+
+impl TemporalResourceRegistry {
+    /// Get all the temporal resources' names which is referenced by the render graph.
+    pub(crate) fn referenced_resource_names(&self) -> HashMap<u32, String> {
+        self.0.iter()
+            .filter_map(|(key, state)| {
+                let id = match state {
+                    TemporalResourceState::Inert { .. } => return None,
+                    TemporalResourceState::Imported { handle, .. } => handle.handle().id,
+                    TemporalResourceState::Exported { handle, .. } => match handle {
+                        ExportedResourceHandle::Image(handle) => handle.handle.id,
+                        ExportedResourceHandle::Buffer(handle) => handle.handle.id,
+                        #[cfg(feature = "gpu_ray_tracing")]
+                        ExportedResourceHandle::RayTracingAccelStruct(handle) => handle.handle.id,
+                    },
+                };
+
+                Some((id, key.0.clone()))
+            })
+            .collect()
+    }
+}
+
+impl CompiledRenderGraph {
+    /// Simulate the resource fetching in prepare_execute() to find out which created resources
+    /// will reuse the memory of the last frames.
+    pub(crate) fn transient_cache_reuses(&self, cache: &TransientResourceCache) -> HashMap<u32, bool> {
+        let mut image_counts = HashMap::new();
+        let mut buffer_counts = HashMap::new();
+
+        self.render_graph.resources.iter()
+            .enumerate()
+            .filter_map(|(idx, resource)| {
+                let reused = match (resource, &self.resource_infos.resource_usages[idx]) {
+                    (GraphResource::Created(created), ResourceUsage::Image(usage)) => {
+                        if let GraphResourceDesc::Image(mut desc) = created.desc {
+                            desc.usage = *usage;
+
+                            let count = image_counts.entry(desc).or_insert_with(|| cache.cached_image_count(&desc));
+                            take_one(count)
+                        } else {
+                            return None;
+                        }
+                    }
+                    (GraphResource::Created(created), ResourceUsage::Buffer(usage)) => {
+                        if let GraphResourceDesc::Buffer(mut desc) = created.desc {
+                            desc.usage = *usage;
+
+                            let count = buffer_counts.entry(desc).or_insert_with(|| cache.cached_buffer_count(&desc));
+                            take_one(count)
+                        } else {
+                            return None;
+                        }
+                    }
+                    _ => return None,
+                };
+
+                Some((idx as u32, reused))
+            })
+            .collect()
+    }
+}
+
+fn take_one(count: &mut usize) -> bool {
+    if *count > 0 {
+        *count -= 1;
+        true
+    } else {
+        false
+    }
+}
+
+/// Graphviz relative functions.
+impl RenderGraph {
+    /// Emit a DOT graph with passes as nodes and resources accesses as edges.
+    pub(crate) fn export_dot_with_annotations(&self, annotations: &DotResourceAnnotations) -> String {
+        let mut dot = String::new();
+
+        writeln!(dot, "digraph render_graph {{").unwrap();
+        writeln!(dot, "    rankdir=LR;").unwrap();
+        writeln!(dot, "    node [fontname=\"Consolas\", fontsize=10];").unwrap();
+        writeln!(dot, "    edge [fontname=\"Consolas\", fontsize=8];").unwrap();
+
+        // passes
+        for (idx, pass) in self.passes.iter().enumerate() {
+            let queue = if pass.async_compute { "\\n[async compute]" } else { "" };
+            let queue = if pass.enabled { queue.to_owned() } else { format!("{}\\n[disabled]", queue) };
+            writeln!(dot, "    pass_{} [shape=box, style=filled, fillcolor=\"#c6dbef\", label=\"#{} {}{}\"];",
+                idx, idx, escape(&pass.name), queue).unwrap();
+        }
+
+        let exported_accesses: HashMap<u32, AccessType> = self.exported_resources.iter()
+            .map(|(res, access)| (res.handle().id, *access))
+            .collect();
+
+        // resources
+        for (idx, resource) in self.resources.iter().enumerate() {
+            let id = idx as u32;
+            let mut label = format!("#{} {}", idx, describe_resource(resource));
+
+            let (lifetime, color) = if let Some(name) = annotations.temporal_names.get(&id) {
+                (format!("temporal \\\"{}\\\"", escape(name)), "#fdd0a2")
+            } else {
+                match resource {
+                    GraphResource::Created(_) => ("transient".to_owned(), "#c7e9c0"),
+                    GraphResource::Imported(_) => ("imported".to_owned(), "#dadaeb"),
+                }
+            };
+            write!(label, "\\n{}", lifetime).unwrap();
+
+            if let Some(reused) = annotations.cache_reuses.get(&id) {
+                label.push_str(if *reused { " (reuses cached memory)" } else { " (newly allocated)" });
+            }
+
+            if let Some(access) = exported_accesses.get(&id) {
+                write!(label, "\\nexported as {:?}", access).unwrap();
+            }
+
+            writeln!(dot, "    res_{} [shape=ellipse, style=filled, fillcolor=\"{}\", label=\"{}\"];", idx, color, label).unwrap();
+        }
+
+        // accesses
+        for (idx, pass) in self.passes.iter().enumerate() {
+            for input in &pass.inputs {
+                writeln!(dot, "    res_{} -> pass_{} [label=\"{:?}\"];", input.handle.id, idx, input.access.access_type).unwrap();
+            }
+
+            for output in &pass.outputs {
+                writeln!(dot, "    pass_{} -> res_{} [label=\"{:?}\", color=\"#cb181d\"];", idx, output.handle.id, output.access.access_type).unwrap();
+            }
+        }
+
+        writeln!(dot, "}}").unwrap();
+        dot
+    }
+}
+
+fn describe_resource(resource: &GraphResource) -> String {
+    match resource {
+        GraphResource::Created(created) => match &created.desc {
+            GraphResourceDesc::Image(desc) => {
+                format!("image {}x{}x{} {:?}", desc.extent[0], desc.extent[1], desc.extent[2], desc.format)
+            }
+            GraphResourceDesc::Buffer(desc) => format!("buffer {} bytes", desc.size),
+            #[cfg(feature = "gpu_ray_tracing")]
+            GraphResourceDesc::RayTracingAccelStruct(_) => "acceleration structure".to_owned(),
+        },
+        GraphResource::Imported(imported) => match imported {
+            GraphResourceImportedData::Image { raw, .. } => {
+                format!("image {}x{}x{} {:?}", raw.desc.extent[0], raw.desc.extent[1], raw.desc.extent[2], raw.desc.format)
+            }
+            GraphResourceImportedData::Buffer { raw, .. } => format!("buffer {} bytes", raw.desc.size),
+            #[cfg(feature = "gpu_ray_tracing")]
+            GraphResourceImportedData::RayTracingAccelStruct { .. } => "acceleration structure".to_owned(),
+            GraphResourceImportedData::SwapchainImage => "swapchain image".to_owned(),
+        },
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
@@ -3,8 +3,10 @@ use std::sync::Arc;
 use ash::vk;
 
 use raven_scene::camera::CameraFrameConstants;
-use raven_rhi::{Rhi, backend::{Device, barrier::{self, ImageBarrier}, Swapchain}, pipeline_cache::PipelineCache, dynamic_buffer::DynamicBuffer, global_constants_descriptor};
+use raven_rhi::{Rhi, backend::{Device, barrier::{self, ImageBarrier}, Swapchain}, pipeline_cache::PipelineCache, dynamic_buffer::{DynamicBuffer, MAX_DYNAMIC_BUFFER_SIZE_BYTES}, global_constants_descriptor};
 
+use crate::executing_graph::AsyncComputeCommandBuffers;
+use crate::graph_dot::DotResourceAnnotations;
 use crate::{compiled_graph::CompiledRenderGraph, transient_resource_cache::TransientResourceCache, graph_builder::TemporalResource};
 use crate::graph_builder::{RenderGraphBuilder, TemporalResourceRegistry, ExportedTemporalResources, TemporalResourceState};
 
@@ -87,7 +89,7 @@ pub struct LightFrameConstants {
     pub color    : [f32; 3], // color in range [0.0, 1.0]
     pub shadowed : u32,      // it is a bool
     pub direction: [f32; 3], // direction vector
-    pub intensity: f32,
+    pub intensity: f32,      // in the scaled luminance of the shaders, see raven-render::light_units
 }
 
 impl Default for LightFrameConstants {
@@ -116,11 +118,20 @@ pub struct FrameConstants {
     pub pad0: u32,
     pub pad1: u32,
     pub pad2: u32,
+
+    /// Quaternion (x, y, z, w) rotating the sky space to the world space.
+    pub sky_rotation: [f32; 4],
+    /// Tint multiplied by the intensity (rgb) of the visible sky, w is unused.
+    pub sky_radiance_scale: [f32; 4],
+    /// Tint multiplied by the intensity (rgb) of the image based lighting, w is unused.
+    pub ibl_radiance_scale: [f32; 4],
 }
 
-#[derive(Copy, Clone)]
+#[derive(Clone)]
 pub struct DrawFrameContextLayout {
     pub frame_constants_offset: u32,
+    /// Offsets of the frame constants of the cameras set by RenderGraph::set_camera_override(), indexed by the camera index.
+    pub camera_frame_constants_offsets: Vec<u32>,
 }
 
 impl GraphExecutor {
@@ -229,11 +240,19 @@ impl GraphExecutor {
         let draw_frame = self.device.begin_frame();
         let device = &self.device;
 
+        let use_async_compute = device.has_async_compute_queue();
+
         // reset and begin recording commands
-        for cb in [
+        let mut command_buffers = vec![
             &draw_frame.main_command_buffer,
-            &draw_frame.present_command_buffer
-        ] {
+            &draw_frame.present_command_buffer,
+        ];
+        if use_async_compute {
+            command_buffers.push(&draw_frame.async_compute_command_buffer);
+            command_buffers.push(&draw_frame.main_post_compute_command_buffer);
+        }
+
+        for cb in command_buffers {
             unsafe {
                 device.raw
                     .reset_command_buffer(cb.raw, vk::CommandBufferResetFlags::default())
@@ -251,13 +270,22 @@ impl GraphExecutor {
         // update frame constant to global dynamic buffer
         let frame_constants_offset = self.global_dynamic_buffer.push(draw_frame_context);
 
+        // passes rendered from other cameras only differ in the camera matrices
+        let camera_frame_constants_offsets = compiled_rg.render_graph.cameras.iter()
+            .map(|camera| {
+                let mut frame_constants = *draw_frame_context;
+                frame_constants.cam_matrices = *camera;
+                self.global_dynamic_buffer.push(&frame_constants)
+            })
+            .collect();
+
         let frame_constants_layout = DrawFrameContextLayout {
             frame_constants_offset,
+            camera_frame_constants_offsets,
         };
 
         let mut executing_rg;
         // record and submit main command buffers
-        // TODO: dispatch some compute commands to async compute queue
         {
             let main_cb = &draw_frame.main_command_buffer;
 
@@ -272,7 +300,35 @@ impl GraphExecutor {
                 &mut self.global_dynamic_buffer,
             );
 
-            executing_rg.record_commands(&main_cb);
+            let async_compute_cbs = use_async_compute.then(|| AsyncComputeCommandBuffers {
+                compute_cb: &draw_frame.async_compute_command_buffer,
+                post_compute_cb: &draw_frame.main_post_compute_command_buffer,
+            });
+
+            let has_async_compute = executing_rg.record_commands(&main_cb, async_compute_cbs);
+
+            if use_async_compute {
+                unsafe {
+                    device.raw.end_command_buffer(draw_frame.async_compute_command_buffer.raw).unwrap();
+                    device.raw.end_command_buffer(draw_frame.main_post_compute_command_buffer.raw).unwrap();
+                }
+            }
+
+            // async compute commands can overlap with the main commands
+            if has_async_compute {
+                let compute_cb = &draw_frame.async_compute_command_buffer;
+
+                let submit_info = [vk::SubmitInfo::builder()
+                    .signal_semaphores(&[draw_frame.async_compute_complete_semaphore])
+                    .command_buffers(&[compute_cb.raw])
+                    .build()];
+
+                unsafe {
+                    device.raw
+                        .queue_submit(device.compute_queue().raw, &submit_info, device.submit_fence(compute_cb))
+                        .expect("Failed to submit async compute commands to compute queue!");
+                }
+            }
 
             unsafe {
                 device.raw.end_command_buffer(main_cb.raw).unwrap();
@@ -284,13 +340,26 @@ impl GraphExecutor {
 
             unsafe {
                 device.raw
-                    .reset_fences(std::slice::from_ref(&main_cb.submit_done_fence))
-                    .expect("Failed to reset command buffer submit fence!");
-
-                device.raw
-                    .queue_submit(device.global_queue.raw, &submit_info, main_cb.submit_done_fence)
+                    .queue_submit(device.global_queue.raw, &submit_info, device.submit_fence(main_cb))
                     .expect("Failed to submit main commands to global queue!");
             }
+
+            // commands depending on async compute passes must wait for the compute queue
+            if has_async_compute {
+                let post_compute_cb = &draw_frame.main_post_compute_command_buffer;
+
+                let submit_info = [vk::SubmitInfo::builder()
+                    .wait_dst_stage_mask(&[vk::PipelineStageFlags::ALL_COMMANDS])
+                    .wait_semaphores(&[draw_frame.async_compute_complete_semaphore])
+                    .command_buffers(&[post_compute_cb.raw])
+                    .build()];
+
+                unsafe {
+                    device.raw
+                        .queue_submit(device.global_queue.raw, &submit_info, device.submit_fence(post_compute_cb))
+                        .expect("Failed to submit post compute commands to global queue!");
+                }
+            }
         }
 
         // after this point, GPU is busying submitting basic commands and executing
@@ -334,22 +403,34 @@ impl GraphExecutor {
                 device.raw.end_command_buffer(present_cb.raw).unwrap();
             }
 
-            let submit_info = [vk::SubmitInfo::builder()
+            // this is the last submission of this frame, signal the frame timeline here.
+            // values of the binary semaphores are ignored.
+            let mut signal_semaphores = vec![swapchain_image.render_finished_semaphore];
+            let mut signal_values = vec![0];
+            if let Some((timeline_semaphore, frame_number)) = device.frame_timeline_signal() {
+                signal_semaphores.push(timeline_semaphore);
+                signal_values.push(frame_number);
+            }
+
+            let mut timeline_submit_info = vk::TimelineSemaphoreSubmitInfo::builder()
+                .signal_semaphore_values(&signal_values);
+
+            let wait_semaphores = [swapchain_image.acquire_semaphore];
+            let command_buffers = [present_cb.raw];
+            let mut submit_info = vk::SubmitInfo::builder()
                 // wait until compute shader finished writing
                 .wait_dst_stage_mask(&[vk::PipelineStageFlags::COMPUTE_SHADER])
-                .wait_semaphores(&[swapchain_image.acquire_semaphore])
-                .signal_semaphores(&[swapchain_image.render_finished_semaphore])
-                .command_buffers(&[present_cb.raw])
-                .build()];
-            
-            // reset fence and submit
-            unsafe {
-                device.raw
-                    .reset_fences(std::slice::from_ref(&present_cb.submit_done_fence))
-                    .expect("Failed to reset command buffer submit fence!");
+                .wait_semaphores(&wait_semaphores)
+                .signal_semaphores(&signal_semaphores)
+                .command_buffers(&command_buffers);
+            if device.is_timeline_semaphore_enabled() {
+                submit_info = submit_info.push_next(&mut timeline_submit_info);
+            }
+            let submit_info = [submit_info.build()];
 
+            unsafe {
                 device.raw
-                    .queue_submit(device.global_queue.raw, &submit_info, present_cb.submit_done_fence)
+                    .queue_submit(device.global_queue.raw, &submit_info, device.submit_fence(present_cb))
                     .expect("Failed to submit present commands to global queue!");
             }
 
@@ -378,10 +459,38 @@ impl GraphExecutor {
         self.device.end_frame(draw_frame);
     }
 
+    /// Export the prepared render graph into Graphviz DOT format.
+    /// 
+    /// Besides the passes and resources accesses, it also shows the temporal resources
+    /// and which transient resources will reuse the memory from the transient resource cache.
+    /// Returns None if there is no prepared render graph (i.e. prepare() is not called or it is already drawn).
+    pub fn export_dot(&self) -> Option<String> {
+        let compiled_rg = self.compiled_rg.as_ref()?;
+
+        let temporal_names = match &self.temporal_resources {
+            RenderGraphTemporalResources::Inert(resources) => resources.referenced_resource_names(),
+            RenderGraphTemporalResources::Exported(resources) => resources.0.referenced_resource_names(),
+        };
+
+        Some(compiled_rg.render_graph.export_dot_with_annotations(&DotResourceAnnotations {
+            temporal_names,
+            cache_reuses: compiled_rg.transient_cache_reuses(&self.transient_resource_cache),
+        }))
+    }
+
+    /// Peak bytes of the global dynamic buffer used in a single frame, and its capacity per frame.
+    #[inline]
+    pub fn dynamic_buffer_usage(&self) -> (u32, u32) {
+        (self.global_dynamic_buffer.high_water_mark(), MAX_DYNAMIC_BUFFER_SIZE_BYTES as u32)
+    }
+
     /// Explicitly clean up all the resources using inside a render graph.
     pub fn shutdown(self) {
         self.device.wait_idle();
 
+        let (high_water_mark, capacity) = self.dynamic_buffer_usage();
+        glog::debug!("Global dynamic buffer used at most {} of {} bytes in a frame", high_water_mark, capacity);
+
         self.global_dynamic_buffer.clean(&self.device);
         self.transient_resource_cache.clean(&self.device);
         self.temporal_resources.clean(&self.device);
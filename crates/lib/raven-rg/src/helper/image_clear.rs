@@ -28,7 +28,7 @@ pub fn clear_depth_stencil(rg: &mut RenderGraph, image: &mut Handle<Image>) {
                 std::slice::from_ref(&vk::ImageSubresourceRange {
                     aspect_mask: vk::ImageAspectFlags::DEPTH,
                     level_count: 1,
-                    layer_count: 1,
+                    layer_count: image.desc.array_layer_count(),
                     ..Default::default()
                 }),
             );
@@ -58,7 +58,7 @@ pub fn clear_color(rg: &mut RenderGraph, image: &mut Handle<Image>, clear_color:
                 std::slice::from_ref(&vk::ImageSubresourceRange {
                     aspect_mask: vk::ImageAspectFlags::COLOR,
                     level_count: 1,
-                    layer_count: 1,
+                    layer_count: image.desc.array_layer_count(),
                     ..Default::default()
                 }),
             );
@@ -3,6 +3,7 @@ mod graph;
 mod compiled_graph;
 mod executing_graph;
 mod retired_graph;
+mod graph_dot;
 
 mod resource;
 mod graph_resource;
@@ -16,7 +17,7 @@ mod transient_resource_cache;
 mod helper;
 
 pub use graph_resource::Handle as RgHandle;
-pub use graph_builder::{RenderGraphBuilder, GetOrCreateTemporal};
+pub use graph_builder::{RenderGraphBuilder, GetOrCreateTemporal, TemporalHistory};
 pub use graph_executor::{GraphExecutor, FrameConstants, LightFrameConstants};
 pub use pass_context::{
     IntoPipelineDescriptorBindings, RenderGraphPassBinding, RenderGraphPassBindable, PassContext,
@@ -55,6 +55,12 @@ pub(crate) struct Pass {
     pub outputs: Vec<PassResourceHandle>,
     /// Render callback function.
     pub render_func: Option<Box<RenderFunc>>,
+    /// Whether this pass should be scheduled on the async compute queue.
+    pub async_compute: bool,
+    /// Disabled passes record no commands, see PassBuilder::add_if().
+    pub enabled: bool,
+    /// Index of the camera used instead of the main camera, see RenderGraph::set_camera_override().
+    pub camera: Option<u32>,
 }
 
 impl Pass {
@@ -66,6 +72,9 @@ impl Pass {
             inputs: Vec::new(),
             outputs: Vec::new(),
             render_func: None,
+            async_compute: false,
+            enabled: true,
+            camera: None,
         }
     }
 }
@@ -148,6 +157,27 @@ impl<'rg> PassBuilder<'rg> {
         self.write_impl(handle, access_type)
     }
 
+    /// Schedule this pass on the async compute queue.
+    /// 
+    /// Only compute commands should be recorded inside this pass.
+    /// Resources used by this pass should be in compute-compatible access state before this pass.
+    /// The resources created by the graph for this pass are shared with the compute queue, the imported ones
+    /// must be created with concurrent sharing (see ImageDesc::concurrent_sharing).
+    /// If the device has no dedicated compute queue, this pass depends on earlier graphic passes,
+    /// or any of its resources is not shared, it will be executed on the graphic queue as usual.
+    pub fn async_compute(&mut self) {
+        self.pass.as_mut().unwrap().async_compute = true;
+    }
+
+    /// Only execute this pass if the condition holds, it can be decided per frame (e.g. skip bloom when the intensity is zero).
+    /// 
+    /// A disabled pass records no commands, and its outputs are not written by it:
+    /// the resources created in this render graph are cleared to zero, the imported ones (e.g. temporal resources) keep their previous contents.
+    /// Passes reading these outputs still see them in the right state.
+    pub fn add_if(&mut self, condition: bool) {
+        self.pass.as_mut().unwrap().enabled = condition;
+    }
+
     /// Add render function to this pass.
     pub fn render(
         mut self,
@@ -9,6 +9,7 @@ use raven_rhi::{
     backend::constants,
     backend::RhiError,
     backend::{renderpass::FrameBufferCacheKey, descriptor},
+    backend::dynamic_rendering::{self, RenderingInfoKHR, RenderingAttachmentInfoKHR},
     backend::{pipeline::CommonPipeline},
     backend::{descriptor::DescriptorSetBinding},
     dynamic_buffer::DynamicBuffer,
@@ -416,6 +417,8 @@ pub struct GraphResourceRegistry<'exec, 'a> {
     pub(crate) pipelines: &'a RenderGraphPipelineHandles,
     pub(crate) registered_resources: &'a Vec<RegisteredResource>,
     pub global_dynamic_buffer: &'a mut DynamicBuffer,
+    /// Frame constants bound to the pipelines of this pass, it may come from another camera.
+    pub(crate) frame_constants_offset: u32,
 }
 
 impl<'exec, 'a> GraphResourceRegistry<'exec, 'a> {
@@ -524,6 +527,10 @@ impl<'exec, 'a> PassContext<'exec, 'a> {
     ) -> anyhow::Result<(), RhiError> {
         let device = self.registry.execution_params.device;
 
+        if device.is_dynamic_rendering_enabled() {
+            return self.begin_dynamic_rendering(render_pass, extent, color_attachments, depth_attachment);
+        }
+
         // get or create the framebuffer from the cache
         let framebuffer = render_pass.frame_buffer_cache.get_or_create(device, FrameBufferCacheKey::new(
             extent, 
@@ -576,12 +583,90 @@ impl<'exec, 'a> PassContext<'exec, 'a> {
         Ok(())
     }
 
+    /// Begin rendering with VK_KHR_dynamic_rendering, the render pass only provides the load and store ops of the attachments.
+    fn begin_dynamic_rendering(
+        &mut self,
+        render_pass: &RenderPass,
+        extent: [u32; 2],
+        color_attachments: &[(GraphResourceRef<Image, Rt>, &ImageViewDesc)],
+        depth_attachment: Option<(GraphResourceRef<Image, Rt>, &ImageViewDesc)>,
+    ) -> anyhow::Result<(), RhiError> {
+        let device = self.registry.execution_params.device;
+        let attachments_desc = &render_pass.frame_buffer_cache.attachments_desc;
+
+        let color_attachment_infos = color_attachments.iter()
+            .zip(attachments_desc.iter())
+            .map(|((refer, view), desc)| {
+                let image_view = self.registry.get_image_view_from_raw_handle(refer.handle, &view)?;
+
+                Ok(RenderingAttachmentInfoKHR::new(
+                    image_view,
+                    vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+                    desc.load_op,
+                    desc.store_op
+                ))
+            })
+            .collect::<anyhow::Result<ArrayVec<RenderingAttachmentInfoKHR, { constants::MAX_RENDERPASS_ATTACHMENTS }>, RhiError>>()?;
+
+        // same layout as the one in the render pass
+        let (depth_attachment_info, has_stencil) = match &depth_attachment {
+            Some((refer, view)) => {
+                let desc = &attachments_desc[render_pass.frame_buffer_cache.color_attachment_count];
+
+                let info = RenderingAttachmentInfoKHR::new(
+                    self.registry.get_image_view_from_raw_handle(refer.handle, &view)?,
+                    vk::ImageLayout::DEPTH_ATTACHMENT_STENCIL_READ_ONLY_OPTIMAL,
+                    desc.load_op,
+                    desc.store_op
+                );
+                (Some(info), dynamic_rendering::has_stencil_aspect(desc.format))
+            }
+            None => (None, false),
+        };
+        // the stencil aspect of the depth image must be provided too, to match the formats in the pipeline
+        let stencil_attachment_info = depth_attachment_info.filter(|_| has_stencil)
+            .map(|mut info| {
+                info.load_op = attachments_desc[render_pass.frame_buffer_cache.color_attachment_count].stencil_load_op;
+                info.store_op = attachments_desc[render_pass.frame_buffer_cache.color_attachment_count].stencil_store_op;
+                info
+            });
+
+        let rendering_info = RenderingInfoKHR::new(
+            vk::Rect2D {
+                extent: vk::Extent2D {
+                    width: extent[0],
+                    height: extent[1],
+                },
+                offset: vk::Offset2D {
+                    x: 0, y: 0,
+                },
+            },
+            &color_attachment_infos,
+            depth_attachment_info.as_ref(),
+            stencil_attachment_info.as_ref()
+        );
+
+        unsafe {
+            device.dynamic_rendering()
+                .unwrap()
+                .cmd_begin_rendering(self.cb.raw, &rendering_info);
+        }
+
+        Ok(())
+    }
+
     #[inline]
     pub fn end_render_pass(
         &mut self,
     ) {
+        let device = self.device();
+
         unsafe {
-            self.device().raw.cmd_end_render_pass(self.cb.raw);
+            if let Some(dynamic_rendering) = device.dynamic_rendering() {
+                dynamic_rendering.cmd_end_rendering(self.cb.raw);
+            } else {
+                device.raw.cmd_end_render_pass(self.cb.raw);
+            }
         }
     }
 
@@ -700,7 +785,7 @@ impl<'exec, 'a> PassContext<'exec, 'a> {
                     &[self.registry.execution_params.global_constants_set], 
                     &[
                         // binding 0
-                        self.registry.execution_params.draw_frame_context_layout.frame_constants_offset
+                        self.registry.frame_constants_offset
                     ]
                 );
             }
@@ -37,6 +37,16 @@ impl TransientResourceCache {
         }
     }
 
+    /// Number of the cached images which can be reused with this description.
+    pub fn cached_image_count(&self, desc: &ImageDesc) -> usize {
+        self.images.get(desc).map_or(0, |vec| vec.len())
+    }
+
+    /// Number of the cached buffers which can be reused with this description.
+    pub fn cached_buffer_count(&self, desc: &BufferDesc) -> usize {
+        self.buffers.get(desc).map_or(0, |vec| vec.len())
+    }
+
     pub fn store_image(&mut self, image: Image) {
         if let Some(vec) = self.images.get_mut(&image.desc) {
             vec.push(image);
@@ -2,4 +2,7 @@ pub(crate) mod vulkan;
 
 pub use vulkan::*;
 
-pub const DEVICE_DRAW_FRAMES: usize = 2;
\ No newline at end of file
+/// Max number of frames the CPU can record ahead of the GPU.
+pub const DEVICE_DRAW_FRAMES: usize = 3;
+/// Number of frames in flight if not configured.
+pub const DEFAULT_FRAMES_IN_FLIGHT: usize = 2;
\ No newline at end of file
@@ -105,6 +105,18 @@ pub fn is_write_only_raster_access(access: &AccessType) -> bool {
     }
 }
 
+/// Whether the access can be synchronized on a compute-only queue, i.e. it doesn't touch any graphic stage.
+#[inline]
+pub fn is_compute_queue_access(access: &AccessType) -> bool {
+    let compute_stages = vk::PipelineStageFlags::TOP_OF_PIPE |
+        vk::PipelineStageFlags::COMPUTE_SHADER |
+        vk::PipelineStageFlags::TRANSFER |
+        vk::PipelineStageFlags::HOST |
+        vk::PipelineStageFlags::BOTTOM_OF_PIPE;
+
+    compute_stages.contains(get_access_info(*access).stage_mask)
+}
+
 // copy from vk_sync
 pub struct AccessInfo {
 	pub stage_mask: ash::vk::PipelineStageFlags,
@@ -426,6 +438,10 @@ pub struct ImageBarrier<'a> {
 	pub aspect_mask: vk::ImageAspectFlags,
 	#[builder(default = "false")]
 	pub discard_contents: bool,
+	#[builder(default = "0")]
+	pub base_mip_level: u32,
+	#[builder(default = "vk::REMAINING_MIP_LEVELS")]
+	pub level_count: u32,
 }
 
 impl<'a> ImageBarrier<'a> {
@@ -440,9 +456,9 @@ pub fn image_barrier(device: &Device, cb: vk::CommandBuffer, barrier: &[ImageBar
 			let subresource_range = vk::ImageSubresourceRange::builder()
 				.aspect_mask(barrier.aspect_mask)
 				.base_array_layer(0)
-				.base_mip_level(0)
+				.base_mip_level(barrier.base_mip_level)
 				.layer_count(vk::REMAINING_ARRAY_LAYERS)
-				.level_count(vk::REMAINING_MIP_LEVELS)
+				.level_count(barrier.level_count)
 				.build();
 
 			vk_sync::ImageBarrier {
@@ -1,10 +1,12 @@
 use std::hash::Hash;
+use std::ops::{RangeBounds, Bound};
 
 use ash::vk::{self};
 
 use crate::copy_engine::CopyDataSource;
 
 use super::allocator::{Allocator, MemoryLocation, AllocationCreateDesc, Allocation, self};
+use super::memory::MemoryCategory;
 use super::{Device, error};
 use super::RhiError;
 
@@ -32,6 +34,10 @@ pub struct BufferDesc {
     pub alignment: Option<usize>,
     pub usage: vk::BufferUsageFlags,
     pub memory_location: MemoryLocation,
+    /// Only used to break down the memory report.
+    pub category: MemoryCategory,
+    /// Share the buffer between the graphic queue and the async compute queue, see ImageDesc::concurrent_sharing.
+    pub concurrent_sharing: bool,
 }
 
 impl BufferDesc {
@@ -41,6 +47,8 @@ impl BufferDesc {
             usage,
             memory_location: MemoryLocation::GpuToCpu,
             alignment: None,
+            category: MemoryCategory::Other,
+            concurrent_sharing: false,
         }
     }
 
@@ -50,6 +58,8 @@ impl BufferDesc {
             usage,
             memory_location: MemoryLocation::GpuOnly,
             alignment: None,
+            category: MemoryCategory::Other,
+            concurrent_sharing: false,
         }
     }
 
@@ -59,6 +69,8 @@ impl BufferDesc {
             usage,
             memory_location: MemoryLocation::CpuToGpu,
             alignment: None,
+            category: MemoryCategory::Other,
+            concurrent_sharing: false,
         }
     }
 
@@ -71,6 +83,16 @@ impl BufferDesc {
         self.usage = usage;
         self
     }
+
+    pub fn category(mut self, category: MemoryCategory) -> Self {
+        self.category = category;
+        self
+    }
+
+    pub fn concurrent_sharing(mut self, concurrent_sharing: bool) -> Self {
+        self.concurrent_sharing = concurrent_sharing;
+        self
+    }
 }
 
 // implement buffer associated function for device
@@ -80,7 +102,11 @@ impl Device {
         desc: BufferDesc,
         name: &str,
     ) -> anyhow::Result<Buffer, RhiError> {
-        let buffer = Self::create_buffer_internal(&self.raw, &mut self.global_allocator.lock(), desc, &name)?;
+        let buffer = self.report_allocation_failure(
+            Self::create_buffer_internal(&self.raw, &mut self.global_allocator.lock(), &self.resource_queue_family_indices, desc, &name)
+        )?;
+        self.set_debug_name(buffer.raw, name);
+        self.track_buffer(&buffer);
 
         Ok(buffer)
     }
@@ -91,14 +117,16 @@ impl Device {
         name: &str,
         data: &impl CopyDataSource
     ) -> anyhow::Result<Buffer, RhiError> {
-        let buffer = Self::create_buffer_internal(
-            &self.raw, &mut self.global_allocator.lock(),
+        let buffer = self.report_allocation_failure(Self::create_buffer_internal(
+            &self.raw, &mut self.global_allocator.lock(), &self.resource_queue_family_indices,
             desc.usage_flags(desc.usage | vk::BufferUsageFlags::TRANSFER_DST), &name
-        )?;
+        ))?;
+        self.set_debug_name(buffer.raw, name);
+        self.track_buffer(&buffer);
 
         if !data.is_empty() {
             let mut staging_buffer = Self::create_buffer_internal(
-                &self.raw, &mut self.global_allocator.lock(),
+                &self.raw, &mut self.global_allocator.lock(), &self.resource_queue_family_indices,
                 BufferDesc::new_cpu_to_gpu(desc.size, vk::BufferUsageFlags::TRANSFER_SRC), "temp staging buffer"
             )?;
     
@@ -126,6 +154,104 @@ impl Device {
         Ok(buffer)
     }
 
+    /// Read back the buffer content in the range (in elements of T, not bytes) to the CPU.
+    /// The buffer must be created with vk::BufferUsageFlags::TRANSFER_SRC.
+    /// 
+    /// This will wait for the device to be idle, so it is only meant to be used in tools and debugging.
+    pub fn read_buffer_to_vec<T: bytemuck::Pod>(
+        &self,
+        buffer: &Buffer,
+        range: impl RangeBounds<usize>,
+    ) -> anyhow::Result<Vec<T>, RhiError> {
+        let t_size = std::mem::size_of::<T>();
+        assert!(t_size > 0, "Can not read back zero sized type!");
+
+        let element_count = buffer.desc.size / t_size;
+        let start = match range.start_bound() {
+            Bound::Included(start) => *start,
+            Bound::Excluded(start) => *start + 1,
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(end) => *end + 1,
+            Bound::Excluded(end) => *end,
+            Bound::Unbounded => element_count,
+        };
+        assert!(start <= end && end <= element_count,
+            "Read back range {}..{} is out of the buffer bound (with {} elements)!", start, end, element_count);
+
+        let count = end - start;
+        if count == 0 {
+            return Ok(Vec::new());
+        }
+
+        let offset_bytes = start * t_size;
+        let size_bytes = count * t_size;
+
+        let staging_buffer = Self::create_buffer_internal(
+            &self.raw, &mut self.global_allocator.lock(), &self.resource_queue_family_indices,
+            BufferDesc::new_gpu_to_cpu(size_bytes, vk::BufferUsageFlags::TRANSFER_DST), "temp readback buffer"
+        )?;
+
+        let copy_result = self.with_setup_commands(|cb| {
+            unsafe {
+                // make sure all the previous writes to this buffer are visible to the copy
+                self.raw.cmd_pipeline_barrier(
+                    cb,
+                    vk::PipelineStageFlags::ALL_COMMANDS,
+                    vk::PipelineStageFlags::TRANSFER,
+                    vk::DependencyFlags::empty(),
+                    &[vk::MemoryBarrier::builder()
+                        .src_access_mask(vk::AccessFlags::MEMORY_WRITE)
+                        .dst_access_mask(vk::AccessFlags::TRANSFER_READ)
+                        .build()],
+                    &[],
+                    &[],
+                );
+
+                self.raw.cmd_copy_buffer(
+                    cb, buffer.raw, staging_buffer.raw,
+                    &[
+                        vk::BufferCopy::builder()
+                            .src_offset(offset_bytes as u64)
+                            .dst_offset(0)
+                            .size(size_bytes as u64)
+                            .build()
+                    ]
+                );
+
+                // make the copied data visible to the host
+                self.raw.cmd_pipeline_barrier(
+                    cb,
+                    vk::PipelineStageFlags::TRANSFER,
+                    vk::PipelineStageFlags::HOST,
+                    vk::DependencyFlags::empty(),
+                    &[vk::MemoryBarrier::builder()
+                        .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+                        .dst_access_mask(vk::AccessFlags::HOST_READ)
+                        .build()],
+                    &[],
+                    &[],
+                );
+            }
+        });
+
+        // the staging buffer is destroyed even if the copy failed
+        let data = copy_result.map(|_| {
+            let mapped = &staging_buffer.allocation.mapped_slice()
+                .expect("Readback buffer is not host visible!")[0..size_bytes];
+
+            // mapped memory may not be aligned to T, copy it byte by byte into the properly aligned vec
+            let mut data = vec![T::zeroed(); count];
+            bytemuck::cast_slice_mut::<T, u8>(&mut data).copy_from_slice(mapped);
+            data
+        });
+
+        self.destroy_buffer(staging_buffer);
+
+        data
+    }
+
     pub fn destroy_buffer(
         &self,
         buffer: Buffer
@@ -133,25 +259,39 @@ impl Device {
         unsafe {
             self.raw.destroy_buffer(buffer.raw, None)
         }
+        self.untrack_allocation(&buffer.allocation);
         self.global_allocator
             .lock()
             .free(buffer.allocation)
             .expect("Failed to free memory of vulkan buffer!");
     }
 
+    fn track_buffer(&self, buffer: &Buffer) {
+        let requirements = unsafe { self.raw.get_buffer_memory_requirements(buffer.raw) };
+        self.track_allocation(&buffer.allocation, requirements.memory_type_bits, buffer.desc.memory_location, buffer.desc.category);
+    }
+
     pub(crate) fn create_buffer_internal(
         device: &ash::Device,
         allocator: &mut Allocator,
+        queue_family_indices: &[u32],
         desc: BufferDesc,
         name: &str // name in here is just for debug purpose
     ) -> anyhow::Result<Buffer, error::RhiError> {
-        let create_info = vk::BufferCreateInfo {
+        let mut create_info = vk::BufferCreateInfo {
             size: desc.size as u64,
             usage: desc.usage,
             sharing_mode: vk::SharingMode::EXCLUSIVE,
             ..Default::default()
         };
 
+        // only the buffers used by the async compute passes pay for the concurrent sharing
+        if desc.concurrent_sharing && queue_family_indices.len() > 1 {
+            create_info.sharing_mode = vk::SharingMode::CONCURRENT;
+            create_info.queue_family_index_count = queue_family_indices.len() as u32;
+            create_info.p_queue_family_indices = queue_family_indices.as_ptr();
+        }
+
         let buffer = unsafe { device.create_buffer(&create_info, None) }
             .expect("Failed to create vulkan buffer!");
         // get memory requirement
@@ -1,9 +1,45 @@
 use std::{ptr, os::raw::c_void, ffi::CStr};
+use std::sync::atomic::{AtomicBool, Ordering};
 
 use ash::{vk};
 
 use super::utility::*;
 
+/// Log target of the messages coming from the validation layers,
+/// so that they can be filtered by the per-module log levels.
+pub const VALIDATION_LOG_TARGET: &str = "vulkan_validation";
+
+/// Known-benign validation messages (by message id name) which are never logged.
+const MUTED_VALIDATION_MESSAGE_IDS: &[&str] = &[
+    // best practices warns about enabling VK_EXT_debug_utils, which is exactly what we want here
+    "UNASSIGNED-BestPractices-vkCreateInstance-specialuse-extension-debugging",
+];
+
+/// Abort with a backtrace when the validation layers report an error.
+static BREAK_ON_VALIDATION_ERROR: AtomicBool = AtomicBool::new(false);
+
+/// Abort the process with a backtrace when the validation layers report an error,
+/// useful for CI to fail on any validation error.
+pub fn set_break_on_validation_error(enable: bool) {
+    BREAK_ON_VALIDATION_ERROR.store(enable, Ordering::Relaxed);
+}
+
+pub fn is_break_on_validation_error() -> bool {
+    BREAK_ON_VALIDATION_ERROR.load(Ordering::Relaxed)
+}
+
+fn message_type_name(message_type: vk::DebugUtilsMessageTypeFlagsEXT) -> &'static str {
+    if message_type.contains(vk::DebugUtilsMessageTypeFlagsEXT::VALIDATION) {
+        "Validation"
+    } else if message_type.contains(vk::DebugUtilsMessageTypeFlagsEXT::PERFORMANCE) {
+        "Performance"
+    } else if message_type.contains(vk::DebugUtilsMessageTypeFlagsEXT::GENERAL) {
+        "General"
+    } else {
+        "Unknown"
+    }
+}
+
 /// Vulkan debug callback function.
 /// use extern "system" here for stdcall abi capability.
 unsafe extern "system" fn vulkan_debug_utils_callback(
@@ -12,20 +48,41 @@ unsafe extern "system" fn vulkan_debug_utils_callback(
     p_callback_data: *const vk::DebugUtilsMessengerCallbackDataEXT,
     _p_user_data: *mut c_void,
 ) -> vk::Bool32 {
-    let types = match message_type {
-        vk::DebugUtilsMessageTypeFlagsEXT::GENERAL => "[General]",
-        vk::DebugUtilsMessageTypeFlagsEXT::PERFORMANCE => "[Performance]",
-        vk::DebugUtilsMessageTypeFlagsEXT::VALIDATION => "[Validation]",
-        _ => "[Unknown]",
+    let callback_data = &*p_callback_data;
+
+    let id_name = if callback_data.p_message_id_name.is_null() {
+        std::borrow::Cow::Borrowed("")
+    } else {
+        CStr::from_ptr(callback_data.p_message_id_name).to_string_lossy()
     };
-    let message = CStr::from_ptr((*p_callback_data).p_message);
+    if MUTED_VALIDATION_MESSAGE_IDS.contains(&id_name.as_ref()) {
+        return vk::FALSE;
+    }
+
+    let message = if callback_data.p_message.is_null() {
+        std::borrow::Cow::Borrowed("")
+    } else {
+        CStr::from_ptr(callback_data.p_message).to_string_lossy()
+    };
+    let types = message_type_name(message_type);
+    // the id number is the hash of the id name, print it in hex as the validation layers do
+    let id_number = callback_data.message_id_number as u32;
 
     match message_severity {
-        vk::DebugUtilsMessageSeverityFlagsEXT::VERBOSE => glog::trace!("{}: {:?}", types, message),
-        vk::DebugUtilsMessageSeverityFlagsEXT::INFO => glog::info!("[{}: {:?}", types, message),
-        vk::DebugUtilsMessageSeverityFlagsEXT::WARNING => glog::warn!("{}: {:?}", types, message),
-        vk::DebugUtilsMessageSeverityFlagsEXT::ERROR => { glog::error!("{}: {:?}", types, message); debug_assert!(false) },
-        _ => unreachable!(),
+        vk::DebugUtilsMessageSeverityFlagsEXT::VERBOSE => glog::trace!(target: VALIDATION_LOG_TARGET, "[{}] {} (0x{:08x}): {}", types, id_name, id_number, message),
+        vk::DebugUtilsMessageSeverityFlagsEXT::INFO => glog::info!(target: VALIDATION_LOG_TARGET, "[{}] {} (0x{:08x}): {}", types, id_name, id_number, message),
+        vk::DebugUtilsMessageSeverityFlagsEXT::WARNING => glog::warn!(target: VALIDATION_LOG_TARGET, "[{}] {} (0x{:08x}): {}", types, id_name, id_number, message),
+        vk::DebugUtilsMessageSeverityFlagsEXT::ERROR => {
+            glog::error!(target: VALIDATION_LOG_TARGET, "[{}] {} (0x{:08x}): {}", types, id_name, id_number, message);
+
+            if is_break_on_validation_error() {
+                // can not unwind across the ffi boundary, so print the backtrace and abort here
+                glog::error!(target: VALIDATION_LOG_TARGET, "Break on validation error, backtrace:\n{}", std::backtrace::Backtrace::force_capture());
+                glog::logger().flush();
+                std::process::abort();
+            }
+        },
+        _ => glog::warn!(target: VALIDATION_LOG_TARGET, "[{}] {} (0x{:08x}): {}", types, id_name, id_number, message),
     };
     vk::FALSE
 }
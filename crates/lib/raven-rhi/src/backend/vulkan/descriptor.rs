@@ -172,6 +172,18 @@ pub fn create_descriptor_set_layout(
                 };
                 suffix = &suffix[1..];
 
+                // comparison samplers have the compare op after the address mode (e.g. sampler_llce_greater)
+                let (suffix, compare_op) = match suffix.split_once('_') {
+                    Some((address, op)) => (address, Some(match op {
+                        "less" => vk::CompareOp::LESS,
+                        "less_equal" => vk::CompareOp::LESS_OR_EQUAL,
+                        "greater" => vk::CompareOp::GREATER,
+                        "greater_equal" => vk::CompareOp::GREATER_OR_EQUAL,
+                        _ => panic!("Unsupported sampler compare op: {}", op),
+                    })),
+                    None => (suffix, None),
+                };
+
                 let address_mode = match &suffix[..] {
                     "ce" => vk::SamplerAddressMode::CLAMP_TO_EDGE,
                     "cb" => vk::SamplerAddressMode::CLAMP_TO_BORDER,
@@ -181,7 +193,7 @@ pub fn create_descriptor_set_layout(
                     _ => panic!("Unsupported sampler address mode: {}", &suffix[..]),
                 };
 
-                let sampler = device.get_immutable_sampler(SamplerDesc { filter, mipmap_mode, address_mode });
+                let sampler = device.get_immutable_sampler(SamplerDesc { filter, mipmap_mode, address_mode, compare_op });
                 bindings.push(
                     vk::DescriptorSetLayoutBinding::builder()
                         .descriptor_count(1)
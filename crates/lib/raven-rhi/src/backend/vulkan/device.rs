@@ -2,29 +2,34 @@ use std::cell::Cell;
 use std::ffi::{CStr, CString};
 use std::sync::Arc;
 use std::os::raw::c_char;
-use std::collections::{HashSet, HashMap};
+use std::collections::{HashSet, HashMap, VecDeque};
 
 use parking_lot::Mutex;
-use ash::{vk, extensions::khr};
+use ash::{vk, vk::Handle, extensions::{khr, ext}};
 
-use crate::backend::{CommandBuffer, DEVICE_DRAW_FRAMES};
+use crate::backend::{CommandBuffer, DEVICE_DRAW_FRAMES, DEFAULT_FRAMES_IN_FLIGHT};
 use crate::backend::vulkan::allocator::{Allocator, AllocatorCreateDesc, AllocatorDebugSettings};
 use crate::backend::vulkan::buffer::BufferDesc;
 use crate::backend::vulkan::{Instance, PhysicalDevice};
 use crate::backend::vulkan::utility;
 use crate::backend::vulkan::constants;
-use crate::draw_frame::{DrawFrame, DeferReleasableResource};
+use crate::draw_frame::DrawFrame;
+use crate::defer_release::{DeferReleasableResource, DeferReleaseQueue};
 
 use super::RhiError;
 use super::physical_device::QueueFamily;
 use super::buffer::Buffer;
+use super::memory::MemoryTracker;
 use super::sampler::SamplerDesc;
+use super::dynamic_rendering::{DynamicRendering, PhysicalDeviceDynamicRenderingFeaturesKHR};
 
 /// Descriptor count to subtract from the max bindless descriptor count,
 /// so that we don't overflow the max when using bindless _and_ non-bindless descriptors
 /// in the same shader stage.
 pub const RESERVED_DESCRIPTOR_COUNT: u32 = 32;
 
+static QUEUE_PRIORITIES: [f32; 1] = [1.0];
+
 pub struct Queue {
     pub raw: vk::Queue,
     pub family: QueueFamily,
@@ -47,21 +52,42 @@ pub struct Device {
     pub(crate) physical_device: Arc<PhysicalDevice>,
     pub(crate) instance: Arc<Instance>,
     pub global_allocator: Mutex<Allocator>,
+    /// Memory usage of the buffers and images, see memory_report().
+    pub(crate) memory_tracker: Mutex<MemoryTracker>,
     pub global_queue: Queue,
+    /// Dedicated compute queue, only exists when the physical device exposes a compute-only queue family.
+    pub async_compute_queue: Option<Queue>,
+    /// Queue families which may access the same resources.
+    /// If it contains more than one family, the resources requesting concurrent sharing are shared by all of them.
+    pub(crate) resource_queue_family_indices: Vec<u32>,
 
     pub(crate) immutable_samplers: HashMap<SamplerDesc, vk::Sampler>,
 
     pub(crate) crash_tracing_buffer: Cell<Option<Buffer>>,
+    /// Used to name vulkan objects and label command buffer regions for GPU captures.
+    debug_utils: Option<ext::DebugUtils>,
     setup_cb: Mutex<CommandBuffer>,
 
     #[cfg(feature = "gpu_ray_tracing")]
     pub ray_tracing_extensions: RayTracingExts,
+    /// Only exists when dynamic rendering is requested and supported by the device.
+    /// Render passes begin with vkCmdBeginRenderingKHR instead of render pass and framebuffer objects.
+    dynamic_rendering: Option<DynamicRendering>,
 
     ray_tracing_enabled: bool,
     current_frame: Cell<u32>,
-    // CPU frames.
-    // Note: In CPU controller side, we only have 2 frames here. But in the swapchain we have 3 images.
-    draw_frames: [Mutex<Arc<DrawFrame>>; DEVICE_DRAW_FRAMES],
+    /// Number of the frame being recorded, starting from 1. It only increases.
+    frame_number: Cell<u64>,
+    /// Signaled with the frame number when all the commands of that frame are done.
+    /// None if timeline semaphores are not supported, then the fences of the command buffers are used.
+    frame_timeline_semaphore: Option<vk::Semaphore>,
+    // CPU frames, one for each frame in flight.
+    // Note: In CPU controller side, we only have frames_in_flight frames here. But in the swapchain we have 3 images.
+    draw_frames: Vec<Mutex<Arc<DrawFrame>>>,
+    /// Last frame number known to be done in the GPU.
+    completed_frame_number: Cell<u64>,
+    /// Resources waiting to be released, tagged with the frame number they are deferred in (in ascending order).
+    deferred_releases: Mutex<VecDeque<(u64, DeferReleaseQueue)>>,
 }
 
 impl Device {
@@ -75,12 +101,59 @@ impl Device {
         }
     }
 
-    pub fn defer_release(&self, resource: impl DeferReleasableResource) {
-        let current_frame = self.current_frame.get() as usize;
-        let draw_frame = self.draw_frames[current_frame].lock();
-        let mut defer_release_resources = draw_frame.defer_release_resources.lock();
-        
-        resource.enqueue(&mut defer_release_resources);
+    /// Release the resource (buffer, image, acceleration structure, descriptor pool) once the GPU has finished
+    /// all the frames submitted so far and the current frame, which may still be using it.
+    /// This avoids waiting for the device to be idle when replacing resources at runtime.
+    pub fn defer_release<T: DeferReleasableResource>(&self, resource: T) {
+        let frame_number = self.frame_number.get();
+        let mut deferred_releases = self.deferred_releases.lock();
+
+        match deferred_releases.back_mut() {
+            Some((number, queue)) if *number == frame_number => resource.enqueue(queue),
+            _ => {
+                let mut queue = DeferReleaseQueue::default();
+                resource.enqueue(&mut queue);
+                deferred_releases.push_back((frame_number, queue));
+            }
+        }
+    }
+
+    /// Release the deferred resources of the frames up to (and including) the frame number.
+    /// The caller must make sure the GPU has finished these frames, see completed_frame_number().
+    pub fn process_deferred_releases(&self, frame_number: u64) {
+        loop {
+            // do not hold the lock while destroying, destroying may defer more resources
+            let queue = {
+                let mut deferred_releases = self.deferred_releases.lock();
+                match deferred_releases.front() {
+                    Some((number, _)) if *number <= frame_number => deferred_releases.pop_front().map(|(_, queue)| queue),
+                    _ => None,
+                }
+            };
+
+            match queue {
+                Some(queue) => queue.release(self),
+                None => break,
+            }
+        }
+    }
+
+    /// Wait for the device to be idle and release all the deferred resources.
+    pub fn flush_deferred_releases(&self) {
+        self.wait_idle();
+        self.completed_frame_number.set(self.frame_number.get().saturating_sub(1));
+        self.process_deferred_releases(u64::MAX);
+    }
+
+    /// Last frame number (by current_frame_number()) known to be done in the GPU, 0 if none.
+    pub fn completed_frame_number(&self) -> u64 {
+        if let Some(timeline_semaphore) = self.frame_timeline_semaphore {
+            let value = unsafe { self.raw.get_semaphore_counter_value(timeline_semaphore) }
+                .expect("Failed to get frame timeline semaphore value!");
+            self.completed_frame_number.set(self.completed_frame_number.get().max(value));
+        }
+
+        self.completed_frame_number.get()
     }
 
     pub fn get_device_frame_index(&self) -> u32 {
@@ -94,21 +167,20 @@ impl Device {
         // make sure user can NOT modify this frame anymore
         match Arc::get_mut(&mut draw_frame) {
             Some(frame) => {
-                // wait for current frame to be submitted in the GPU-side, or we may change the command buffer while GPU is submitting.
-                unsafe {
-                    self.raw
-                        .wait_for_fences(&[
-                            frame.main_command_buffer.submit_done_fence,
-                            frame.present_command_buffer.submit_done_fence
-                        ], true, std::u64::MAX)
-                        .unwrap();
+                // wait for the last frame using this draw frame to be done in the GPU-side, or we may change the command buffer while GPU is executing it.
+                let prev_frame_number = self.frame_number.get().saturating_sub(self.frames_in_flight() as u64);
+                if let Some(timeline_semaphore) = self.frame_timeline_semaphore {
+                    self.wait_timeline_semaphore(timeline_semaphore, prev_frame_number);
+                } else {
+                    self.wait_draw_frame_fences(frame);
                 }
+                self.mark_frame_completed(prev_frame_number);
             },
             None => panic!("User-side is still using DrawFrame data!"),
         };
 
-        // release previous frame's stale resources
-        draw_frame.release_stale_render_resources(self);
+        // release stale resources of the finished frames
+        self.process_deferred_releases(self.completed_frame_number());
         draw_frame.clone()
     }
 
@@ -124,12 +196,124 @@ impl Device {
         // advance to next frame
         current_frame = (current_frame + 1) % self.draw_frames.len();
         self.current_frame.set(current_frame as u32);
+        self.frame_number.set(self.frame_number.get() + 1);
+    }
+
+    /// Number of frames the CPU can record ahead of the GPU.
+    #[inline]
+    pub fn frames_in_flight(&self) -> usize {
+        self.draw_frames.len()
+    }
+
+    /// Number of the frame being recorded, starting from 1.
+    #[inline]
+    pub fn current_frame_number(&self) -> u64 {
+        self.frame_number.get()
+    }
+
+    #[inline]
+    pub fn is_timeline_semaphore_enabled(&self) -> bool {
+        self.frame_timeline_semaphore.is_some()
+    }
+
+    /// The timeline semaphore and the value to signal in the last submission of the current frame.
+    /// Returns None if timeline semaphores are not supported.
+    pub fn frame_timeline_signal(&self) -> Option<(vk::Semaphore, u64)> {
+        self.frame_timeline_semaphore.map(|semaphore| (semaphore, self.frame_number.get()))
+    }
+
+    /// Returns the fence to signal when submitting the command buffer of the current frame.
+    /// The fence is reset here. With timeline semaphores, frames are tracked by the timeline and a null fence is returned.
+    pub fn submit_fence(&self, cb: &CommandBuffer) -> vk::Fence {
+        if self.frame_timeline_semaphore.is_some() {
+            return vk::Fence::null();
+        }
+
+        unsafe {
+            self.raw
+                .reset_fences(std::slice::from_ref(&cb.submit_done_fence))
+                .expect("Failed to reset command buffer submit fence!");
+        }
+        cb.submit_done_fence
+    }
+
+    /// Wait in the CPU until all the commands of the frame (by current_frame_number()) are done in the GPU.
+    /// The frame must be submitted already, i.e. it is before the current frame.
+    pub fn wait_frame_done(&self, frame_number: u64) {
+        let current_frame_number = self.frame_number.get();
+        assert!(frame_number < current_frame_number, "Can not wait for frame {} which is not submitted yet!", frame_number);
+
+        if let Some(timeline_semaphore) = self.frame_timeline_semaphore {
+            self.wait_timeline_semaphore(timeline_semaphore, frame_number);
+        } else if frame_number + self.frames_in_flight() as u64 > current_frame_number {
+            // older frames are already waited when their draw frames are reused
+            let draw_frame_idx = ((frame_number - 1) % self.frames_in_flight() as u64) as usize;
+            self.wait_draw_frame_fences(&self.draw_frames[draw_frame_idx].lock());
+        }
+        self.mark_frame_completed(frame_number);
+    }
+
+    fn mark_frame_completed(&self, frame_number: u64) {
+        self.completed_frame_number.set(self.completed_frame_number.get().max(frame_number));
+    }
+
+    fn wait_timeline_semaphore(&self, semaphore: vk::Semaphore, value: u64) {
+        // the timeline starts from zero, which is always signaled
+        if value == 0 {
+            return;
+        }
+
+        unsafe {
+            self.raw
+                .wait_semaphores(
+                    &vk::SemaphoreWaitInfo::builder()
+                        .semaphores(std::slice::from_ref(&semaphore))
+                        .values(std::slice::from_ref(&value))
+                        .build(),
+                    std::u64::MAX,
+                )
+                .expect("Failed to wait frame timeline semaphore!");
+        }
+    }
+
+    fn wait_draw_frame_fences(&self, frame: &DrawFrame) {
+        unsafe {
+            self.raw
+                .wait_for_fences(&[
+                    frame.main_command_buffer.submit_done_fence,
+                    frame.async_compute_command_buffer.submit_done_fence,
+                    frame.main_post_compute_command_buffer.submit_done_fence,
+                    frame.present_command_buffer.submit_done_fence
+                ], true, std::u64::MAX)
+                .unwrap();
+        }
+    }
+
+    /// Returns true if the device has a dedicated compute queue.
+    pub fn has_async_compute_queue(&self) -> bool {
+        self.async_compute_queue.is_some()
+    }
+
+    /// Returns the queue to submit compute work to.
+    /// Fallback to global queue if there is no dedicated compute queue.
+    pub fn compute_queue(&self) -> &Queue {
+        self.async_compute_queue.as_ref().unwrap_or(&self.global_queue)
     }
 
     pub fn is_ray_tracing_enabled(&self) -> bool {
         self.ray_tracing_enabled
     }
 
+    #[inline]
+    pub fn dynamic_rendering(&self) -> Option<&DynamicRendering> {
+        self.dynamic_rendering.as_ref()
+    }
+
+    #[inline]
+    pub fn is_dynamic_rendering_enabled(&self) -> bool {
+        self.dynamic_rendering.is_some()
+    }
+
     fn check_extensions_supported(required_extensions: &Vec<&'static CStr>, device_extensions: &HashSet<String>) -> bool {
         required_extensions.iter()
             .all(|ext| {
@@ -154,7 +338,7 @@ impl Device {
             })
     }
 
-    fn populate_device_queue_create_info(physical_device: &Arc<PhysicalDevice>) -> (Vec<vk::DeviceQueueCreateInfo>, QueueFamily) {
+    fn populate_device_queue_create_info(physical_device: &Arc<PhysicalDevice>) -> (Vec<vk::DeviceQueueCreateInfo>, QueueFamily, Option<QueueFamily>) {
         // find a graphic queue
         let graphic_queue = physical_device.queue_families
             .iter()
@@ -167,14 +351,33 @@ impl Device {
             panic!("No suitable graphic queue!");
         };
 
-        let priorities = [1.0];
+        // find a dedicated compute queue for async compute
+        let compute_queue = physical_device.queue_families
+            .iter()
+            .filter(|qf| {
+                qf.properties.queue_count > 0 &&
+                qf.properties.queue_flags.contains(vk::QueueFlags::COMPUTE) &&
+                !qf.properties.queue_flags.contains(vk::QueueFlags::GRAPHICS)
+            })
+            .copied()
+            .next();
 
-        (vec![
+        let mut queue_ci = vec![
             vk::DeviceQueueCreateInfo::builder()
             .queue_family_index(graphic_queue.index)
-            .queue_priorities(&priorities)
+            .queue_priorities(&QUEUE_PRIORITIES)
             .build()
-        ], graphic_queue)
+        ];
+
+        if let Some(compute_queue) = &compute_queue {
+            queue_ci.push(vk::DeviceQueueCreateInfo::builder()
+                .queue_family_index(compute_queue.index)
+                .queue_priorities(&QUEUE_PRIORITIES)
+                .build()
+            );
+        }
+
+        (queue_ci, graphic_queue, compute_queue)
     }
 
     fn required_layers() -> Vec<CString> {
@@ -230,6 +433,18 @@ impl Device {
             ray_tracing_enabled = true;
         }
 
+        let dynamic_rendering_supported = builder.enable_dynamic_rendering && {
+            let supported = device_extensions.contains(DynamicRendering::name().to_str().unwrap());
+            if !supported {
+                glog::warn!("Graphic card do not support {:?}, fallback to render pass!", DynamicRendering::name());
+            }
+            supported
+        };
+
+        if dynamic_rendering_supported {
+            required_extensions.push(DynamicRendering::name());
+        }
+
         // this function will panic if any extension is not supported
         Self::check_extensions_supported(&required_extensions, &device_extensions);
 
@@ -237,7 +452,7 @@ impl Device {
             .map(|ext| { ext.as_ptr() as *const c_char })
             .collect();
 
-        let (queue_ci, graphic_queue_family) = Self::populate_device_queue_create_info(&physical_device);
+        let (queue_ci, graphic_queue_family, compute_queue_family) = Self::populate_device_queue_create_info(&physical_device);
 
         // enable validation for device
         let required_layers = Self::required_layers();
@@ -252,6 +467,7 @@ impl Device {
         let mut ray_tracing_pipeline_feature = vk::PhysicalDeviceRayTracingPipelineFeaturesKHR::default();
         let mut accel_struct_feature = vk::PhysicalDeviceAccelerationStructureFeaturesKHR::default();
         let mut ray_query_feature = vk::PhysicalDeviceRayQueryFeaturesKHR::builder().ray_query(true).build();
+        let mut timeline_semaphore_feature = vk::PhysicalDeviceTimelineSemaphoreFeatures::default();
 
         let mut features2 = if ray_tracing_enabled {
             vk::PhysicalDeviceFeatures2::builder()
@@ -261,15 +477,24 @@ impl Device {
                 .push_next(&mut buffer_device_address_feature)
                 .push_next(&mut descriptor_indexing)
                 .push_next(&mut imageless_framebuffer)
+                .push_next(&mut timeline_semaphore_feature)
                 .build()
         } else {
             vk::PhysicalDeviceFeatures2::builder()
                 .push_next(&mut buffer_device_address_feature)
                 .push_next(&mut descriptor_indexing)
                 .push_next(&mut imageless_framebuffer)
+                .push_next(&mut timeline_semaphore_feature)
                 .build()
         };
 
+        // ash do not know this structure, chain it manually
+        let mut dynamic_rendering_feature = PhysicalDeviceDynamicRenderingFeaturesKHR::default();
+        if dynamic_rendering_supported {
+            dynamic_rendering_feature.p_next = features2.p_next;
+            features2.p_next = &mut dynamic_rendering_feature as *mut PhysicalDeviceDynamicRenderingFeaturesKHR as *mut _;
+        }
+
         // query device features
         unsafe {
             physical_device.instance.raw.get_physical_device_features2(physical_device.raw, &mut features2);
@@ -320,22 +545,77 @@ impl Device {
             family: graphic_queue_family,
         };
 
+        let async_compute_queue = compute_queue_family.map(|family| {
+            glog::trace!("Use queue family {} as async compute queue.", family.index);
+
+            Queue {
+                raw: unsafe { device.get_device_queue(family.index, 0) },
+                family,
+            }
+        });
+        // fallback to graphic queue if there is no dedicated compute queue
+        let compute_queue_family = compute_queue_family.unwrap_or(graphic_queue_family);
+
+        let mut resource_queue_family_indices = vec![graphic_queue_family.index];
+        if compute_queue_family.index != graphic_queue_family.index {
+            resource_queue_family_indices.push(compute_queue_family.index);
+        }
+
         // create crash tracking buffer
         let crash_tracing_buffer = Self::create_buffer_internal(
             &device, 
             &mut global_allocator, 
+            &resource_queue_family_indices,
             BufferDesc::new_gpu_to_cpu(4, vk::BufferUsageFlags::TRANSFER_DST),
             "crash_tracking_buffer"
         )?;
 
-        let draw_frames = [
-            Mutex::new(Arc::new(DrawFrame::new(&device, &global_queue.family))),
-            Mutex::new(Arc::new(DrawFrame::new(&device, &global_queue.family)))
-        ];
+        let draw_frames = (0..builder.frames_in_flight)
+            .map(|_| Mutex::new(Arc::new(DrawFrame::new(&device, &global_queue.family, &compute_queue_family))))
+            .collect::<Vec<_>>();
+
+        // timeline semaphores are core in vulkan 1.2
+        let timeline_semaphore_supported = physical_device.properties.api_version >= vk::make_api_version(0, 1, 2, 0) &&
+            timeline_semaphore_feature.timeline_semaphore == vk::TRUE;
+
+        let frame_timeline_semaphore = if timeline_semaphore_supported {
+            let mut semaphore_type_ci = vk::SemaphoreTypeCreateInfo::builder()
+                .semaphore_type(vk::SemaphoreType::TIMELINE)
+                .initial_value(0);
+
+            let semaphore = unsafe { device
+                .create_semaphore(&vk::SemaphoreCreateInfo::builder().push_next(&mut semaphore_type_ci).build(), None)
+                .expect("Failed to create frame timeline semaphore!")
+            };
+            glog::trace!("Use timeline semaphore to track the frames in flight.");
+            Some(semaphore)
+        } else {
+            glog::warn!("Graphic card do not support timeline semaphore, fallback to fences!");
+            None
+        };
 
         let setup_cb = Mutex::new(CommandBuffer::new(&device, &global_queue.family));
+
+        let debug_utils = if builder.enable_debug {
+            Some(ext::DebugUtils::new(&physical_device.instance.entry, &physical_device.instance.raw))
+        } else {
+            None
+        };
         let immutable_samplers = Self::create_immutable_samplers(&device);
 
+        let dynamic_rendering = if dynamic_rendering_feature.dynamic_rendering == vk::TRUE {
+            let dynamic_rendering = DynamicRendering::new(&physical_device.instance.raw, &device);
+            if dynamic_rendering.is_some() {
+                glog::trace!("Dynamic rendering enabled!");
+            }
+            dynamic_rendering
+        } else {
+            if dynamic_rendering_supported {
+                glog::warn!("Dynamic rendering feature is not supported, fallback to render pass!");
+            }
+            None
+        };
+
         #[cfg(feature = "gpu_ray_tracing")]
         let ray_tracing_extensions = {
             let acceleration_structure_ext =
@@ -369,19 +649,28 @@ impl Device {
             physical_device: physical_device.clone(),
             instance: physical_device.instance.clone(),
             global_allocator: Mutex::new(global_allocator),
+            memory_tracker: Mutex::new(MemoryTracker::new(builder.memory_warning_threshold)),
             global_queue,
+            async_compute_queue,
+            resource_queue_family_indices,
 
             immutable_samplers,
 
             crash_tracing_buffer: Cell::new(Some(crash_tracing_buffer)),
+            debug_utils,
             setup_cb,
 
             #[cfg(feature = "gpu_ray_tracing")]
             ray_tracing_extensions,
+            dynamic_rendering,
 
             ray_tracing_enabled,
             current_frame: Cell::new(0),
+            frame_number: Cell::new(1),
+            frame_timeline_semaphore,
             draw_frames,
+            completed_frame_number: Cell::new(0),
+            deferred_releases: Mutex::new(VecDeque::new()),
         })
     }
 
@@ -395,6 +684,49 @@ impl Device {
         )
     }
 
+    /// Name a vulkan object, so that it can be recognized in GPU captures (e.g. RenderDoc).
+    /// Does nothing if debug is not enabled.
+    pub fn set_debug_name<T: Handle>(&self, object: T, name: &str) {
+        if let Some(debug_utils) = &self.debug_utils {
+            let name = CString::new(name).unwrap();
+            let name_info = vk::DebugUtilsObjectNameInfoEXT::builder()
+                .object_type(T::TYPE)
+                .object_handle(object.as_raw())
+                .object_name(&name)
+                .build();
+
+            unsafe {
+                debug_utils
+                    .debug_utils_set_object_name(self.raw.handle(), &name_info)
+                    .expect("Failed to set vulkan object debug name!");
+            }
+        }
+    }
+
+    /// Begin a labeled region of commands, which shows up in GPU captures.
+    /// Does nothing if debug is not enabled.
+    pub fn cmd_begin_debug_label(&self, cb: vk::CommandBuffer, name: &str) {
+        if let Some(debug_utils) = &self.debug_utils {
+            let name = CString::new(name).unwrap();
+            let label = vk::DebugUtilsLabelEXT::builder()
+                .label_name(&name)
+                .build();
+
+            unsafe {
+                debug_utils.cmd_begin_debug_utils_label(cb, &label);
+            }
+        }
+    }
+
+    /// End the labeled region begun by [`Device::cmd_begin_debug_label`].
+    pub fn cmd_end_debug_label(&self, cb: vk::CommandBuffer) {
+        if let Some(debug_utils) = &self.debug_utils {
+            unsafe {
+                debug_utils.cmd_end_debug_utils_label(cb);
+            }
+        }
+    }
+
     pub(crate) fn release_debug_resources(&self) {
         if let Some(crash_tracking_buffer) = self.crash_tracing_buffer.take() {
             self.destroy_buffer(crash_tracking_buffer);
@@ -439,12 +771,20 @@ impl Device {
 
 pub struct DeviceBuilder {
     required_extensions: Vec<&'static CStr>,
+    enable_debug: bool,
+    enable_dynamic_rendering: bool,
+    frames_in_flight: usize,
+    memory_warning_threshold: Option<f32>,
 }
 
 impl Default for DeviceBuilder {
     fn default() -> Self {
         Self {
             required_extensions: Vec::new(),
+            enable_debug: false,
+            enable_dynamic_rendering: false,
+            frames_in_flight: DEFAULT_FRAMES_IN_FLIGHT,
+            memory_warning_threshold: None,
         }
     }
 }
@@ -456,6 +796,35 @@ impl DeviceBuilder {
         self
     }
 
+    /// Enable debug object names and command labels.
+    pub fn enable_debug(mut self, enable: bool) -> Self {
+        self.enable_debug = enable;
+        self
+    }
+
+    /// Use VK_KHR_dynamic_rendering if the device supports it, otherwise fallback to render pass.
+    pub fn enable_dynamic_rendering(mut self, enable: bool) -> Self {
+        self.enable_dynamic_rendering = enable;
+        self
+    }
+
+    /// Number of frames the CPU can record ahead of the GPU, clamped to [1, DEVICE_DRAW_FRAMES].
+    /// More frames in flight hide more CPU stalls, but add input latency.
+    pub fn frames_in_flight(mut self, frames: usize) -> Self {
+        let clamped = frames.clamp(1, DEVICE_DRAW_FRAMES);
+        if clamped != frames {
+            glog::warn!("Frames in flight must be in [1, {}], but got {}, use {} instead.", DEVICE_DRAW_FRAMES, frames, clamped);
+        }
+        self.frames_in_flight = clamped;
+        self
+    }
+
+    /// Warn with a memory report once the used device local memory crosses this fraction (in (0, 1]) of it.
+    pub fn memory_warning_threshold(mut self, threshold: Option<f32>) -> Self {
+        self.memory_warning_threshold = threshold.map(|threshold| threshold.clamp(0.0, 1.0));
+        self
+    }
+
     pub fn build(self, physical_device: &Arc<PhysicalDevice>) -> anyhow::Result<Arc<Device>> {
         Ok(Arc::new(Device::new(self, &physical_device)?))
     }
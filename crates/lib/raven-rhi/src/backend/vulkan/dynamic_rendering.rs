@@ -0,0 +1,190 @@
+use std::ffi::{CStr, c_void};
+use std::ptr;
+
+use ash::vk;
+
+// ash 0.33 is generated from the vulkan headers before VK_KHR_dynamic_rendering (1.2.197),
+// so the structures and commands of the extension are declared here by hand.
+// They must be kept identical to the layout in the vulkan headers.
+
+pub const STRUCTURE_TYPE_RENDERING_INFO_KHR: vk::StructureType = vk::StructureType::from_raw(1000044000);
+pub const STRUCTURE_TYPE_RENDERING_ATTACHMENT_INFO_KHR: vk::StructureType = vk::StructureType::from_raw(1000044001);
+pub const STRUCTURE_TYPE_PIPELINE_RENDERING_CREATE_INFO_KHR: vk::StructureType = vk::StructureType::from_raw(1000044002);
+pub const STRUCTURE_TYPE_PHYSICAL_DEVICE_DYNAMIC_RENDERING_FEATURES_KHR: vk::StructureType = vk::StructureType::from_raw(1000044003);
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct PhysicalDeviceDynamicRenderingFeaturesKHR {
+    pub s_type: vk::StructureType,
+    pub p_next: *mut c_void,
+    pub dynamic_rendering: vk::Bool32,
+}
+
+impl Default for PhysicalDeviceDynamicRenderingFeaturesKHR {
+    fn default() -> Self {
+        Self {
+            s_type: STRUCTURE_TYPE_PHYSICAL_DEVICE_DYNAMIC_RENDERING_FEATURES_KHR,
+            p_next: ptr::null_mut(),
+            dynamic_rendering: vk::FALSE,
+        }
+    }
+}
+
+/// Chained into vk::GraphicsPipelineCreateInfo to describe the attachment formats instead of a render pass.
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct PipelineRenderingCreateInfoKHR {
+    pub s_type: vk::StructureType,
+    pub p_next: *const c_void,
+    pub view_mask: u32,
+    pub color_attachment_count: u32,
+    pub p_color_attachment_formats: *const vk::Format,
+    pub depth_attachment_format: vk::Format,
+    pub stencil_attachment_format: vk::Format,
+}
+
+impl PipelineRenderingCreateInfoKHR {
+    /// The color formats must outlive the returned structure.
+    pub fn new(color_formats: &[vk::Format], depth_format: vk::Format, stencil_format: vk::Format) -> Self {
+        Self {
+            s_type: STRUCTURE_TYPE_PIPELINE_RENDERING_CREATE_INFO_KHR,
+            p_next: ptr::null(),
+            view_mask: 0,
+            color_attachment_count: color_formats.len() as u32,
+            p_color_attachment_formats: color_formats.as_ptr(),
+            depth_attachment_format: depth_format,
+            stencil_attachment_format: stencil_format,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct RenderingAttachmentInfoKHR {
+    pub s_type: vk::StructureType,
+    pub p_next: *const c_void,
+    pub image_view: vk::ImageView,
+    pub image_layout: vk::ImageLayout,
+    pub resolve_mode: vk::ResolveModeFlags,
+    pub resolve_image_view: vk::ImageView,
+    pub resolve_image_layout: vk::ImageLayout,
+    pub load_op: vk::AttachmentLoadOp,
+    pub store_op: vk::AttachmentStoreOp,
+    pub clear_value: vk::ClearValue,
+}
+
+impl RenderingAttachmentInfoKHR {
+    pub fn new(
+        image_view: vk::ImageView,
+        image_layout: vk::ImageLayout,
+        load_op: vk::AttachmentLoadOp,
+        store_op: vk::AttachmentStoreOp,
+    ) -> Self {
+        Self {
+            s_type: STRUCTURE_TYPE_RENDERING_ATTACHMENT_INFO_KHR,
+            p_next: ptr::null(),
+            image_view,
+            image_layout,
+            resolve_mode: vk::ResolveModeFlags::NONE,
+            resolve_image_view: vk::ImageView::null(),
+            resolve_image_layout: vk::ImageLayout::UNDEFINED,
+            load_op,
+            store_op,
+            clear_value: vk::ClearValue::default(),
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct RenderingInfoKHR {
+    pub s_type: vk::StructureType,
+    pub p_next: *const c_void,
+    pub flags: vk::Flags,
+    pub render_area: vk::Rect2D,
+    pub layer_count: u32,
+    pub view_mask: u32,
+    pub color_attachment_count: u32,
+    pub p_color_attachments: *const RenderingAttachmentInfoKHR,
+    pub p_depth_attachment: *const RenderingAttachmentInfoKHR,
+    pub p_stencil_attachment: *const RenderingAttachmentInfoKHR,
+}
+
+impl RenderingInfoKHR {
+    /// The attachments must outlive the returned structure.
+    pub fn new(
+        render_area: vk::Rect2D,
+        color_attachments: &[RenderingAttachmentInfoKHR],
+        depth_attachment: Option<&RenderingAttachmentInfoKHR>,
+        stencil_attachment: Option<&RenderingAttachmentInfoKHR>,
+    ) -> Self {
+        Self {
+            s_type: STRUCTURE_TYPE_RENDERING_INFO_KHR,
+            p_next: ptr::null(),
+            flags: 0,
+            render_area,
+            layer_count: 1,
+            view_mask: 0,
+            color_attachment_count: color_attachments.len() as u32,
+            p_color_attachments: color_attachments.as_ptr(),
+            p_depth_attachment: depth_attachment.map_or(ptr::null(), |attach| attach as *const _),
+            p_stencil_attachment: stencil_attachment.map_or(ptr::null(), |attach| attach as *const _),
+        }
+    }
+}
+
+type PfnCmdBeginRenderingKHR = unsafe extern "system" fn(
+    command_buffer: vk::CommandBuffer,
+    p_rendering_info: *const RenderingInfoKHR,
+);
+type PfnCmdEndRenderingKHR = unsafe extern "system" fn(command_buffer: vk::CommandBuffer);
+
+/// Loaded commands of VK_KHR_dynamic_rendering.
+pub struct DynamicRendering {
+    cmd_begin_rendering_khr: PfnCmdBeginRenderingKHR,
+    cmd_end_rendering_khr: PfnCmdEndRenderingKHR,
+}
+
+impl DynamicRendering {
+    pub fn name() -> &'static CStr {
+        unsafe { CStr::from_bytes_with_nul_unchecked(b"VK_KHR_dynamic_rendering\0") }
+    }
+
+    /// Load the commands from the device, the extension must be enabled on the device.
+    /// Returns None if any of the commands can not be found.
+    pub fn new(instance: &ash::Instance, device: &ash::Device) -> Option<Self> {
+        unsafe {
+            let begin = instance.get_device_proc_addr(
+                device.handle(),
+                b"vkCmdBeginRenderingKHR\0".as_ptr() as *const _
+            )?;
+            let end = instance.get_device_proc_addr(
+                device.handle(),
+                b"vkCmdEndRenderingKHR\0".as_ptr() as *const _
+            )?;
+
+            Some(Self {
+                cmd_begin_rendering_khr: std::mem::transmute(begin),
+                cmd_end_rendering_khr: std::mem::transmute(end),
+            })
+        }
+    }
+
+    pub unsafe fn cmd_begin_rendering(&self, command_buffer: vk::CommandBuffer, rendering_info: &RenderingInfoKHR) {
+        (self.cmd_begin_rendering_khr)(command_buffer, rendering_info);
+    }
+
+    pub unsafe fn cmd_end_rendering(&self, command_buffer: vk::CommandBuffer) {
+        (self.cmd_end_rendering_khr)(command_buffer);
+    }
+}
+
+/// Returns true if the depth format also contains a stencil aspect.
+pub fn has_stencil_aspect(format: vk::Format) -> bool {
+    matches!(format,
+        vk::Format::D16_UNORM_S8_UINT |
+        vk::Format::D24_UNORM_S8_UINT |
+        vk::Format::D32_SFLOAT_S8_UINT |
+        vk::Format::S8_UINT
+    )
+}
@@ -19,6 +19,15 @@ pub enum RhiError {
 
     #[error("Vulkan failed on acquiring next image: {err:?}")]
     AcquiredImageFailed { err: ash::vk::Result },
+
+    #[error("Dynamic buffer overflowed: {required} bytes are required in this frame, but the capacity is {capacity} bytes")]
+    DynamicBufferOverflow { required: usize, capacity: usize },
+
+    #[error("Invalid ray tracing geometry: {reason}")]
+    InvalidRayTracingGeometry { reason: String },
+
+    #[error("Format {format:?} doesn't support linear blit, can't generate the mipmaps")]
+    UnsupportedMipmapGeneration { format: ash::vk::Format },
 }
 
 impl From<ash::vk::Result> for RhiError {
@@ -8,6 +8,7 @@ use vk_sync::AccessType;
 use raven_math;
 
 use super::allocator::{MemoryLocation, AllocationCreateDesc, self, Allocation};
+use super::memory::MemoryCategory;
 use super::{Device, RhiError, BufferDesc, ImageBarrier};
 
 // image type is associated with image view type.
@@ -70,6 +71,16 @@ impl Image {
     }
 
     fn populate_view_create_info(image_desc: &ImageDesc, view_desc: &ImageViewDesc) -> vk::ImageViewCreateInfo {
+        let view_type = view_desc.view_type
+            .unwrap_or_else(|| image_type_to_view_type(image_desc.image_type));
+
+        // non-array views can only see one layer (or one cube)
+        let layer_count = view_desc.layer_count.unwrap_or_else(|| match view_type {
+            vk::ImageViewType::TYPE_1D | vk::ImageViewType::TYPE_2D | vk::ImageViewType::TYPE_3D => 1,
+            vk::ImageViewType::CUBE => 6,
+            _ => image_desc.array_layer_count() - view_desc.base_array_layer,
+        });
+
         vk::ImageViewCreateInfo::builder()
             .format(view_desc.format.unwrap_or(image_desc.format))
             // no swizzle
@@ -79,19 +90,13 @@ impl Image {
                 b: vk::ComponentSwizzle::B,
                 a: vk::ComponentSwizzle::A,
             })
-            .view_type(
-                view_desc.view_type
-                    .unwrap_or_else(|| image_type_to_view_type(image_desc.image_type)),
-            )
+            .view_type(view_type)
             .subresource_range(vk::ImageSubresourceRange {
                 aspect_mask: view_desc.aspect_mask,
                 base_mip_level: view_desc.base_mip_level,
                 level_count: view_desc.level_count.unwrap_or(image_desc.mip_levels as u32 - view_desc.base_mip_level),
-                base_array_layer: 0,
-                layer_count: match image_desc.image_type {
-                    ImageType::Cube | ImageType::CubeArray => 6,
-                    _ => 1,
-                },
+                base_array_layer: view_desc.base_array_layer,
+                layer_count,
             })
             .build()
     }
@@ -110,7 +115,14 @@ impl Device {
         desc: ImageDesc,
         init_datas: Option<Vec<ImageSubResource<'_>>>
     ) -> anyhow::Result<Image, RhiError> {
-        let image_ci = get_image_create_info(&desc, init_datas.is_some());
+        let mut image_ci = get_image_create_info(&desc, init_datas.is_some());
+
+        // only the images used by the async compute passes pay for the concurrent sharing
+        if desc.concurrent_sharing && self.resource_queue_family_indices.len() > 1 {
+            image_ci.sharing_mode = vk::SharingMode::CONCURRENT;
+            image_ci.queue_family_index_count = self.resource_queue_family_indices.len() as u32;
+            image_ci.p_queue_family_indices = self.resource_queue_family_indices.as_ptr();
+        }
 
         let image = unsafe {
             self.raw
@@ -120,7 +132,7 @@ impl Device {
 
         let requirements = unsafe { self.raw.get_image_memory_requirements(image) };
 
-        let allocation = self.global_allocator
+        let allocation = self.report_allocation_failure(self.global_allocator
             .lock()
             .allocate(&AllocationCreateDesc {
                 name: "image",
@@ -131,7 +143,9 @@ impl Device {
             .map_err(|err| RhiError::AllocationFailure {
                 name: "Image".into(),
                 error: err,
-            })?;
+            })
+        )?;
+        self.track_allocation(&allocation, requirements.memory_type_bits, MemoryLocation::GpuOnly, MemoryCategory::from_image_usage(image_ci.usage));
 
         // bind memory
         unsafe {
@@ -256,6 +270,138 @@ impl Device {
         Ok(())
     }
 
+    /// Whether the images of this format can generate the mipmaps by linear blits, see generate_mipmaps().
+    pub fn supports_linear_blit(&self, format: vk::Format) -> bool {
+        let format_props = unsafe {
+            self.physical_device.instance.raw
+                .get_physical_device_format_properties(self.physical_device.raw, format)
+        };
+
+        format_props.optimal_tiling_features.contains(
+            vk::FormatFeatureFlags::BLIT_SRC | vk::FormatFeatureFlags::BLIT_DST | vk::FormatFeatureFlags::SAMPLED_IMAGE_FILTER_LINEAR
+        )
+    }
+
+    /// Generate all the mipmaps from the base level by blitting each level from the previous one.
+    /// The image must be created with TRANSFER_SRC and TRANSFER_DST usages, and be in the access of `access`.
+    /// Return an error if the format doesn't support linear blit (see supports_linear_blit()),
+    /// the caller should create the image with only one mip level in that case.
+    pub fn generate_mipmaps(&self, image: &Image, access: AccessType) -> anyhow::Result<(), RhiError> {
+        let desc = &image.desc;
+        let mip_levels = desc.mip_levels as u32;
+
+        if mip_levels <= 1 {
+            return Ok(());
+        }
+
+        if !self.supports_linear_blit(desc.format) {
+            return Err(RhiError::UnsupportedMipmapGeneration { format: desc.format });
+        }
+
+        let layer_count = desc.array_layer_count();
+        let mip_extent = |level: u32| {
+            [
+                (desc.extent[0] >> level).max(1) as i32,
+                (desc.extent[1] >> level).max(1) as i32,
+                (desc.extent[2] >> level).max(1) as i32,
+            ]
+        };
+
+        self.with_setup_commands(|cb| unsafe {
+            super::barrier::image_barrier(
+                self,
+                cb,
+                &[
+                    ImageBarrier::builder()
+                    .aspect_mask(vk::ImageAspectFlags::COLOR)
+                    .image(image)
+                    .prev_access(&[access])
+                    .next_access(&[AccessType::TransferWrite])
+                    .build().unwrap()
+                ]
+            );
+
+            for level in 1..mip_levels {
+                // the previous level is the blit source
+                super::barrier::image_barrier(
+                    self,
+                    cb,
+                    &[
+                        ImageBarrier::builder()
+                        .aspect_mask(vk::ImageAspectFlags::COLOR)
+                        .image(image)
+                        .prev_access(&[AccessType::TransferWrite])
+                        .next_access(&[AccessType::TransferRead])
+                        .base_mip_level(level - 1)
+                        .level_count(1)
+                        .build().unwrap()
+                    ]
+                );
+
+                let src_extent = mip_extent(level - 1);
+                let dst_extent = mip_extent(level);
+
+                let blit = vk::ImageBlit::builder()
+                    .src_subresource(vk::ImageSubresourceLayers::builder()
+                        .aspect_mask(vk::ImageAspectFlags::COLOR)
+                        .mip_level(level - 1)
+                        .base_array_layer(0)
+                        .layer_count(layer_count)
+                        .build()
+                    )
+                    .src_offsets([
+                        vk::Offset3D { x: 0, y: 0, z: 0 },
+                        vk::Offset3D { x: src_extent[0], y: src_extent[1], z: src_extent[2] },
+                    ])
+                    .dst_subresource(vk::ImageSubresourceLayers::builder()
+                        .aspect_mask(vk::ImageAspectFlags::COLOR)
+                        .mip_level(level)
+                        .base_array_layer(0)
+                        .layer_count(layer_count)
+                        .build()
+                    )
+                    .dst_offsets([
+                        vk::Offset3D { x: 0, y: 0, z: 0 },
+                        vk::Offset3D { x: dst_extent[0], y: dst_extent[1], z: dst_extent[2] },
+                    ])
+                    .build();
+
+                self.raw.cmd_blit_image(
+                    cb,
+                    image.raw,
+                    vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                    image.raw,
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    std::slice::from_ref(&blit),
+                    vk::Filter::LINEAR,
+                );
+            }
+
+            // all levels except the last one are blit sources now
+            super::barrier::image_barrier(
+                self,
+                cb,
+                &[
+                    ImageBarrier::builder()
+                    .aspect_mask(vk::ImageAspectFlags::COLOR)
+                    .image(image)
+                    .prev_access(&[AccessType::TransferRead])
+                    .next_access(&[access])
+                    .level_count(mip_levels - 1)
+                    .build().unwrap(),
+                    ImageBarrier::builder()
+                    .aspect_mask(vk::ImageAspectFlags::COLOR)
+                    .image(image)
+                    .prev_access(&[AccessType::TransferWrite])
+                    .next_access(&[access])
+                    .base_mip_level(mip_levels - 1)
+                    .level_count(1)
+                    .build().unwrap(),
+                ]
+            );
+        })
+    }
+
     pub fn create_image_view(
         &self,
         raw: vk::Image,
@@ -288,6 +434,7 @@ impl Device {
         }
 
         if let Some(alloc) = image.allocation {
+            self.untrack_allocation(&alloc);
             self.global_allocator.lock().free(alloc).expect("Failed to free vulkan image memory!");
         }
 
@@ -309,6 +456,9 @@ pub struct ImageDesc {
     pub tiling: vk::ImageTiling,
     pub array_elements: u32,
     pub mip_levels: u16,
+    /// Share the image between the graphic queue and the async compute queue without the queue ownership transfers.
+    /// Concurrent sharing may be slower to access, so it is only set for the resources used by the async compute passes.
+    pub concurrent_sharing: bool,
 }
 
 impl Default for ImageDesc {
@@ -325,6 +475,7 @@ impl Default for ImageDesc {
             tiling: vk::ImageTiling::OPTIMAL,
             array_elements: 1,
             mip_levels: 1,
+            concurrent_sharing: false,
         }
     }
 }
@@ -374,6 +525,21 @@ impl ImageDesc {
         }.array_elements(6).create_flags(vk::ImageCreateFlags::CUBE_COMPATIBLE)
     }
 
+    /// Array of cubemaps, array_elements is the number of cubes (not faces).
+    pub fn new_cube_array(extent: u32, format: vk::Format, array_elements: u32) -> Self {
+        Self::new_cube(extent, format).array_elements(array_elements).image_type(ImageType::CubeArray)
+    }
+
+    /// Number of the array layers of the image, each face of a cubemap is one layer.
+    pub fn array_layer_count(&self) -> u32 {
+        match self.image_type {
+            ImageType::Tex1d | ImageType::Tex2d | ImageType::Tex3d => 1,
+            ImageType::Tex1dArray | ImageType::Tex2dArray => self.array_elements,
+            ImageType::Cube => 6,
+            ImageType::CubeArray => 6 * self.array_elements,
+        }
+    }
+
     pub fn divide_up_extent(mut self, division: [u32; 3]) -> Self {
         for (extent, div) in self.extent.iter_mut().zip(&division) {
             *extent = ((*extent + div - 1) / div).max(1);
@@ -426,6 +592,11 @@ impl ImageDesc {
         self.mip_levels = level;
         self
     }
+
+    pub fn concurrent_sharing(mut self, concurrent_sharing: bool) -> Self {
+        self.concurrent_sharing = concurrent_sharing;
+        self
+    }
 }
 
 #[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, Builder)]
@@ -443,12 +614,50 @@ pub struct ImageViewDesc {
     pub base_mip_level: u32,
     #[builder(default = "None")]
     pub level_count: Option<u32>,
+    #[builder(default = "0")]
+    pub base_array_layer: u32,
+    /// If this is None, use all the remaining layers for array views, or one layer (one cube) otherwise
+    #[builder(default = "None")]
+    pub layer_count: Option<u32>,
 }
 
 impl ImageViewDesc {
     pub fn builder() -> ImageViewDescBuilder {
         Default::default()
     }
+
+    /// View the 6 layers of a cube compatible image as a cubemap.
+    pub fn new_cube() -> Self {
+        Self {
+            view_type: Some(vk::ImageViewType::CUBE),
+            ..Default::default()
+        }
+    }
+
+    /// View the layers of an array image (or the faces of a cubemap) as a 2d array, e.g. to write cubemaps in compute shaders.
+    pub fn new_2d_array() -> Self {
+        Self {
+            view_type: Some(vk::ImageViewType::TYPE_2D_ARRAY),
+            ..Default::default()
+        }
+    }
+
+    pub fn new_3d() -> Self {
+        Self {
+            view_type: Some(vk::ImageViewType::TYPE_3D),
+            ..Default::default()
+        }
+    }
+
+    /// View a single layer (or a single cubemap face) as a 2d image.
+    pub fn new_2d_layer(layer: u32) -> Self {
+        Self {
+            view_type: Some(vk::ImageViewType::TYPE_2D),
+            base_array_layer: layer,
+            layer_count: Some(1),
+            ..Default::default()
+        }
+    }
 }
 
 impl Default for ImageViewDesc {
@@ -520,7 +729,7 @@ pub fn get_image_create_info(desc: &ImageDesc, with_initial_data: bool) -> vk::I
                 height: desc.extent[1],
                 depth: 1,
             },
-            6 * desc.array_elements,
+            desc.array_layer_count(),
         ),
     };
 
@@ -10,6 +10,8 @@ use super::platform;
 pub struct Instance {
     pub(crate) entry: ash::Entry, // should entry be here? 
     pub raw: ash::Instance,
+    /// VK_EXT_swapchain_colorspace is enabled, the surface may then report HDR color spaces.
+    pub(crate) swapchain_colorspace_enabled: bool,
 }
 
 impl Instance {
@@ -28,12 +30,19 @@ impl Instance {
             panic!("vulkan validation layer not support, but requested!");
         }
 
+        // HDR color spaces are only exposed with this extension, it is optional
+        let swapchain_colorspace_enabled = entry.enumerate_instance_extension_properties()
+            .map(|extensions| extensions.iter()
+                .any(|ext| unsafe { CStr::from_ptr(ext.extension_name.as_ptr()) } == vk::ExtSwapchainColorspaceFn::name()))
+            .unwrap_or(false);
+
         // create vulkan instance
-        let instance = Self::create_instance(&entry, &builder);
+        let instance = Self::create_instance(&entry, &builder, swapchain_colorspace_enabled);
 
         Ok(Self {
             entry,
             raw: instance,
+            swapchain_colorspace_enabled,
         })
     }
 
@@ -50,6 +59,7 @@ impl Instance {
     fn create_instance(
         entry: &ash::Entry, 
         builder: &InstanceBuilder,
+        enable_swapchain_colorspace: bool,
     ) -> ash::Instance {
         let app_info = vk::ApplicationInfo::builder()
             .api_version(vk::make_api_version(0, 1, 3, 0))
@@ -63,6 +73,7 @@ impl Instance {
         let extension_names: Vec<*const i8> = builder.required_extensions.iter()
             .map(|s| s.as_ptr())
             .chain(platform::required_extension_names().into_iter().map(|n| n.as_ptr()))
+            .chain(enable_swapchain_colorspace.then(|| vk::ExtSwapchainColorspaceFn::name().as_ptr()))
             .collect();
         // all required layers
         let layer_names = Self::required_layers(&builder);
@@ -0,0 +1,232 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+
+use ash::vk;
+
+use super::allocator::{Allocation, MemoryLocation};
+use super::{Device, RhiError};
+
+/// What the memory of a resource is used for, only used to break down the memory report.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+pub enum MemoryCategory {
+    /// Vertex, index and mesh description buffers.
+    MeshBuffer,
+    /// Sampled images uploaded from the CPU (e.g. the bindless material textures).
+    Texture,
+    /// Images written by the GPU (color, depth or storage images).
+    RenderTarget,
+    /// Acceleration structures and their build and instance buffers.
+    AccelerationStructure,
+    #[default]
+    Other,
+}
+
+impl MemoryCategory {
+    pub const ALL: [MemoryCategory; 5] = [
+        MemoryCategory::MeshBuffer,
+        MemoryCategory::Texture,
+        MemoryCategory::RenderTarget,
+        MemoryCategory::AccelerationStructure,
+        MemoryCategory::Other,
+    ];
+
+    /// Images are categorized by their usages.
+    pub fn from_image_usage(usage: vk::ImageUsageFlags) -> Self {
+        if usage.intersects(
+            vk::ImageUsageFlags::COLOR_ATTACHMENT |
+            vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT |
+            vk::ImageUsageFlags::STORAGE
+        ) {
+            MemoryCategory::RenderTarget
+        } else {
+            MemoryCategory::Texture
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct MemoryHeapReport {
+    /// Index of the heap in the physical device memory properties.
+    pub index: u32,
+    pub device_local: bool,
+    /// Size of the heap in bytes.
+    pub size: u64,
+    /// Bytes allocated in this heap.
+    pub used: u64,
+}
+
+/// Snapshot of the GPU memory allocated by the device.
+///
+/// Only the sizes of the live buffers and images are counted,
+/// the memory blocks reserved by the allocator may be larger.
+#[derive(Clone, Debug)]
+pub struct MemoryReport {
+    /// Bytes allocated in the device local heaps (VRAM).
+    pub used: u64,
+    /// Size of the device local heaps in bytes.
+    pub total: u64,
+    pub per_heap: Vec<MemoryHeapReport>,
+    /// Bytes allocated for each category, in the order of MemoryCategory::ALL.
+    pub per_category: Vec<(MemoryCategory, u64)>,
+}
+
+const MB: f64 = 1024.0 * 1024.0;
+
+impl Display for MemoryReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "GPU memory: {:.1} MB / {:.1} MB", self.used as f64 / MB, self.total as f64 / MB)?;
+
+        for heap in &self.per_heap {
+            write!(f, "\n    heap {}{}: {:.1} MB / {:.1} MB",
+                heap.index, if heap.device_local { " (device local)" } else { "" },
+                heap.used as f64 / MB, heap.size as f64 / MB)?;
+        }
+        for (category, used) in &self.per_category {
+            write!(f, "\n    {:?}: {:.1} MB", category, *used as f64 / MB)?;
+        }
+        Ok(())
+    }
+}
+
+struct TrackedAllocation {
+    heap_index: usize,
+    category: MemoryCategory,
+    size: u64,
+}
+
+/// Book keeping of the allocated buffers and images, keyed by their memory and offset.
+#[derive(Default)]
+pub(crate) struct MemoryTracker {
+    allocations: HashMap<(vk::DeviceMemory, u64), TrackedAllocation>,
+    heap_used: [u64; vk::MAX_MEMORY_HEAPS],
+    category_used: [u64; MemoryCategory::ALL.len()],
+    /// Warn once the device local usage crosses this fraction of the device local memory.
+    warning_threshold: Option<f32>,
+    over_threshold: bool,
+}
+
+impl MemoryTracker {
+    pub fn new(warning_threshold: Option<f32>) -> Self {
+        Self {
+            warning_threshold,
+            ..Default::default()
+        }
+    }
+}
+
+impl Device {
+    /// Report the memory currently allocated by the buffers and images of this device.
+    pub fn memory_report(&self) -> MemoryReport {
+        let tracker = self.memory_tracker.lock();
+        let memory_properties = &self.physical_device.memory_properties;
+
+        let per_heap: Vec<_> = memory_properties.memory_heaps[..memory_properties.memory_heap_count as usize].iter()
+            .enumerate()
+            .map(|(index, heap)| MemoryHeapReport {
+                index: index as u32,
+                device_local: heap.flags.contains(vk::MemoryHeapFlags::DEVICE_LOCAL),
+                size: heap.size,
+                used: tracker.heap_used[index],
+            })
+            .collect();
+
+        let device_local_heaps = || per_heap.iter().filter(|heap| heap.device_local);
+
+        MemoryReport {
+            used: device_local_heaps().map(|heap| heap.used).sum(),
+            total: device_local_heaps().map(|heap| heap.size).sum(),
+            per_category: MemoryCategory::ALL.iter()
+                .map(|category| (*category, tracker.category_used[category.index()]))
+                .collect(),
+            per_heap,
+        }
+    }
+
+    pub fn log_memory_report(&self) {
+        glog::info!("{}", self.memory_report());
+    }
+
+    /// Log the memory report if the resource failed to allocate its memory.
+    pub(crate) fn report_allocation_failure<T>(&self, result: Result<T, RhiError>) -> Result<T, RhiError> {
+        if let Err(RhiError::AllocationFailure { name, .. }) = &result {
+            glog::error!("Failed to allocate memory for {}!\n{}", name, self.memory_report());
+        }
+        result
+    }
+
+    /// Record a new allocation, `memory_type_bits` is from the memory requirements of the resource.
+    pub(crate) fn track_allocation(&self, allocation: &Allocation, memory_type_bits: u32, location: MemoryLocation, category: MemoryCategory) {
+        let heap_index = self.memory_heap_index(memory_type_bits, location);
+        let size = allocation.size();
+
+        let crossed_threshold = {
+            let mut tracker = self.memory_tracker.lock();
+            tracker.heap_used[heap_index] += size;
+            tracker.category_used[category.index()] += size;
+            tracker.allocations.insert((allocation.memory(), allocation.offset()), TrackedAllocation { heap_index, category, size });
+
+            self.update_threshold(&mut tracker)
+        };
+
+        if crossed_threshold {
+            glog::warn!("GPU memory usage crossed the warning threshold!\n{}", self.memory_report());
+        }
+    }
+
+    /// Forget an allocation before it is freed, allocations not tracked are ignored.
+    pub(crate) fn untrack_allocation(&self, allocation: &Allocation) {
+        let mut tracker = self.memory_tracker.lock();
+
+        if let Some(tracked) = tracker.allocations.remove(&(allocation.memory(), allocation.offset())) {
+            tracker.heap_used[tracked.heap_index] -= tracked.size;
+            tracker.category_used[tracked.category.index()] -= tracked.size;
+            self.update_threshold(&mut tracker);
+        }
+    }
+
+    /// Returns true if the usage just crossed the warning threshold.
+    fn update_threshold(&self, tracker: &mut MemoryTracker) -> bool {
+        let threshold = match tracker.warning_threshold {
+            Some(threshold) => threshold,
+            None => return false,
+        };
+
+        let memory_properties = &self.physical_device.memory_properties;
+        let (used, total) = memory_properties.memory_heaps[..memory_properties.memory_heap_count as usize].iter()
+            .enumerate()
+            .filter(|(_, heap)| heap.flags.contains(vk::MemoryHeapFlags::DEVICE_LOCAL))
+            .fold((0, 0), |(used, total), (index, heap)| (used + tracker.heap_used[index], total + heap.size));
+
+        let over_threshold = used as f64 > total as f64 * threshold as f64;
+        let crossed = over_threshold && !tracker.over_threshold;
+        tracker.over_threshold = over_threshold;
+        crossed
+    }
+
+    /// Find the heap of the memory type the allocator would choose for this location.
+    fn memory_heap_index(&self, memory_type_bits: u32, location: MemoryLocation) -> usize {
+        let memory_properties = &self.physical_device.memory_properties;
+        let memory_types = &memory_properties.memory_types[..memory_properties.memory_type_count as usize];
+
+        let host_visible = vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT;
+        let (preferred, required) = match location {
+            MemoryLocation::GpuOnly => (vk::MemoryPropertyFlags::DEVICE_LOCAL, vk::MemoryPropertyFlags::DEVICE_LOCAL),
+            MemoryLocation::CpuToGpu => (host_visible | vk::MemoryPropertyFlags::DEVICE_LOCAL, host_visible),
+            MemoryLocation::GpuToCpu => (host_visible | vk::MemoryPropertyFlags::HOST_CACHED, host_visible),
+            MemoryLocation::Unknown => (vk::MemoryPropertyFlags::empty(), vk::MemoryPropertyFlags::empty()),
+        };
+
+        let find = |flags: vk::MemoryPropertyFlags| memory_types.iter()
+            .enumerate()
+            .find(|(index, ty)| (memory_type_bits & (1 << index)) != 0 && ty.property_flags.contains(flags))
+            .map(|(_, ty)| ty.heap_index as usize);
+
+        find(preferred)
+            .or_else(|| find(required))
+            .unwrap_or(0)
+    }
+}
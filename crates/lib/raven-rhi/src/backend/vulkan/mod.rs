@@ -11,6 +11,7 @@ mod device;
 mod swapchain;
 
 pub mod allocator;
+mod memory;
 mod buffer;
 mod image;
 mod sampler;
@@ -19,6 +20,7 @@ mod shader;
 pub mod descriptor;
 pub mod pipeline;
 pub mod renderpass;
+pub mod dynamic_rendering;
 
 #[cfg(feature = "gpu_ray_tracing")]
 mod ray_tracing;
@@ -29,17 +31,19 @@ mod error;
 
 pub use instance::Instance;
 pub use surface::Surface;
-pub use physical_device::{PhysicalDevice, QueueFamily};
+pub use physical_device::{PhysicalDevice, QueueFamily, AdapterSelection, AdapterInfo};
 pub use device::Device;
-pub use swapchain::{Swapchain, SwapchainImage};
+pub use swapchain::{Swapchain, SwapchainImage, SwapchainEncoding};
 pub use buffer::{Buffer, BufferDesc};
 pub use image::{Image, ImageDesc, ImageSubResource, ImageType, ImageViewDesc};
 pub use sampler::{SamplerDesc};
+pub use memory::{MemoryCategory, MemoryReport, MemoryHeapReport};
 
 pub use shader::{ShaderSource, ShaderBinary, ShaderBinaryStage, PipelineShaderStage, PipelineShaderDesc};
 pub use pipeline::{
     RasterPipelineDesc, ComputePipelineDesc, RasterPipeline, ComputePipeline,
-    RasterPipelinePrimitiveTopology, RasterPipelineCullMode
+    RasterPipelinePrimitiveTopology, RasterPipelineCullMode,
+    PipelineLayoutCache, PipelineLayoutCacheStats,
 };
 #[cfg(feature = "gpu_ray_tracing")]
 pub use pipeline::{RayTracingPipelineDesc, RayTracingPipeline};
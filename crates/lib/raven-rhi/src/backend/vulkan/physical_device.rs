@@ -53,42 +53,131 @@ pub fn enumerate_physical_devices(instance: &Arc<Instance>) -> Vec<PhysicalDevic
     physical_devices
 }
 
+/// Which physical device (adapter) to use.
+#[derive(Clone, Debug, Default)]
+pub enum AdapterSelection {
+    /// Prefer discrete gpus over integrated ones.
+    #[default]
+    PreferDiscrete,
+    /// Index in the enumerated physical devices, see Rhi::enumerate_devices().
+    Index(usize),
+    /// The first device whose name contains the substring (case insensitive).
+    Name(String),
+}
+
+/// Description of a physical device, e.g. to list the options in a launcher.
+#[derive(Clone, Debug)]
+pub struct AdapterInfo {
+    /// Index in the enumerated physical devices, can be used in AdapterSelection::Index.
+    pub index: usize,
+    pub name: String,
+    pub device_type: vk::PhysicalDeviceType,
+    pub vendor_id: u32,
+    pub device_id: u32,
+    /// Vulkan api version supported by the device (major, minor, patch).
+    pub api_version: (u32, u32, u32),
+    /// Size of the device local memory heaps in bytes.
+    pub device_local_memory_bytes: u64,
+}
+
+impl PhysicalDevice {
+    pub fn name(&self) -> String {
+        unsafe {
+            std::ffi::CStr::from_ptr(self.properties.device_name.as_ptr() as *const std::os::raw::c_char)
+        }.to_string_lossy().into_owned()
+    }
+
+    pub fn adapter_info(&self, index: usize) -> AdapterInfo {
+        let memory_heaps = &self.memory_properties.memory_heaps[..self.memory_properties.memory_heap_count as usize];
+
+        AdapterInfo {
+            index,
+            name: self.name(),
+            device_type: self.properties.device_type,
+            vendor_id: self.properties.vendor_id,
+            device_id: self.properties.device_id,
+            api_version: (
+                vk::api_version_major(self.properties.api_version),
+                vk::api_version_minor(self.properties.api_version),
+                vk::api_version_patch(self.properties.api_version),
+            ),
+            device_local_memory_bytes: memory_heaps.iter()
+                .filter(|heap| heap.flags.contains(vk::MemoryHeapFlags::DEVICE_LOCAL))
+                .map(|heap| heap.size)
+                .sum(),
+        }
+    }
+
+    fn device_type_score(&self) -> u32 {
+        match self.properties.device_type {
+            vk::PhysicalDeviceType::VIRTUAL_GPU => 1,
+            vk::PhysicalDeviceType::INTEGRATED_GPU => 100,
+            vk::PhysicalDeviceType::DISCRETE_GPU => 1000,
+            _ => 0,
+        }
+    }
+}
+
 pub fn pick_suitable_physical_device(
     instance: &Arc<Instance>,
     surface: &Surface,
+    selection: &AdapterSelection,
+    log_devices: bool,
 ) -> PhysicalDevice {
-    // NOT support multiple GPUs for now!
     let physical_devices = enumerate_physical_devices(&instance);
 
+    let is_presentable = |device: &PhysicalDevice| {
+        // check if this physical device supports presentation
+        device.queue_families.iter()
+            .any(|queue| {
+                queue.properties.queue_count > 0 &&
+                queue.properties.queue_flags.contains(vk::QueueFlags::GRAPHICS) &&
+                unsafe { surface.func_loader.get_physical_device_surface_support(device.raw, queue.index, surface.raw).unwrap() }
+            })
+    };
+
+    if log_devices {
+        glog::info!("All physical devices:");
+        for (idx, device) in physical_devices.iter().enumerate() {
+            let info = device.adapter_info(idx);
+            glog::info!("  [{}] {} ({:?}, vulkan {}.{}.{}, {} MB device local memory){}",
+                info.index, info.name, info.device_type,
+                info.api_version.0, info.api_version.1, info.api_version.2,
+                info.device_local_memory_bytes / (1024 * 1024),
+                if is_presentable(device) { "" } else { ", can not present" });
+        }
+    }
+
     let device: Vec<_> = physical_devices.into_iter()
-        .filter(|device| {
-            // check if this physical device supports presentation
-            device.queue_families.iter()
-                .any(|queue| {
-                    queue.properties.queue_count > 0 &&
-                    queue.properties.queue_flags.contains(vk::QueueFlags::GRAPHICS) &&
-                    unsafe { surface.func_loader.get_physical_device_surface_support(device.raw, queue.index, surface.raw).unwrap() }
-                })
-        })
+        .enumerate()
+        .filter(|(_, device)| is_presentable(device))
         .collect();
 
-    glog::trace!("All available physical devices:");
-    glog::trace!("{:#?}", device.iter()
-        .map(|device| {
-            unsafe {
-                std::ffi::CStr::from_ptr(device.properties.device_name.as_ptr() as *const std::os::raw::c_char)
+    let selected = match selection {
+        AdapterSelection::PreferDiscrete => None,
+        AdapterSelection::Index(gpu_index) => {
+            let pos = device.iter().position(|(idx, _)| idx == gpu_index);
+            if pos.is_none() {
+                glog::warn!("Physical device {} is not available or doesn't support presentation, pick the most suitable one instead!", gpu_index);
             }
-        })
-        .collect::<Vec<_>>()
-    );
+            pos
+        }
+        AdapterSelection::Name(name) => {
+            let name = name.to_lowercase();
+            let pos = device.iter().position(|(_, device)| device.name().to_lowercase().contains(&name));
+            if pos.is_none() {
+                glog::warn!("No physical device named like {:?} supports presentation, pick the most suitable one instead!", name);
+            }
+            pos
+        }
+    };
+
+    if let Some(pos) = selected {
+        return device.into_iter().nth(pos).unwrap().1;
+    }
 
     device.into_iter()
-        .max_by_key(|device| {
-            match device.properties.device_type {
-                vk::PhysicalDeviceType::VIRTUAL_GPU => 1,
-                vk::PhysicalDeviceType::INTEGRATED_GPU => 100,
-                vk::PhysicalDeviceType::DISCRETE_GPU => 1000,
-                _ => 0,
-            }
-        }).expect("Failed to find at least one suitable physical device!")
-}
\ No newline at end of file
+        .map(|(_, device)| device)
+        .max_by_key(|device| device.device_type_score())
+        .expect("Failed to find at least one suitable physical device!")
+}
@@ -1,17 +1,192 @@
-use std::{sync::Arc, collections::BTreeMap, ffi::CString, ops::Deref};
+use std::{sync::Arc, collections::{BTreeMap, HashMap}, ffi::CString, ops::Deref};
 
 use ash::vk;
 use raven_container::TempList;
-use rspirv_reflect::PushConstantInfo;
+use rspirv_reflect::{PushConstantInfo, BindingCount};
 use byte_slice_cast::AsSliceOf;
 
 use super::{RenderPass, ShaderSource, Device, ShaderBinaryStage, RhiError, descriptor::{self, PipelineSetLayouts}, PipelineShaderStage, ShaderBinary, constants};
 use super::descriptor::PipelineSetBindings;
+use super::dynamic_rendering::{self, PipelineRenderingCreateInfoKHR};
 #[cfg(feature = "gpu_ray_tracing")]
 use super::Buffer;
 
 pub type PipelineSetLayoutInfo = BTreeMap<u32, vk::DescriptorType>;
 
+/// Identify a pipeline layout by the reflected descriptor set layouts (after the overwrites) and push constant range.
+/// The binding names are part of the key, because they decide immutable samplers and dynamic buffers.
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct PipelineLayoutKey {
+    /// (set index, [(binding index, descriptor type, binding count, name)])
+    sets: Vec<(u32, Vec<(u32, u32, Option<usize>, String)>)>,
+    stage_flags: vk::ShaderStageFlags,
+    /// (offset, size)
+    push_constant: Option<(u32, u32)>,
+}
+
+impl PipelineLayoutKey {
+    fn new(set_layouts: &PipelineSetLayouts, stage_flags: vk::ShaderStageFlags, push_constant: Option<&PushConstantInfo>) -> Self {
+        let sets = set_layouts.iter()
+            .map(|(&set_idx, bindings)| {
+                let bindings = bindings.iter()
+                    .map(|(&binding_idx, info)| {
+                        let binding_count = match info.binding_count {
+                            BindingCount::One => Some(1),
+                            BindingCount::StaticSized(size) => Some(size),
+                            BindingCount::Unbounded => None,
+                        };
+
+                        (binding_idx, info.ty.0, binding_count, info.name.clone())
+                    })
+                    .collect();
+
+                (set_idx, bindings)
+            })
+            .collect();
+
+        Self {
+            sets,
+            stage_flags,
+            push_constant: push_constant.map(|info| (info.offset, info.size)),
+        }
+    }
+}
+
+struct CachedPipelineLayout {
+    pipeline_layout: vk::PipelineLayout,
+    descriptor_set_layouts: Vec<vk::DescriptorSetLayout>,
+    set_layout_infos: Vec<PipelineSetLayoutInfo>,
+    /// Number of alive pipelines using this layout.
+    ref_count: usize,
+}
+
+/// Hit and miss counts of the pipeline layout cache, for diagnostics.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PipelineLayoutCacheStats {
+    pub hits: usize,
+    pub misses: usize,
+    /// Number of pipeline layouts currently alive.
+    pub cached_layouts: usize,
+}
+
+/// Share the descriptor set layouts and pipeline layouts between pipelines with identical bindings.
+/// The layouts are reference counted by the pipelines and destroyed when the last pipeline using it is destroyed.
+#[derive(Default)]
+pub struct PipelineLayoutCache {
+    layouts: HashMap<PipelineLayoutKey, CachedPipelineLayout>,
+    hits: usize,
+    misses: usize,
+}
+
+impl PipelineLayoutCache {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Get a cached pipeline layout or create a new one.
+    /// The caller must release the returned pipeline layout by calling release() when the pipeline is destroyed.
+    fn get_or_create(
+        &mut self,
+        device: &Device,
+        set_layouts: &PipelineSetLayouts,
+        stage_flags: vk::ShaderStageFlags,
+        push_constant: Option<&PushConstantInfo>,
+    ) -> (vk::PipelineLayout, Vec<vk::DescriptorSetLayout>, Vec<PipelineSetLayoutInfo>) {
+        let key = PipelineLayoutKey::new(set_layouts, stage_flags, push_constant);
+
+        if let Some(cached) = self.layouts.get_mut(&key) {
+            self.hits += 1;
+            cached.ref_count += 1;
+
+            return (cached.pipeline_layout, cached.descriptor_set_layouts.clone(), cached.set_layout_infos.clone());
+        }
+        self.misses += 1;
+
+        // TODO: thing of the global descriptors layout of the engine
+        let (descriptor_set_layouts, set_layout_infos) = descriptor::create_descriptor_set_layouts_with_unified_stage(
+            &device,
+            set_layouts,
+            stage_flags
+        ).expect("Failed to create vulkan descriptor set layout!");
+
+        let push_constant_ranges = push_constant.iter()
+            .map(|info| {
+                vk::PushConstantRange::builder()
+                    .size(info.size)
+                    .offset(info.offset)
+                    .stage_flags(stage_flags)
+                    .build()
+            })
+            .collect::<Vec<_>>();
+
+        let pipeline_layout_ci = vk::PipelineLayoutCreateInfo::builder()
+            .set_layouts(&descriptor_set_layouts)
+            .push_constant_ranges(&push_constant_ranges)
+            .build();
+
+        let pipeline_layout = unsafe { device.raw
+            .create_pipeline_layout(&pipeline_layout_ci, None)
+            .expect("Failed to create vulkan pipeline layout!")
+        };
+
+        self.layouts.insert(key, CachedPipelineLayout {
+            pipeline_layout,
+            descriptor_set_layouts: descriptor_set_layouts.clone(),
+            set_layout_infos: set_layout_infos.clone(),
+            ref_count: 1,
+        });
+
+        (pipeline_layout, descriptor_set_layouts, set_layout_infos)
+    }
+
+    /// Release one reference of the pipeline layout, destroy it if no pipeline is using it anymore.
+    pub(crate) fn release(&mut self, device: &Device, pipeline_layout: vk::PipelineLayout) {
+        let key = self.layouts.iter()
+            .find(|(_, cached)| cached.pipeline_layout == pipeline_layout)
+            .map(|(key, _)| key.clone())
+            .expect("Try to release a pipeline layout which is not in the cache!");
+
+        let cached = self.layouts.get_mut(&key).unwrap();
+        cached.ref_count -= 1;
+
+        if cached.ref_count == 0 {
+            let cached = self.layouts.remove(&key).unwrap();
+            destroy_cached_pipeline_layout(device, cached);
+        }
+    }
+
+    pub fn stats(&self) -> PipelineLayoutCacheStats {
+        PipelineLayoutCacheStats {
+            hits: self.hits,
+            misses: self.misses,
+            cached_layouts: self.layouts.len(),
+        }
+    }
+
+    /// Destroy all the cached layouts. All the pipelines must be destroyed before calling this.
+    pub fn clean(self, device: &Device) {
+        for (_, cached) in self.layouts {
+            if cached.ref_count != 0 {
+                glog::warn!("Pipeline layout {:?} is still referenced by {} pipelines when cleaning the cache!", cached.pipeline_layout, cached.ref_count);
+            }
+
+            destroy_cached_pipeline_layout(device, cached);
+        }
+    }
+}
+
+fn destroy_cached_pipeline_layout(device: &Device, cached: CachedPipelineLayout) {
+    unsafe {
+        device.raw
+            .destroy_pipeline_layout(cached.pipeline_layout, None);
+
+        for set_layout in cached.descriptor_set_layouts {
+            device.raw
+                .destroy_descriptor_set_layout(set_layout, None);
+        }
+    }
+}
+
 #[derive(Copy, Clone, Debug)]
 pub struct CommonPipelinePtrs {
     pub pipeline_layout: vk::PipelineLayout,
@@ -49,13 +224,13 @@ impl CommonPipeline {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum RasterPipelinePrimitiveTopology {
     LineList,
     TriangleList,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum RasterPipelineCullMode {
     Back,
     Front,
@@ -80,12 +255,44 @@ pub struct RasterPipelineDesc {
     pub depth_test: bool,
     #[builder(default = "true")]
     pub depth_write: bool,
+    /// Only pass the fragments at exactly the same depth (e.g. after a depth prepass), instead of the reverse-z greater or equal test.
+    #[builder(default = "false")]
+    pub depth_equal: bool,
+    /// Blend the output color with the attachment color using source alpha (i.e. src * a + dst * (1 - a)).
+    #[builder(default = "false")]
+    pub alpha_blend: bool,
 }
 
 impl RasterPipelineDesc {
     pub fn builder() -> RasterPipelineDescBuilder {
         RasterPipelineDescBuilder::default()
     }
+
+    /// Fixed function states which will make two pipelines with the same shaders different.
+    pub(crate) fn fixed_states_key(&self) -> RasterPipelineFixedStates {
+        RasterPipelineFixedStates {
+            render_pass: self.render_pass.raw,
+            cull_mode: self.cull_mode,
+            topology: self.topology,
+            depth_bias: self.depth_bias,
+            depth_test: self.depth_test,
+            depth_write: self.depth_write,
+            depth_equal: self.depth_equal,
+            alpha_blend: self.alpha_blend,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) struct RasterPipelineFixedStates {
+    render_pass: vk::RenderPass,
+    cull_mode: RasterPipelineCullMode,
+    topology: RasterPipelinePrimitiveTopology,
+    depth_bias: bool,
+    depth_test: bool,
+    depth_write: bool,
+    depth_equal: bool,
+    alpha_blend: bool,
 }
 
 #[derive(Debug)]
@@ -202,6 +409,7 @@ impl Deref for RayTracingPipeline {
 
 pub fn create_raster_pipeline(
     device: &Device,
+    layout_cache: &mut PipelineLayoutCache,
     desc: RasterPipelineDesc, 
     shader_binaries: &[ShaderBinaryStage]
 ) -> anyhow::Result<RasterPipeline, RhiError> {
@@ -230,13 +438,6 @@ pub fn create_raster_pipeline(
 
     //glog::debug!("Raster pipeline layout: {:#?}", pipeline_set_layouts);
 
-    // TODO: thing of the global descriptors layout of the engine
-    let (set_layout, set_layout_infos) = descriptor::create_descriptor_set_layouts_with_unified_stage(
-        &device,
-        &pipeline_set_layouts,
-        vk::ShaderStageFlags::ALL_GRAPHICS
-    ).expect("Failed to create vulkan descriptor set layout!");
-
     // merge push constants into a single one (the layout must be the same!)
     let push_constant = push_constants.iter()
         .reduce(|lhs, rhs| {
@@ -257,27 +458,12 @@ pub fn create_raster_pipeline(
         })
         .unwrap();
 
-    let pipeline_layout_ci = if push_constant.0.is_some() {
-        let push_constant_ranges = vk::PushConstantRange::builder()
-            .size(push_constant.0.as_ref().unwrap().size)
-            .offset(push_constant.0.as_ref().unwrap().offset)
-            .stage_flags(vk::ShaderStageFlags::ALL_GRAPHICS)
-            .build();
-
-        vk::PipelineLayoutCreateInfo::builder()
-            .set_layouts(&set_layout)
-            .push_constant_ranges(std::slice::from_ref(&push_constant_ranges))
-            .build()
-    } else {
-        vk::PipelineLayoutCreateInfo::builder()
-            .set_layouts(&set_layout)
-            .build()
-    };
-
-    let pipeline_layout = unsafe { device.raw
-        .create_pipeline_layout(&pipeline_layout_ci, None)
-        .expect("Failed to create vulkan pipeline layout!")
-    };
+    let (pipeline_layout, set_layout, set_layout_infos) = layout_cache.get_or_create(
+        device,
+        &pipeline_set_layouts,
+        vk::ShaderStageFlags::ALL_GRAPHICS,
+        push_constant.0.as_ref()
+    );
 
     // contain the CStr which will be dropped inside the scope, and lead to badly formatted CStr error inside vulkan.
     let temp_name = TempList::new();
@@ -352,13 +538,25 @@ pub fn create_raster_pipeline(
     let depth_stencil_state = vk::PipelineDepthStencilStateCreateInfo::builder()
         .depth_test_enable(desc.depth_test)
         .depth_write_enable(desc.depth_write)
-        .depth_compare_op(vk::CompareOp::GREATER_OR_EQUAL) // Use reverse depth to gain better z-depth precision
+        // Use reverse depth to gain better z-depth precision
+        .depth_compare_op(if desc.depth_equal { vk::CompareOp::EQUAL } else { vk::CompareOp::GREATER_OR_EQUAL })
         .front(noop_stencil_op)
         .back(noop_stencil_op)
         .max_depth_bounds(1.0)
         .build();
 
-    let attachments = vec![
+    let blend_attachment = if desc.alpha_blend {
+        vk::PipelineColorBlendAttachmentState::builder()
+            .blend_enable(true)
+            .src_color_blend_factor(vk::BlendFactor::SRC_ALPHA)
+            .dst_color_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
+            .color_blend_op(vk::BlendOp::ADD)
+            .src_alpha_blend_factor(vk::BlendFactor::ONE)
+            .dst_alpha_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
+            .alpha_blend_op(vk::BlendOp::ADD)
+            .color_write_mask(vk::ColorComponentFlags::all())
+            .build()
+    } else {
         vk::PipelineColorBlendAttachmentState::builder()
             .blend_enable(false)
             .src_color_blend_factor(vk::BlendFactor::SRC_COLOR)
@@ -368,7 +566,11 @@ pub fn create_raster_pipeline(
             .dst_alpha_blend_factor(vk::BlendFactor::ZERO)
             .alpha_blend_op(vk::BlendOp::ADD)
             .color_write_mask(vk::ColorComponentFlags::all())
-            .build();
+            .build()
+    };
+
+    let attachments = vec![
+        blend_attachment;
         desc.render_pass.frame_buffer_cache.color_attachment_count
     ];
 
@@ -386,7 +588,21 @@ pub fn create_raster_pipeline(
             .build()
     };
 
-    let graphic_pipeline_ci = vk::GraphicsPipelineCreateInfo::builder()
+    // with dynamic rendering, the pipeline only needs to know the attachment formats of the render pass
+    let frame_buffer_cache = &desc.render_pass.frame_buffer_cache;
+    let color_formats = frame_buffer_cache.attachments_desc[..frame_buffer_cache.color_attachment_count].iter()
+        .map(|attachment| attachment.format)
+        .collect::<Vec<_>>();
+    let depth_format = frame_buffer_cache.attachments_desc.get(frame_buffer_cache.color_attachment_count)
+        .map_or(vk::Format::UNDEFINED, |attachment| attachment.format);
+    let stencil_format = if dynamic_rendering::has_stencil_aspect(depth_format) {
+        depth_format
+    } else {
+        vk::Format::UNDEFINED
+    };
+    let rendering_ci = PipelineRenderingCreateInfoKHR::new(&color_formats, depth_format, stencil_format);
+
+    let mut graphic_pipeline_ci = vk::GraphicsPipelineCreateInfo::builder()
         .stages(&shader_modules)
         .layout(pipeline_layout)
         .vertex_input_state(&vertex_input_state)
@@ -400,6 +616,11 @@ pub fn create_raster_pipeline(
         .render_pass(desc.render_pass.raw)
         .build();
 
+    if device.is_dynamic_rendering_enabled() {
+        graphic_pipeline_ci.render_pass = vk::RenderPass::null();
+        graphic_pipeline_ci.p_next = &rendering_ci as *const PipelineRenderingCreateInfoKHR as *const _;
+    }
+
     let pipeline = unsafe { device.raw
         // TODO: add pipeline cache
         .create_graphics_pipelines(vk::PipelineCache::null(), &[graphic_pipeline_ci], None)
@@ -439,6 +660,7 @@ pub fn create_raster_pipeline(
 
 pub fn create_compute_pipeline(
     device: &Device,
+    layout_cache: &mut PipelineLayoutCache,
     desc: ComputePipelineDesc,
     shader_binary: &ShaderBinary,
 ) -> anyhow::Result<ComputePipeline, RhiError> {
@@ -461,49 +683,12 @@ pub fn create_compute_pipeline(
         }
     }
 
-    // TODO: thing of the global descriptors layout of the engine
-    let (set_layout, set_layout_infos) = descriptor::create_descriptor_set_layouts_with_unified_stage(
-        &device,
+    let (pipeline_layout, set_layout, set_layout_infos) = layout_cache.get_or_create(
+        device,
         &set_layouts,
-        vk::ShaderStageFlags::COMPUTE
-    ).expect("Failed to create vulkan descriptor set layout!");
-
-    let pipeline_layout_builder = vk::PipelineLayoutCreateInfo::builder()
-        .set_layouts(&set_layout);
-
-    let pipeline_layout = if push_constants.is_some() {
-        // merge push constants into a single one (the layout must be the same!)
-        let push_constant = push_constants.iter()
-            .reduce(|lhs, rhs| {
-                assert_eq!(lhs.size, rhs.size);                
-                assert_eq!(lhs.offset, rhs.offset);
-
-                lhs
-            })
-            .unwrap();
-
-        let push_constant_ranges = vk::PushConstantRange::builder()
-            .size(push_constant.size)
-            .offset(push_constant.offset)
-            .stage_flags(vk::ShaderStageFlags::COMPUTE)
-            .build();
-
-        let pipeline_layout_ci = pipeline_layout_builder
-            .push_constant_ranges(std::slice::from_ref(&push_constant_ranges))
-            .build();
-
-        unsafe { device.raw
-            .create_pipeline_layout(&pipeline_layout_ci, None)
-            .expect("Failed to create vulkan pipeline layout!")
-        }
-    } else {
-        let pipeline_layout_ci = pipeline_layout_builder.build();
-
-        unsafe { device.raw
-            .create_pipeline_layout(&pipeline_layout_ci, None)
-            .expect("Failed to create vulkan pipeline layout!")
-        }
-    };
+        vk::ShaderStageFlags::COMPUTE,
+        push_constants.as_ref()
+    );
 
     let temp_names = TempList::new();
     let shader_module = {
@@ -569,6 +754,7 @@ pub fn create_compute_pipeline(
 #[cfg(feature = "gpu_ray_tracing")]
 pub fn create_ray_tracing_pipeline(
     device: &Device,
+    layout_cache: &mut PipelineLayoutCache,
     desc: RayTracingPipelineDesc,
     shader_binaries: &[ShaderBinaryStage]
 ) -> anyhow::Result<RayTracingPipeline, RhiError>  {
@@ -604,12 +790,6 @@ pub fn create_ray_tracing_pipeline(
         }
     }
 
-    let (set_layout, set_layout_infos) = descriptor::create_descriptor_set_layouts_with_unified_stage(
-        &device,
-        &pipeline_set_layouts,
-        vk::ShaderStageFlags::ALL
-    ).expect("Failed to create vulkan descriptor set layout!");
-
     // merge push constants into a single one (the layout must be the same!)
     let push_constant = push_constants.iter()
         .reduce(|lhs, rhs| {
@@ -630,27 +810,12 @@ pub fn create_ray_tracing_pipeline(
         })
         .unwrap();
 
-    let pipeline_layout_ci = if push_constant.0.is_some() {
-        let push_constant_ranges = vk::PushConstantRange::builder()
-            .size(push_constant.0.as_ref().unwrap().size)
-            .offset(push_constant.0.as_ref().unwrap().offset)
-            .stage_flags(vk::ShaderStageFlags::ALL)
-            .build();
-
-        vk::PipelineLayoutCreateInfo::builder()
-            .set_layouts(&set_layout)
-            .push_constant_ranges(std::slice::from_ref(&push_constant_ranges))
-            .build()
-    } else {
-        vk::PipelineLayoutCreateInfo::builder()
-            .set_layouts(&set_layout)
-            .build()
-    };
-
-    let pipeline_layout = unsafe { device.raw
-        .create_pipeline_layout(&pipeline_layout_ci, None)
-        .expect("Failed to create vulkan pipeline layout!")
-    };
+    let (pipeline_layout, set_layout, set_layout_infos) = layout_cache.get_or_create(
+        device,
+        &pipeline_set_layouts,
+        vk::ShaderStageFlags::ALL,
+        push_constant.0.as_ref()
+    );
 
     let mut raygen_entry_count = 0;
     let mut miss_entry_count = 0;
@@ -830,27 +995,27 @@ pub fn create_ray_tracing_pipeline(
     })
 }
 
-pub fn destroy_raster_pipeline(device: &Device, pipeline: RasterPipeline) {
-    destroy_common_pipeline_ptrs(device, pipeline.pipeline.pipeline_ptrs);
+pub fn destroy_raster_pipeline(device: &Device, layout_cache: &mut PipelineLayoutCache, pipeline: RasterPipeline) {
+    destroy_common_pipeline_ptrs(device, layout_cache, pipeline.pipeline.pipeline_ptrs);
 }
 
-pub fn destroy_compute_pipeline(device: &Device, pipeline: ComputePipeline) {
-    destroy_common_pipeline_ptrs(device, pipeline.pipeline.pipeline_ptrs);
+pub fn destroy_compute_pipeline(device: &Device, layout_cache: &mut PipelineLayoutCache, pipeline: ComputePipeline) {
+    destroy_common_pipeline_ptrs(device, layout_cache, pipeline.pipeline.pipeline_ptrs);
 }
 
 #[cfg(feature = "gpu_ray_tracing")]
-pub fn destroy_ray_tracing_pipeline(device: &Device, pipeline: RayTracingPipeline) {
+pub fn destroy_ray_tracing_pipeline(device: &Device, layout_cache: &mut PipelineLayoutCache, pipeline: RayTracingPipeline) {
     device.destroy_ray_tracing_shader_binding_table(pipeline.sbt);
-    destroy_common_pipeline_ptrs(device, pipeline.pipeline.pipeline_ptrs);
+    destroy_common_pipeline_ptrs(device, layout_cache, pipeline.pipeline.pipeline_ptrs);
 }
 
+/// Destroy the pipeline and release its pipeline layout from the cache.
 #[inline]
-pub(crate) fn destroy_common_pipeline_ptrs(device: &Device, pipeline_ptrs: CommonPipelinePtrs) {
+pub(crate) fn destroy_common_pipeline_ptrs(device: &Device, layout_cache: &mut PipelineLayoutCache, pipeline_ptrs: CommonPipelinePtrs) {
     unsafe {
-        device.raw
-            .destroy_pipeline_layout(pipeline_ptrs.pipeline_layout, None);
-
         device.raw
             .destroy_pipeline(pipeline_ptrs.pipeline, None);
     }
-}
\ No newline at end of file
+
+    layout_cache.release(device, pipeline_ptrs.pipeline_layout);
+}
@@ -1,4 +1,4 @@
-use std::{sync::Arc, cell::RefCell};
+use std::{sync::Arc, cell::{Cell, RefCell}};
 
 use ash::vk;
 use parking_lot::Mutex;
@@ -7,7 +7,7 @@ use raven_math::{self, Affine3A};
 
 use crate::{dynamic_buffer::DynamicBuffer};
 
-use super::{Device, RhiError, Buffer, pipeline::{RayTracingShaderBindingTable, RayTracingShaderBindingTableDesc}};
+use super::{Device, RhiError, Buffer, MemoryCategory, pipeline::{RayTracingShaderBindingTable, RayTracingShaderBindingTableDesc}};
 
 #[derive(Debug, Clone)]
 pub enum RayTracingGeometryType {
@@ -15,12 +15,16 @@ pub enum RayTracingGeometryType {
     AABB = 1,
 }
 
+/// A range of the index buffer (e.g. the triangles of one material) built as a separate geometry in the BLAS.
+/// In the hit shaders, GeometryIndex() is the index of the sub geometry and PrimitiveIndex() restarts from zero in each one.
 #[derive(Copy, Clone, Debug)]
 pub struct RayTracingSubGeometry {
     /// Index count within this sub geometry.
     pub index_count: usize,
     /// The first used index offset of the index buffer (passed by vk::DeviceAddress)
     pub index_offset: usize,
+    /// Value added to the indices of this sub geometry before fetching the vertices.
+    pub first_vertex: u32,
     /// The highest index of a vertex which will be addressed by a build command using this BLAS
     pub max_vertex: u32,
 }
@@ -33,6 +37,8 @@ pub struct RayTracingGeometry {
     pub vb_address: vk::DeviceAddress,
     /// Referenced index buffer address.
     pub ib_address: vk::DeviceAddress,
+    /// Number of indices in the referenced index buffer, the sub geometries must stay within it.
+    pub index_buffer_count: usize,
     pub vertex_format: vk::Format,
     pub vertex_stride: usize,
     pub sub_geometries: Vec<RayTracingSubGeometry>,
@@ -45,6 +51,9 @@ pub struct RayTracingGeometry {
 #[derive(Debug, Clone)]
 pub struct RayTracingBlasBuildDesc {
     pub geometries: Vec<RayTracingGeometry>,
+    /// Copy the blas into a compacted one after building, which can reclaim a lot of memory.
+    /// It costs an extra copy and a wait on the build, so only use it for static geometries which are built once.
+    pub compact: bool,
 }
 
 /// Each instance store a blas reference pointer and the transform of the blas.
@@ -76,6 +85,8 @@ pub struct RayTracingAccelerationStructure {
     backing_buffer: Buffer,
     // TODO: this must be removed!
     init_instance_buffer: RefCell<Option<Buffer>>,
+    /// Primitive (or instance) count of the last full build, update mode can only refit the same count.
+    built_primitive_count: Cell<u32>,
 }
 
 #[derive(Clone)]
@@ -142,6 +153,49 @@ impl RayTracingGeometryInstance {
     }
 }
 
+fn validate_blas_geometries(blas_desc: &RayTracingBlasBuildDesc) -> anyhow::Result<(), RhiError> {
+    for (geo_idx, geo) in blas_desc.geometries.iter().enumerate() {
+        if geo.sub_geometries.is_empty() {
+            return Err(RhiError::InvalidRayTracingGeometry {
+                reason: format!("geometry {} has no sub geometry", geo_idx),
+            });
+        }
+
+        for (sub_idx, sub_geometry) in geo.sub_geometries.iter().enumerate() {
+            if sub_geometry.index_count == 0 || sub_geometry.index_count % 3 != 0 {
+                return Err(RhiError::InvalidRayTracingGeometry {
+                    reason: format!("sub geometry {} of geometry {} has {} indices, which is not a list of triangles",
+                        sub_idx, geo_idx, sub_geometry.index_count),
+                });
+            }
+
+            let index_end = sub_geometry.index_offset.checked_add(sub_geometry.index_count);
+            if index_end.map_or(true, |end| end > geo.index_buffer_count) {
+                return Err(RhiError::InvalidRayTracingGeometry {
+                    reason: format!("indices {}..{} of sub geometry {} of geometry {} are out of the index buffer (with {} indices)",
+                        sub_geometry.index_offset, sub_geometry.index_offset.saturating_add(sub_geometry.index_count),
+                        sub_idx, geo_idx, geo.index_buffer_count),
+                });
+            }
+
+            // primitive offset is passed in bytes as u32
+            if (index_end.unwrap() * std::mem::size_of::<u32>()) > u32::MAX as usize {
+                return Err(RhiError::InvalidRayTracingGeometry {
+                    reason: format!("index offset of sub geometry {} of geometry {} is too large", sub_idx, geo_idx),
+                });
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Build flags of all the tlas builds and updates, update mode requires the flags to be the same as the source.
+const TLAS_BUILD_FLAGS: vk::BuildAccelerationStructureFlagsKHR = vk::BuildAccelerationStructureFlagsKHR::from_raw(
+    vk::BuildAccelerationStructureFlagsKHR::PREFER_FAST_BUILD.as_raw() |
+    vk::BuildAccelerationStructureFlagsKHR::ALLOW_UPDATE.as_raw()
+);
+
 impl Device {
     pub fn create_ray_tracing_acceleration_scratch_buffer(
         &self
@@ -153,7 +207,8 @@ impl Device {
                 RAY_TRACING_TLAS_SCRATCH_BUFFER_SIZE,
                 vk::BufferUsageFlags::STORAGE_BUFFER | vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS,
             )
-            .alignment(self.ray_tracing_extensions.acceleration_structure_props.min_acceleration_structure_scratch_offset_alignment as _),
+            .alignment(self.ray_tracing_extensions.acceleration_structure_props.min_acceleration_structure_scratch_offset_alignment as _)
+            .category(MemoryCategory::AccelerationStructure),
             "tlas scratch buffer",
         )?;
 
@@ -168,12 +223,16 @@ impl Device {
     ) -> anyhow::Result<RayTracingAccelerationStructure, RhiError> {
         const BUILD_AS_TYPE: vk::AccelerationStructureTypeKHR = vk::AccelerationStructureTypeKHR::BOTTOM_LEVEL;
 
-        // 1. fill the VkAccelerationStructureGeometryKHR 
-        let geometries: anyhow::Result<Vec<vk::AccelerationStructureGeometryKHR>, RhiError> = blas_desc.geometries.iter()
-            .map(|geo| -> anyhow::Result<vk::AccelerationStructureGeometryKHR, RhiError> {
-                // TODO: combine sub-geometries
-                let sub_geometry = geo.sub_geometries[0];
+        validate_blas_geometries(&blas_desc)?;
 
+        // 1. fill one VkAccelerationStructureGeometryKHR and one build range for each sub geometry,
+        // all sub geometries of a geometry share the same vertex and index buffer.
+        let mut geometries = Vec::new();
+        let mut build_range_infos = Vec::new();
+        let mut max_primitive_counts = Vec::new();
+
+        for geo in blas_desc.geometries.iter() {
+            for sub_geometry in geo.sub_geometries.iter() {
                 let as_geo = vk::AccelerationStructureGeometryKHR::builder()
                     .geometry_type(match geo.geo_type {
                         RayTracingGeometryType::Triangle => vk::GeometryTypeKHR::TRIANGLES,
@@ -196,29 +255,31 @@ impl Device {
                     .flags(vk::GeometryFlagsKHR::OPAQUE)
                     .build();
 
-                Ok(as_geo)
-            })
-            .collect();
-        let geometries = geometries?;
-
-        // 2. specified VkAccelerationStructureBuildRangeInfoKHR and each max_prims
-        let (build_range_infos, max_primitive_counts): (Vec<vk::AccelerationStructureBuildRangeInfoKHR>, Vec<_>) = blas_desc.geometries.iter()
-            .map(|desc| {
-                let primitive_count = desc.sub_geometries[0].index_count as u32 / 3;
+                let primitive_count = sub_geometry.index_count as u32 / 3;
 
-                (vk::AccelerationStructureBuildRangeInfoKHR::builder()
-                    // TODO: combine sub-geometries
-                    // first vertex is 0 and no offset by default
+                // 2. specified VkAccelerationStructureBuildRangeInfoKHR and each max_prims
+                let build_range_info = vk::AccelerationStructureBuildRangeInfoKHR::builder()
                     .primitive_count(primitive_count)
-                    .build(),
-                primitive_count)
-            })
-            .unzip();
+                    // offset in bytes into the index buffer
+                    .primitive_offset((sub_geometry.index_offset * std::mem::size_of::<u32>()) as u32)
+                    .first_vertex(sub_geometry.first_vertex)
+                    .build();
+
+                geometries.push(as_geo);
+                build_range_infos.push(build_range_info);
+                max_primitive_counts.push(primitive_count);
+            }
+        }
+
+        let mut build_flags = vk::BuildAccelerationStructureFlagsKHR::PREFER_FAST_TRACE;
+        if blas_desc.compact {
+            build_flags |= vk::BuildAccelerationStructureFlagsKHR::ALLOW_COMPACTION;
+        }
 
         // 3. build blas geometry info
         let geometry_build_info = vk::AccelerationStructureBuildGeometryInfoKHR::builder()
             .ty(BUILD_AS_TYPE)
-            .flags(vk::BuildAccelerationStructureFlagsKHR::PREFER_FAST_TRACE)
+            .flags(build_flags)
             .geometries(geometries.as_slice())
             .mode(vk::BuildAccelerationStructureModeKHR::BUILD)
             .build();
@@ -230,11 +291,15 @@ impl Device {
             &max_primitive_counts,
             0,
             None,
+            blas_desc.compact,
 
             None,
         )
     }
 
+    /// Build a tlas with ALLOW_UPDATE, so that update_tlas() can refit it in place when the instances move.
+    /// The same flags must be used by all the later builds and updates of this tlas,
+    /// so they are fixed to TLAS_BUILD_FLAGS (fast build is preferred since the tlas is updated every frame).
     pub fn build_tlas(
         &self,
         tlas_desc: RayTracingTlasBuildDesc,
@@ -286,7 +351,8 @@ impl Device {
                 instance_buffer_size,
                 vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS |
                 vk::BufferUsageFlags::ACCELERATION_STRUCTURE_BUILD_INPUT_READ_ONLY_KHR,
-            ),
+            )
+            .category(MemoryCategory::AccelerationStructure),
             "Acceleration tlas instance buffer",
             &geo_instances
         )?;
@@ -313,7 +379,7 @@ impl Device {
         // 4. fill geometry build info
         let geometry_build_info = vk::AccelerationStructureBuildGeometryInfoKHR::builder()
             .ty(BUILD_AS_TYPE)
-            .flags(vk::BuildAccelerationStructureFlagsKHR::PREFER_FAST_TRACE)
+            .flags(TLAS_BUILD_FLAGS)
             .geometries(std::slice::from_ref(&geometry_info))
             .mode(vk::BuildAccelerationStructureModeKHR::BUILD)
             .build();
@@ -327,6 +393,8 @@ impl Device {
             &max_primitive_counts,
             tlas_desc.preallocate_bytes,
             Some(scratch_buffer),
+            // the tlas is updated every frame, and the preallocated bytes are needed for the later builds
+            false,
 
             Some(instance_buffer),
         )
@@ -336,6 +404,9 @@ impl Device {
     /// 
     /// We may want to reuse the scratch buffer here when build tlas.
     /// Use Option here can pass optional scratch buffer in it.
+    /// 
+    /// If compact is true, geometry_build_info must be built with ALLOW_COMPACTION,
+    /// and the built acceleration structure is replaced by a compacted copy.
     fn build_acceleration_structure(
         &self,
         ty: vk::AccelerationStructureTypeKHR,
@@ -344,13 +415,11 @@ impl Device {
         max_primitive_counts: &[u32],
         preallocate_bytes: usize,
         scratch_buffer: Option<&RayTracingAccelerationScratchBuffer>,
+        compact: bool,
         backing_instance_buffer: Option<Buffer>,
     ) -> anyhow::Result<RayTracingAccelerationStructure, RhiError> {
         // finding sizes to create acceleration structures and scratch
-        // TODO: this function will return the sizes in the worst case, to optimize this,
-        // use VK_QUERY_TYPE_ACCELERATION_STRUCTURE_COMPACTED_SIZE_KHR to query the compact size.
-        // This could save a lot of memory usage.
-        // Use VkQueryPool to get the compact size of each acceleration structure and copy it.
+        // this function will return the sizes in the worst case, use compact to shrink it after building.
         let memory_requirements = unsafe {
             self.ray_tracing_extensions.acceleration_structure_khr
                 .get_acceleration_structure_build_sizes(
@@ -375,7 +444,8 @@ impl Device {
                 backing_buffer_size,
                 vk::BufferUsageFlags::ACCELERATION_STRUCTURE_STORAGE_KHR |
                 vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS,
-            ),
+            )
+            .category(MemoryCategory::AccelerationStructure),
             "Acceleration structure backing buffer",
         )?;
 
@@ -402,7 +472,8 @@ impl Device {
                         vk::BufferUsageFlags::STORAGE_BUFFER |
                         vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS,
                     )
-                    .alignment(self.ray_tracing_extensions.acceleration_structure_props.min_acceleration_structure_scratch_offset_alignment as _),
+                    .alignment(self.ray_tracing_extensions.acceleration_structure_props.min_acceleration_structure_scratch_offset_alignment as _)
+                    .category(MemoryCategory::AccelerationStructure),
                     "Acceleration structure scratch buffer",
                 )?,
             );
@@ -422,7 +493,22 @@ impl Device {
         geometry_build_info.dst_acceleration_structure = accel_raw;
         geometry_build_info.scratch_data = vk::DeviceOrHostAddressKHR { device_address: scratch_buffer.device_address(self) };
 
-        unsafe {
+        // query the compacted size right after building
+        let compacted_size_query_pool = if compact {
+            Some(unsafe {
+                self.raw.create_query_pool(
+                    &vk::QueryPoolCreateInfo::builder()
+                        .query_type(vk::QueryType::ACCELERATION_STRUCTURE_COMPACTED_SIZE_KHR)
+                        .query_count(1)
+                        .build(),
+                    None
+                )
+            }?)
+        } else {
+            None
+        };
+
+        let build_result = unsafe {
             // build and wait device idle
             self.with_setup_commands(|cb| {
                 self.ray_tracing_extensions.acceleration_structure_khr
@@ -451,24 +537,157 @@ impl Device {
                     &[],
                     &[],
                 );
-            })?;
-        }
 
-        // TODO: add query pool to find the real compact size of the acceleration structure
-        // use vkCmdWriteAccelerationStructuresPropertiesKHR
+                if let Some(query_pool) = compacted_size_query_pool {
+                    self.raw.cmd_reset_query_pool(cb, query_pool, 0, 1);
+                    self.ray_tracing_extensions.acceleration_structure_khr
+                        .cmd_write_acceleration_structures_properties(
+                            cb,
+                            std::slice::from_ref(&accel_raw),
+                            vk::QueryType::ACCELERATION_STRUCTURE_COMPACTED_SIZE_KHR,
+                            query_pool,
+                            0,
+                        );
+                }
+            })
+        };
+
+        if let Err(err) = build_result {
+            if let Some(query_pool) = compacted_size_query_pool {
+                unsafe { self.raw.destroy_query_pool(query_pool, None); }
+            }
+            return Err(err);
+        }
 
         if let Some(temp_scratch_buffer) = temp_scratch_buffer {
             self.destroy_buffer(temp_scratch_buffer);
         }
 
+        let (accel_raw, backing_buffer) = if let Some(query_pool) = compacted_size_query_pool {
+            let compacted = self.compact_acceleration_structure(ty, accel_raw, &backing_buffer, query_pool);
+            unsafe { self.raw.destroy_query_pool(query_pool, None); }
+
+            match compacted {
+                Ok((compacted_raw, compacted_buffer)) => {
+                    // free the original one, the compacted copy is independent of it
+                    unsafe {
+                        self.ray_tracing_extensions.acceleration_structure_khr
+                            .destroy_acceleration_structure(accel_raw, None);
+                    }
+                    self.destroy_buffer(backing_buffer);
+
+                    (compacted_raw, compacted_buffer)
+                }
+                Err(err) => {
+                    glog::warn!("Failed to compact acceleration structure, use the original one: {:?}", err);
+                    (accel_raw, backing_buffer)
+                }
+            }
+        } else {
+            (accel_raw, backing_buffer)
+        };
+
         Ok(RayTracingAccelerationStructure { 
             raw: accel_raw, 
             backing_buffer,
 
             init_instance_buffer: RefCell::new(backing_instance_buffer),
+            built_primitive_count: Cell::new(max_primitive_counts.iter().sum()),
         })
     }
     
+    /// Copy the built acceleration structure into a new one with the compacted size written in the query pool.
+    /// Return the compacted acceleration structure and its backing buffer, the source is kept untouched.
+    fn compact_acceleration_structure(
+        &self,
+        ty: vk::AccelerationStructureTypeKHR,
+        src_accel: vk::AccelerationStructureKHR,
+        src_backing_buffer: &Buffer,
+        query_pool: vk::QueryPool,
+    ) -> anyhow::Result<(vk::AccelerationStructureKHR, Buffer), RhiError> {
+        let mut compacted_size = [0u64];
+        unsafe {
+            // the query is written in the setup commands, which are already finished
+            self.raw.get_query_pool_results(
+                query_pool,
+                0,
+                1,
+                &mut compacted_size,
+                vk::QueryResultFlags::TYPE_64 | vk::QueryResultFlags::WAIT,
+            )?;
+        }
+        let compacted_size = compacted_size[0] as usize;
+
+        glog::info!(
+            "Compact acceleration structure size: {} -> {}",
+            src_backing_buffer.desc.size,
+            compacted_size,
+        );
+
+        let backing_buffer = self.create_buffer(
+            super::buffer::BufferDesc::new_gpu_only(
+                compacted_size,
+                vk::BufferUsageFlags::ACCELERATION_STRUCTURE_STORAGE_KHR |
+                vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS,
+            )
+            .category(MemoryCategory::AccelerationStructure),
+            "Acceleration structure compacted backing buffer",
+        )?;
+
+        let accel_info = vk::AccelerationStructureCreateInfoKHR::builder()
+            .ty(ty)
+            .buffer(backing_buffer.raw)
+            .size(compacted_size as u64)
+            .build();
+
+        let accel_raw = match unsafe {
+            self.ray_tracing_extensions.acceleration_structure_khr
+                .create_acceleration_structure(&accel_info, None)
+        } {
+            Ok(accel_raw) => accel_raw,
+            Err(err) => {
+                self.destroy_buffer(backing_buffer);
+                return Err(err.into());
+            }
+        };
+
+        let copy_result = self.with_setup_commands(|cb| unsafe {
+            self.ray_tracing_extensions.acceleration_structure_khr
+                .cmd_copy_acceleration_structure(
+                    cb,
+                    &vk::CopyAccelerationStructureInfoKHR::builder()
+                        .src(src_accel)
+                        .dst(accel_raw)
+                        .mode(vk::CopyAccelerationStructureModeKHR::COMPACT)
+                        .build(),
+                );
+
+            self.raw.cmd_pipeline_barrier(
+                cb,
+                vk::PipelineStageFlags::ACCELERATION_STRUCTURE_BUILD_KHR,
+                vk::PipelineStageFlags::ACCELERATION_STRUCTURE_BUILD_KHR,
+                vk::DependencyFlags::empty(),
+                &[vk::MemoryBarrier::builder()
+                    .src_access_mask(vk::AccessFlags::ACCELERATION_STRUCTURE_WRITE_KHR)
+                    .dst_access_mask(vk::AccessFlags::ACCELERATION_STRUCTURE_READ_KHR)
+                    .build()],
+                &[],
+                &[],
+            );
+        });
+
+        if let Err(err) = copy_result {
+            unsafe {
+                self.ray_tracing_extensions.acceleration_structure_khr
+                    .destroy_acceleration_structure(accel_raw, None);
+            }
+            self.destroy_buffer(backing_buffer);
+            return Err(err);
+        }
+
+        Ok((accel_raw, backing_buffer))
+    }
+
     pub fn create_ray_tracing_shader_binding_table(
         &self,
         desc: RayTracingShaderBindingTableDesc,
@@ -603,6 +822,8 @@ impl Device {
         }
     }
 
+    /// Update the tlas with the new instances, it is refitted in place if the instance count is unchanged,
+    /// otherwise it is fully rebuilt (into the same backing buffer).
     pub fn update_tlas(
         &self,
         cb_raw: vk::CommandBuffer,
@@ -635,13 +856,20 @@ impl Device {
             .primitive_count(new_instance_count as _)
             .build()];
 
+        // refit the tlas in place if only the instances moved, which is much cheaper than a full rebuild.
+        // update mode can not handle a different instance count, so rebuild it then.
+        let mode = if new_instance_count > 0 && update_tlas.built_primitive_count.get() == new_instance_count as u32 {
+            vk::BuildAccelerationStructureModeKHR::UPDATE
+        } else {
+            vk::BuildAccelerationStructureModeKHR::BUILD
+        };
+
         // 4. fill geometry build info
         let geometry_build_info = vk::AccelerationStructureBuildGeometryInfoKHR::builder()
             .ty(BUILD_AS_TYPE)
-            .flags(vk::BuildAccelerationStructureFlagsKHR::PREFER_FAST_TRACE)
+            .flags(TLAS_BUILD_FLAGS)
             .geometries(std::slice::from_ref(&geometry_info))
-            // TODO: use Update mode
-            .mode(vk::BuildAccelerationStructureModeKHR::BUILD)
+            .mode(mode)
             .build();
 
         let max_primitive_counts = [new_instance_count as u32]; // have only one tlas
@@ -663,6 +891,7 @@ impl Device {
     /// Due to the fact that we only update tlas for now.
     /// 
     /// Update blas will be inefficient, prefer building a new blas.
+    /// In update mode, update_as is refitted in place, it must be built with ALLOW_UPDATE and the same primitive count.
     fn rebuild_acceleration_structure(
         &self,
         cb_raw: vk::CommandBuffer,
@@ -689,11 +918,24 @@ impl Device {
 
         let scratch_buffer = scratch_buffer.buffer.lock();
 
+        let is_update = geometry_build_info.mode == vk::BuildAccelerationStructureModeKHR::UPDATE;
+        let scratch_size = if is_update {
+            memory_requirements.update_scratch_size
+        } else {
+            memory_requirements.build_scratch_size
+        };
+
         assert!(
-            memory_requirements.build_scratch_size as usize <= scratch_buffer.desc.size,
+            scratch_size as usize <= scratch_buffer.desc.size,
             "Inadequate acceleration structure scratch buffer size!"
         );
 
+        if is_update {
+            // refit in place
+            geometry_build_info.src_acceleration_structure = update_as.raw;
+        } else {
+            update_as.built_primitive_count.set(max_primitive_counts.iter().sum());
+        }
         geometry_build_info.dst_acceleration_structure = update_as.raw;
         geometry_build_info.scratch_data = vk::DeviceOrHostAddressKHR { device_address: scratch_buffer.device_address(self) };
 
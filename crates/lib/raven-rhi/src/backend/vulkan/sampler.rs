@@ -9,6 +9,8 @@ pub struct SamplerDesc {
     pub filter: vk::Filter,
     pub mipmap_mode: vk::SamplerMipmapMode,
     pub address_mode: vk::SamplerAddressMode,
+    /// Comparison sampler (e.g. for hardware PCF) if this is Some, the result is the fraction of the texels where `reference <op> texel` passes.
+    pub compare_op: Option<vk::CompareOp>,
 }
 
 impl Device {
@@ -26,32 +28,43 @@ impl Device {
             vk::SamplerAddressMode::REPEAT, vk::SamplerAddressMode::MIRRORED_REPEAT, vk::SamplerAddressMode::MIRROR_CLAMP_TO_EDGE,
         ];
 
+        // only the ordered comparisons are useful for depth
+        let compare_ops = [
+            None,
+            Some(vk::CompareOp::LESS), Some(vk::CompareOp::LESS_OR_EQUAL),
+            Some(vk::CompareOp::GREATER), Some(vk::CompareOp::GREATER_OR_EQUAL),
+        ];
+
         let mut map = HashMap::new();
 
         for filter in filters {
             for mipmap_mode in mipmap_modes {
                 for address_mode in address_modes {
-                    let anisotropy_enable = filter == vk::Filter::LINEAR;
-
-                    let sampler = unsafe {
-                        device.create_sampler(
-                &vk::SamplerCreateInfo::builder()
-                                .min_filter(filter)
-                                .mag_filter(filter)
-                                .mipmap_mode(mipmap_mode)
-                                .address_mode_u(address_mode)
-                                .address_mode_v(address_mode)
-                                .address_mode_w(address_mode)
-                                .max_lod(vk::LOD_CLAMP_NONE)
-                                .max_anisotropy(16.0)
-                                .anisotropy_enable(anisotropy_enable)
-                                .build(),
-                        None
-                        )
-                        .expect("Failed to create device immutable sampler!")
-                    };
-
-                    map.insert(SamplerDesc { filter, mipmap_mode, address_mode }, sampler);
+                    for compare_op in compare_ops {
+                        let anisotropy_enable = filter == vk::Filter::LINEAR && compare_op.is_none();
+
+                        let sampler = unsafe {
+                            device.create_sampler(
+                    &vk::SamplerCreateInfo::builder()
+                                    .min_filter(filter)
+                                    .mag_filter(filter)
+                                    .mipmap_mode(mipmap_mode)
+                                    .address_mode_u(address_mode)
+                                    .address_mode_v(address_mode)
+                                    .address_mode_w(address_mode)
+                                    .max_lod(vk::LOD_CLAMP_NONE)
+                                    .max_anisotropy(16.0)
+                                    .anisotropy_enable(anisotropy_enable)
+                                    .compare_enable(compare_op.is_some())
+                                    .compare_op(compare_op.unwrap_or(vk::CompareOp::NEVER))
+                                    .build(),
+                            None
+                            )
+                            .expect("Failed to create device immutable sampler!")
+                        };
+
+                        map.insert(SamplerDesc { filter, mipmap_mode, address_mode, compare_op }, sampler);
+                    }
                 }
             }
         }
@@ -25,6 +25,24 @@ impl DerefMut for SwapchainImage {
     }
 }
 
+/// How the final image is encoded when it is presented.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SwapchainEncoding {
+    /// 8-bit sRGB, the HDR image is tonemapped to SDR.
+    Sdr,
+    /// 10-bit Rec.2020 with the ST.2084 (PQ) transfer function.
+    Hdr10,
+    /// 16-bit float linear extended sRGB, 1.0 is 80 nits.
+    ScRgb,
+}
+
+impl SwapchainEncoding {
+    #[inline]
+    pub fn is_hdr(&self) -> bool {
+        *self != SwapchainEncoding::Sdr
+    }
+}
+
 pub struct Swapchain {
     pub(crate) raw: vk::SwapchainKHR,
     pub(crate) func_loader: ash::extensions::khr::Swapchain,
@@ -44,6 +62,8 @@ pub struct Swapchain {
 
     pub extent: vk::Extent2D,
     pub enable_vsync: bool,
+    pub format: vk::SurfaceFormatKHR,
+    pub encoding: SwapchainEncoding,
 }
 
 impl Swapchain {
@@ -125,9 +145,41 @@ impl Swapchain {
         }
     }
 
-    fn pick_suitable_surface_format(device: &Arc<Device>, surface: &Arc<Surface>) -> anyhow::Result<vk::SurfaceFormatKHR> {
+    /// Pick a HDR surface format which can be written as a storage image, HDR10 is preferred over scRGB.
+    fn pick_hdr_surface_format(device: &Arc<Device>, surface_formats: &[vk::SurfaceFormatKHR]) -> Option<(vk::SurfaceFormatKHR, SwapchainEncoding)> {
+        const HDR_SURFACE_FORMATS: [(vk::Format, vk::ColorSpaceKHR, SwapchainEncoding); 3] = [
+            (vk::Format::A2B10G10R10_UNORM_PACK32, vk::ColorSpaceKHR::HDR10_ST2084_EXT, SwapchainEncoding::Hdr10),
+            (vk::Format::A2R10G10B10_UNORM_PACK32, vk::ColorSpaceKHR::HDR10_ST2084_EXT, SwapchainEncoding::Hdr10),
+            (vk::Format::R16G16B16A16_SFLOAT, vk::ColorSpaceKHR::EXTENDED_SRGB_LINEAR_EXT, SwapchainEncoding::ScRgb),
+        ];
+
+        HDR_SURFACE_FORMATS.iter()
+            .filter(|(format, _, _)| {
+                let properties = unsafe { device.instance.raw
+                    .get_physical_device_format_properties(device.physical_device.raw, *format) };
+                properties.optimal_tiling_features.contains(vk::FormatFeatureFlags::STORAGE_IMAGE)
+            })
+            .find_map(|(format, color_space, encoding)| {
+                surface_formats.iter()
+                    .find(|sf| sf.format == *format && sf.color_space == *color_space)
+                    .map(|sf| (*sf, *encoding))
+            })
+    }
+
+    fn pick_suitable_surface_format(device: &Arc<Device>, surface: &Arc<Surface>, hdr_output: bool) -> anyhow::Result<(vk::SurfaceFormatKHR, SwapchainEncoding)> {
         let surface_formats = Self::enumerate_available_surface_format(&device, &surface)?;
 
+        if hdr_output {
+            if !device.instance.swapchain_colorspace_enabled {
+                glog::warn!("HDR output is requested, but VK_EXT_swapchain_colorspace is not supported, fallback to SDR output.");
+            } else if let Some((format, encoding)) = Self::pick_hdr_surface_format(device, &surface_formats) {
+                glog::info!("HDR output enabled with {:?} ({:?}, {:?}).", encoding, format.format, format.color_space);
+                return Ok((format, encoding));
+            } else {
+                glog::warn!("HDR output is requested, but the surface does not support any HDR format, fallback to SDR output.");
+            }
+        }
+
         let pick_surface_formats = match surface_formats.len() {
             0 => unreachable!(),
             // if there is only one format with vk::Format::UNDEFINED,
@@ -146,7 +198,7 @@ impl Swapchain {
             }
         };
 
-        Ok(pick_surface_formats)
+        Ok((pick_surface_formats, SwapchainEncoding::Sdr))
     }
 
     fn new(builder: SwapchainBuilder, device: &Arc<Device>, surface: &Arc<Surface>) -> anyhow::Result<Self> {
@@ -187,7 +239,7 @@ impl Swapchain {
             surface_capabilities.current_transform
         };
 
-        let surface_format = Self::pick_suitable_surface_format(&device, &surface)
+        let (surface_format, encoding) = Self::pick_suitable_surface_format(&device, &surface, builder.hdr_output)
             .expect("Failed to pick a suitable surface format!");
 
         let swapchain_ci = vk::SwapchainCreateInfoKHR::builder()
@@ -219,7 +271,7 @@ impl Swapchain {
                 Arc::new(Image {
                     raw: raw,
                     allocation: None,
-                    desc: ImageDesc::new_2d([builder.extent.width, builder.extent.height], vk::Format::B8G8R8A8_UNORM)
+                    desc: ImageDesc::new_2d([builder.extent.width, builder.extent.height], surface_format.format)
                         .usage_flags(vk::ImageUsageFlags::STORAGE),
                     views: Default::default(),
             }))
@@ -261,6 +313,8 @@ impl Swapchain {
             func_loader,
             extent: builder.extent,
             enable_vsync: builder.enable_vsync,
+            format: surface_format,
+            encoding,
 
             images,
             acquire_semaphores,
@@ -276,6 +330,8 @@ impl Swapchain {
 pub struct SwapchainBuilder {
     pub extent: vk::Extent2D,
     pub enable_vsync: bool,
+    /// Present in a HDR color space if the surface supports one.
+    pub hdr_output: bool,
 }
 
 impl Default for SwapchainBuilder {
@@ -287,6 +343,7 @@ impl Default for SwapchainBuilder {
                 height: 0,
             },
             enable_vsync: false,
+            hdr_output: false,
         }
     }
 }
@@ -305,6 +362,11 @@ impl SwapchainBuilder {
         self
     }
 
+    pub fn hdr_output(mut self, hdr_output: bool) -> Self {
+        self.hdr_output = hdr_output;
+        self
+    }
+
     pub fn build(self, device: &Arc<Device>, surface: &Arc<Surface>) -> anyhow::Result<Swapchain> {
         Ok(Swapchain::new(self, device, surface)?)
     }
@@ -57,9 +57,19 @@ pub struct CopyPrimitive<'a> {
     offset: u32,
 }
 
+/// Copies targeting the same destination buffer.
+struct BufferCopies<'a> {
+    dst_buffer: &'a Buffer,
+    copy_primitives: Vec<CopyPrimitive<'a>>,
+    current_offset: u32,
+}
+
 pub struct CopyEngine<'a> {
     copy_primitives: Vec<CopyPrimitive<'a>>,
     current_offset: u32,
+
+    /// Copies recorded by copy_to(), uploaded all together by upload_all().
+    buffer_copies: Vec<BufferCopies<'a>>,
 }
 
 impl<'a> CopyEngine<'a> {
@@ -67,6 +77,8 @@ impl<'a> CopyEngine<'a> {
         Self {
             copy_primitives: Default::default(),
             current_offset: 0,
+
+            buffer_copies: Default::default(),
         }
     }
 
@@ -76,23 +88,113 @@ impl<'a> CopyEngine<'a> {
     
     /// Copy the data from CopyDataSource and return an offset.
     pub fn copy(&mut self, source: &'a impl CopyDataSource) -> u32 {
+        Self::push_copy(&mut self.copy_primitives, &mut self.current_offset, source)
+    }
+
+    /// Copy the data from CopyDataSource to dst_buffer and return the offset in dst_buffer.
+    /// Each destination buffer has its own offset starting from 0, all the copies are uploaded together by upload_all().
+    pub fn copy_to(&mut self, dst_buffer: &'a Buffer, source: &'a impl CopyDataSource) -> u32 {
+        let idx = match self.buffer_copies.iter().position(|copies| copies.dst_buffer.raw == dst_buffer.raw) {
+            Some(idx) => idx,
+            None => {
+                self.buffer_copies.push(BufferCopies {
+                    dst_buffer,
+                    copy_primitives: Vec::new(),
+                    current_offset: 0,
+                });
+                self.buffer_copies.len() - 1
+            }
+        };
+
+        let copies = &mut self.buffer_copies[idx];
+        Self::push_copy(&mut copies.copy_primitives, &mut copies.current_offset, source)
+    }
+
+    fn push_copy(copy_primitives: &mut Vec<CopyPrimitive<'a>>, current_offset: &mut u32, source: &'a impl CopyDataSource) -> u32 {
         let alignment = source.alignment();
         // alignment must be the power of two or 1.
         assert_eq!(alignment.count_ones(), 1);
 
-        let offset_beg = self.current_offset();
+        let offset_beg = *current_offset;
         assert!(offset_beg as usize % alignment == 0);
         let data_len = source.as_bytes().len();
 
-        self.copy_primitives.push(CopyPrimitive {
+        copy_primitives.push(CopyPrimitive {
             source: Box::new(source),
             offset: offset_beg,
         });
-        self.current_offset = offset_beg + raven_math::min_value_align_to(data_len, alignment) as u32;
+        *current_offset = offset_beg + raven_math::min_value_align_to(data_len, alignment) as u32;
 
         offset_beg
     }
 
+    /// Upload all the copies recorded by copy_to() with one staging buffer and one submit.
+    pub fn upload_all(self, device: &Device) -> anyhow::Result<(), RhiError> {
+        assert!(self.copy_primitives.is_empty(), "Copies without a destination buffer need to be uploaded by upload()!");
+
+        for copies in &self.buffer_copies {
+            // the copy data should not exceed the size of dst_buffer
+            assert!(copies.current_offset as usize <= copies.dst_buffer.desc.size);
+        }
+
+        // pack all the copies tightly into the staging buffer
+        let mut staging_size = 0;
+        let regions = self.buffer_copies.iter()
+            .map(|copies| {
+                copies.copy_primitives.iter()
+                    .filter(|prim| !prim.source.is_empty())
+                    .map(|prim| {
+                        let size = prim.source.as_bytes().len();
+                        let region = vk::BufferCopy::builder()
+                            .src_offset(staging_size as u64)
+                            .dst_offset(prim.offset as u64)
+                            .size(size as u64)
+                            .build();
+                        staging_size += size;
+                        region
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        if staging_size == 0 {
+            return Ok(());
+        }
+
+        let mut staging_buffer = device.create_buffer(BufferDesc::new_cpu_to_gpu(
+            staging_size,
+            vk::BufferUsageFlags::TRANSFER_SRC),
+            "copy engine batched staging buffer"
+        )?;
+
+        {
+            let mapped = staging_buffer.allocation.mapped_slice_mut().unwrap();
+            for (copies, regions) in self.buffer_copies.iter().zip(regions.iter()) {
+                let sources = copies.copy_primitives.iter().filter(|prim| !prim.source.is_empty());
+                for (prim, region) in sources.zip(regions.iter()) {
+                    let src_offset = region.src_offset as usize;
+                    mapped[src_offset..src_offset + region.size as usize].copy_from_slice(prim.source.as_bytes());
+                }
+            }
+        }
+
+        device.with_setup_commands(|cb| {
+            for (copies, regions) in self.buffer_copies.iter().zip(regions.iter()) {
+                if regions.is_empty() {
+                    continue;
+                }
+
+                unsafe {
+                    device.raw.cmd_copy_buffer(cb, staging_buffer.raw, copies.dst_buffer.raw, regions);
+                }
+            }
+        })?;
+
+        device.destroy_buffer(staging_buffer);
+
+        Ok(())
+    }
+
     pub fn upload(
         self, 
         device: &Device,
@@ -0,0 +1,67 @@
+use ash::vk;
+
+use crate::backend::{Buffer, Image, Device};
+#[cfg(feature = "gpu_ray_tracing")]
+use crate::backend::RayTracingAccelerationStructure;
+
+/// Resources which can be released later by Device::defer_release(),
+/// once the GPU has finished all the frames which may use them.
+pub trait DeferReleasableResource {
+    fn enqueue(self, queue: &mut DeferReleaseQueue);
+}
+
+impl DeferReleasableResource for vk::DescriptorPool {
+    fn enqueue(self, queue: &mut DeferReleaseQueue) {
+        queue.descriptor_pools.push(self);
+    }
+}
+
+impl DeferReleasableResource for Buffer {
+    fn enqueue(self, queue: &mut DeferReleaseQueue) {
+        queue.buffers.push(self);
+    }
+}
+
+impl DeferReleasableResource for Image {
+    fn enqueue(self, queue: &mut DeferReleaseQueue) {
+        queue.images.push(self);
+    }
+}
+
+#[cfg(feature = "gpu_ray_tracing")]
+impl DeferReleasableResource for RayTracingAccelerationStructure {
+    fn enqueue(self, queue: &mut DeferReleaseQueue) {
+        queue.acceleration_structures.push(self);
+    }
+}
+
+/// Resources released in the same frame.
+#[derive(Default)]
+pub struct DeferReleaseQueue {
+    descriptor_pools: Vec<vk::DescriptorPool>,
+    buffers: Vec<Buffer>,
+    images: Vec<Image>,
+    #[cfg(feature = "gpu_ray_tracing")]
+    acceleration_structures: Vec<RayTracingAccelerationStructure>,
+}
+
+impl DeferReleaseQueue {
+    pub(crate) fn release(self, device: &Device) {
+        for pool in self.descriptor_pools {
+            unsafe { device.raw.destroy_descriptor_pool(pool, None); }
+        }
+
+        for buffer in self.buffers {
+            device.destroy_buffer(buffer);
+        }
+
+        for image in self.images {
+            device.destroy_image(image);
+        }
+
+        #[cfg(feature = "gpu_ray_tracing")]
+        for accel_struct in self.acceleration_structures {
+            device.destroy_acceleration_structure(accel_struct);
+        }
+    }
+}
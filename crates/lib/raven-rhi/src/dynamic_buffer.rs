@@ -1,12 +1,11 @@
 use ash::vk;
 
-use crate::{backend::{Buffer, BufferDesc, Device}, Rhi};
+use crate::{backend::{Buffer, BufferDesc, Device, RhiError}, Rhi};
 
 use raven_container::as_bytes;
 use raven_math;
 
 pub const MAX_DYNAMIC_BUFFER_SIZE_BYTES: usize = 1024 * 1024 * 16;
-pub const MAX_DYNAMIC_BUFFER_FRAME_COUNT: usize = 2;
 
 pub struct DynamicBuffer {
     pub buffer: Buffer,
@@ -14,6 +13,10 @@ pub struct DynamicBuffer {
     prev_offset_bytes: u32, // cached previous push data offset to have the ability to reuse some buffer data
     current_offset_bytes: u32,
     current_frame: u32,
+    /// Number of frame regions, one for each frame in flight.
+    frame_count: u32,
+    /// Peak bytes used in a single frame.
+    high_water_mark_bytes: u32,
 
     alignment: u32,
     max_uniform_buffer_range: u32,
@@ -34,10 +37,13 @@ impl DynamicBuffer {
             vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS |
             vk::BufferUsageFlags::ACCELERATION_STRUCTURE_BUILD_INPUT_READ_ONLY_KHR;
 
-        // total size of the buffer should be MAX_DYNAMIC_BUFFER_FRAME_COUNT * MAX_DYNAMIC_BUFFER_SIZE_BYTES;
+        // the region of a frame can only be reused after the gpu finished that frame
+        let frame_count = rhi.device.frames_in_flight();
+
+        // total size of the buffer should be frame_count * MAX_DYNAMIC_BUFFER_SIZE_BYTES;
         let buffer = rhi.device.create_buffer(
             BufferDesc::new_cpu_to_gpu(
-                MAX_DYNAMIC_BUFFER_FRAME_COUNT * MAX_DYNAMIC_BUFFER_SIZE_BYTES, 
+                frame_count * MAX_DYNAMIC_BUFFER_SIZE_BYTES, 
                 dynamic_buffer_flags,
             ),
             "dynamic buffer"
@@ -51,6 +57,8 @@ impl DynamicBuffer {
             prev_offset_bytes: 0,
             current_offset_bytes: 0,
             current_frame: 0,
+            frame_count: frame_count as u32,
+            high_water_mark_bytes: 0,
 
             alignment: alignment as _,
             max_uniform_buffer_range: limits.max_uniform_buffer_range,
@@ -72,13 +80,43 @@ impl DynamicBuffer {
         self.max_storage_buffer_range.min(MAX_DYNAMIC_CONSTANTS_STORAGE_BUFFER_BYTES)
     }
 
+    /// Switch to the region of the next frame and reset it.
+    /// It must be called once per frame by the owner (i.e. the render graph executor),
+    /// the data pushed in this frame stays valid until the region is used again frames_in_flight frames later.
     pub fn advance_frame(&mut self) {
-        self.current_frame = (self.current_frame + 1) % MAX_DYNAMIC_BUFFER_FRAME_COUNT as u32;
+        self.current_frame = (self.current_frame + 1) % self.frame_count;
         // reset next frame's buffer data
         self.prev_offset_bytes = 0;
         self.current_offset_bytes = 0;
     }
 
+    /// Bytes used in the current frame.
+    #[inline]
+    pub fn used_bytes(&self) -> u32 {
+        self.current_offset_bytes
+    }
+
+    /// Peak bytes used in a single frame since the creation, useful to size MAX_DYNAMIC_BUFFER_SIZE_BYTES.
+    #[inline]
+    pub fn high_water_mark(&self) -> u32 {
+        self.high_water_mark_bytes
+    }
+
+    /// Reserve size bytes in the current frame's region, returning the offset (relative to the region) of the reserved bytes.
+    fn reserve(&mut self, size: usize) -> anyhow::Result<usize, RhiError> {
+        let beg = self.current_offset_bytes as usize;
+        let end = beg + size;
+        if end > MAX_DYNAMIC_BUFFER_SIZE_BYTES {
+            return Err(RhiError::DynamicBufferOverflow { required: end, capacity: MAX_DYNAMIC_BUFFER_SIZE_BYTES });
+        }
+
+        self.current_offset_bytes = raven_math::min_value_align_to(end, self.alignment as usize)
+            .min(MAX_DYNAMIC_BUFFER_SIZE_BYTES) as u32;
+        self.high_water_mark_bytes = self.high_water_mark_bytes.max(self.current_offset_bytes);
+
+        Ok(beg)
+    }
+
     #[inline]
     fn current_offset(&self) -> u32 {
         (self.current_frame * MAX_DYNAMIC_BUFFER_SIZE_BYTES as u32) + self.current_offset_bytes
@@ -95,49 +133,59 @@ impl DynamicBuffer {
     }
 
     /// Push a value into GPU dynamic buffer, returning the offset in the buffer.
+    /// Panics if the buffer overflows in this frame, use try_push() to handle it.
     pub fn push<T: Copy>(&mut self, value: &T) -> u32 {
-        let t_size = std::mem::size_of::<T>();
-        // can not exceed the max buffer size
-        assert!(self.current_offset_bytes as usize + t_size <= MAX_DYNAMIC_BUFFER_SIZE_BYTES);
+        self.try_push(value).unwrap_or_else(|err| panic!("{}", err))
+    }
 
-        let curr_offset = self.current_offset() as usize;
+    /// Push a value into GPU dynamic buffer, returning the offset in the buffer.
+    /// Nothing is pushed if the buffer overflows in this frame.
+    pub fn try_push<T: Copy>(&mut self, value: &T) -> anyhow::Result<u32, RhiError> {
+        let t_size = std::mem::size_of::<T>();
+        let frame_base = self.current_frame as usize * MAX_DYNAMIC_BUFFER_SIZE_BYTES;
+        let curr_offset = frame_base + self.reserve(t_size)?;
         self.prev_offset_bytes = curr_offset as u32;
 
         let copy_slice = &mut self.buffer.allocation.mapped_slice_mut().unwrap()[curr_offset..curr_offset + t_size];
         copy_slice.copy_from_slice(as_bytes::as_byte_slice(value));
 
-        self.current_offset_bytes += raven_math::min_value_align_to(t_size, self.alignment as usize) as u32;
+        Ok(curr_offset as _)
+    }
 
-        curr_offset as _
+    /// Push the values into GPU dynamic buffer as an array, returning the offset of the first element in the buffer.
+    /// Panics if the buffer overflows in this frame, use try_push_from_iter() to handle it.
+    pub fn push_from_iter<T: Copy, Iter: ExactSizeIterator<Item = T>>(&mut self, iter: Iter) -> u32 {
+        self.try_push_from_iter(iter).unwrap_or_else(|err| panic!("{}", err))
     }
 
-    pub fn push_from_iter<T: Copy, Iter: Iterator<Item = T>>(&mut self, iter: Iter) -> u32 {
+    /// Push the values into GPU dynamic buffer as an array, returning the offset of the first element in the buffer.
+    /// Nothing is pushed if the buffer overflows in this frame.
+    pub fn try_push_from_iter<T: Copy, Iter: ExactSizeIterator<Item = T>>(&mut self, iter: Iter) -> anyhow::Result<u32, RhiError> {
         let t_size = std::mem::size_of::<T>();
         let t_align = std::mem::align_of::<T>();
-        // can not exceed the max buffer size
-        assert!(self.current_offset_bytes as usize + t_size <= MAX_DYNAMIC_BUFFER_SIZE_BYTES);
         // alignment must be consistent
         assert!(self.alignment as usize % t_align == 0);
 
-        let curr_offset = self.current_offset() as usize;
-        self.prev_offset_bytes = curr_offset as u32;
-
+        // reserve the whole array before writing anything, so that nothing is written on overflow
+        let stride = raven_math::min_value_align_to(t_size, t_align);
+        let len = iter.len();
+        let frame_base = self.current_frame as usize * MAX_DYNAMIC_BUFFER_SIZE_BYTES;
+        let curr_offset = frame_base + self.reserve(len * stride)?;
         // current offset must be aligned to t_align
         assert!(curr_offset % t_align == 0);
-        
-        let mut offset = curr_offset;
 
+        let mapped_slice = self.buffer.allocation.mapped_slice_mut().unwrap();
         // TODO: optimize: should be faster to copy once, instead of copy n times
-        for v in iter {
-            let copy_slice = &mut self.buffer.allocation.mapped_slice_mut().unwrap()[offset..offset + t_size];
+        // never write more than the reserved bytes, even if the iterator reports a wrong length
+        for (idx, v) in iter.take(len).enumerate() {
+            let offset = curr_offset + idx * stride;
+
+            let copy_slice = &mut mapped_slice[offset..offset + t_size];
             copy_slice.copy_from_slice(as_bytes::as_byte_slice(&v));
-            // array elements should be aligned
-            offset += raven_math::min_value_align_to(t_size, t_align);
         }
+        self.prev_offset_bytes = curr_offset as u32;
 
-        self.current_offset_bytes += raven_math::min_value_align_to(offset - curr_offset, self.alignment as usize) as u32;
-
-        curr_offset as _
+        Ok(curr_offset as _)
     }
 
     pub fn clean(self, device: &Device) {
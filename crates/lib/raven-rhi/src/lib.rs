@@ -5,6 +5,7 @@ pub mod backend;
 pub mod pipeline_cache;
 pub mod shader_compiler;
 pub mod draw_frame;
+pub mod defer_release;
 pub mod dynamic_buffer;
 pub mod copy_engine;
 
@@ -14,13 +15,25 @@ pub mod global_constants_descriptor; // set 2
 use std::sync::Arc;
 use winit::window::Window;
 
-use crate::backend::vulkan::{Instance, Surface, physical_device, Device, Swapchain, debug};
+use crate::backend::vulkan::{Instance, Surface, physical_device, Device, Swapchain, debug, AdapterSelection, AdapterInfo};
 
-#[derive(Clone, Copy)]
+#[derive(Clone)]
 pub struct RhiConfig {
     pub swapchain_extent: [u32; 2],
     pub enable_debug: bool,
+    /// Abort with a backtrace on any validation error, only meaningful when enable_debug is true.
+    pub break_on_validation_error: bool,
     pub enable_vsync: bool,
+    /// Present in a HDR color space (HDR10 or scRGB) if the display supports it, fallback to SDR otherwise.
+    pub hdr_output: bool,
+    /// Which physical device to use, discrete gpus are preferred by default.
+    pub adapter: AdapterSelection,
+    /// Use VK_KHR_dynamic_rendering if supported, fallback to render pass otherwise.
+    pub enable_dynamic_rendering: bool,
+    /// Number of frames the CPU can record ahead of the GPU, see backend::DEVICE_DRAW_FRAMES for the max.
+    pub frames_in_flight: usize,
+    /// Warn with a memory report once this fraction of the device local memory is used, None to never warn.
+    pub memory_warning_threshold: Option<f32>,
 }
 
 // maybe raven will support RHI in the future.
@@ -31,28 +44,50 @@ pub struct Rhi {
 }
 
 impl Rhi {
+    /// List all the physical devices, so that a launcher can show the options before creating the rhi.
+    /// Presentation support is not checked here since there is no surface yet.
+    pub fn enumerate_devices() -> Vec<AdapterInfo> {
+        let instance = Instance::builder().build()
+            .expect("Failed to create vulkan instance!");
+
+        let adapters = physical_device::enumerate_physical_devices(&instance).iter()
+            .enumerate()
+            .map(|(idx, device)| device.adapter_info(idx))
+            .collect();
+
+        // this temporary instance is not used by anything else
+        unsafe { instance.raw.destroy_instance(None); }
+        adapters
+    }
+
     pub fn new(config: RhiConfig, window: &Window) -> anyhow::Result<Self> {
         let instance = Instance::builder().build()?;
         let surface = Arc::new(Surface::new(&instance, &window)?);
 
+        debug::set_break_on_validation_error(config.break_on_validation_error);
         let (_debug_util, _debug_messager) = debug::setup_debug_utils(
             config.enable_debug, 
             &instance.entry, 
             &instance.raw
         );
 
-        let physical_device = Arc::new(physical_device::pick_suitable_physical_device(&instance, &surface));
+        let physical_device = Arc::new(physical_device::pick_suitable_physical_device(&instance, &surface, &config.adapter, config.enable_debug));
         glog::trace!("Selected Physical Device: {:#?}", unsafe {
             std::ffi::CStr::from_ptr(physical_device.properties.device_name.as_ptr() as *const std::os::raw::c_char)
         });
 
         let device = Device::builder()
+            .enable_debug(config.enable_debug)
+            .enable_dynamic_rendering(config.enable_dynamic_rendering)
+            .frames_in_flight(config.frames_in_flight)
+            .memory_warning_threshold(config.memory_warning_threshold)
             .build(&physical_device)?;
 
         glog::trace!("Required swapchain extent: {:?}", config.swapchain_extent);
         let swapchain = Swapchain::builder()
             .extent(config.swapchain_extent)
             .enable_vsync(config.enable_vsync)
+            .hdr_output(config.hdr_output)
             .build(&device, &surface)?;
 
         Ok(Self {
@@ -64,6 +99,7 @@ impl Rhi {
 
 impl Drop for Rhi {
     fn drop(&mut self) {
+        self.device.flush_deferred_releases();
         self.device.release_debug_resources();
     }
 }
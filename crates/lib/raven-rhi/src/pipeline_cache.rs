@@ -13,7 +13,8 @@ use crate::{
         ComputePipelineDesc, ComputePipeline,
         ShaderBinary, PipelineShaderDesc, ShaderSource, ShaderBinaryStage,
         Device,
-        pipeline::{self, CommonPipelinePtrs}},
+        PipelineLayoutCache, PipelineLayoutCacheStats,
+        pipeline::{self, CommonPipelinePtrs, RasterPipelineFixedStates}},
     shader_compiler::{CompileShader, CompileShaderStage}
 };
 #[cfg(feature = "gpu_ray_tracing")]
@@ -80,7 +81,7 @@ pub struct PipelineCache {
     ray_tracing_pipelines_entry: HashMap<RayTracingPipelineHandle, RayTracingPipelineEntry>,
 
     /// for fast backward search
-    desc_to_raster_handle: HashMap<Vec<PipelineShaderDesc>, RasterPipelineHandle>,
+    desc_to_raster_handle: HashMap<(Vec<PipelineShaderDesc>, RasterPipelineFixedStates), RasterPipelineHandle>,
     desc_to_compute_handle: HashMap<ComputePipelineDesc, ComputePipelineHandle>,
     #[cfg(feature = "gpu_ray_tracing")]
     desc_to_ray_tracing_handle: HashMap<Vec<PipelineShaderDesc>, RayTracingPipelineHandle>,
@@ -91,6 +92,8 @@ pub struct PipelineCache {
     ray_tracing_pipeline_spirv_cache: HashMap<RayTracingPipelineHandle, Arc<Vec<ShaderBinaryStage>>>,
 
     defer_release_pipelines: [Vec<CommonPipelinePtrs>; backend::DEVICE_DRAW_FRAMES],
+    /// Pipeline layouts shared by all the pipelines with the same bindings
+    layout_cache: PipelineLayoutCache,
 }
 
 impl PipelineCache {
@@ -114,13 +117,16 @@ impl PipelineCache {
             ray_tracing_pipeline_spirv_cache: HashMap::new(),
 
             defer_release_pipelines: Default::default(),
+            layout_cache: PipelineLayoutCache::new(),
         }
     }
 
     // is the order of parameter 'shaders' matters? (i.e. will its order affect desc_to_raster_handle?)
     pub fn register_raster_pipeline(&mut self, shaders: &[PipelineShaderDesc], desc: &RasterPipelineDesc) -> RasterPipelineHandle {
         // found a cached pipeline, just return it.
-        if let Entry::Occupied(entry) = self.desc_to_raster_handle.entry(shaders.to_vec()) {
+        // pipelines with the same shaders but different fixed function states can NOT share the same handle.
+        let key = (shaders.to_vec(), desc.fixed_states_key());
+        if let Entry::Occupied(entry) = self.desc_to_raster_handle.entry(key.clone()) {
             return entry.get().clone()
         };
 
@@ -134,7 +140,7 @@ impl PipelineCache {
                 .build()
                 .into_lazy(),
         });
-        self.desc_to_raster_handle.insert(shaders.to_vec(), RasterPipelineHandle(idx));
+        self.desc_to_raster_handle.insert(key, RasterPipelineHandle(idx));
 
         RasterPipelineHandle(idx)
     }
@@ -227,8 +233,9 @@ impl PipelineCache {
         for (handle, cache) in self.raster_pipeline_spirv_cache.drain() {
             let raster_pipe_entry = self.raster_pipelines_entry.get_mut(&handle).unwrap();
 
-            let raster_pipe = pipeline::create_raster_pipeline(&device, raster_pipe_entry.desc.clone(), cache.as_slice())
+            let raster_pipe = pipeline::create_raster_pipeline(&device, &mut self.layout_cache, raster_pipe_entry.desc.clone(), cache.as_slice())
                 .expect(format!("Failed to create new raster pipeline for {}", handle).as_str());
+            device.set_debug_name(raster_pipe.pipeline_ptrs.pipeline, &format!("{:?}", cache[0].source));
 
             raster_pipe_entry.pipeline = Some(Arc::new(raster_pipe));
         }
@@ -236,8 +243,9 @@ impl PipelineCache {
         for (handle, cache) in self.compute_pipeline_spirv_cache.drain() {
             let compute_pipe_entry = self.compute_pipelines_entry.get_mut(&handle).unwrap();
             
-            let compute_pipe = pipeline::create_compute_pipeline(&device, compute_pipe_entry.desc.clone(), &cache)
+            let compute_pipe = pipeline::create_compute_pipeline(&device, &mut self.layout_cache, compute_pipe_entry.desc.clone(), &cache)
                 .expect(format!("Failed to create new compute pipeline for {}", handle).as_str());
+            device.set_debug_name(compute_pipe.pipeline_ptrs.pipeline, &format!("{:?}", compute_pipe_entry.desc.source));
             
             compute_pipe_entry.pipeline = Some(Arc::new(compute_pipe));
         }
@@ -246,8 +254,9 @@ impl PipelineCache {
         for (handle, cache) in self.ray_tracing_pipeline_spirv_cache.drain() {
             let ray_tracing_pipe_entry = self.ray_tracing_pipelines_entry.get_mut(&handle).unwrap();
             
-            let ray_tracing_pipe = pipeline::create_ray_tracing_pipeline(&device, ray_tracing_pipe_entry.desc.clone(), cache.as_slice())
+            let ray_tracing_pipe = pipeline::create_ray_tracing_pipeline(&device, &mut self.layout_cache, ray_tracing_pipe_entry.desc.clone(), cache.as_slice())
                 .expect(format!("Failed to create new ray tracing pipeline for {}", handle).as_str());
+            device.set_debug_name(ray_tracing_pipe.pipeline_ptrs.pipeline, &format!("{:?}", cache[0].source));
             
             ray_tracing_pipe_entry.pipeline = Some(Arc::new(ray_tracing_pipe));
         }
@@ -258,7 +267,7 @@ impl PipelineCache {
 
         // destroy last frame's stale pipelines
         for stale_pipelines in self.defer_release_pipelines[device_frame_idx].drain(..) {
-            pipeline::destroy_common_pipeline_ptrs(device, stale_pipelines);
+            pipeline::destroy_common_pipeline_ptrs(device, &mut self.layout_cache, stale_pipelines);
         }
 
         // insert this frame's stale pipelines
@@ -418,13 +427,25 @@ impl PipelineCache {
         Ok(())
     }
 
+    /// Hit and miss counts of the shared pipeline layouts.
+    pub fn layout_cache_stats(&self) -> PipelineLayoutCacheStats {
+        self.layout_cache.stats()
+    }
+
     /// Clean all the pipelines.
-    pub fn clean(self, device: &Device) {
+    pub fn clean(mut self, device: &Device) {
+        let mut layout_cache = std::mem::take(&mut self.layout_cache);
+
+        // stale pipelines waiting to be released
+        for stale_pipelines in self.defer_release_pipelines.iter_mut().flat_map(|pipelines| pipelines.drain(..)) {
+            pipeline::destroy_common_pipeline_ptrs(device, &mut layout_cache, stale_pipelines);
+        }
+
         for (_, entry) in self.raster_pipelines_entry {
             if let Some(pipe) = entry.pipeline {
                 // make sure no one is still using this pipeline
                 let pipe = Arc::try_unwrap(pipe).expect("User holding a smart pointer to some stale raster pipeline!");
-                pipeline::destroy_raster_pipeline(&device, pipe);
+                pipeline::destroy_raster_pipeline(&device, &mut layout_cache, pipe);
             }
         }
         
@@ -432,7 +453,7 @@ impl PipelineCache {
             if let Some(pipe) = entry.pipeline {
                 // make sure no one is still using this pipeline
                 let pipe = Arc::try_unwrap(pipe).expect("User holding a smart pointer to some stale compute pipeline!");
-                pipeline::destroy_compute_pipeline(&device, pipe);
+                pipeline::destroy_compute_pipeline(&device, &mut layout_cache, pipe);
             }
         }
 
@@ -441,9 +462,13 @@ impl PipelineCache {
             if let Some(pipe) = entry.pipeline {
                 // make sure no one is still using this pipeline
                 let pipe = Arc::try_unwrap(pipe).expect("User holding a smart pointer to some stale ray tracing pipeline!");
-                pipeline::destroy_ray_tracing_pipeline(&device, pipe);
+                pipeline::destroy_ray_tracing_pipeline(&device, &mut layout_cache, pipe);
             }
         }
+
+        let stats = layout_cache.stats();
+        glog::trace!("Pipeline layout cache: {} hits, {} misses", stats.hits, stats.misses);
+        layout_cache.clean(device);
     }
 }
 
@@ -1,5 +1,7 @@
 mod position;
 mod rotation;
+mod smoothing;
 
 pub use position::Position as CamCtrlPosition;
-pub use rotation::Rotation as CamCtrlRotation;
\ No newline at end of file
+pub use rotation::Rotation as CamCtrlRotation;
+pub use smoothing::Smoothing as CamCtrlSmoothing;
\ No newline at end of file
@@ -23,7 +23,7 @@ impl Position {
 }
 
 impl CameraControl for Position {
-    fn update(&self, prev_trans: CameraTransform) -> CameraTransform {
+    fn update(&mut self, prev_trans: CameraTransform, _dt: f32) -> CameraTransform {
         CameraTransform {
             position: self.position,
             rotation: prev_trans.rotation,
@@ -39,7 +39,7 @@ impl Rotation {
 }
 
 impl CameraControl for Rotation {
-    fn update(&self, prev_trans: CameraTransform) -> CameraTransform {
+    fn update(&mut self, prev_trans: CameraTransform, _dt: f32) -> CameraTransform {
         CameraTransform {
             position: prev_trans.position,
             rotation: self.rotation,
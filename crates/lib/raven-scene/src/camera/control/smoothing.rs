@@ -0,0 +1,61 @@
+use crate::camera::{CameraTransform, CameraControl};
+
+/// Exponentially move the camera toward the transform output by the previous controls.
+/// 
+/// The time constants (in seconds) control the responsiveness, it takes about a time constant
+/// to cover 63% of the distance to the target. A zero time constant disables the smoothing.
+pub struct Smoothing {
+    position_time_constant: f32,
+    rotation_time_constant: f32,
+    smoothed: Option<CameraTransform>,
+}
+
+impl Smoothing {
+    pub fn new(position_time_constant: f32, rotation_time_constant: f32) -> Self {
+        Self {
+            position_time_constant: position_time_constant.max(0.0),
+            rotation_time_constant: rotation_time_constant.max(0.0),
+            smoothed: None,
+        }
+    }
+
+    pub fn set_time_constants(&mut self, position_time_constant: f32, rotation_time_constant: f32) {
+        self.position_time_constant = position_time_constant.max(0.0);
+        self.rotation_time_constant = rotation_time_constant.max(0.0);
+    }
+
+    /// Snap to the target transform on the next update (e.g. after teleporting the camera).
+    pub fn reset(&mut self) {
+        self.smoothed = None;
+    }
+
+    /// Interpolation factor toward the target in this frame, which is independent of the frame rate.
+    /// Return None if the smoothing is disabled.
+    fn blend_factor(time_constant: f32, dt: f32) -> Option<f32> {
+        (time_constant > 0.0).then(|| 1.0 - (-dt.max(0.0) / time_constant).exp())
+    }
+}
+
+impl CameraControl for Smoothing {
+    fn update(&mut self, prev_trans: CameraTransform, dt: f32) -> CameraTransform {
+        let smoothed = match self.smoothed {
+            Some(smoothed) => {
+                let position_t = Self::blend_factor(self.position_time_constant, dt);
+                let rotation_t = Self::blend_factor(self.rotation_time_constant, dt);
+
+                CameraTransform {
+                    position: position_t.map_or(prev_trans.position, |t| smoothed.position.lerp(prev_trans.position, t)),
+                    rotation: rotation_t.map_or(prev_trans.rotation, |t| smoothed.rotation.slerp(prev_trans.rotation, t).normalize()),
+                }
+            }
+            None => prev_trans,
+        };
+
+        self.smoothed = Some(smoothed);
+        smoothed
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
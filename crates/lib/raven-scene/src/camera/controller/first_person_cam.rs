@@ -1,7 +1,7 @@
 use raven_math::{Vec3, Quat, Vec2, Mat3};
 
 use crate::camera::{CameraController, CameraControllerBuilder, Camera};
-use super::super::control::{CamCtrlPosition, CamCtrlRotation};
+use super::super::control::{CamCtrlPosition, CamCtrlRotation, CamCtrlSmoothing};
 
 /// This is just a simple wrapper on CameraController.
 /// Provide user some common camera controller behaviors.
@@ -43,14 +43,31 @@ impl FirstPersonController {
         }
     }
 
+    /// Smooth the camera movement, see [`CamCtrlSmoothing`] for the time constants.
+    pub fn with_smoothing(mut self, position_time_constant: f32, rotation_time_constant: f32) -> Self {
+        self.controller.add_control(CamCtrlSmoothing::new(position_time_constant, rotation_time_constant));
+        self
+    }
+
+    /// Move the camera to the transform immediately, without smoothing (e.g. restoring a saved camera).
+    pub fn teleport_to(&mut self, camera: &mut Camera, pos: Vec3, rotation: Quat) {
+        self.controller.get_control_mut::<CamCtrlPosition>().move_to(pos);
+        self.controller.get_control_mut::<CamCtrlRotation>().rotate_to(rotation);
+        if let Some(smoothing) = self.controller.try_get_control_mut::<CamCtrlSmoothing>() {
+            smoothing.reset();
+        }
+        self.controller.update(camera, 0.0);
+    }
+
     pub fn change_to(&mut self, controller: impl Into<CameraController>) {
         self.controller = controller.into();
     }
 
     // TODO: consider using event system or callback function?
+    #[allow(clippy::too_many_arguments)]
     pub fn update(&mut self, 
         camera: &mut Camera, mouse_delta: Vec2, is_left_mouse_holding: bool,
-        input_walk: f32, input_strafe: f32, input_lift: f32
+        input_walk: f32, input_strafe: f32, input_lift: f32, dt: f32
     ) {
         let (mut right, mut up, mut forward) = self.controller.get_control_mut::<CamCtrlRotation>().to_coordinates();
 
@@ -91,6 +108,6 @@ impl FirstPersonController {
             input_walk   * self.move_speed * forward;
 
         self.controller.get_control_mut::<CamCtrlPosition>().move_by(delta_pos);
-        self.controller.update(camera);
+        self.controller.update(camera, dt);
     }
 }
\ No newline at end of file
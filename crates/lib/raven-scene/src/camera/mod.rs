@@ -3,9 +3,9 @@ pub mod controller;
 
 use std::any::Any;
 
-use raven_math::{Vec3, Quat, Mat4, Vec4};
+use raven_math::{Vec2, Vec3, Quat, Mat3, Mat4, Vec4};
 
-use raven_math::AABB;
+use raven_math::{AABB, Frustum};
 
 // TODO: move this to config
 const CAMERA_MAX_SHADOW_Z: f32 = 500.0;
@@ -64,6 +64,12 @@ pub struct CameraLens {
     pub near_plane: f32,
 }
 
+impl CameraLens {
+    /// Sane range of the vertical field of view, the projection degenerates when it approaches 0 or 180 degrees.
+    pub const MIN_FOV_VERTICAL_DEGREES: f32 = 1.0;
+    pub const MAX_FOV_VERTICAL_DEGREES: f32 = 170.0;
+}
+
 impl Default for CameraLens {
     fn default() -> Self {
         Self {
@@ -93,6 +99,31 @@ impl Camera {
         self.lens = lens;
     }
 
+    /// Reorient the camera to face the target, the position is unchanged.
+    /// If the target is at the camera position, the rotation is unchanged.
+    pub fn look_at(&mut self, target: Vec3, up: Vec3) {
+        if let Some(rotation) = look_at_rotation(target - self.body.position, up) {
+            self.body.rotation = rotation;
+        }
+    }
+
+    /// Position which fits the whole AABB in the view with the current rotation and lens, i.e. the camera is moved backwards from the AABB center.
+    /// The bounding sphere of the AABB is fitted, so it is conservative for any rotation.
+    pub fn get_framing_position(&self, aabb: &AABB) -> Vec3 {
+        let center = (aabb.min + aabb.max) * 0.5;
+        let radius = ((aabb.max - aabb.min) * 0.5).length();
+
+        // fit the sphere in the narrower one of the vertical and the horizontal field of view
+        let half_fov_vertical = self.lens.fov_vertical_degrees.to_radians() * 0.5;
+        let half_fov_horizontal = (half_fov_vertical.tan() * self.lens.aspect_ratio).atan();
+        let half_fov = half_fov_vertical.min(half_fov_horizontal);
+
+        let distance = (radius / half_fov.sin()).max(radius + self.lens.near_plane);
+        let forward = self.body.rotation * Vec3::NEG_Z;
+
+        center - forward * distance
+    }
+
     pub fn get_camera_render_data(&self) -> CameraFrameConstants {
         // rotation first, and then translation
         let view_to_world = {
@@ -126,9 +157,18 @@ impl Camera {
             view_to_world,
             view_to_clip,
             clip_to_view,
+            // no history in camera itself
+            prev_world_to_clip: view_to_clip * world_to_view,
+            jitter: Vec4::ZERO,
         }
     }
 
+    /// View frustum of the camera in world space (with the infinite far plane), e.g. for culling.
+    pub fn get_camera_frustum(&self) -> Frustum {
+        let render_data = self.get_camera_render_data();
+        Frustum::from_world_to_clip(render_data.view_to_clip * render_data.world_to_view)
+    }
+
     pub fn get_camera_frustum_aabb(&self) -> AABB {
         // TODO: cache matrix data
         let view_to_world = {
@@ -251,6 +291,26 @@ impl Camera {
     }
 }
 
+/// Compute the normalized rotation which turns the camera forward (-Z) to the direction.
+/// Return None if the direction is zero.
+fn look_at_rotation(dir: Vec3, up: Vec3) -> Option<Quat> {
+    let forward = dir.try_normalize()?;
+
+    // when looking along the up vector, the right vector can not be determined by the up vector,
+    // pick another axis which is not parallel to forward instead.
+    let up = match up.try_normalize() {
+        Some(up) if forward.dot(up).abs() < 0.9999 => up,
+        _ if forward.dot(Vec3::Z).abs() < 0.9999 => Vec3::Z,
+        _ => Vec3::X,
+    };
+
+    let right = forward.cross(up).normalize();
+    let up = right.cross(forward);
+
+    let rotation_mat = Mat3::from_cols(right, up, -forward);
+    Some(Quat::from_mat3(&rotation_mat).normalize())
+}
+
 #[derive(Default)]
 pub struct CameraBuilder {
     body: CameraBody,
@@ -275,6 +335,16 @@ impl CameraBuilder {
         self
     }
 
+    /// Place the camera at eye and make it face the target.
+    /// If the target is at eye, the rotation is unchanged.
+    pub fn look_at(mut self, eye: Vec3, target: Vec3, up: Vec3) -> Self {
+        self.body.position = eye;
+        if let Some(rotation) = look_at_rotation(target - eye, up) {
+            self.body.rotation = rotation;
+        }
+        self
+    }
+
     pub fn aspect_ratio(mut self, ar: f32) -> Self {
         self.lens.aspect_ratio = ar;
         self
@@ -299,7 +369,9 @@ impl CameraBuilder {
 }
 
 pub trait CameraControl: Any {
-    fn update(&self, prev_trans: CameraTransform) -> CameraTransform;
+    /// Compute the new transform from the transform output by the previous control.
+    /// dt is the delta time of this frame in seconds.
+    fn update(&mut self, prev_trans: CameraTransform, dt: f32) -> CameraTransform;
 
     fn as_any_mut(&mut self) -> &mut dyn Any;
 }
@@ -315,6 +387,31 @@ pub struct CameraFrameConstants {
     pub view_to_world: Mat4,
     pub view_to_clip: Mat4,
     pub clip_to_view: Mat4,
+    /// Last frame's world_to_clip without jitter, used to reproject into the last frame.
+    pub prev_world_to_clip: Mat4,
+    /// xy: this frame's sub-pixel jitter in clip space (already applied in view_to_clip), zw: last frame's jitter.
+    pub jitter: Vec4,
+}
+
+impl CameraFrameConstants {
+    /// world_to_clip without jitter.
+    pub fn unjittered_world_to_clip(&self) -> Mat4 {
+        let unjitter = Mat4::from_translation(Vec3::new(-self.jitter.x, -self.jitter.y, 0.0));
+        unjitter * self.view_to_clip * self.world_to_view
+    }
+
+    /// Offset the projection by a sub-pixel jitter in clip space (e.g. for TAA).
+    /// 
+    /// Jitter is not accumulated, calling it again replaces the old jitter.
+    pub fn with_jitter(mut self, jitter: Vec2) -> Self {
+        let delta = jitter - Vec2::new(self.jitter.x, self.jitter.y);
+
+        self.view_to_clip = Mat4::from_translation(delta.extend(0.0)) * self.view_to_clip;
+        self.clip_to_view *= Mat4::from_translation(-delta.extend(0.0));
+        self.jitter.x = jitter.x;
+        self.jitter.y = jitter.y;
+        self
+    }
 }
 
 impl CameraController {
@@ -323,16 +420,24 @@ impl CameraController {
     }
 
     pub fn get_control_mut<T: CameraControl>(&mut self) -> &mut T {
+        self.try_get_control_mut::<T>()
+            .unwrap_or_else(|| panic!("No camera control {} is found", std::any::type_name::<T>()))
+    }
+
+    pub fn try_get_control_mut<T: CameraControl>(&mut self) -> Option<&mut T> {
         self.controls.iter_mut()
             .find_map(|control| { control.as_any_mut().downcast_mut::<T>() })
-            .unwrap_or_else(|| panic!("No camera control {} is found", std::any::type_name::<T>()))
     }
 
-    pub fn update(&mut self, camera: &mut Camera) {
+    pub fn add_control(&mut self, control: impl CameraControl) {
+        self.controls.push(Box::new(control));
+    }
+
+    pub fn update(&mut self, camera: &mut Camera, dt: f32) {
         let mut prev_transform = camera.body.into();
 
         for control in self.controls.iter_mut() {
-            let new_transform = control.update(prev_transform);
+            let new_transform = control.update(prev_transform, dt);
 
             prev_transform = new_transform;
         }
@@ -340,9 +445,9 @@ impl CameraController {
         camera.body = prev_transform.into();
     }
 
-    pub fn update_batch<'a>(&'a mut self, iter: impl Iterator<Item = &'a mut Camera>) {
+    pub fn update_batch<'a>(&'a mut self, iter: impl Iterator<Item = &'a mut Camera>, dt: f32) {
         for cam in iter {
-            self.update(cam);
+            self.update(cam, dt);
         }
     }
 }
@@ -1,3 +1,5 @@
+extern crate log as glog; // to avoid name collision with my log module
+
 pub mod camera;
 
 pub mod persistence;
\ No newline at end of file
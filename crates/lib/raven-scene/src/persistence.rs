@@ -1,4 +1,11 @@
-use raven_math::{Vec3, Quat};
+use std::path::Path;
+
+use anyhow::Context;
+use serde::de::DeserializeSeed;
+
+use raven_math::{Vec3, Quat, Affine3A};
+use raven_reflect::{Reflect, FromReflect, ReflectDefault, ReflectSerializer, UntypedReflectDeserializer};
+use raven_reflect::type_registry::TypeRegistry;
 
 pub trait IsStatesChanged {
     fn is_states_changed(&self, _: &Self) -> bool {
@@ -6,7 +13,8 @@ pub trait IsStatesChanged {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Reflect, FromReflect)]
+#[reflect(Default)]
 pub struct CameraPersistState {
     pub position: Vec3,
     pub rotation: Quat,
@@ -28,21 +36,115 @@ impl Default for CameraPersistState {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Reflect, FromReflect)]
+#[reflect(Default)]
+pub struct DirectionalLightPersistState {
+    /// Rotation from -Z to the direction pointing to the light.
+    pub direction: Quat,
+    pub color: Vec3,
+    /// In lux if is_lux, otherwise the relative intensity.
+    pub intensity: f32,
+    /// The states saved before the unit was persisted are all relative.
+    #[reflect(default)]
+    pub is_lux: bool,
+}
+
+impl IsStatesChanged for DirectionalLightPersistState {
+    fn is_states_changed(&self, other: &Self) -> bool {
+        !self.direction.abs_diff_eq(other.direction, 1e-5) ||
+        !self.color.abs_diff_eq(other.color, 1e-5) ||
+        (self.intensity - other.intensity).abs() > 1e-5 ||
+        self.is_lux != other.is_lux
+    }
+}
+
+impl Default for DirectionalLightPersistState {
+    fn default() -> Self {
+        Self {
+            direction: Quat::IDENTITY,
+            color: Vec3::ONE,
+            intensity: 1.0,
+            is_lux: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Reflect, FromReflect)]
+#[reflect(Default)]
 pub struct PersistStates {
     pub camera: CameraPersistState,
+    /// Transforms of the mesh instances, indexed by the order they are added.
+    pub mesh_instances: Vec<Affine3A>,
+    /// Directional lights, indexed by the order they are added.
+    pub directional_lights: Vec<DirectionalLightPersistState>,
 }
 
 impl PersistStates {
     pub fn new() -> Self {
-        Self {
-            camera: Default::default(),
+        Self::default()
+    }
+
+    fn type_registry() -> TypeRegistry {
+        let mut registry = TypeRegistry::new();
+        registry.register::<CameraPersistState>();
+        registry.register::<DirectionalLightPersistState>();
+        registry.register::<Vec<Affine3A>>();
+        registry.register::<Vec<DirectionalLightPersistState>>();
+        registry.register::<PersistStates>();
+        registry
+    }
+
+    /// Serialize the states into a ron string.
+    pub fn to_ron(&self) -> anyhow::Result<String> {
+        let registry = Self::type_registry();
+        let serializer = ReflectSerializer::new(self, &registry);
+
+        Ok(ron::ser::to_string_pretty(&serializer, ron::ser::PrettyConfig::default())?)
+    }
+
+    /// Deserialize the states from a ron string serialized by to_ron().
+    pub fn from_ron(source: &str) -> anyhow::Result<Self> {
+        let registry = Self::type_registry();
+        let mut deserializer = ron::de::Deserializer::from_str(source)?;
+        let reflected = UntypedReflectDeserializer::new(&registry).deserialize(&mut deserializer)?;
+
+        Self::from_reflect(&*reflected)
+            .context("Serialized value is not PersistStates!")
+    }
+
+    /// Save the states to a ron file.
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        std::fs::write(path, self.to_ron()?)
+            .with_context(|| format!("Failed to write persist states to {}!", path.display()))
+    }
+
+    /// Load the states from a ron file.
+    /// Return None with a warning if the file is missing or corrupted, so that the caller can start fresh.
+    pub fn load(path: impl AsRef<Path>) -> Option<Self> {
+        let path = path.as_ref();
+        let states = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read persist states from {}!", path.display()))
+            .and_then(|source| Self::from_ron(&source));
+
+        match states {
+            Ok(states) => Some(states),
+            Err(err) => {
+                glog::warn!("{:?}, start fresh.", err);
+                None
+            }
         }
     }
 }
 
 impl IsStatesChanged for PersistStates {
     fn is_states_changed(&self, other: &Self) -> bool {
-        self.camera.is_states_changed(&other.camera)
+        self.camera.is_states_changed(&other.camera) ||
+        self.mesh_instances.len() != other.mesh_instances.len() ||
+        self.mesh_instances.iter().zip(other.mesh_instances.iter())
+            .any(|(a, b)| !a.abs_diff_eq(*b, 1e-5)) ||
+        self.directional_lights.len() != other.directional_lights.len() ||
+        self.directional_lights.iter().zip(other.directional_lights.iter())
+            .any(|(a, b)| a.is_states_changed(b))
     }
-}
\ No newline at end of file
+}
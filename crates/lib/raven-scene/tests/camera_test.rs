@@ -0,0 +1,142 @@
+use raven_math::{Vec2, Vec3, Vec4, Quat, AABB};
+use raven_scene::camera::{Camera, CameraController, control::{CamCtrlPosition, CamCtrlSmoothing}};
+
+fn forward_of(rotation: Quat) -> Vec3 {
+    rotation * Vec3::NEG_Z
+}
+
+#[test]
+fn test_camera_builder_look_at() {
+    let eye = Vec3::new(0.0, 2.0, 5.0);
+    let target = Vec3::new(1.0, 0.0, -3.0);
+
+    let camera = Camera::builder()
+        .look_at(eye, target, Vec3::Y)
+        .build();
+
+    assert!(camera.body.position.abs_diff_eq(eye, 1e-5));
+    assert!(camera.body.rotation.is_normalized());
+    assert!(forward_of(camera.body.rotation).abs_diff_eq((target - eye).normalize(), 1e-5));
+
+    // camera should stay upright
+    let up = camera.body.rotation * Vec3::Y;
+    assert!(up.dot(Vec3::Y) > 0.0);
+    let right = camera.body.rotation * Vec3::X;
+    assert!(right.dot(Vec3::Y).abs() < 1e-5);
+}
+
+#[test]
+fn test_camera_look_at_degenerate() {
+    let mut camera = Camera::builder()
+        .position(Vec3::new(0.0, 10.0, 0.0))
+        .build();
+
+    // looking straight down along the up vector
+    camera.look_at(Vec3::ZERO, Vec3::Y);
+    assert!(camera.body.rotation.is_normalized());
+    assert!(forward_of(camera.body.rotation).abs_diff_eq(Vec3::NEG_Y, 1e-5));
+
+    // looking at itself keeps the previous rotation
+    let prev_rotation = camera.body.rotation;
+    camera.look_at(camera.body.position, Vec3::Y);
+    assert!(camera.body.rotation.abs_diff_eq(prev_rotation, 1e-6));
+}
+
+#[test]
+fn test_camera_smoothing() {
+    let mut camera = Camera::builder().build();
+    let mut controller = CameraController::builder()
+        .with(CamCtrlPosition::new())
+        .with(CamCtrlSmoothing::new(0.1, 0.1))
+        .build();
+
+    // the first update snaps to the target
+    controller.update(&mut camera, 1.0 / 60.0);
+    assert!(camera.body.position.abs_diff_eq(Vec3::ZERO, 1e-6));
+
+    controller.get_control_mut::<CamCtrlPosition>().move_to(Vec3::new(10.0, 0.0, 0.0));
+    controller.update(&mut camera, 0.1);
+    // one time constant covers about 63% of the distance
+    assert!((camera.body.position.x - 6.3212).abs() < 1e-3);
+
+    for _ in 0..100 {
+        controller.update(&mut camera, 0.1);
+    }
+    assert!(camera.body.position.abs_diff_eq(Vec3::new(10.0, 0.0, 0.0), 1e-3));
+}
+
+#[test]
+fn test_camera_zero_smoothing() {
+    let mut camera = Camera::builder().build();
+    let mut controller = CameraController::builder()
+        .with(CamCtrlPosition::new())
+        .with(CamCtrlSmoothing::new(0.0, 0.0))
+        .build();
+
+    controller.update(&mut camera, 1.0 / 60.0);
+    controller.get_control_mut::<CamCtrlPosition>().move_to(Vec3::new(0.0, 3.0, 0.0));
+    controller.update(&mut camera, 1.0 / 60.0);
+    assert_eq!(camera.body.position, Vec3::new(0.0, 3.0, 0.0));
+}
+
+#[test]
+fn test_camera_render_data_jitter() {
+    let camera = Camera::builder()
+        .position(Vec3::new(1.0, 2.0, 3.0))
+        .build();
+
+    let data = camera.get_camera_render_data();
+    let jitter = Vec2::new(0.002, -0.001);
+    let jittered = data.with_jitter(jitter);
+
+    // jitter only shifts the clip space xy
+    let pos_ws = Vec4::new(0.5, 1.0, -4.0, 1.0);
+    let clip = data.view_to_clip * data.world_to_view * pos_ws;
+    let jittered_clip = jittered.view_to_clip * jittered.world_to_view * pos_ws;
+    let offset = Vec2::new(jittered_clip.x / jittered_clip.w - clip.x / clip.w, jittered_clip.y / jittered_clip.w - clip.y / clip.w);
+    assert!(offset.abs_diff_eq(jitter, 1e-5));
+
+    // inverse still matches
+    let view = jittered.clip_to_view * jittered.view_to_clip;
+    assert!(view.abs_diff_eq(raven_math::Mat4::IDENTITY, 1e-4));
+
+    assert!(jittered.unjittered_world_to_clip().abs_diff_eq(data.unjittered_world_to_clip(), 1e-5));
+
+    // jitter is replaced, not accumulated
+    let rejittered = jittered.with_jitter(Vec2::ZERO);
+    assert!(rejittered.view_to_clip.abs_diff_eq(data.view_to_clip, 1e-5));
+}
+
+#[test]
+fn test_camera_framing_position() {
+    let aabb = AABB::from_min_max(Vec3::new(-1.0, 0.0, -3.0), Vec3::new(3.0, 2.0, 1.0));
+
+    for aspect_ratio in [0.5, 1.0, 16.0 / 9.0] {
+        let mut camera = Camera::builder()
+            .look_at(Vec3::ZERO, Vec3::new(1.0, -0.5, -1.0), Vec3::Y)
+            .aspect_ratio(aspect_ratio)
+            .build();
+        let rotation = camera.body.rotation;
+
+        camera.body.position = camera.get_framing_position(&aabb);
+        // only moved, and still looking at the center
+        assert_eq!(camera.body.rotation, rotation);
+        let to_center = (Vec3::new(1.0, 1.0, -1.0) - camera.body.position).normalize();
+        assert!(forward_of(rotation).abs_diff_eq(to_center, 1e-5));
+
+        // all the corners are in front of the camera and inside the view
+        let world_to_clip = camera.get_camera_render_data().unjittered_world_to_clip();
+        for i in 0..8 {
+            let corner = Vec3::new(
+                if i & 1 == 0 { aabb.min.x } else { aabb.max.x },
+                if i & 2 == 0 { aabb.min.y } else { aabb.max.y },
+                if i & 4 == 0 { aabb.min.z } else { aabb.max.z },
+            );
+            let pos_cs = world_to_clip * corner.extend(1.0);
+            assert!(pos_cs.w > 0.0);
+
+            let ndc = pos_cs.truncate() / pos_cs.w;
+            assert!(ndc.x.abs() <= 1.0 && ndc.y.abs() <= 1.0, "corner {} is outside of the view {}", corner, ndc);
+        }
+    }
+}
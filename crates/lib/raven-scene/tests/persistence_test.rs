@@ -0,0 +1,66 @@
+use raven_math::{Vec3, Quat, Affine3A};
+use raven_scene::persistence::{PersistStates, DirectionalLightPersistState, IsStatesChanged};
+
+fn test_states() -> PersistStates {
+    let mut states = PersistStates::new();
+    states.camera.position = Vec3::new(1.0, 2.0, 3.0);
+    states.camera.rotation = Quat::from_rotation_y(0.5);
+    states.mesh_instances = vec![
+        Affine3A::IDENTITY,
+        Affine3A::from_scale_rotation_translation(Vec3::splat(2.0), Quat::from_rotation_x(1.0), Vec3::new(-4.0, 0.0, 8.0)),
+    ];
+    states.directional_lights = vec![DirectionalLightPersistState {
+        direction: Quat::from_rotation_z(0.25),
+        color: Vec3::new(1.0, 0.9, 0.8),
+        intensity: 5.0,
+        is_lux: true,
+    }];
+    states
+}
+
+#[test]
+fn test_persist_states_ron_round_trip() {
+    let states = test_states();
+
+    let ron = states.to_ron().unwrap();
+    let loaded = PersistStates::from_ron(&ron).unwrap();
+
+    assert!(!loaded.is_states_changed(&states));
+    assert_eq!(loaded.mesh_instances.len(), 2);
+    assert_eq!(loaded.directional_lights.len(), 1);
+}
+
+#[test]
+fn test_persist_states_light_unit() {
+    let states = test_states();
+    let ron = states.to_ron().unwrap();
+    assert!(PersistStates::from_ron(&ron).unwrap().directional_lights[0].is_lux);
+
+    // the lights saved before the unit was persisted are relative, not lux
+    let old_ron = ron.replace("is_lux: true,", "");
+    assert_ne!(old_ron, ron);
+    let loaded = PersistStates::from_ron(&old_ron).unwrap();
+    assert!(!loaded.directional_lights[0].is_lux);
+    assert_eq!(loaded.directional_lights[0].intensity, 5.0);
+}
+
+#[test]
+fn test_persist_states_file() {
+    let dir = std::env::temp_dir().join(format!("raven_persist_test_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let path = dir.join("scene.ron");
+    let states = test_states();
+    states.save(&path).unwrap();
+    assert!(!PersistStates::load(&path).unwrap().is_states_changed(&states));
+
+    // missing file starts fresh
+    assert!(PersistStates::load(dir.join("missing.ron")).is_none());
+
+    // corrupted file starts fresh
+    let corrupted = dir.join("corrupted.ron");
+    std::fs::write(&corrupted, "(camera: oops").unwrap();
+    assert!(PersistStates::load(&corrupted).is_none());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
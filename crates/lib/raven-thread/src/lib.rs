@@ -1,4 +1,4 @@
 mod thread_pool;
 pub mod executor;
 
-pub use thread_pool::{ThreadPool, JobHandle};
\ No newline at end of file
+pub use thread_pool::{ThreadPool, JobHandle, WaitGroup, CancelToken};
\ No newline at end of file
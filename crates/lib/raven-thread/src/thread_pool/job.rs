@@ -3,43 +3,92 @@ use std::sync::Arc;
 
 pub type JobFunc = Box<dyn FnOnce() -> () + Send + 'static>;
 
+/// Flag to cancel a job.
+/// The cancellation is cooperative: a job which is not started yet will be skipped,
+/// but a running job will only stop if it polls is_cancelled() and bails out by itself.
+#[derive(Clone, Default)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
 pub struct Job {
     func: Option<JobFunc>,
     complete: Arc<AtomicBool>,
+    token: CancelToken,
 }
 
 unsafe impl Send for Job {}
 
 impl Job {
-    pub(super) fn new(func: JobFunc) -> Self {
+    pub(super) fn new(func: JobFunc, token: CancelToken) -> Self {
         Self {
             func: Some(func),
             complete: Arc::new(AtomicBool::new(false)),
+            token,
         }
     }
 
     /// Execute the task from current thread, after executing the complete flag will be marked.
+    /// A job cancelled before execution is skipped, but still marked as complete.
     pub(super) fn execute(&mut self) {
         // function call only be executed once.
         if let Some(func) = self.func.take() {
-            func();
+            if !self.token.is_cancelled() {
+                func();
+            }
         }
         self.complete.store(true, Ordering::Relaxed);
     }
 
     pub fn handle(&self) -> JobHandle {
-        JobHandle::new(self.complete.clone())
+        JobHandle::new(self.complete.clone(), self.token.clone())
     }
 }
 
 /// handle to check if a job is done.
 pub struct JobHandle {
     complete: Arc<AtomicBool>,
+    token: CancelToken,
 }
 
 impl JobHandle {
-    fn new(complete: Arc<AtomicBool>) -> Self {
-        Self { complete }
+    fn new(complete: Arc<AtomicBool>, token: CancelToken) -> Self {
+        Self { complete, token }
+    }
+
+    /// Request to cancel the job.
+    /// If the job is not started yet, it will be skipped. Otherwise it is up to the job to poll the cancel token.
+    /// A cancelled job is still marked as complete once the thread pool pulled it out.
+    #[inline]
+    pub fn cancel(&self) {
+        self.token.cancel();
+    }
+
+    #[inline]
+    pub fn is_cancelled(&self) -> bool {
+        self.token.is_cancelled()
+    }
+
+    /// Get the cancel token of this job (e.g. to cancel it from another place).
+    #[inline]
+    pub fn cancel_token(&self) -> CancelToken {
+        self.token.clone()
     }
 
     /// If the job is completed.
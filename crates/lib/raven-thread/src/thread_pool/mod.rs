@@ -1,5 +1,6 @@
 mod job;
 mod worker;
+mod wait_group;
 
 use std::{
     sync::atomic::AtomicBool,  
@@ -10,7 +11,9 @@ use crossbeam_deque::{Injector as GlobalQueue};
 use job::Job;
 use worker::Worker;
 
-pub use job::JobHandle;
+pub use job::{JobHandle, CancelToken};
+pub use wait_group::WaitGroup;
+use wait_group::WaitGroupGuard;
 
 pub struct ThreadPool {
     /// Shared by all the worker threads.
@@ -71,22 +74,76 @@ impl ThreadPool {
     pub fn add_job<F>(&self, f: F) -> JobHandle
     where
         F : FnOnce() -> () + Send + 'static,
+    {
+        self.add_cancelable_job(move |_| f())
+    }
+
+    /// Add a job which receives its cancel token, the job can poll the token and bail out early when it is cancelled by the job handle.
+    /// Cancellation is cooperative, a running job will not be interrupted.
+    pub fn add_cancelable_job<F>(&self, f: F) -> JobHandle
+    where
+        F : FnOnce(&CancelToken) + Send + 'static,
     {
         assert!(!self.workers.is_empty(), "No worker threads in this thread pool!");
 
-        let job = Job::new(Box::new(f));
+        let token = CancelToken::new();
+        let job_token = token.clone();
+        let job = Job::new(Box::new(move || f(&job_token)), token);
         let job_handle = job.handle();
         self.global_queue.push(job);
         job_handle
     }
 
+    /// Add a job which is counted by the wait group, the wait group is decremented once the job is done.
+    pub fn add_job_with_group<F>(&self, wait_group: &WaitGroup, f: F) -> JobHandle
+    where
+        F : FnOnce() + Send + 'static,
+    {
+        wait_group.add(1);
+
+        let guard = WaitGroupGuard(wait_group.clone());
+        self.add_job(move || {
+            let _guard = guard;
+            f();
+        })
+    }
+
+    /// Number of worker threads spawned by the thread pool.
+    #[inline]
+    pub fn worker_count(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// Number of jobs which are not started yet, including the jobs in the global queue and in the local queues of the workers.
+    /// The thread pool keeps running while counting, so it is only an approximation.
+    pub fn pending_job_count(&self) -> usize {
+        self.global_queue.len() + self.workers.iter().map(|w| w.pending_job_count()).sum::<usize>()
+    }
+
+    /// Number of jobs executed by the worker thread at worker_index.
+    /// Jobs executed by help_once() are not counted.
+    #[inline]
+    pub fn jobs_executed(&self, worker_index: usize) -> usize {
+        self.workers[worker_index].jobs_executed()
+    }
+
+    /// Is the worker thread at worker_index waiting for jobs (i.e. not executing any job).
+    #[inline]
+    pub fn is_worker_idle(&self, worker_index: usize) -> bool {
+        self.workers[worker_index].is_idle()
+    }
+
     /// Try pop one job from the thread pool and execute it in current thread.
     /// This can be useful to avoid some deadlock scenarios when some tasks are waiting other tasks to finish,
     /// Or can help mitigate the burden of the thread pool.
-    pub fn help_once(&mut self) {
+    /// Returns true if a job was executed.
+    pub fn help_once(&mut self) -> bool {
         // try pop task from global queue
         if let Some(ref mut task) = self.global_queue.steal().success() {
             task.execute();
+            true
+        } else {
+            false
         }
     }
 
@@ -0,0 +1,61 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use super::ThreadPool;
+
+/// Counter to wait for a batch of jobs without joining the job handles one by one.
+/// Increment it before dispatching the jobs, and each job decrements it on completion.
+/// Cloned wait groups share the same counter.
+#[derive(Clone, Default)]
+pub struct WaitGroup {
+    count: Arc<AtomicUsize>,
+}
+
+impl WaitGroup {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Increase the number of jobs to wait for.
+    #[inline]
+    pub fn add(&self, num_jobs: usize) {
+        self.count.fetch_add(num_jobs, Ordering::Relaxed);
+    }
+
+    /// Mark one job as done.
+    pub fn done(&self) {
+        // release the side effects of the job to the waiting thread
+        let prev = self.count.fetch_sub(1, Ordering::Release);
+        assert!(prev > 0, "WaitGroup::done() called more times than the jobs added!");
+    }
+
+    /// Number of jobs which are not done yet.
+    #[inline]
+    pub fn count(&self) -> usize {
+        self.count.load(Ordering::Acquire)
+    }
+
+    #[inline]
+    pub fn is_done(&self) -> bool {
+        self.count() == 0
+    }
+
+    /// Wait for all the jobs to be done.
+    /// It will block current thread, but current thread helps the thread pool to execute the pending jobs while waiting.
+    pub fn wait(&self, pool: &mut ThreadPool) {
+        while !self.is_done() {
+            if !pool.help_once() {
+                std::thread::yield_now();
+            }
+        }
+    }
+}
+
+/// Mark the job as done when dropped, so that a panicked job will not block the waiting thread forever.
+pub(super) struct WaitGroupGuard(pub(super) WaitGroup);
+
+impl Drop for WaitGroupGuard {
+    fn drop(&mut self) {
+        self.0.done();
+    }
+}
@@ -1,15 +1,25 @@
 use std::{
     thread,
     thread::JoinHandle,
-    sync::{Arc, atomic::{AtomicBool, Ordering}},
+    sync::{Arc, atomic::{AtomicBool, AtomicUsize, Ordering}},
 };
 use crossbeam_deque::{Worker as LocalQueue, Injector as GlobalQueue, Stealer};
 
 use super::Job;
 
+/// Counters of a worker thread, they are only used for introspection so all the accesses are relaxed.
+#[derive(Default)]
+struct WorkerStats {
+    /// Number of jobs this worker thread had executed.
+    jobs_executed: AtomicUsize,
+    /// Is this worker thread executing a job now.
+    is_executing: AtomicBool,
+}
+
 pub struct Worker {
     /// Atomic flag to check if this worker thread had finished its jobs.
     is_finish: Arc<AtomicBool>,
+    stats: Arc<WorkerStats>,
     /// Join handle of this worker thread.
     handle: Option<JoinHandle<()>>,
     /// Coworkers which can steal jobs from.
@@ -27,6 +37,7 @@ impl Worker {
 
         Self {
             is_finish: Arc::new(AtomicBool::new(false)),
+            stats: Arc::new(WorkerStats::default()),
             handle: None,
             stealer: Some(local_queue.stealer()),
             local_queue: Some(local_queue),
@@ -42,6 +53,7 @@ impl Worker {
         let global_queue = self.global_queue.clone();
         let thread_name = self.id.to_owned();
         let finish = self.is_finish.clone();
+        let stats = self.stats.clone();
 
         // spawn thread and store the thread handle
         self.handle = Some(thread::Builder::new()
@@ -72,7 +84,10 @@ impl Worker {
                             had_sent_finished = false;
                         }
 
-                        job.execute()
+                        stats.is_executing.store(true, Ordering::Relaxed);
+                        job.execute();
+                        stats.jobs_executed.fetch_add(1, Ordering::Relaxed);
+                        stats.is_executing.store(false, Ordering::Relaxed);
                     } else {
                         if !had_sent_finished {
                             finish.fetch_or(true, Ordering::Release);
@@ -111,6 +126,24 @@ impl Worker {
         self.is_finish.load(Ordering::Acquire)
     }
 
+    /// Number of jobs this worker thread had executed.
+    #[inline]
+    pub fn jobs_executed(&self) -> usize {
+        self.stats.jobs_executed.load(Ordering::Relaxed)
+    }
+
+    /// Is this worker thread waiting for jobs (i.e. not executing any job).
+    #[inline]
+    pub fn is_idle(&self) -> bool {
+        !self.stats.is_executing.load(Ordering::Relaxed)
+    }
+
+    /// Number of jobs waiting in the local queue of this worker thread.
+    #[inline]
+    pub fn pending_job_count(&self) -> usize {
+        self.stealer.as_ref().map_or(0, |stealer| stealer.len())
+    }
+
     /// Get the name of this worker thread.
     #[inline]
     pub fn name(&self) -> &str {
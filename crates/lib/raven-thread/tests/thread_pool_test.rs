@@ -1,4 +1,6 @@
-use raven_thread::ThreadPool;
+use std::sync::{Arc, atomic::{AtomicBool, AtomicUsize, Ordering}};
+
+use raven_thread::{ThreadPool, WaitGroup};
 
 #[test]
 fn thread_pool_works() {
@@ -25,4 +27,111 @@ fn thread_pool_works() {
     for job in jobs {
         assert_eq!(job.is_complete(), true);
     }
-}
\ No newline at end of file
+}
+
+#[test]
+fn thread_pool_metrics() {
+    let mut pool = ThreadPool::new(1);
+    assert_eq!(pool.worker_count(), 0);
+    pool.spawn_workers();
+    assert_eq!(pool.worker_count(), 1);
+
+    let jobs: Vec<_> = (0..20).map(|_| pool.add_job(|| {})).collect();
+    for job in &jobs {
+        job.wait();
+    }
+
+    // the counter is bumped right after the job is marked as complete
+    while pool.jobs_executed(0) < jobs.len() || !pool.is_worker_idle(0) {
+        std::thread::yield_now();
+    }
+
+    assert_eq!(pool.jobs_executed(0), jobs.len());
+    assert_eq!(pool.pending_job_count(), 0);
+
+    pool.terminate_block();
+}
+
+#[test]
+fn wait_group_works() {
+    let mut pool = ThreadPool::new(1);
+    pool.spawn_workers();
+
+    let wait_group = WaitGroup::new();
+    let counter = Arc::new(AtomicUsize::new(0));
+
+    let num_jobs = 1000;
+    for _ in 0..num_jobs {
+        let counter = counter.clone();
+        pool.add_job_with_group(&wait_group, move || {
+            counter.fetch_add(1, Ordering::Relaxed);
+        });
+    }
+
+    wait_group.wait(&mut pool);
+
+    assert!(wait_group.is_done());
+    assert_eq!(counter.load(Ordering::Relaxed), num_jobs);
+
+    pool.terminate_block();
+}
+
+#[test]
+fn cancel_pending_job() {
+    let mut pool = ThreadPool::new(1);
+    pool.spawn_workers();
+
+    // keep the only worker busy, so that the next job stays in the queue
+    let release = Arc::new(AtomicBool::new(false));
+    let blocker = {
+        let release = release.clone();
+        pool.add_job(move || {
+            while !release.load(Ordering::Relaxed) {
+                std::thread::yield_now();
+            }
+        })
+    };
+
+    let executed = Arc::new(AtomicBool::new(false));
+    let job = {
+        let executed = executed.clone();
+        pool.add_job(move || executed.store(true, Ordering::Relaxed))
+    };
+    job.cancel();
+    release.store(true, Ordering::Relaxed);
+
+    blocker.wait();
+    job.wait();
+
+    assert!(job.is_cancelled());
+    assert!(!executed.load(Ordering::Relaxed));
+
+    pool.terminate_block();
+}
+
+#[test]
+fn cancel_running_job() {
+    let mut pool = ThreadPool::new(1);
+    pool.spawn_workers();
+
+    let started = Arc::new(AtomicBool::new(false));
+    let job = {
+        let started = started.clone();
+        pool.add_cancelable_job(move |token| {
+            started.store(true, Ordering::Relaxed);
+            while !token.is_cancelled() {
+                std::thread::yield_now();
+            }
+        })
+    };
+
+    while !started.load(Ordering::Relaxed) {
+        std::thread::yield_now();
+    }
+    job.cancel();
+    job.wait();
+
+    assert!(job.is_complete());
+
+    pool.terminate_block();
+}
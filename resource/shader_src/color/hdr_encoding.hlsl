@@ -0,0 +1,66 @@
+#ifndef _HDR_ENCODING_HLSL_
+#define _HDR_ENCODING_HLSL_
+
+// must match raven_rhi::backend::SwapchainEncoding
+#define OUTPUT_ENCODING_SDR   0
+#define OUTPUT_ENCODING_HDR10 1
+#define OUTPUT_ENCODING_SCRGB 2
+
+// nits of 1.0 in scRGB
+#define SCRGB_REFERENCE_WHITE_NITS 80.0
+
+float3 rec709_to_rec2020(float3 color)
+{
+    static const float3x3 mat = float3x3(
+        0.627402, 0.329292, 0.043306,
+        0.069095, 0.919544, 0.011360,
+        0.016394, 0.088028, 0.895578
+    );
+    return mul(mat, color);
+}
+
+// SMPTE ST.2084 inverse EOTF, the input is normalized to 10000 nits.
+float3 linear_to_pq(float3 color)
+{
+    const float m1 = 2610.0 / 16384.0;
+    const float m2 = 2523.0 / 32.0;
+    const float c1 = 3424.0 / 4096.0;
+    const float c2 = 2413.0 / 128.0;
+    const float c3 = 2392.0 / 128.0;
+
+    float3 ym1 = pow(max(color, 0.0), m1);
+    return pow((c1 + c2 * ym1) / (1.0 + c3 * ym1), m2);
+}
+
+// Compress the luminance above the knee smoothly towards the max luminance of the display.
+float3 hdr_highlight_rolloff(float3 nits, float max_nits)
+{
+    const float knee = 0.75 * max_nits;
+    const float peak = max(nits.r, max(nits.g, nits.b));
+
+    if (peak <= knee)
+    {
+        return nits;
+    }
+
+    const float range = max_nits - knee;
+    const float compressed = knee + range * (1.0 - exp(-(peak - knee) / range));
+    return nits * (compressed / peak);
+}
+
+// Encode the linear Rec.709 color (1.0 is paper white) for the swapchain.
+float3 encode_hdr_output(float3 color, uint encoding, float paper_white_nits, float max_nits)
+{
+    float3 nits = hdr_highlight_rolloff(max(color, 0.0) * paper_white_nits, max_nits);
+
+    if (encoding == OUTPUT_ENCODING_HDR10)
+    {
+        return linear_to_pq(rec709_to_rec2020(nits) / 10000.0);
+    }
+    else
+    {
+        return nits / SCRGB_REFERENCE_WHITE_NITS;
+    }
+}
+
+#endif
@@ -2,19 +2,22 @@
 #define _FRAME_CONSTANTS_HLSL_
 
 #include "../light/light_commons.hlsl"
+#include "../math/math.hlsl"
 
 struct CameraFrameConstants {
     float4x4 world_to_view;
     float4x4 view_to_world;
     float4x4 view_to_clip;
     float4x4 clip_to_view;
+    float4x4 prev_world_to_clip; // last frame's world_to_clip without jitter
+    float4   jitter;             // xy: this frame's clip space jitter, zw: last frame's jitter
 };
 
 struct LightFrameConstants {
     float3 color;     // color in range [0.0, 1.0]
     uint   shadowed;  // it is a bool
     float3 direction; // direction vector
-    float  intensity;
+    float  intensity; // converted from the photometric units, see raven-render::light_units
 };
 
 // Same in raven-rg::executor::DrawFrameContext
@@ -31,8 +34,18 @@ struct FrameConstants {
     uint  pad0;
     uint  pad1;
     uint  pad2;
+
+    float4 sky_rotation; // quaternion (xyzw) rotating the sky space to the world space
+    float4 sky_radiance_scale; // rgb: tint * intensity of the visible sky
+    float4 ibl_radiance_scale; // rgb: tint * intensity of the image based lighting
 };
 
 [[vk::binding(0, 2)]] ConstantBuffer<FrameConstants> frame_constants_dyn;
 
+// Transform a world space direction into the sky space to do the environment lookup.
+float3 world_to_sky_dir(float3 dir_ws)
+{
+    return quat_rotate(quat_conjugate(frame_constants_dyn.sky_rotation), dir_ws);
+}
+
 #endif
\ No newline at end of file
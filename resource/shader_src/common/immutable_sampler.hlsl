@@ -5,5 +5,7 @@
 [[vk::binding(33)]] SamplerState sampler_llr;
 [[vk::binding(34)]] SamplerState sampler_nnce;
 [[vk::binding(35)]] SamplerState sampler_llce;
+// passes if the reference is greater than the texel, i.e. closer with reverse-z
+[[vk::binding(36)]] SamplerComparisonState sampler_llce_greater;
 
 #endif
\ No newline at end of file
@@ -7,11 +7,60 @@ struct Material {
     float roughness;
     float base_color[4];
     float emissive[3];
+    float emissive_intensity; // hdr multiplier of the emissive color
     uint  albedo_map;
     uint  normal_map;
     uint  specular_map;
     uint  emissive_map;
     float texture_transform[6 * 4];
+    uint  alpha_blend; // it is a bool
+    uint  unlit;       // it is a bool
+    float normal_strength;
+    uint  normal_flip_green; // it is a bool
 };
 
+// Decode the tangent space normal from the normal map texel, applying the strength and the green channel convention of the material.
+float3 decode_normal_ts(Material mat, float4 normal_texel)
+{
+    float2 normal_xy = normal_texel.xy * 2.0 - 1.0; // remap from [0, 1] to [-1, 1]
+    if (mat.normal_flip_green != 0)
+    {
+        normal_xy.y = -normal_xy.y;
+    }
+    normal_xy *= mat.normal_strength;
+
+    // normal in normal map is already normalized
+    return float3(normal_xy, sqrt(max(0.001, 1.0 - dot(normal_xy, normal_xy))));
+}
+
+// Same in the raven-render::renderer::mesh_renderer::GpuMaterialOverride
+struct InstanceMaterialOverride {
+    float4 base_color;
+    float  metalness;
+    float  roughness;
+    uint   is_enabled; // it is a bool
+    uint   _padding;
+    float4 tint;       // multiplied into the base color, white when the instance is not tinted
+};
+
+// Only the factors are replaced, the textures are still sampled and multiplied with them.
+// The instance tint is applied on top of either the override or the mesh material.
+void apply_material_override(inout Material mat, InstanceMaterialOverride mat_override)
+{
+    if (mat_override.is_enabled != 0)
+    {
+        mat.base_color[0] = mat_override.base_color.r;
+        mat.base_color[1] = mat_override.base_color.g;
+        mat.base_color[2] = mat_override.base_color.b;
+        mat.base_color[3] = mat_override.base_color.a;
+        mat.metalness = mat_override.metalness;
+        mat.roughness = mat_override.roughness;
+    }
+
+    mat.base_color[0] *= mat_override.tint.r;
+    mat.base_color[1] *= mat_override.tint.g;
+    mat.base_color[2] *= mat_override.tint.b;
+    mat.base_color[3] *= mat_override.tint.a;
+}
+
 #endif
\ No newline at end of file
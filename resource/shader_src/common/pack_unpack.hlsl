@@ -59,4 +59,32 @@ float2 unpack_2x16f_uint(uint value) {
 	);
 }
 
+// Shared exponent hdr color (same as VK_FORMAT_E5B9G9R9_UFLOAT_PACK32), the max value is 65408.0.
+uint pack_rgb9e5_uint(float3 color) {
+    static const float MAX_RGB9E5 = 65408.0;
+    color = clamp(color, 0.0, MAX_RGB9E5);
+
+    const float max_channel = max(max(color.r, color.g), color.b);
+    // exponent bias is 15 and the mantissa has 9 bits
+    int shared_exp = max(-16, int(floor(log2(max(max_channel, 1e-30))))) + 16;
+    float denom = exp2(float(shared_exp - 15 - 9));
+    // rounding may overflow the mantissa
+    if (uint(floor(max_channel / denom + 0.5)) == 512) {
+        shared_exp += 1;
+        denom *= 2.0;
+    }
+
+    const uint3 mantissa = uint3(floor(color / denom + 0.5));
+    return mantissa.r | (mantissa.g << 9) | (mantissa.b << 18) | (uint(shared_exp) << 27);
+}
+
+float3 unpack_rgb9e5_uint(uint packed) {
+    const float scale = exp2(float(int(packed >> 27) - 15 - 9));
+    return float3(
+        packed & 0x1ff,
+        (packed >> 9) & 0x1ff,
+        (packed >> 18) & 0x1ff
+    ) * scale;
+}
+
 #endif
\ No newline at end of file
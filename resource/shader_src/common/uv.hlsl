@@ -13,6 +13,12 @@ float2 uv_to_clip(float2 uv)
     return (uv - 0.5.xx) * float2(2.0, -2.0);
 }
 
+// vulkan only
+float2 clip_to_uv(float2 clip)
+{
+    return clip * float2(0.5, -0.5) + 0.5.xx;
+}
+
 // Convert a cubemap texel coordinate to the cubemap sample direction.
 float3 cube_to_world_dir(int3 coord, float2 cubemap_size)
 {
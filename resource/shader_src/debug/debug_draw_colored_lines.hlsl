@@ -0,0 +1,37 @@
+#include "../common/frame_constants.hlsl"
+#include "../common/bindless_resources.hlsl"
+
+// Same in raven-render::renderer::debug_renderer::DebugLineVertex
+struct DebugLineVertex {
+    float4 position; // w is unused
+    float4 color;    // w is unused
+};
+
+[[vk::binding(0)]] StructuredBuffer<DebugLineVertex> debug_line_vertices_dyn; // dynamic read-only storage buffer
+
+struct VsOut {
+	float4 out_position: SV_Position;
+    [[vk::location(0)]] float3 color: TEXCOORD0;
+};
+
+VsOut vs_main(uint vid: SV_VertexID)
+{
+    CameraFrameConstants cam = frame_constants_dyn.camera_constants;
+
+    DebugLineVertex vertex = debug_line_vertices_dyn[vid];
+
+    VsOut result;
+    result.out_position = mul(cam.view_to_clip, mul(cam.world_to_view, float4(vertex.position.xyz, 1.0)));
+    result.color = vertex.color.rgb;
+    return result;
+}
+
+struct PsOut {
+    float3 output: SV_TARGET0;
+};
+
+PsOut ps_main(VsOut ps_in) {
+    PsOut res;
+    res.output = ps_in.color;
+    return res;
+}
@@ -1,4 +1,5 @@
 #include "gbuffer.hlsl"
+#include "lighting_resolution.hlsl"
 #include "../math/constants.hlsl"
 #include "../math/math.hlsl"
 #include "../color/color_space.hlsl"
@@ -16,8 +17,17 @@
 struct {
     uint render_res_width;
     uint render_res_height;
+    uint is_cube_map_linear; // it is a bool, the procedural sky is linear while the cubemaps are in srgb
+    uint feature_flags;
+    // the lighting may run at a lower resolution than the gbuffer, see lighting_upsample.hlsl
+    uint lighting_res_width;
+    uint lighting_res_height;
 } push_constants;
 
+// Same in raven-render::world_renderer::prepare_rg_raster(), the resources of the disabled features are not bound.
+static const uint FEATURE_FLAG_SKY_BIT = (1 << 0);
+static const uint FEATURE_FLAG_IBL_BIT = (1 << 1);
+
 struct SHBuffer
 {
     float red_coeffs[9];
@@ -27,7 +37,7 @@ struct SHBuffer
 
 [[vk::binding(0)]] Texture2D<float4> gbuffer_tex;
 [[vk::binding(1)]] Texture2D<float> depth_tex;
-[[vk::binding(2)]] RWTexture2D<float4> output_tex;
+[[vk::binding(2)]] RWTexture2D<float4> output_tex; // in the lighting resolution
 [[vk::binding(3)]] Texture2D<float> light_map[MAX_DIRECTIONAL_LIGHT_COUNT];
 [[vk::binding(4)]] StructuredBuffer<row_major float4x4> light_map_transforms_dyn; // TODO: maybe move this to frame constants?
 [[vk::binding(5)]] TextureCube cube_map;
@@ -62,23 +72,30 @@ float is_shadowed(uint light_index, float3 position_ws)
     float shadowed = 1.0;
 
     // outside the depth range is all shadowed
-    if (shadow_coord.z >= 0.0 && shadow_coord.z <= 1.0)
+    if (shadow_coord.z >= 0.0 && shadow_coord.z <= 1.0 && shadow_coord.w > 0.0)
 	{
-		const float closest_depth = light_map[light_index].SampleLevel(sampler_lnce, shadow_coord.xy, 0.0).r;
-
-		if (shadow_coord.w > 0.0 && closest_depth < shadow_coord.z)
-		{
-			shadowed = 0.0;
-		}
+		// hardware PCF, with reverse-z the receiver is lit if it is closer (greater) than the occluder in the shadow map
+		const float lit = light_map[light_index].SampleCmpLevelZero(sampler_llce_greater, shadow_coord.xy, shadow_coord.z);
+		shadowed = 1.0 - lit;
 	}
 
     return shadowed;
 }
 
 [numthreads(8, 8, 1)]
-void main(in uint2 px: SV_DispatchThreadID)
+void main(in uint2 out_px: SV_DispatchThreadID)
 {
-    float2 resolution = float2(push_constants.render_res_width, push_constants.render_res_height);
+    const uint2 render_res = uint2(push_constants.render_res_width, push_constants.render_res_height);
+    const uint2 lighting_res = uint2(push_constants.lighting_res_width, push_constants.lighting_res_height);
+    if (any(out_px >= lighting_res))
+    {
+        return;
+    }
+
+    // the gbuffer pixel this output pixel is shaded at
+    const uint2 px = lighting_to_render_px(out_px, lighting_res, render_res);
+
+    float2 resolution = float2(render_res);
     float2 uv = pixel_to_uv(float2(px), resolution);
 
     CameraRayContext cam_ctx = CameraRayContext::from_screen_uv(uv);
@@ -87,10 +104,17 @@ void main(in uint2 px: SV_DispatchThreadID)
     // draw environment map on depth 0.0 (infinite far away)
     if (depth - 0.0 < FLOAT_EPSILON)
     {
+        if ((push_constants.feature_flags & FEATURE_FLAG_SKY_BIT) == 0)
+        {
+            output_tex[out_px] = float4(0.0, 0.0, 0.0, 1.0);
+            return;
+        }
+
         float3 direction = cam_ctx.get_direction_ws();
 
-        float4 pixel = cube_map.SampleLevel(sampler_llce, direction, 0.0);
-        output_tex[px] = float4(srgb_to_linear(pixel.rgb) * frame_constants_dyn.pre_exposure_mult, 1.0);
+        float4 pixel = cube_map.SampleLevel(sampler_llce, world_to_sky_dir(direction), 0.0);
+        const float3 radiance = push_constants.is_cube_map_linear ? pixel.rgb : srgb_to_linear(pixel.rgb);
+        output_tex[out_px] = float4(radiance * frame_constants_dyn.sky_radiance_scale.rgb * frame_constants_dyn.pre_exposure_mult, 1.0);
         return;
     }
 
@@ -103,6 +127,13 @@ void main(in uint2 px: SV_DispatchThreadID)
 
     GBuffer gbuffer = PackedGBuffer::from_uint4(asuint(gbuffer_tex[px])).unpack();
 
+    // unlit surfaces are neither lit nor shadowed
+    if ((gbuffer.flags & GBUFFER_FLAG_UNLIT) != 0)
+    {
+        output_tex[out_px] = float4((gbuffer.albedo + gbuffer.emissive) * frame_constants_dyn.pre_exposure_mult, 1.0);
+        return;
+    }
+
     // Build a orthonormal basis that transform tangent space vector to world space.
     // Notice that during multiplication we put the vector on the right side of the mul(),
     // this is equivalent to multiply a transpose matrix.
@@ -146,16 +177,20 @@ void main(in uint2 px: SV_DispatchThreadID)
     }
 
     // indirect lighting
+    if ((push_constants.feature_flags & FEATURE_FLAG_IBL_BIT) != 0)
     {
         Ibl ibl = Ibl::from_brdf(brdf.specular_brdf); 
         const float3 R = reflect(view_ray.Direction, gbuffer.normal);
 
         float3 irradiance = ibl.eval_gbuffer(gbuffer, wo, R, compensate, brdf.diffuse_brdf.reflectance);
 
-        total_radiance += irradiance;
+        total_radiance += irradiance * frame_constants_dyn.ibl_radiance_scale.rgb;
     }
 
+    // emissive is in hdr and added before the post processing, so the values beyond 1.0 drive the bloom and the tonemapping
+    total_radiance += gbuffer.emissive;
+
     total_radiance *= frame_constants_dyn.pre_exposure_mult;
 
-    output_tex[px] = float4(total_radiance, 1.0);
+    output_tex[out_px] = float4(total_radiance, 1.0);
 }
\ No newline at end of file
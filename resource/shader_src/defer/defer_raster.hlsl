@@ -2,6 +2,7 @@
 #include "../common/bindless_resources.hlsl"
 #include "../common/material.hlsl"
 #include "../common/immutable_sampler.hlsl"
+#include "../common/uv.hlsl"
 #include "../color/color_space.hlsl"
 
 #include "gbuffer.hlsl"
@@ -12,7 +13,8 @@
 [[vk::push_constant]]
 struct {
     uint mesh_index;
-    uint instance_index;
+    // instances of the same mesh are drawn in one instanced draw call, this is the index of the first one.
+    uint first_instance_index;
 } push_constants;
 
 // float3x4 float4x4 matrix is column major matrix in hlsl by default.
@@ -23,6 +25,8 @@ struct {
 // But when the matrix is left-multiply (i.e. the matrix is row matrix), hlsl wil not do the transpose operation.
 // So put the matrix in the left side of the mul() function will save some instrctions for us.
 [[vk::binding(0)]] StructuredBuffer<row_major float3x4> instance_transforms_dyn; // dynamic read-only storage buffer
+[[vk::binding(1)]] StructuredBuffer<InstanceMaterialOverride> instance_material_overrides_dyn;
+[[vk::binding(2)]] StructuredBuffer<row_major float3x4> prev_instance_transforms_dyn; // transforms in the last frame
 
 struct VsOut {
 	float4 out_position: SV_Position;
@@ -34,9 +38,14 @@ struct VsOut {
     [[vk::location(5)]] float3 bitangent: TEXCOORD5;
 
     [[vk::location(6)]] float3 pos_vs: TEXCOORD6;
+    [[vk::location(7)]] nointerpolation uint instance_index: TEXCOORD7;
+
+    // clip space xyw, used to output the motion vector
+    [[vk::location(8)]] float3 cur_pos_cs: TEXCOORD8;
+    [[vk::location(9)]] float3 prev_pos_cs: TEXCOORD9;
 };
 
-VsOut vs_main(uint vid: SV_VertexID)
+VsOut vs_main(uint vid: SV_VertexID, uint iid: SV_InstanceID)
 {
     VsOut vsout;
 
@@ -53,11 +62,16 @@ VsOut vs_main(uint vid: SV_VertexID)
     float2 uv = asfloat(draw_datas.Load2(vid * sizeof(float2) + mesh.uv_offset));
     uint material_id = draw_datas.Load(vid * sizeof(uint) + mesh.mat_id_offset);
 
-    float3x4 transform = instance_transforms_dyn[push_constants.instance_index];
-    float3 ws_pos = mul(transform, float4(vertex.position, 1.0));
+    const uint instance_index = push_constants.first_instance_index + iid;
+    float3x4 transform = instance_transforms_dyn[instance_index];
+    // precise, so that the depth matches the depth prepass (shadow_mapping.hlsl vs_depth_prepass) exactly
+    precise float3 ws_pos = mul(transform, float4(vertex.position, 1.0));
     
-    float4 vs_pos = mul(cam.world_to_view, float4(ws_pos, 1.0));
-    float4 cs_pos = mul(cam.view_to_clip, vs_pos);
+    precise float4 vs_pos = mul(cam.world_to_view, float4(ws_pos, 1.0));
+    precise float4 cs_pos = mul(cam.view_to_clip, vs_pos);
+
+    float3 prev_ws_pos = mul(prev_instance_transforms_dyn[instance_index], float4(vertex.position, 1.0));
+    float4 prev_cs_pos = mul(cam.prev_world_to_clip, float4(prev_ws_pos, 1.0));
 
     vsout.out_position = cs_pos;
     vsout.color = color;
@@ -69,6 +83,10 @@ VsOut vs_main(uint vid: SV_VertexID)
 
     // normalize in homogeneous coordinate
     vsout.pos_vs = vs_pos.xyz / vs_pos.w;
+    vsout.instance_index = instance_index;
+
+    vsout.cur_pos_cs = cs_pos.xyw;
+    vsout.prev_pos_cs = prev_cs_pos.xyw;
 
     return vsout;
 }
@@ -82,11 +100,18 @@ struct PsIn {
     [[vk::location(5)]] float3 bitangent: TEXCOORD5;
 
     [[vk::location(6)]] float3 pos_vs: TEXCOORD6;
+    [[vk::location(7)]] nointerpolation uint instance_index: TEXCOORD7;
+
+    // clip space xyw, used to output the motion vector
+    [[vk::location(8)]] float3 cur_pos_cs: TEXCOORD8;
+    [[vk::location(9)]] float3 prev_pos_cs: TEXCOORD9;
 };
 
 struct PsOut {
     float4 gbuffer: SV_TARGET0;
     float3 geometric_normal: SV_TARGET1;
+    uint instance_id: SV_TARGET2; // instance index plus one, zero means background
+    float2 motion_vector: SV_TARGET3; // uv offset from the last frame
 };
 
 PsOut ps_main(PsIn ps)
@@ -94,6 +119,13 @@ PsOut ps_main(PsIn ps)
     const Mesh mesh = meshes[push_constants.mesh_index];
 
     Material mat = draw_datas.Load<Material>(ps.material_id * sizeof(Material) + mesh.mat_data_offset);
+    apply_material_override(mat, instance_material_overrides_dyn[ps.instance_index]);
+
+    // alpha blended materials of the mixed meshes are drawn in the forward transparency pass
+    if (mat.alpha_blend != 0)
+    {
+        discard;
+    }
     
     // TODO: apply uv transform (using Material.texture_transform)
 
@@ -128,14 +160,13 @@ PsOut ps_main(PsIn ps)
     {
         Texture2D normal_map = bindless_textures[NonUniformResourceIndex(mat.normal_map)];
         float4 normal_texel = normal_map.Sample(sampler_llr, ps.uv);
-        float3 normal_ts = float3(normal_texel.xy * 2.0 - 1.0, 0.0); // remap from [0, 1] to [-1, 1]
-        normal_ts.z = sqrt(max(0.001, 1.0 - dot(normal_ts.xy, normal_ts.xy))); // normal in normal map is already normalized
+        float3 normal_ts = decode_normal_ts(mat, normal_texel);
 
         float3x3 tbn_matrix = float3x3(ps.tangent, ps.bitangent, ps.normal);
         normal_os = mul(normal_ts, tbn_matrix);
     }
 
-    float3 normal_ws = normalize(mul(instance_transforms_dyn[push_constants.instance_index], float4(normal_os, 0.0)));
+    float3 normal_ws = normalize(mul(instance_transforms_dyn[ps.instance_index], float4(normal_os, 0.0)));
 
     // derive geometric normal from view space pos
     // TODO: why not derive it using world space pos?
@@ -153,8 +184,13 @@ PsOut ps_main(PsIn ps)
         normal_ws *= -1; // simply flipping opposite
     }
 
-    // Texture2D emissive_map = bindless_textures[NonUniformResourceIndex(mat.emissive_map)];
-    // float4 emissive_texel = emissive_map.Sample(sampler_llr, ps.uv);
+    // Sample emissive
+    float3 emissive = float3(mat.emissive[0], mat.emissive[1], mat.emissive[2]) * mat.emissive_intensity;
+    if ((mesh.texture_mask & TEXTURE_MASK_EMISSIVE_BIT) != 0)
+    {
+        Texture2D emissive_map = bindless_textures[NonUniformResourceIndex(mat.emissive_map)];
+        emissive *= emissive_map.Sample(sampler_llr, ps.uv).rgb;
+    }
 
     GBuffer gbuffer = GBuffer::zero();
     
@@ -164,6 +200,7 @@ PsOut ps_main(PsIn ps)
     gbuffer.albedo = base_color * ps.color.rgb * albedo_texel.rgb;
 #endif
     gbuffer.normal = normal_ws;
+    gbuffer.emissive = emissive;
 
 #if FORCE_NO_TEX_GRAY_MODEL
     gbuffer.metalness = 0.0;
@@ -172,10 +209,20 @@ PsOut ps_main(PsIn ps)
     gbuffer.metalness = metalness;
     gbuffer.roughness = roughness;
 #endif
+    if (mat.unlit != 0)
+    {
+        gbuffer.flags |= GBUFFER_FLAG_UNLIT;
+    }
 
     PsOut psout;
     psout.gbuffer = asfloat(gbuffer.pack().data);
     // store the geometric view space normal
     psout.geometric_normal = geometric_normal_vs * 0.5 + 0.5;
+    psout.instance_id = ps.instance_index + 1;
+
+    // remove the jitter, so that a static pixel has no motion (the last frame matrix has no jitter)
+    float2 cur_uv = clip_to_uv(ps.cur_pos_cs.xy / ps.cur_pos_cs.z - cam.jitter.xy);
+    float2 prev_uv = clip_to_uv(ps.prev_pos_cs.xy / ps.prev_pos_cs.z);
+    psout.motion_vector = cur_uv - prev_uv;
     return psout;
 }
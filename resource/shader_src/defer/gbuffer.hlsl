@@ -6,11 +6,16 @@
 
 struct PackedGBuffer;
 
+// the surface skips lighting and the albedo is output as the emitted radiance
+static const uint GBUFFER_FLAG_UNLIT = (1 << 0);
+
 struct GBuffer {
     float3 albedo;
     float3 normal;
     float  metalness;
     float  roughness;
+    uint   flags;    // only the lower 8 bits are stored
+    float3 emissive; // hdr radiance emitted by the surface, added on top of the lighting
 
     static GBuffer zero() {
         GBuffer res;
@@ -18,6 +23,8 @@ struct GBuffer {
         res.normal = 0;
         res.metalness = 0;
         res.roughness = 0;
+        res.flags = 0;
+        res.emissive = 0;
         return res;
     }
 
@@ -41,10 +48,11 @@ PackedGBuffer GBuffer::pack() {
     res.x = asfloat(pack_color_888_uint(albedo));
     res.y = pack_normal_11_10_11(normal);
 
-    float2 mr = float2(metalness, roughness_to_perceptual_roughness(roughness));
-    res.z = asfloat(pack_2x16f_uint(mr));
-    // reserved
-    res.w = 0;
+    // metalness is mostly 0.0 or 1.0, 8 bits are enough for it
+    uint mrf = pack_unorm(metalness, 8) | ((flags & 0xff) << 8);
+    mrf |= f32tof16(roughness_to_perceptual_roughness(roughness)) << 16;
+    res.z = asfloat(mrf);
+    res.w = asfloat(pack_rgb9e5_uint(emissive));
 
     PackedGBuffer packed;
     packed.data = asuint(res);
@@ -56,9 +64,10 @@ GBuffer PackedGBuffer::unpack() {
     gbuffer.albedo = unpack_color_888_uint(data.x);
     gbuffer.normal = unpack_normal_11_10_11(asfloat(data.y));
 
-    float2 mr = unpack_2x16f_uint(data.z);
-    gbuffer.metalness = mr.x;
-    gbuffer.roughness = perceptual_roughness_to_roughness(mr.y);
+    gbuffer.metalness = unpack_unorm(data.z, 8);
+    gbuffer.flags = (data.z >> 8) & 0xff;
+    gbuffer.roughness = perceptual_roughness_to_roughness(f16tof32(data.z >> 16));
+    gbuffer.emissive = unpack_rgb9e5_uint(data.w);
 
     return gbuffer;
 }
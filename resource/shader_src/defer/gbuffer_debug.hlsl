@@ -0,0 +1,71 @@
+#include "gbuffer.hlsl"
+#include "../common/frame_constants.hlsl"
+#include "../common/uv.hlsl"
+
+[[vk::push_constant]]
+struct {
+    uint render_res_width;
+    uint render_res_height;
+    uint debug_view;
+} push_constants;
+
+// Same in raven-render::world_renderer::RenderMode::gbuffer_debug_view()
+static const uint DEBUG_VIEW_ALBEDO = 0;
+static const uint DEBUG_VIEW_NORMAL = 1;
+static const uint DEBUG_VIEW_DEPTH = 2;
+static const uint DEBUG_VIEW_ROUGHNESS_METALLIC = 3;
+
+// view distance where the depth view fades to about one third
+static const float DEBUG_DEPTH_DISTANCE_SCALE = 50.0;
+
+[[vk::binding(0)]] Texture2D<float4> gbuffer_tex;
+[[vk::binding(1)]] Texture2D<float> depth_tex;
+[[vk::binding(2)]] RWTexture2D<float4> output_tex;
+
+[numthreads(8, 8, 1)]
+void main(in uint2 px: SV_DispatchThreadID)
+{
+    const float2 resolution = float2(push_constants.render_res_width, push_constants.render_res_height);
+    if (any(px >= uint2(resolution)))
+    {
+        return;
+    }
+
+    const float depth = depth_tex[px];
+    // the background is left black in all the views
+    if (depth == 0.0)
+    {
+        output_tex[px] = float4(0.0, 0.0, 0.0, 1.0);
+        return;
+    }
+
+    GBuffer gbuffer = PackedGBuffer::from_uint4(asuint(gbuffer_tex[px])).unpack();
+
+    float3 color = 0.0;
+    switch (push_constants.debug_view)
+    {
+        case DEBUG_VIEW_ALBEDO:
+            color = gbuffer.albedo;
+            break;
+        case DEBUG_VIEW_NORMAL:
+            // world space normal remapped to [0, 1]
+            color = gbuffer.normal * 0.5 + 0.5;
+            break;
+        case DEBUG_VIEW_DEPTH:
+        {
+            // reverse-z is not linear, show the view distance instead (near is bright)
+            CameraFrameConstants cam = frame_constants_dyn.camera_constants;
+            const float2 uv = pixel_to_uv(float2(px), resolution);
+            const float4 pos_vs = mul(cam.clip_to_view, float4(uv_to_clip(uv), depth, 1.0));
+            const float view_distance = length(pos_vs.xyz / pos_vs.w);
+
+            color = exp(-view_distance / DEBUG_DEPTH_DISTANCE_SCALE).xxx;
+            break;
+        }
+        case DEBUG_VIEW_ROUGHNESS_METALLIC:
+            color = float3(roughness_to_perceptual_roughness(gbuffer.roughness), gbuffer.metalness, 0.0);
+            break;
+    }
+
+    output_tex[px] = float4(color, 1.0);
+}
@@ -0,0 +1,12 @@
+#ifndef _LIGHTING_RESOLUTION_HLSL_
+#define _LIGHTING_RESOLUTION_HLSL_
+
+// The full resolution pixel a lighting pixel is shaded at, when the lighting runs at a lower resolution than the gbuffer.
+// It is the pixel covering the center of the lighting pixel, and the identity when both resolutions are the same.
+uint2 lighting_to_render_px(uint2 lighting_px, uint2 lighting_res, uint2 render_res)
+{
+    const float2 pos = (float2(lighting_px) + 0.5) * float2(render_res) / float2(lighting_res);
+    return min(uint2(pos), render_res - 1);
+}
+
+#endif
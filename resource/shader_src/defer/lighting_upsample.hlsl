@@ -0,0 +1,103 @@
+#include "../common/frame_constants.hlsl"
+#include "../common/uv.hlsl"
+
+#include "lighting_resolution.hlsl"
+
+[[vk::push_constant]]
+struct {
+    uint render_res_width;
+    uint render_res_height;
+    uint lighting_res_width;
+    uint lighting_res_height;
+} push_constants;
+
+// how fast the weight falls off with the relative view distance difference
+static const float DEPTH_WEIGHT_SCALE = 32.0;
+// sharpness of the weight on the angle between the geometric normals
+static const float NORMAL_WEIGHT_POWER = 16.0;
+
+[[vk::binding(0)]] Texture2D<float4> lighting_tex;
+[[vk::binding(1)]] Texture2D<float> depth_tex;
+[[vk::binding(2)]] Texture2D<float3> geometric_normal_tex;
+[[vk::binding(3)]] RWTexture2D<float4> output_tex;
+
+float view_distance(uint2 px, float depth, float2 resolution)
+{
+    CameraFrameConstants cam = frame_constants_dyn.camera_constants;
+    const float2 uv = pixel_to_uv(float2(px), resolution);
+    const float4 pos_vs = mul(cam.clip_to_view, float4(uv_to_clip(uv), depth, 1.0));
+    return length(pos_vs.xyz / pos_vs.w);
+}
+
+float3 load_geometric_normal(uint2 px)
+{
+    return geometric_normal_tex[px] * 2.0 - 1.0;
+}
+
+// Joint bilateral upsample, the bilinear weights of the four nearest lighting samples are scaled by
+// how similar their surfaces (taken from the full resolution depth and geometric normal) are to this pixel.
+[numthreads(8, 8, 1)]
+void main(in uint2 px: SV_DispatchThreadID)
+{
+    const uint2 render_res = uint2(push_constants.render_res_width, push_constants.render_res_height);
+    const uint2 lighting_res = uint2(push_constants.lighting_res_width, push_constants.lighting_res_height);
+    if (any(px >= render_res))
+    {
+        return;
+    }
+
+    const float depth = depth_tex[px];
+    const bool is_sky = depth == 0.0;
+    const float pixel_distance = is_sky ? 0.0 : view_distance(px, depth, float2(render_res));
+    const float3 normal = load_geometric_normal(px);
+
+    // position of this pixel in the lighting image, in texels
+    const float2 lighting_pos = (float2(px) + 0.5) * float2(lighting_res) / float2(render_res) - 0.5;
+    const int2 base = int2(floor(lighting_pos));
+    const float2 f = lighting_pos - float2(base);
+
+    float4 total = 0.0;
+    float total_weight = 0.0;
+    // fallback when no sample is on a similar surface (e.g. thin geometry missed by the lighting)
+    float4 best_sample = 0.0;
+    float best_weight = -1.0;
+
+    [unroll]
+    for (uint i = 0; i < 4; ++i)
+    {
+        const int2 offset = int2(i & 1, i >> 1);
+        const uint2 sample_px = uint2(clamp(base + offset, 0, int2(lighting_res) - 1));
+        const float bilinear = (offset.x == 0 ? 1.0 - f.x : f.x) * (offset.y == 0 ? 1.0 - f.y : f.y);
+
+        // the surface this lighting sample was shaded at
+        const uint2 source_px = lighting_to_render_px(sample_px, lighting_res, render_res);
+        const float source_depth = depth_tex[source_px];
+
+        float similarity;
+        if (is_sky || source_depth == 0.0)
+        {
+            // never mix the sky with the geometry
+            similarity = (is_sky && source_depth == 0.0) ? 1.0 : 0.0;
+        }
+        else
+        {
+            const float source_distance = view_distance(source_px, source_depth, float2(render_res));
+            const float depth_weight = exp(-DEPTH_WEIGHT_SCALE * abs(source_distance - pixel_distance) / max(pixel_distance, 1e-4));
+            const float normal_weight = pow(saturate(dot(normal, load_geometric_normal(source_px))), NORMAL_WEIGHT_POWER);
+            similarity = depth_weight * normal_weight;
+        }
+
+        const float4 lighting = lighting_tex[sample_px];
+        const float weight = bilinear * similarity;
+        total += lighting * weight;
+        total_weight += weight;
+
+        if (similarity > best_weight)
+        {
+            best_weight = similarity;
+            best_sample = lighting;
+        }
+    }
+
+    output_tex[px] = total_weight > 1e-5 ? total / total_weight : best_sample;
+}
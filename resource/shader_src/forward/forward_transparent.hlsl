@@ -0,0 +1,228 @@
+#include "../math/constants.hlsl"
+#include "../math/math.hlsl"
+#include "../common/frame_constants.hlsl"
+#include "../common/bindless_resources.hlsl"
+#include "../common/material.hlsl"
+#include "../common/immutable_sampler.hlsl"
+#include "../color/color_space.hlsl"
+#include "../light/light_commons.hlsl"
+
+#include "../defer/gbuffer.hlsl"
+#include "../pbr/brdf.hlsl"
+#include "../pbr/multi_scatter_compensate.hlsl"
+
+[[vk::push_constant]]
+struct {
+    uint mesh_index;
+    uint instance_index;
+} push_constants;
+
+// Same as the defer_raster.hlsl, the transforms are row major matrices.
+[[vk::binding(0)]] StructuredBuffer<row_major float3x4> instance_transforms_dyn; // dynamic read-only storage buffer
+[[vk::binding(1)]] Texture2D<float> light_map[MAX_DIRECTIONAL_LIGHT_COUNT];
+[[vk::binding(2)]] StructuredBuffer<row_major float4x4> light_map_transforms_dyn;
+[[vk::binding(3)]] StructuredBuffer<InstanceMaterialOverride> instance_material_overrides_dyn;
+
+// Note: bias matrix to move NDC (coord x and y) [-1, 1] to [0, 1] for texture sampling
+// inverse y here to compensate the negative y viewport in vulkan (see ctx.set_viewport())
+static const float4x4 bias_mat = float4x4(
+    0.5,  0.0, 0.0, 0.5,
+    0.0, -0.5, 0.0, 0.5,
+    0.0,  0.0, 1.0, 0.0,
+    0.0,  0.0, 0.0, 1.0
+);
+
+float is_shadowed(uint light_index, float3 position_ws)
+{
+    float4 shadow_coord = mul(bias_mat, mul(light_map_transforms_dyn[light_index], float4(position_ws, 1.0)));
+    shadow_coord /= shadow_coord.w;
+
+    float shadowed = 1.0;
+
+    // outside the depth range is all shadowed
+    if (shadow_coord.z >= 0.0 && shadow_coord.z <= 1.0 && shadow_coord.w > 0.0)
+	{
+		// same hardware PCF as defer_lighting.hlsl
+		const float lit = light_map[light_index].SampleCmpLevelZero(sampler_llce_greater, shadow_coord.xy, shadow_coord.z);
+		shadowed = 1.0 - lit;
+	}
+
+    return shadowed;
+}
+
+struct VsOut {
+	float4 out_position: SV_Position;
+    [[vk::location(0)]] float4 color: TEXCOORD0;
+    [[vk::location(1)]] float2 uv: TEXCOORD1;
+    [[vk::location(2)]] float3 normal: TEXCOORD2;
+    [[vk::location(3)]] nointerpolation uint material_id: TEXCOORD3;
+    [[vk::location(4)]] float3 tangent: TEXCOORD4;
+    [[vk::location(5)]] float3 bitangent: TEXCOORD5;
+
+    [[vk::location(6)]] float3 pos_ws: TEXCOORD6;
+};
+
+VsOut vs_main(uint vid: SV_VertexID)
+{
+    VsOut vsout;
+
+    CameraFrameConstants cam = frame_constants_dyn.camera_constants;
+
+    // get mesh offset data
+    const Mesh mesh = meshes[push_constants.mesh_index];
+
+    PackedVertex packed_vertex = PackedVertex(asfloat(draw_datas.Load4(vid * sizeof(float4) + mesh.vertex_offset)));
+    Vertex vertex = packed_vertex.unpack();
+
+    float4 color = asfloat(draw_datas.Load4(vid * sizeof(float4) + mesh.color_offset));
+    float4 tangent = asfloat(draw_datas.Load4(vid * sizeof(float4) + mesh.tangent_offset));
+    float2 uv = asfloat(draw_datas.Load2(vid * sizeof(float2) + mesh.uv_offset));
+    uint material_id = draw_datas.Load(vid * sizeof(uint) + mesh.mat_id_offset);
+
+    float3x4 transform = instance_transforms_dyn[push_constants.instance_index];
+    float3 ws_pos = mul(transform, float4(vertex.position, 1.0));
+
+    float4 vs_pos = mul(cam.world_to_view, float4(ws_pos, 1.0));
+    float4 cs_pos = mul(cam.view_to_clip, vs_pos);
+
+    vsout.out_position = cs_pos;
+    vsout.color = color;
+    vsout.uv = uv;
+    vsout.normal = vertex.normal;
+    vsout.material_id = material_id;
+    vsout.tangent = tangent.xyz;
+    vsout.bitangent = normalize(cross(vertex.normal, vsout.tangent) * tangent.w);
+    vsout.pos_ws = ws_pos;
+
+    return vsout;
+}
+
+struct PsIn {
+    [[vk::location(0)]] float4 color: TEXCOORD0;
+    [[vk::location(1)]] float2 uv: TEXCOORD1;
+    [[vk::location(2)]] float3 normal: TEXCOORD2;
+    [[vk::location(3)]] nointerpolation uint material_id: TEXCOORD3;
+    [[vk::location(4)]] float3 tangent: TEXCOORD4;
+    [[vk::location(5)]] float3 bitangent: TEXCOORD5;
+
+    [[vk::location(6)]] float3 pos_ws: TEXCOORD6;
+};
+
+float4 ps_main(PsIn ps, bool is_front_face: SV_IsFrontFace): SV_TARGET0
+{
+    const Mesh mesh = meshes[push_constants.mesh_index];
+
+    Material mat = draw_datas.Load<Material>(ps.material_id * sizeof(Material) + mesh.mat_data_offset);
+    apply_material_override(mat, instance_material_overrides_dyn[push_constants.instance_index]);
+
+    // opaque materials of the mixed meshes are drawn in gbuffer rasterization, with the indirect lighting
+    if (mat.alpha_blend == 0)
+    {
+        discard;
+    }
+
+    // Sample albedo map
+    float4 albedo_texel = 1.0.xxxx;
+    if ((mesh.texture_mask & TEXTURE_MASK_ALBEDO_BIT) != 0)
+    {
+        Texture2D albedo_map = bindless_textures[NonUniformResourceIndex(mat.albedo_map)];
+        albedo_texel = albedo_map.Sample(sampler_llr, ps.uv);
+    }
+    float4 base_color = float4(mat.base_color[0], mat.base_color[1], mat.base_color[2], mat.base_color[3]);
+
+    // Sample Metallic Rougheness
+    float metalness = mat.metalness;
+    float roughness = clamp(perceptual_roughness_to_roughness(mat.roughness), 1e-3, 1.0);
+    if ((mesh.texture_mask & TEXTURE_MASK_SPECULAR_BIT) != 0)
+    {
+        Texture2D specular_map = bindless_textures[NonUniformResourceIndex(mat.specular_map)];
+        float4 specular_texel = specular_map.Sample(sampler_llr, ps.uv);
+        metalness *= specular_texel.z;
+        float peceptual_roughness = mat.roughness * specular_texel.y;
+        roughness = clamp(perceptual_roughness_to_roughness(peceptual_roughness), 1e-3, 1.0);
+    }
+
+    // Sample normal
+    float3 normal_os = ps.normal;
+
+    if ((mesh.texture_mask & TEXTURE_MASK_NORMAL_BIT) != 0)
+    {
+        Texture2D normal_map = bindless_textures[NonUniformResourceIndex(mat.normal_map)];
+        float4 normal_texel = normal_map.Sample(sampler_llr, ps.uv);
+        float3 normal_ts = decode_normal_ts(mat, normal_texel);
+
+        float3x3 tbn_matrix = float3x3(ps.tangent, ps.bitangent, ps.normal);
+        normal_os = mul(normal_ts, tbn_matrix);
+    }
+
+    float3 normal_ws = normalize(mul(instance_transforms_dyn[push_constants.instance_index], float4(normal_os, 0.0)));
+    // back faces are not culled for transparent meshes, shade them with the flipped normal
+    if (!is_front_face)
+    {
+        normal_ws *= -1;
+    }
+
+    // Sample emissive
+    float3 emissive = float3(mat.emissive[0], mat.emissive[1], mat.emissive[2]) * mat.emissive_intensity;
+    if ((mesh.texture_mask & TEXTURE_MASK_EMISSIVE_BIT) != 0)
+    {
+        Texture2D emissive_map = bindless_textures[NonUniformResourceIndex(mat.emissive_map)];
+        emissive *= emissive_map.Sample(sampler_llr, ps.uv).rgb;
+    }
+
+    GBuffer gbuffer = GBuffer::zero();
+    gbuffer.albedo = base_color.rgb * ps.color.rgb * albedo_texel.rgb;
+    gbuffer.emissive = emissive;
+    gbuffer.normal = normal_ws;
+    gbuffer.metalness = metalness;
+    gbuffer.roughness = roughness;
+
+    const float alpha = base_color.a * ps.color.a * albedo_texel.a;
+
+    // unlit surfaces are neither lit nor shadowed
+    if (mat.unlit != 0)
+    {
+        return float4((gbuffer.albedo + gbuffer.emissive) * frame_constants_dyn.pre_exposure_mult, alpha);
+    }
+
+    CameraFrameConstants cam = frame_constants_dyn.camera_constants;
+    const float3 camera_pos_ws = mul(cam.view_to_world, float4(0.0, 0.0, 0.0, 1.0)).xyz;
+    const float3 view_dir = normalize(ps.pos_ws - camera_pos_ws);
+
+    // See defer_lighting.hlsl
+    const float3x3 tangent_to_world = build_orthonormal_basis(gbuffer.normal);
+    float3 wo = mul(-view_dir, tangent_to_world);
+
+    Brdf brdf = Brdf::from_gbuffer(gbuffer);
+    MultiScatterCompensate compensate = MultiScatterCompensate::compensate_for(wo, gbuffer.roughness, brdf.specular_brdf.F0);
+
+    float3 total_radiance = 0.0.xxx;
+    // direct lighting
+    // TODO: indirect lighting (ibl) for transparent meshes
+    {
+        uint directional_shadow_map_index = 0;
+        for (uint i = 0; i < frame_constants_dyn.directional_light_count; ++i)
+        {
+            const LightFrameConstants light = frame_constants_dyn.light_constants[i];
+            const float3 wi = mul(normalize(light.direction), tangent_to_world);
+
+            const float3 brdf_value = brdf.eval_directional_light(wi, wo, compensate);
+            const float3 light_radiance = light.color * light.intensity;
+
+            float shadowed = 0.0;
+            if (light.shadowed)
+            {
+                shadowed = is_shadowed(directional_shadow_map_index, ps.pos_ws);
+                directional_shadow_map_index += 1;
+            }
+            total_radiance += (1.0 - shadowed) * (brdf_value * light_radiance * max(0.0, wi.z));
+        }
+    }
+
+    // See defer_lighting.hlsl, emissive is added in hdr
+    total_radiance += gbuffer.emissive;
+
+    total_radiance *= frame_constants_dyn.pre_exposure_mult;
+
+    return float4(total_radiance, alpha);
+}
@@ -1,7 +1,71 @@
+#include "common/immutable_sampler.hlsl"
+#include "color/hdr_encoding.hlsl"
+
+[[vk::push_constant]]
+struct {
+    uint src_width;
+    uint src_height;
+    uint dst_width;
+    uint dst_height;
+    // the source is tonemapped SDR if the output encoding is SDR, linear HDR otherwise
+    uint output_encoding;
+    float paper_white_nits;
+    float max_nits;
+} push_constants;
+
 [[vk::binding(0)]] Texture2D<float4> src_tex;
 [[vk::binding(1)]] RWTexture2D<float4> dst_tex;
 
+// max taps in one dimension when downsampling
+#define MAX_DOWNSAMPLE_TAPS 4
+
+void write_output(uint2 px, float3 color)
+{
+    if (push_constants.output_encoding != OUTPUT_ENCODING_SDR)
+    {
+        color = encode_hdr_output(color, push_constants.output_encoding, push_constants.paper_white_nits, push_constants.max_nits);
+    }
+
+    dst_tex[px] = float4(color, 1.0);
+}
+
 [numthreads(8, 8, 1)]
 void main(in uint2 px: SV_DispatchThreadID) {
-    dst_tex[px] = float4(src_tex[px].rgb, 1.0);
-}
\ No newline at end of file
+    const uint2 src_size = uint2(push_constants.src_width, push_constants.src_height);
+    const uint2 dst_size = uint2(push_constants.dst_width, push_constants.dst_height);
+
+    if (any(px >= dst_size)) {
+        return;
+    }
+
+    // same size, just copy it
+    if (all(src_size == dst_size)) {
+        write_output(px, src_tex[px].rgb);
+        return;
+    }
+
+    const float2 uv = (float2(px) + 0.5) / float2(dst_size);
+    const float2 ratio = float2(src_size) / float2(dst_size);
+
+    // upsampling (or only slightly downsampling), bilinear filtering is enough
+    if (all(ratio <= 1.0)) {
+        write_output(px, src_tex.SampleLevel(sampler_llce, uv, 0.0).rgb);
+        return;
+    }
+
+    // supersampling, average the source pixels covered by this destination pixel to avoid aliasing
+    const uint2 tap_count = min(uint2(ceil(ratio)), MAX_DOWNSAMPLE_TAPS.xx);
+    const float2 dst_texel_size = 1.0 / float2(dst_size);
+
+    float3 sum = 0.0.xxx;
+    for (uint y = 0; y < tap_count.y; ++y)
+    {
+        for (uint x = 0; x < tap_count.x; ++x)
+        {
+            const float2 offset = (float2(x, y) + 0.5) / float2(tap_count) - 0.5;
+            sum += src_tex.SampleLevel(sampler_llce, uv + offset * dst_texel_size, 0.0).rgb;
+        }
+    }
+
+    write_output(px, sum / float(tap_count.x * tap_count.y));
+}
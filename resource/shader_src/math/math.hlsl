@@ -75,4 +75,16 @@ float3 spherical_fibonacci(uint i, uint n) {
     return normalize(float3(x, y, z));
 }
 
+float4 quat_conjugate(float4 q)
+{
+    return float4(-q.xyz, q.w);
+}
+
+// Rotate vector v by the unit quaternion q (xyzw).
+float3 quat_rotate(float4 q, float3 v)
+{
+    const float3 t = 2.0 * cross(q.xyz, v);
+    return v + q.w * t + cross(q.xyz, t);
+}
+
 #endif
\ No newline at end of file
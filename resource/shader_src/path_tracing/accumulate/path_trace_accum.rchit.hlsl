@@ -163,8 +163,7 @@ void main(inout GBufferRayPayload payload: SV_RayPayload, in RayHitAttrib attrib
         );
         float4 normal_texel = normal_tex.sample_tex(sampler_llce, uv);
 
-        float3 normal_ts = float3(normal_texel.xy * 2.0 - 1.0, 0.0); // remap from [0, 1] to [-1, 1]
-        normal_ts.z = sqrt(max(0.001, 1.0 - dot(normal_ts.xy, normal_ts.xy))); // normal in normal map is already normalized
+        float3 normal_ts = decode_normal_ts(material, normal_texel);
 
         float3x3 tbn_matrix = float3x3(tangent, bitangent, shading_normal); // object sapce to tangent space matrix
         float3 normal_os = mul(normal_ts, tbn_matrix);
@@ -172,11 +171,27 @@ void main(inout GBufferRayPayload payload: SV_RayPayload, in RayHitAttrib attrib
         shading_normal = normal_os;
     }
 
+    // emissive map
+    float3 emissive = float3(material.emissive[0], material.emissive[1], material.emissive[2]) * material.emissive_intensity;
+    if ((mesh.texture_mask & TEXTURE_MASK_EMISSIVE_BIT) != 0)
+    {
+        BindlessTextureWithLod emissive_tex = ray_cone_sample_texture_with_lod(
+            material.emissive_map, delta_constants, curr_cone_width,
+            geometric_normal_ws, WorldRayDirection()
+        );
+        emissive *= emissive_tex.sample_tex(sampler_llr, uv).rgb;
+    }
+
     GBuffer gbuffer = GBuffer::zero();
     gbuffer.albedo = albedo;
+    gbuffer.emissive = emissive;
     gbuffer.normal = normalize(mul(ObjectToWorld3x4(), float4(shading_normal, 0.0)));
     gbuffer.metalness = metalness;
     gbuffer.roughness = roughness;
+    if (material.unlit != 0)
+    {
+        gbuffer.flags |= GBUFFER_FLAG_UNLIT;
+    }
 
     // force double-sided
     if (dot(WorldRayDirection(), gbuffer.normal) > 0) {
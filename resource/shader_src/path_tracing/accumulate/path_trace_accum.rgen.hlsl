@@ -15,29 +15,37 @@
 // reference paper:
 // [1] https://media.contentapi.ea.com/content/dam/ea/seed/presentations/2019-ray-tracing-gems-chapter-20-akenine-moller-et-al.pdf
 
+[[vk::push_constant]]
+struct {
+    uint is_env_map_linear; // it is a bool, the procedural sky is linear while the cubemaps are in srgb
+    uint max_path_length;
+    uint sample_count; // samples per pixel in this frame
+    uint max_accumulated_sample_count;
+} push_constants;
+
 [[vk::binding(0)]] RaytracingAccelerationStructure tlas;
 // w channel in output_tex is the accumulated time.
 [[vk::binding(1)]] RWTexture2D<float4> output_tex;
 [[vk::binding(2)]] TextureCube env_map;
 
-static const uint  MAX_PATH_TRACING_LENGTH = 16;
 // used to restrict the secondary ray hit range
 static const float MAX_SECONDARY_RAY_LENGTH = FLOAT_MAX;
 
 static const uint RUSSIAN_ROULETTE_START_PATH_INDEX = 3;
 
-static const float MAX_PIXEL_ACCUMULATETION = 1000.0;
-static const uint  SAMPLE_COUNT = 1; // one ray one sample now
-
 #define BRDF_LUT brdf_lut
 
 #include "../../pbr/brdf.hlsl"
 #include "../../pbr/multi_scatter_compensate.hlsl"
 
-float3 sample_env(float3 direction)
+// the camera sees the sky directly, while the bounced rays gather the environment lighting
+float3 sample_env(float3 direction, bool is_primary_ray)
 {
-    float3 env_light = env_map.SampleLevel(sampler_lnce, direction, 0.0).rgb;
-    return srgb_to_linear(env_light);
+    float3 env_light = env_map.SampleLevel(sampler_lnce, world_to_sky_dir(direction), 0.0).rgb;
+    env_light = push_constants.is_env_map_linear ? env_light : srgb_to_linear(env_light);
+
+    const float3 scale = is_primary_ray ? frame_constants_dyn.sky_radiance_scale.rgb : frame_constants_dyn.ibl_radiance_scale.rgb;
+    return env_light * scale;
 }
 
 [shader("raygeneration")]
@@ -49,12 +57,12 @@ void main()
     // load prev frame's output value
     float4 prev_px = output_tex[px];
 
-    if (prev_px.w < MAX_PIXEL_ACCUMULATETION)
+    if (prev_px.w < float(push_constants.max_accumulated_sample_count))
     {
         float4 output_packed_total_radiance = 0.0;
         uint rng = hash_combine3(px.x, px.y, frame_constants_dyn.frame_index);
 
-        for (uint sample_index = 0; sample_index < SAMPLE_COUNT; ++sample_index)
+        for (uint sample_index = 0; sample_index < push_constants.sample_count; ++sample_index)
         {
             // 1. Calculate eye ray.
             float2 pixel_offset = float2(0.5, 0.5);
@@ -80,7 +88,7 @@ void main()
 
             // 2. Sample the ray along the path.
             [loop]
-            for (uint path_index = 0; path_index < MAX_PATH_TRACING_LENGTH; ++path_index)
+            for (uint path_index = 0; path_index < push_constants.max_path_length; ++path_index)
             {
                 if (path_index == 1)
                 {
@@ -105,6 +113,14 @@ void main()
                     ray_cone = ray_cone.propagate(primary_hit.t, subsequent_spread_angle);
 
                     GBuffer gbuffer_data = primary_hit.packed_gbuffer.unpack();
+                    total_radiance += throughput * gbuffer_data.emissive;
+
+                    // unlit surfaces only emit their albedo, they do not scatter any light
+                    if ((gbuffer_data.flags & GBUFFER_FLAG_UNLIT) != 0)
+                    {
+                        total_radiance += throughput * gbuffer_data.albedo;
+                        break;
+                    }
 
                     // if we hit the back face of a triangle
                     if (dot(gbuffer_data.normal, sample_ray.Direction) >= 0.0)
@@ -199,7 +215,7 @@ void main()
                 }
                 else // sample sky
                 {
-                    total_radiance += throughput * sample_env(sample_ray.Direction);
+                    total_radiance += throughput * sample_env(sample_ray.Direction, path_index == 0);
                     // terminate the ray path
                     break;
                 }
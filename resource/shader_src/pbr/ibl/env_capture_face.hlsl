@@ -0,0 +1,24 @@
+#include "../../common/frame_constants.hlsl"
+
+[[vk::binding(0)]] Texture2D<float4> face_tex;
+[[vk::binding(1)]] RWTexture2DArray<float4> cube_map;
+[[vk::binding(2)]] cbuffer _dyn {
+    uint face;
+    uint cube_map_res;
+};
+
+// Copy the lit image of a face camera into its layer of the environment cubemap.
+// The camera is right-handed while the cubemap faces are left-handed, so the image is flipped horizontally
+// (see raven-render::env_capture_renderer::face_camera()).
+[numthreads(8, 8, 1)]
+void main(uint2 px: SV_DispatchThreadID)
+{
+    if (any(px >= cube_map_res))
+    {
+        return;
+    }
+
+    // the lit image is pre-exposed, the cubemap stores the radiance like the procedural sky
+    const float3 radiance = face_tex[uint2(cube_map_res - 1 - px.x, px.y)].rgb / frame_constants_dyn.pre_exposure_mult;
+    cube_map[uint3(px, face)] = float4(radiance, 1.0);
+}
@@ -3,6 +3,7 @@
 #include "../../math/coordinate.hlsl"
 
 #include "../../common/immutable_sampler.hlsl"
+#include "../../common/frame_constants.hlsl"
 #include "../../math/spherical_harmonics.hlsl"
 
 struct SHBuffer
@@ -15,9 +16,10 @@ struct SHBuffer
 [[vk::binding(0)]] Texture2DArray<float4> cube_map;
 [[vk::binding(1)]] RWStructuredBuffer<SHBuffer> sh_buffer;
 
+// location is in world space, the sh coefficients are projected in world space
 float3 get_env_radiance(float3 location)
 {
-    return cube_map.SampleLevel(sampler_llce, location, 0.0).rgb;
+    return cube_map.SampleLevel(sampler_llce, world_to_sky_dir(location), 0.0).rgb;
 }
 
 [numthreads(8, 8, 1)]
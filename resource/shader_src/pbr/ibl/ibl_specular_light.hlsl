@@ -5,6 +5,7 @@
 #include "../brdf.hlsl"
 
 #include "../../common/immutable_sampler.hlsl"
+#include "../../common/frame_constants.hlsl"
 
 [[vk::push_constant]]
 struct {
@@ -17,9 +18,10 @@ struct {
 
 #define ENABLE_LOW_SAMPLES_PREFILTER_MAP 1
 
+// dir is in world space, the prefiltered cubemap is stored in world space
 float3 get_env_radiance(float3 dir, float level)
 {
-    return cube_map.SampleLevel(sampler_llce, dir, level).rgb;
+    return cube_map.SampleLevel(sampler_llce, world_to_sky_dir(dir), level).rgb;
 }
 
 // generate sample vectors in some region constrained by the roughness oriented around the microfacet's halfway vector.
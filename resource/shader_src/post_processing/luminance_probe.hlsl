@@ -0,0 +1,19 @@
+#include "../color/luminance.hlsl"
+#include "../common/frame_constants.hlsl"
+
+[[vk::binding(0)]] Texture2D<float4> input_tex;
+[[vk::binding(1)]] RWStructuredBuffer<float> output_buffer;
+[[vk::binding(2)]] cbuffer _dyn {
+    // xy: probe pixel, zw: screen center pixel
+    uint4 probe_pixels;
+};
+
+[numthreads(1, 1, 1)]
+void main(uint idx: SV_DispatchThreadID)
+{
+    const uint2 px = idx == 0 ? probe_pixels.xy : probe_pixels.zw;
+
+    // remove the pre-exposure to get the luminance of the scene
+    const float3 color = input_tex[px].rgb;
+    output_buffer[idx] = rgb_color_to_luminance(color) / frame_constants_dyn.pre_exposure_mult;
+}
@@ -12,6 +12,7 @@
     float  contrast;
     uint   enable_auto_exposure;
     uint   bloom_mip_level;
+    uint   hdr_output;
 };
 [[vk::binding(3)]] Texture2D<float3> bloom_pyramid;
 [[vk::binding(4)]] Texture2D<float3> blur_pyramid_tex;
@@ -46,11 +47,15 @@ void main(uint2 px: SV_DispatchThreadID)
 
     color = pow(color, contrast);
 
-    // tone mapping
-    color = aces_film(color);
- 
-    // gamma correction
-    color = gamma_correction(color, 2.2);
+    // keep it linear for HDR displays, the final blit encodes it for the swapchain
+    if (hdr_output == 0)
+    {
+        // tone mapping
+        color = aces_film(color);
+    
+        // gamma correction
+        color = gamma_correction(color, 2.2);
+    }
 
     output_tex[px] = color;
 }
\ No newline at end of file
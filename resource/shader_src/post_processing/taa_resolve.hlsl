@@ -0,0 +1,105 @@
+#include "../common/uv.hlsl"
+#include "../common/frame_constants.hlsl"
+#include "../common/immutable_sampler.hlsl"
+
+[[vk::binding(0)]] Texture2D<float4> input_tex;
+[[vk::binding(1)]] Texture2D<float4> history_tex;
+[[vk::binding(2)]] Texture2D<float> depth_tex;
+[[vk::binding(3)]] RWTexture2D<float4> output_tex;
+[[vk::binding(4)]] cbuffer _dyn {
+    float4 output_tex_size;
+    float  blend_factor;    // weight of the current frame
+    uint   history_valid;
+};
+[[vk::binding(5)]] Texture2D<float2> motion_vector_tex;
+
+// how many standard deviations the history can be away from the neighborhood mean
+static const float VARIANCE_CLIP_GAMMA = 1.25;
+
+float luminance(float3 color)
+{
+    return dot(color, float3(0.2126, 0.7152, 0.0722));
+}
+
+// Weight the samples by the inverse luminance, so that a few bright pixels do not flicker.
+float3 tonemap(float3 color)
+{
+    return color / (1.0 + luminance(color));
+}
+
+float3 inverse_tonemap(float3 color)
+{
+    return color / max(1.0 - luminance(color), 1e-5);
+}
+
+[numthreads(8, 8, 1)]
+void main(uint2 px: SV_DispatchThreadID)
+{
+    float2 uv = pixel_to_uv(float2(px), output_tex_size.xy);
+    float3 current = tonemap(max(input_tex[px].rgb, 0.0));
+
+    if (history_valid == 0)
+    {
+        output_tex[px] = float4(inverse_tonemap(current), 1.0);
+        return;
+    }
+
+    // neighborhood color distribution of the current frame
+    float3 moment1 = 0.0;
+    float3 moment2 = 0.0;
+
+    [unroll]
+    for (int y = -1; y <= 1; ++y)
+    {
+        [unroll]
+        for (int x = -1; x <= 1; ++x)
+        {
+            int2 coord = clamp(int2(px) + int2(x, y), 0, int2(output_tex_size.xy) - 1);
+            float3 color = tonemap(max(input_tex[coord].rgb, 0.0));
+
+            moment1 += color;
+            moment2 += color * color;
+        }
+    }
+
+    float3 mean = moment1 / 9.0;
+    float3 sigma = sqrt(max(moment2 / 9.0 - mean * mean, 0.0));
+    float3 clamp_min = mean - VARIANCE_CLIP_GAMMA * sigma;
+    float3 clamp_max = mean + VARIANCE_CLIP_GAMMA * sigma;
+
+    const float depth = depth_tex[px];
+    float2 history_uv;
+    bool behind_camera = false;
+
+    if (depth > 0.0)
+    {
+        // meshes write their own motion (including the camera motion)
+        history_uv = uv - motion_vector_tex[px];
+    }
+    else
+    {
+        // reproject into the last frame, depth 0.0 (the sky) is a point at infinity and only rotates with the camera
+        CameraFrameConstants cam = frame_constants_dyn.camera_constants;
+
+        const float4 pos_cs = float4(uv_to_clip(uv), depth, 1.0);
+        const float4 pos_ws = mul(cam.view_to_world, mul(cam.clip_to_view, pos_cs));
+        const float4 prev_pos_cs = mul(cam.prev_world_to_clip, pos_ws);
+
+        // this pixel is sampled with this frame's jitter, and the history converges to the unjittered image
+        history_uv = clip_to_uv(prev_pos_cs.xy / prev_pos_cs.w) + cam.jitter.xy * float2(0.5, -0.5);
+        behind_camera = prev_pos_cs.w <= 0.0;
+    }
+
+    // disocclusion, the history is off-screen or behind the camera
+    if (behind_camera || any(history_uv < 0.0) || any(history_uv > 1.0))
+    {
+        output_tex[px] = float4(inverse_tonemap(current), 1.0);
+        return;
+    }
+
+    float3 history = tonemap(max(history_tex.SampleLevel(sampler_lnce, history_uv, 0.0).rgb, 0.0));
+    history = clamp(history, clamp_min, clamp_max);
+
+    float3 result = lerp(history, current, blend_factor);
+    output_tex[px] = float4(inverse_tonemap(result), 1.0);
+}
@@ -0,0 +1,22 @@
+[[vk::binding(0)]] Texture2D<float3> input_tex;
+[[vk::binding(1)]] RWTexture2D<float3> output_tex;
+[[vk::binding(2)]] cbuffer _dyn {
+    uint2 viewport_offset;  // top-left corner of the viewport in the output image
+    uint2 viewport_extent;
+};
+
+// Copy the image of a viewport camera into its rectangle of the output image.
+[numthreads(8, 8, 1)]
+void main(uint2 px: SV_DispatchThreadID)
+{
+    uint2 output_size;
+    output_tex.GetDimensions(output_size.x, output_size.y);
+
+    const uint2 output_px = px + viewport_offset;
+    if (any(px >= viewport_extent) || any(output_px >= output_size))
+    {
+        return;
+    }
+
+    output_tex[output_px] = input_tex[px];
+}
@@ -1,16 +1,18 @@
+#include "../common/frame_constants.hlsl"
 #include "../common/bindless_resources.hlsl"
 
 [[vk::push_constant]]
 struct {
     uint mesh_index;
-    uint instance_index;
+    // instances of the same mesh are drawn in one instanced draw call, this is the index of the first one.
+    uint first_instance_index;
     uint light_index;
 } push_constants;
 
 [[vk::binding(0)]] StructuredBuffer<row_major float4x4> light_transforms_dyn;  // dynamic read-only storage buffer
 [[vk::binding(1)]] StructuredBuffer<row_major float3x4> object_transforms_dyn; // dynamic read-only storage buffer
 
-float4 vs_main(uint vid: SV_VertexID) : SV_Position
+float4 vs_main(uint vid: SV_VertexID, uint iid: SV_InstanceID) : SV_Position
 {
     // get mesh offset data
     const Mesh mesh = meshes[push_constants.mesh_index];
@@ -18,10 +20,29 @@ float4 vs_main(uint vid: SV_VertexID) : SV_Position
     PackedVertex packed_vertex = PackedVertex(asfloat(draw_datas.Load4(vid * sizeof(float4) + mesh.vertex_offset)));
     Vertex vertex = packed_vertex.unpack();
 
-    float3 vertex_pos_ws = mul(object_transforms_dyn[push_constants.instance_index], float4(vertex.position, 1.0));
+    float3 vertex_pos_ws = mul(object_transforms_dyn[push_constants.first_instance_index + iid], float4(vertex.position, 1.0));
 
     return mul(light_transforms_dyn[push_constants.light_index], float4(vertex_pos_ws, 1.0));
 }
 
+// Position only pass of the camera to fill the depth before the gbuffer rasterization (i.e. depth prepass).
+// It must do the exact same math as defer_raster.hlsl vs_main, the gbuffer pass tests the depth with EQUAL.
+float4 vs_depth_prepass(uint vid: SV_VertexID, uint iid: SV_InstanceID) : SV_Position
+{
+    CameraFrameConstants cam = frame_constants_dyn.camera_constants;
+
+    const Mesh mesh = meshes[push_constants.mesh_index];
+
+    PackedVertex packed_vertex = PackedVertex(asfloat(draw_datas.Load4(vid * sizeof(float4) + mesh.vertex_offset)));
+    Vertex vertex = packed_vertex.unpack();
+
+    float3x4 transform = object_transforms_dyn[push_constants.first_instance_index + iid];
+    precise float3 ws_pos = mul(transform, float4(vertex.position, 1.0));
+
+    precise float4 vs_pos = mul(cam.world_to_view, float4(ws_pos, 1.0));
+    precise float4 cs_pos = mul(cam.view_to_clip, vs_pos);
+    return cs_pos;
+}
+
 // Only draw objects in shadow map
 void ps_main() {}
\ No newline at end of file
@@ -0,0 +1,117 @@
+#include "../math/constants.hlsl"
+#include "../common/uv.hlsl"
+
+// Preetham analytic sky model.
+// See "A Practical Analytic Model for Daylight" (Preetham, Shirley, Smits 1999).
+
+[[vk::binding(0)]] RWTexture2DArray<float4> sky_cube_map;
+[[vk::binding(1)]] cbuffer _dyn {
+    float3 sun_direction; // normalized direction pointing to the sun
+    float  turbidity;
+    float  intensity;
+    uint   cube_map_res;
+};
+
+// scale the luminance (in kcd/m^2) to about the same range of the ldr cubemaps
+#define SKY_LUMINANCE_SCALE 0.05
+// the horizon is clamped slightly above zero to avoid the singularity of the perez function
+#define MIN_COS_THETA 0.01
+
+struct PerezCoeffs
+{
+    float A;
+    float B;
+    float C;
+    float D;
+    float E;
+
+    float eval(float cos_theta, float gamma, float cos_gamma)
+    {
+        return (1.0 + A * exp(B / cos_theta)) * (1.0 + C * exp(D * gamma) + E * cos_gamma * cos_gamma);
+    }
+};
+
+PerezCoeffs perez_coeffs(float a, float b, float c, float d, float e)
+{
+    PerezCoeffs coeffs;
+    coeffs.A = a;
+    coeffs.B = b;
+    coeffs.C = c;
+    coeffs.D = d;
+    coeffs.E = e;
+    return coeffs;
+}
+
+float3 xyY_to_linear_srgb(float3 xyY)
+{
+    const float Y = xyY.z;
+    const float X = xyY.x * (Y / xyY.y);
+    const float Z = (1.0 - xyY.x - xyY.y) * (Y / xyY.y);
+
+    return float3(
+         3.2406 * X - 1.5372 * Y - 0.4986 * Z,
+        -0.9689 * X + 1.8758 * Y + 0.0415 * Z,
+         0.0557 * X - 0.2040 * Y + 1.0570 * Z
+    );
+}
+
+float3 preetham_sky(float3 view_dir, float3 sun_dir, float T)
+{
+    const PerezCoeffs coeffs_Y = perez_coeffs( 0.1787 * T - 1.4630, -0.3554 * T + 0.4275, -0.0227 * T + 5.3251,  0.1206 * T - 2.5771, -0.0670 * T + 0.3703);
+    const PerezCoeffs coeffs_x = perez_coeffs(-0.0193 * T - 0.2592, -0.0665 * T + 0.0008, -0.0004 * T + 0.2125, -0.0641 * T - 0.8989, -0.0033 * T + 0.0452);
+    const PerezCoeffs coeffs_y = perez_coeffs(-0.0167 * T - 0.2608, -0.0950 * T + 0.0092, -0.0079 * T + 0.2102, -0.0441 * T - 1.6537, -0.0109 * T + 0.0529);
+
+    // sun zenith angle
+    const float cos_theta_s = max(sun_dir.y, MIN_COS_THETA);
+    const float theta_s = acos(cos_theta_s);
+    const float theta_s2 = theta_s * theta_s;
+    const float theta_s3 = theta_s2 * theta_s;
+    const float T2 = T * T;
+
+    // zenith luminance and chromaticity
+    const float chi = (4.0 / 9.0 - T / 120.0) * (PI - 2.0 * theta_s);
+    const float zenith_Y = (4.0453 * T - 4.9710) * tan(chi) - 0.2155 * T + 2.4192;
+    const float zenith_x =
+        T2 * ( 0.00166 * theta_s3 - 0.00375 * theta_s2 + 0.00209 * theta_s) +
+        T  * (-0.02903 * theta_s3 + 0.06377 * theta_s2 - 0.03202 * theta_s + 0.00394) +
+             ( 0.11693 * theta_s3 - 0.21196 * theta_s2 + 0.06052 * theta_s + 0.25886);
+    const float zenith_y =
+        T2 * ( 0.00275 * theta_s3 - 0.00610 * theta_s2 + 0.00317 * theta_s) +
+        T  * (-0.04214 * theta_s3 + 0.08970 * theta_s2 - 0.04153 * theta_s + 0.00516) +
+             ( 0.15346 * theta_s3 - 0.26756 * theta_s2 + 0.06670 * theta_s + 0.26688);
+
+    const float cos_theta = max(view_dir.y, MIN_COS_THETA);
+    const float cos_gamma = clamp(dot(view_dir, sun_dir), -1.0, 1.0);
+    const float gamma = acos(cos_gamma);
+
+    const float3 xyY = float3(
+        zenith_x * coeffs_x.eval(cos_theta, gamma, cos_gamma) / coeffs_x.eval(1.0, theta_s, cos_theta_s),
+        zenith_y * coeffs_y.eval(cos_theta, gamma, cos_gamma) / coeffs_y.eval(1.0, theta_s, cos_theta_s),
+        zenith_Y * coeffs_Y.eval(cos_theta, gamma, cos_gamma) / coeffs_Y.eval(1.0, theta_s, cos_theta_s)
+    );
+
+    return max(xyY_to_linear_srgb(xyY), 0.0.xxx);
+}
+
+[numthreads(8, 8, 6)]
+void main(in uint3 px : SV_DispatchThreadID)
+{
+    if (any(px.xy >= cube_map_res.xx))
+    {
+        return;
+    }
+
+    const float3 view_dir = normalize(cube_to_world_dir(int3(px), float2(cube_map_res, cube_map_res)));
+    const float3 sun_dir = normalize(sun_direction);
+
+    float3 radiance = preetham_sky(view_dir, sun_dir, turbidity);
+
+    // fade the ground to a dark color below the horizon
+    const float below_horizon = saturate(-view_dir.y * 8.0);
+    radiance = lerp(radiance, radiance * 0.1, below_horizon);
+
+    // the sky goes dark when the sun is below the horizon
+    radiance *= saturate(sun_dir.y * 10.0 + 1.0);
+
+    sky_cube_map[px] = float4(radiance * SKY_LUMINANCE_SCALE * intensity, 1.0);
+}